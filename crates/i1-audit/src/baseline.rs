@@ -0,0 +1,323 @@
+//! Named audit baselines.
+//!
+//! A baseline is a snapshot of a known-good machine (a golden image)
+//! saved under a name, so other machines can be checked against it rather
+//! than only against their own previous run. Baselines are stored as
+//! gzip-compressed JSON; the file is self-contained and can be copied to
+//! other hosts as is.
+//!
+//! Comparison only looks at content-addressable facts: which binary paths
+//! exist and what they hash to. Host-specific fields (node ID, uptime,
+//! inodes, timestamps, trust scores) are ignored, so a baseline captured
+//! on one machine can be applied to the whole fleet.
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::consensus::Severity;
+use crate::error::{AuditError, Result};
+use crate::hash::HashAlgorithm;
+use crate::types::{AuditSnapshot, BinaryInfo};
+
+/// File extension of stored baselines.
+pub const BASELINE_EXTENSION: &str = "json.gz";
+
+/// A saved snapshot to compare machines against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Name the baseline is stored under
+    pub name: String,
+    /// When the baseline was saved
+    pub saved_at: DateTime<Utc>,
+    /// Directories scanned for binaries, so comparisons scan the same ones
+    pub bin_paths: Vec<String>,
+    /// The captured system state
+    pub snapshot: AuditSnapshot,
+}
+
+impl Baseline {
+    /// Capture `snapshot`, taken by scanning `bin_paths`, as baseline `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError::BaselineName` if `name` isn't usable as a file name.
+    pub fn new(name: &str, bin_paths: Vec<String>, snapshot: AuditSnapshot) -> Result<Self> {
+        check_name(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            saved_at: Utc::now(),
+            bin_paths,
+            snapshot,
+        })
+    }
+
+    /// Algorithm the baseline's binaries were hashed with; compare against
+    /// binaries hashed the same way.
+    #[must_use]
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.snapshot
+            .binaries
+            .first()
+            .map_or_else(HashAlgorithm::default, |b| b.hash_algorithm)
+    }
+
+    /// Read a baseline file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError` if the file can't be read or isn't a baseline.
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            std::fs::File::open(path).map_err(|e| AuditError::io(path.display().to_string(), e))?;
+        let mut json = String::new();
+        GzDecoder::new(file)
+            .read_to_string(&mut json)
+            .map_err(|e| AuditError::io(path.display().to_string(), e))?;
+        let baseline: Self = serde_json::from_str(&json)?;
+        check_name(&baseline.name)?;
+        Ok(baseline)
+    }
+
+    /// Write the baseline to `path`, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError` if the file or its directory can't be written.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let io_err = |e| AuditError::io(path.display().to_string(), e);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AuditError::io(parent.display().to_string(), e))?;
+        }
+        let file = std::fs::File::create(path).map_err(io_err)?;
+        let mut gz = GzEncoder::new(file, Compression::default());
+        serde_json::to_writer(&mut gz, self)?;
+        gz.finish()
+            .and_then(|mut file| file.flush())
+            .map_err(io_err)
+    }
+
+    /// Compare the binaries found on this machine against the baseline.
+    #[must_use]
+    pub fn compare(&self, binaries: &[BinaryInfo]) -> BaselineDiff {
+        let expected: BTreeMap<&str, &BinaryInfo> = self
+            .snapshot
+            .binaries
+            .iter()
+            .map(|b| (b.path.as_str(), b))
+            .collect();
+        let actual: BTreeMap<&str, &BinaryInfo> =
+            binaries.iter().map(|b| (b.path.as_str(), b)).collect();
+
+        let mut diff = BaselineDiff {
+            baseline: self.name.clone(),
+            saved_at: self.saved_at,
+            ..BaselineDiff::default()
+        };
+        for (path, bin) in &actual {
+            match expected.get(path) {
+                None => diff.added.push(BaselineChange {
+                    // Something new is executing that the image never had.
+                    severity: if bin.running {
+                        Severity::High
+                    } else {
+                        Severity::Medium
+                    },
+                    path: (*path).to_string(),
+                    expected: None,
+                    actual: Some(bin.hash.clone()),
+                    running: bin.running,
+                }),
+                Some(base) if base.hash_algorithm != bin.hash_algorithm => {
+                    diff.unverified.push((*path).to_string());
+                }
+                Some(base) if base.hash != bin.hash => diff.changed.push(BaselineChange {
+                    severity: Severity::Critical,
+                    path: (*path).to_string(),
+                    expected: Some(base.hash.clone()),
+                    actual: Some(bin.hash.clone()),
+                    running: bin.running,
+                }),
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        for (path, base) in &expected {
+            if !actual.contains_key(path) {
+                diff.removed.push(BaselineChange {
+                    severity: Severity::Low,
+                    path: (*path).to_string(),
+                    expected: Some(base.hash.clone()),
+                    actual: None,
+                    running: false,
+                });
+            }
+        }
+        diff
+    }
+}
+
+/// Reject names that can't be used as a baseline file name.
+///
+/// # Errors
+///
+/// Returns `AuditError::BaselineName` unless `name` is non-empty, made of
+/// ASCII letters, digits, `.`, `_` and `-`, and doesn't start with `.`.
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AuditError::BaselineName(name.to_string()))
+    }
+}
+
+/// One binary that differs from the baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineChange {
+    /// How much the difference matters
+    pub severity: Severity,
+    /// Binary path
+    pub path: String,
+    /// Hash in the baseline (None for added binaries)
+    pub expected: Option<String>,
+    /// Hash on this machine (None for removed binaries)
+    pub actual: Option<String>,
+    /// Whether the binary is running here
+    pub running: bool,
+}
+
+/// How this machine's binaries differ from a baseline.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineDiff {
+    /// Baseline name
+    pub baseline: String,
+    /// When the baseline was saved
+    pub saved_at: DateTime<Utc>,
+    /// Binaries the baseline doesn't have: High if running, else Medium
+    pub added: Vec<BaselineChange>,
+    /// Baseline binaries missing here: Low
+    pub removed: Vec<BaselineChange>,
+    /// Baseline binaries whose hash differs: Critical
+    pub changed: Vec<BaselineChange>,
+    /// Paths hashed with a different algorithm than the baseline, which
+    /// can't be compared
+    pub unverified: Vec<String>,
+    /// Binaries matching the baseline
+    pub unchanged: usize,
+}
+
+impl BaselineDiff {
+    /// Whether this machine matches the baseline.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Severity of the worst difference (None when there are none).
+    #[must_use]
+    pub fn highest(&self) -> Option<Severity> {
+        self.changes().map(|c| c.severity).max()
+    }
+
+    /// Every difference, changed binaries first.
+    pub fn changes(&self) -> impl Iterator<Item = &BaselineChange> {
+        self.changed.iter().chain(&self.added).chain(&self.removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::tests::{binary, snapshot};
+
+    #[test]
+    fn compare_ignores_host_fields() {
+        let baseline = Baseline::new("golden", vec!["/usr/bin".into()], snapshot()).unwrap();
+
+        // Same content on another machine: different node, inodes, dates.
+        let mut other = snapshot();
+        other.node_id = "node2".into();
+        other.system_uptime_secs = 1;
+        for bin in &mut other.binaries {
+            bin.identity.inode += 100;
+            bin.create_date = Utc::now();
+            bin.trust_score = None;
+        }
+        let diff = baseline.compare(&other.binaries);
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, other.binaries.len());
+        assert_eq!(diff.highest(), None);
+    }
+
+    #[test]
+    fn compare_reports_differences() {
+        let baseline = Baseline::new("golden", Vec::new(), snapshot()).unwrap();
+        let mut binaries = snapshot().binaries;
+        binaries[0].hash = "cd".repeat(32);
+        binaries.retain(|b| b.path != "/opt/app/agent");
+        binaries.push(binary("/usr/bin/nc", true, 0.2));
+        binaries.push(binary("/usr/bin/idle", false, 0.2));
+        let mut blake = binary("/usr/bin/rarely-used", false, 0.1);
+        blake.hash_algorithm = HashAlgorithm::Blake3;
+        binaries.retain(|b| b.path != blake.path);
+        binaries.push(blake);
+
+        let diff = baseline.compare(&binaries);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, "/usr/bin/sshd");
+        assert_eq!(diff.changed[0].severity, Severity::Critical);
+        let added: Vec<_> = diff
+            .added
+            .iter()
+            .map(|c| (c.path.as_str(), c.severity))
+            .collect();
+        assert_eq!(
+            added,
+            [
+                ("/usr/bin/idle", Severity::Medium),
+                ("/usr/bin/nc", Severity::High)
+            ]
+        );
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].path, "/opt/app/agent");
+        assert_eq!(diff.unverified, ["/usr/bin/rarely-used"]);
+        assert_eq!(diff.highest(), Some(Severity::Critical));
+    }
+
+    #[test]
+    fn baseline_roundtrips_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baselines").join("golden.json.gz");
+        let baseline = Baseline::new("golden", vec!["/usr/bin".into()], snapshot()).unwrap();
+        baseline.write(&path).unwrap();
+
+        let read = Baseline::read(&path).unwrap();
+        assert_eq!(read.name, "golden");
+        assert_eq!(read.bin_paths, ["/usr/bin"]);
+        assert_eq!(read.saved_at, baseline.saved_at);
+        assert!(read.compare(&baseline.snapshot.binaries).is_empty());
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(Baseline::read(&path).is_err());
+    }
+
+    #[test]
+    fn names_must_be_file_names() {
+        for name in ["golden", "web-01", "v2.1_base"] {
+            assert!(check_name(name).is_ok(), "{name}");
+        }
+        for name in ["", ".hidden", "../etc", "a/b", "with space"] {
+            assert!(check_name(name).is_err(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! On-disk cache for consensus query results.
+//!
+//! Consensus answers change rarely but every audit run would otherwise
+//! re-query every hash. Results are cached as JSON keyed by hash, with the
+//! DNS TTL honored as the cache lifetime.
+//!
+//! When the network is unreachable, expired entries are still served
+//! (stale-while-revalidate) but marked `from_cache: true, stale: true`
+//! so scoring can discount them.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use super::query::ConsensusResult;
+use crate::error::{AuditError, Result};
+
+/// Default cache file name inside the i1 data directory.
+pub const CACHE_FILE_NAME: &str = "consensus_cache.json";
+
+/// A cached consensus result with its expiry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    result: ConsensusResult,
+    expires_at: DateTime<Utc>,
+}
+
+/// Hit/miss counters for a cache session (not persisted).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Fresh entries served without a network query
+    pub hits: u32,
+    /// Lookups that went to the network
+    pub misses: u32,
+    /// Expired entries served because the network query failed
+    pub stale_hits: u32,
+}
+
+/// Persistent consensus cache keyed by hash or fingerprint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConsensusCache {
+    entries: HashMap<String, CacheEntry>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    stats: CacheStats,
+}
+
+impl ConsensusCache {
+    /// Create an empty in-memory cache (never written to disk).
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load the cache from a JSON file.
+    ///
+    /// A missing or unparsable file yields an empty cache bound to `path`,
+    /// so a corrupt cache never blocks an audit.
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        let mut cache = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| {
+                serde_json::from_str::<Self>(&content)
+                    .map_err(|e| {
+                        warn!(path = %path.display(), error = %e, "discarding corrupt consensus cache");
+                    })
+                    .ok()
+            })
+            .unwrap_or_default();
+        cache.path = Some(path.to_path_buf());
+        cache
+    }
+
+    /// Write the cache back to the file it was loaded from.
+    ///
+    /// No-op for in-memory caches.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AuditError::io(parent.display().to_string(), e))?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).map_err(|e| AuditError::io(path.display().to_string(), e))
+    }
+
+    /// Return a fresh (unexpired) cached result, counting a hit.
+    pub fn get_fresh(&mut self, hash: &str) -> Option<ConsensusResult> {
+        let entry = self.entries.get(hash)?;
+        if entry.expires_at <= Utc::now() {
+            return None;
+        }
+        self.stats.hits += 1;
+        let mut result = entry.result.clone();
+        result.from_cache = true;
+        result.stale = false;
+        Some(result)
+    }
+
+    /// Return a cached result regardless of expiry, marked stale, counting a stale hit.
+    pub fn get_stale(&mut self, hash: &str) -> Option<ConsensusResult> {
+        let entry = self.entries.get(hash)?;
+        self.stats.stale_hits += 1;
+        let mut result = entry.result.clone();
+        result.from_cache = true;
+        result.stale = entry.expires_at <= Utc::now();
+        Some(result)
+    }
+
+    /// Store a freshly fetched result, expiring after its TTL.
+    pub fn insert(&mut self, result: &ConsensusResult) {
+        let expires_at = result.fetched_at + Duration::seconds(i64::from(result.ttl_secs));
+        debug!(hash = %result.hash, ttl = result.ttl_secs, "caching consensus result");
+        self.entries.insert(
+            result.hash.clone(),
+            CacheEntry {
+                result: result.clone(),
+                expires_at,
+            },
+        );
+    }
+
+    /// Record that a lookup had to go to the network.
+    pub const fn record_miss(&mut self) {
+        self.stats.misses += 1;
+    }
+
+    /// Drop entries that expired more than `max_age` ago.
+    pub fn prune(&mut self, max_age: Duration) {
+        let cutoff = Utc::now() - max_age;
+        self.entries.retain(|_, e| e.expires_at > cutoff);
+    }
+
+    /// Number of cached entries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Session hit/miss counters.
+    #[must_use]
+    pub const fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn make_result(hash: &str, ttl_secs: u32, fetched_at: DateTime<Utc>) -> ConsensusResult {
+        ConsensusResult {
+            hash: hash.into(),
+            found: true,
+            node_count: 12,
+            network_trust: Some(90),
+            total_nodes: None,
+            attestations: Vec::new(),
+            fetched_at,
+            ttl_secs,
+            from_cache: false,
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn fresh_entry_is_a_hit() {
+        let mut cache = ConsensusCache::in_memory();
+        cache.insert(&make_result("abc", 3600, Utc::now()));
+
+        let hit = cache.get_fresh("abc").unwrap();
+        assert!(hit.from_cache);
+        assert!(!hit.stale);
+        assert_eq!(hit.node_count, 12);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn expired_entry_only_served_stale() {
+        let mut cache = ConsensusCache::in_memory();
+        cache.insert(&make_result("abc", 60, Utc::now() - Duration::hours(2)));
+
+        assert!(cache.get_fresh("abc").is_none());
+        let stale = cache.get_stale("abc").unwrap();
+        assert!(stale.from_cache);
+        assert!(stale.stale);
+        assert_eq!(cache.stats().stale_hits, 1);
+    }
+
+    #[test]
+    fn roundtrip_through_disk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CACHE_FILE_NAME);
+
+        let mut cache = ConsensusCache::load(&path);
+        assert!(cache.is_empty());
+        cache.insert(&make_result("abc", 3600, Utc::now()));
+        cache.save().unwrap();
+
+        let mut reloaded = ConsensusCache::load(&path);
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded.get_fresh("abc").is_some());
+    }
+
+    #[test]
+    fn corrupt_file_yields_empty_cache() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CACHE_FILE_NAME);
+        std::fs::write(&path, "not json").unwrap();
+
+        let cache = ConsensusCache::load(&path);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn prune_drops_old_entries() {
+        let mut cache = ConsensusCache::in_memory();
+        cache.insert(&make_result("old", 60, Utc::now() - Duration::days(30)));
+        cache.insert(&make_result("new", 3600, Utc::now()));
+
+        cache.prune(Duration::days(7));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get_fresh("new").is_some());
+    }
+}
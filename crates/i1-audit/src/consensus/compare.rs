@@ -2,11 +2,24 @@
 //!
 //! After consensus queries, compare local state to what the network knows.
 //! Unknown binaries or certs that aren't in consensus are flagged.
+//! Kernel modules are compared snapshot-to-snapshot: a module that
+//! appears between audits is worth a look. Running processes are checked
+//! for a binary backing them, on disk and in discovery, and shared
+//! libraries for where they were loaded from.
 
-use crate::types::{BinaryInfo, RootCertInfo};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::BuildHasher;
+
+use serde::{Deserialize, Serialize};
+
+use super::query::ConsensusResult;
+use crate::discovery::is_unusual_library_path;
+use crate::scoring::qualifying_network_count;
+use crate::types::{BinaryInfo, KernelModule, ProcessInfo, RootCertInfo, TrustWeights};
 
 /// Anomaly detected during comparison.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Anomaly {
     /// What kind of anomaly
     pub kind: AnomalyKind,
@@ -17,22 +30,48 @@ pub struct Anomaly {
 }
 
 /// Types of anomalies.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AnomalyKind {
     /// Binary hash not found in network consensus
     UnknownBinary,
     /// Binary hash found but with very few nodes
     RareBinary,
+    /// Binary hash vouched for only by new or network-clustered nodes
+    ThinConsensus,
     /// Root cert not found in network consensus
     UnknownCert,
     /// Root cert that is expired
     ExpiredCert,
     /// Binary in non-standard location that is running
     SuspiciousLocation,
+    /// Running binary with a low trust score
+    LowTrustBinary,
+    /// Process whose executable was deleted from disk after it started
+    DeletedExecutable,
+    /// Process running an executable that binary discovery didn't find
+    UnbackedProcess,
+    /// Shared library loaded from a temp, shared memory or home directory
+    UnusualLibraryPath,
+    /// Kernel module loaded since the previous snapshot
+    NewKernelModule,
+    /// Loaded kernel module with a low trust score
+    UntrustedModule,
+    /// Root cert whose self-signature doesn't verify, or that isn't self-issued
+    BadCertSignature,
+    /// Root cert with a small or weak public key
+    WeakCertKey,
+    /// Root cert signed with a broken digest (MD5, SHA-1)
+    WeakCertSignature,
+    /// Root cert valid for an implausibly long time
+    LongCertValidity,
+    /// Root cert sharing its subject with a cert under a different key
+    CertKeyCollision,
 }
 
 /// Severity levels.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     /// Informational
     Info,
@@ -46,17 +85,89 @@ pub enum Severity {
     Critical,
 }
 
+impl Severity {
+    /// Every severity, least severe first.
+    pub const ALL: [Self; 5] = [
+        Self::Info,
+        Self::Low,
+        Self::Medium,
+        Self::High,
+        Self::Critical,
+    ];
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "Info",
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::Critical => "Critical",
+        })
+    }
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UnknownBinary => "unknown binary",
+            Self::RareBinary => "rare binary",
+            Self::ThinConsensus => "thin consensus",
+            Self::UnknownCert => "unknown cert",
+            Self::ExpiredCert => "expired cert",
+            Self::SuspiciousLocation => "suspicious location",
+            Self::LowTrustBinary => "low-trust binary",
+            Self::DeletedExecutable => "deleted executable",
+            Self::UnbackedProcess => "unbacked process",
+            Self::UnusualLibraryPath => "unusual library path",
+            Self::NewKernelModule => "new kernel module",
+            Self::UntrustedModule => "untrusted module",
+            Self::BadCertSignature => "bad cert signature",
+            Self::WeakCertKey => "weak cert key",
+            Self::WeakCertSignature => "weak cert signature",
+            Self::LongCertValidity => "long cert validity",
+            Self::CertKeyCollision => "cert key collision",
+        })
+    }
+}
+
 /// Compare local binaries against consensus data.
 ///
-/// Returns anomalies for binaries that the network doesn't know about
-/// or that have suspiciously few reports.
+/// Returns anomalies for binaries that the network doesn't know about,
+/// that have suspiciously few reports, or whose reports all come from
+/// new or clustered nodes. `consensus` maps each binary hash to its query result.
 #[must_use]
-pub fn compare_binaries(binaries: &[BinaryInfo], _min_node_threshold: u32) -> Vec<Anomaly> {
+pub fn compare_binaries<S: BuildHasher>(
+    binaries: &[BinaryInfo],
+    consensus: &HashMap<String, ConsensusResult, S>,
+    weights: &TrustWeights,
+) -> Vec<Anomaly> {
     let mut anomalies = Vec::new();
 
     for bin in binaries {
         let score = bin.trust_score.as_ref();
 
+        // Enough nodes on paper, but not enough independent ones
+        if let Some(result) = consensus.get(&bin.hash) {
+            if is_thin(result, weights) {
+                anomalies.push(Anomaly {
+                    kind: AnomalyKind::ThinConsensus,
+                    severity: if bin.running {
+                        Severity::High
+                    } else {
+                        Severity::Medium
+                    },
+                    description: format!(
+                        "Binary vouched only by new or clustered nodes: {} ({} nodes, {} established networks)",
+                        bin.path,
+                        result.node_count,
+                        qualifying_network_count(result, weights)
+                    ),
+                });
+            }
+        }
+
         // Check consensus
         if let Some(s) = score {
             if s.hash_consensus < 0.01 {
@@ -102,6 +213,14 @@ pub fn compare_binaries(binaries: &[BinaryInfo], _min_node_threshold: u32) -> Ve
     anomalies
 }
 
+/// Raw node count passes the gate but attestations lack diversity.
+fn is_thin(result: &ConsensusResult, weights: &TrustWeights) -> bool {
+    result.found
+        && result.node_count >= weights.min_consensus_nodes
+        && !result.attestations.is_empty()
+        && qualifying_network_count(result, weights) < weights.min_distinct_networks as usize
+}
+
 /// Compare local root certs against consensus data.
 #[must_use]
 pub fn compare_certs(certs: &[RootCertInfo]) -> Vec<Anomaly> {
@@ -134,3 +253,247 @@ pub fn compare_certs(certs: &[RootCertInfo]) -> Vec<Anomaly> {
 
     anomalies
 }
+
+/// Suffix Linux appends to `/proc/<pid>/exe` once the file is gone.
+const DELETED_SUFFIX: &str = " (deleted)";
+
+/// Compare running processes against the binaries on disk.
+///
+/// A process whose executable is gone from disk is the classic trait of
+/// memory-resident malware, and one running a binary that discovery
+/// didn't find escaped hashing and scoring; both are high severity.
+/// Kernel threads have no executable and are skipped, as is the discovery
+/// check when `binaries` is empty.
+#[must_use]
+pub fn compare_processes(processes: &[ProcessInfo], binaries: &[BinaryInfo]) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+
+    for proc in processes {
+        let Some(exe) = proc.exe_path.as_deref() else {
+            continue;
+        };
+        let deleted = exe.strip_suffix(DELETED_SUFFIX);
+        if deleted.is_some() || proc.exe_missing {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::DeletedExecutable,
+                severity: Severity::High,
+                description: format!(
+                    "Process running a deleted executable: {} (pid={}, exe={})",
+                    proc.name,
+                    proc.pid,
+                    deleted.unwrap_or(exe)
+                ),
+            });
+        } else if !binaries.is_empty() && !binaries.iter().any(|b| b.path == exe) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::UnbackedProcess,
+                severity: Severity::High,
+                description: format!(
+                    "Process running a binary outside discovery: {} (pid={}, exe={exe})",
+                    proc.name, proc.pid
+                ),
+            });
+        }
+    }
+
+    anomalies
+}
+
+/// Compare shared libraries mapped by running processes against where
+/// packaged libraries live.
+///
+/// A library loaded from a temp, shared memory or home directory is how
+/// `LD_PRELOAD` and planted-`.so` injection usually looks, so it is high
+/// severity.
+#[must_use]
+pub fn compare_libraries(libraries: &[BinaryInfo]) -> Vec<Anomaly> {
+    libraries
+        .iter()
+        .filter(|lib| is_unusual_library_path(&lib.path))
+        .map(|lib| Anomaly {
+            kind: AnomalyKind::UnusualLibraryPath,
+            severity: Severity::High,
+            description: format!(
+                "Shared library loaded from an unusual path: {} (processes={})",
+                lib.path,
+                lib.process_names.join(",")
+            ),
+        })
+        .collect()
+}
+
+/// Trust score below which a loaded kernel module is flagged.
+const MODULE_TRUST_THRESHOLD: f64 = 0.5;
+
+/// Compare loaded kernel modules against a previous snapshot.
+///
+/// Flags modules that were not loaded last time, and any currently
+/// loaded module whose trust score is below threshold. Pass an empty
+/// `previous` slice when there is no earlier snapshot.
+#[must_use]
+pub fn compare_modules(previous: &[KernelModule], current: &[KernelModule]) -> Vec<Anomaly> {
+    let known: HashSet<&str> = previous.iter().map(|m| m.name.as_str()).collect();
+    let mut anomalies = Vec::new();
+
+    for module in current {
+        let score = module.trust.as_ref().map(|t| t.score);
+        let untrusted = score.is_some_and(|s| s < MODULE_TRUST_THRESHOLD);
+
+        if !previous.is_empty() && !known.contains(module.name.as_str()) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::NewKernelModule,
+                severity: if untrusted {
+                    Severity::Critical
+                } else {
+                    Severity::Medium
+                },
+                description: format!("Kernel module loaded since last audit: {}", module.name),
+            });
+        }
+
+        if let Some(score) = score.filter(|_| untrusted) {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::UntrustedModule,
+                severity: Severity::High,
+                description: format!(
+                    "Untrusted kernel module: {} (trust={:.0}%, unsigned={}, out_of_tree={})",
+                    module.name,
+                    score * 100.0,
+                    module.taint.unsigned,
+                    module.taint.out_of_tree
+                ),
+            });
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::HashAlgorithm;
+    use crate::scoring::score_module;
+    use crate::types::{Attestation, FileIdentity, ModuleOrigin, ModuleTaint};
+    use chrono::{Duration, Utc};
+
+    fn make_module(name: &str, taint: &str, origin: ModuleOrigin) -> KernelModule {
+        let mut module = KernelModule {
+            name: name.into(),
+            size: 16384,
+            ref_count: 0,
+            used_by: Vec::new(),
+            state: "Live".into(),
+            taint: ModuleTaint::parse(taint),
+            ko_path: None,
+            sha256: None,
+            origin,
+            trust: None,
+        };
+        module.trust = Some(score_module(&module));
+        module
+    }
+
+    #[test]
+    fn new_untrusted_module_is_critical() {
+        let previous = vec![make_module("ext4", "", ModuleOrigin::ModuleTree)];
+        let current = vec![
+            make_module("ext4", "", ModuleOrigin::ModuleTree),
+            make_module("diamorphine", "OE", ModuleOrigin::NotInModuleTree),
+        ];
+
+        let anomalies = compare_modules(&previous, &current);
+        assert_eq!(anomalies.len(), 2);
+        assert_eq!(anomalies[0].kind, AnomalyKind::NewKernelModule);
+        assert_eq!(anomalies[0].severity, Severity::Critical);
+        assert_eq!(anomalies[1].kind, AnomalyKind::UntrustedModule);
+    }
+
+    #[test]
+    fn clustered_vouching_is_thin_consensus() {
+        let bin = BinaryInfo {
+            path: "/usr/bin/sshd".into(),
+            hash: "ab".repeat(32),
+            hash_algorithm: HashAlgorithm::Sha256,
+            create_date: Utc::now(),
+            modify_date: Utc::now(),
+            identity: FileIdentity {
+                inode: 1,
+                device_id: 1,
+            },
+            size: 1024,
+            running: true,
+            process_names: vec!["sshd".into()],
+            trust_score: None,
+        };
+        let attestations = (0..20)
+            .map(|i| Attestation {
+                node: format!("node{i}"),
+                network: Some("10.1".into()),
+                first_seen: Utc::now() - Duration::days(60),
+                digest_at: Utc::now() - Duration::hours(1),
+            })
+            .collect();
+        let result = ConsensusResult {
+            hash: bin.hash.clone(),
+            found: true,
+            node_count: 20,
+            network_trust: None,
+            total_nodes: None,
+            attestations,
+            fetched_at: Utc::now(),
+            ttl_secs: 3600,
+            from_cache: false,
+            stale: false,
+        };
+        let consensus = HashMap::from([(bin.hash.clone(), result)]);
+
+        let anomalies = compare_binaries(&[bin], &consensus, &TrustWeights::default());
+        let thin: Vec<_> = anomalies
+            .iter()
+            .filter(|a| a.kind == AnomalyKind::ThinConsensus)
+            .collect();
+        assert_eq!(thin.len(), 1);
+        assert_eq!(thin[0].severity, Severity::High);
+    }
+
+    #[test]
+    fn processes_without_a_backing_binary_are_high() {
+        use crate::report::tests::{binary, process};
+
+        let binaries = vec![binary("/usr/bin/sshd", true, 0.9)];
+        let mut replaced = process(300, "/usr/bin/sshd");
+        replaced.exe_missing = true;
+        let mut kernel_thread = process(2, "kthreadd");
+        kernel_thread.exe_path = None;
+        let processes = vec![
+            process(100, "/usr/bin/sshd"),
+            process(200, "/dev/shm/.k (deleted)"),
+            replaced,
+            process(400, "/home/user/.cache/agent"),
+            kernel_thread,
+        ];
+
+        let anomalies = compare_processes(&processes, &binaries);
+        let found: Vec<_> = anomalies.iter().map(|a| (a.kind, a.severity)).collect();
+        assert_eq!(
+            found,
+            [
+                (AnomalyKind::DeletedExecutable, Severity::High),
+                (AnomalyKind::DeletedExecutable, Severity::High),
+                (AnomalyKind::UnbackedProcess, Severity::High),
+            ]
+        );
+        assert!(anomalies[0].description.ends_with("exe=/dev/shm/.k)"));
+        assert!(anomalies[2].description.contains("pid=400"));
+
+        // Without discovery results only deleted executables are flagged
+        assert_eq!(compare_processes(&processes, &[]).len(), 2);
+    }
+
+    #[test]
+    fn first_audit_reports_no_new_modules() {
+        let current = vec![make_module("ext4", "", ModuleOrigin::ModuleTree)];
+        assert!(compare_modules(&[], &current).is_empty());
+    }
+}
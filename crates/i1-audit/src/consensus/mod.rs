@@ -1,9 +1,18 @@
 //! Network consensus — query and compare against community data.
 
+pub mod cache;
 pub mod compare;
 pub mod query;
+pub mod summary;
 
-pub use compare::{compare_binaries, compare_certs, Anomaly, AnomalyKind, Severity};
+pub use cache::{CacheStats, ConsensusCache};
+pub use compare::{
+    compare_binaries, compare_certs, compare_libraries, compare_modules, compare_processes,
+    Anomaly, AnomalyKind, Severity,
+};
 pub use query::{
-    create_resolver, query_binary_consensus, query_cert_consensus, ConsensusResult,
+    create_resolver, parse_consensus_records, query_binary_consensus,
+    query_binary_consensus_cached, query_cert_consensus, query_cert_consensus_cached,
+    ConsensusResult,
 };
+pub use summary::{AnomalyGroup, AnomalyReport};
@@ -3,14 +3,24 @@
 //! Phase 3 implementation -- queries the network to see how many
 //! other nodes report the same binary hash or cert fingerprint.
 
+use chrono::{DateTime, Utc};
 use hickory_resolver::TokioResolver;
-use tracing::debug;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use crate::encoding::{binary_dns_name, cert_dns_name};
+use super::cache::ConsensusCache;
+use crate::encoding::{binary_dns_name, cert_dns_name, decode_attestation_txt, ATTESTATION_PREFIX};
 use crate::error::{AuditError, Result};
+use crate::hash::HashAlgorithm;
+use crate::types::Attestation;
+
+/// Cache lifetime for negative (not found) answers, in seconds.
+///
+/// Matches i1-srv's SOA minimum so a newly published hash is picked up quickly.
+const NEGATIVE_TTL_SECS: u32 = 300;
 
 /// Result of a consensus query for a single hash/fingerprint.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsensusResult {
     /// The hash or fingerprint queried
     pub hash: String,
@@ -20,22 +30,73 @@ pub struct ConsensusResult {
     pub node_count: u32,
     /// Trust percentage from the network (parsed from TXT `trust=` field)
     pub network_trust: Option<u32>,
+    /// Nodes reporting *any* hash for this binary name (TXT `total=` field),
+    /// used for the agreement ratio
+    #[serde(default)]
+    pub total_nodes: Option<u32>,
+    /// Per-node attestations, when the zone publishes them
+    #[serde(default)]
+    pub attestations: Vec<Attestation>,
+    /// When this answer was fetched from the network
+    pub fetched_at: DateTime<Utc>,
+    /// DNS TTL of the answer (seconds), used as cache lifetime
+    pub ttl_secs: u32,
+    /// Served from the local cache rather than a live query
+    #[serde(default)]
+    pub from_cache: bool,
+    /// Cached answer past its TTL, served because the network was unreachable
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl ConsensusResult {
+    /// Fraction of reporting nodes that agree on this hash (0.0..1.0).
+    ///
+    /// `node_count / total_nodes`; 1.0 when the zone doesn't publish a total.
+    #[must_use]
+    pub fn agreement_ratio(&self) -> f64 {
+        match self.total_nodes {
+            Some(total) if total > 0 => {
+                (f64::from(self.node_count) / f64::from(total)).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// A "not in consensus" answer fetched just now.
+    fn not_found(hash: &str) -> Self {
+        Self {
+            hash: hash.to_string(),
+            found: false,
+            node_count: 0,
+            network_trust: None,
+            total_nodes: None,
+            attestations: Vec::new(),
+            fetched_at: Utc::now(),
+            ttl_secs: NEGATIVE_TTL_SECS,
+            from_cache: false,
+            stale: false,
+        }
+    }
 }
 
-/// Query the network for a binary hash consensus.
+/// Query the network for a binary hash consensus, in the namespace for
+/// `algorithm`.
 ///
 /// # Errors
 ///
 /// Returns `AuditError::DnsQuery` if the DNS query fails unexpectedly.
 pub async fn query_binary_consensus(
     resolver: &TokioResolver,
-    sha256: &str,
+    hash: &str,
+    algorithm: HashAlgorithm,
 ) -> Result<ConsensusResult> {
-    let name = binary_dns_name(sha256);
-    query_txt_record(resolver, &name, sha256).await
+    let name = binary_dns_name(hash, algorithm);
+    query_txt_record(resolver, &name, hash).await
 }
 
-/// Query the network for a certificate fingerprint consensus.
+/// Query the network for a certificate fingerprint consensus, in the
+/// namespace for `algorithm`.
 ///
 /// # Errors
 ///
@@ -43,54 +104,168 @@ pub async fn query_binary_consensus(
 pub async fn query_cert_consensus(
     resolver: &TokioResolver,
     fingerprint: &str,
+    algorithm: HashAlgorithm,
 ) -> Result<ConsensusResult> {
-    let name = cert_dns_name(fingerprint);
+    let name = cert_dns_name(fingerprint, algorithm);
     query_txt_record(resolver, &name, fingerprint).await
 }
 
+/// Query binary hash consensus, consulting the cache first.
+///
+/// With `refresh = true` the cache is bypassed for reads (but still updated).
+/// If the network is unreachable, a stale cached answer is returned instead.
+///
+/// # Errors
+///
+/// Returns `AuditError::DnsQuery` if the DNS query fails unexpectedly.
+pub async fn query_binary_consensus_cached(
+    resolver: &TokioResolver,
+    cache: &mut ConsensusCache,
+    hash: &str,
+    algorithm: HashAlgorithm,
+    refresh: bool,
+) -> Result<ConsensusResult> {
+    let name = binary_dns_name(hash, algorithm);
+    query_cached(resolver, cache, &name, hash, refresh).await
+}
+
+/// Query certificate fingerprint consensus, consulting the cache first.
+///
+/// See [`query_binary_consensus_cached`] for cache semantics.
+///
+/// # Errors
+///
+/// Returns `AuditError::DnsQuery` if the DNS query fails unexpectedly.
+pub async fn query_cert_consensus_cached(
+    resolver: &TokioResolver,
+    cache: &mut ConsensusCache,
+    fingerprint: &str,
+    algorithm: HashAlgorithm,
+    refresh: bool,
+) -> Result<ConsensusResult> {
+    let name = cert_dns_name(fingerprint, algorithm);
+    query_cached(resolver, cache, &name, fingerprint, refresh).await
+}
+
+/// Cache-aware lookup with stale-while-revalidate fallback.
+async fn query_cached(
+    resolver: &TokioResolver,
+    cache: &mut ConsensusCache,
+    dns_name: &str,
+    hash: &str,
+    refresh: bool,
+) -> Result<ConsensusResult> {
+    if !refresh {
+        if let Some(hit) = cache.get_fresh(hash) {
+            return Ok(hit);
+        }
+    }
+
+    cache.record_miss();
+    match fetch_txt_record(resolver, dns_name, hash).await {
+        Ok(result) => {
+            cache.insert(&result);
+            Ok(result)
+        }
+        Err(e @ AuditError::SchemaMismatch(_)) => {
+            // Retrying won't help until one side upgrades, so say so
+            warn!(name = dns_name, error = %e, "consensus record unreadable, trying stale cache");
+            Ok(cache
+                .get_stale(hash)
+                .unwrap_or_else(|| ConsensusResult::not_found(hash)))
+        }
+        Err(e) => {
+            debug!(name = dns_name, error = %e, "consensus query failed, trying stale cache");
+            Ok(cache
+                .get_stale(hash)
+                .unwrap_or_else(|| ConsensusResult::not_found(hash)))
+        }
+    }
+}
+
 /// Generic TXT record query and parse.
+///
+/// Network failures are reported as "not found" (no cache to fall back on).
 async fn query_txt_record(
     resolver: &TokioResolver,
     dns_name: &str,
     hash: &str,
+) -> Result<ConsensusResult> {
+    Ok(fetch_txt_record(resolver, dns_name, hash)
+        .await
+        .unwrap_or_else(|_| ConsensusResult::not_found(hash)))
+}
+
+/// Query a TXT record, distinguishing "not in consensus" from network failure.
+///
+/// NXDOMAIN / no records is a definitive answer (`Ok` with `found: false`);
+/// anything else (timeout, SERVFAIL, no route) is an `Err`.
+async fn fetch_txt_record(
+    resolver: &TokioResolver,
+    dns_name: &str,
+    hash: &str,
 ) -> Result<ConsensusResult> {
     debug!(name = dns_name, "querying DNS consensus");
 
-    let lookup = resolver.txt_lookup(dns_name).await;
-
-    match lookup {
-        Ok(records) => Ok(records.iter().next().map_or_else(
-            || ConsensusResult {
-                hash: hash.to_string(),
-                found: false,
-                node_count: 0,
-                network_trust: None,
-            },
-            |record| {
-                let txt = record.to_string();
-                let node_count = parse_field(&txt, "nodes").unwrap_or(0);
-                let network_trust = parse_field(&txt, "trust");
-                ConsensusResult {
-                    hash: hash.to_string(),
-                    found: true,
-                    node_count,
-                    network_trust,
-                }
-            },
-        )),
-        Err(e) => {
-            // NXDOMAIN or SERVFAIL -- hash not in network
+    match resolver.txt_lookup(dns_name).await {
+        Ok(records) => {
+            let ttl_secs = records
+                .as_lookup()
+                .records()
+                .first()
+                .map_or(NEGATIVE_TTL_SECS, hickory_resolver::proto::rr::Record::ttl);
+            let txts: Vec<String> = records.iter().map(ToString::to_string).collect();
+            parse_consensus_records(hash, &txts, ttl_secs)
+        }
+        Err(e) if e.is_nx_domain() || e.is_no_records_found() => {
+            // Hash not in network
             debug!(name = dns_name, error = %e, "no consensus record found");
-            Ok(ConsensusResult {
-                hash: hash.to_string(),
-                found: false,
-                node_count: 0,
-                network_trust: None,
-            })
+            Ok(ConsensusResult::not_found(hash))
         }
+        Err(e) => Err(AuditError::DnsQuery(format!("{dns_name}: {e}"))),
     }
 }
 
+/// Build a consensus result from the TXT strings at a hash's DNS name.
+///
+/// The record set holds one summary record (`hash=...;nodes=...`) plus zero or
+/// more attestation records (`att=...`). Unparsable attestations are skipped.
+///
+/// # Errors
+///
+/// Returns `AuditError::SchemaMismatch` if the summary record was written
+/// with another wire schema version.
+pub fn parse_consensus_records(
+    hash: &str,
+    txts: &[String],
+    ttl_secs: u32,
+) -> Result<ConsensusResult> {
+    let (attestation_txts, summaries): (Vec<&String>, Vec<&String>) = txts
+        .iter()
+        .partition(|t| t.starts_with(ATTESTATION_PREFIX));
+
+    let Some(summary) = summaries.first() else {
+        return Ok(ConsensusResult::not_found(hash));
+    };
+    i1_core::wire::check(summary)?;
+
+    Ok(ConsensusResult {
+        hash: hash.to_string(),
+        found: true,
+        node_count: parse_field(summary, "nodes").unwrap_or(0),
+        network_trust: parse_field(summary, "trust"),
+        total_nodes: parse_field(summary, "total"),
+        attestations: attestation_txts
+            .iter()
+            .filter_map(|t| decode_attestation_txt(t))
+            .collect(),
+        fetched_at: Utc::now(),
+        ttl_secs,
+        from_cache: false,
+        stale: false,
+    })
+}
+
 /// Parse a `key=value` field from a semicolon-delimited TXT record.
 fn parse_field(txt: &str, key: &str) -> Option<u32> {
     let prefix = format!("{key}=");
@@ -130,4 +305,37 @@ mod tests {
         assert_eq!(parse_field(txt, "size"), Some(1024));
         assert_eq!(parse_field(txt, "missing"), None);
     }
+
+    #[test]
+    fn parse_records_separates_attestations() {
+        let txts = vec![
+            "att=a1b2c3d4e5f6;net=203.0;first=1700000000;dts=1700000000".to_string(),
+            "hash=abc;name=sshd;size=1024;trust=87;nodes=142;total=150;v=1".to_string(),
+            "att=garbage".to_string(),
+        ];
+        let result = parse_consensus_records("abc", &txts, 3600).unwrap();
+        assert!(result.found);
+        assert_eq!(result.node_count, 142);
+        assert_eq!(result.total_nodes, Some(150));
+        assert_eq!(result.attestations.len(), 1);
+        assert_eq!(result.attestations[0].network.as_deref(), Some("203.0"));
+        assert!((result.agreement_ratio() - 142.0 / 150.0).abs() < f64::EPSILON);
+
+        let only_attestations = vec![txts[0].clone()];
+        assert!(
+            !parse_consensus_records("abc", &only_attestations, 3600)
+                .unwrap()
+                .found
+        );
+    }
+
+    #[test]
+    fn parse_records_rejects_other_schema() {
+        let txts = vec!["hash=abc;nodes=142;v=2".to_string()];
+        let err = parse_consensus_records("abc", &txts, 3600).unwrap_err();
+        assert!(matches!(
+            err,
+            AuditError::SchemaMismatch(mismatch) if mismatch.found == 2
+        ));
+    }
 }
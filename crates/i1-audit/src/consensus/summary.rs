@@ -0,0 +1,180 @@
+//! Anomaly rollup -- comparison results grouped by severity and kind.
+//!
+//! [`compare_binaries`](super::compare_binaries), [`compare_certs`](super::compare_certs)
+//! and [`compare_modules`](super::compare_modules) each return a flat list.
+//! [`AnomalyReport`] merges any number of them into groups of one severity
+//! and kind, most severe first, with counts for an at-a-glance summary
+//! such as `3 Critical, 5 High`.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::compare::{Anomaly, AnomalyKind, Severity};
+use crate::report::SeverityCounts;
+
+/// Anomalies sharing a severity and kind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnomalyGroup {
+    /// Severity of every anomaly in the group
+    pub severity: Severity,
+    /// Kind of every anomaly in the group
+    pub kind: AnomalyKind,
+    /// The anomalies, in the order they were added
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// Anomalies grouped by severity and kind, most severe first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnomalyReport {
+    /// Anomalies per severity
+    pub counts: SeverityCounts,
+    /// Groups ordered by severity (worst first), then kind
+    pub groups: Vec<AnomalyGroup>,
+}
+
+impl AnomalyReport {
+    /// Group `anomalies`, e.g. the chained output of several comparisons.
+    #[must_use]
+    pub fn new(anomalies: impl IntoIterator<Item = Anomaly>) -> Self {
+        let mut anomalies: Vec<Anomaly> = anomalies.into_iter().collect();
+        anomalies.sort_by_key(|a| (Reverse(a.severity), a.kind));
+
+        let mut report = Self::default();
+        for anomaly in anomalies {
+            report.counts.add(anomaly.severity);
+            match report.groups.last_mut() {
+                Some(group) if group.severity == anomaly.severity && group.kind == anomaly.kind => {
+                    group.anomalies.push(anomaly);
+                }
+                _ => report.groups.push(AnomalyGroup {
+                    severity: anomaly.severity,
+                    kind: anomaly.kind,
+                    anomalies: vec![anomaly],
+                }),
+            }
+        }
+        report
+    }
+
+    /// Number of anomalies.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.counts.total()
+    }
+
+    /// Whether there are no anomalies.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Severity of the worst anomaly.
+    #[must_use]
+    pub fn highest(&self) -> Option<Severity> {
+        self.groups.first().map(|g| g.severity)
+    }
+
+    /// Anomalies of `kind` at any severity.
+    #[must_use]
+    pub fn count_kind(&self, kind: AnomalyKind) -> usize {
+        self.groups
+            .iter()
+            .filter(|g| g.kind == kind)
+            .map(|g| g.anomalies.len())
+            .sum()
+    }
+
+    /// Groups at `severity`, ordered by kind.
+    pub fn at(&self, severity: Severity) -> impl Iterator<Item = &AnomalyGroup> {
+        self.groups.iter().filter(move |g| g.severity == severity)
+    }
+
+    /// Every anomaly, most severe first.
+    pub fn anomalies(&self) -> impl Iterator<Item = &Anomaly> {
+        self.groups.iter().flat_map(|g| &g.anomalies)
+    }
+}
+
+impl FromIterator<Anomaly> for AnomalyReport {
+    fn from_iter<I: IntoIterator<Item = Anomaly>>(iter: I) -> Self {
+        Self::new(iter)
+    }
+}
+
+/// The counts line, then each severity with its kinds and their counts:
+///
+/// ```text
+/// 1 Critical, 2 Medium
+/// Critical
+///   unknown cert: 1
+/// Medium
+///   expired cert: 1
+///   suspicious location: 1
+/// ```
+impl fmt::Display for AnomalyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.counts)?;
+        let mut current = None;
+        for group in &self.groups {
+            if current != Some(group.severity) {
+                current = Some(group.severity);
+                write!(f, "\n{}", group.severity)?;
+            }
+            write!(f, "\n  {}: {}", group.kind, group.anomalies.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anomaly(kind: AnomalyKind, severity: Severity, description: &str) -> Anomaly {
+        Anomaly {
+            kind,
+            severity,
+            description: description.into(),
+        }
+    }
+
+    #[test]
+    fn groups_worst_first_and_counts() {
+        let report: AnomalyReport = [
+            anomaly(AnomalyKind::SuspiciousLocation, Severity::Medium, "a"),
+            anomaly(AnomalyKind::UnknownCert, Severity::Critical, "b"),
+            anomaly(AnomalyKind::ExpiredCert, Severity::Medium, "c"),
+            anomaly(AnomalyKind::SuspiciousLocation, Severity::Medium, "d"),
+            anomaly(AnomalyKind::UnknownBinary, Severity::High, "e"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(report.len(), 5);
+        assert_eq!(report.highest(), Some(Severity::Critical));
+        assert_eq!(report.counts.medium, 3);
+        assert_eq!(report.count_kind(AnomalyKind::SuspiciousLocation), 2);
+        assert_eq!(report.at(Severity::Medium).count(), 2);
+
+        let order: Vec<_> = report.anomalies().map(|a| a.description.as_str()).collect();
+        assert_eq!(order, ["b", "e", "c", "a", "d"]);
+
+        assert_eq!(
+            report.to_string(),
+            "1 Critical, 1 High, 3 Medium\n\
+             Critical\n  unknown cert: 1\n\
+             High\n  unknown binary: 1\n\
+             Medium\n  expired cert: 1\n  suspicious location: 2"
+        );
+    }
+
+    #[test]
+    fn empty_report() {
+        let report = AnomalyReport::new(Vec::new());
+        assert!(report.is_empty());
+        assert_eq!(report.highest(), None);
+        assert_eq!(report.to_string(), "none");
+    }
+}
@@ -6,11 +6,13 @@ use std::path::Path;
 use tracing::{debug, warn};
 use walkdir::WalkDir;
 
+use super::hash_cache::{FileStamp, HashCache};
 use crate::error::{AuditError, Result};
-use crate::hash::sha256_file;
+use crate::hash::{hash_file, HashAlgorithm};
 use crate::types::{BinaryInfo, FileIdentity};
 
 /// Default paths to scan for binaries.
+#[cfg(not(target_os = "macos"))]
 pub const DEFAULT_BIN_PATHS: &[&str] = &[
     "/usr/bin",
     "/usr/sbin",
@@ -20,16 +22,59 @@ pub const DEFAULT_BIN_PATHS: &[&str] = &[
     "/sbin",
 ];
 
+/// Default paths to scan for binaries (including Homebrew prefixes).
+#[cfg(target_os = "macos")]
+pub const DEFAULT_BIN_PATHS: &[&str] = &[
+    "/usr/bin",
+    "/usr/sbin",
+    "/usr/local/bin",
+    "/usr/local/sbin",
+    "/bin",
+    "/sbin",
+    "/opt/homebrew/bin",
+    "/opt/homebrew/sbin",
+];
+
+/// Default scan paths for this platform.
+///
+/// `DEFAULT_BIN_PATHS` plus, on macOS, each installed application's
+/// `/Applications/*.app/Contents/MacOS` directory.
+#[must_use]
+pub fn default_bin_paths() -> Vec<String> {
+    #[allow(unused_mut)]
+    let mut paths: Vec<String> = DEFAULT_BIN_PATHS.iter().map(|p| (*p).to_string()).collect();
+    #[cfg(target_os = "macos")]
+    paths.extend(super::macos::app_bundle_bin_paths());
+    paths
+}
+
 /// Discover all executable binaries in the given paths.
 ///
 /// Walks each directory, skipping symlinks-to-nowhere and unreadable files.
-/// Returns `BinaryInfo` with hashes but without process correlation (that's
+/// Returns `BinaryInfo` with `algorithm` hashes but without process correlation (that's
 /// done by `correlate_processes`).
 ///
 /// # Errors
 ///
 /// Returns `AuditError` if directory walking fails catastrophically.
-pub async fn discover_binaries(paths: &[&str]) -> Result<Vec<BinaryInfo>> {
+pub async fn discover_binaries(
+    paths: &[&str],
+    algorithm: HashAlgorithm,
+) -> Result<Vec<BinaryInfo>> {
+    discover_binaries_cached(paths, algorithm, &mut HashCache::in_memory()).await
+}
+
+/// Discover binaries like [`discover_binaries`], reusing hashes from
+/// `cache` for files whose identity, size and mtime are unchanged.
+///
+/// # Errors
+///
+/// Returns `AuditError` if directory walking fails catastrophically.
+pub async fn discover_binaries_cached(
+    paths: &[&str],
+    algorithm: HashAlgorithm,
+    cache: &mut HashCache,
+) -> Result<Vec<BinaryInfo>> {
     let mut binaries = Vec::new();
 
     for base_path in paths {
@@ -49,7 +94,7 @@ pub async fn discover_binaries(paths: &[&str]) -> Result<Vec<BinaryInfo>> {
 
         for entry in entries {
             let path = entry.path();
-            match collect_binary_info(path).await {
+            match collect_binary_info(path, algorithm, cache, true).await {
                 Ok(info) => binaries.push(info),
                 Err(e) => {
                     warn!(path = %path.display(), error = %e, "skipping binary");
@@ -62,7 +107,15 @@ pub async fn discover_binaries(paths: &[&str]) -> Result<Vec<BinaryInfo>> {
 }
 
 /// Collect metadata + hash for a single binary.
-async fn collect_binary_info(path: &Path) -> Result<BinaryInfo> {
+///
+/// Files without an executable bit are rejected when `executable` is set;
+/// shared libraries are often mapped without one.
+pub(super) async fn collect_binary_info(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    cache: &mut HashCache,
+    executable: bool,
+) -> Result<BinaryInfo> {
     let path_str = path.display().to_string();
     let meta = tokio::fs::metadata(path)
         .await
@@ -70,14 +123,30 @@ async fn collect_binary_info(path: &Path) -> Result<BinaryInfo> {
 
     // Check executable bit
     let mode = meta.mode();
-    if mode & 0o111 == 0 {
+    if executable && mode & 0o111 == 0 {
         return Err(AuditError::Hash {
             path: path_str,
             reason: "not executable".into(),
         });
     }
 
-    let sha256 = sha256_file(path).await?;
+    let identity = FileIdentity {
+        inode: meta.ino(),
+        device_id: meta.dev(),
+    };
+    let stamp = FileStamp {
+        identity,
+        size: meta.len(),
+        mtime_secs: meta.mtime(),
+        mtime_nsecs: meta.mtime_nsec(),
+    };
+    let hash = if let Some(hash) = cache.get(&stamp, algorithm) {
+        hash
+    } else {
+        let hash = hash_file(path, algorithm).await?;
+        cache.insert(&stamp, algorithm, &hash);
+        hash
+    };
 
     // File timestamps
     let mtime = meta.mtime();
@@ -94,14 +163,10 @@ async fn collect_binary_info(path: &Path) -> Result<BinaryInfo> {
             .unwrap_or(modify_date)
     });
 
-    let identity = FileIdentity {
-        inode: meta.ino(),
-        device_id: meta.dev(),
-    };
-
     Ok(BinaryInfo {
         path: path_str,
-        sha256,
+        hash,
+        hash_algorithm: algorithm,
         create_date,
         modify_date,
         identity,
@@ -5,10 +5,12 @@ use std::path::Path;
 use tracing::{debug, warn};
 
 use crate::error::{AuditError, Result};
-use crate::hash::sha256_bytes;
+use crate::hash::{hash_bytes, HashAlgorithm};
 use crate::types::RootCertInfo;
 
 /// Known root CA store locations across Linux distributions.
+///
+/// On macOS the keychains in `macos::KEYCHAINS` are read as well.
 const CA_STORE_PATHS: &[&str] = &[
     // Arch / Fedora / RHEL bundle
     "/etc/ssl/certs/ca-certificates.crt",
@@ -28,13 +30,14 @@ const CA_STORE_PATHS: &[&str] = &[
     "/etc/ca-certificates/extracted",
 ];
 
-/// Discover all root certificates in system trust stores.
+/// Discover all root certificates in system trust stores, fingerprinting
+/// each with `algorithm`.
 ///
 /// # Errors
 ///
 /// Returns `AuditError` if certificate parsing fails for an entire store.
 /// Individual cert parse failures are logged and skipped.
-pub async fn discover_root_certs() -> Result<Vec<RootCertInfo>> {
+pub async fn discover_root_certs(algorithm: HashAlgorithm) -> Result<Vec<RootCertInfo>> {
     let mut certs = Vec::new();
     let mut seen_fingerprints = std::collections::HashSet::new();
 
@@ -46,7 +49,7 @@ pub async fn discover_root_certs() -> Result<Vec<RootCertInfo>> {
         }
 
         if path.is_file() {
-            match parse_pem_bundle(path).await {
+            match parse_pem_bundle(path, algorithm).await {
                 Ok(found) => {
                     for cert in found {
                         if seen_fingerprints.insert(cert.fingerprint.clone()) {
@@ -57,7 +60,7 @@ pub async fn discover_root_certs() -> Result<Vec<RootCertInfo>> {
                 Err(e) => warn!(path = store_path, error = %e, "failed to parse CA bundle"),
             }
         } else if path.is_dir() {
-            match parse_cert_directory(path).await {
+            match parse_cert_directory(path, algorithm).await {
                 Ok(found) => {
                     for cert in found {
                         if seen_fingerprints.insert(cert.fingerprint.clone()) {
@@ -70,18 +73,41 @@ pub async fn discover_root_certs() -> Result<Vec<RootCertInfo>> {
         }
     }
 
+    #[cfg(target_os = "macos")]
+    for keychain in super::macos::KEYCHAINS {
+        match super::macos::keychain_pems(keychain)
+            .and_then(|pems| parse_pem_bytes(&pems, keychain, algorithm))
+        {
+            Ok(found) => certs.extend(
+                found
+                    .into_iter()
+                    .filter(|c| seen_fingerprints.insert(c.fingerprint.clone())),
+            ),
+            Err(e) => warn!(keychain, error = %e, "failed to read keychain"),
+        }
+    }
+
     Ok(certs)
 }
 
 /// Parse a PEM bundle file containing multiple certificates.
-async fn parse_pem_bundle(path: &Path) -> Result<Vec<RootCertInfo>> {
+async fn parse_pem_bundle(path: &Path, algorithm: HashAlgorithm) -> Result<Vec<RootCertInfo>> {
     let path_str = path.display().to_string();
     let content = tokio::fs::read(path)
         .await
         .map_err(|e| AuditError::io(&path_str, e))?;
 
-    let pems = pem::parse_many(&content).map_err(|e| AuditError::PemDecode {
-        path: path_str.clone(),
+    parse_pem_bytes(&content, &path_str, algorithm)
+}
+
+/// Parse concatenated PEM certificates, attributing them to `source`.
+fn parse_pem_bytes(
+    content: &[u8],
+    source: &str,
+    algorithm: HashAlgorithm,
+) -> Result<Vec<RootCertInfo>> {
+    let pems = pem::parse_many(content).map_err(|e| AuditError::PemDecode {
+        path: source.to_string(),
         reason: e.to_string(),
     })?;
 
@@ -90,9 +116,9 @@ async fn parse_pem_bundle(path: &Path) -> Result<Vec<RootCertInfo>> {
         if p.tag() != "CERTIFICATE" {
             continue;
         }
-        match parse_x509_der(p.contents(), &path_str) {
+        match parse_x509_der(p.contents(), source, algorithm) {
             Ok(cert) => certs.push(cert),
-            Err(e) => debug!(path = %path_str, error = %e, "skipping cert in bundle"),
+            Err(e) => debug!(path = %source, error = %e, "skipping cert in bundle"),
         }
     }
 
@@ -100,7 +126,7 @@ async fn parse_pem_bundle(path: &Path) -> Result<Vec<RootCertInfo>> {
 }
 
 /// Parse all .pem / .crt files in a directory.
-async fn parse_cert_directory(dir: &Path) -> Result<Vec<RootCertInfo>> {
+async fn parse_cert_directory(dir: &Path, algorithm: HashAlgorithm) -> Result<Vec<RootCertInfo>> {
     let mut certs = Vec::new();
     let mut entries = tokio::fs::read_dir(dir)
         .await
@@ -116,7 +142,7 @@ async fn parse_cert_directory(dir: &Path) -> Result<Vec<RootCertInfo>> {
         if !matches!(ext, "pem" | "crt" | "cer") {
             continue;
         }
-        match parse_pem_bundle(&path).await {
+        match parse_pem_bundle(&path, algorithm).await {
             Ok(found) => certs.extend(found),
             Err(e) => debug!(path = %path.display(), error = %e, "skipping cert file"),
         }
@@ -126,7 +152,7 @@ async fn parse_cert_directory(dir: &Path) -> Result<Vec<RootCertInfo>> {
 }
 
 /// Parse a single DER-encoded X.509 certificate.
-fn parse_x509_der(der: &[u8], source_path: &str) -> Result<RootCertInfo> {
+pub(crate) fn parse_x509_der(der: &[u8], source_path: &str, algorithm: HashAlgorithm) -> Result<RootCertInfo> {
     let (_, cert) = x509_parser::parse_x509_certificate(der).map_err(|e| {
         AuditError::CertParse {
             path: source_path.to_string(),
@@ -134,7 +160,7 @@ fn parse_x509_der(der: &[u8], source_path: &str) -> Result<RootCertInfo> {
         }
     })?;
 
-    let fingerprint = sha256_bytes(der);
+    let fingerprint = hash_bytes(der, algorithm);
     let issuer = cert.issuer().to_string();
     let subject = cert.subject().to_string();
     let serial = cert.raw_serial_as_string();
@@ -146,6 +172,7 @@ fn parse_x509_der(der: &[u8], source_path: &str) -> Result<RootCertInfo> {
     Ok(RootCertInfo {
         path: source_path.to_string(),
         fingerprint,
+        fingerprint_algorithm: algorithm,
         issuer,
         subject,
         serial,
@@ -154,6 +181,8 @@ fn parse_x509_der(der: &[u8], source_path: &str) -> Result<RootCertInfo> {
         expired,
         in_consensus: None,
         trust_score: None,
+        findings: Vec::new(),
+        der: der.to_vec(),
     })
 }
 
@@ -0,0 +1,292 @@
+//! On-disk cache of binary hashes, keyed by file identity.
+//!
+//! Hashing every binary on every run dominates audit time, yet almost none
+//! of them change between runs. A file whose device, inode, size and mtime
+//! all match the cached entry is assumed unchanged and its stored hash is
+//! reused; any difference forces a rehash.
+//!
+//! Entries are kept per hash algorithm, so switching `--hash` never serves
+//! a digest from the other namespace.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::error::{AuditError, Result};
+use crate::hash::HashAlgorithm;
+use crate::types::FileIdentity;
+
+/// Name of the hash cache file, kept next to the consensus cache.
+pub const HASH_CACHE_FILE_NAME: &str = "hash_cache.json";
+
+/// What a cached hash is valid for: a file's identity plus the metadata
+/// that changes whenever its contents are rewritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    /// Device + inode
+    pub identity: FileIdentity,
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time, seconds since the epoch
+    pub mtime_secs: i64,
+    /// Sub-second part of the modification time
+    pub mtime_nsecs: i64,
+}
+
+impl FileStamp {
+    fn key(&self, algorithm: HashAlgorithm) -> String {
+        format!(
+            "{algorithm}:{}:{}",
+            self.identity.device_id, self.identity.inode
+        )
+    }
+}
+
+/// A cached hash and the size/mtime it was computed for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    hash: String,
+    size: u64,
+    mtime_secs: i64,
+    mtime_nsecs: i64,
+    seen_at: DateTime<Utc>,
+}
+
+/// How many files one discovery run skipped hashing and how many it hashed.
+/// Only kept in memory; the file records hashes, not runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashCacheStats {
+    /// Files whose stamp matched, so the stored hash was reused
+    pub hits: u32,
+    /// Files that were new, changed, or rehashed on request
+    pub misses: u32,
+}
+
+/// Persistent hash cache keyed by `(device, inode)` and algorithm.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HashCache {
+    entries: HashMap<String, CacheEntry>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    #[serde(skip)]
+    rehash: bool,
+    #[serde(skip)]
+    stats: HashCacheStats,
+}
+
+impl HashCache {
+    /// A cache with no file behind it, so every file is hashed and
+    /// nothing is kept after the run. Used by the uncached discovery entry
+    /// points.
+    #[must_use]
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Read the stored hashes at `path`.
+    ///
+    /// The first run, or a file that no longer parses, starts from nothing:
+    /// every binary is hashed and [`save`](Self::save) replaces the file.
+    #[must_use]
+    pub fn load(path: &Path) -> Self {
+        let mut cache = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| {
+                serde_json::from_str::<Self>(&content)
+                    .map_err(|e| {
+                        warn!(path = %path.display(), error = %e, "discarding corrupt hash cache");
+                    })
+                    .ok()
+            })
+            .unwrap_or_default();
+        cache.path = Some(path.to_path_buf());
+        cache
+    }
+
+    /// Ignore stored hashes for this session, hashing every file again.
+    ///
+    /// Fresh results are still recorded, so the next run benefits.
+    #[must_use]
+    pub const fn with_rehash(mut self, rehash: bool) -> Self {
+        self.rehash = rehash;
+        self
+    }
+
+    /// Store the hashes at the path given to [`load`](Self::load), creating
+    /// its directory. An [`in_memory`](Self::in_memory) cache has nowhere to
+    /// go and is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError` if the file or its directory can't be written.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AuditError::io(parent.display().to_string(), e))?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).map_err(|e| AuditError::io(path.display().to_string(), e))
+    }
+
+    /// Return the stored hash if `stamp` still matches it, counting a hit;
+    /// otherwise count a miss.
+    pub fn get(&mut self, stamp: &FileStamp, algorithm: HashAlgorithm) -> Option<String> {
+        let entry = self
+            .entries
+            .get_mut(&stamp.key(algorithm))
+            .filter(|_| !self.rehash)
+            .filter(|e| {
+                e.size == stamp.size
+                    && e.mtime_secs == stamp.mtime_secs
+                    && e.mtime_nsecs == stamp.mtime_nsecs
+            });
+        let Some(entry) = entry else {
+            self.stats.misses += 1;
+            return None;
+        };
+        entry.seen_at = Utc::now();
+        self.stats.hits += 1;
+        Some(entry.hash.clone())
+    }
+
+    /// Record the hash computed for `stamp`, replacing any older entry.
+    pub fn insert(&mut self, stamp: &FileStamp, algorithm: HashAlgorithm, hash: &str) {
+        debug!(
+            device = stamp.identity.device_id,
+            inode = stamp.identity.inode,
+            "caching binary hash"
+        );
+        self.entries.insert(
+            stamp.key(algorithm),
+            CacheEntry {
+                hash: hash.to_string(),
+                size: stamp.size,
+                mtime_secs: stamp.mtime_secs,
+                mtime_nsecs: stamp.mtime_nsecs,
+                seen_at: Utc::now(),
+            },
+        );
+    }
+
+    /// Drop entries for files not seen in `max_age`.
+    pub fn prune(&mut self, max_age: Duration) {
+        let cutoff = Utc::now() - max_age;
+        self.entries.retain(|_, e| e.seen_at > cutoff);
+    }
+
+    /// Number of stored hashes, counting each algorithm separately.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no file has a stored hash yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Reused and rehashed counts since this cache was loaded.
+    #[must_use]
+    pub const fn stats(&self) -> HashCacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn stamp(inode: u64, size: u64, mtime_secs: i64) -> FileStamp {
+        FileStamp {
+            identity: FileIdentity {
+                inode,
+                device_id: 2049,
+            },
+            size,
+            mtime_secs,
+            mtime_nsecs: 0,
+        }
+    }
+
+    #[test]
+    fn unchanged_file_is_a_hit() {
+        let mut cache = HashCache::in_memory();
+        cache.insert(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256, "abc");
+
+        let hit = cache.get(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256);
+        assert_eq!(hit.as_deref(), Some("abc"));
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[test]
+    fn changed_metadata_invalidates() {
+        let mut cache = HashCache::in_memory();
+        cache.insert(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256, "abc");
+
+        assert!(cache
+            .get(&stamp(7, 101, 1_700_000_000), HashAlgorithm::Sha256)
+            .is_none());
+        assert!(cache
+            .get(&stamp(7, 100, 1_700_000_001), HashAlgorithm::Sha256)
+            .is_none());
+        assert!(cache
+            .get(&stamp(8, 100, 1_700_000_000), HashAlgorithm::Sha256)
+            .is_none());
+        assert!(cache
+            .get(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Blake3)
+            .is_none());
+        assert_eq!(cache.stats().misses, 4);
+    }
+
+    #[test]
+    fn rehash_ignores_stored_hashes() {
+        let mut cache = HashCache::in_memory().with_rehash(true);
+        cache.insert(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256, "abc");
+
+        assert!(cache
+            .get(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256)
+            .is_none());
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn roundtrip_through_disk() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(HASH_CACHE_FILE_NAME);
+
+        let mut cache = HashCache::load(&path);
+        assert!(cache.is_empty());
+        cache.insert(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Blake3, "abc");
+        cache.save().unwrap();
+
+        let mut reloaded = HashCache::load(&path);
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded
+            .get(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Blake3)
+            .is_some());
+    }
+
+    #[test]
+    fn corrupt_file_yields_empty_cache() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(HASH_CACHE_FILE_NAME);
+        std::fs::write(&path, "not json").unwrap();
+
+        assert!(HashCache::load(&path).is_empty());
+    }
+
+    #[test]
+    fn prune_drops_unseen_entries() {
+        let mut cache = HashCache::in_memory();
+        cache.insert(&stamp(7, 100, 1_700_000_000), HashAlgorithm::Sha256, "abc");
+        cache.prune(Duration::zero());
+        assert!(cache.is_empty());
+    }
+}
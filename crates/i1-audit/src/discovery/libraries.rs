@@ -0,0 +1,230 @@
+//! Shared library discovery via `/proc/<pid>/maps`.
+//!
+//! Attackers rarely need to touch a binary: an `LD_PRELOAD`ed or replaced
+//! `.so` runs inside every process that maps it. Each library mapped by a
+//! running process is hashed once, however many processes map it, and
+//! returned as a [`BinaryInfo`] so it is scored like any binary.
+//!
+//! Reading another user's maps needs root, so an unprivileged audit only
+//! sees its own processes. Mappings whose file was deleted can't be hashed
+//! and are skipped. Missing `/proc` (non-Linux) yields no libraries.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{debug, warn};
+
+use super::binaries::collect_binary_info;
+use super::hash_cache::HashCache;
+use crate::error::Result;
+use crate::hash::HashAlgorithm;
+use crate::types::BinaryInfo;
+
+/// Process information root.
+const PROC: &str = "/proc";
+
+/// Directories no packaged library is loaded from: temp dirs, shared
+/// memory and home directories.
+pub const UNUSUAL_LIBRARY_DIRS: &[&str] = &[
+    "/tmp/",
+    "/var/tmp/",
+    "/dev/shm/",
+    "/run/user/",
+    "/home/",
+    "/root/",
+];
+
+/// Suffix Linux appends to a mapping once its file is gone.
+const DELETED_SUFFIX: &str = " (deleted)";
+
+/// Whether a library at `path` was loaded from a temp, shared memory or
+/// home directory.
+#[must_use]
+pub fn is_unusual_library_path(path: &str) -> bool {
+    UNUSUAL_LIBRARY_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Discover shared libraries mapped by running processes.
+///
+/// Hashes are reused from `cache` for files whose identity, size and
+/// mtime are unchanged.
+///
+/// # Errors
+///
+/// Returns `AuditError` only for unexpected failures; unreadable
+/// processes and libraries are skipped.
+pub async fn discover_shared_libraries(
+    algorithm: HashAlgorithm,
+    cache: &mut HashCache,
+) -> Result<Vec<BinaryInfo>> {
+    discover_shared_libraries_in(Path::new(PROC), algorithm, cache).await
+}
+
+/// Discover shared libraries from an explicit `/proc` root.
+///
+/// # Errors
+///
+/// Returns `AuditError` only for unexpected failures; unreadable
+/// processes and libraries are skipped.
+pub async fn discover_shared_libraries_in(
+    proc_root: &Path,
+    algorithm: HashAlgorithm,
+    cache: &mut HashCache,
+) -> Result<Vec<BinaryInfo>> {
+    let mut libraries = Vec::new();
+    for (path, mut process_names) in mapped_libraries(proc_root) {
+        match collect_binary_info(Path::new(&path), algorithm, cache, false).await {
+            Ok(mut info) => {
+                process_names.sort();
+                info.running = true;
+                info.process_names = process_names;
+                libraries.push(info);
+            }
+            Err(e) => warn!(path, error = %e, "skipping shared library"),
+        }
+    }
+    Ok(libraries)
+}
+
+/// Map each shared library path to the names of the processes mapping it.
+fn mapped_libraries(proc_root: &Path) -> BTreeMap<String, Vec<String>> {
+    let mut libraries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let Ok(entries) = std::fs::read_dir(proc_root) else {
+        debug!(path = %proc_root.display(), "process list unavailable, skipping libraries");
+        return libraries;
+    };
+
+    for entry in entries.filter_map(std::result::Result::ok) {
+        let file_name = entry.file_name();
+        if !file_name
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        // Kernel threads have empty maps; other users' need root.
+        let Ok(maps) = std::fs::read_to_string(entry.path().join("maps")) else {
+            continue;
+        };
+        let name = std::fs::read_to_string(entry.path().join("comm")).map_or_else(
+            |_| file_name.to_string_lossy().into_owned(),
+            |comm| comm.trim().to_string(),
+        );
+        for path in parse_maps(&maps) {
+            let names = libraries.entry(path.to_string()).or_default();
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    libraries
+}
+
+/// Shared object paths in the contents of a `/proc/<pid>/maps` file.
+///
+/// Each line is `address perms offset dev inode [path]`; libraries are
+/// mapped several times (text, data, ...) and may repeat.
+fn parse_maps(maps: &str) -> impl Iterator<Item = &str> {
+    maps.lines().filter_map(|line| {
+        let mut rest = line;
+        for _ in 0..5 {
+            rest = rest.trim_start().split_once(' ')?.1;
+        }
+        let path = rest.trim_start();
+        (path.starts_with('/') && !path.ends_with(DELETED_SUFFIX) && is_shared_object(path))
+            .then_some(path)
+    })
+}
+
+/// Whether `path` names a shared object (`libc.so.6`, `libfoo.so`).
+fn is_shared_object(path: &str) -> bool {
+    let path = Path::new(path);
+    path.extension().is_some_and(|ext| ext == "so")
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().contains(".so."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const MAPS: &str = "\
+55d0c0a00000-55d0c0a28000 r--p 00000000 08:01 1310      /usr/bin/bash
+7f2a4c000000-7f2a4c028000 r--p 00000000 08:01 2301      /usr/lib/x86_64-linux-gnu/libc.so.6
+7f2a4c028000-7f2a4c1bd000 r-xp 00028000 08:01 2301      /usr/lib/x86_64-linux-gnu/libc.so.6
+7f2a4c300000-7f2a4c302000 r-xp 00000000 08:01 4410      /tmp/.x/libhook.so
+7f2a4c400000-7f2a4c402000 r-xp 00000000 08:01 4411      /tmp/libgone.so (deleted)
+7f2a4c500000-7f2a4c521000 rw-p 00000000 00:00 0         [heap]
+7f2a4c600000-7f2a4c601000 r--s 00000000 08:01 5120      /var/cache/fontconfig/cache-4
+7ffd1c000000-7ffd1c021000 rw-p 00000000 00:00 0
+";
+
+    #[test]
+    fn parse_maps_keeps_shared_objects() {
+        let paths: Vec<&str> = parse_maps(MAPS).collect();
+        assert_eq!(
+            paths,
+            [
+                "/usr/lib/x86_64-linux-gnu/libc.so.6",
+                "/usr/lib/x86_64-linux-gnu/libc.so.6",
+                "/tmp/.x/libhook.so",
+            ]
+        );
+    }
+
+    #[test]
+    fn unusual_paths() {
+        assert!(is_unusual_library_path("/tmp/.x/libhook.so"));
+        assert!(is_unusual_library_path("/dev/shm/libx.so"));
+        assert!(is_unusual_library_path("/home/alice/.local/lib/libx.so.1"));
+        assert!(!is_unusual_library_path(
+            "/usr/lib/x86_64-linux-gnu/libc.so.6"
+        ));
+        assert!(!is_unusual_library_path("/opt/app/lib/libapp.so"));
+    }
+
+    #[tokio::test]
+    async fn discover_from_fixture() {
+        let dir = TempDir::new().unwrap();
+        let lib = dir.path().join("libhook.so");
+        std::fs::write(&lib, b"\x7fELF").unwrap();
+        let maps = format!(
+            "7f2a4c300000-7f2a4c302000 r-xp 00000000 08:01 4410 {}\n",
+            lib.display()
+        );
+
+        let proc_root = dir.path().join("proc");
+        for (pid, comm) in [("812", "sshd"), ("813", "sshd"), ("900", "cron")] {
+            let pid_dir = proc_root.join(pid);
+            std::fs::create_dir_all(&pid_dir).unwrap();
+            std::fs::write(pid_dir.join("maps"), &maps).unwrap();
+            std::fs::write(pid_dir.join("comm"), format!("{comm}\n")).unwrap();
+        }
+        // Not a process
+        std::fs::create_dir_all(proc_root.join("sys")).unwrap();
+
+        let libraries = discover_shared_libraries_in(
+            &proc_root,
+            HashAlgorithm::Sha256,
+            &mut HashCache::in_memory(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].path, lib.display().to_string());
+        assert!(libraries[0].running);
+        assert_eq!(libraries[0].process_names, ["cron", "sshd"]);
+
+        let missing = dir.path().join("nope");
+        let none = discover_shared_libraries_in(
+            &missing,
+            HashAlgorithm::Sha256,
+            &mut HashCache::in_memory(),
+        )
+        .await
+        .unwrap();
+        assert!(none.is_empty());
+    }
+}
@@ -0,0 +1,143 @@
+//! macOS-specific discovery helpers.
+//!
+//! macOS has no `/proc`, no `/etc/machine-id`, and keeps root certs in
+//! keychains rather than PEM bundles. These helpers shell out to the
+//! stock system tools (`security`, `codesign`, `ioreg`, `sysctl`) so no
+//! framework bindings are needed.
+
+use std::path::Path;
+use std::process::Command;
+use tracing::debug;
+
+use crate::error::{AuditError, Result};
+
+/// Keychains holding trusted roots: Apple's system roots and the
+/// admin-managed system keychain.
+pub const KEYCHAINS: &[&str] = &[
+    "/System/Library/Keychains/SystemRootCertificates.keychain",
+    "/Library/Keychains/System.keychain",
+];
+
+/// Directory scanned for application bundles.
+const APPLICATIONS_DIR: &str = "/Applications";
+
+/// Export every certificate in a keychain as concatenated PEM.
+///
+/// # Errors
+///
+/// Returns `AuditError::Process` if `security` cannot be run or fails.
+pub fn keychain_pems(keychain: &str) -> Result<Vec<u8>> {
+    let output = Command::new("security")
+        .args(["find-certificate", "-a", "-p", keychain])
+        .output()
+        .map_err(|e| AuditError::Process(format!("failed to run security: {e}")))?;
+
+    if !output.status.success() {
+        return Err(AuditError::Process(format!(
+            "security find-certificate failed for {keychain}"
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Executable directories inside installed application bundles
+/// (`/Applications/*.app/Contents/MacOS`).
+#[must_use]
+pub fn app_bundle_bin_paths() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(APPLICATIONS_DIR) else {
+        debug!("applications directory unreadable, skipping app bundles");
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "app"))
+        .map(|p| p.join("Contents").join("MacOS"))
+        .filter(|p| p.is_dir())
+        .map(|p| p.display().to_string())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Whether the file carries a valid code signature.
+///
+/// Equivalent to `codesign --verify --strict`; an unsigned or tampered
+/// binary fails verification.
+#[must_use]
+pub fn is_codesigned(path: &str) -> bool {
+    Command::new("codesign")
+        .args(["--verify", "--strict", path])
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+/// Hardware UUID (`IOPlatformUUID`), stable across reinstalls.
+#[must_use]
+pub fn platform_uuid() -> Option<String> {
+    let output = Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    parse_ioreg_uuid(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Logical CPU count from `sysctl hw.ncpu`.
+#[must_use]
+pub fn sysctl_cpu_count() -> Option<u32> {
+    let output = Command::new("sysctl").args(["-n", "hw.ncpu"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Extract `IOPlatformUUID` from `ioreg` output.
+///
+/// The relevant line looks like `"IOPlatformUUID" = "0A1B2C3D-..."`.
+fn parse_ioreg_uuid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|l| l.contains("\"IOPlatformUUID\""))
+        .and_then(|l| l.split('=').nth(1))
+        .map(|v| v.trim().trim_matches('"').to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Whether a path lives inside an application bundle.
+#[must_use]
+pub fn is_app_bundle_path(path: &str) -> bool {
+    Path::new(path)
+        .ancestors()
+        .any(|a| a.extension().is_some_and(|ext| ext == "app"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_uuid_from_ioreg() {
+        let output = r#"+-o J314sAP  <class IOPlatformExpertDevice>
+    {
+      "IOPlatformSerialNumber" = "C02XXXXXXX"
+      "IOPlatformUUID" = "0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9"
+    }"#;
+        assert_eq!(
+            parse_ioreg_uuid(output).as_deref(),
+            Some("0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9")
+        );
+        assert!(parse_ioreg_uuid("nothing here").is_none());
+    }
+
+    #[test]
+    fn app_bundle_detection() {
+        assert!(is_app_bundle_path("/Applications/Safari.app/Contents/MacOS/Safari"));
+        assert!(!is_app_bundle_path("/usr/local/bin/git"));
+    }
+
+    #[test]
+    fn system_keychains_export_pem() {
+        let pems = keychain_pems(KEYCHAINS[0]).unwrap();
+        assert!(String::from_utf8_lossy(&pems).contains("BEGIN CERTIFICATE"));
+    }
+}
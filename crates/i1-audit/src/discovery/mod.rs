@@ -1,18 +1,32 @@
-//! System discovery — binaries, processes, and root certificates.
+//! System discovery — binaries, shared libraries, processes, root certificates,
+//! and kernel modules.
 
 pub mod binaries;
 pub mod certs;
+pub mod hash_cache;
+pub mod libraries;
+pub mod modules;
 
 // procfs-based process discovery (Linux only)
 #[cfg(target_os = "linux")]
 pub mod processes;
 
+// keychain, code signing, and hardware UUID helpers
+#[cfg(target_os = "macos")]
+pub mod macos;
+
 // Cross-platform fallback using std/command
 #[cfg(not(target_os = "linux"))]
 pub mod processes_fallback;
 
-pub use binaries::{correlate_processes, discover_binaries, DEFAULT_BIN_PATHS};
+pub use binaries::{
+    correlate_processes, default_bin_paths, discover_binaries, discover_binaries_cached,
+    DEFAULT_BIN_PATHS,
+};
 pub use certs::discover_root_certs;
+pub use hash_cache::HashCache;
+pub use libraries::{discover_shared_libraries, is_unusual_library_path};
+pub use modules::discover_kernel_modules;
 
 #[cfg(target_os = "linux")]
 pub use processes::{discover_processes, get_cpu_count, get_system_uptime};
@@ -0,0 +1,230 @@
+//! Loaded kernel module discovery via `/proc/modules` and `/sys/module`.
+//!
+//! Each loaded module is mapped to its `.ko` file under
+//! `/lib/modules/$(uname -r)` and hashed. Missing `/proc/modules`,
+//! a restricted `/sys`, or an absent module directory (containers) all
+//! degrade gracefully rather than failing the audit.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::error::Result;
+use crate::hash::sha256_file;
+use crate::types::{KernelModule, ModuleOrigin, ModuleTaint};
+
+/// Loaded module list.
+const PROC_MODULES: &str = "/proc/modules";
+
+/// Per-module sysfs directory.
+const SYS_MODULE: &str = "/sys/module";
+
+/// Root of per-kernel module trees.
+const LIB_MODULES: &str = "/lib/modules";
+
+/// Running kernel release (same as `uname -r`).
+const OSRELEASE: &str = "/proc/sys/kernel/osrelease";
+
+/// Discover loaded kernel modules on the running system.
+///
+/// Returns an empty list where `/proc/modules` is unavailable
+/// (non-Linux, or a kernel without module support).
+///
+/// # Errors
+///
+/// Returns `AuditError` only for unexpected failures; missing sources are not errors.
+pub async fn discover_kernel_modules() -> Result<Vec<KernelModule>> {
+    let module_dir = std::fs::read_to_string(OSRELEASE)
+        .ok()
+        .map(|release| Path::new(LIB_MODULES).join(release.trim()));
+
+    discover_kernel_modules_in(
+        Path::new(PROC_MODULES),
+        Path::new(SYS_MODULE),
+        module_dir.as_deref(),
+    )
+    .await
+}
+
+/// Discover kernel modules from explicit source paths.
+///
+/// # Errors
+///
+/// Returns `AuditError` only for unexpected failures; missing sources are not errors.
+pub async fn discover_kernel_modules_in(
+    proc_modules: &Path,
+    sys_module: &Path,
+    module_dir: Option<&Path>,
+) -> Result<Vec<KernelModule>> {
+    let Ok(content) = tokio::fs::read_to_string(proc_modules).await else {
+        debug!(path = %proc_modules.display(), "module list unavailable, skipping");
+        return Ok(Vec::new());
+    };
+
+    let ko_index = module_dir
+        .filter(|d| d.is_dir())
+        .map(index_module_tree);
+    if ko_index.is_none() {
+        debug!("kernel module directory unavailable, module origins unknown");
+    }
+
+    let mut modules = Vec::new();
+    for line in content.lines() {
+        let Some(mut module) = parse_proc_modules_line(line) else {
+            debug!(line, "skipping unparsable module line");
+            continue;
+        };
+
+        // sysfs taint is authoritative when readable; merge with /proc flags
+        if let Ok(flags) = std::fs::read_to_string(sys_module.join(&module.name).join("taint")) {
+            module.taint = module.taint.merge(ModuleTaint::parse(flags.trim()));
+        }
+
+        match &ko_index {
+            Some(index) => match index.get(&normalize_name(&module.name)) {
+                Some(path) => {
+                    module.origin = ModuleOrigin::ModuleTree;
+                    module.sha256 = sha256_file(path).await.ok();
+                    module.ko_path = Some(path.display().to_string());
+                }
+                None => module.origin = ModuleOrigin::NotInModuleTree,
+            },
+            None => module.origin = ModuleOrigin::Unknown,
+        }
+
+        modules.push(module);
+    }
+
+    Ok(modules)
+}
+
+/// Parse one `/proc/modules` line.
+///
+/// Format: `name size refcount used_by state address [(taint)]`, e.g.
+/// `nvidia 56623104 1 nvidia_modeset, Live 0x0000000000000000 (POE)`
+fn parse_proc_modules_line(line: &str) -> Option<KernelModule> {
+    let mut parts = line.split_whitespace();
+    let name = parts.next()?.to_string();
+    let size = parts.next()?.parse().ok()?;
+    let ref_count = parts.next()?.parse().ok()?;
+    let used_by = parts
+        .next()?
+        .split(',')
+        .filter(|s| !s.is_empty() && *s != "-")
+        .map(String::from)
+        .collect();
+    let state = parts.next()?.to_string();
+    let _address = parts.next();
+    let taint = parts.next().map(ModuleTaint::parse).unwrap_or_default();
+
+    Some(KernelModule {
+        name,
+        size,
+        ref_count,
+        used_by,
+        state,
+        taint,
+        ko_path: None,
+        sha256: None,
+        origin: ModuleOrigin::Unknown,
+        trust: None,
+    })
+}
+
+/// Map normalized module names to their `.ko` files.
+fn index_module_tree(dir: &Path) -> HashMap<String, PathBuf> {
+    WalkDir::new(dir)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let file_name = e.file_name().to_str()?;
+            let (stem, _) = file_name.split_once(".ko")?;
+            Some((normalize_name(stem), e.into_path()))
+        })
+        .collect()
+}
+
+/// The kernel treats `-` and `_` in module names as equivalent.
+fn normalize_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parse_module_line() {
+        let m = parse_proc_modules_line(
+            "nvidia_uvm 1327104 2 - Live 0x0000000000000000 (POE)",
+        )
+        .unwrap();
+        assert_eq!(m.name, "nvidia_uvm");
+        assert_eq!(m.size, 1_327_104);
+        assert_eq!(m.ref_count, 2);
+        assert!(m.used_by.is_empty());
+        assert!(m.taint.out_of_tree && m.taint.unsigned);
+
+        let m = parse_proc_modules_line("ext4 1015808 1 - Live 0x0000000000000000").unwrap();
+        assert!(!m.taint.any());
+
+        let m = parse_proc_modules_line("snd 110592 3 snd_hda_codec,snd_pcm, Live 0x0").unwrap();
+        assert_eq!(m.used_by, vec!["snd_hda_codec", "snd_pcm"]);
+
+        assert!(parse_proc_modules_line("garbage").is_none());
+    }
+
+    #[tokio::test]
+    async fn discover_from_fixture() {
+        let dir = TempDir::new().unwrap();
+        let proc_modules = dir.path().join("modules");
+        std::fs::write(
+            &proc_modules,
+            "ext4 1015808 1 - Live 0x0\nrootkit 16384 0 - Live 0x0 (OE)\n",
+        )
+        .unwrap();
+
+        let sys = dir.path().join("sys");
+        std::fs::create_dir_all(sys.join("ext4")).unwrap();
+        std::fs::write(sys.join("ext4").join("taint"), "\n").unwrap();
+
+        let tree = dir.path().join("lib/modules/6.1.0");
+        std::fs::create_dir_all(tree.join("kernel/fs/ext4")).unwrap();
+        std::fs::write(tree.join("kernel/fs/ext4/ext4.ko.zst"), b"ko").unwrap();
+
+        let modules = discover_kernel_modules_in(&proc_modules, &sys, Some(&tree))
+            .await
+            .unwrap();
+        assert_eq!(modules.len(), 2);
+
+        let ext4 = &modules[0];
+        assert_eq!(ext4.origin, ModuleOrigin::ModuleTree);
+        assert!(ext4.sha256.is_some());
+
+        let rootkit = &modules[1];
+        assert_eq!(rootkit.origin, ModuleOrigin::NotInModuleTree);
+        assert!(rootkit.taint.unsigned);
+    }
+
+    #[tokio::test]
+    async fn missing_sources_degrade_gracefully() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("nope");
+        let modules = discover_kernel_modules_in(&missing, &missing, None)
+            .await
+            .unwrap();
+        assert!(modules.is_empty());
+
+        // Module list present but no module tree (container case)
+        let proc_modules = dir.path().join("modules");
+        std::fs::write(&proc_modules, "overlay 151552 0 - Live 0x0\n").unwrap();
+        let modules = discover_kernel_modules_in(&proc_modules, &missing, Some(&missing))
+            .await
+            .unwrap();
+        assert_eq!(modules[0].origin, ModuleOrigin::Unknown);
+    }
+}
@@ -61,7 +61,9 @@ fn collect_process_info(
         .unwrap_or(u32::MAX);
 
     // Exe path (may fail for kernel threads or permission issues)
-    let exe_path = proc.exe().ok().map(|p| p.display().to_string());
+    let exe = proc.exe().ok();
+    let exe_missing = exe.as_deref().is_some_and(|exe| !exe_on_disk(pid, exe));
+    let exe_path = exe.map(|p| p.display().to_string());
 
     // Command line
     let cmdline = proc.cmdline().unwrap_or_default();
@@ -95,12 +97,22 @@ fn collect_process_info(
         pid,
         name,
         exe_path,
+        exe_missing,
         cmdline,
         uid,
         usage,
     })
 }
 
+/// Whether the process's executable still exists, looked up through
+/// `/proc/<pid>/root` so paths inside containers resolve.
+fn exe_on_disk(pid: i32, exe: &std::path::Path) -> bool {
+    let relative = exe.strip_prefix("/").unwrap_or(exe);
+    std::path::Path::new(&format!("/proc/{pid}/root"))
+        .join(relative)
+        .exists()
+}
+
 /// Get system uptime in seconds.
 ///
 /// # Errors
@@ -86,6 +86,7 @@ fn parse_ps_line(
         pid,
         name,
         exe_path: None, // not easily available from ps
+        exe_missing: false,
         cmdline: Vec::new(),
         uid,
         usage,
@@ -175,6 +176,11 @@ pub fn get_system_uptime() -> Result<u64> {
 #[must_use]
 #[allow(clippy::cast_possible_truncation)]
 pub fn get_cpu_count() -> u32 {
+    #[cfg(target_os = "macos")]
+    if let Some(count) = super::macos::sysctl_cpu_count() {
+        return count;
+    }
+
     std::thread::available_parallelism()
         .map(|n| n.get() as u32)
         .unwrap_or(1)
@@ -5,6 +5,11 @@
 //!
 //! DNS label = first 12 hex chars of SHA-256 (48 bits).
 //! Full hash is stored inside the TXT record value.
+//!
+//! BLAKE3 digests live in their own namespace one label down
+//! (`<prefix>.b3.bin.i1.is.`), so they never mix with SHA-256 reports.
+
+use crate::hash::HashAlgorithm;
 
 /// Zone suffix for binary hash lookups.
 pub const BIN_ZONE: &str = "bin.i1.is.";
@@ -15,22 +20,36 @@ pub const CA_ZONE: &str = "ca.i1.is.";
 /// Length of the hash prefix used as DNS label (hex chars).
 const HASH_PREFIX_LEN: usize = 12;
 
+/// Label marking the BLAKE3 namespace beneath a consensus zone.
+pub const BLAKE3_LABEL: &str = "b3";
+
+/// Labels identifying a hash within a consensus zone (without the zone).
+///
+/// Example: `"a3f2b8c91d4e..."` -> `"a3f2b8c91d4e"` for SHA-256,
+/// `"a3f2b8c91d4e.b3"` for BLAKE3.
+#[must_use]
+pub fn hash_labels(hash: &str, algorithm: HashAlgorithm) -> String {
+    let prefix = &hash[..HASH_PREFIX_LEN.min(hash.len())];
+    match algorithm {
+        HashAlgorithm::Sha256 => prefix.to_string(),
+        HashAlgorithm::Blake3 => format!("{prefix}.{BLAKE3_LABEL}"),
+    }
+}
+
 /// Build a DNS query name for a binary hash.
 ///
-/// Example: `sha256 = "a3f2b8c91d4e..."` -> `"a3f2b8c91d4e.bin.i1.is."`
+/// Example: `hash = "a3f2b8c91d4e..."` -> `"a3f2b8c91d4e.bin.i1.is."`
 #[must_use]
-pub fn binary_dns_name(sha256: &str) -> String {
-    let prefix = &sha256[..HASH_PREFIX_LEN.min(sha256.len())];
-    format!("{prefix}.{BIN_ZONE}")
+pub fn binary_dns_name(hash: &str, algorithm: HashAlgorithm) -> String {
+    format!("{}.{BIN_ZONE}", hash_labels(hash, algorithm))
 }
 
 /// Build a DNS query name for a certificate fingerprint.
 ///
 /// Example: `fingerprint = "d4e5f6a7b8c9..."` -> `"d4e5f6a7b8c9.ca.i1.is."`
 #[must_use]
-pub fn cert_dns_name(fingerprint: &str) -> String {
-    let prefix = &fingerprint[..HASH_PREFIX_LEN.min(fingerprint.len())];
-    format!("{prefix}.{CA_ZONE}")
+pub fn cert_dns_name(fingerprint: &str, algorithm: HashAlgorithm) -> String {
+    format!("{}.{CA_ZONE}", hash_labels(fingerprint, algorithm))
 }
 
 /// Extract the hash prefix from a DNS name.
@@ -45,16 +64,28 @@ mod tests {
 
     #[test]
     fn binary_name_format() {
-        let name = binary_dns_name("a3f2b8c91d4e567890abcdef");
+        let name = binary_dns_name("a3f2b8c91d4e567890abcdef", HashAlgorithm::Sha256);
         assert_eq!(name, "a3f2b8c91d4e.bin.i1.is.");
     }
 
     #[test]
     fn cert_name_format() {
-        let name = cert_dns_name("d4e5f6a7b8c9012345abcdef");
+        let name = cert_dns_name("d4e5f6a7b8c9012345abcdef", HashAlgorithm::Sha256);
         assert_eq!(name, "d4e5f6a7b8c9.ca.i1.is.");
     }
 
+    #[test]
+    fn blake3_names_use_own_namespace() {
+        assert_eq!(
+            binary_dns_name("a3f2b8c91d4e567890abcdef", HashAlgorithm::Blake3),
+            "a3f2b8c91d4e.b3.bin.i1.is."
+        );
+        assert_eq!(
+            cert_dns_name("d4e5f6a7b8c9012345abcdef", HashAlgorithm::Blake3),
+            "d4e5f6a7b8c9.b3.ca.i1.is."
+        );
+    }
+
     #[test]
     fn extract_prefix_works() {
         assert_eq!(
@@ -3,5 +3,8 @@
 pub mod dns_names;
 pub mod txt_audit;
 
-pub use dns_names::{binary_dns_name, cert_dns_name, BIN_ZONE, CA_ZONE};
-pub use txt_audit::{encode_binary_txt, encode_cert_txt};
+pub use dns_names::{binary_dns_name, cert_dns_name, hash_labels, BIN_ZONE, BLAKE3_LABEL, CA_ZONE};
+pub use txt_audit::{
+    decode_attestation_txt, encode_attestation_txt, encode_binary_txt, encode_cert_txt,
+    ATTESTATION_PREFIX,
+};
@@ -3,19 +3,32 @@
 //! Small payloads use plain `k=v;k=v` pairs.
 //! When the payload exceeds the 255-byte TXT record limit,
 //! overflow fields are CBOR-encoded and base64-appended.
+//!
+//! Alongside the summary record, a hash's record set may carry one
+//! attestation record per reporting node (`att=...`), used to weight
+//! consensus by node diversity rather than raw count.
+//!
+//! Every record ends with the wire schema version (`v=`, see
+//! [`i1_core::wire`]); decoders refuse records from another version.
 
 use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use chrono::{DateTime, TimeZone, Utc};
+use i1_core::wire;
 
 use crate::error::{AuditError, Result};
+use crate::types::Attestation;
 use crate::types::BinaryInfo;
 use crate::types::RootCertInfo;
 
+/// Leading key that marks a TXT string as an attestation record.
+pub const ATTESTATION_PREFIX: &str = "att=";
+
 /// Maximum length for a single DNS TXT string.
 const TXT_MAX: usize = 255;
 
 /// Encode a binary's audit data as a TXT record value.
 ///
-/// Format: `"hash=<full>;name=<basename>;size=<bytes>;trust=<0-100>;nodes=<count>"`
+/// Format: `"hash=<full>;name=<basename>;size=<bytes>;trust=<0-100>;nodes=<count>;v=<schema>"`
 ///
 /// If the data overflows 255 bytes, extra fields go into CBOR+base64 overflow.
 ///
@@ -35,9 +48,10 @@ pub fn encode_binary_txt(binary: &BinaryInfo, node_count: u32) -> Result<String>
         .as_ref()
         .map_or(0, |s| (s.total * 100.0) as u32);
 
+    let version = wire::version_field();
     let core = format!(
-        "hash={};name={};size={};trust={};nodes={}",
-        binary.sha256, basename, binary.size, trust_pct, node_count
+        "hash={};name={};size={};trust={};nodes={};{version}",
+        binary.hash, basename, binary.size, trust_pct, node_count
     );
 
     if core.len() <= TXT_MAX {
@@ -45,14 +59,14 @@ pub fn encode_binary_txt(binary: &BinaryInfo, node_count: u32) -> Result<String>
     }
 
     // Overflow: put full hash in CBOR
-    let short_hash = &binary.sha256[..12];
+    let short_hash = &binary.hash[..12];
     let kv = format!(
-        "h={};name={};size={};trust={};nodes={}",
+        "h={};name={};size={};trust={};nodes={};{version}",
         short_hash, basename, binary.size, trust_pct, node_count
     );
 
     let mut cbor_buf = Vec::new();
-    ciborium::into_writer(&binary.sha256, &mut cbor_buf)
+    ciborium::into_writer(&binary.hash, &mut cbor_buf)
         .map_err(|e| AuditError::Encoding(e.to_string()))?;
     let overflow = B64.encode(&cbor_buf);
 
@@ -66,14 +80,70 @@ pub fn encode_cert_txt(cert: &RootCertInfo, node_count: u32) -> String {
     let short_issuer = extract_cn(&cert.issuer).unwrap_or(&cert.issuer);
 
     format!(
-        "fp={};issuer={};exp={};nodes={}",
+        "fp={};issuer={};exp={};nodes={};{}",
         cert.fingerprint,
         short_issuer,
         cert.not_after.format("%Y-%m-%d"),
-        node_count
+        node_count,
+        wire::version_field()
+    )
+}
+
+/// Encode a node attestation as a TXT record value.
+///
+/// Format: `"att=<node>;net=<bucket>;first=<epoch>;dts=<epoch>;v=<schema>"`
+/// (`net` omitted when the node's network is unknown).
+#[must_use]
+pub fn encode_attestation_txt(attestation: &Attestation) -> String {
+    let net = attestation
+        .network
+        .as_ref()
+        .map(|n| format!(";net={n}"))
+        .unwrap_or_default();
+    format!(
+        "{ATTESTATION_PREFIX}{}{net};first={};dts={};{}",
+        attestation.node,
+        attestation.first_seen.timestamp(),
+        attestation.digest_at.timestamp(),
+        wire::version_field()
     )
 }
 
+/// Decode an attestation TXT record value.
+///
+/// Returns `None` for non-attestation records, malformed timestamps, or
+/// records from another wire schema version.
+#[must_use]
+pub fn decode_attestation_txt(txt: &str) -> Option<Attestation> {
+    wire::check(txt).ok()?;
+    let mut node = None;
+    let mut network = None;
+    let mut first_seen = None;
+    let mut digest_at = None;
+
+    for part in txt.split(';') {
+        match part.trim().split_once('=')? {
+            ("att", v) => node = Some(v.to_string()),
+            ("net", v) => network = Some(v.to_string()),
+            ("first", v) => first_seen = parse_epoch(v),
+            ("dts", v) => digest_at = parse_epoch(v),
+            _ => {}
+        }
+    }
+
+    Some(Attestation {
+        node: node.filter(|n| !n.is_empty())?,
+        network,
+        first_seen: first_seen?,
+        digest_at: digest_at?,
+    })
+}
+
+/// Parse Unix seconds into a UTC timestamp.
+fn parse_epoch(value: &str) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(value.parse().ok()?, 0).single()
+}
+
 /// Extract the CN= value from a distinguished name string.
 fn extract_cn(dn: &str) -> Option<&str> {
     for part in dn.split(',') {
@@ -88,6 +158,7 @@ fn extract_cn(dn: &str) -> Option<&str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hash::HashAlgorithm;
     use crate::types::FileIdentity;
     use chrono::Utc;
 
@@ -95,7 +166,8 @@ mod tests {
     fn encode_binary_basic() {
         let bin = BinaryInfo {
             path: "/usr/bin/sshd".into(),
-            sha256: "a".repeat(64),
+            hash: "a".repeat(64),
+            hash_algorithm: HashAlgorithm::Sha256,
             create_date: Utc::now(),
             modify_date: Utc::now(),
             identity: FileIdentity {
@@ -114,6 +186,40 @@ mod tests {
         assert!(txt.contains("size=1047552"));
     }
 
+    #[test]
+    fn attestation_roundtrip() {
+        let now = Utc.timestamp_opt(Utc::now().timestamp(), 0).unwrap();
+        let att = Attestation {
+            node: "a1b2c3d4e5f6".into(),
+            network: Some("203.0".into()),
+            first_seen: now - chrono::Duration::days(90),
+            digest_at: now - chrono::Duration::days(2),
+        };
+        let txt = encode_attestation_txt(&att);
+        assert!(txt.starts_with(ATTESTATION_PREFIX));
+        assert!(txt.len() <= TXT_MAX);
+        assert_eq!(decode_attestation_txt(&txt), Some(att.clone()));
+
+        let anonymous = Attestation {
+            network: None,
+            ..att
+        };
+        let txt = encode_attestation_txt(&anonymous);
+        assert!(!txt.contains("net="));
+        assert_eq!(decode_attestation_txt(&txt), Some(anonymous.clone()));
+
+        // Unversioned records predate the field; other versions are refused
+        let legacy = txt.replace(";v=1", "");
+        assert_eq!(decode_attestation_txt(&legacy), Some(anonymous));
+        assert_eq!(decode_attestation_txt(&txt.replace("v=1", "v=2")), None);
+    }
+
+    #[test]
+    fn decode_rejects_non_attestations() {
+        assert!(decode_attestation_txt("hash=abc;nodes=3").is_none());
+        assert!(decode_attestation_txt("att=abc;first=nope;dts=1").is_none());
+    }
+
     #[test]
     fn extract_cn_works() {
         assert_eq!(
@@ -19,6 +19,18 @@ pub enum AuditError {
     #[error("hashing failed for {path}: {reason}")]
     Hash { path: String, reason: String },
 
+    /// Unrecognised hash algorithm name
+    #[error("unknown hash algorithm '{0}' (expected sha256 or blake3)")]
+    UnknownHashAlgorithm(String),
+
+    /// Unrecognised risk grade
+    #[error("unknown grade '{0}' (expected A, B, C, D or F)")]
+    UnknownGrade(String),
+
+    /// Baseline name unusable as a file name
+    #[error("invalid baseline name '{0}' (use letters, digits, '.', '_' and '-')")]
+    BaselineName(String),
+
     /// Process discovery error
     #[error("process discovery error: {0}")]
     Process(String),
@@ -39,6 +51,14 @@ pub enum AuditError {
     #[error("encoding error: {0}")]
     Encoding(String),
 
+    /// Trust digest signing or signature verification failed
+    #[error("signature error: {0}")]
+    Signature(String),
+
+    /// Record written with another wire schema version
+    #[error(transparent)]
+    SchemaMismatch(#[from] i1_core::wire::SchemaMismatch),
+
     /// Serialization error
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
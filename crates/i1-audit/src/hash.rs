@@ -1,7 +1,15 @@
-//! Streaming SHA-256 hashing via `ring::digest`.
+//! Streaming file and byte hashing.
+//!
+//! SHA-256 (via `ring::digest`) is the default and is what existing
+//! `bin.i1.is` / `ca.i1.is` data is keyed by. BLAKE3 is several times
+//! faster on large binaries and is published under its own namespace
+//! (see [`crate::encoding::dns_names`]).
 
 use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::Path;
+use std::str::FromStr;
 use tokio::io::AsyncReadExt;
 
 use crate::error::{AuditError, Result};
@@ -9,20 +17,95 @@ use crate::error::{AuditError, Result};
 /// Buffer size for streaming file reads (64 KiB).
 const BUF_SIZE: usize = 64 * 1024;
 
-/// Compute SHA-256 of a file, streaming to avoid loading it all into memory.
+/// Digest used for binary hashes and certificate fingerprints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// SHA-256, compatible with existing consensus data
+    #[default]
+    Sha256,
+    /// BLAKE3 (256-bit output)
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Lowercase name, as used in config files and on the command line.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            Self::Sha256 => Hasher::Sha256(Context::new(&SHA256)),
+            Self::Blake3 => Hasher::Blake3(blake3::Hasher::new()),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = AuditError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(Self::Sha256),
+            "blake3" | "b3" => Ok(Self::Blake3),
+            _ => Err(AuditError::UnknownHashAlgorithm(s.to_string())),
+        }
+    }
+}
+
+/// Incremental state for either algorithm.
+///
+/// Lives on the stack for the duration of one hash, so the size
+/// difference between variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
+enum Hasher {
+    Sha256(Context),
+    Blake3(blake3::Hasher),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(context) => context.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finish_hex(self) -> String {
+        match self {
+            Self::Sha256(context) => hex::encode(context.finish().as_ref()),
+            Self::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Hash a file with `algorithm`, streaming to avoid loading it all into memory.
 ///
 /// Returns lowercase hex-encoded digest.
 ///
 /// # Errors
 ///
 /// Returns `AuditError::Io` if the file cannot be opened or read.
-pub async fn sha256_file(path: &Path) -> Result<String> {
+pub async fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
     let path_str = path.display().to_string();
     let mut file = tokio::fs::File::open(path)
         .await
         .map_err(|e| AuditError::io(&path_str, e))?;
 
-    let mut context = Context::new(&SHA256);
+    let mut hasher = algorithm.hasher();
     let mut buf = vec![0u8; BUF_SIZE];
 
     loop {
@@ -33,11 +116,29 @@ pub async fn sha256_file(path: &Path) -> Result<String> {
         if n == 0 {
             break;
         }
-        context.update(&buf[..n]);
+        hasher.update(&buf[..n]);
     }
 
-    let digest = context.finish();
-    Ok(hex::encode(digest.as_ref()))
+    Ok(hasher.finish_hex())
+}
+
+/// Hash raw bytes (for certificate DER data) with `algorithm`.
+#[must_use]
+pub fn hash_bytes(data: &[u8], algorithm: HashAlgorithm) -> String {
+    let mut hasher = algorithm.hasher();
+    hasher.update(data);
+    hasher.finish_hex()
+}
+
+/// Compute SHA-256 of a file, streaming to avoid loading it all into memory.
+///
+/// Returns lowercase hex-encoded digest.
+///
+/// # Errors
+///
+/// Returns `AuditError::Io` if the file cannot be opened or read.
+pub async fn sha256_file(path: &Path) -> Result<String> {
+    hash_file(path, HashAlgorithm::Sha256).await
 }
 
 /// Compute SHA-256 of raw bytes (for certificate DER data).
@@ -84,4 +185,40 @@ mod tests {
             "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
         );
     }
+
+    #[tokio::test]
+    async fn test_blake3_file() {
+        let mut tmp = NamedTempFile::new().unwrap();
+        write!(tmp, "hello world").unwrap();
+        tmp.flush().unwrap();
+
+        let hash = hash_file(tmp.path(), HashAlgorithm::Blake3).await.unwrap();
+        assert_eq!(
+            hash,
+            "d74981efa70a0c880b8d8c1985d075dbcbf679b99a5f9914e5aaf96b831a9e24"
+        );
+        assert_eq!(hash, hash_bytes(b"hello world", HashAlgorithm::Blake3));
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        assert_eq!(HashAlgorithm::default(), HashAlgorithm::Sha256);
+        assert_eq!(
+            "BLAKE3".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Blake3
+        );
+        assert_eq!(
+            "sha-256".parse::<HashAlgorithm>().unwrap(),
+            HashAlgorithm::Sha256
+        );
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        assert_eq!(
+            serde_json::to_string(&HashAlgorithm::Blake3).unwrap(),
+            "\"blake3\""
+        );
+        assert_eq!(
+            hash_bytes(b"hello world", HashAlgorithm::Sha256),
+            sha256_bytes(b"hello world")
+        );
+    }
 }
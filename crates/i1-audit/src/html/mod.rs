@@ -0,0 +1,626 @@
+//! Self-contained HTML audit report.
+//!
+//! [`HtmlReport`] renders a snapshot with its findings and risk grade into
+//! a single HTML file for attaching to a ticket: CSS is embedded, a few
+//! lines of script make the tables sortable, and the verification QR code
+//! is inline SVG, so opening it fetches nothing. Each binary's trust score
+//! expands into its factors. Given the previous snapshot, the binaries,
+//! root certs and kernel modules that appeared, disappeared or changed
+//! since then are listed as well.
+//!
+//! The page skeleton is `template.html`; its `{{name}}` slots are filled
+//! with fragments rendered here, all text HTML-escaped.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use qrcode::render::svg;
+
+use crate::consensus::Severity;
+use crate::error::Result;
+use crate::qr::QrPayload;
+use crate::report::{AuditReport, Category};
+use crate::risk::RiskReport;
+use crate::types::{AuditSnapshot, BinaryInfo, ProcessInfo, RootCertInfo, TrustScore};
+
+/// Page skeleton with `{{name}}` slots.
+const TEMPLATE: &str = include_str!("template.html");
+
+/// Hex digits of a hash or fingerprint shown before the rest is elided.
+const SHORT_HASH: usize = 16;
+
+/// Renders an audit as one HTML page.
+#[derive(Debug, Clone, Copy)]
+pub struct HtmlReport<'a> {
+    snapshot: &'a AuditSnapshot,
+    report: &'a AuditReport,
+    risk: &'a RiskReport,
+    previous: Option<&'a AuditSnapshot>,
+    qr: Option<&'a QrPayload>,
+}
+
+impl<'a> HtmlReport<'a> {
+    /// Report on `snapshot`, whose findings and grade are `report` and `risk`.
+    #[must_use]
+    pub const fn new(
+        snapshot: &'a AuditSnapshot,
+        report: &'a AuditReport,
+        risk: &'a RiskReport,
+    ) -> Self {
+        Self {
+            snapshot,
+            report,
+            risk,
+            previous: None,
+            qr: None,
+        }
+    }
+
+    /// Also list what changed since `previous`.
+    #[must_use]
+    pub const fn with_previous(mut self, previous: &'a AuditSnapshot) -> Self {
+        self.previous = Some(previous);
+        self
+    }
+
+    /// Embed the verification QR code for `qr`.
+    #[must_use]
+    pub const fn with_qr(mut self, qr: &'a QrPayload) -> Self {
+        self.qr = Some(qr);
+        self
+    }
+
+    /// Render the page.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError::Encoding` if the QR code can't be generated.
+    pub fn render(&self) -> Result<String> {
+        let snapshot = self.snapshot;
+        let summary = &snapshot.summary;
+        let totals = format!(
+            "{} binaries ({} running), {} processes, {} root certs, {} kernel modules",
+            summary.total_binaries,
+            summary.running_binaries,
+            summary.total_processes,
+            summary.total_root_certs,
+            snapshot.kernel_modules.len()
+        );
+        let changes = self
+            .previous
+            .map(|previous| render_changes(&SnapshotDiff::between(previous, snapshot)))
+            .unwrap_or_default();
+        let verification = self.qr.map(render_verification).transpose()?;
+
+        let slots = BTreeMap::from([
+            ("node", escape(&snapshot.node_id)),
+            ("collected_at", timestamp(snapshot.collected_at)),
+            ("totals", escape(&totals)),
+            ("grade", render_grade(self.risk)),
+            ("findings", render_findings(self.report)),
+            ("changes", changes),
+            ("binary_count", snapshot.binaries.len().to_string()),
+            ("binaries", render_binaries(&snapshot.binaries)),
+            ("process_count", snapshot.processes.len().to_string()),
+            ("processes", render_processes(&snapshot.processes)),
+            ("cert_count", snapshot.root_certs.len().to_string()),
+            ("certs", render_certs(&snapshot.root_certs)),
+            ("verification", verification.unwrap_or_default()),
+        ]);
+        Ok(fill(TEMPLATE, &slots))
+    }
+}
+
+/// What changed between two snapshots of the same machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// Paths of binaries that are new
+    pub added_binaries: Vec<String>,
+    /// Paths of binaries that are gone
+    pub removed_binaries: Vec<String>,
+    /// Paths of binaries whose contents changed
+    pub changed_binaries: Vec<String>,
+    /// Subjects of root certs that are new
+    pub added_certs: Vec<String>,
+    /// Subjects of root certs that are gone
+    pub removed_certs: Vec<String>,
+    /// Names of kernel modules loaded since
+    pub added_modules: Vec<String>,
+    /// Names of kernel modules unloaded since
+    pub removed_modules: Vec<String>,
+}
+
+impl SnapshotDiff {
+    /// Compare `current` against `previous`.
+    ///
+    /// Binaries match by path and root certs by fingerprint. A binary
+    /// hashed with a different algorithm than last time isn't reported as
+    /// changed, since its digests can't be compared.
+    #[must_use]
+    pub fn between(previous: &AuditSnapshot, current: &AuditSnapshot) -> Self {
+        let old_bins: BTreeMap<&str, &BinaryInfo> = previous
+            .binaries
+            .iter()
+            .map(|b| (b.path.as_str(), b))
+            .collect();
+        let new_bins: BTreeMap<&str, &BinaryInfo> = current
+            .binaries
+            .iter()
+            .map(|b| (b.path.as_str(), b))
+            .collect();
+        let changed_binaries = new_bins
+            .iter()
+            .filter_map(|(path, new)| Some((path, new, old_bins.get(path)?)))
+            .filter(|(_, new, old)| {
+                new.hash_algorithm == old.hash_algorithm && new.hash != old.hash
+            })
+            .map(|(path, _, _)| (*path).to_string())
+            .collect();
+
+        let old_certs: BTreeMap<&str, &str> = previous
+            .root_certs
+            .iter()
+            .map(|c| (c.fingerprint.as_str(), c.subject.as_str()))
+            .collect();
+        let new_certs: BTreeMap<&str, &str> = current
+            .root_certs
+            .iter()
+            .map(|c| (c.fingerprint.as_str(), c.subject.as_str()))
+            .collect();
+
+        let old_modules: BTreeSet<&str> = previous
+            .kernel_modules
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        let new_modules: BTreeSet<&str> = current
+            .kernel_modules
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+
+        Self {
+            added_binaries: only_in(&new_bins, &old_bins, |path, _| path),
+            removed_binaries: only_in(&old_bins, &new_bins, |path, _| path),
+            changed_binaries,
+            added_certs: only_in(&new_certs, &old_certs, |_, subject| subject),
+            removed_certs: only_in(&old_certs, &new_certs, |_, subject| subject),
+            added_modules: new_modules
+                .difference(&old_modules)
+                .map(ToString::to_string)
+                .collect(),
+            removed_modules: old_modules
+                .difference(&new_modules)
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+
+    /// Whether nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sections().iter().all(|(_, items)| items.is_empty())
+    }
+
+    fn sections(&self) -> [(&'static str, &[String]); 7] {
+        [
+            ("New binaries", &self.added_binaries),
+            ("Changed binaries", &self.changed_binaries),
+            ("Removed binaries", &self.removed_binaries),
+            ("New root certs", &self.added_certs),
+            ("Removed root certs", &self.removed_certs),
+            ("Newly loaded kernel modules", &self.added_modules),
+            ("Unloaded kernel modules", &self.removed_modules),
+        ]
+    }
+}
+
+/// Labels of the entries in `ours` that `theirs` lacks.
+fn only_in<'a, V: Copy>(
+    ours: &BTreeMap<&'a str, V>,
+    theirs: &BTreeMap<&'a str, V>,
+    label: impl Fn(&'a str, V) -> &'a str,
+) -> Vec<String> {
+    ours.iter()
+        .filter(|(key, _)| !theirs.contains_key(*key))
+        .map(|(key, value)| label(key, *value).to_string())
+        .collect()
+}
+
+/// Fill each `{{name}}` slot in `template` in one pass, so slot syntax
+/// inside the filled-in text is left alone.
+fn fill(template: &str, slots: &BTreeMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len() * 4);
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        if let Some((value, end)) = after
+            .find("}}")
+            .and_then(|end| Some((slots.get(&after[..end])?, end)))
+        {
+            out.push_str(value);
+            rest = &after[end + 2..];
+        } else {
+            out.push_str("{{");
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_grade(risk: &RiskReport) -> String {
+    let mut html = format!(
+        "<section class=\"grade grade-{grade}\"><div class=\"letter\">{grade}</div><div>\
+         <strong>Risk grade {grade}</strong> &middot; {penalty:.1} penalty points",
+        grade = risk.grade,
+        penalty = risk.penalty
+    );
+    if risk.top.is_empty() {
+        html.push_str("<p class=\"empty\">Nothing of concern</p>");
+    } else {
+        html.push_str("<ul>");
+        for concern in &risk.top {
+            let _ = write!(
+                html,
+                "<li>{} {}</li>",
+                severity_badge(concern.severity),
+                escape(&concern.reason)
+            );
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("</div></section>");
+    html
+}
+
+fn render_findings(report: &AuditReport) -> String {
+    if report.findings.is_empty() {
+        return "<p class=\"empty\">No findings</p>".into();
+    }
+    let mut rows = String::new();
+    for finding in &report.findings {
+        let _ = writeln!(
+            rows,
+            "<tr><td data-sort=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+            severity_rank(finding.severity),
+            severity_badge(finding.severity),
+            category_name(finding.category),
+            escape(&finding.description)
+        );
+    }
+    table(&["Severity", "Category", "Description"], &rows)
+}
+
+fn render_changes(diff: &SnapshotDiff) -> String {
+    let mut html = String::from("<h2>Changes since the previous snapshot</h2>\n");
+    if diff.is_empty() {
+        html.push_str("<p class=\"empty\">No changes</p>");
+        return html;
+    }
+    for (title, items) in diff.sections() {
+        if items.is_empty() {
+            continue;
+        }
+        let _ = write!(html, "<h3>{title} ({})</h3><ul>", items.len());
+        for item in items {
+            let _ = write!(html, "<li><code>{}</code></li>", escape(item));
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+fn render_binaries(binaries: &[BinaryInfo]) -> String {
+    if binaries.is_empty() {
+        return "<p class=\"empty\">No binaries</p>".into();
+    }
+    let mut sorted: Vec<&BinaryInfo> = binaries.iter().collect();
+    // Least trusted first, unscored last.
+    sorted.sort_by(|a, b| {
+        trust_key(a)
+            .total_cmp(&trust_key(b))
+            .then(a.path.cmp(&b.path))
+    });
+
+    let mut rows = String::new();
+    for bin in sorted {
+        let (sort, trust) = bin.trust_score.as_ref().map_or_else(
+            || ("-1".into(), "<span class=\"muted\">unscored</span>".into()),
+            |score| (format!("{:.4}", score.total), trust_details(score)),
+        );
+        let _ = writeln!(
+            rows,
+            "<tr><td class=\"mono\">{path}</td><td class=\"num\" data-sort=\"{sort}\">{trust}</td>\
+             <td>{running}</td><td>{processes}</td><td class=\"mono\" title=\"{algorithm}:{hash}\">{short}</td>\
+             <td class=\"num\" data-sort=\"{size}\">{size}</td><td>{modified}</td></tr>",
+            path = escape(&bin.path),
+            running = if bin.running { "yes" } else { "no" },
+            processes = escape(&bin.process_names.join(", ")),
+            algorithm = bin.hash_algorithm,
+            hash = escape(&bin.hash),
+            short = escape(short_hash(&bin.hash)),
+            size = bin.size,
+            modified = timestamp(bin.modify_date),
+        );
+    }
+    table(
+        &[
+            "Path",
+            "Trust",
+            "Running",
+            "Processes",
+            "Hash",
+            "Size",
+            "Modified",
+        ],
+        &rows,
+    )
+}
+
+/// Sort key for a binary's trust: its total, with unscored binaries last.
+fn trust_key(bin: &BinaryInfo) -> f64 {
+    bin.trust_score.as_ref().map_or(f64::INFINITY, |s| s.total)
+}
+
+/// The trust total, expanding into the factors behind it.
+fn trust_details(score: &TrustScore) -> String {
+    let mut html = format!("<details><summary>{}</summary><dl>", percent(score.total));
+    for (name, value) in [
+        ("Consensus", score.hash_consensus),
+        ("Age", score.age_factor),
+        ("Identity", score.identity_stability),
+        ("Usage", score.usage_normality),
+        ("Provenance", score.provenance_score),
+    ] {
+        let _ = write!(html, "<dt>{name}</dt><dd>{}</dd>", percent(value));
+    }
+    html.push_str("</dl></details>");
+    html
+}
+
+fn render_processes(processes: &[ProcessInfo]) -> String {
+    if processes.is_empty() {
+        return "<p class=\"empty\">No processes</p>".into();
+    }
+    let mut rows = String::new();
+    for proc in processes {
+        let _ = writeln!(
+            rows,
+            "<tr><td class=\"num\" data-sort=\"{pid}\">{pid}</td><td>{name}</td><td class=\"mono\">{exe}</td>\
+             <td class=\"num\" data-sort=\"{uid}\">{uid}</td><td class=\"num\" data-sort=\"{usage:.6}\">{shown}</td></tr>",
+            pid = proc.pid,
+            name = escape(&proc.name),
+            exe = escape(proc.exe_path.as_deref().unwrap_or("")),
+            uid = proc.uid,
+            usage = proc.usage.value,
+            shown = percent(proc.usage.value),
+        );
+    }
+    table(&["PID", "Name", "Executable", "UID", "Usage"], &rows)
+}
+
+fn render_certs(certs: &[RootCertInfo]) -> String {
+    if certs.is_empty() {
+        return "<p class=\"empty\">No root certificates</p>".into();
+    }
+    let mut rows = String::new();
+    for cert in certs {
+        let worst = cert.findings.iter().map(|f| f.severity).max();
+        let status = if cert.expired {
+            "<span class=\"bad\">expired</span>"
+        } else {
+            "<span class=\"ok\">valid</span>"
+        };
+        let consensus = match cert.in_consensus {
+            Some(true) => "<span class=\"ok\">known</span>",
+            Some(false) => "<span class=\"bad\">unknown</span>",
+            None => "<span class=\"muted\">not checked</span>",
+        };
+        let mut findings = String::new();
+        for finding in &cert.findings {
+            let _ = write!(
+                findings,
+                "<div>{} {}</div>",
+                severity_badge(finding.severity),
+                escape(&finding.description)
+            );
+        }
+        let _ = writeln!(
+            rows,
+            "<tr><td>{subject}</td><td>{status}</td><td>{expires}</td><td>{consensus}</td>\
+             <td data-sort=\"{rank}\">{findings}</td><td class=\"mono\" title=\"{algorithm}:{fp}\">{short}</td></tr>",
+            subject = escape(&cert.subject),
+            expires = cert.not_after.format("%Y-%m-%d"),
+            rank = worst.map_or(-1, severity_rank),
+            algorithm = cert.fingerprint_algorithm,
+            fp = escape(&cert.fingerprint),
+            short = escape(short_hash(&cert.fingerprint)),
+        );
+    }
+    table(
+        &[
+            "Subject",
+            "Status",
+            "Expires",
+            "Consensus",
+            "Findings",
+            "Fingerprint",
+        ],
+        &rows,
+    )
+}
+
+fn render_verification(qr: &QrPayload) -> Result<String> {
+    let svg = qr
+        .code()?
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .quiet_zone(true)
+        .build();
+    // Inline SVG must not carry the XML declaration.
+    let svg = svg.find("<svg").map_or(svg.as_str(), |start| &svg[start..]);
+    Ok(format!(
+        "<h2>Verification</h2>\n<div class=\"qr\">{svg}<div><p>Scan with a phone on a \
+         different network (cellular) to check this machine's trust digest from an \
+         independent path.</p><p><code>{url}</code></p></div></div>",
+        url = escape(&qr.url)
+    ))
+}
+
+/// A sortable table with `headers` around pre-rendered `rows`.
+fn table(headers: &[&str], rows: &str) -> String {
+    let mut html = String::from("<table class=\"sortable\"><thead><tr>");
+    for header in headers {
+        let _ = write!(html, "<th>{header}</th>");
+    }
+    let _ = write!(html, "</tr></thead><tbody>\n{rows}</tbody></table>");
+    html
+}
+
+fn severity_badge(severity: Severity) -> String {
+    let name = severity_name(severity);
+    format!("<span class=\"sev sev-{name}\">{name}</span>")
+}
+
+const fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "critical",
+        Severity::High => "high",
+        Severity::Medium => "medium",
+        Severity::Low => "low",
+        Severity::Info => "info",
+    }
+}
+
+/// Higher is more severe, for sorting.
+const fn severity_rank(severity: Severity) -> i8 {
+    match severity {
+        Severity::Info => 0,
+        Severity::Low => 1,
+        Severity::Medium => 2,
+        Severity::High => 3,
+        Severity::Critical => 4,
+    }
+}
+
+const fn category_name(category: Category) -> &'static str {
+    match category {
+        Category::Binary => "binary",
+        Category::Process => "process",
+        Category::Cert => "cert",
+        Category::Module => "module",
+        Category::Library => "library",
+    }
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.0}%", value * 100.0)
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+fn short_hash(hash: &str) -> &str {
+    hash.get(..SHORT_HASH).unwrap_or(hash)
+}
+
+/// Escape text for HTML content and quoted attributes.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qr::UrlMode;
+    use crate::risk::RiskProfile;
+    use crate::verify::VerifyToken;
+
+    fn fixture() -> AuditSnapshot {
+        serde_json::from_str(include_str!("../../testdata/audit_snapshot.json")).unwrap()
+    }
+
+    fn token() -> VerifyToken {
+        VerifyToken {
+            dns_name: "a3f2b8c91d4e.sig.i1.is.".into(),
+            expected_value: "v=1;d=a3f2b8c91d4e".into(),
+            expected_ttl: 1,
+            generated_at: 1_767_225_600,
+            node_prefix: "a3f2b8c91d4e".into(),
+            digest: "a3f2b8c91d4e".repeat(4),
+            signature: None,
+        }
+    }
+
+    fn render(snapshot: &AuditSnapshot, previous: Option<&AuditSnapshot>) -> String {
+        let report = snapshot.report();
+        let risk = RiskReport::from_snapshot(snapshot, &report, &RiskProfile::default(), false);
+        let qr = QrPayload::new(&token(), UrlMode::Compact).unwrap();
+        let mut html = HtmlReport::new(snapshot, &report, &risk).with_qr(&qr);
+        if let Some(previous) = previous {
+            html = html.with_previous(previous);
+        }
+        html.render().unwrap()
+    }
+
+    fn previous() -> AuditSnapshot {
+        let mut previous = fixture();
+        previous.binaries.retain(|b| b.path != "/tmp/.x/miner");
+        previous.binaries[0].hash = "cd".repeat(32);
+        previous.root_certs.pop();
+        let mut module = previous.kernel_modules[0].clone();
+        module.name = "nvidia".into();
+        previous.kernel_modules.push(module);
+        previous
+    }
+
+    #[test]
+    fn golden_report() {
+        let snapshot = fixture();
+        let html = render(&snapshot, Some(&previous()));
+        // Regenerate with I1_BLESS=1 after an intended change.
+        if std::env::var_os("I1_BLESS").is_some() {
+            let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/audit_report.html");
+            std::fs::write(path, &html).unwrap();
+        }
+        assert_eq!(html, include_str!("../../testdata/audit_report.html"));
+    }
+
+    #[test]
+    fn diff_lists_changes() {
+        let diff = SnapshotDiff::between(&previous(), &fixture());
+        assert_eq!(diff.added_binaries, ["/tmp/.x/miner"]);
+        assert_eq!(diff.changed_binaries, ["/usr/bin/sshd"]);
+        assert!(diff.removed_binaries.is_empty());
+        assert_eq!(diff.added_certs, ["Rogue Root"]);
+        assert_eq!(diff.removed_modules, ["nvidia"]);
+        assert!(SnapshotDiff::between(&fixture(), &fixture()).is_empty());
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let mut snapshot = fixture();
+        snapshot.node_id = "<script>alert('x')</script>".into();
+        snapshot.binaries[0].path = "/opt/{{binaries}}/a&b".into();
+        let html = render(&snapshot, None);
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"));
+        assert!(html.contains("/opt/{{binaries}}/a&amp;b"));
+        assert!(!html.contains("Changes since"));
+    }
+}
@@ -9,7 +9,7 @@
 //!
 //! ## Multi-Factor Trust Scoring
 //!
-//! - **Hash** (SHA-256) -- is this binary known?
+//! - **Hash** (SHA-256, or BLAKE3 for faster full-disk audits) -- is this binary known?
 //! - **Create date** -- when did it appear on disk?
 //! - **Unique ID** (inode + device) -- has the file been replaced?
 //! - **Process name** -- does it match expectations?
@@ -21,41 +21,57 @@
 //! ```text
 //! Phase 1: Local Collection (no network)
 //!   discover_binaries() + discover_processes() + discover_root_certs()
-//!   -> correlate_processes() -> sha256_file() each
+//!   + discover_kernel_modules() [+ discover_shared_libraries() on request]
+//!   -> correlate_processes() -> hash_file() each (SHA-256 or BLAKE3)
 //!   -> AuditSnapshot
 //!
 //! Phase 2: Local Trust Scoring (no network)
 //!   score_binary() with age, identity, usage, provenance factors
+//!   score_module() from taint flags and module-tree provenance
+//!   validate_roots() for root signatures, keys and subject collisions
 //!   -> AuditSnapshot with partial scores (consensus=0.0)
 //!
 //! Phase 3: Network Consensus (requires i1-srv)
 //!   query bin.i1.is / ca.i1.is for each hash
-//!   -> fill in hash_consensus + network_consensus factors
+//!   -> apply_binary_consensus() fills in hash_consensus, compare_binaries()
+//!      flags unknown, rare and thinly vouched binaries
 //!
 //! Phase 4: Publish (optional --publish)
 //!   AuditSnapshot -> DNS records at bin.i1.is + ca.i1.is
 //! ```
 
+pub mod baseline;
 pub mod consensus;
 pub mod discovery;
 pub mod encoding;
 pub mod error;
 pub mod hash;
+#[cfg(feature = "report")]
+pub mod html;
 pub mod qr;
+pub mod report;
+pub mod risk;
 pub mod scoring;
 pub mod types;
+pub mod validate;
 pub mod verify;
 
 pub use error::{AuditError, Result};
+pub use hash::HashAlgorithm;
 pub use types::*;
 
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
 use chrono::Utc;
 
 /// Collect a full audit snapshot of the local system.
 ///
-/// Runs Phases 1 & 2: local discovery + local trust scoring.
-/// Network consensus (Phase 3) is not included -- call consensus
-/// queries separately.
+/// Runs Phases 1 & 2: local discovery + local trust scoring. Binaries and
+/// root certs are hashed with `algorithm`; binary hashes are reused from
+/// `hash_cache` when the file is unchanged.
+/// Network consensus (Phase 3) is not included -- query the hashes, then
+/// call [`apply_binary_consensus`].
 ///
 /// # Errors
 ///
@@ -63,11 +79,15 @@ use chrono::Utc;
 pub async fn collect_snapshot(
     bin_paths: &[&str],
     weights: &TrustWeights,
+    algorithm: HashAlgorithm,
+    hash_cache: &mut discovery::HashCache,
 ) -> Result<AuditSnapshot> {
     // Phase 1: Discover
     let processes = discovery::discover_processes()?;
-    let mut binaries = discovery::discover_binaries(bin_paths).await?;
-    let mut root_certs = discovery::discover_root_certs().await?;
+    let mut binaries =
+        discovery::discover_binaries_cached(bin_paths, algorithm, hash_cache).await?;
+    let mut root_certs = discovery::discover_root_certs(algorithm).await?;
+    let mut kernel_modules = discovery::discover_kernel_modules().await?;
 
     // Correlate binaries with running processes
     discovery::correlate_processes(&mut binaries, &processes);
@@ -79,13 +99,17 @@ pub async fn collect_snapshot(
     for cert in &mut root_certs {
         cert.trust_score = Some(scoring::score_cert(cert));
     }
+    for module in &mut kernel_modules {
+        module.trust = Some(scoring::score_module(module));
+    }
 
     // Build snapshot
     let system_uptime = discovery::get_system_uptime().unwrap_or(0);
     let cpu_count = discovery::get_cpu_count();
 
     let node_id = get_node_id();
-    let summary = AuditSummary::from_snapshot(&binaries, &processes, &root_certs, 0.5);
+    let summary = AuditSummary::from_snapshot(&binaries, &processes, &root_certs, 0.5)
+        .with_modules(&kernel_modules, 0.5);
 
     Ok(AuditSnapshot {
         node_id,
@@ -95,13 +119,56 @@ pub async fn collect_snapshot(
         binaries,
         processes,
         root_certs,
+        kernel_modules,
+        shared_libraries: Vec::new(),
         summary,
     })
 }
 
+/// Hash and score the shared libraries mapped by running processes.
+///
+/// Not part of [`collect_snapshot`], since it widens the scan to every
+/// library loaded on the system; store the result in
+/// [`AuditSnapshot::shared_libraries`] to have it reported. Hashes are
+/// reused from `hash_cache` when the file is unchanged.
+///
+/// # Errors
+///
+/// Returns `AuditError` if library discovery fails.
+pub async fn collect_shared_libraries(
+    weights: &TrustWeights,
+    algorithm: HashAlgorithm,
+    hash_cache: &mut discovery::HashCache,
+) -> Result<Vec<BinaryInfo>> {
+    let mut libraries = discovery::discover_shared_libraries(algorithm, hash_cache).await?;
+    for lib in &mut libraries {
+        lib.trust_score = Some(scoring::score_binary(lib, weights));
+    }
+    Ok(libraries)
+}
+
+/// Score binaries with network consensus and compare them against it.
+///
+/// Runs Phase 3 once the hashes have been queried: each binary is
+/// rescored with its entry in `consensus` (keyed by hash), then checked
+/// for being unknown, rare or vouched for only by new or clustered nodes.
+/// Binaries without an entry score no consensus at all.
+#[must_use]
+pub fn apply_binary_consensus<S: BuildHasher>(
+    binaries: &mut [BinaryInfo],
+    consensus: &HashMap<String, consensus::ConsensusResult, S>,
+    weights: &TrustWeights,
+) -> Vec<consensus::Anomaly> {
+    for bin in binaries.iter_mut() {
+        let result = consensus.get(&bin.hash);
+        bin.trust_score = Some(scoring::score_binary_with_consensus(bin, result, weights));
+    }
+    consensus::compare_binaries(binaries, consensus, weights)
+}
+
 /// Get a stable node identifier.
 ///
-/// Tries `/etc/machine-id` first, then hostname.
+/// Tries `/etc/machine-id` first, then the macOS `IOPlatformUUID`, then hostname.
 fn get_node_id() -> String {
     // Try machine-id
     if let Ok(id) = std::fs::read_to_string("/etc/machine-id") {
@@ -111,9 +178,78 @@ fn get_node_id() -> String {
         }
     }
 
+    // macOS has no machine-id; use the hardware UUID
+    #[cfg(target_os = "macos")]
+    if let Some(uuid) = discovery::macos::platform_uuid() {
+        return uuid;
+    }
+
     // Fallback: hostname
     hostname::get().map_or_else(
         |_| "unknown".to_string(),
         |h| h.to_string_lossy().into_owned(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{AnomalyKind, ConsensusResult};
+    use crate::report::tests::binary;
+    use chrono::Duration;
+
+    /// Consensus for `hash` from 20 established nodes spread over `networks`.
+    fn vouched(hash: &str, networks: usize) -> ConsensusResult {
+        ConsensusResult {
+            hash: hash.to_string(),
+            found: true,
+            node_count: 20,
+            network_trust: None,
+            total_nodes: None,
+            attestations: (0..20)
+                .map(|i| Attestation {
+                    node: format!("node{i}"),
+                    network: Some(format!("10.{}", i % networks)),
+                    first_seen: Utc::now() - Duration::days(90),
+                    digest_at: Utc::now() - Duration::days(30),
+                })
+                .collect(),
+            fetched_at: Utc::now(),
+            ttl_secs: 3600,
+            from_cache: false,
+            stale: false,
+        }
+    }
+
+    #[test]
+    fn low_diversity_consensus_lowers_trust() {
+        let weights = TrustWeights::default();
+        let mut diverse = vec![binary("/usr/bin/sshd", true, 0.0)];
+        let mut clustered = diverse.clone();
+        let hash = diverse[0].hash.clone();
+
+        let anomalies = apply_binary_consensus(
+            &mut diverse,
+            &HashMap::from([(hash.clone(), vouched(&hash, 10))]),
+            &weights,
+        );
+        assert!(anomalies.is_empty(), "{anomalies:?}");
+        let diverse_score = diverse[0].trust_score.as_ref().unwrap();
+        assert!(diverse_score.hash_consensus > 0.5);
+
+        // Same node count, all from one network
+        let anomalies = apply_binary_consensus(
+            &mut clustered,
+            &HashMap::from([(hash.clone(), vouched(&hash, 1))]),
+            &weights,
+        );
+        let clustered_score = clustered[0].trust_score.as_ref().unwrap();
+        assert!(clustered_score.hash_consensus.abs() < f64::EPSILON);
+        assert!(clustered_score.total < diverse_score.total);
+        let kinds: Vec<_> = anomalies.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            [AnomalyKind::ThinConsensus, AnomalyKind::UnknownBinary]
+        );
+    }
+}
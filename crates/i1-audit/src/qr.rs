@@ -9,26 +9,155 @@
 //!
 //! The phone's cell network is the independent trust anchor. If your local
 //! DNS is poisoned, the phone will see different results.
+//!
+//! Error correction is picked from the payload length: short URLs get the
+//! strongest correction that still makes a small code, long ones give it
+//! up to stay scannable. A URL too long for a comfortable code can be
+//! swapped for the compact one ([`UrlMode`]), and one that makes a code
+//! past [`MAX_SCANNABLE_VERSION`] is logged as a warning.
 
 use image::Luma;
-use qrcode::QrCode;
+use qrcode::{EcLevel, QrCode, Version};
 use std::path::Path;
+use tracing::warn;
 
 use crate::error::{AuditError, Result};
 use crate::verify::VerifyToken;
 
+/// Largest QR version (57x57 modules) phone cameras read reliably.
+pub const MAX_SCANNABLE_VERSION: i16 = 10;
+
+/// Largest QR version (41x41 modules) that scans at a glance; in
+/// [`UrlMode::Auto`], a full URL past this is swapped for the compact one.
+pub const COMFORTABLE_VERSION: i16 = 6;
+
+/// Byte-mode capacity at each error-correction level (L, M, Q, H), for
+/// [`COMFORTABLE_VERSION`] and [`MAX_SCANNABLE_VERSION`].
+const CAPACITY: [(i16, [usize; 4]); 2] = [
+    (COMFORTABLE_VERSION, [134, 106, 74, 58]),
+    (MAX_SCANNABLE_VERSION, [271, 213, 151, 119]),
+];
+
+/// Which verification URL a QR code carries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UrlMode {
+    /// The full URL, with every field of the token.
+    Full,
+    /// The compact URL; the page fetches the rest from DNS.
+    Compact,
+    /// The full URL when it makes a comfortable code, else the compact one.
+    #[default]
+    Auto,
+}
+
+/// The URL chosen for a QR code, and how it encodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrPayload {
+    /// Encoded URL.
+    pub url: String,
+    /// Whether `url` is the compact form.
+    pub compact: bool,
+    /// Error-correction level used.
+    pub ec_level: EcLevel,
+    /// Resulting QR version (1-40).
+    pub version: i16,
+}
+
+impl QrPayload {
+    /// Choose the URL for `token` under `mode`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError::Encoding` if the URL doesn't fit in a QR code.
+    pub fn new(token: &VerifyToken, mode: UrlMode) -> Result<Self> {
+        let payload = match mode {
+            UrlMode::Compact => Self::encoded(token.compact_url(), true)?,
+            UrlMode::Full => Self::encoded(token.verification_url(), false)?,
+            UrlMode::Auto => {
+                let full = Self::encoded(token.verification_url(), false)?;
+                if full.version <= COMFORTABLE_VERSION {
+                    full
+                } else {
+                    Self::encoded(token.compact_url(), true)?
+                }
+            }
+        };
+        if !payload.is_scannable() {
+            warn!(
+                version = payload.version,
+                len = payload.url.len(),
+                "verification URL makes a QR code too dense to scan reliably"
+            );
+        }
+        Ok(payload)
+    }
+
+    fn encoded(url: String, compact: bool) -> Result<Self> {
+        let ec_level = ec_level_for(url.len());
+        let version = match encode(&url, ec_level)?.version() {
+            Version::Normal(v) | Version::Micro(v) => v,
+        };
+        Ok(Self {
+            url,
+            compact,
+            ec_level,
+            version,
+        })
+    }
+
+    /// Will phone cameras read this reliably?
+    #[must_use]
+    pub const fn is_scannable(&self) -> bool {
+        self.version <= MAX_SCANNABLE_VERSION
+    }
+
+    /// The QR code.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError::Encoding` if QR generation fails.
+    pub fn code(&self) -> Result<QrCode> {
+        encode(&self.url, self.ec_level)
+    }
+}
+
+/// Error correction for a payload of `len` bytes: the strongest level
+/// that fits a comfortable code, else the strongest that stays scannable,
+/// else the weakest.
+#[must_use]
+pub fn ec_level_for(len: usize) -> EcLevel {
+    const LEVELS: [EcLevel; 4] = [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L];
+    CAPACITY
+        .iter()
+        .find_map(|(_, capacity)| {
+            LEVELS
+                .into_iter()
+                .find(|level| len <= capacity[*level as usize])
+        })
+        .unwrap_or(EcLevel::L)
+}
+
+fn encode(url: &str, ec_level: EcLevel) -> Result<QrCode> {
+    QrCode::with_error_correction_level(url.as_bytes(), ec_level)
+        .map_err(|e| AuditError::Encoding(e.to_string()))
+}
+
 /// Generate a QR code PNG file from a verification token.
 ///
-/// The QR encodes the verification URL. Scan it with any phone camera.
+/// The QR encodes the verification URL chosen by `mode`. Scan it with any
+/// phone camera. Returns what was encoded.
 ///
 /// # Errors
 ///
 /// Returns `AuditError::Encoding` if QR generation fails,
 /// or `AuditError::Io` if the file cannot be written.
-pub fn generate_qr_png(token: &VerifyToken, output_path: &Path) -> Result<()> {
-    let url = token.verification_url();
-
-    let code = QrCode::new(url.as_bytes()).map_err(|e| AuditError::Encoding(e.to_string()))?;
+pub fn generate_qr_png(
+    token: &VerifyToken,
+    output_path: &Path,
+    mode: UrlMode,
+) -> Result<QrPayload> {
+    let payload = QrPayload::new(token, mode)?;
+    let code = payload.code()?;
 
     let image = code.render::<Luma<u8>>().quiet_zone(true).build();
 
@@ -36,17 +165,15 @@ pub fn generate_qr_png(token: &VerifyToken, output_path: &Path) -> Result<()> {
         .save(output_path)
         .map_err(|e| AuditError::io(output_path.display().to_string(), std::io::Error::other(e)))?;
 
-    Ok(())
+    Ok(payload)
 }
 
 /// Render a QR code as a terminal-friendly Unicode string.
 ///
 /// Uses block characters so it displays in any terminal.
 #[must_use]
-pub fn render_qr_terminal(token: &VerifyToken) -> String {
-    let url = token.verification_url();
-
-    let Ok(code) = QrCode::new(url.as_bytes()) else {
+pub fn render_qr_terminal(token: &VerifyToken, mode: UrlMode) -> String {
+    let Ok(code) = QrPayload::new(token, mode).and_then(|payload| payload.code()) else {
         return "Error: failed to generate QR code".to_string();
     };
 
@@ -74,6 +201,8 @@ mod tests {
             binaries: vec![],
             processes: vec![],
             root_certs: vec![],
+            kernel_modules: vec![],
+            shared_libraries: vec![],
             summary: AuditSummary {
                 total_binaries: 0,
                 total_processes: 0,
@@ -82,6 +211,8 @@ mod tests {
                 expired_certs: 0,
                 low_trust_binaries: 0,
                 unknown_certs: 0,
+                critical_certs: 0,
+                low_trust_modules: 0,
             },
         };
         generate_verify_token(&snap)
@@ -93,7 +224,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         let path = dir.path().join("verify.png");
 
-        generate_qr_png(&token, &path).unwrap();
+        generate_qr_png(&token, &path, UrlMode::Auto).unwrap();
 
         assert!(path.exists());
         let meta = std::fs::metadata(&path).unwrap();
@@ -103,9 +234,44 @@ mod tests {
     #[test]
     fn terminal_render_non_empty() {
         let token = make_token();
-        let rendered = render_qr_terminal(&token);
+        let rendered = render_qr_terminal(&token, UrlMode::Auto);
         assert!(!rendered.is_empty());
         // Should contain block characters
         assert!(rendered.contains('\u{2588}'));
     }
+
+    #[test]
+    fn ec_level_bands_by_length() {
+        assert_eq!(ec_level_for(32), EcLevel::H);
+        assert_eq!(ec_level_for(70), EcLevel::Q);
+        assert_eq!(ec_level_for(100), EcLevel::M);
+        assert_eq!(ec_level_for(130), EcLevel::L);
+        // Past a comfortable code, fall back to the scannable band.
+        assert_eq!(ec_level_for(140), EcLevel::Q);
+        assert_eq!(ec_level_for(200), EcLevel::M);
+        assert_eq!(ec_level_for(250), EcLevel::L);
+        assert_eq!(ec_level_for(1000), EcLevel::L);
+    }
+
+    #[test]
+    fn auto_mode_falls_back_to_compact_url() {
+        let token = make_token();
+        let full = QrPayload::new(&token, UrlMode::Auto).unwrap();
+        assert!(!full.compact && full.version <= COMFORTABLE_VERSION);
+
+        // A signature pushes the full URL past a comfortable code.
+        let signed = VerifyToken {
+            signature: Some("A".repeat(86)),
+            ..token
+        };
+        let auto = QrPayload::new(&signed, UrlMode::Auto).unwrap();
+        assert!(auto.compact);
+        assert_eq!(auto.url, signed.compact_url());
+        assert_eq!(auto.ec_level, EcLevel::H);
+        assert!(auto.version < full.version);
+
+        let forced = QrPayload::new(&signed, UrlMode::Full).unwrap();
+        assert!(!forced.compact && forced.is_scannable());
+        assert!(forced.version > COMFORTABLE_VERSION);
+    }
 }
@@ -0,0 +1,484 @@
+//! Risk report -- the worst findings of an audit in one prioritized list.
+//!
+//! An audit reports binaries, processes, certs and kernel modules in
+//! separate sections. [`AuditSnapshot::report`] pulls out the findings worth
+//! acting on: the lowest-trust running binaries, processes whose executable
+//! was deleted or escaped discovery, shared libraries loaded from unusual
+//! paths, expired or unknown root certs, and root certs flagged by
+//! [`crate::validate`]. Consensus comparison results
+//! are folded in with [`AuditReport::with_anomalies`]. Findings are ordered
+//! most severe first and rolled up per severity.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::consensus::{
+    compare_certs, compare_libraries, compare_processes, Anomaly, AnomalyKind, Severity,
+};
+use crate::types::AuditSnapshot;
+
+/// Running binaries below this trust score are reported.
+pub(crate) const LOW_TRUST_THRESHOLD: f64 = 0.5;
+
+/// Running binaries below this trust score are reported as high severity.
+pub(crate) const HIGH_RISK_THRESHOLD: f64 = 0.3;
+
+/// At most this many low-trust binaries are reported, lowest trust first.
+const MAX_BINARY_FINDINGS: usize = 10;
+
+/// What part of the system a finding is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// A binary on disk
+    Binary,
+    /// A running process
+    Process,
+    /// A root certificate
+    Cert,
+    /// A loaded kernel module
+    Module,
+    /// A shared library mapped by a running process
+    Library,
+}
+
+impl From<AnomalyKind> for Category {
+    fn from(kind: AnomalyKind) -> Self {
+        match kind {
+            AnomalyKind::UnknownBinary
+            | AnomalyKind::RareBinary
+            | AnomalyKind::ThinConsensus
+            | AnomalyKind::SuspiciousLocation
+            | AnomalyKind::LowTrustBinary => Self::Binary,
+            AnomalyKind::DeletedExecutable | AnomalyKind::UnbackedProcess => Self::Process,
+            AnomalyKind::UnknownCert
+            | AnomalyKind::ExpiredCert
+            | AnomalyKind::BadCertSignature
+            | AnomalyKind::WeakCertKey
+            | AnomalyKind::WeakCertSignature
+            | AnomalyKind::LongCertValidity
+            | AnomalyKind::CertKeyCollision => Self::Cert,
+            AnomalyKind::NewKernelModule | AnomalyKind::UntrustedModule => Self::Module,
+            AnomalyKind::UnusualLibraryPath => Self::Library,
+        }
+    }
+}
+
+/// One entry in the report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// How severe it is
+    pub severity: Severity,
+    /// What part of the system it concerns
+    pub category: Category,
+    /// What was found
+    pub kind: AnomalyKind,
+    /// Binary path, process ID or cert fingerprint, when known
+    pub subject: Option<String>,
+    /// Human-readable description
+    pub description: String,
+}
+
+/// Number of findings at each severity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeverityCounts {
+    /// Critical findings
+    pub critical: usize,
+    /// High severity findings
+    pub high: usize,
+    /// Medium severity findings
+    pub medium: usize,
+    /// Low severity findings
+    pub low: usize,
+    /// Informational findings
+    pub info: usize,
+}
+
+impl SeverityCounts {
+    /// Count one more finding at `severity`.
+    pub const fn add(&mut self, severity: Severity) {
+        match severity {
+            Severity::Critical => self.critical += 1,
+            Severity::High => self.high += 1,
+            Severity::Medium => self.medium += 1,
+            Severity::Low => self.low += 1,
+            Severity::Info => self.info += 1,
+        }
+    }
+
+    /// Findings at `severity`.
+    #[must_use]
+    pub const fn get(&self, severity: Severity) -> usize {
+        match severity {
+            Severity::Critical => self.critical,
+            Severity::High => self.high,
+            Severity::Medium => self.medium,
+            Severity::Low => self.low,
+            Severity::Info => self.info,
+        }
+    }
+
+    /// Findings at every severity.
+    #[must_use]
+    pub const fn total(&self) -> usize {
+        self.critical + self.high + self.medium + self.low + self.info
+    }
+}
+
+/// Nonzero counts, most severe first, e.g. `3 Critical, 5 High`; `none`
+/// when there are no findings.
+impl fmt::Display for SeverityCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = Severity::ALL
+            .into_iter()
+            .rev()
+            .filter(|s| self.get(*s) > 0)
+            .map(|s| format!("{} {s}", self.get(s)))
+            .collect();
+        if parts.is_empty() {
+            return f.write_str("none");
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Prioritized summary of an audit's findings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// Node the audit ran on
+    pub node_id: String,
+    /// When the audited snapshot was collected
+    pub collected_at: DateTime<Utc>,
+    /// Severity of the worst finding (None when there are no findings)
+    pub highest: Option<Severity>,
+    /// Findings per severity
+    pub counts: SeverityCounts,
+    /// Findings, most severe first
+    pub findings: Vec<Finding>,
+}
+
+impl AuditSnapshot {
+    /// Collect this snapshot's worst findings into a report.
+    #[must_use]
+    pub fn report(&self) -> AuditReport {
+        let mut findings = Vec::new();
+
+        let mut low_trust: Vec<_> = self
+            .binaries
+            .iter()
+            .filter(|b| b.running)
+            .filter_map(|b| Some((b, b.trust_score.as_ref()?.total)))
+            .filter(|(_, trust)| *trust < LOW_TRUST_THRESHOLD)
+            .collect();
+        low_trust.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for (bin, trust) in low_trust.into_iter().take(MAX_BINARY_FINDINGS) {
+            findings.push(Finding {
+                severity: if trust < HIGH_RISK_THRESHOLD {
+                    Severity::High
+                } else {
+                    Severity::Medium
+                },
+                category: Category::Binary,
+                kind: AnomalyKind::LowTrustBinary,
+                subject: Some(bin.path.clone()),
+                description: format!(
+                    "Low-trust running binary: {} (trust={:.0}%, processes={})",
+                    bin.path,
+                    trust * 100.0,
+                    bin.process_names.join(",")
+                ),
+            });
+        }
+
+        for proc in &self.processes {
+            for anomaly in compare_processes(std::slice::from_ref(proc), &self.binaries) {
+                findings.push(Finding {
+                    subject: Some(proc.pid.to_string()),
+                    ..Finding::from(anomaly)
+                });
+            }
+        }
+
+        for lib in &self.shared_libraries {
+            for anomaly in compare_libraries(std::slice::from_ref(lib)) {
+                findings.push(Finding {
+                    subject: Some(lib.path.clone()),
+                    ..Finding::from(anomaly)
+                });
+            }
+        }
+
+        for cert in &self.root_certs {
+            let validated = cert.findings.iter().cloned();
+            for anomaly in compare_certs(std::slice::from_ref(cert)).into_iter().chain(validated) {
+                findings.push(Finding {
+                    subject: Some(cert.fingerprint.clone()),
+                    ..Finding::from(anomaly)
+                });
+            }
+        }
+
+        AuditReport {
+            node_id: self.node_id.clone(),
+            collected_at: self.collected_at,
+            highest: None,
+            counts: SeverityCounts::default(),
+            findings,
+        }
+        .rolled_up()
+    }
+}
+
+impl From<Anomaly> for Finding {
+    fn from(anomaly: Anomaly) -> Self {
+        Self {
+            severity: anomaly.severity,
+            category: anomaly.kind.into(),
+            kind: anomaly.kind,
+            subject: None,
+            description: anomaly.description,
+        }
+    }
+}
+
+impl AuditReport {
+    /// Add anomalies from consensus comparison.
+    ///
+    /// Anomalies the report already holds (same kind and description) are
+    /// skipped, so passing `compare_certs` output doesn't double-count.
+    #[must_use]
+    pub fn with_anomalies(mut self, anomalies: &[Anomaly]) -> Self {
+        for anomaly in anomalies {
+            let known = self
+                .findings
+                .iter()
+                .any(|f| f.kind == anomaly.kind && f.description == anomaly.description);
+            if !known {
+                self.findings.push(anomaly.clone().into());
+            }
+        }
+        self.rolled_up()
+    }
+
+    /// Sort most severe first and recompute the rollup.
+    fn rolled_up(mut self) -> Self {
+        self.findings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.category.cmp(&b.category))
+        });
+        self.highest = self.findings.first().map(|f| f.severity);
+        self.counts = SeverityCounts::default();
+        for finding in &self.findings {
+            self.counts.add(finding.severity);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::hash::HashAlgorithm;
+    use crate::types::{
+        AuditSummary, BinaryInfo, FileIdentity, ProcessInfo, RootCertInfo, TrustScore, UsageMetric,
+    };
+    use chrono::Duration;
+
+    pub fn binary(path: &str, running: bool, trust: f64) -> BinaryInfo {
+        BinaryInfo {
+            path: path.into(),
+            hash: "ab".repeat(32),
+            hash_algorithm: HashAlgorithm::Sha256,
+            create_date: Utc::now(),
+            modify_date: Utc::now(),
+            identity: FileIdentity {
+                inode: 1,
+                device_id: 1,
+            },
+            size: 1024,
+            running,
+            process_names: if running {
+                vec![path.rsplit('/').next().unwrap().into()]
+            } else {
+                Vec::new()
+            },
+            trust_score: Some(TrustScore {
+                total: trust,
+                hash_consensus: 0.0,
+                age_factor: 0.0,
+                identity_stability: 0.0,
+                usage_normality: 0.0,
+                provenance_score: 0.0,
+            }),
+        }
+    }
+
+    pub fn process(pid: i32, exe: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: exe.rsplit('/').next().unwrap().into(),
+            exe_path: Some(exe.into()),
+            exe_missing: false,
+            cmdline: Vec::new(),
+            uid: 0,
+            usage: UsageMetric::compute(60, 3600, 0.1, 4.0),
+        }
+    }
+
+    fn cert(subject: &str, expired: bool, in_consensus: Option<bool>) -> RootCertInfo {
+        RootCertInfo {
+            path: "/etc/ssl/certs/ca.pem".into(),
+            fingerprint: format!("{subject}-fp"),
+            fingerprint_algorithm: HashAlgorithm::Sha256,
+            issuer: subject.into(),
+            subject: subject.into(),
+            serial: "01".into(),
+            not_before: Utc::now() - Duration::days(3650),
+            not_after: if expired {
+                Utc::now() - Duration::days(1)
+            } else {
+                Utc::now() + Duration::days(365)
+            },
+            expired,
+            in_consensus,
+            trust_score: None,
+            findings: Vec::new(),
+            der: Vec::new(),
+        }
+    }
+
+    pub fn snapshot() -> AuditSnapshot {
+        // Packaged, so running it as root adds no risk
+        let mut sshd = binary("/usr/bin/sshd", true, 0.9);
+        if let Some(score) = sshd.trust_score.as_mut() {
+            score.provenance_score = 1.0;
+        }
+        let binaries = vec![
+            sshd,
+            binary("/tmp/.x/miner", true, 0.1),
+            binary("/opt/app/agent", true, 0.4),
+            binary("/usr/bin/rarely-used", false, 0.1),
+        ];
+        let processes = vec![
+            process(812, "/usr/bin/sshd"),
+            process(4242, "/tmp/.x/dropper (deleted)"),
+        ];
+        let root_certs = vec![
+            cert("Good Root", false, Some(true)),
+            cert("Old Root", true, None),
+            cert("Rogue Root", false, Some(false)),
+        ];
+        let summary = AuditSummary::from_snapshot(&binaries, &processes, &root_certs, 0.5);
+        AuditSnapshot {
+            node_id: "node1".into(),
+            collected_at: Utc::now(),
+            system_uptime_secs: 3600,
+            cpu_count: 4,
+            binaries,
+            processes,
+            root_certs,
+            kernel_modules: Vec::new(),
+            shared_libraries: Vec::new(),
+            summary,
+        }
+    }
+
+    #[test]
+    fn report_orders_worst_first() {
+        let report = snapshot().report();
+        let kinds: Vec<_> = report.findings.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                AnomalyKind::UnknownCert,
+                AnomalyKind::LowTrustBinary,
+                AnomalyKind::DeletedExecutable,
+                AnomalyKind::LowTrustBinary,
+                AnomalyKind::ExpiredCert,
+            ]
+        );
+        assert_eq!(report.findings[1].subject.as_deref(), Some("/tmp/.x/miner"));
+        assert_eq!(report.findings[2].subject.as_deref(), Some("4242"));
+        assert_eq!(report.findings[3].severity, Severity::Medium);
+        assert_eq!(report.highest, Some(Severity::Critical));
+        assert_eq!(
+            report.counts,
+            SeverityCounts {
+                critical: 1,
+                high: 2,
+                medium: 2,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn anomalies_are_merged_once() {
+        let snapshot = snapshot();
+        let module = Anomaly {
+            kind: AnomalyKind::UntrustedModule,
+            severity: Severity::High,
+            description: "Untrusted kernel module: rootkit".into(),
+        };
+        let mut anomalies = compare_certs(&snapshot.root_certs);
+        anomalies.push(module);
+
+        let report = snapshot.report().with_anomalies(&anomalies);
+        assert_eq!(report.findings.len(), 6);
+        assert_eq!(report.counts.high, 3);
+        let merged = report
+            .findings
+            .iter()
+            .find(|f| f.kind == AnomalyKind::UntrustedModule)
+            .unwrap();
+        assert_eq!(merged.category, Category::Module);
+        assert!(merged.subject.is_none());
+    }
+
+    #[test]
+    fn unusual_libraries_are_reported() {
+        let mut snapshot = snapshot();
+        snapshot.binaries.truncate(1);
+        snapshot.processes.truncate(1);
+        snapshot.root_certs.truncate(1);
+        snapshot.shared_libraries = vec![
+            binary("/usr/lib/x86_64-linux-gnu/libc.so.6", true, 0.9),
+            binary("/dev/shm/libhook.so", true, 0.9),
+        ];
+
+        let report = snapshot.report();
+        assert_eq!(report.findings.len(), 1);
+        let finding = &report.findings[0];
+        assert_eq!(finding.kind, AnomalyKind::UnusualLibraryPath);
+        assert_eq!(finding.category, Category::Library);
+        assert_eq!(finding.severity, Severity::High);
+        assert_eq!(finding.subject.as_deref(), Some("/dev/shm/libhook.so"));
+        assert!(finding.description.contains("processes=libhook.so"));
+    }
+
+    #[test]
+    fn clean_snapshot_has_empty_report() {
+        let mut snapshot = snapshot();
+        snapshot.binaries.truncate(1);
+        snapshot.processes.truncate(1);
+        snapshot.root_certs.truncate(1);
+
+        let report = snapshot.report();
+        assert!(report.findings.is_empty());
+        assert_eq!(report.highest, None);
+        assert_eq!(report.counts, SeverityCounts::default());
+    }
+
+    #[test]
+    fn json_uses_lowercase_names() {
+        let json = serde_json::to_value(snapshot().report()).unwrap();
+        assert_eq!(json["highest"], "critical");
+        assert_eq!(json["counts"]["high"], 2);
+        assert_eq!(json["findings"][0]["kind"], "unknown_cert");
+        assert_eq!(json["findings"][0]["category"], "cert");
+        assert_eq!(json["findings"][0]["subject"], "Rogue Root-fp");
+    }
+}
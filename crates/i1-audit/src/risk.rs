@@ -0,0 +1,524 @@
+//! Risk grade -- the "so what" of an audit as one letter.
+//!
+//! [`RiskReport::from_snapshot`] turns a snapshot and its [`AuditReport`]
+//! into penalty points: low-trust running binaries (worse the lower their
+//! trust), binaries running as root that neither a package manager nor the
+//! network knows, expired and unknown root certs, every other finding by
+//! severity, and, when consensus was queried, missing consensus coverage.
+//! The total maps to a grade from A to F, and the items that cost the most
+//! points are listed with a one-line reason each.
+//!
+//! Weights and grade thresholds come from a [`RiskProfile`], which
+//! deserializes from a profile file; fields it leaves out keep their
+//! defaults.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::consensus::{AnomalyKind, Severity};
+use crate::error::{AuditError, Result};
+use crate::report::{AuditReport, Category, HIGH_RISK_THRESHOLD, LOW_TRUST_THRESHOLD};
+use crate::types::AuditSnapshot;
+
+/// Number of concerns listed in a risk report.
+const TOP_CONCERNS: usize = 5;
+
+/// Overall grade, A (best) to F.
+///
+/// Ordered best first, so `grade > Grade::B` means "worse than B".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Grade {
+    /// Nothing worth acting on
+    A,
+    /// Minor findings
+    B,
+    /// Findings worth a look
+    C,
+    /// Serious findings
+    D,
+    /// Act now
+    F,
+}
+
+impl Grade {
+    /// The grade's letter.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+            Self::D => "D",
+            Self::F => "F",
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Grade {
+    type Err = AuditError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "A" => Ok(Self::A),
+            "B" => Ok(Self::B),
+            "C" => Ok(Self::C),
+            "D" => Ok(Self::D),
+            "F" => Ok(Self::F),
+            _ => Err(AuditError::UnknownGrade(s.to_string())),
+        }
+    }
+}
+
+/// Penalty points per finding.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskWeights {
+    /// Per low-trust running binary at zero trust; scaled by `1 - trust`
+    pub low_trust_binary: f64,
+    /// Per binary run as root that neither a package manager nor the
+    /// network vouches for
+    pub privileged_unknown_binary: f64,
+    /// Per expired root cert
+    pub expired_cert: f64,
+    /// Per root cert the network doesn't know
+    pub unknown_cert: f64,
+    /// At zero consensus coverage; scaled by the uncovered fraction.
+    /// Only applies when consensus was queried.
+    pub missing_coverage: f64,
+    /// Per other critical finding
+    pub critical: f64,
+    /// Per other high severity finding
+    pub high: f64,
+    /// Per other medium severity finding
+    pub medium: f64,
+    /// Per other low severity finding
+    pub low: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        Self {
+            low_trust_binary: 4.0,
+            privileged_unknown_binary: 10.0,
+            expired_cert: 2.0,
+            unknown_cert: 15.0,
+            missing_coverage: 20.0,
+            critical: 30.0,
+            high: 8.0,
+            medium: 2.0,
+            low: 0.5,
+        }
+    }
+}
+
+impl RiskWeights {
+    const fn severity(&self, severity: Severity) -> f64 {
+        match severity {
+            Severity::Critical => self.critical,
+            Severity::High => self.high,
+            Severity::Medium => self.medium,
+            Severity::Low => self.low,
+            Severity::Info => 0.0,
+        }
+    }
+}
+
+/// Most penalty points each grade allows; anything above `d` is an F.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GradeThresholds {
+    /// Most points for an A
+    pub a: f64,
+    /// Most points for a B
+    pub b: f64,
+    /// Most points for a C
+    pub c: f64,
+    /// Most points for a D
+    pub d: f64,
+}
+
+impl Default for GradeThresholds {
+    fn default() -> Self {
+        Self {
+            a: 5.0,
+            b: 15.0,
+            c: 35.0,
+            d: 60.0,
+        }
+    }
+}
+
+impl GradeThresholds {
+    /// Grade for `penalty` points.
+    #[must_use]
+    pub fn grade(&self, penalty: f64) -> Grade {
+        [
+            (self.a, Grade::A),
+            (self.b, Grade::B),
+            (self.c, Grade::C),
+            (self.d, Grade::D),
+        ]
+        .into_iter()
+        .find(|(most, _)| penalty <= *most)
+        .map_or(Grade::F, |(_, grade)| grade)
+    }
+}
+
+/// Weights and thresholds for grading an audit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskProfile {
+    /// Penalty points per finding
+    pub weights: RiskWeights,
+    /// Penalty points per grade
+    pub thresholds: GradeThresholds,
+}
+
+/// The measurements a grade is computed from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskFactors {
+    /// Running binaries below the low-trust threshold
+    pub low_trust_binaries: usize,
+    /// Lowest trust among running binaries
+    pub worst_binary_trust: Option<f64>,
+    /// Binaries run as root without package or network provenance
+    pub privileged_unknown_binaries: usize,
+    /// Lowest trust among those binaries
+    pub worst_privileged_trust: Option<f64>,
+    /// Expired root certs
+    pub expired_certs: usize,
+    /// Root certs the network doesn't know
+    pub unknown_certs: usize,
+    /// Fraction of running binaries the network knows (None when offline)
+    pub consensus_coverage: Option<f64>,
+}
+
+/// One of the items that cost the most points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Concern {
+    /// Penalty points this item cost
+    pub penalty: f64,
+    /// How severe it is
+    pub severity: Severity,
+    /// What part of the system it concerns
+    pub category: Category,
+    /// Binary path, process ID or cert fingerprint, when known
+    pub subject: Option<String>,
+    /// One-line reason
+    pub reason: String,
+}
+
+/// Overall grade of an audit, with what drove it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskReport {
+    /// Overall grade
+    pub grade: Grade,
+    /// Total penalty points
+    pub penalty: f64,
+    /// Measurements behind the grade
+    pub factors: RiskFactors,
+    /// Most concerning items, costliest first
+    pub top: Vec<Concern>,
+}
+
+impl RiskReport {
+    /// Grade `snapshot`, whose findings are in `report`.
+    ///
+    /// Set `online` when binary consensus was queried; offline every
+    /// binary has zero consensus, so coverage isn't counted.
+    #[must_use]
+    pub fn from_snapshot(
+        snapshot: &AuditSnapshot,
+        report: &AuditReport,
+        profile: &RiskProfile,
+        online: bool,
+    ) -> Self {
+        let mut tally = Tally {
+            weights: &profile.weights,
+            factors: RiskFactors::default(),
+            concerns: Vec::new(),
+        };
+        tally.binaries(snapshot, online);
+        tally.certs(snapshot);
+        tally.findings(report);
+        let Tally {
+            factors,
+            mut concerns,
+            ..
+        } = tally;
+
+        let penalty: f64 = concerns.iter().map(|c| c.penalty).sum();
+        concerns.retain(|c| c.penalty > 0.0);
+        concerns.sort_by(|a, b| {
+            b.penalty
+                .total_cmp(&a.penalty)
+                .then(b.severity.cmp(&a.severity))
+        });
+        concerns.truncate(TOP_CONCERNS);
+
+        Self {
+            grade: profile.thresholds.grade(penalty),
+            penalty,
+            factors,
+            top: concerns,
+        }
+    }
+
+    /// Whether the grade is worse than `minimum`.
+    #[must_use]
+    pub fn below(&self, minimum: Grade) -> bool {
+        self.grade > minimum
+    }
+}
+
+/// Factors and concerns gathered while grading.
+struct Tally<'a> {
+    weights: &'a RiskWeights,
+    factors: RiskFactors,
+    concerns: Vec<Concern>,
+}
+
+impl Tally<'_> {
+    /// Low-trust and privileged unknown binaries, and consensus coverage.
+    fn binaries(&mut self, snapshot: &AuditSnapshot, online: bool) {
+        let privileged: HashSet<&str> = snapshot
+            .processes
+            .iter()
+            .filter(|p| p.uid == 0)
+            .filter_map(|p| p.exe_path.as_deref())
+            .collect();
+
+        let running = snapshot.binaries.iter().filter(|b| b.running);
+        let mut known = 0usize;
+        let mut scored = 0usize;
+        for (bin, score) in running.filter_map(|b| Some((b, b.trust_score.as_ref()?))) {
+            scored += 1;
+            if score.hash_consensus > 0.0 {
+                known += 1;
+            }
+            let trust = score.total;
+            self.factors.worst_binary_trust = Some(worst(self.factors.worst_binary_trust, trust));
+
+            let mut penalty = 0.0;
+            let mut reasons = Vec::new();
+            let low_trust = trust < LOW_TRUST_THRESHOLD;
+            if low_trust {
+                self.factors.low_trust_binaries += 1;
+                penalty += self.weights.low_trust_binary * (1.0 - trust);
+                reasons.push(format!("trust {:.0}%", trust * 100.0));
+            }
+            let unvouched = score.provenance_score == 0.0 && score.hash_consensus == 0.0;
+            let privileged_unknown = unvouched && privileged.contains(bin.path.as_str());
+            if privileged_unknown {
+                self.factors.privileged_unknown_binaries += 1;
+                self.factors.worst_privileged_trust =
+                    Some(worst(self.factors.worst_privileged_trust, trust));
+                penalty += self.weights.privileged_unknown_binary;
+                reasons.push("runs as root, not from a package or known to the network".into());
+            }
+            if reasons.is_empty() {
+                continue;
+            }
+            self.concerns.push(Concern {
+                penalty,
+                severity: if privileged_unknown || trust < HIGH_RISK_THRESHOLD {
+                    Severity::High
+                } else {
+                    Severity::Medium
+                },
+                category: Category::Binary,
+                subject: Some(bin.path.clone()),
+                reason: format!("{}: {}", bin.path, reasons.join(", ")),
+            });
+        }
+
+        if !online || scored == 0 {
+            return;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let coverage = known as f64 / scored as f64;
+        self.factors.consensus_coverage = Some(coverage);
+        if coverage < 1.0 {
+            self.concerns.push(Concern {
+                penalty: self.weights.missing_coverage * (1.0 - coverage),
+                severity: Severity::Medium,
+                category: Category::Binary,
+                subject: None,
+                reason: format!(
+                    "The network knows only {known} of {scored} running binaries ({:.0}%)",
+                    coverage * 100.0
+                ),
+            });
+        }
+    }
+
+    /// Expired and unknown root certs.
+    fn certs(&mut self, snapshot: &AuditSnapshot) {
+        for cert in &snapshot.root_certs {
+            let unknown = cert.in_consensus == Some(false);
+            let mut penalty = 0.0;
+            let mut reasons = Vec::new();
+            if unknown {
+                self.factors.unknown_certs += 1;
+                penalty += self.weights.unknown_cert;
+                reasons.push("not in network consensus");
+            }
+            if cert.expired {
+                self.factors.expired_certs += 1;
+                penalty += self.weights.expired_cert;
+                reasons.push("expired");
+            }
+            if reasons.is_empty() {
+                continue;
+            }
+            self.concerns.push(Concern {
+                penalty,
+                severity: if unknown {
+                    Severity::Critical
+                } else {
+                    Severity::Medium
+                },
+                category: Category::Cert,
+                subject: Some(cert.fingerprint.clone()),
+                reason: format!("Root cert {}: {}", cert.subject, reasons.join(", ")),
+            });
+        }
+    }
+
+    /// Every finding the factors don't already account for, by severity.
+    fn findings(&mut self, report: &AuditReport) {
+        for finding in &report.findings {
+            if matches!(
+                finding.kind,
+                AnomalyKind::LowTrustBinary | AnomalyKind::ExpiredCert | AnomalyKind::UnknownCert
+            ) {
+                continue;
+            }
+            self.concerns.push(Concern {
+                penalty: self.weights.severity(finding.severity),
+                severity: finding.severity,
+                category: finding.category,
+                subject: finding.subject.clone(),
+                reason: finding.description.clone(),
+            });
+        }
+    }
+}
+
+fn worst(current: Option<f64>, trust: f64) -> f64 {
+    current.map_or(trust, |w| w.min(trust))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::tests::{binary, process, snapshot};
+
+    #[test]
+    fn grade_counts_every_factor() {
+        let mut snapshot = snapshot();
+        snapshot.processes.push(process(31337, "/tmp/.x/miner"));
+        let report = snapshot.report();
+
+        let risk = RiskReport::from_snapshot(&snapshot, &report, &RiskProfile::default(), false);
+        assert_eq!(
+            risk.factors,
+            RiskFactors {
+                low_trust_binaries: 2,
+                worst_binary_trust: Some(0.1),
+                privileged_unknown_binaries: 1,
+                worst_privileged_trust: Some(0.1),
+                expired_certs: 1,
+                unknown_certs: 1,
+                consensus_coverage: None,
+            }
+        );
+        // miner 3.6 + 10, agent 2.4, rogue root 15, old root 2, deleted exe 8
+        assert!((risk.penalty - 41.0).abs() < 1e-9);
+        assert_eq!(risk.grade, Grade::D);
+        assert!(risk.below(Grade::B));
+        assert!(!risk.below(Grade::D));
+
+        let subjects: Vec<_> = risk.top.iter().map(|c| c.subject.as_deref()).collect();
+        assert_eq!(
+            subjects,
+            [
+                Some("Rogue Root-fp"),
+                Some("/tmp/.x/miner"),
+                Some("4242"),
+                Some("/opt/app/agent"),
+                Some("Old Root-fp"),
+            ]
+        );
+        assert_eq!(
+            risk.top[1].reason,
+            "/tmp/.x/miner: trust 10%, runs as root, not from a package or known to the network"
+        );
+    }
+
+    #[test]
+    fn online_counts_coverage() {
+        let mut snapshot = snapshot();
+        snapshot.binaries.truncate(1);
+        snapshot.processes.truncate(1);
+        snapshot.root_certs.truncate(1);
+        snapshot.binaries.push(binary("/usr/bin/curl", true, 0.9));
+        snapshot.binaries[0]
+            .trust_score
+            .as_mut()
+            .unwrap()
+            .hash_consensus = 0.8;
+        let report = snapshot.report();
+
+        let offline = RiskReport::from_snapshot(&snapshot, &report, &RiskProfile::default(), false);
+        assert_eq!(offline.grade, Grade::A);
+        assert!(offline.top.is_empty());
+
+        let online = RiskReport::from_snapshot(&snapshot, &report, &RiskProfile::default(), true);
+        assert_eq!(online.factors.consensus_coverage, Some(0.5));
+        assert!((online.penalty - 10.0).abs() < 1e-9);
+        assert_eq!(online.grade, Grade::B);
+        assert_eq!(
+            online.top[0].reason,
+            "The network knows only 1 of 2 running binaries (50%)"
+        );
+    }
+
+    #[test]
+    fn profile_overrides_defaults() {
+        let profile: RiskProfile = serde_json::from_str(
+            r#"{"weights": {"unknown_cert": 100.0}, "thresholds": {"d": 200.0}}"#,
+        )
+        .unwrap();
+        assert!((profile.weights.high - RiskWeights::default().high).abs() < f64::EPSILON);
+        assert!(serde_json::from_str::<RiskProfile>(r#"{"weigths": {}}"#).is_err());
+
+        let snapshot = snapshot();
+        let risk = RiskReport::from_snapshot(&snapshot, &snapshot.report(), &profile, false);
+        assert_eq!(risk.grade, Grade::D);
+        assert!((risk.top[0].penalty - 100.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn grades_parse_and_order() {
+        assert_eq!("b".parse::<Grade>().unwrap(), Grade::B);
+        assert!("E".parse::<Grade>().is_err());
+        assert!(Grade::A < Grade::F);
+
+        let thresholds = GradeThresholds::default();
+        assert_eq!(thresholds.grade(0.0), Grade::A);
+        assert_eq!(thresholds.grade(5.0), Grade::A);
+        assert_eq!(thresholds.grade(5.5), Grade::B);
+        assert_eq!(thresholds.grade(61.0), Grade::F);
+        assert_eq!(serde_json::to_value(Grade::C).unwrap(), "C");
+    }
+}
@@ -1,23 +1,46 @@
 //! Multi-factor trust scoring for binaries.
 
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
 use std::process::Command;
 
+use crate::consensus::ConsensusResult;
 use crate::types::{BinaryInfo, TrustScore, TrustWeights};
 
+/// Node count at which the consensus factor saturates at 1.0.
+const CONSENSUS_SATURATION_NODES: f64 = 100.0;
+
+/// Multiplier applied to consensus answers served stale from cache.
+pub const STALE_CONSENSUS_DISCOUNT: f64 = 0.85;
+
+/// Attestation age (hours) at which a node's vouch counts in full.
+const ATTESTATION_MATURITY_HOURS: f64 = 30.0 * 24.0;
+
 /// Score a binary's trustworthiness using local factors.
 ///
-/// Network consensus factor (`hash_consensus`) is left at 0.0 until
-/// Phase 3 (consensus queries) fills it in.
+/// The network consensus factor (`hash_consensus`) is left at 0.0; use
+/// [`score_binary_with_consensus`] once Phase 3 has queried the hash.
 #[must_use]
 pub fn score_binary(binary: &BinaryInfo, weights: &TrustWeights) -> TrustScore {
+    score_binary_with_consensus(binary, None, weights)
+}
+
+/// Score a binary's trustworthiness using local factors and, when the
+/// network was asked about its hash, the consensus answer.
+///
+/// `hash_consensus` comes from [`consensus_factor`], so thin or clustered
+/// consensus counts for nothing; without an answer it is 0.0.
+#[must_use]
+pub fn score_binary_with_consensus(
+    binary: &BinaryInfo,
+    consensus: Option<&ConsensusResult>,
+    weights: &TrustWeights,
+) -> TrustScore {
     let age_factor = compute_age_factor(binary);
     let identity_stability = compute_identity_stability(binary);
     let usage_normality = compute_usage_normality(binary);
     let provenance_score = compute_provenance(binary);
-
-    // Consensus is 0.0 until network queries fill it in
-    let hash_consensus = 0.0;
+    let hash_consensus = consensus.map_or(0.0, |result| consensus_factor(result, weights));
 
     TrustScore::compute(
         hash_consensus,
@@ -29,6 +52,83 @@ pub fn score_binary(binary: &BinaryInfo, weights: &TrustWeights) -> TrustScore {
     )
 }
 
+/// Convert a consensus answer into the `hash_consensus` factor (0.0..1.0).
+///
+/// Log-scaled so the first handful of reports matter most, saturating at
+/// 100 nodes. Stale cached answers are discounted slightly.
+///
+/// Answers from fewer than `weights.min_consensus_nodes` nodes contribute
+/// nothing, so a few colluding nodes cannot vouch for a binary.
+///
+/// When the zone publishes per-node attestations, the raw count is
+/// replaced by a diversity-adjusted one: only established nodes count,
+/// each distinct source network counts once (weighted by how long it has
+/// vouched for the hash), and the result is scaled by the agreement ratio.
+#[must_use]
+pub fn consensus_factor(result: &ConsensusResult, weights: &TrustWeights) -> f64 {
+    if !result.found || result.node_count == 0 {
+        return 0.0;
+    }
+    if result.node_count < weights.min_consensus_nodes {
+        return 0.0;
+    }
+
+    let factor = if result.attestations.is_empty() {
+        saturating_count_factor(f64::from(result.node_count))
+    } else {
+        let networks = qualifying_networks(result, weights);
+        if networks.len() < weights.min_distinct_networks as usize {
+            return 0.0;
+        }
+        let effective: f64 = networks.values().sum();
+        saturating_count_factor(effective) * result.agreement_ratio()
+    };
+
+    if result.stale {
+        factor * STALE_CONSENSUS_DISCOUNT
+    } else {
+        factor
+    }
+}
+
+/// Number of distinct source networks among attestations from
+/// established nodes (trust digest older than `min_digest_age_hours`).
+///
+/// Attestations with no network share a single bucket, since their
+/// diversity can't be shown.
+#[must_use]
+pub fn qualifying_network_count(result: &ConsensusResult, weights: &TrustWeights) -> usize {
+    qualifying_networks(result, weights).len()
+}
+
+/// Log-scaled node count factor, saturating at 1.0.
+fn saturating_count_factor(nodes: f64) -> f64 {
+    (nodes.ln_1p() / CONSENSUS_SATURATION_NODES.ln_1p()).min(1.0)
+}
+
+/// Established attestations grouped by network, each weighted by its
+/// most mature attestation (0.0..1.0 by age of `first_seen`).
+#[allow(clippy::cast_precision_loss)]
+fn qualifying_networks<'a>(
+    result: &'a ConsensusResult,
+    weights: &TrustWeights,
+) -> HashMap<&'a str, f64> {
+    let now = Utc::now();
+    let min_digest_age = Duration::hours(i64::from(weights.min_digest_age_hours));
+
+    let mut networks: HashMap<&str, f64> = HashMap::new();
+    for att in &result.attestations {
+        if now - att.digest_at < min_digest_age {
+            continue;
+        }
+        let age_hours = (now - att.first_seen).num_hours().max(0) as f64;
+        let weight = (age_hours / ATTESTATION_MATURITY_HOURS).min(1.0);
+        let entry = networks.entry(att.network.as_deref().unwrap_or("")).or_default();
+        *entry = entry.max(weight);
+    }
+    networks
+}
+
 /// Age factor: sigmoid curve.
 ///
 /// - 0 days -> 0.0
@@ -65,6 +165,11 @@ fn compute_usage_normality(binary: &BinaryInfo) -> f64 {
     let in_system_path = binary.path.starts_with("/usr/")
         || binary.path.starts_with("/bin")
         || binary.path.starts_with("/sbin");
+    // Homebrew and app bundles are the normal install locations on macOS
+    #[cfg(target_os = "macos")]
+    let in_system_path = in_system_path
+        || binary.path.starts_with("/opt/homebrew/")
+        || crate::discovery::macos::is_app_bundle_path(&binary.path);
 
     if in_system_path {
         // System binaries are expected; running or not is fine
@@ -79,8 +184,13 @@ fn compute_usage_normality(binary: &BinaryInfo) -> f64 {
 
 /// Check if the binary is managed by a package manager.
 ///
-/// Checks dpkg, rpm, and pacman.
+/// Checks dpkg, rpm, and pacman; on macOS a valid code signature counts.
 fn compute_provenance(binary: &BinaryInfo) -> f64 {
+    #[cfg(target_os = "macos")]
+    if crate::discovery::macos::is_codesigned(&binary.path) {
+        return 1.0;
+    }
+
     // Try pacman (Arch)
     if is_pacman_owned(&binary.path) {
         return 1.0;
@@ -125,13 +235,14 @@ fn is_rpm_owned(path: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::FileIdentity;
-    use chrono::Duration;
+    use crate::hash::HashAlgorithm;
+    use crate::types::{Attestation, FileIdentity};
 
     fn make_binary(age_days: i64, running: bool, path: &str) -> BinaryInfo {
         BinaryInfo {
             path: path.to_string(),
-            sha256: "deadbeef".into(),
+            hash: "deadbeef".into(),
+            hash_algorithm: HashAlgorithm::Sha256,
             create_date: Utc::now() - Duration::days(age_days),
             modify_date: Utc::now() - Duration::days(age_days),
             identity: FileIdentity {
@@ -163,6 +274,120 @@ mod tests {
         assert!(f_old > 0.9, "old binary should have high age factor");
     }
 
+    fn make_consensus(node_count: u32, stale: bool) -> ConsensusResult {
+        ConsensusResult {
+            hash: "deadbeef".into(),
+            found: node_count > 0,
+            node_count,
+            network_trust: None,
+            total_nodes: None,
+            attestations: Vec::new(),
+            fetched_at: Utc::now(),
+            ttl_secs: 3600,
+            from_cache: stale,
+            stale,
+        }
+    }
+
+    #[test]
+    fn consensus_factor_scales_and_discounts_stale() {
+        let w = TrustWeights::default();
+        assert!(consensus_factor(&make_consensus(0, false), &w).abs() < f64::EPSILON);
+
+        let few = consensus_factor(&make_consensus(8, false), &w);
+        let many = consensus_factor(&make_consensus(150, false), &w);
+        assert!(few > 0.0 && few < many);
+        assert!((many - 1.0).abs() < f64::EPSILON);
+
+        let stale = consensus_factor(&make_consensus(150, true), &w);
+        assert!((stale - STALE_CONSENSUS_DISCOUNT).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn consensus_below_min_nodes_contributes_nothing() {
+        let w = TrustWeights {
+            min_consensus_nodes: 5,
+            ..TrustWeights::default()
+        };
+        // Two colluding nodes vouching for a binary count for nothing
+        assert!(consensus_factor(&make_consensus(2, false), &w).abs() < f64::EPSILON);
+        assert!(consensus_factor(&make_consensus(4, false), &w).abs() < f64::EPSILON);
+        assert!(consensus_factor(&make_consensus(5, false), &w) > 0.0);
+
+        let lax = TrustWeights {
+            min_consensus_nodes: 1,
+            ..TrustWeights::default()
+        };
+        assert!(consensus_factor(&make_consensus(2, false), &lax) > 0.0);
+    }
+
+    #[test]
+    fn score_ignores_consensus_below_min_nodes() {
+        let w = TrustWeights::default();
+        let bin = make_binary(90, true, "/usr/bin/sshd");
+        let offline = score_binary(&bin, &w);
+
+        let few = make_consensus(w.min_consensus_nodes - 1, false);
+        let score = score_binary_with_consensus(&bin, Some(&few), &w);
+        assert!(score.hash_consensus.abs() < f64::EPSILON);
+        assert!((score.total - offline.total).abs() < f64::EPSILON);
+
+        let enough = make_consensus(w.min_consensus_nodes, false);
+        let score = score_binary_with_consensus(&bin, Some(&enough), &w);
+        assert!(score.hash_consensus > 0.0);
+        assert!(score.total > offline.total);
+    }
+
+    fn make_attestation(network: &str, first_days: i64, digest_days: i64) -> Attestation {
+        Attestation {
+            node: format!("node-{network}-{first_days}"),
+            network: Some(network.into()),
+            first_seen: Utc::now() - Duration::days(first_days),
+            digest_at: Utc::now() - Duration::days(digest_days),
+        }
+    }
+
+    #[test]
+    fn clustered_attestations_contribute_nothing() {
+        let w = TrustWeights::default();
+        // 50 sock puppets, all in one /16
+        let mut result = make_consensus(50, false);
+        result.attestations = (0..50).map(|_| make_attestation("10.1", 90, 30)).collect();
+        assert!(consensus_factor(&result, &w).abs() < f64::EPSILON);
+        assert_eq!(qualifying_network_count(&result, &w), 1);
+    }
+
+    #[test]
+    fn new_nodes_are_ignored() {
+        let w = TrustWeights::default();
+        let mut result = make_consensus(6, false);
+        result.attestations = ["1.2", "3.4", "5.6", "7.8", "9.10", "11.12"]
+            .iter()
+            .map(|net| make_attestation(net, 90, 0))
+            .collect();
+        assert!(consensus_factor(&result, &w).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn diverse_mature_attestations_score_and_respect_agreement() {
+        let w = TrustWeights::default();
+        let nets = ["1.2", "3.4", "5.6", "7.8", "9.10", "11.12"];
+        let mut result = make_consensus(6, false);
+        result.attestations = nets.iter().map(|net| make_attestation(net, 90, 30)).collect();
+        let diverse = consensus_factor(&result, &w);
+        assert!(diverse > 0.0);
+
+        // Same nodes, but most reporters of this binary name disagree
+        result.total_nodes = Some(60);
+        let contested = consensus_factor(&result, &w);
+        assert!(contested < diverse * 0.2);
+
+        // Fresh attestations count for less than established ones
+        result.total_nodes = None;
+        result.attestations = nets.iter().map(|net| make_attestation(net, 2, 30)).collect();
+        assert!(consensus_factor(&result, &w) < diverse);
+    }
+
     #[test]
     fn usage_normality_system_path() {
         let running = make_binary(30, true, "/usr/bin/sshd");
@@ -72,6 +72,7 @@ fn is_known_issuer(issuer: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hash::HashAlgorithm;
     use chrono::Utc;
 
     fn make_cert(issuer: &str, expired: bool) -> RootCertInfo {
@@ -79,6 +80,7 @@ mod tests {
         RootCertInfo {
             path: "/etc/ssl/certs/test.pem".into(),
             fingerprint: "aabbccdd".into(),
+            fingerprint_algorithm: HashAlgorithm::Sha256,
             issuer: issuer.into(),
             subject: "Test CA".into(),
             serial: "01".into(),
@@ -91,6 +93,8 @@ mod tests {
             expired,
             in_consensus: None,
             trust_score: None,
+            findings: Vec::new(),
+            der: Vec::new(),
         }
     }
 
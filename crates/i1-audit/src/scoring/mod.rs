@@ -1,9 +1,13 @@
-//! Trust scoring for binaries and certificates.
+//! Trust scoring for binaries, certificates, and kernel modules.
 
 pub mod binary_trust;
 pub mod cert_trust;
+pub mod module_trust;
 pub mod weights;
 
-pub use binary_trust::score_binary;
+pub use binary_trust::{
+    consensus_factor, qualifying_network_count, score_binary, score_binary_with_consensus,
+};
 pub use cert_trust::score_cert;
+pub use module_trust::score_module;
 pub use weights::{offline_weights, paranoid_weights};
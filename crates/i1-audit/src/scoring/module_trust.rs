@@ -0,0 +1,96 @@
+//! Kernel module trust scoring.
+
+use crate::types::{KernelModule, ModuleOrigin, ModuleTrust};
+
+/// Penalty for a module the kernel could not verify a signature for.
+const UNSIGNED_PENALTY: f64 = 0.35;
+
+/// Penalty for a module built outside the kernel tree.
+const OUT_OF_TREE_PENALTY: f64 = 0.3;
+
+/// Penalty for a module with no backing file in the running kernel's module tree.
+const UNBACKED_PENALTY: f64 = 0.3;
+
+/// Penalty for a force-loaded module (version checks bypassed).
+const FORCED_PENALTY: f64 = 0.2;
+
+/// Penalty for a staging driver.
+const STAGING_PENALTY: f64 = 0.1;
+
+/// Score a loaded kernel module's trustworthiness.
+///
+/// Starts at full trust and subtracts for each red flag. An unsigned,
+/// out-of-tree module with no file in `/lib/modules` (the signature of
+/// an `insmod /tmp/foo.ko`) lands near zero. An unknown origin (module
+/// tree unavailable) is not penalized -- absence of evidence is not evidence.
+#[must_use]
+pub fn score_module(module: &KernelModule) -> ModuleTrust {
+    let taint = module.taint;
+    let backed_by_file = module.origin != ModuleOrigin::NotInModuleTree;
+
+    let mut score: f64 = 1.0;
+    if taint.unsigned {
+        score -= UNSIGNED_PENALTY;
+    }
+    if taint.out_of_tree {
+        score -= OUT_OF_TREE_PENALTY;
+    }
+    if !backed_by_file {
+        score -= UNBACKED_PENALTY;
+    }
+    if taint.forced {
+        score -= FORCED_PENALTY;
+    }
+    if taint.staging {
+        score -= STAGING_PENALTY;
+    }
+
+    ModuleTrust {
+        score: score.clamp(0.0, 1.0),
+        signed: !taint.unsigned,
+        in_tree: !taint.out_of_tree,
+        backed_by_file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ModuleTaint;
+
+    fn make_module(taint: &str, origin: ModuleOrigin) -> KernelModule {
+        KernelModule {
+            name: "test".into(),
+            size: 16384,
+            ref_count: 0,
+            used_by: Vec::new(),
+            state: "Live".into(),
+            taint: ModuleTaint::parse(taint),
+            ko_path: None,
+            sha256: None,
+            origin,
+            trust: None,
+        }
+    }
+
+    #[test]
+    fn clean_in_tree_module_fully_trusted() {
+        let trust = score_module(&make_module("", ModuleOrigin::ModuleTree));
+        assert!((trust.score - 1.0).abs() < f64::EPSILON);
+        assert!(trust.signed && trust.in_tree && trust.backed_by_file);
+    }
+
+    #[test]
+    fn unsigned_out_of_tree_from_tmp_near_zero() {
+        let trust = score_module(&make_module("OE", ModuleOrigin::NotInModuleTree));
+        assert!(trust.score < 0.1);
+        assert!(!trust.signed && !trust.in_tree && !trust.backed_by_file);
+    }
+
+    #[test]
+    fn unknown_origin_not_penalized() {
+        let unknown = score_module(&make_module("", ModuleOrigin::Unknown));
+        assert!(unknown.backed_by_file);
+        assert!((unknown.score - 1.0).abs() < f64::EPSILON);
+    }
+}
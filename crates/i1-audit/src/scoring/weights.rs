@@ -1,6 +1,9 @@
 //! Trust weight presets.
 
-use crate::types::TrustWeights;
+use crate::types::{
+    TrustWeights, DEFAULT_MIN_CONSENSUS_NODES, DEFAULT_MIN_DIGEST_AGE_HOURS,
+    DEFAULT_MIN_DISTINCT_NETWORKS,
+};
 
 /// Offline-only weights: no network consensus available, redistribute its weight.
 ///
@@ -13,10 +16,17 @@ pub const fn offline_weights() -> TrustWeights {
         identity_stability: 0.25,
         usage_normality: 0.25,
         provenance_score: 0.20,
+        min_consensus_nodes: DEFAULT_MIN_CONSENSUS_NODES,
+        min_distinct_networks: DEFAULT_MIN_DISTINCT_NETWORKS,
+        min_digest_age_hours: DEFAULT_MIN_DIGEST_AGE_HOURS,
     }
 }
 
 /// Paranoid weights: consensus matters most, age matters least.
+///
+/// Raises the minimum node count, network diversity, and node age, since
+/// the heavier consensus weight makes a small colluding group more
+/// valuable to an attacker.
 #[must_use]
 pub const fn paranoid_weights() -> TrustWeights {
     TrustWeights {
@@ -25,5 +35,8 @@ pub const fn paranoid_weights() -> TrustWeights {
         identity_stability: 0.15,
         usage_normality: 0.10,
         provenance_score: 0.15,
+        min_consensus_nodes: DEFAULT_MIN_CONSENSUS_NODES * 2,
+        min_distinct_networks: DEFAULT_MIN_DISTINCT_NETWORKS * 2,
+        min_digest_age_hours: DEFAULT_MIN_DIGEST_AGE_HOURS * 3,
     }
 }
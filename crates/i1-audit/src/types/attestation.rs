@@ -0,0 +1,68 @@
+//! Per-node consensus attestation types.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::hash::sha256_bytes;
+
+/// Length of the node prefix published in attestations (hex chars).
+const NODE_PREFIX_LEN: usize = 12;
+
+/// One node vouching for a hash in the consensus zone.
+///
+/// Raw node counts are easy to inflate with sock-puppet nodes, so the
+/// zone may carry one attestation per reporting node describing where
+/// the vouch came from and how established the node is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    /// Hashed node identifier prefix (never the raw machine-id)
+    pub node: String,
+    /// Source network bucket (IPv4 /16 or IPv6 /32), if known
+    pub network: Option<String>,
+    /// When this node first reported the hash
+    pub first_seen: DateTime<Utc>,
+    /// When this node's own trust digest was published
+    pub digest_at: DateTime<Utc>,
+}
+
+impl Attestation {
+    /// Stable, non-reversible node prefix for a node identifier.
+    #[must_use]
+    pub fn node_prefix(node_id: &str) -> String {
+        sha256_bytes(node_id.as_bytes())[..NODE_PREFIX_LEN].to_string()
+    }
+
+    /// Network diversity bucket for an address: IPv4 /16 or IPv6 /32.
+    #[must_use]
+    pub fn network_of(ip: IpAddr) -> String {
+        match ip {
+            IpAddr::V4(v4) => {
+                let o = v4.octets();
+                format!("{}.{}", o[0], o[1])
+            }
+            IpAddr::V6(v6) => {
+                let s = v6.segments();
+                format!("{:x}:{:x}", s[0], s[1])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_buckets() {
+        assert_eq!(Attestation::network_of("203.0.113.7".parse().unwrap()), "203.0");
+        assert_eq!(Attestation::network_of("2001:db8::1".parse().unwrap()), "2001:db8");
+    }
+
+    #[test]
+    fn node_prefix_hides_node_id() {
+        let prefix = Attestation::node_prefix("0123456789abcdef0123456789abcdef");
+        assert_eq!(prefix.len(), NODE_PREFIX_LEN);
+        assert!(!"0123456789abcdef0123456789abcdef".starts_with(&prefix));
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use super::trust::TrustScore;
+use crate::hash::HashAlgorithm;
 
 /// Unique file identity on disk (inode + device).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -17,8 +18,12 @@ pub struct FileIdentity {
 pub struct BinaryInfo {
     /// Absolute path on disk
     pub path: String,
-    /// SHA-256 hex digest
-    pub sha256: String,
+    /// Hex digest of the file contents, computed with `hash_algorithm`
+    #[serde(alias = "sha256")]
+    pub hash: String,
+    /// Algorithm behind `hash` (snapshots predating the field are SHA-256)
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
     /// File creation time (statx btime, fallback to mtime)
     pub create_date: DateTime<Utc>,
     /// Last modification time
@@ -3,7 +3,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// SHA-256 fingerprint of a certificate's DER encoding.
+use crate::consensus::Anomaly;
+use crate::hash::HashAlgorithm;
+
+/// Hex fingerprint of a certificate's DER encoding.
 pub type CertFingerprint = String;
 
 /// Trust assessment for a root certificate.
@@ -24,8 +27,11 @@ pub struct CertTrust {
 pub struct RootCertInfo {
     /// Path to the file containing this cert
     pub path: String,
-    /// SHA-256 fingerprint of DER bytes (hex)
+    /// Fingerprint of DER bytes (hex), computed with `fingerprint_algorithm`
     pub fingerprint: CertFingerprint,
+    /// Algorithm behind `fingerprint` (snapshots predating the field are SHA-256)
+    #[serde(default)]
+    pub fingerprint_algorithm: HashAlgorithm,
     /// Issuer distinguished name (human-readable)
     pub issuer: String,
     /// Subject distinguished name (human-readable)
@@ -42,4 +48,10 @@ pub struct RootCertInfo {
     pub in_consensus: Option<bool>,
     /// Trust score (None until scored)
     pub trust_score: Option<CertTrust>,
+    /// Signature, key and policy findings (empty until validated)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Anomaly>,
+    /// DER encoding, kept for validation (not serialized)
+    #[serde(skip)]
+    pub der: Vec<u8>,
 }
@@ -1,13 +1,20 @@
 //! Core types for the audit system.
 
+pub mod attestation;
 pub mod binary;
 pub mod cert;
+pub mod module;
 pub mod process;
 pub mod snapshot;
 pub mod trust;
 
+pub use attestation::Attestation;
 pub use binary::{BinaryInfo, FileIdentity};
 pub use cert::{CertFingerprint, CertTrust, RootCertInfo};
+pub use module::{KernelModule, ModuleOrigin, ModuleTaint, ModuleTrust};
 pub use process::{ProcessInfo, UsageMetric};
 pub use snapshot::{AuditSnapshot, AuditSummary};
-pub use trust::{TrustScore, TrustWeights};
+pub use trust::{
+    TrustScore, TrustWeights, DEFAULT_MIN_CONSENSUS_NODES, DEFAULT_MIN_DIGEST_AGE_HOURS,
+    DEFAULT_MIN_DISTINCT_NETWORKS,
+};
@@ -0,0 +1,123 @@
+//! Loaded kernel module types.
+
+use serde::{Deserialize, Serialize};
+
+/// Kernel taint flags reported for a single module.
+///
+/// Parsed from the `(OE)`-style suffix in `/proc/modules` and
+/// `/sys/module/<name>/taint`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ModuleTaint {
+    /// `O` -- built outside the kernel tree
+    pub out_of_tree: bool,
+    /// `E` -- unsigned module loaded into a kernel that supports signing
+    pub unsigned: bool,
+    /// `P` -- proprietary (non-GPL) license
+    pub proprietary: bool,
+    /// `C` -- staging driver
+    pub staging: bool,
+    /// `F` -- force-loaded (version magic mismatch ignored)
+    pub forced: bool,
+}
+
+impl ModuleTaint {
+    /// Parse taint flag letters (e.g., `"OE"` or `"(POE)"`).
+    #[must_use]
+    pub fn parse(flags: &str) -> Self {
+        let mut taint = Self::default();
+        for c in flags.chars() {
+            match c {
+                'O' => taint.out_of_tree = true,
+                'E' => taint.unsigned = true,
+                'P' => taint.proprietary = true,
+                'C' => taint.staging = true,
+                'F' => taint.forced = true,
+                _ => {}
+            }
+        }
+        taint
+    }
+
+    /// Combine flags from two sources.
+    #[must_use]
+    pub const fn merge(self, other: Self) -> Self {
+        Self {
+            out_of_tree: self.out_of_tree || other.out_of_tree,
+            unsigned: self.unsigned || other.unsigned,
+            proprietary: self.proprietary || other.proprietary,
+            staging: self.staging || other.staging,
+            forced: self.forced || other.forced,
+        }
+    }
+
+    /// Whether any taint flag is set.
+    #[must_use]
+    pub const fn any(self) -> bool {
+        self.out_of_tree || self.unsigned || self.proprietary || self.staging || self.forced
+    }
+}
+
+/// Where a loaded module's object file was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleOrigin {
+    /// Found under `/lib/modules/$(uname -r)`
+    ModuleTree,
+    /// Module directory exists but has no `.ko` for this module
+    /// (loaded with `insmod` from elsewhere, or the file was removed)
+    NotInModuleTree,
+    /// Module directory unavailable (containers, restricted hosts)
+    Unknown,
+}
+
+/// Trust assessment for a loaded kernel module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleTrust {
+    /// Overall trust 0.0..1.0
+    pub score: f64,
+    /// Module is signed (no `E` taint)
+    pub signed: bool,
+    /// Module was built in-tree (no `O` taint)
+    pub in_tree: bool,
+    /// Module is backed by a file in the running kernel's module tree
+    pub backed_by_file: bool,
+}
+
+/// A loaded kernel module.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelModule {
+    /// Module name as shown in `/proc/modules`
+    pub name: String,
+    /// Memory size in bytes
+    pub size: u64,
+    /// Reference count
+    pub ref_count: u32,
+    /// Modules that depend on this one
+    pub used_by: Vec<String>,
+    /// Load state (`Live`, `Loading`, `Unloading`)
+    pub state: String,
+    /// Taint flags
+    pub taint: ModuleTaint,
+    /// Path of the backing `.ko` file, if found
+    pub ko_path: Option<String>,
+    /// SHA-256 of the backing `.ko` file, if found
+    pub sha256: Option<String>,
+    /// Where the backing file was found
+    pub origin: ModuleOrigin,
+    /// Trust assessment (None until scored)
+    pub trust: Option<ModuleTrust>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_taint_flags() {
+        let t = ModuleTaint::parse("(POE)");
+        assert!(t.proprietary && t.out_of_tree && t.unsigned);
+        assert!(!t.forced && !t.staging);
+        assert!(!ModuleTaint::parse("").any());
+    }
+}
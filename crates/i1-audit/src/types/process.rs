@@ -11,6 +11,10 @@ pub struct ProcessInfo {
     pub name: String,
     /// Path to the executable binary
     pub exe_path: Option<String>,
+    /// The executable is gone from disk: deleted or replaced after the
+    /// process started
+    #[serde(default)]
+    pub exe_missing: bool,
     /// Command line arguments
     pub cmdline: Vec<String>,
     /// User ID running the process
@@ -5,7 +5,9 @@ use serde::{Deserialize, Serialize};
 
 use super::binary::BinaryInfo;
 use super::cert::RootCertInfo;
+use super::module::KernelModule;
 use super::process::ProcessInfo;
+use crate::consensus::Severity;
 
 /// Complete audit snapshot of a system.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +26,13 @@ pub struct AuditSnapshot {
     pub processes: Vec<ProcessInfo>,
     /// Root certificates
     pub root_certs: Vec<RootCertInfo>,
+    /// Loaded kernel modules (empty on non-Linux or older snapshots)
+    #[serde(default)]
+    pub kernel_modules: Vec<KernelModule>,
+    /// Shared libraries mapped by running processes (only collected on
+    /// request, see [`crate::collect_shared_libraries`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_libraries: Vec<BinaryInfo>,
     /// Summary statistics
     pub summary: AuditSummary,
 }
@@ -45,6 +54,12 @@ pub struct AuditSummary {
     pub low_trust_binaries: usize,
     /// Certs not found in consensus
     pub unknown_certs: usize,
+    /// Certs with a critical validation finding
+    #[serde(default)]
+    pub critical_certs: usize,
+    /// Kernel modules with trust score below threshold
+    #[serde(default)]
+    pub low_trust_modules: usize,
 }
 
 impl AuditSummary {
@@ -71,6 +86,21 @@ impl AuditSummary {
                 })
                 .count(),
             unknown_certs: certs.iter().filter(|c| c.in_consensus == Some(false)).count(),
+            critical_certs: certs
+                .iter()
+                .filter(|c| c.findings.iter().any(|f| f.severity == Severity::Critical))
+                .count(),
+            low_trust_modules: 0,
         }
     }
+
+    /// Count kernel modules below the trust threshold.
+    #[must_use]
+    pub fn with_modules(mut self, modules: &[KernelModule], trust_threshold: f64) -> Self {
+        self.low_trust_modules = modules
+            .iter()
+            .filter(|m| m.trust.as_ref().is_some_and(|t| t.score < trust_threshold))
+            .count();
+        self
+    }
 }
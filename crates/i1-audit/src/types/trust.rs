@@ -47,6 +47,15 @@ impl TrustScore {
     }
 }
 
+/// Default minimum number of agreeing nodes before consensus counts.
+pub const DEFAULT_MIN_CONSENSUS_NODES: u32 = 5;
+
+/// Default minimum distinct source networks among attestations.
+pub const DEFAULT_MIN_DISTINCT_NETWORKS: u32 = 3;
+
+/// Default minimum age (hours) of an attesting node's trust digest.
+pub const DEFAULT_MIN_DIGEST_AGE_HOURS: u32 = 24;
+
 /// Configurable weights for trust score factors.
 ///
 /// All weights should sum to 1.0.
@@ -62,6 +71,40 @@ pub struct TrustWeights {
     pub usage_normality: f64,
     /// Package manager provenance
     pub provenance_score: f64,
+    /// Minimum agreeing nodes before `hash_consensus` contributes at all.
+    ///
+    /// Consensus is the heaviest factor, which makes it the obvious target:
+    /// an attacker who stands up a couple of nodes (a Sybil attack) could
+    /// otherwise vouch for their own malicious binary and inflate its
+    /// trust. Below this threshold consensus contributes zero, so faking
+    /// trust requires controlling a meaningful slice of the network.
+    #[serde(default = "default_min_consensus_nodes")]
+    pub min_consensus_nodes: u32,
+    /// Minimum distinct /16 networks among attesting nodes.
+    ///
+    /// Node counts are cheap to inflate from one botnet or cloud account;
+    /// network diversity is not. Only applies when the zone publishes
+    /// per-node attestations.
+    #[serde(default = "default_min_distinct_networks")]
+    pub min_distinct_networks: u32,
+    /// Minimum age (hours) of an attesting node's own trust digest.
+    ///
+    /// Nodes that only just appeared are ignored, so a freshly spun-up
+    /// fleet can't vouch for anything until it has been around a while.
+    #[serde(default = "default_min_digest_age_hours")]
+    pub min_digest_age_hours: u32,
+}
+
+const fn default_min_consensus_nodes() -> u32 {
+    DEFAULT_MIN_CONSENSUS_NODES
+}
+
+const fn default_min_distinct_networks() -> u32 {
+    DEFAULT_MIN_DISTINCT_NETWORKS
+}
+
+const fn default_min_digest_age_hours() -> u32 {
+    DEFAULT_MIN_DIGEST_AGE_HOURS
 }
 
 impl Default for TrustWeights {
@@ -72,6 +115,9 @@ impl Default for TrustWeights {
             identity_stability: 0.15,
             usage_normality: 0.15,
             provenance_score: 0.15,
+            min_consensus_nodes: DEFAULT_MIN_CONSENSUS_NODES,
+            min_distinct_networks: DEFAULT_MIN_DISTINCT_NETWORKS,
+            min_digest_age_hours: DEFAULT_MIN_DIGEST_AGE_HOURS,
         }
     }
 }
@@ -98,4 +144,13 @@ mod tests {
         let s = TrustScore::compute(0.0, 0.0, 0.0, 0.0, 0.0, &w);
         assert!((s.total).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn min_consensus_nodes_defaults_when_missing() {
+        let json = r#"{"hash_consensus":0.4,"age_factor":0.15,"identity_stability":0.15,"usage_normality":0.15,"provenance_score":0.15}"#;
+        let w: TrustWeights = serde_json::from_str(json).unwrap();
+        assert_eq!(w.min_consensus_nodes, DEFAULT_MIN_CONSENSUS_NODES);
+        assert_eq!(w.min_distinct_networks, DEFAULT_MIN_DISTINCT_NETWORKS);
+        assert_eq!(w.min_digest_age_hours, DEFAULT_MIN_DIGEST_AGE_HOURS);
+    }
 }
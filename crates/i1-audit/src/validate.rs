@@ -0,0 +1,343 @@
+//! Root certificate validation -- signature, key and policy checks.
+//!
+//! Consensus says whether other nodes trust a root; validation says whether
+//! the root is sound on its own. Each cert's self-signature is checked, its
+//! key and signature digest are held to current baseline requirements, and
+//! the store is searched for subjects that appear under more than one key.
+//! Findings are attached to each [`RootCertInfo`] as [`Anomaly`]s, so they
+//! flow into the summary, JSON output and risk report like any other.
+
+use std::collections::HashMap;
+
+use x509_parser::certificate::X509Certificate;
+use x509_parser::error::X509Error;
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::public_key::PublicKey;
+
+use crate::consensus::{Anomaly, AnomalyKind, Severity};
+use crate::hash::{hash_bytes, HashAlgorithm};
+use crate::types::RootCertInfo;
+
+/// RSA keys shorter than this are flagged (CA/B Forum minimum).
+pub const MIN_RSA_BITS: usize = 2048;
+
+/// RSA keys shorter than this are trivially factorable.
+const BROKEN_RSA_BITS: usize = 1024;
+
+/// EC keys shorter than this are flagged.
+const MIN_EC_BITS: usize = 256;
+
+/// Roots valid for longer than this are suspicious.
+pub const MAX_VALIDITY_YEARS: i64 = 30;
+
+/// Validate every cert in `certs`, replacing their findings.
+///
+/// Certs without DER (e.g. loaded from an older snapshot) are skipped.
+pub fn validate_roots(certs: &mut [RootCertInfo]) {
+    let mut keys: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+    for (i, cert) in certs.iter_mut().enumerate() {
+        if cert.der.is_empty() {
+            continue;
+        }
+        let Ok((_, parsed)) = x509_parser::parse_x509_certificate(&cert.der) else {
+            continue;
+        };
+        cert.findings = check_cert(&parsed, &cert.subject);
+        let spki = hash_bytes(parsed.public_key().raw, HashAlgorithm::Sha256);
+        keys.entry(cert.subject.clone())
+            .or_default()
+            .push((i, spki));
+    }
+
+    for (subject, holders) in keys {
+        for (i, spki) in &holders {
+            let others = holders.iter().filter(|(_, other)| other != spki).count();
+            if others == 0 {
+                continue;
+            }
+            certs[*i].findings.push(Anomaly {
+                kind: AnomalyKind::CertKeyCollision,
+                severity: Severity::High,
+                description: format!(
+                    "Root cert subject shared with {others} cert(s) under a different key: {subject} (key {})",
+                    &spki[..16]
+                ),
+            });
+        }
+    }
+}
+
+/// Severity of the worst finding across `certs`.
+#[must_use]
+pub fn highest(certs: &[RootCertInfo]) -> Option<Severity> {
+    certs
+        .iter()
+        .flat_map(|c| &c.findings)
+        .map(|f| f.severity)
+        .max()
+}
+
+/// Per-cert checks: self-signature, key strength, digest, validity period.
+fn check_cert(cert: &X509Certificate<'_>, subject: &str) -> Vec<Anomaly> {
+    let mut findings = Vec::new();
+    let mut push = |kind, severity, description: String| {
+        findings.push(Anomaly {
+            kind,
+            severity,
+            description,
+        });
+    };
+
+    let key = cert.public_key().parsed();
+    let rsa_bits = match &key {
+        Ok(PublicKey::RSA(rsa)) => Some(modulus_bits(rsa.modulus)),
+        _ => None,
+    };
+    let digest = signature_name(cert);
+    let sha1 = digest.contains("sha1");
+
+    if cert.issuer().as_raw() == cert.subject().as_raw() {
+        match cert.verify_signature(None) {
+            Ok(()) => {}
+            // ring only checks SHA-2 RSA signatures from 2048-bit keys up, and
+            // a small key is reported below anyway.
+            Err(X509Error::SignatureVerificationError)
+                if rsa_bits.is_some_and(|bits| bits < MIN_RSA_BITS) && !sha1 => {}
+            Err(X509Error::SignatureVerificationError) => push(
+                AnomalyKind::BadCertSignature,
+                Severity::Critical,
+                format!("Root cert self-signature does not verify: {subject}"),
+            ),
+            Err(_) => push(
+                AnomalyKind::BadCertSignature,
+                Severity::Info,
+                format!("Root cert self-signature ({digest}) not checked: {subject}"),
+            ),
+        }
+    } else {
+        push(
+            AnomalyKind::BadCertSignature,
+            Severity::Medium,
+            format!(
+                "Root store cert is not self-issued: {subject} (issuer={})",
+                cert.issuer()
+            ),
+        );
+    }
+
+    match (&key, rsa_bits) {
+        (_, Some(bits)) if bits < BROKEN_RSA_BITS => push(
+            AnomalyKind::WeakCertKey,
+            Severity::Critical,
+            format!("Root cert has a {bits}-bit RSA key: {subject}"),
+        ),
+        (_, Some(bits)) if bits < MIN_RSA_BITS => push(
+            AnomalyKind::WeakCertKey,
+            Severity::High,
+            format!("Root cert has a {bits}-bit RSA key (minimum {MIN_RSA_BITS}): {subject}"),
+        ),
+        (Ok(PublicKey::EC(ec)), _) if ec.key_size() < MIN_EC_BITS => push(
+            AnomalyKind::WeakCertKey,
+            Severity::High,
+            format!("Root cert has a {}-bit EC key: {subject}", ec.key_size()),
+        ),
+        (Ok(PublicKey::DSA(_)), _) => push(
+            AnomalyKind::WeakCertKey,
+            Severity::Medium,
+            format!("Root cert has a DSA key: {subject}"),
+        ),
+        (Err(_), _) => push(
+            AnomalyKind::WeakCertKey,
+            Severity::High,
+            format!("Root cert public key does not parse: {subject}"),
+        ),
+        _ => {}
+    }
+
+    if digest.contains("md2") || digest.contains("md5") {
+        push(
+            AnomalyKind::WeakCertSignature,
+            Severity::High,
+            format!("Root cert signed with {digest}: {subject}"),
+        );
+    } else if sha1 {
+        // Many trusted roots still carry SHA-1 self-signatures, which nothing
+        // relies on for a trust anchor.
+        push(
+            AnomalyKind::WeakCertSignature,
+            Severity::Low,
+            format!("Root cert signed with SHA-1 ({digest}): {subject}"),
+        );
+    }
+
+    let validity = cert.validity();
+    let years =
+        (validity.not_after.timestamp() - validity.not_before.timestamp()) / (365 * 24 * 60 * 60);
+    if years > MAX_VALIDITY_YEARS {
+        push(
+            AnomalyKind::LongCertValidity,
+            Severity::Medium,
+            format!("Root cert valid for {years} years (over {MAX_VALIDITY_YEARS}): {subject}"),
+        );
+    }
+
+    findings
+}
+
+/// Short name of the signature algorithm, lowercased (`sha1withrsaencryption`).
+fn signature_name(cert: &X509Certificate<'_>) -> String {
+    let oid = &cert.signature_algorithm.algorithm;
+    oid2sn(oid, oid_registry())
+        .map_or_else(|_| oid.to_id_string(), ToString::to_string)
+        .to_lowercase()
+}
+
+/// Bit length of a big-endian RSA modulus.
+fn modulus_bits(modulus: &[u8]) -> usize {
+    let Some(start) = modulus.iter().position(|&b| b != 0) else {
+        return 0;
+    };
+    (modulus.len() - start) * 8 - modulus[start].leading_zeros() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::certs::parse_x509_der;
+    use crate::types::AuditSummary;
+    use rcgen::{
+        date_time_ymd, BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair,
+    };
+
+    /// A 1024-bit RSA root self-signed with SHA-1, as `openssl req -x509
+    /// -newkey rsa:1024 -sha1` makes.
+    const WEAK_LEGACY_ROOT: &str = "-----BEGIN CERTIFICATE-----
+MIICEjCCAXugAwIBAgIUCsI0FZ9Yn4+U4qxTuRnjpSaTkr4wDQYJKoZIhvcNAQEF
+BQAwGzEZMBcGA1UEAwwQV2VhayBMZWdhY3kgUm9vdDAeFw0yNjEwMTgxMDEzMzJa
+Fw0zNjEwMTUxMDEzMzJaMBsxGTAXBgNVBAMMEFdlYWsgTGVnYWN5IFJvb3QwgZ8w
+DQYJKoZIhvcNAQEBBQADgY0AMIGJAoGBAOFVGvB2HnuHRNO5u0JsnXk5I+LlnY1u
+YsQAbqeI/IsBmO+SZcm6epS76FMlAUO3HywbOYAGOgxxgQzzCV+V4aq0SRXKzwDM
+inkO56WGVj7wqH+TdYCKiQ97VLoB6DW9kwFQIjUrPLs54EuOdmZ3cNzaPZ0+CPNx
+bePzSN/tDAy1AgMBAAGjUzBRMB0GA1UdDgQWBBR6YbiV21v8IBah/xHCPu9larXk
+ijAfBgNVHSMEGDAWgBR6YbiV21v8IBah/xHCPu9larXkijAPBgNVHRMBAf8EBTAD
+AQH/MA0GCSqGSIb3DQEBBQUAA4GBAAHDxR67zv4dZdGVQi1/EFlv+7lDeB/6uBj4
+SHGGTYEFaohum9qZbCW+LLpCTFH9qXvAc+7QlvB3uNF4Ja4tx3nm7wZnoS5+IjCl
+uwXFSwPt+/WJ6Gc20z58mMLvb66F+uEGLVcf85Q9INZdtaB+NeJb5L8KBILhFTJq
+vCnMuuOb
+-----END CERTIFICATE-----
+";
+
+    fn params(cn: &str, years: i32) -> CertificateParams {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.distinguished_name.push(DnType::CommonName, cn);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.not_before = date_time_ymd(2020, 1, 1);
+        params.not_after = date_time_ymd(2020 + years, 1, 1);
+        params
+    }
+
+    fn root(cn: &str, years: i32) -> (Certificate, KeyPair) {
+        let key = KeyPair::generate().unwrap();
+        (params(cn, years).self_signed(&key).unwrap(), key)
+    }
+
+    fn info(der: &[u8]) -> RootCertInfo {
+        parse_x509_der(der, "test.pem", HashAlgorithm::Sha256).unwrap()
+    }
+
+    fn kinds(cert: &RootCertInfo) -> Vec<(AnomalyKind, Severity)> {
+        cert.findings.iter().map(|f| (f.kind, f.severity)).collect()
+    }
+
+    #[test]
+    fn sound_root_has_no_findings() {
+        let mut certs = vec![info(root("Sound Root", 20).0.der())];
+        validate_roots(&mut certs);
+        assert!(certs[0].findings.is_empty(), "{:?}", certs[0].findings);
+        assert_eq!(highest(&certs), None);
+    }
+
+    #[test]
+    fn tampered_and_long_lived_roots() {
+        let mut tampered = root("Tampered Root", 20).0.der().to_vec();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let mut certs = vec![info(&tampered), info(root("Forever Root", 50).0.der())];
+        validate_roots(&mut certs);
+
+        assert_eq!(
+            kinds(&certs[0]),
+            [(AnomalyKind::BadCertSignature, Severity::Critical)]
+        );
+        assert_eq!(
+            kinds(&certs[1]),
+            [(AnomalyKind::LongCertValidity, Severity::Medium)]
+        );
+        assert_eq!(highest(&certs), Some(Severity::Critical));
+
+        let summary = AuditSummary::from_snapshot(&[], &[], &certs, 0.5);
+        assert_eq!(summary.critical_certs, 1);
+
+        // Findings reach the JSON output, and a cert without any leaves none.
+        let json = serde_json::to_value(&certs).unwrap();
+        assert_eq!(json[0]["findings"][0]["kind"], "bad_cert_signature");
+        assert!(json[1]["der"].is_null());
+        let clean = serde_json::to_value(info(root("Sound Root", 20).0.der())).unwrap();
+        assert!(clean.get("findings").is_none());
+    }
+
+    #[test]
+    fn weak_legacy_root() {
+        let der = pem::parse(WEAK_LEGACY_ROOT).unwrap();
+        let mut certs = vec![info(der.contents())];
+        validate_roots(&mut certs);
+        // The SHA-1 self-signature still verifies; the key and digest don't pass.
+        assert_eq!(
+            kinds(&certs[0]),
+            [
+                (AnomalyKind::WeakCertKey, Severity::High),
+                (AnomalyKind::WeakCertSignature, Severity::Low),
+            ]
+        );
+        assert!(certs[0].findings[0].description.contains("1024-bit RSA"));
+        assert_eq!(modulus_bits(&[0x00, 0x80, 0x00]), 16);
+        assert_eq!(modulus_bits(&[0x01, 0xff]), 9);
+    }
+
+    #[test]
+    fn subject_key_collisions_and_intermediates() {
+        let (original, original_key) = root("Shared Name Root", 20);
+        let (impostor, _) = root("Shared Name Root", 20);
+        // Same subject and key, new validity: a legitimate re-issue.
+        let mut reissue_params = params("Shared Name Root", 25);
+        reissue_params.serial_number = Some(7u64.into());
+        let reissue = reissue_params.self_signed(&original_key).unwrap();
+
+        let leaf_key = KeyPair::generate().unwrap();
+        let intermediate = params("Some Intermediate", 5)
+            .signed_by(&leaf_key, &original, &original_key)
+            .unwrap();
+
+        let mut certs = vec![
+            info(original.der()),
+            info(reissue.der()),
+            info(impostor.der()),
+            info(intermediate.der()),
+        ];
+        validate_roots(&mut certs);
+
+        for cert in &certs[..3] {
+            assert_eq!(
+                kinds(cert),
+                [(AnomalyKind::CertKeyCollision, Severity::High)],
+                "{}",
+                cert.fingerprint
+            );
+        }
+        assert!(certs[2].findings[0].description.contains("2 cert(s)"));
+        assert_eq!(
+            kinds(&certs[3]),
+            [(AnomalyKind::BadCertSignature, Severity::Medium)]
+        );
+    }
+}
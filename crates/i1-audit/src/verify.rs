@@ -15,13 +15,47 @@
 //! replaying the record. If the value differs, the record was tampered.
 //! The phone's cell network provides a trust path independent of your
 //! local network.
+//!
+//! ## Signed digests
+//!
+//! Anyone can compute a digest for a snapshot they made up, so the digest
+//! alone doesn't prove which node produced it. A node can sign the signal
+//! record with its i1-ca key ([`generate_verify_token_signed`]): the TXT
+//! value gains a final `;sig=<base64url>` field, an ECDSA P-256 signature
+//! over everything before it, and the verification URL carries the same
+//! signature. Holders of the node's public key check it with
+//! [`verify_token_signed`] or [`verify_signal_txt`].
+//!
+//! ## Compact URLs
+//!
+//! The full URL (64-char digest, timestamp, maybe a signature) makes a
+//! dense QR code. [`VerifyToken::compact_url`] carries only the first
+//! [`COMPACT_DIGEST_LEN`] digest characters: the node prefix is the start
+//! of the digest, so the verify page resolves the signal record and reads
+//! the full digest, timestamp and signature from DNS, then checks the
+//! digest starts with the URL's ([`CompactRef::matches`]).
+//!
+//! ## Checking from the node
+//!
+//! [`check_token`] resolves the signal record through the local resolver
+//! and compares it with [`verify_token`]. That only proves what this box's
+//! network path sees, but makes the check scriptable in CI.
 
+use base64::Engine;
 use chrono::Utc;
+use hickory_resolver::TokioResolver;
+use i1_core::wire;
 use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
 
+use crate::error::{AuditError, Result};
 use crate::hash::sha256_bytes;
 use crate::types::AuditSnapshot;
 
+/// The node key that signs signal records, shared with i1-srv's intel
+/// signatures.
+pub use i1_ca::NodeSigner;
+
 /// Expected TTL for signal records (seconds).
 /// Kept low so stale cache is detectable.
 pub const SIGNAL_TTL: u32 = 60;
@@ -30,6 +64,22 @@ pub const SIGNAL_TTL: u32 = 60;
 /// If the observed TTL differs by more than this, flag it.
 pub const MAX_TTL_DRIFT: u32 = 10;
 
+/// Digest characters naming a node's signal record.
+pub const NODE_PREFIX_LEN: usize = 12;
+
+/// Digest characters carried by a compact verification URL (64 bits).
+pub const COMPACT_DIGEST_LEN: usize = 16;
+
+/// Base of compact verification URLs.
+const COMPACT_URL_BASE: &str = "https://i1.is/v/";
+
+/// Separator for the trailing signature field of a signal record.
+const SIG_SEPARATOR: &str = ";sig=";
+
+/// Signature encoding: URL-safe so the same value works in the TXT record
+/// and the verification URL.
+const SIG_ENGINE: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
 /// A verification token: everything needed to check system integrity
 /// from an independent network path.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +96,9 @@ pub struct VerifyToken {
     pub node_prefix: String,
     /// Full trust digest hash
     pub digest: String,
+    /// Node signature over the signal record (base64url), when signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 impl VerifyToken {
@@ -55,10 +108,30 @@ impl VerifyToken {
     /// the DNS from its perspective and shows the comparison.
     #[must_use]
     pub fn verification_url(&self) -> String {
-        format!(
+        let mut url = format!(
             "https://i1.is/verify?n={}&d={}&ttl={}&ts={}",
             self.node_prefix, self.digest, self.expected_ttl, self.generated_at
-        )
+        );
+        if let Some(sig) = &self.signature {
+            url.push_str("&sig=");
+            url.push_str(sig);
+        }
+        url
+    }
+
+    /// Build a short verification URL for dense-QR-averse scanners.
+    ///
+    /// Only the digest prefix is carried; the rest is in the signal record
+    /// (see [`CompactRef`]). The TTL is added only when it isn't the
+    /// standard [`SIGNAL_TTL`].
+    #[must_use]
+    pub fn compact_url(&self) -> String {
+        let end = COMPACT_DIGEST_LEN.min(self.digest.len());
+        let mut url = format!("{COMPACT_URL_BASE}{}", &self.digest[..end]);
+        if self.expected_ttl != SIGNAL_TTL {
+            let _ = write!(url, "?ttl={}", self.expected_ttl);
+        }
+        url
     }
 
     /// Build the DNS name for this token's signal record.
@@ -68,6 +141,156 @@ impl VerifyToken {
     }
 }
 
+/// What a scanned compact URL refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactRef {
+    /// Leading characters of the trust digest.
+    pub digest_prefix: String,
+    /// Expected TTL (seconds).
+    pub expected_ttl: u32,
+}
+
+impl CompactRef {
+    /// Parse a URL built by [`VerifyToken::compact_url`].
+    #[must_use]
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(COMPACT_URL_BASE)?;
+        let (prefix, query) = rest.split_once('?').unwrap_or((rest, ""));
+        if prefix.len() < 12 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let expected_ttl = match query.strip_prefix("ttl=") {
+            Some(ttl) => ttl.parse().ok()?,
+            None if query.is_empty() => SIGNAL_TTL,
+            None => return None,
+        };
+        Some(Self {
+            digest_prefix: prefix.to_ascii_lowercase(),
+            expected_ttl,
+        })
+    }
+
+    /// The signal record holding the full token data.
+    #[must_use]
+    pub fn dns_name(&self) -> String {
+        VerifyToken::signal_dns_name(&self.digest_prefix[..NODE_PREFIX_LEN])
+    }
+
+    /// Does the signal record's digest start with this reference's?
+    #[must_use]
+    pub fn matches(&self, txt: &str) -> bool {
+        txt_digest(txt).is_some_and(|digest| digest.starts_with(&self.digest_prefix))
+    }
+}
+
+/// The `digest=` field of a signal record.
+fn txt_digest(txt: &str) -> Option<&str> {
+    txt.split(';')
+        .find_map(|field| field.strip_prefix("digest="))
+}
+
+/// A parsed signal record value.
+///
+/// Format: `digest=<hash>;ts=<epoch>;bins=<count>;certs=<count>;v=<schema>`,
+/// with an optional trailing `;sig=<base64url>`. [`build_signal_txt`] writes
+/// this schema; a server publishing records for other nodes parses it with
+/// [`SignalRecord::parse`]. Records from before the `v=` field parse as the
+/// current version, and are written back with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalRecord {
+    /// Full trust digest (64 lowercase hex chars)
+    pub digest: String,
+    /// When the digest was computed (epoch seconds)
+    pub ts: i64,
+    /// Binaries covered by the digest
+    pub bins: usize,
+    /// Root certificates covered by the digest
+    pub certs: usize,
+    /// Node signature over the rest of the record (base64url), when signed
+    pub sig: Option<String>,
+}
+
+impl SignalRecord {
+    /// Parse a signal record value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuditError::Encoding` unless `txt` has exactly the fields
+    /// above, in order, with a well-formed digest and signature, and
+    /// `AuditError::SchemaMismatch` if it names another schema version.
+    pub fn parse(txt: &str) -> Result<Self> {
+        let invalid = |reason: &str| AuditError::Encoding(format!("signal record: {reason}"));
+
+        let (payload, sig) = match txt.rsplit_once(SIG_SEPARATOR) {
+            Some((payload, sig)) => {
+                if sig.is_empty() || SIG_ENGINE.decode(sig).is_err() {
+                    return Err(invalid("malformed signature"));
+                }
+                (payload, Some(sig.to_string()))
+            }
+            None => (txt, None),
+        };
+
+        let mut fields = payload.split(';');
+        let mut field = |key: &str| {
+            fields
+                .next()
+                .and_then(|field| field.strip_prefix(key)?.strip_prefix('='))
+                .ok_or_else(|| invalid(&format!("expected `{key}=`")))
+        };
+        let digest = field("digest")?;
+        let ts = field("ts")?;
+        let bins = field("bins")?;
+        let certs = field("certs")?;
+        let mut rest = fields.peekable();
+        if let Some(version) = rest.next_if(|field| field.starts_with("v=")) {
+            wire::check(version)?;
+        }
+        if rest.next().is_some() {
+            return Err(invalid("unexpected trailing fields"));
+        }
+
+        if digest.len() != 64
+            || !digest
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, 'a'..='f'))
+        {
+            return Err(invalid("digest is not 64 lowercase hex chars"));
+        }
+        Ok(Self {
+            digest: digest.to_string(),
+            ts: ts.parse().map_err(|_| invalid("bad `ts`"))?,
+            bins: bins.parse().map_err(|_| invalid("bad `bins`"))?,
+            certs: certs.parse().map_err(|_| invalid("bad `certs`"))?,
+            sig,
+        })
+    }
+
+    /// The node prefix naming this record (`<prefix>.sig.i1.is`).
+    #[must_use]
+    pub fn node_prefix(&self) -> &str {
+        &self.digest[..NODE_PREFIX_LEN]
+    }
+}
+
+impl std::fmt::Display for SignalRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "digest={};ts={};bins={};certs={};{}",
+            self.digest,
+            self.ts,
+            self.bins,
+            self.certs,
+            wire::version_field()
+        )?;
+        if let Some(sig) = &self.sig {
+            write!(f, "{SIG_SEPARATOR}{sig}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Compute a trust digest from an audit snapshot.
 ///
 /// The digest is SHA-256 of: `node_id || binary_count || binary_hashes || cert_count || cert_fingerprints`
@@ -83,7 +306,7 @@ pub fn compute_trust_digest(snapshot: &AuditSnapshot) -> String {
     material.extend_from_slice(b"|");
 
     // Binary hashes (sorted for determinism)
-    let mut bin_hashes: Vec<&str> = snapshot.binaries.iter().map(|b| b.sha256.as_str()).collect();
+    let mut bin_hashes: Vec<&str> = snapshot.binaries.iter().map(|b| b.hash.as_str()).collect();
     bin_hashes.sort_unstable();
     material.extend_from_slice(bin_hashes.len().to_string().as_bytes());
     material.extend_from_slice(b"|");
@@ -110,26 +333,31 @@ pub fn compute_trust_digest(snapshot: &AuditSnapshot) -> String {
 
 /// Build the TXT record value for a signal record.
 ///
-/// Format: `digest=<hash>;ts=<epoch>;bins=<count>;certs=<count>`
+/// Format: `digest=<hash>;ts=<epoch>;bins=<count>;certs=<count>;v=<schema>`
 #[must_use]
 pub fn build_signal_txt(snapshot: &AuditSnapshot, digest: &str) -> String {
-    format!(
-        "digest={};ts={};bins={};certs={}",
-        digest,
-        Utc::now().timestamp(),
-        snapshot.binaries.len(),
-        snapshot.root_certs.len()
-    )
+    signal_txt_at(snapshot, digest, Utc::now().timestamp())
+}
+
+fn signal_txt_at(snapshot: &AuditSnapshot, digest: &str, ts: i64) -> String {
+    SignalRecord {
+        digest: digest.to_string(),
+        ts,
+        bins: snapshot.binaries.len(),
+        certs: snapshot.root_certs.len(),
+        sig: None,
+    }
+    .to_string()
 }
 
 /// Generate a complete verification token from a snapshot.
 #[must_use]
 pub fn generate_verify_token(snapshot: &AuditSnapshot) -> VerifyToken {
     let digest = compute_trust_digest(snapshot);
-    let node_prefix = &digest[..12];
+    let node_prefix = &digest[..NODE_PREFIX_LEN];
     let dns_name = VerifyToken::signal_dns_name(node_prefix);
-    let expected_value = build_signal_txt(snapshot, &digest);
     let now = Utc::now().timestamp();
+    let expected_value = signal_txt_at(snapshot, &digest, now);
 
     VerifyToken {
         dns_name,
@@ -138,7 +366,68 @@ pub fn generate_verify_token(snapshot: &AuditSnapshot) -> VerifyToken {
         generated_at: now,
         node_prefix: node_prefix.to_string(),
         digest,
+        signature: None,
+    }
+}
+
+/// Generate a verification token whose signal record is signed by `key`.
+///
+/// The expected TXT value ends in `;sig=<signature>`, and the signature is
+/// also carried in [`VerifyToken::signature`] and the verification URL.
+pub fn generate_verify_token_signed(
+    snapshot: &AuditSnapshot,
+    key: &NodeSigner,
+) -> Result<VerifyToken> {
+    let mut token = generate_verify_token(snapshot);
+    let sig = key
+        .sign(token.expected_value.as_bytes())
+        .map_err(|e| AuditError::Signature(format!("signing failed: {e}")))?;
+    let sig = SIG_ENGINE.encode(sig);
+    token.expected_value = format!("{}{SIG_SEPARATOR}{sig}", token.expected_value);
+    token.signature = Some(sig);
+    Ok(token)
+}
+
+/// Check that `token` was signed by the node holding `public_key`.
+///
+/// Fails unless the token is signed, the signature covers its signal
+/// record, and that record carries the token's digest.
+pub fn verify_token_signed(token: &VerifyToken, public_key: &[u8]) -> Result<()> {
+    let sig = token
+        .signature
+        .as_deref()
+        .ok_or_else(|| AuditError::Signature("token is not signed".into()))?;
+    if !token
+        .expected_value
+        .ends_with(&format!("{SIG_SEPARATOR}{sig}"))
+    {
+        return Err(AuditError::Signature(
+            "token signature does not match its signal record".into(),
+        ));
+    }
+    let digest = verify_signal_txt(&token.expected_value, public_key)?;
+    if digest != token.digest {
+        return Err(AuditError::Signature(
+            "signed digest does not match the token".into(),
+        ));
     }
+    Ok(())
+}
+
+/// Check a signal record's signature against a node's public key.
+///
+/// Returns the digest the record vouches for.
+pub fn verify_signal_txt<'a>(txt: &'a str, public_key: &[u8]) -> Result<&'a str> {
+    let (payload, sig) = txt
+        .rsplit_once(SIG_SEPARATOR)
+        .ok_or_else(|| AuditError::Signature("signal record is not signed".into()))?;
+    let sig = SIG_ENGINE
+        .decode(sig)
+        .map_err(|e| AuditError::Signature(format!("malformed signature: {e}")))?;
+    i1_ca::verify_signature(public_key, payload.as_bytes(), &sig)
+        .map_err(|_| AuditError::Signature("trust digest signature mismatch".into()))?;
+
+    txt_digest(payload).ok_or_else(|| AuditError::Signature("signal record has no digest".into()))
 }
 
 /// Result of a TTL verification check.
@@ -177,6 +466,87 @@ pub enum Verdict {
     Compromised,
 }
 
+impl Verdict {
+    /// Should automation treat this verdict as a failed check?
+    ///
+    /// A record that isn't published yet is not evidence of tampering.
+    #[must_use]
+    pub const fn is_failure(self) -> bool {
+        matches!(self, Self::Tampered | Self::StaleCache | Self::Compromised)
+    }
+}
+
+/// Compare an observed signal record (TXT value and TTL) with a token.
+///
+/// Values match when they carry the same digest: the timestamp and
+/// signature fields depend on when and by whom the record was published.
+/// A TTL below the expected one is a resolver counting down its cache and
+/// is fine; one more than [`MAX_TTL_DRIFT`] above it is not.
+#[must_use]
+pub fn verify_token(token: &VerifyToken, observed: Option<(&str, u32)>) -> VerifyResult {
+    let Some((value, ttl)) = observed else {
+        return VerifyResult {
+            value_match: false,
+            expected_value: token.expected_value.clone(),
+            observed_value: None,
+            expected_ttl: token.expected_ttl,
+            observed_ttl: None,
+            ttl_drift: None,
+            ttl_ok: false,
+            verdict: Verdict::NotPublished,
+        };
+    };
+
+    let value_match = txt_digest(value) == Some(token.digest.as_str());
+    let ttl_ok = ttl <= token.expected_ttl.saturating_add(MAX_TTL_DRIFT);
+    let verdict = match (value_match, ttl_ok) {
+        (true, true) => Verdict::Ok,
+        (false, true) => Verdict::Tampered,
+        (true, false) => Verdict::StaleCache,
+        (false, false) => Verdict::Compromised,
+    };
+    VerifyResult {
+        value_match,
+        expected_value: token.expected_value.clone(),
+        observed_value: Some(value.to_string()),
+        expected_ttl: token.expected_ttl,
+        observed_ttl: Some(ttl),
+        ttl_drift: Some(ttl.abs_diff(token.expected_ttl)),
+        ttl_ok,
+        verdict,
+    }
+}
+
+/// Resolve a token's signal record and compare it with [`verify_token`].
+///
+/// # Errors
+///
+/// Returns `AuditError::DnsQuery` if the lookup fails for any reason other
+/// than the record not existing.
+pub async fn check_token(resolver: &TokioResolver, token: &VerifyToken) -> Result<VerifyResult> {
+    match resolver.txt_lookup(token.dns_name.as_str()).await {
+        Ok(records) => {
+            let ttl = records
+                .as_lookup()
+                .records()
+                .first()
+                .map_or(0, hickory_resolver::proto::rr::Record::ttl);
+            let txts: Vec<String> = records.iter().map(ToString::to_string).collect();
+            // Prefer the record for this digest if several are published.
+            let value = txts
+                .iter()
+                .find(|txt| txt_digest(txt) == Some(token.digest.as_str()))
+                .or_else(|| txts.first());
+            Ok(verify_token(
+                token,
+                value.map(|value| (value.as_str(), ttl)),
+            ))
+        }
+        Err(e) if e.is_nx_domain() || e.is_no_records_found() => Ok(verify_token(token, None)),
+        Err(e) => Err(AuditError::DnsQuery(format!("{}: {e}", token.dns_name))),
+    }
+}
+
 impl std::fmt::Display for Verdict {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -203,6 +573,8 @@ mod tests {
             binaries: vec![],
             processes: vec![],
             root_certs: vec![],
+            kernel_modules: vec![],
+            shared_libraries: vec![],
             summary: AuditSummary {
                 total_binaries: 0,
                 total_processes: 0,
@@ -211,6 +583,8 @@ mod tests {
                 expired_certs: 0,
                 low_trust_binaries: 0,
                 unknown_certs: 0,
+                critical_certs: 0,
+                low_trust_modules: 0,
             },
         }
     }
@@ -240,6 +614,102 @@ mod tests {
         assert!(url.contains(&token.node_prefix));
     }
 
+    #[test]
+    fn compact_url_resolves_through_dns() {
+        let snap = make_snapshot();
+        let token = generate_verify_token(&snap);
+        let url = token.compact_url();
+        assert!(url.len() < token.verification_url().len() / 2);
+
+        let compact = CompactRef::parse(&url).unwrap();
+        assert_eq!(compact.dns_name(), token.dns_name);
+        assert_eq!(compact.expected_ttl, SIGNAL_TTL);
+        assert!(compact.matches(&token.expected_value));
+        assert!(!compact.matches(&token.expected_value.replace("digest=", "digest=0")));
+
+        let slow = VerifyToken {
+            expected_ttl: 300,
+            ..token
+        };
+        assert_eq!(
+            CompactRef::parse(&slow.compact_url()).unwrap().expected_ttl,
+            300
+        );
+        assert!(CompactRef::parse("https://i1.is/v/not-hex").is_none());
+    }
+
+    fn test_signer() -> NodeSigner {
+        use ring::rand::SystemRandom;
+        use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        NodeSigner::from_pkcs8_der(pkcs8.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn signed_token_verifies() {
+        let snap = make_snapshot();
+        let key = test_signer();
+        let token = generate_verify_token_signed(&snap, &key).unwrap();
+        let sig = token.signature.clone().unwrap();
+        assert!(token.expected_value.ends_with(&format!(";sig={sig}")));
+        assert!(token.verification_url().ends_with(&format!("&sig={sig}")));
+        verify_token_signed(&token, key.public_key()).unwrap();
+        assert_eq!(
+            verify_signal_txt(&token.expected_value, key.public_key()).unwrap(),
+            token.digest
+        );
+
+        // Unsigned tokens and other nodes' keys are rejected.
+        let unsigned = generate_verify_token(&snap);
+        assert!(verify_token_signed(&unsigned, key.public_key()).is_err());
+        assert!(verify_token_signed(&token, test_signer().public_key()).is_err());
+    }
+
+    #[test]
+    fn forged_digest_is_rejected() {
+        let snap = make_snapshot();
+        let key = test_signer();
+        let token = generate_verify_token_signed(&snap, &key).unwrap();
+
+        // A digest swapped into the signed record breaks the signature.
+        let mut forged = token.clone();
+        let fake = "0".repeat(64);
+        forged.expected_value = token.expected_value.replace(&token.digest, &fake);
+        forged.digest = fake;
+        assert!(verify_token_signed(&forged, key.public_key()).is_err());
+
+        // So does claiming a different digest than the one signed.
+        let mut mismatched = token;
+        mismatched.digest = "f".repeat(64);
+        assert!(verify_token_signed(&mismatched, key.public_key()).is_err());
+    }
+
+    #[test]
+    fn verdicts_from_observed_record() {
+        let token = generate_verify_token(&make_snapshot());
+        let verdict = |observed| verify_token(&token, observed).verdict;
+
+        // A later publish of the same digest, cached for a while, is fine.
+        let republished = format!("digest={};ts=1;bins=0;certs=0", token.digest);
+        assert_eq!(verdict(Some((&republished, 60))), Verdict::Ok);
+        assert_eq!(verdict(Some((&republished, 3))), Verdict::Ok);
+        assert_eq!(verdict(Some((&republished, 70))), Verdict::Ok);
+
+        let other = format!("digest={};ts=1", "0".repeat(64));
+        assert_eq!(verdict(Some((&republished, 3600))), Verdict::StaleCache);
+        assert_eq!(verdict(Some((&other, 60))), Verdict::Tampered);
+        assert_eq!(verdict(Some((&other, 3600))), Verdict::Compromised);
+        assert_eq!(verdict(None), Verdict::NotPublished);
+
+        let result = verify_token(&token, Some((&republished, 3600)));
+        assert_eq!(result.ttl_drift, Some(3540));
+        assert!(result.value_match && !result.ttl_ok);
+        assert!(result.verdict.is_failure());
+        assert!(!Verdict::NotPublished.is_failure());
+    }
+
     #[test]
     fn signal_txt_format() {
         let snap = make_snapshot();
@@ -249,5 +719,50 @@ mod tests {
         assert!(txt.contains(";ts="));
         assert!(txt.contains(";bins=0"));
         assert!(txt.contains(";certs=0"));
+        assert!(txt.ends_with(";v=1"));
+    }
+
+    #[test]
+    fn signal_record_round_trips() {
+        let snap = make_snapshot();
+        let key = test_signer();
+        let token = generate_verify_token_signed(&snap, &key).unwrap();
+
+        let record = SignalRecord::parse(&token.expected_value).unwrap();
+        assert_eq!(record.digest, token.digest);
+        assert_eq!(record.ts, token.generated_at);
+        assert_eq!(record.node_prefix(), token.node_prefix);
+        assert_eq!(record.sig, token.signature);
+        assert_eq!(record.to_string(), token.expected_value);
+
+        let unsigned = generate_verify_token(&snap).expected_value;
+        assert_eq!(
+            SignalRecord::parse(&unsigned).unwrap().to_string(),
+            unsigned
+        );
+
+        for bad in [
+            "digest=abc;ts=1;bins=0;certs=0",
+            &format!("digest={};ts=x;bins=0;certs=0", token.digest),
+            &format!("digest={};ts=1;certs=0;bins=0", token.digest),
+            &format!("digest={};ts=1;bins=0;certs=0;extra=1", token.digest),
+            &format!("digest={};ts=1;bins=0;certs=0;sig=", token.digest),
+            &format!("digest={};ts=1;bins=0;certs=0", token.digest.to_uppercase()),
+            &format!("digest={};ts=1;bins=0;certs=0;v=1;v=1", token.digest),
+        ] {
+            assert!(SignalRecord::parse(bad).is_err(), "{bad}");
+        }
+
+        // Records from before the version field still parse
+        let legacy = format!("digest={};ts=1;bins=0;certs=0", token.digest);
+        assert_eq!(
+            SignalRecord::parse(&legacy).unwrap().to_string(),
+            format!("{legacy};v=1")
+        );
+        let newer = format!("digest={};ts=1;bins=0;certs=0;v=2", token.digest);
+        assert!(matches!(
+            SignalRecord::parse(&newer),
+            Err(AuditError::SchemaMismatch(mismatch)) if mismatch.found == 2
+        ));
     }
 }
@@ -33,14 +33,26 @@ pub enum CaError {
     #[error("Certificate not yet valid")]
     NotYetValid,
 
+    /// A detached signature does not match the data and public key.
+    #[error("Signature mismatch")]
+    SignatureMismatch,
+
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// JSON serialization error.
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
     /// PEM encoding/decoding error.
     #[error("PEM error: {0}")]
     Pem(String),
 
+    /// PKCS#12 encoding error.
+    #[error("PKCS#12 error: {0}")]
+    Pkcs12(String),
+
     /// Certificate generation error from rcgen.
     #[error("Certificate generation error: {0}")]
     RcGen(String),
@@ -173,6 +173,30 @@ impl IntermediateCa {
 
     /// Sign an end-entity certificate for a domain.
     pub fn sign_domain(&self, domain: &str, validity_days: u32) -> Result<(String, String), CaError> {
+        self.sign_end_entity(domain, validity_days, vec![ExtendedKeyUsagePurpose::ServerAuth])
+    }
+
+    /// Sign an i1-srv node certificate.
+    ///
+    /// Nodes authenticate each other with mutual TLS, so the certificate is
+    /// valid for both server and client authentication.
+    pub fn sign_node(&self, node_name: &str, validity_days: u32) -> Result<(String, String), CaError> {
+        self.sign_end_entity(
+            node_name,
+            validity_days,
+            vec![
+                ExtendedKeyUsagePurpose::ServerAuth,
+                ExtendedKeyUsagePurpose::ClientAuth,
+            ],
+        )
+    }
+
+    fn sign_end_entity(
+        &self,
+        domain: &str,
+        validity_days: u32,
+        extended_key_usages: Vec<ExtendedKeyUsagePurpose>,
+    ) -> Result<(String, String), CaError> {
         // Generate key for end-entity
         let end_key = KeyPair::generate()?;
         let end_key_pem = end_key.serialize_pem();
@@ -188,7 +212,7 @@ impl IntermediateCa {
             KeyUsagePurpose::DigitalSignature,
             KeyUsagePurpose::KeyEncipherment,
         ];
-        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        params.extended_key_usages = extended_key_usages;
 
         params.not_before = time::OffsetDateTime::now_utc();
         params.not_after =
@@ -248,6 +272,16 @@ mod tests {
         assert!(key_pem.contains("PRIVATE KEY"));
     }
 
+    #[test]
+    fn test_sign_node() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let intermediate = IntermediateCa::for_region("eu", &root).unwrap();
+
+        let (cert_pem, key_pem) = intermediate.sign_node("node1.srv.i1.is", 30).unwrap();
+        assert!(cert_pem.contains("BEGIN CERTIFICATE"));
+        assert!(key_pem.contains("PRIVATE KEY"));
+    }
+
     #[test]
     fn test_patient_zero_tracking() {
         // Simulate: Root CA in vault, user-specific intermediates online
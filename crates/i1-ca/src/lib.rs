@@ -41,12 +41,18 @@ mod root;
 mod intermediate;
 mod end_entity;
 mod revocation;
+mod verify;
+mod pkcs12;
+mod signer;
 
 pub use error::CaError;
 pub use root::RootCa;
 pub use intermediate::IntermediateCa;
 pub use end_entity::{EndEntityCert, CertificateRequest};
-pub use revocation::{RevocationList, RevocationReason};
+pub use revocation::{RevocationList, RevocationReason, RevokedCert};
+pub use verify::{verify_chain, verify_chain_at};
+pub use pkcs12::to_pkcs12;
+pub use signer::{verify_signature, NodeSigner};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,134 @@
+//! PKCS#12 (PFX) export.
+//!
+//! Windows certificate stores and Java keystores want a key and its chain
+//! bundled in one password-protected file rather than PEM. Bundles use
+//! PBES2 with AES-256 and an HMAC-SHA256 MAC, which Windows 10+, Java 11+
+//! and OpenSSL 1.1+ all read.
+
+use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKeyChain};
+use ring::digest;
+
+use crate::{CaError, EndEntityCert, IntermediateCa};
+
+/// Bundle a private key with its certificate chain as PKCS#12.
+///
+/// `chain_pem` holds the certificate for `key_pem` first, followed by its
+/// issuers; a root at the end is included as is. The entry is named after
+/// the certificate's common name.
+pub fn to_pkcs12(key_pem: &str, chain_pem: &str, password: &str) -> Result<Vec<u8>, CaError> {
+    let key = pem::parse(key_pem).map_err(|e| CaError::Pem(e.to_string()))?;
+    if key.tag() != "PRIVATE KEY" {
+        return Err(CaError::Pem(format!(
+            "expected a PKCS#8 private key, found {}",
+            key.tag()
+        )));
+    }
+    let chain = pem::parse_many(chain_pem)
+        .map_err(|e| CaError::Pem(e.to_string()))?
+        .into_iter()
+        .filter(|block| block.tag() == "CERTIFICATE")
+        .map(|block| Certificate::from_der(block.contents()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| CaError::Parsing(e.to_string()))?;
+    let leaf = chain
+        .first()
+        .ok_or_else(|| CaError::Pem("no certificate in chain PEM".into()))?;
+
+    let alias = alias(leaf.as_der());
+    // The SHA-1 of the certificate, as OpenSSL and keytool use.
+    let key_id = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, leaf.as_der());
+    let entry = PrivateKeyChain::new(key.contents(), key_id.as_ref(), chain);
+
+    let mut store = KeyStore::new();
+    store.add_entry(&alias, KeyStoreEntry::PrivateKeyChain(entry));
+    store
+        .writer(password)
+        .write()
+        .map_err(|e| CaError::Pkcs12(e.to_string()))
+}
+
+/// Common name of a certificate, for the entry's friendly name.
+fn alias(der: &[u8]) -> String {
+    x509_parser::parse_x509_certificate(der)
+        .ok()
+        .and_then(|(_, cert)| {
+            cert.subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(ToString::to_string)
+        })
+        .unwrap_or_else(|| "i1".to_string())
+}
+
+impl EndEntityCert {
+    /// Bundle the certificate, its key and chain as PKCS#12.
+    pub fn to_pkcs12(&self, password: &str) -> Result<Vec<u8>, CaError> {
+        let chain = if self.chain_pem.is_empty() {
+            &self.cert_pem
+        } else {
+            &self.chain_pem
+        };
+        to_pkcs12(&self.key_pem, chain, password)
+    }
+}
+
+impl IntermediateCa {
+    /// Bundle the intermediate's key and chain (intermediate + root) as
+    /// PKCS#12.
+    pub fn to_pkcs12(&self, password: &str) -> Result<Vec<u8>, CaError> {
+        to_pkcs12(self.private_key_pem(), self.chain_pem(), password)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyAlgorithm, RootCa};
+
+    fn parse_back(p12: &[u8], password: &str) -> (String, PrivateKeyChain) {
+        let store = KeyStore::from_pkcs12(p12, password).unwrap();
+        let (alias, chain) = store.private_key_chain().unwrap();
+        (alias.to_string(), chain.clone())
+    }
+
+    #[test]
+    fn test_end_entity_pkcs12_round_trip() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let intermediate = IntermediateCa::for_region("eu", &root).unwrap();
+        let (cert_pem, key_pem) = intermediate.sign_domain("example.com", 1).unwrap();
+        let cert = EndEntityCert {
+            chain_pem: format!("{cert_pem}{}", intermediate.chain_pem()),
+            cert_pem,
+            key_pem,
+            info: EndEntityCert::create_info(&["example.com".into()], "eu", 1),
+        };
+
+        let p12 = cert.to_pkcs12("hunter2").unwrap();
+        let (alias, chain) = parse_back(&p12, "hunter2");
+        assert_eq!(alias, "example.com");
+        assert_eq!(chain.key(), pem::parse(&cert.key_pem).unwrap().contents());
+        let subjects: Vec<_> = chain.chain().iter().map(Certificate::subject).collect();
+        assert_eq!(subjects.len(), 3);
+        assert!(subjects[0].contains("CN=example.com"));
+        assert!(subjects[2].contains("CN=Root"));
+
+        assert!(KeyStore::from_pkcs12(&p12, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_intermediate_pkcs12_round_trip() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let intermediate = IntermediateCa::for_honeypot(&root).unwrap();
+
+        let p12 = intermediate.to_pkcs12("").unwrap();
+        let (alias, chain) = parse_back(&p12, "");
+        assert_eq!(alias, "i1.is Honeypot CA");
+        assert_eq!(chain.chain().len(), 2);
+
+        assert!(matches!(
+            to_pkcs12(intermediate.chain_pem(), intermediate.chain_pem(), "x"),
+            Err(CaError::Pem(_))
+        ));
+    }
+}
@@ -3,11 +3,17 @@
 //! When an intermediate or end-entity cert is compromised,
 //! we revoke it here. The root can revoke intermediates,
 //! intermediates can revoke end-entities.
+//!
+//! The list is kept as JSON on disk so revocations survive restarts;
+//! it's the data the CRL and OCSP responders are built from.
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use uuid::Uuid;
 
+use crate::{CaError, CertificateInfo};
+
 /// Reason for certificate revocation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RevocationReason {
@@ -47,9 +53,9 @@ impl std::fmt::Display for RevocationReason {
     }
 }
 
-/// Entry in the revocation list.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RevocationEntry {
+/// A revoked certificate: which one, when and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedCert {
     /// Certificate serial number
     pub serial: String,
     /// When it was revoked
@@ -57,6 +63,7 @@ pub struct RevocationEntry {
     /// Why it was revoked
     pub reason: RevocationReason,
     /// Optional notes
+    #[serde(default)]
     pub notes: Option<String>,
 }
 
@@ -72,7 +79,7 @@ pub struct RevocationList {
     /// When the next CRL will be published
     pub next_update: DateTime<Utc>,
     /// Revoked certificates
-    pub entries: Vec<RevocationEntry>,
+    pub entries: Vec<RevokedCert>,
 }
 
 impl RevocationList {
@@ -88,29 +95,85 @@ impl RevocationList {
         }
     }
 
-    /// Add a revocation entry.
-    pub fn revoke(&mut self, serial: impl Into<String>, reason: RevocationReason) {
-        self.entries.push(RevocationEntry {
-            serial: serial.into(),
-            revoked_at: Utc::now(),
-            reason,
-            notes: None,
-        });
+    /// Load a list saved with [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CaError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(Self::from_json(&json)?)
+    }
+
+    /// Load the list at `path`, or start an empty one for `issuer` if
+    /// there is no file yet.
+    pub fn open(path: impl AsRef<Path>, issuer: impl Into<String>) -> Result<Self, CaError> {
+        match Self::load(&path) {
+            Err(CaError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Self::new(issuer))
+            }
+            result => result,
+        }
+    }
+
+    /// Write the list to `path`, replacing any previous copy atomically.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CaError> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.to_json()?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
     }
 
-    /// Add a revocation with notes.
+    /// Revoke a certificate.
+    ///
+    /// Revoking a serial that is already revoked keeps the original
+    /// record, time and reason included, and returns `false`.
+    pub fn revoke(&mut self, serial: impl Into<String>, reason: RevocationReason) -> bool {
+        self.insert(serial.into(), reason, None)
+    }
+
+    /// Revoke a certificate, recording notes with it.
+    ///
+    /// Like [`revoke`](Self::revoke), this is a no-op for serials that are
+    /// already revoked.
     pub fn revoke_with_notes(
         &mut self,
         serial: impl Into<String>,
         reason: RevocationReason,
         notes: impl Into<String>,
-    ) {
-        self.entries.push(RevocationEntry {
-            serial: serial.into(),
-            revoked_at: Utc::now(),
+    ) -> bool {
+        self.insert(serial.into(), reason, Some(notes.into()))
+    }
+
+    fn insert(&mut self, serial: String, reason: RevocationReason, notes: Option<String>) -> bool {
+        if self.is_revoked(&serial) {
+            return false;
+        }
+        let now = Utc::now();
+        self.entries.push(RevokedCert {
+            serial,
+            revoked_at: now,
             reason,
-            notes: Some(notes.into()),
+            notes,
         });
+        self.this_update = now;
+        true
+    }
+
+    /// Revoked certificates, in the order they were revoked.
+    pub fn entries(&self) -> &[RevokedCert] {
+        &self.entries
+    }
+
+    /// Mark `info` revoked if its serial is on the list.
+    ///
+    /// Returns whether it is revoked.
+    pub fn apply(&self, info: &mut CertificateInfo) -> bool {
+        match self.get_revocation(&info.serial) {
+            Some(entry) => {
+                info.revoked = true;
+                info.revocation_reason = Some(entry.reason);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Check if a serial number is revoked.
@@ -119,7 +182,7 @@ impl RevocationList {
     }
 
     /// Get revocation entry if revoked.
-    pub fn get_revocation(&self, serial: &str) -> Option<&RevocationEntry> {
+    pub fn get_revocation(&self, serial: &str) -> Option<&RevokedCert> {
         self.entries.iter().find(|e| e.serial == serial)
     }
 
@@ -187,4 +250,55 @@ mod tests {
         assert_eq!(loaded.entries.len(), 1);
         assert!(loaded.is_revoked("123"));
     }
+
+    #[test]
+    fn test_persistence_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("crl.json");
+
+        let mut crl = RevocationList::open(&path, "Test CA").unwrap();
+        assert!(crl.is_empty());
+        crl.revoke("ABC123", RevocationReason::KeyCompromise);
+        crl.revoke_with_notes("DEF456", RevocationReason::Superseded, "rotated");
+        crl.save(&path).unwrap();
+
+        let loaded = RevocationList::open(&path, "ignored").unwrap();
+        assert_eq!(loaded.issuer, "Test CA");
+        assert_eq!(loaded.id, crl.id);
+        assert_eq!(loaded.entries(), crl.entries());
+        assert_eq!(loaded.entries()[1].notes.as_deref(), Some("rotated"));
+        assert!(matches!(
+            RevocationList::load(dir.path().join("missing.json")),
+            Err(CaError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_rerevocation_is_idempotent() {
+        let mut crl = RevocationList::new("Test CA");
+        assert!(crl.revoke("ABC123", RevocationReason::CertificateHold));
+        let first = crl.get_revocation("ABC123").unwrap().clone();
+
+        assert!(!crl.revoke("ABC123", RevocationReason::KeyCompromise));
+        assert!(!crl.revoke_with_notes("ABC123", RevocationReason::Unspecified, "again"));
+        assert_eq!(crl.len(), 1);
+        assert_eq!(crl.get_revocation("ABC123"), Some(&first));
+
+        let mut info = CertificateInfo {
+            id: Uuid::new_v4(),
+            serial: "ABC123".into(),
+            subject: "node.i1.is".into(),
+            issuer: "Test CA".into(),
+            not_before: Utc::now(),
+            not_after: Utc::now(),
+            cert_type: crate::CertificateType::EndEntity,
+            revoked: false,
+            revocation_reason: None,
+        };
+        assert!(crl.apply(&mut info));
+        assert_eq!(
+            info.revocation_reason,
+            Some(RevocationReason::CertificateHold)
+        );
+    }
 }
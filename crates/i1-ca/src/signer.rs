@@ -0,0 +1,131 @@
+//! Detached signatures made with a node's key.
+//!
+//! A node signs what it publishes outside of TLS (intel TXT records,
+//! trust digests) with its ECDSA P-256 key as written by i1-ca, so that
+//! holders of its public key can check the data after it has passed
+//! through untrusted resolvers:
+//!
+//! ```rust,ignore
+//! let signer = i1_ca::NodeSigner::load(Path::new("node.key"))?;
+//! let sig = signer.sign(b"payload")?;
+//! i1_ca::verify_signature(signer.public_key(), b"payload", &sig)?;
+//! ```
+
+use ring::rand::SystemRandom;
+use ring::signature::{
+    EcdsaKeyPair, KeyPair, UnparsedPublicKey, ECDSA_P256_SHA256_FIXED,
+    ECDSA_P256_SHA256_FIXED_SIGNING,
+};
+
+use crate::CaError;
+
+/// Signs data with a node's ECDSA P-256 key.
+pub struct NodeSigner {
+    key_pair: EcdsaKeyPair,
+}
+
+impl std::fmt::Debug for NodeSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        let preview = self
+            .public_key()
+            .iter()
+            .take(8)
+            .fold(String::new(), |mut out, b| {
+                let _ = write!(out, "{b:02x}");
+                out
+            });
+        f.debug_struct("NodeSigner")
+            .field("public_key", &preview)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NodeSigner {
+    /// Load from a PKCS#8 PEM private key (as written by i1-ca).
+    pub fn from_pkcs8_pem(pem_str: &str) -> Result<Self, CaError> {
+        let parsed = pem::parse(pem_str).map_err(|e| CaError::Pem(e.to_string()))?;
+        Self::from_pkcs8_der(parsed.contents())
+    }
+
+    /// Load from a PKCS#8 DER private key.
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Self, CaError> {
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &SystemRandom::new())
+                .map_err(|e| CaError::Signing(format!("unsupported signing key: {e}")))?;
+        Ok(Self { key_pair })
+    }
+
+    /// Load a PKCS#8 PEM private key from a file.
+    pub fn load(path: &std::path::Path) -> Result<Self, CaError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            CaError::Io(std::io::Error::new(
+                e.kind(),
+                format!("{}: {e}", path.display()),
+            ))
+        })?;
+        Self::from_pkcs8_pem(&content)
+    }
+
+    /// Uncompressed SEC1 public key bytes, for distribution to verifiers.
+    #[must_use]
+    pub fn public_key(&self) -> &[u8] {
+        self.key_pair.public_key().as_ref()
+    }
+
+    /// Sign `message`, returning the fixed-length (r || s) signature.
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CaError> {
+        let sig = self
+            .key_pair
+            .sign(&SystemRandom::new(), message)
+            .map_err(|e| CaError::Signing(e.to_string()))?;
+        Ok(sig.as_ref().to_vec())
+    }
+}
+
+/// Check a [`NodeSigner`] signature over `message` against the node's
+/// uncompressed SEC1 public key.
+pub fn verify_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), CaError> {
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, public_key)
+        .verify(message, signature)
+        .map_err(|_| CaError::SignatureMismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer() -> NodeSigner {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .unwrap();
+        let pem_str = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec()));
+        NodeSigner::from_pkcs8_pem(&pem_str).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let node = signer();
+        let sig = node.sign(b"digest=abc").unwrap();
+        assert!(verify_signature(node.public_key(), b"digest=abc", &sig).is_ok());
+
+        assert!(matches!(
+            verify_signature(node.public_key(), b"digest=abd", &sig),
+            Err(CaError::SignatureMismatch)
+        ));
+        assert!(verify_signature(signer().public_key(), b"digest=abc", &sig).is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_p256_keys() {
+        let ed25519 =
+            ring::signature::Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        assert!(NodeSigner::from_pkcs8_der(ed25519.as_ref()).is_err());
+        assert!(NodeSigner::from_pkcs8_pem("not a key").is_err());
+    }
+}
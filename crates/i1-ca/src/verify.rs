@@ -0,0 +1,368 @@
+//! Certificate chain verification.
+//!
+//! Checks that a leaf certificate chains up to a trusted root the way a
+//! TLS client would, so a cert can be checked before it is served:
+//!
+//! ```rust,ignore
+//! let (leaf_pem, _key) = intermediate.sign_domain("example.com", 1)?;
+//! i1_ca::verify_chain(&leaf_pem, intermediate.chain_pem(), root.certificate_pem())?;
+//! ```
+//!
+//! Every certificate on the path must be inside its validity window and
+//! signed by the next one up. Each issuer must be a CA allowed to sign
+//! certificates, with a path length that covers the CAs below it. DNS name
+//! constraints on a CA are applied to the DNS names of everything it
+//! issued; other name forms in a constraint are not checked.
+
+use chrono::{DateTime, Utc};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::{GeneralName, GeneralSubtree};
+
+use crate::CaError;
+
+/// Verify that `leaf_pem` chains up to `root_pem` through the
+/// intermediates in `chain_pem`, as of now.
+///
+/// `chain_pem` may be in any order and may include the root itself, as
+/// [`IntermediateCa::chain_pem`](crate::IntermediateCa::chain_pem) does.
+pub fn verify_chain(leaf_pem: &str, chain_pem: &str, root_pem: &str) -> Result<(), CaError> {
+    verify_chain_at(leaf_pem, chain_pem, root_pem, Utc::now())
+}
+
+/// Verify a chain as [`verify_chain`] does, as of `at`.
+pub fn verify_chain_at(
+    leaf_pem: &str,
+    chain_pem: &str,
+    root_pem: &str,
+    at: DateTime<Utc>,
+) -> Result<(), CaError> {
+    let leaf_der = single_der(leaf_pem, "leaf")?;
+    let root_der = single_der(root_pem, "root")?;
+    let chain_ders: Vec<Vec<u8>> = parse_ders(chain_pem)?
+        .into_iter()
+        .filter(|der| *der != root_der)
+        .collect();
+
+    let leaf = parse(&leaf_der)?;
+    let root = parse(&root_der)?;
+    let intermediates = chain_ders
+        .iter()
+        .map(|der| parse(der))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    root.verify_signature(None).map_err(|e| {
+        CaError::InvalidChain(format!("root {} is not self-signed: {e}", root.subject()))
+    })?;
+
+    // Walk up from the leaf, picking each issuer by name.
+    let mut path = vec![&leaf];
+    let mut used = vec![false; intermediates.len()];
+    loop {
+        let current = path[path.len() - 1];
+        if current.issuer() == root.subject() {
+            path.push(&root);
+            break;
+        }
+        let next = intermediates
+            .iter()
+            .enumerate()
+            .find(|(i, cert)| !used[*i] && cert.subject() == current.issuer());
+        match next {
+            Some((i, cert)) => {
+                used[i] = true;
+                path.push(cert);
+            }
+            None => {
+                return Err(CaError::InvalidChain(format!(
+                    "no issuer for {} (issued by {})",
+                    current.subject(),
+                    current.issuer()
+                )))
+            }
+        }
+    }
+
+    let timestamp = at.timestamp();
+    for cert in &path {
+        let validity = cert.validity();
+        if timestamp < validity.not_before.timestamp() {
+            return Err(CaError::NotYetValid);
+        }
+        if timestamp > validity.not_after.timestamp() {
+            return Err(CaError::Expired);
+        }
+    }
+
+    for (depth, pair) in path.windows(2).enumerate() {
+        let (cert, issuer) = (pair[0], pair[1]);
+        cert.verify_signature(Some(issuer.public_key()))
+            .map_err(|e| {
+                CaError::InvalidChain(format!(
+                    "signature on {} does not verify against {}: {e}",
+                    cert.subject(),
+                    issuer.subject()
+                ))
+            })?;
+        check_issuer(issuer, depth)?;
+        check_name_constraints(issuer, &path[..=depth])?;
+    }
+
+    Ok(())
+}
+
+/// Check that `issuer` may sign certificates with `cas_below` CA
+/// certificates between it and the leaf.
+fn check_issuer(issuer: &X509Certificate<'_>, cas_below: usize) -> Result<(), CaError> {
+    let constraints = issuer
+        .basic_constraints()
+        .map_err(|e| CaError::Parsing(e.to_string()))?
+        .filter(|ext| ext.value.ca)
+        .ok_or_else(|| CaError::InvalidChain(format!("{} is not a CA", issuer.subject())))?;
+    if let Some(limit) = constraints.value.path_len_constraint {
+        if cas_below > limit as usize {
+            return Err(CaError::InvalidChain(format!(
+                "{} allows {limit} CA(s) below it, found {cas_below}",
+                issuer.subject()
+            )));
+        }
+    }
+
+    let key_usage = issuer
+        .key_usage()
+        .map_err(|e| CaError::Parsing(e.to_string()))?;
+    if key_usage.is_some_and(|ext| !ext.value.key_cert_sign()) {
+        return Err(CaError::InvalidChain(format!(
+            "{} may not sign certificates",
+            issuer.subject()
+        )));
+    }
+    Ok(())
+}
+
+/// Check the DNS names of everything `issuer` vouches for against its
+/// name constraints, if it has any.
+fn check_name_constraints(
+    issuer: &X509Certificate<'_>,
+    below: &[&X509Certificate<'_>],
+) -> Result<(), CaError> {
+    let Some(constraints) = issuer
+        .name_constraints()
+        .map_err(|e| CaError::Parsing(e.to_string()))?
+    else {
+        return Ok(());
+    };
+    let permitted = dns_subtrees(constraints.value.permitted_subtrees.as_deref());
+    let excluded = dns_subtrees(constraints.value.excluded_subtrees.as_deref());
+
+    for cert in below {
+        for name in dns_names(cert)? {
+            if excluded.iter().any(|base| dns_within(name, base)) {
+                return Err(CaError::InvalidChain(format!(
+                    "{name} is excluded by {}",
+                    issuer.subject()
+                )));
+            }
+            if !permitted.is_empty() && !permitted.iter().any(|base| dns_within(name, base)) {
+                return Err(CaError::InvalidChain(format!(
+                    "{name} is outside the names permitted by {}",
+                    issuer.subject()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn dns_subtrees<'a>(subtrees: Option<&[GeneralSubtree<'a>]>) -> Vec<&'a str> {
+    subtrees
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|subtree| match subtree.base {
+            GeneralName::DNSName(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dns_names<'a>(cert: &X509Certificate<'a>) -> Result<Vec<&'a str>, CaError> {
+    let san = cert
+        .subject_alternative_name()
+        .map_err(|e| CaError::Parsing(e.to_string()))?;
+    Ok(san
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(*name),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// RFC 5280 DNS subtree matching: `example.com` covers itself and its
+/// subdomains, `.example.com` only its subdomains.
+fn dns_within(name: &str, base: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let base = base.to_ascii_lowercase();
+    if base.is_empty() {
+        return true;
+    }
+    if base.starts_with('.') {
+        return name.ends_with(&base);
+    }
+    name == base || name.ends_with(&format!(".{base}"))
+}
+
+fn parse_ders(pem_data: &str) -> Result<Vec<Vec<u8>>, CaError> {
+    Ok(pem::parse_many(pem_data)
+        .map_err(|e| CaError::Pem(e.to_string()))?
+        .into_iter()
+        .filter(|block| block.tag() == "CERTIFICATE")
+        .map(pem::Pem::into_contents)
+        .collect())
+}
+
+fn single_der(pem_data: &str, what: &str) -> Result<Vec<u8>, CaError> {
+    parse_ders(pem_data)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CaError::Pem(format!("no certificate in {what} PEM")))
+}
+
+fn parse(der: &[u8]) -> Result<X509Certificate<'_>, CaError> {
+    x509_parser::parse_x509_certificate(der)
+        .map(|(_, cert)| cert)
+        .map_err(|e| CaError::Parsing(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IntermediateCa, KeyAlgorithm, RootCa};
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, KeyPair,
+        KeyUsagePurpose, NameConstraints,
+    };
+
+    fn sub_ca(
+        name: &str,
+        path_len: u8,
+        constraints: Option<NameConstraints>,
+        issuer: &Certificate,
+        issuer_key: &KeyPair,
+    ) -> (Certificate, KeyPair) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::default();
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, name);
+        params.distinguished_name = dn;
+        params.is_ca = IsCa::Ca(BasicConstraints::Constrained(path_len));
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+        params.name_constraints = constraints;
+        let cert = params.signed_by(&key, issuer, issuer_key).unwrap();
+        (cert, key)
+    }
+
+    fn leaf(domain: &str, issuer: &Certificate, issuer_key: &KeyPair) -> String {
+        let key = KeyPair::generate().unwrap();
+        let params = CertificateParams::new(vec![domain.to_string()]).unwrap();
+        params.signed_by(&key, issuer, issuer_key).unwrap().pem()
+    }
+
+    #[test]
+    fn test_issued_chain_verifies() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let intermediate = IntermediateCa::for_region("eu", &root).unwrap();
+        let (leaf_pem, _) = intermediate.sign_domain("example.com", 1).unwrap();
+
+        verify_chain(&leaf_pem, intermediate.chain_pem(), root.certificate_pem()).unwrap();
+
+        let tomorrow = Utc::now() + chrono::Duration::days(2);
+        assert!(matches!(
+            verify_chain_at(
+                &leaf_pem,
+                intermediate.chain_pem(),
+                root.certificate_pem(),
+                tomorrow
+            ),
+            Err(CaError::Expired)
+        ));
+
+        let other = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        assert!(matches!(
+            verify_chain(&leaf_pem, intermediate.chain_pem(), other.certificate_pem()),
+            Err(CaError::InvalidChain(_))
+        ));
+        assert!(matches!(
+            verify_chain(&leaf_pem, "", root.certificate_pem()),
+            Err(CaError::InvalidChain(_))
+        ));
+    }
+
+    #[test]
+    fn test_path_length_is_enforced() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let (outer, outer_key) = sub_ca("Outer", 0, None, root.certificate(), root.key_pair());
+        let (inner, inner_key) = sub_ca("Inner", 0, None, &outer, &outer_key);
+        let leaf_pem = leaf("example.com", &inner, &inner_key);
+
+        // Listed out of order: the path is built by name.
+        let chain = format!("{}{}", inner.pem(), outer.pem());
+        let err = verify_chain(&leaf_pem, &chain, root.certificate_pem()).unwrap_err();
+        assert!(err.to_string().contains("Outer allows 0"), "{err}");
+
+        let leaf_pem = leaf("example.com", &outer, &outer_key);
+        verify_chain(&leaf_pem, &outer.pem(), root.certificate_pem()).unwrap();
+    }
+
+    #[test]
+    fn test_name_constraints_are_enforced() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let constraints = NameConstraints {
+            permitted_subtrees: vec![rcgen::GeneralSubtree::DnsName("i1.is".into())],
+            excluded_subtrees: vec![rcgen::GeneralSubtree::DnsName("admin.i1.is".into())],
+        };
+        let (ca, ca_key) = sub_ca(
+            "Scoped",
+            0,
+            Some(constraints),
+            root.certificate(),
+            root.key_pair(),
+        );
+
+        for allowed in ["i1.is", "node1.srv.i1.is", "*.srv.i1.is"] {
+            let leaf_pem = leaf(allowed, &ca, &ca_key);
+            verify_chain(&leaf_pem, &ca.pem(), root.certificate_pem()).unwrap();
+        }
+        for denied in ["example.com", "evili1.is", "admin.i1.is", "x.admin.i1.is"] {
+            let leaf_pem = leaf(denied, &ca, &ca_key);
+            assert!(
+                verify_chain(&leaf_pem, &ca.pem(), root.certificate_pem()).is_err(),
+                "{denied} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_leaf_cannot_issue() {
+        let root = RootCa::generate("Root", KeyAlgorithm::EcdsaP256).unwrap();
+        let intermediate =
+            IntermediateCa::generate("Intermediate", &root, KeyAlgorithm::EcdsaP256).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["leaf.example.com".to_string()]).unwrap();
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, "leaf.example.com");
+        params.distinguished_name = dn;
+        let fake_ca = params
+            .signed_by(&key, intermediate.certificate(), intermediate.key_pair())
+            .unwrap();
+        let below = leaf("example.com", &fake_ca, &key);
+
+        let chain = format!("{}{}", fake_ca.pem(), intermediate.chain_pem());
+        let err = verify_chain(&below, &chain, root.certificate_pem()).unwrap_err();
+        assert!(err.to_string().contains("is not a CA"), "{err}");
+    }
+}
@@ -14,18 +14,26 @@
 //! let provider = CensysProvider::new("api-id", "api-secret");
 //! let host = provider.lookup_host("8.8.8.8").await?;
 //! println!("Organization: {:?}", host.org);
+//!
+//! // Custom User-Agent and extra headers for gateways that require them
+//! let provider = CensysProvider::builder("api-id", "api-secret")
+//!     .user_agent("acme-soc/1.0")
+//!     .header("X-Correlation-Id", "run-42")
+//!     .build()?;
 //! ```
 
 use std::sync::Arc;
 use std::time::Instant;
 
 use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use governor::{Quota, RateLimiter};
 use i1_core::{GeoLocation, HostInfo, I1Error, Result, Service};
 use i1_providers::{
     AuthConfig, HealthStatus, HostLookup, Provider, ProviderHealth, RateLimitConfig,
     SearchProvider, SearchResults,
 };
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::num::NonZeroU32;
@@ -33,6 +41,9 @@ use tracing::{debug, instrument};
 
 const DEFAULT_BASE_URL: &str = "https://search.censys.io/api/v2";
 
+/// User-Agent sent when none is configured
+pub const DEFAULT_USER_AGENT: &str = concat!("i1-censys/", env!("CARGO_PKG_VERSION"));
+
 /// Censys provider for i1
 pub struct CensysProvider {
     inner: Arc<CensysInner>,
@@ -61,6 +72,31 @@ impl CensysProvider {
         api_id: impl Into<String>,
         api_secret: impl Into<String>,
         rate_limit: RateLimitConfig,
+    ) -> Self {
+        let http = Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self::from_parts(
+            http,
+            api_id.into(),
+            api_secret.into(),
+            DEFAULT_BASE_URL.to_string(),
+            &rate_limit,
+        )
+    }
+
+    /// Create a builder for custom User-Agent, headers, or base URL
+    pub fn builder(api_id: impl Into<String>, api_secret: impl Into<String>) -> CensysBuilder {
+        CensysBuilder::new(api_id, api_secret)
+    }
+
+    fn from_parts(
+        http: Client,
+        api_id: String,
+        api_secret: String,
+        base_url: String,
+        rate_limit: &RateLimitConfig,
     ) -> Self {
         let quota = Quota::per_second(
             NonZeroU32::new((rate_limit.requests_per_second.max(0.1) * 10.0) as u32)
@@ -70,10 +106,10 @@ impl CensysProvider {
 
         Self {
             inner: Arc::new(CensysInner {
-                http: Client::new(),
-                api_id: api_id.into(),
-                api_secret: api_secret.into(),
-                base_url: DEFAULT_BASE_URL.to_string(),
+                http,
+                api_id,
+                api_secret,
+                base_url,
                 rate_limiter: RateLimiter::direct(quota),
             }),
         }
@@ -100,7 +136,7 @@ impl CensysProvider {
             .basic_auth(&self.inner.api_id, Some(&self.inner.api_secret))
             .send()
             .await
-            .map_err(|e| I1Error::Http(e.to_string()))?;
+            .map_err(I1Error::from)?;
 
         let status = response.status();
         if !status.is_success() {
@@ -117,10 +153,7 @@ impl CensysProvider {
             };
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))
+        response.json().await.map_err(I1Error::from)
     }
 
     /// Make a POST request to the Censys API
@@ -143,7 +176,7 @@ impl CensysProvider {
             .json(body)
             .send()
             .await
-            .map_err(|e| I1Error::Http(e.to_string()))?;
+            .map_err(I1Error::from)?;
 
         let status = response.status();
         if !status.is_success() {
@@ -157,10 +190,27 @@ impl CensysProvider {
             };
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))
+        response.json().await.map_err(I1Error::from)
+    }
+
+    /// One page of search hits, continuing from `cursor` when given
+    async fn search_page(&self, query: &str, cursor: Option<&str>) -> Result<CensysSearchResult> {
+        #[derive(Serialize)]
+        struct SearchRequest<'a> {
+            q: &'a str,
+            per_page: u32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cursor: Option<&'a str>,
+        }
+
+        let request = SearchRequest {
+            q: query,
+            per_page: 25,
+            cursor,
+        };
+
+        let response: CensysSearchResponse = self.post("/hosts/search", &request).await?;
+        Ok(response.result)
     }
 
     /// Convert Censys host response to i1 `HostInfo`
@@ -168,31 +218,14 @@ impl CensysProvider {
         let services: Vec<Service> = host
             .services
             .into_iter()
-            .map(|s| Service {
-                port: s.port,
-                transport: i1_core::Transport::from_str(&s.transport_protocol),
-                product: s
-                    .software
-                    .as_ref()
-                    .and_then(|sw| sw.first().map(|s| s.product.clone()))
-                    .flatten(),
-                version: s
-                    .software
-                    .as_ref()
-                    .and_then(|sw| sw.first().map(|s| s.version.clone()))
-                    .flatten(),
-                cpe: vec![],
-                data: s.banner,
-                timestamp: None,
-                shodan_module: None,
-                http: None,
-                ssl: None,
-                ssh: None,
-                vulns: std::collections::HashMap::new(),
-                tags: vec![],
-                devicetype: None,
-                info: None,
-                os: None,
+            .map(|s| {
+                let first = s.software.as_ref().and_then(|sw| sw.first());
+                Service {
+                    product: first.and_then(|sw| sw.product.clone()),
+                    version: first.and_then(|sw| sw.version.clone()),
+                    data: s.banner,
+                    ..Service::new(s.port, i1_core::Transport::from_str(&s.transport_protocol))
+                }
             })
             .collect();
 
@@ -228,8 +261,96 @@ impl CensysProvider {
                 ..Default::default()
             },
             data: services,
-            last_update: host.last_updated_at,
+            last_update: host
+                .last_updated_at
+                .as_deref()
+                .and_then(i1_core::parse_timestamp),
+            last_update_raw: host.last_updated_at,
+        }
+    }
+}
+
+/// Builder for a [`CensysProvider`] with custom request settings
+///
+/// The User-Agent and extra headers are applied to every request.
+pub struct CensysBuilder {
+    api_id: String,
+    api_secret: String,
+    base_url: String,
+    rate_limit: RateLimitConfig,
+    user_agent: String,
+    headers: Vec<(String, String)>,
+}
+
+impl CensysBuilder {
+    /// Create a new builder with API credentials
+    pub fn new(api_id: impl Into<String>, api_secret: impl Into<String>) -> Self {
+        Self {
+            api_id: api_id.into(),
+            api_secret: api_secret.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            rate_limit: RateLimitConfig::censys(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Set the rate limit config
+    #[must_use]
+    pub const fn rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Set the User-Agent header
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Add an extra header (e.g., a correlation ID)
+    #[must_use]
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Override the API base URL (proxies, gateways, testing)
+    #[must_use]
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Build the provider
+    ///
+    /// # Errors
+    ///
+    /// Returns `I1Error::Config` if a header name or value is invalid.
+    pub fn build(self) -> Result<CensysProvider> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| I1Error::Config(format!("invalid header name '{name}': {e}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| I1Error::Config(format!("invalid value for header '{name}': {e}")))?;
+            headers.append(name, value);
         }
+
+        let http = Client::builder()
+            .user_agent(self.user_agent)
+            .default_headers(headers)
+            .build()
+            .map_err(|e| I1Error::Config(format!("failed to build HTTP client: {e}")))?;
+
+        Ok(CensysProvider::from_parts(
+            http,
+            self.api_id,
+            self.api_secret,
+            self.base_url,
+            &self.rate_limit,
+        ))
     }
 }
 
@@ -306,40 +427,43 @@ impl HostLookup for CensysProvider {
 
 #[async_trait]
 impl SearchProvider for CensysProvider {
+    /// Censys pages by cursor rather than number, so this always returns
+    /// the first page; [`search_all`](SearchProvider::search_all) follows
+    /// the cursors.
     #[instrument(skip(self), fields(provider = "censys"))]
     async fn search(&self, query: &str, page: Option<u32>) -> Result<SearchResults> {
-        #[derive(Serialize)]
-        struct SearchRequest<'a> {
-            q: &'a str,
-            per_page: u32,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            cursor: Option<String>,
-        }
-
-        let request = SearchRequest {
-            q: query,
-            per_page: 25,
-            cursor: None, // TODO: implement cursor-based pagination
-        };
-
-        let response: CensysSearchResponse = self.post("/hosts/search", &request).await?;
-
-        let results: Vec<HostInfo> = response
-            .result
-            .hits
-            .into_iter()
-            .map(Self::convert_host)
-            .collect();
+        let result = self.search_page(query, None).await?;
 
         Ok(SearchResults {
             provider: "censys".to_string(),
-            total: response.result.total as u64,
+            total: result.total as u64,
             page: page.unwrap_or(1),
-            results,
+            results: result.hits.into_iter().map(Self::convert_host).collect(),
             facets: None,
         })
     }
 
+    fn search_all<'a>(&'a self, query: &'a str) -> BoxStream<'a, Result<HostInfo>> {
+        // `None` once the last page is in; the first request has no cursor
+        stream::try_unfold(
+            Some(None),
+            move |cursor: Option<Option<String>>| async move {
+                let Some(cursor) = cursor else {
+                    return Ok::<_, I1Error>(None);
+                };
+                let result = self.search_page(query, cursor.as_deref()).await?;
+                if result.hits.is_empty() {
+                    return Ok(None);
+                }
+                let next = Some(result.links.next).filter(|next| !next.is_empty());
+                Ok(Some((result.hits, next.map(Some))))
+            },
+        )
+        .map_ok(|hits| stream::iter(hits.into_iter().map(|hit| Ok(Self::convert_host(hit)))))
+        .try_flatten()
+        .boxed()
+    }
+
     #[instrument(skip(self), fields(provider = "censys"))]
     async fn count(&self, query: &str) -> Result<u64> {
         #[derive(Serialize)]
@@ -446,6 +570,15 @@ struct CensysSearchResult {
     #[serde(default)]
     hits: Vec<CensysHost>,
     total: usize,
+    #[serde(default)]
+    links: CensysLinks,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CensysLinks {
+    /// Cursor for the next page; empty on the last one
+    #[serde(default)]
+    next: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -457,3 +590,80 @@ struct CensysAggregateResponse {
 struct CensysAggregateResult {
     total: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn custom_user_agent_and_headers_are_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/account"))
+            .and(header("user-agent", "acme-soc/1.0"))
+            .and(header("x-correlation-id", "run-42"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"quota": {"remaining": 7}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = CensysProvider::builder("id", "secret")
+            .base_url(server.uri())
+            .user_agent("acme-soc/1.0")
+            .header("X-Correlation-Id", "run-42")
+            .build()
+            .unwrap();
+
+        let health = provider.health_check().await.unwrap();
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.credits_remaining, Some(7));
+    }
+
+    #[tokio::test]
+    async fn search_all_follows_cursors() {
+        let server = MockServer::start().await;
+        let page = |ips: &[&str], next: &str| {
+            let hits: Vec<_> = ips.iter().map(|ip| serde_json::json!({"ip": ip})).collect();
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "result": {"total": 3, "hits": hits, "links": {"next": next, "prev": ""}}
+            }))
+        };
+        Mock::given(method("POST"))
+            .and(path("/hosts/search"))
+            .and(body_json(
+                serde_json::json!({"q": "port:22", "per_page": 25}),
+            ))
+            .respond_with(page(&["192.0.2.1", "192.0.2.2"], "c2"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hosts/search"))
+            .and(body_partial_json(serde_json::json!({"cursor": "c2"})))
+            .respond_with(page(&["192.0.2.3"], ""))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = CensysProvider::builder("id", "secret")
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let hosts: Vec<HostInfo> = provider.search_all("port:22").try_collect().await.unwrap();
+        let ips: Vec<&str> = hosts.iter().map(|h| h.ip_str.as_str()).collect();
+        assert_eq!(ips, ["192.0.2.1", "192.0.2.2", "192.0.2.3"]);
+    }
+
+    #[test]
+    fn invalid_header_is_rejected() {
+        let result = CensysProvider::builder("id", "secret")
+            .header("bad header", "x")
+            .build();
+        assert!(matches!(result, Err(I1Error::Config(_))));
+    }
+}
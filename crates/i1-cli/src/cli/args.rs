@@ -1,5 +1,8 @@
 //! Command-line argument definitions using clap.
 
+use crate::defend::{self, feeds::FeedFormat};
+use crate::logging::{self, LogFormat};
+use crate::output::group::GroupBy;
 use crate::output::OutputFormat;
 use clap::{Args, Parser, Subcommand};
 
@@ -25,13 +28,30 @@ pub struct Cli {
     #[arg(short, long, global = true, value_enum)]
     pub output: Option<OutputFormat>,
 
+    /// Only show these fields, in this order, e.g. `ip_str,country_code`
+    /// (dots reach into nested values, e.g. `data.port`)
+    #[arg(long, global = true, value_delimiter = ',', value_name = "KEYS")]
+    pub fields: Option<Vec<String>>,
+
     /// Explain what this command does
     #[arg(long, global = true)]
     pub explain: bool,
 
-    /// Increase verbosity
-    #[arg(short, long, global = true)]
-    pub verbose: bool,
+    /// Increase verbosity (-v for debug logs, -vv for trace logs)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only log warnings and errors
+    #[arg(long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Format of notes and warnings on stderr (default: text, or `log_format` in config)
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Log filter, e.g. `debug` or `warn,i1_shodan=trace` (overrides -v and --quiet)
+    #[arg(long, global = true, env = logging::FILTER_ENV, value_name = "FILTER")]
+    pub log_level: Option<String>,
 
     /// Disable colored output
     #[arg(long, global = true)]
@@ -56,9 +76,15 @@ pub enum Commands {
     /// Count results without using query credits
     Count(CountArgs),
 
+    /// Manage and run saved search queries
+    Query(QueryArgs),
+
     /// DNS lookups and domain information
     Dns(DnsArgs),
 
+    /// Domain report: passive DNS, live resolution and certificate exposure
+    Domain(DomainArgs),
+
     /// Show your public IP address
     Myip,
 
@@ -74,6 +100,9 @@ pub enum Commands {
 
     /// System integrity audit: binary hashes, root certs, trust scoring
     Audit(AuditArgs),
+
+    /// Network monitoring alert tools
+    Alert(AlertArgs),
 }
 
 // ============================================================================
@@ -125,9 +154,136 @@ pub struct SearchArgs {
     /// Search query (e.g., "apache country:US port:80")
     pub query: String,
 
+    #[command(flatten)]
+    pub options: SearchOptions,
+}
+
+/// Search flags, shared by `search` and `query run`.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Args, Debug, Clone)]
+pub struct SearchOptions {
     /// Page number (1-indexed)
-    #[arg(short, long, default_value = "1")]
+    #[arg(long, default_value = "1")]
     pub page: u32,
+
+    /// Fetch every page of results, starting at --page
+    #[arg(long)]
+    pub all: bool,
+
+    /// Show at most this many results (with --all, stop fetching once
+    /// there are enough)
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Request host summaries only, without service banners
+    #[arg(long)]
+    pub minify: bool,
+
+    /// Collapse matches into one row per key, with the ports, org, country
+    /// and most recent timestamp
+    #[arg(long, value_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// Keep only the first match for each IP
+    #[arg(long, conflicts_with = "group_by")]
+    pub unique_ips: bool,
+
+    /// Push the results to MISP as a new event (see `i1 config set misp-url`)
+    #[arg(long)]
+    pub to_misp: bool,
+}
+
+// ============================================================================
+// Query command - saved searches
+// ============================================================================
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    #[command(subcommand)]
+    pub command: QueryCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum QueryCommands {
+    /// Save a search query under a name
+    Save {
+        /// Name to save it as (letters, digits, `-`, `_`, `.`)
+        name: String,
+
+        /// Search query (e.g., 'port:3389 country:DE')
+        query: String,
+
+        /// Default facets, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        facets: Vec<String>,
+
+        /// Tags, comma-separated
+        #[arg(short, long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Replace an existing query with the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// List saved queries, with when each last ran
+    #[command(alias = "ls")]
+    List {
+        /// Only show queries with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+
+    /// Show one saved query
+    Show {
+        /// Saved query name
+        name: String,
+    },
+
+    /// Run a saved query through `search`
+    Run {
+        /// Saved query name
+        name: String,
+
+        #[command(flatten)]
+        options: SearchOptions,
+    },
+
+    /// Rename a saved query
+    Rename {
+        /// Current name
+        from: String,
+
+        /// New name
+        to: String,
+    },
+
+    /// Delete a saved query
+    #[command(alias = "rm")]
+    Delete {
+        /// Saved query name
+        name: String,
+    },
+
+    /// Export saved queries as TOML, for sharing
+    Export {
+        /// Queries to export (default: all)
+        names: Vec<String>,
+
+        /// Write to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Import queries from an exported TOML file
+    Import {
+        /// File written by `query export`
+        file: std::path::PathBuf,
+
+        /// Replace queries whose names are already taken
+        #[arg(short, long)]
+        force: bool,
+    },
 }
 
 // ============================================================================
@@ -158,13 +314,71 @@ pub enum DnsCommands {
         hostname: String,
     },
 
-    /// Reverse DNS lookup
+    /// Reverse DNS lookup for one or more IPs
     Reverse {
-        /// IP address
-        ip: String,
+        /// IP addresses
+        #[arg(required_unless_present_any = ["file", "stdin"])]
+        ips: Vec<String>,
+
+        /// Read IPs from a file, one per line
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Read IPs from stdin, one per line
+        #[arg(long)]
+        stdin: bool,
+
+        /// IPs sent per API request
+        #[arg(long, default_value_t = 100)]
+        batch_size: usize,
+
+        /// API requests in flight at once
+        #[arg(long, short = 'j', default_value_t = 4)]
+        concurrency: usize,
+    },
+
+    /// Show a domain's DNS records, grouped by type, and its subdomains
+    Domain {
+        /// Domain name (e.g., example.com)
+        domain: String,
+
+        /// Only show records of this type (A, AAAA, MX, NS, TXT, SOA, CNAME)
+        #[arg(long, short = 't')]
+        record_type: Option<i1_providers::DnsRecordType>,
+
+        /// Include historical records, with when each was last seen
+        #[arg(long)]
+        history: bool,
+
+        /// Compare the two most recent historical snapshots
+        #[arg(long, requires = "history")]
+        diff: bool,
     },
 }
 
+// ============================================================================
+// Domain command
+// ============================================================================
+
+#[derive(Args, Debug)]
+pub struct DomainArgs {
+    /// Domain name (e.g., example.com)
+    pub domain: String,
+
+    /// Resolve the apex and subdomains live and compare with passive DNS
+    /// (the default; needs the `recon` feature)
+    #[arg(long, overrides_with = "no_live")]
+    pub live: bool,
+
+    /// Show passive DNS only
+    #[arg(long, overrides_with = "live")]
+    pub no_live: bool,
+
+    /// Most subdomains to list and resolve
+    #[arg(long, default_value_t = 50)]
+    pub limit_subdomains: usize,
+}
+
 // ============================================================================
 // Defend command
 // ============================================================================
@@ -189,16 +403,20 @@ pub enum DefendCommands {
 
     /// Ban an IP address or CIDR range
     Ban {
-        /// IP address or CIDR to block
+        /// IPv4 or IPv6 address or CIDR to block
         target: String,
 
         /// Treat target as AS number
         #[arg(long, short = 'a')]
         as_number: bool,
 
-        /// Show what would happen without making changes
+        /// Show the rules `export` would add, without making changes
         #[arg(long)]
         dry_run: bool,
+
+        /// Firewall format the dry run previews: nftables, iptables, pf
+        #[arg(long, default_value = "nftables")]
+        format: String,
     },
 
     /// Remove an IP or AS from the block list
@@ -210,22 +428,44 @@ pub enum DefendCommands {
     /// Manage whitelist (IPs that are never blocked)
     Whitelist(WhitelistArgs),
 
-    /// Export firewall rules
+    /// Export firewall rules or a DNS response policy zone
     Export {
-        /// Output format: nftables, iptables, pf
+        /// Output format: nftables, iptables, pf, rpz
         #[arg(long, default_value = "nftables")]
         format: String,
+
+        /// Zone origin for --format rpz
+        #[arg(long, default_value = defend::rpz::DEFAULT_ORIGIN)]
+        zone: String,
+    },
+
+    /// Check exported firewall rules for syntax errors without applying them
+    TestRules {
+        /// Formats to check: nftables, iptables, pf (default: all three)
+        #[arg(long, value_delimiter = ',')]
+        format: Vec<String>,
     },
 
-    /// Import IPs from file or stdin
+    /// Import a blocklist feed from a URL, file or stdin
     Import {
+        /// Feed URL or file path ("-" for stdin)
+        source: Option<String>,
+
+        /// Feed format
+        #[arg(long, short, value_enum, default_value = "cidr")]
+        format: FeedFormat,
+
         /// Read from stdin
         #[arg(long)]
         stdin: bool,
 
         /// Read from file
-        #[arg(long)]
+        #[arg(long, conflicts_with = "source")]
         file: Option<String>,
+
+        /// Show what would be imported without saving
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Undo the last change
@@ -408,9 +648,13 @@ pub enum GeoblockCommands {
         #[arg(long, short, default_value = "inbound")]
         direction: String,
 
-        /// Show what would happen without making changes
+        /// Show the rules `export` would add, without making changes
         #[arg(long)]
         dry_run: bool,
+
+        /// Firewall format the dry run previews: nftables, iptables, pf
+        #[arg(long, default_value = "nftables")]
+        format: String,
     },
 
     /// Unblock a country
@@ -480,6 +724,111 @@ pub enum ConfigCommands {
 
     /// Show config file path
     Path,
+
+    /// Replace the Shodan API key after checking the new one works
+    ///
+    /// Prompts for the key, or reads it from stdin when piped, so it never
+    /// lands in shell history. The old key is kept as `previous_api_key`
+    /// for 7 days.
+    RotateKey,
+
+    /// Check that the Shodan API key works and show its plan and credits
+    Verify,
+}
+
+// ============================================================================
+// Alert command
+// ============================================================================
+
+#[derive(Args, Debug)]
+pub struct AlertArgs {
+    #[command(subcommand)]
+    pub command: AlertCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AlertCommands {
+    /// List your network alerts
+    List {
+        /// Only active alerts expiring within this many days
+        #[arg(long, value_name = "DAYS")]
+        expiring: Option<u32>,
+
+        /// Leave out expired alerts
+        #[arg(long)]
+        active: bool,
+
+        /// Page to show (1-based)
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+
+        /// Alerts per page
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+        per_page: u32,
+    },
+
+    /// Create an alert watching IPs or networks
+    Create {
+        /// Alert name
+        name: String,
+
+        /// IP addresses or CIDR ranges to watch
+        #[arg(long, required = true, value_delimiter = ',')]
+        ips: Vec<String>,
+
+        /// Expire the alert after this many days (0 = never)
+        #[arg(long, default_value_t = 0)]
+        expires: u32,
+    },
+
+    /// Show one alert with its networks and triggers
+    Get {
+        /// Alert ID
+        id: String,
+    },
+
+    /// Delete an alert
+    Delete {
+        /// Alert ID
+        id: String,
+    },
+
+    /// List the triggers an alert can enable
+    Triggers,
+
+    /// Print incoming alert webhook payloads until Ctrl-C
+    Listen {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to bind (point your tunnel here)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: std::net::IpAddr,
+    },
+
+    /// Follow banners for your alerted networks live, via Shodan's stream
+    ///
+    /// Reconnects with backoff when the stream drops or goes silent.
+    /// Shodan can't replay what was missed, so each gap is logged with
+    /// its start and end time.
+    Stream {
+        /// Only this alert's networks (default: every alert)
+        id: Option<String>,
+
+        /// Run this shell command per banner, with the banner as JSON on
+        /// stdin and the host in `I1_IP` and `I1_PORT`
+        #[arg(long, value_name = "COMMAND")]
+        exec: Option<String>,
+
+        /// POST each banner as JSON to this URL
+        #[arg(long, value_name = "URL")]
+        notify_url: Option<String>,
+
+        /// Seconds without data or a keep-alive before reconnecting
+        #[arg(long, value_name = "SECS", default_value_t = 90)]
+        heartbeat: u64,
+    },
 }
 
 // ============================================================================
@@ -488,6 +837,21 @@ pub enum ConfigCommands {
 
 #[derive(Args, Debug)]
 pub struct AuditArgs {
+    /// Bypass the local consensus cache and re-query the network
+    #[arg(long, global = true)]
+    pub refresh_consensus: bool,
+
+    /// Hash every binary again instead of reusing cached hashes for
+    /// files whose inode, size and mtime are unchanged
+    #[arg(long, global = true)]
+    pub rehash: bool,
+
+    /// Hash algorithm for binaries and cert fingerprints (sha256, blake3).
+    /// BLAKE3 is much faster but is looked up in its own consensus
+    /// namespace. Defaults to the `audit_hash` setting, else sha256.
+    #[arg(long, global = true)]
+    pub hash: Option<i1_audit::HashAlgorithm>,
+
     #[command(subcommand)]
     pub command: AuditCommands,
 }
@@ -507,23 +871,80 @@ pub enum AuditCommands {
         /// Additional paths to scan (besides system defaults)
         #[arg(long, value_delimiter = ',')]
         paths: Option<Vec<String>>,
+
+        /// Also hash and score the shared libraries loaded by running
+        /// processes (Linux; run as root to see every process)
+        #[arg(long)]
+        libs: bool,
+
+        /// Query bin.i1.is for every hash and score against network
+        /// consensus, flagging unknown and thinly vouched binaries
+        #[arg(long)]
+        consensus: bool,
+
+        /// Compare against a saved baseline (name or file) instead of
+        /// listing trust scores. Exits 1 if anything differs.
+        #[arg(
+            long,
+            value_name = "NAME|FILE",
+            conflicts_with_all = ["publish", "below", "paths", "libs", "consensus"]
+        )]
+        baseline: Option<String>,
     },
 
+    /// Save and inspect golden-image baselines
+    Baseline(BaselineArgs),
+
     /// Show running process metrics
     Processes,
 
     /// Root certificate store inventory
     Certs {
-        /// Cross-check certs against network consensus
+        /// Check self-signatures, key and digest strength, validity periods
+        /// and subject collisions, and cross-check certs against network
+        /// consensus. Exits nonzero on critical findings.
         #[arg(long)]
         validate: bool,
     },
 
-    /// Full system audit (binaries + processes + certs)
+    /// Loaded kernel module inventory
+    ///
+    /// Flags unsigned or out-of-tree modules and modules loaded since
+    /// the last published snapshot.
+    Modules,
+
+    /// Full system audit (binaries + processes + certs + modules)
+    ///
+    /// With --libs, shared libraries loaded by running processes too.
+    /// Ends with an overall risk grade (A-F) and the most concerning items.
     Full {
         /// Publish results to the i1.is network
         #[arg(long)]
         publish: bool,
+
+        /// Exit nonzero when the grade is worse than this (A-F), for
+        /// provisioning pipelines
+        #[arg(long, value_name = "GRADE")]
+        fail_below: Option<i1_audit::risk::Grade>,
+
+        /// TOML file overriding the grading weights and thresholds
+        /// (`[weights]` and `[thresholds]` tables)
+        #[arg(long, value_name = "FILE")]
+        profile: Option<std::path::PathBuf>,
+
+        /// Also write a self-contained HTML report to this file
+        #[arg(long, value_name = "FILE")]
+        report: Option<std::path::PathBuf>,
+
+        /// Also audit the shared libraries loaded by running processes,
+        /// flagging any loaded from temp or home directories
+        #[arg(long)]
+        libs: bool,
+
+        /// Query bin.i1.is for every binary hash, so consensus counts
+        /// toward trust scores and the grade
+        #[arg(long)]
+        consensus: bool,
     },
 
     /// Generate a QR code for independent TTL verification
@@ -539,5 +960,87 @@ pub enum AuditCommands {
         /// Only print the verification URL, skip QR generation
         #[arg(long)]
         url_only: bool,
+
+        /// Sign the trust digest with this i1-ca node key (PKCS#8 PEM),
+        /// so verifiers can tell it came from this node
+        #[arg(long)]
+        sign_key: Option<String>,
+
+        /// Always encode the short URL; the verify page reads the rest
+        /// from DNS (default: only when the full URL makes a dense QR)
+        #[arg(long)]
+        compact: bool,
+
+        /// Resolve the signal record from this box and print the verdict
+        /// instead of a QR code; exits non-zero if it was tampered with
+        #[arg(long, conflicts_with_all = ["url_only", "compact"])]
+        check: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct BaselineArgs {
+    #[command(subcommand)]
+    pub command: BaselineCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BaselineCommands {
+    /// Snapshot this machine as a named baseline
+    Save {
+        /// Baseline name (letters, digits, '.', '_', '-')
+        name: String,
+
+        /// Additional paths to scan (besides system defaults)
+        #[arg(long, value_delimiter = ',')]
+        paths: Option<Vec<String>>,
+
+        /// Replace an existing baseline with the same name
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List saved baselines
+    List,
+
+    /// Show what a baseline contains
+    Show {
+        /// Baseline name or file
+        name: String,
     },
+
+    /// Write a baseline to a single file for other hosts
+    Export {
+        /// Baseline name
+        name: String,
+
+        /// Destination file
+        file: std::path::PathBuf,
+    },
+
+    /// Save a baseline file exported on another host
+    Import {
+        /// Baseline file
+        file: std::path::PathBuf,
+
+        /// Save under this name instead of the one in the file
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Replace an existing baseline with the same name
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn arguments_are_consistent() {
+        // Catches clashes such as a subcommand flag reusing a global short
+        Cli::command().debug_assert();
+    }
 }
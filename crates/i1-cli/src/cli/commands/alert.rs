@@ -1,195 +1,663 @@
-//! `showdi1 alert` - Network monitoring alerts.
-
-use anyhow::Result;
+//! `i1 alert` - Network monitoring alert tools.
+//!
+//! The alert API returns every alert at once, so `alert list` filters and
+//! pages them locally.
+//!
+//! `alert listen` is a developer aid: a tiny HTTP server that prints each
+//! webhook delivery it receives, so notifier payloads can be inspected
+//! against a real alert (e.g. through a tunnel) while building on them.
+//!
+//! `alert stream` follows the same events without a public endpoint, over
+//! Shodan's streaming API. A dropped or silent stream is reopened with
+//! backoff; Shodan can't replay what it sent meanwhile, so every gap is
+//! logged with its start and end.
+
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Local};
 use colored::Colorize;
+use futures_util::StreamExt;
+use i1::Banner;
+use i1_core::{Alert, AlertListExt, AlertPage, I1Error};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::Write as _;
+use std::net::SocketAddr;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
 
 use super::Context;
 use crate::cli::args::{AlertArgs, AlertCommands};
-use crate::education::Explain;
+use crate::output::render::{Column, Document, Renderer};
 use crate::output::OutputFormat;
 
+/// Largest webhook body accepted.
+const MAX_BODY: usize = 4 * 1024 * 1024;
+
+/// How long a sender gets to deliver its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Headers Shodan sends with every alert webhook.
+const ALERT_HEADERS: [&str; 3] = [
+    "shodan-alert-id",
+    "shodan-alert-name",
+    "shodan-alert-trigger",
+];
+
+/// Banner fields every alert payload carries beyond what [`i1_core::Service`] requires.
+const BANNER_FIELDS: [&str; 2] = ["ip_str", "timestamp"];
+
+/// First wait before reopening a lost stream.
+const BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+/// Longest wait between attempts; a stream that stayed up this long
+/// starts over from [`BACKOFF_MIN`].
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Execute the alert command.
 pub async fn execute(ctx: Context, args: AlertArgs) -> Result<()> {
     match args.command {
-        AlertCommands::List => list_alerts(ctx).await,
-        AlertCommands::Create { name, ips, expires } => create_alert(ctx, &name, &ips, expires).await,
+        AlertCommands::List {
+            expiring,
+            active,
+            page,
+            per_page,
+        } => list_alerts(ctx, expiring, active, AlertPage::new(page, per_page)).await,
+        AlertCommands::Create { name, ips, expires } => {
+            create_alert(ctx, &name, &ips, expires).await
+        }
         AlertCommands::Get { id } => get_alert(ctx, &id).await,
         AlertCommands::Delete { id } => delete_alert(ctx, &id).await,
         AlertCommands::Triggers => list_triggers(ctx).await,
+        AlertCommands::Listen { port, bind } => listen(&ctx, SocketAddr::new(bind, port)).await,
+        AlertCommands::Stream {
+            id,
+            exec,
+            notify_url,
+            heartbeat,
+        } => {
+            let hooks = Hooks {
+                exec,
+                notify_url,
+                http: reqwest::Client::new(),
+            };
+            stream(&ctx, id.as_deref(), &hooks, Duration::from_secs(heartbeat)).await
+        }
     }
 }
 
-async fn list_alerts(ctx: Context) -> Result<()> {
-    if ctx.explain {
-        Explain::alert_list().print();
+/// Show one page of the account's alerts, optionally only active ones or
+/// those about to lapse.
+async fn list_alerts(
+    ctx: Context,
+    expiring: Option<u32>,
+    active: bool,
+    page: AlertPage,
+) -> Result<()> {
+    let mut alerts = ctx.shodan_provider()?.alerts().list().await?;
+    if active {
+        alerts = alerts.active_only();
     }
+    if let Some(days) = expiring {
+        alerts = alerts.expiring_within(days);
+    }
+    let total = alerts.len();
+    let document = list_document(page.apply(&alerts), total, page, expiring)?;
+    Renderer::from_context(&ctx).print_document(&document)
+}
 
-    let client = ctx.client()?;
-    let alerts = client.alerts().list().await?;
-
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&alerts)?);
+/// One page of alerts as a document with one row per alert.
+fn list_document(
+    shown: &[Alert],
+    total: usize,
+    page: AlertPage,
+    expiring: Option<u32>,
+) -> Result<Document> {
+    let columns = vec![
+        Column::new("id", "ID"),
+        Column::new("name", "Name"),
+        Column::new("filters.ip", "IPs"),
+        Column::new("expiration", "Expires"),
+        Column::new("expired", "Expired"),
+    ];
+    let empty = match expiring {
+        Some(days) => format!("No alerts expire within {days} days."),
+        None if total > 0 => "No alerts on this page.".to_string(),
+        None => {
+            "No alerts configured. Create one with: i1 alert create <NAME> --ips <IP>".to_string()
         }
-        _ => {
-            println!("{}", "Your Alerts:".bold());
-            println!();
+    };
+    let document = Document::new(&shown, columns)?;
+    let table = document.rows_table().empty(&empty);
+    let document = document.table(Some("Your Alerts:"), table);
+
+    let pages = page.page_count(total);
+    Ok(if pages > 1 {
+        document.note(format!(
+            "Page {} of {pages} ({total} alerts)",
+            page.page.max(1)
+        ))
+    } else {
+        document
+    })
+}
 
-            if alerts.is_empty() {
-                println!("  No alerts configured.");
-                println!();
-                println!("  Create one with: {} alert create <NAME> --ips <IP>", "showdi1".cyan());
-            } else {
-                for alert in &alerts {
-                    println!("  {} {}", alert.id.cyan(), alert.name);
-                    if !alert.filters.ip.is_empty() {
-                        println!("    IPs: {}", alert.filters.ip.join(", "));
-                    }
-                    if let Some(exp) = &alert.expires {
-                        println!("    Expires: {}", exp);
-                    }
-                    println!();
-                }
-            }
+async fn create_alert(ctx: Context, name: &str, ips: &[String], expires: u32) -> Result<()> {
+    let alert = ips
+        .iter()
+        .fold(
+            ctx.shodan_provider()?.alerts().create(name),
+            i1::CreateAlert::ip,
+        )
+        .expires_in_days(expires)
+        .send()
+        .await
+        .context("Could not create the alert")?;
+
+    if ctx.output_format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&alert)?);
+    } else {
+        println!("{}", "Alert created!".green().bold());
+        println!();
+        println!("  {} {}", "ID:".bold(), alert.id.cyan());
+        println!("  {} {}", "Name:".bold(), alert.name);
+        println!("  {} {}", "IPs:".bold(), alert.filters.ip.join(", "));
+
+        if expires > 0 {
+            println!("  {} {} days", "Expires in:".bold(), expires);
+        } else {
+            println!("  {} Never", "Expires:".bold());
         }
+
+        println!();
+        println!("View details: {} alert get {}", "i1".cyan(), alert.id);
     }
 
     Ok(())
 }
 
-async fn create_alert(ctx: Context, name: &str, ips: &[String], expires: u32) -> Result<()> {
-    if ctx.explain {
-        Explain::alert_create().print();
-    }
-
-    let client = ctx.client()?;
+async fn get_alert(ctx: Context, id: &str) -> Result<()> {
+    let alert = ctx.shodan_provider()?.alerts().get(id).await?;
+
+    if ctx.output_format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&alert)?);
+    } else {
+        println!("{} {}", "Alert:".bold(), alert.name.cyan());
+        println!();
+        println!("  {} {}", "ID:".bold(), alert.id);
+        println!(
+            "  {} {}",
+            "Created:".bold(),
+            alert.created.as_deref().unwrap_or("?")
+        );
+
+        if alert.expired {
+            println!("  {} expired", "Expires:".bold());
+        } else if let Some(at) = alert.expiration {
+            println!(
+                "  {} {}",
+                "Expires:".bold(),
+                at.format("%Y-%m-%d %H:%M UTC")
+            );
+        }
 
-    let mut builder = client.alerts().create(name);
+        println!();
+        println!("{}", "Monitored IPs:".bold().underline());
+        for ip in &alert.filters.ip {
+            println!("  {ip}");
+        }
 
-    for ip in ips {
-        builder = builder.ip(ip);
+        if !alert.triggers.is_empty() {
+            println!();
+            println!("{}", "Triggers:".bold().underline());
+            let mut triggers: Vec<_> = alert.triggers.iter().collect();
+            triggers.sort();
+            for (name, enabled) in triggers {
+                let status = if *enabled {
+                    "enabled".green()
+                } else {
+                    "disabled".dimmed()
+                };
+                println!("  {name} [{status}]");
+            }
+        }
     }
 
-    if expires > 0 {
-        builder = builder.expires_in_days(expires);
+    Ok(())
+}
+
+async fn delete_alert(ctx: Context, id: &str) -> Result<()> {
+    ctx.shodan_provider()?.alerts().delete(id).await?;
+
+    if ctx.output_format == OutputFormat::Json {
+        println!("{}", serde_json::json!({ "deleted": id }));
+    } else {
+        println!("{} Alert {} deleted.", "Success:".green().bold(), id.cyan());
     }
 
-    let alert = builder.send().await?;
+    Ok(())
+}
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&alert)?);
-        }
-        _ => {
-            println!("{}", "Alert created!".green().bold());
-            println!();
-            println!("  {} {}", "ID:".bold(), alert.id.cyan());
-            println!("  {} {}", "Name:".bold(), alert.name);
-            println!("  {} {}", "IPs:".bold(), alert.filters.ip.join(", "));
-
-            if expires > 0 {
-                println!("  {} {} days", "Expires in:".bold(), expires);
-            } else {
-                println!("  {} Never", "Expires:".bold());
+async fn list_triggers(ctx: Context) -> Result<()> {
+    let triggers = ctx.shodan_provider()?.alerts().triggers().await?;
+
+    if ctx.output_format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&triggers)?);
+    } else {
+        println!("{}", "Available Alert Triggers:".bold());
+        println!();
+
+        for trigger in &triggers {
+            println!("  {}", trigger.name.cyan().bold());
+            if let Some(desc) = &trigger.description {
+                println!("    {desc}");
+            }
+            if let Some(rule) = &trigger.rule {
+                println!("    Rule: {}", rule.dimmed());
             }
-
             println!();
-            println!("View details: {} alert get {}", "showdi1".cyan(), alert.id);
         }
     }
 
     Ok(())
 }
 
-async fn get_alert(ctx: Context, id: &str) -> Result<()> {
-    if ctx.explain {
-        Explain::alert_get().print();
-    }
+/// One received webhook request.
+struct Delivery {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
 
-    let client = ctx.client()?;
-    let alert = client.alerts().get(id).await?;
+impl Delivery {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&alert)?);
-        }
-        _ => {
-            println!("{} {}", "Alert:".bold(), alert.name.cyan());
-            println!();
-            println!("  {} {}", "ID:".bold(), alert.id);
-            println!("  {} {}", "Created:".bold(), alert.created.as_deref().unwrap_or("?"));
+/// Accept webhook deliveries one at a time until the process is interrupted.
+async fn listen(ctx: &Context, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {addr}"))?;
+
+    if ctx.output_format != OutputFormat::Json {
+        println!(
+            "{} http://{}",
+            "Listening for alert webhooks on".bold(),
+            listener.local_addr()?
+        );
+        println!("Press Ctrl-C to stop.");
+        println!();
+    }
 
-            if let Some(exp) = &alert.expires {
-                println!("  {} {}", "Expires:".bold(), exp);
+    loop {
+        let (mut stream, peer) = listener.accept().await?;
+        let delivery = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+            Ok(Ok(delivery)) => delivery,
+            Ok(Err(e)) => {
+                tracing::warn!(%peer, "bad request: {e:#}");
+                let _ = respond(&mut stream, "400 Bad Request").await;
+                continue;
             }
-
-            println!();
-            println!("{}", "Monitored IPs:".bold().underline());
-            for ip in &alert.filters.ip {
-                println!("  {}", ip);
+            Err(_) => {
+                tracing::warn!(%peer, "timed out reading request");
+                continue;
             }
+        };
 
-            if !alert.triggers.is_empty() {
-                println!();
-                println!("{}", "Triggers:".bold().underline());
-                for (name, enabled) in &alert.triggers {
-                    let status = if *enabled { "enabled".green() } else { "disabled".dimmed() };
-                    println!("  {} [{}]", name, status);
-                }
-            }
+        // Acknowledge before printing so the sender never retries.
+        let _ = respond(&mut stream, "200 OK").await;
+        print_delivery(ctx, peer, &delivery)?;
+    }
+}
+
+/// Read one HTTP/1.1 request with a `Content-Length` body.
+async fn read_request(stream: &mut TcpStream) -> Result<Delivery> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        anyhow::bail!("malformed request line");
+    };
+    let mut delivery = Delivery {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            anyhow::bail!("connection closed before end of headers");
         }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').context("malformed header")?;
+        delivery
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
     }
 
-    Ok(())
+    if delivery
+        .header("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        anyhow::bail!("chunked bodies are not supported");
+    }
+    let len = delivery
+        .header("content-length")
+        .map(str::parse::<usize>)
+        .transpose()
+        .context("invalid Content-Length")?
+        .unwrap_or(0);
+    if len > MAX_BODY {
+        anyhow::bail!("body of {len} bytes exceeds the {MAX_BODY} byte limit");
+    }
+    delivery.body = vec![0; len];
+    reader.read_exact(&mut delivery.body).await?;
+
+    Ok(delivery)
 }
 
-async fn delete_alert(ctx: Context, id: &str) -> Result<()> {
-    if ctx.explain {
-        Explain::alert_delete().print();
-    }
+async fn respond(stream: &mut TcpStream, status: &str) -> std::io::Result<()> {
+    let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
 
-    let client = ctx.client()?;
-    client.alerts().delete(id).await?;
+/// Parse the body and check it against the structure alert webhooks carry:
+/// a JSON service banner plus the alert headers.
+fn check_payload(delivery: &Delivery) -> (Option<Value>, Vec<String>) {
+    let mut warnings = Vec::new();
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::json!({ "deleted": id }));
+    if delivery.method != "POST" {
+        warnings.push(format!("expected POST, got {}", delivery.method));
+    }
+    for name in ALERT_HEADERS {
+        if delivery.header(name).is_none() {
+            warnings.push(format!("missing {name} header"));
         }
-        _ => {
-            println!("{} Alert {} deleted.", "Success:".green().bold(), id.cyan());
+    }
+
+    let payload = match serde_json::from_slice::<Value>(&delivery.body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warnings.push(format!("body is not JSON: {e}"));
+            return (None, warnings);
+        }
+    };
+    if let Err(e) = i1_core::Service::deserialize(&payload) {
+        warnings.push(format!("payload is not a service banner: {e}"));
+    }
+    for field in BANNER_FIELDS {
+        if payload.get(field).is_none() {
+            warnings.push(format!("payload has no `{field}` field"));
         }
     }
 
-    Ok(())
+    (Some(payload), warnings)
 }
 
-async fn list_triggers(ctx: Context) -> Result<()> {
-    if ctx.explain {
-        Explain::alert_triggers().print();
+fn print_delivery(ctx: &Context, peer: SocketAddr, delivery: &Delivery) -> Result<()> {
+    let (payload, warnings) = check_payload(delivery);
+    let received = chrono::Local::now();
+
+    if ctx.output_format == OutputFormat::Json {
+        let body = payload
+            .unwrap_or_else(|| Value::String(String::from_utf8_lossy(&delivery.body).into_owned()));
+        let record = serde_json::json!({
+            "received": received.to_rfc3339(),
+            "peer": peer.to_string(),
+            "method": delivery.method,
+            "path": delivery.path,
+            "alert": {
+                "id": delivery.header("shodan-alert-id"),
+                "name": delivery.header("shodan-alert-name"),
+                "trigger": delivery.header("shodan-alert-trigger"),
+            },
+            "payload": body,
+            "warnings": warnings,
+        });
+        println!("{}", serde_json::to_string(&record)?);
+        return Ok(());
     }
 
-    let client = ctx.client()?;
-    let triggers = client.alerts().triggers().await?;
+    println!(
+        "{} {} {} from {}",
+        received.format("%Y-%m-%d %H:%M:%S").to_string().dimmed(),
+        delivery.method.bold(),
+        delivery.path,
+        peer
+    );
+    if let Some(name) = delivery.header("shodan-alert-name") {
+        let id = delivery.header("shodan-alert-id").unwrap_or("?");
+        println!("  {} {} ({})", "Alert:".bold(), name.cyan(), id);
+    }
+    if let Some(trigger) = delivery.header("shodan-alert-trigger") {
+        println!("  {} {}", "Trigger:".bold(), trigger);
+    }
+    for warning in &warnings {
+        println!("  {} {}", "Warning:".bright_yellow(), warning);
+    }
+    println!();
+    match payload {
+        Some(payload) => println!("{}", serde_json::to_string_pretty(&payload)?),
+        None => println!("{}", String::from_utf8_lossy(&delivery.body)),
+    }
+    println!();
+
+    Ok(())
+}
+
+/// What to run for each streamed banner.
+struct Hooks {
+    exec: Option<String>,
+    notify_url: Option<String>,
+    http: reqwest::Client,
+}
+
+/// Follow the alert stream until the process is interrupted.
+async fn stream(ctx: &Context, id: Option<&str>, hooks: &Hooks, heartbeat: Duration) -> Result<()> {
+    let shodan = ctx.shodan_provider()?.stream().heartbeat(heartbeat);
+
+    if ctx.output_format != OutputFormat::Json {
+        let scope = id.map_or_else(|| "all alerts".to_string(), |id| format!("alert {id}"));
+        println!("{} ({scope})", "Streaming alert banners".bold());
+        println!("Press Ctrl-C to stop.");
+        println!();
+    }
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&triggers)?);
+    let mut backoff = BACKOFF_MIN;
+    // Set from losing the stream until it is open again
+    let mut lost: Option<DateTime<Local>> = None;
+    loop {
+        let opened = match id {
+            Some(id) => shodan.alerts_for(id).await,
+            None => shodan.alerts().await,
+        };
+        let mut banners = match opened {
+            Ok(banners) => banners,
+            Err(e) if e.is_auth() || matches!(e, I1Error::NotFound { .. }) => {
+                return Err(e).context("Could not open the Shodan alert stream");
+            }
+            Err(e) => {
+                let since = *lost.get_or_insert_with(Local::now);
+                tracing::warn!(
+                    since = %since.to_rfc3339(),
+                    "could not reopen the alert stream: {e}; retrying in {}s",
+                    backoff.as_secs()
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+                continue;
+            }
+        };
+        if let Some(since) = lost.take() {
+            let now = Local::now();
+            tracing::warn!(
+                gap_start = %since.to_rfc3339(),
+                gap_end = %now.to_rfc3339(),
+                "alert stream reopened; banners sent between {} and {} were missed",
+                since.format("%Y-%m-%d %H:%M:%S"),
+                now.format("%Y-%m-%d %H:%M:%S")
+            );
         }
-        _ => {
-            println!("{}", "Available Alert Triggers:".bold());
-            println!();
+        let opened_at = Instant::now();
 
-            for trigger in &triggers {
-                println!("  {}", trigger.name.cyan().bold());
-                if let Some(desc) = &trigger.description {
-                    println!("    {}", desc);
+        let reason = loop {
+            match banners.next().await {
+                Some(Ok(banner)) => {
+                    print_banner(ctx, &banner)?;
+                    run_hooks(hooks, &banner).await;
                 }
-                if let Some(rule) = &trigger.rule {
-                    println!("    Rule: {}", rule.dimmed());
+                Some(Err(I1Error::Timeout(secs))) => {
+                    break format!("no data or keep-alive for {secs}s");
                 }
-                println!();
+                Some(Err(e)) => break e.to_string(),
+                None => break "closed by Shodan".to_string(),
             }
+        };
+
+        let since = *lost.insert(Local::now());
+        if opened_at.elapsed() >= BACKOFF_MAX {
+            backoff = BACKOFF_MIN;
+        }
+        tracing::warn!(
+            since = %since.to_rfc3339(),
+            "alert stream lost at {} ({reason}); reconnecting in {}s",
+            since.format("%Y-%m-%d %H:%M:%S"),
+            backoff.as_secs()
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+/// One line per banner, or the banner as NDJSON.
+fn print_banner(ctx: &Context, banner: &Banner) -> Result<()> {
+    if ctx.output_format == OutputFormat::Json {
+        println!("{}", serde_json::to_string(banner)?);
+        return Ok(());
+    }
+
+    let service = &banner.service;
+    let seen = service
+        .observed_at()
+        .map_or_else(Local::now, |at| at.with_timezone(&Local));
+    let mut parts = vec![
+        seen.format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+            .dimmed()
+            .to_string(),
+        format!("{}:{}/{}", banner.ip_str, service.port, service.transport)
+            .bold()
+            .to_string(),
+    ];
+    let product = [service.product.as_deref(), service.version.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !product.is_empty() {
+        parts.push(product.cyan().to_string());
+    }
+    if let Some(org) = &banner.org {
+        parts.push(format!("[{org}]"));
+    }
+    if let Some(hostname) = banner.hostnames.first() {
+        parts.push(hostname.dimmed().to_string());
+    }
+    if !service.vulns.is_empty() {
+        parts.push(format!("{} vulns", service.vulns.len()).red().to_string());
+    }
+    println!("{}", parts.join(" "));
+    Ok(())
+}
+
+/// Run the per-banner hooks. A failing hook is logged, not fatal, so the
+/// stream keeps going.
+async fn run_hooks(hooks: &Hooks, banner: &Banner) {
+    if let Some(command) = &hooks.exec {
+        if let Err(e) = exec(command, banner) {
+            tracing::warn!(ip = %banner.ip_str, "--exec failed: {e:#}");
         }
     }
+    if let Some(url) = &hooks.notify_url {
+        let sent = hooks
+            .http
+            .post(url)
+            .json(banner)
+            .timeout(READ_TIMEOUT)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = sent {
+            tracing::warn!(ip = %banner.ip_str, "--notify-url failed: {}", e.without_url());
+        }
+    }
+}
 
+/// Run `command` through the shell with the banner as JSON on stdin, and
+/// the host in `I1_IP` and `I1_PORT`.
+fn exec(command: &str, banner: &Banner) -> Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("I1_IP", &banner.ip_str)
+        .env("I1_PORT", banner.service.port.to_string())
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run `{command}`"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may not read its input at all
+        let _ = stdin.write_all(&serde_json::to_vec(banner)?);
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("`{command}` exited with {status}");
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render::{all_formats, assert_golden};
+    use serde_json::json;
+
+    #[test]
+    fn golden_list() {
+        let alerts: Vec<Alert> = serde_json::from_value(json!([
+            {
+                "id": "OYPRB8IR9Z35AZPR",
+                "name": "office",
+                "filters": {"ip": ["198.51.100.0/24", "203.0.113.7"]},
+                "expiration": "2026-11-01T12:00:00.000000",
+                "expired": false
+            },
+            {
+                "id": "W3GHLW8ZE7SL1UVM",
+                "name": "lab",
+                "filters": {"ip": ["192.0.2.0/28"]},
+                "expired": true
+            }
+        ]))
+        .unwrap();
+        let page = AlertPage::new(1, 2);
+        let document = list_document(page.apply(&alerts), 3, page, None).unwrap();
+        assert_golden("alert_list.txt", &all_formats(&document, None));
+        let fields = all_formats(&document, Some(&["id", "expired"]));
+        assert_golden("alert_list_fields.txt", &fields);
+    }
+}
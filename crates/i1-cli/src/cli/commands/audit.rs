@@ -1,41 +1,121 @@
 //! Audit command implementation — system integrity checks.
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use colored::Colorize;
+use i1_audit::baseline::Baseline;
+use i1_audit::risk::{Grade, RiskProfile, RiskReport};
+use i1_audit::verify::{Verdict, VerifyResult};
 
-use crate::cli::args::{AuditArgs, AuditCommands};
+use crate::cli::args::{AuditArgs, AuditCommands, BaselineCommands};
 use crate::output::OutputFormat;
 
 use super::Context;
 
 /// Execute the audit command.
-pub async fn execute(ctx: Context, args: AuditArgs) -> Result<()> {
+pub async fn execute(mut ctx: Context, args: AuditArgs) -> Result<()> {
+    // --hash overrides the configured default.
+    if let Some(algorithm) = args.hash {
+        ctx.audit_hash = algorithm;
+    }
+
     match args.command {
+        // clap rejects --baseline together with the listing options.
+        AuditCommands::Binaries {
+            baseline: Some(baseline),
+            ..
+        } => audit_binaries_against(&ctx, &baseline, args.rehash).await,
         AuditCommands::Binaries {
             publish,
             below,
             paths,
-        } => audit_binaries(&ctx, publish, below, paths.as_deref()).await,
+            libs,
+            consensus,
+            baseline: None,
+        } => {
+            audit_binaries(
+                &ctx,
+                publish,
+                below,
+                paths.as_deref(),
+                libs,
+                consensus,
+                args.refresh_consensus,
+                args.rehash,
+            )
+            .await
+        }
+        AuditCommands::Baseline(baseline) => {
+            audit_baseline(&ctx, baseline.command, args.rehash).await
+        }
         AuditCommands::Processes => audit_processes(&ctx).await,
-        AuditCommands::Certs { validate: _ } => audit_certs(&ctx).await,
-        AuditCommands::Full { publish } => audit_full(&ctx, publish).await,
-        AuditCommands::Verify { output, url_only } => {
-            audit_verify(&ctx, &output, url_only).await
+        AuditCommands::Certs { validate } => {
+            audit_certs(&ctx, validate, args.refresh_consensus).await
+        }
+        AuditCommands::Modules => audit_modules(&ctx).await,
+        AuditCommands::Full {
+            publish,
+            fail_below,
+            profile,
+            report,
+            libs,
+            consensus,
+        } => {
+            let profile = load_risk_profile(profile.as_deref())?;
+            audit_full(
+                &ctx,
+                publish,
+                libs,
+                consensus,
+                args.refresh_consensus,
+                args.rehash,
+                &profile,
+                fail_below,
+                report.as_deref(),
+            )
+            .await
+        }
+        AuditCommands::Verify {
+            output,
+            url_only,
+            sign_key,
+            compact,
+            check,
+        } => {
+            audit_verify(
+                &ctx,
+                &output,
+                url_only,
+                sign_key.as_deref(),
+                compact,
+                check,
+                args.rehash,
+            )
+            .await
         }
     }
 }
 
-/// Audit system binaries: discover, hash, score.
+/// Audit system binaries: discover, hash, score. With `libs`, the shared
+/// libraries loaded by running processes are listed alongside; with
+/// `consensus`, every hash is checked against bin.i1.is.
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
 async fn audit_binaries(
     ctx: &Context,
     publish: bool,
     below: Option<f64>,
     extra_paths: Option<&[String]>,
+    libs: bool,
+    consensus: bool,
+    refresh_consensus: bool,
+    rehash: bool,
 ) -> Result<()> {
+    use i1_audit::consensus::AnomalyReport;
     use i1_audit::discovery::{
-        correlate_processes, discover_binaries, discover_processes, DEFAULT_BIN_PATHS,
+        correlate_processes, default_bin_paths, discover_binaries_cached, discover_processes,
+        discover_shared_libraries, is_unusual_library_path,
     };
     use i1_audit::scoring::{offline_weights, score_binary};
+    use i1_audit::TrustWeights;
 
     println!(
         "{}",
@@ -44,7 +124,8 @@ async fn audit_binaries(
     println!();
 
     // Build path list
-    let mut paths: Vec<&str> = DEFAULT_BIN_PATHS.to_vec();
+    let defaults = default_bin_paths();
+    let mut paths: Vec<&str> = defaults.iter().map(String::as_str).collect();
     if let Some(extra) = extra_paths {
         for p in extra {
             paths.push(p.as_str());
@@ -52,13 +133,29 @@ async fn audit_binaries(
     }
 
     let processes = discover_processes().unwrap_or_default();
-    let mut binaries = discover_binaries(&paths).await?;
+    let mut hash_cache = open_hash_cache(rehash);
+    let mut binaries = discover_binaries_cached(&paths, ctx.audit_hash, &mut hash_cache).await?;
     correlate_processes(&mut binaries, &processes);
+    let libraries = if libs {
+        discover_shared_libraries(ctx.audit_hash, &mut hash_cache).await?
+    } else {
+        Vec::new()
+    };
+    close_hash_cache(ctx, &mut hash_cache)?;
+    let library_count = libraries.len();
+    binaries.extend(libraries);
 
-    let weights = offline_weights();
-    for bin in &mut binaries {
-        bin.trust_score = Some(score_binary(bin, &weights));
-    }
+    let anomalies = if consensus {
+        let results = query_binaries_consensus(ctx, &binaries, refresh_consensus).await?;
+        i1_audit::apply_binary_consensus(&mut binaries, &results, &TrustWeights::default())
+    } else {
+        let weights = offline_weights();
+        for bin in &mut binaries {
+            bin.trust_score = Some(score_binary(bin, &weights));
+        }
+        Vec::new()
+    };
+    let anomalies = AnomalyReport::new(anomalies);
 
     // Sort by trust score ascending (lowest trust first)
     binaries.sort_by(|a, b| {
@@ -90,6 +187,12 @@ async fn audit_binaries(
         total.to_string().bright_white(),
         running.to_string().bright_green()
     );
+    if libs {
+        println!(
+            "  {} of them shared libraries loaded by running processes",
+            library_count.to_string().bright_white()
+        );
+    }
     println!();
 
     for bin in &binaries {
@@ -106,18 +209,26 @@ async fn audit_binaries(
         } else {
             "".normal()
         };
+        let path_indicator = if is_unusual_library_path(&bin.path) {
+            " UNUSUAL PATH".bright_red()
+        } else {
+            "".normal()
+        };
 
         let basename = bin.path.rsplit('/').next().unwrap_or(&bin.path);
         println!(
-            "  {} {} {} {}{}",
+            "  {} {} {} {}{}{}",
             trust_color,
-            &bin.sha256[..12].dimmed(),
+            &bin.hash[..12].dimmed(),
             basename.bright_white(),
             format_size(bin.size).dimmed(),
-            running_indicator
+            running_indicator,
+            path_indicator
         );
     }
 
+    print_anomalies(&anomalies);
+
     if publish {
         publish_snapshot_from_binaries(&binaries)?;
     }
@@ -126,8 +237,317 @@ async fn audit_binaries(
     Ok(())
 }
 
+/// Ask bin.i1.is about each distinct binary hash, using the on-disk
+/// consensus cache.
+async fn query_binaries_consensus(
+    ctx: &Context,
+    binaries: &[i1_audit::BinaryInfo],
+    refresh: bool,
+) -> Result<std::collections::HashMap<String, i1_audit::consensus::ConsensusResult>> {
+    use i1_audit::consensus::cache::CACHE_FILE_NAME;
+    use i1_audit::consensus::{create_resolver, query_binary_consensus_cached, ConsensusCache};
+
+    let resolver = create_resolver()?;
+    let mut cache = ConsensusCache::load(&audit_data_dir().join(CACHE_FILE_NAME));
+
+    let mut results = std::collections::HashMap::new();
+    for bin in binaries {
+        if results.contains_key(&bin.hash) {
+            continue;
+        }
+        let result = query_binary_consensus_cached(
+            &resolver,
+            &mut cache,
+            &bin.hash,
+            bin.hash_algorithm,
+            refresh,
+        )
+        .await?;
+        results.insert(bin.hash.clone(), result);
+    }
+
+    let stale = results.values().filter(|r| r.stale).count();
+    save_consensus_cache(ctx, &mut cache, stale)?;
+    Ok(results)
+}
+
+/// Compare this machine's binaries against a saved baseline, failing if
+/// anything differs.
+async fn audit_binaries_against(ctx: &Context, baseline: &str, rehash: bool) -> Result<()> {
+    use i1_audit::discovery::{correlate_processes, discover_binaries_cached, discover_processes};
+
+    let baseline = load_baseline(baseline)?;
+    // Scan what the baseline scanned, hashed the way it was hashed.
+    let paths: Vec<&str> = baseline.bin_paths.iter().map(String::as_str).collect();
+    let processes = discover_processes().unwrap_or_default();
+    let mut hash_cache = open_hash_cache(rehash);
+    let mut binaries =
+        discover_binaries_cached(&paths, baseline.hash_algorithm(), &mut hash_cache).await?;
+    close_hash_cache(ctx, &mut hash_cache)?;
+    correlate_processes(&mut binaries, &processes);
+
+    let diff = baseline.compare(&binaries);
+    if matches!(ctx.output_format, OutputFormat::Json) {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print_baseline_diff(&diff);
+    }
+
+    if diff.is_empty() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "system differs from baseline '{}': {} changed, {} added, {} removed",
+        diff.baseline,
+        diff.changed.len(),
+        diff.added.len(),
+        diff.removed.len()
+    )
+}
+
+fn print_baseline_diff(diff: &i1_audit::baseline::BaselineDiff) {
+    println!(
+        "  {} {} {}",
+        "Baseline".bright_white().bold(),
+        diff.baseline.bright_cyan(),
+        format!("(saved {})", diff.saved_at.format("%Y-%m-%d %H:%M UTC")).dimmed()
+    );
+    println!();
+    if diff.is_empty() {
+        println!(
+            "  {} ({} binaries match)",
+            "No differences".bright_green(),
+            diff.unchanged
+        );
+    }
+
+    // Tampered binaries are the strongest signal, so they lead.
+    for (title, changes, mark) in [
+        ("Changed binaries", &diff.changed, "!"),
+        ("New binaries", &diff.added, "+"),
+        ("Removed binaries", &diff.removed, "-"),
+    ] {
+        if changes.is_empty() {
+            continue;
+        }
+        println!("  {title} ({})", changes.len());
+        for change in changes {
+            let running = if change.running {
+                " RUNNING".bright_green()
+            } else {
+                "".normal()
+            };
+            println!(
+                "  {} {mark} {}{running}",
+                severity_label(change.severity),
+                change.path
+            );
+        }
+        println!();
+    }
+    if !diff.unverified.is_empty() {
+        println!(
+            "  {} {} binaries hashed with a different algorithm than the baseline",
+            "Skipped".dimmed(),
+            diff.unverified.len()
+        );
+        println!();
+    }
+}
+
+/// Manage saved baselines.
+async fn audit_baseline(ctx: &Context, command: BaselineCommands, rehash: bool) -> Result<()> {
+    match command {
+        BaselineCommands::Save { name, paths, force } => {
+            save_baseline(ctx, &name, paths.as_deref(), force, rehash).await
+        }
+        BaselineCommands::List => list_baselines(ctx),
+        BaselineCommands::Show { name } => show_baseline(ctx, &load_baseline(&name)?),
+        BaselineCommands::Export { name, file } => {
+            let path = stored_baseline(&name)?;
+            std::fs::copy(&path, &file)
+                .with_context(|| format!("failed to write {}", file.display()))?;
+            println!(
+                "  {} {} to {}",
+                "Exported".bright_green(),
+                name.bright_cyan(),
+                file.display().to_string().bright_white()
+            );
+            Ok(())
+        }
+        BaselineCommands::Import { file, name, force } => {
+            let mut baseline = Baseline::read(&file)
+                .with_context(|| format!("failed to read baseline {}", file.display()))?;
+            if let Some(name) = name {
+                i1_audit::baseline::check_name(&name)?;
+                baseline.name = name;
+            }
+            write_baseline(&baseline, force)
+        }
+    }
+}
+
+/// Snapshot this machine and store it as baseline `name`.
+async fn save_baseline(
+    ctx: &Context,
+    name: &str,
+    extra_paths: Option<&[String]>,
+    force: bool,
+    rehash: bool,
+) -> Result<()> {
+    use i1_audit::discovery::default_bin_paths;
+    use i1_audit::scoring::offline_weights;
+
+    // Check before the (slow) snapshot so a taken name fails fast.
+    if baseline_path(name)?.exists() && !force {
+        anyhow::bail!("baseline '{name}' already exists (use --force to replace it)");
+    }
+
+    let mut bin_paths = default_bin_paths();
+    bin_paths.extend(extra_paths.unwrap_or_default().iter().cloned());
+    let paths: Vec<&str> = bin_paths.iter().map(String::as_str).collect();
+    let mut hash_cache = open_hash_cache(rehash);
+    let snapshot =
+        i1_audit::collect_snapshot(&paths, &offline_weights(), ctx.audit_hash, &mut hash_cache)
+            .await?;
+    close_hash_cache(ctx, &mut hash_cache)?;
+
+    write_baseline(&Baseline::new(name, bin_paths, snapshot)?, force)
+}
+
+/// Store `baseline` in the data directory under its name.
+fn write_baseline(baseline: &Baseline, force: bool) -> Result<()> {
+    let path = baseline_path(&baseline.name)?;
+    if path.exists() && !force {
+        anyhow::bail!(
+            "baseline '{}' already exists (use --force to replace it)",
+            baseline.name
+        );
+    }
+    baseline.write(&path)?;
+    println!(
+        "  {} {} ({} binaries, {} root certs, {} kernel modules)",
+        "Saved baseline".bright_green(),
+        baseline.name.bright_cyan(),
+        baseline.snapshot.binaries.len(),
+        baseline.snapshot.root_certs.len(),
+        baseline.snapshot.kernel_modules.len()
+    );
+    Ok(())
+}
+
+fn list_baselines(ctx: &Context) -> Result<()> {
+    let mut baselines = Vec::new();
+    let suffix = format!(".{}", i1_audit::baseline::BASELINE_EXTENSION);
+    if let Ok(entries) = std::fs::read_dir(baseline_dir()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.to_string_lossy().ends_with(&suffix) {
+                continue;
+            }
+            match Baseline::read(&path) {
+                Ok(baseline) => baselines.push(baseline),
+                Err(e) => tracing::warn!("skipping baseline: {e}"),
+            }
+        }
+    }
+    baselines.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if matches!(ctx.output_format, OutputFormat::Json) {
+        let summaries: Vec<_> = baselines.iter().map(baseline_summary).collect();
+        println!("{}", serde_json::to_string_pretty(&summaries)?);
+        return Ok(());
+    }
+
+    if baselines.is_empty() {
+        println!("  No baselines saved (see `i1 audit baseline save`)");
+        return Ok(());
+    }
+    for baseline in &baselines {
+        println!(
+            "  {:<24} {}  {} binaries  {}",
+            baseline.name.bright_cyan(),
+            baseline.saved_at.format("%Y-%m-%d %H:%M UTC"),
+            baseline.snapshot.binaries.len(),
+            baseline.snapshot.node_id.dimmed()
+        );
+    }
+    Ok(())
+}
+
+fn show_baseline(ctx: &Context, baseline: &Baseline) -> Result<()> {
+    if matches!(ctx.output_format, OutputFormat::Json) {
+        let mut json = baseline_summary(baseline);
+        json["bin_paths"] = serde_json::to_value(&baseline.bin_paths)?;
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
+    let snapshot = &baseline.snapshot;
+    println!(
+        "  {} {}",
+        "Baseline".bright_white().bold(),
+        baseline.name.bright_cyan()
+    );
+    println!(
+        "  Saved:          {}",
+        baseline.saved_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    println!("  Captured on:    {}", snapshot.node_id);
+    println!("  Hash:           {}", baseline.hash_algorithm());
+    println!("  Binaries:       {}", snapshot.binaries.len());
+    println!("  Root certs:     {}", snapshot.root_certs.len());
+    println!("  Kernel modules: {}", snapshot.kernel_modules.len());
+    println!("  Scanned:        {}", baseline.bin_paths.join(", "));
+    Ok(())
+}
+
+/// What `baseline list` and `baseline show` report, without the snapshot.
+fn baseline_summary(baseline: &Baseline) -> serde_json::Value {
+    serde_json::json!({
+        "name": baseline.name,
+        "saved_at": baseline.saved_at,
+        "node_id": baseline.snapshot.node_id,
+        "hash_algorithm": baseline.hash_algorithm(),
+        "binaries": baseline.snapshot.binaries.len(),
+        "root_certs": baseline.snapshot.root_certs.len(),
+        "kernel_modules": baseline.snapshot.kernel_modules.len(),
+    })
+}
+
+/// Load a baseline by name, or from a file (e.g. one exported elsewhere).
+fn load_baseline(name_or_path: &str) -> Result<Baseline> {
+    let file = std::path::Path::new(name_or_path);
+    let path = if file.is_file() {
+        file.to_path_buf()
+    } else {
+        stored_baseline(name_or_path)?
+    };
+    Baseline::read(&path).with_context(|| format!("failed to read baseline {}", path.display()))
+}
+
+/// Path of the saved baseline `name`, which must exist.
+fn stored_baseline(name: &str) -> Result<std::path::PathBuf> {
+    let path = baseline_path(name)?;
+    if !path.is_file() {
+        anyhow::bail!("no baseline named '{name}' (see `i1 audit baseline list`)");
+    }
+    Ok(path)
+}
+
+/// Where baseline `name` is stored.
+fn baseline_path(name: &str) -> Result<std::path::PathBuf> {
+    i1_audit::baseline::check_name(name)?;
+    Ok(baseline_dir().join(format!("{name}.{}", i1_audit::baseline::BASELINE_EXTENSION)))
+}
+
+fn baseline_dir() -> std::path::PathBuf {
+    audit_data_dir().join("baselines")
+}
+
 /// Audit running processes.
 async fn audit_processes(ctx: &Context) -> Result<()> {
+    use i1_audit::consensus::compare_processes;
     use i1_audit::discovery::discover_processes;
 
     println!("{}", "  Auditing running processes...".bright_cyan());
@@ -188,27 +608,49 @@ async fn audit_processes(ctx: &Context) -> Result<()> {
         );
     }
 
+    // Binaries aren't discovered here, so only deleted executables show
+    let anomalies = compare_processes(&sorted, &[]);
+    if !anomalies.is_empty() {
+        println!();
+        for anomaly in &anomalies {
+            println!("  {} {}", "ALERT".bright_red(), anomaly.description);
+        }
+    }
+
     println!();
     Ok(())
 }
 
 /// Audit root certificate store.
-async fn audit_certs(ctx: &Context) -> Result<()> {
+async fn audit_certs(ctx: &Context, validate: bool, refresh_consensus: bool) -> Result<()> {
+    use i1_audit::consensus::{AnomalyReport, Severity};
     use i1_audit::discovery::discover_root_certs;
     use i1_audit::scoring::score_cert;
+    use i1_audit::validate::validate_roots;
 
     println!("{}", "  Auditing root certificates...".bright_cyan());
     println!();
 
-    let mut certs = discover_root_certs().await?;
+    let mut certs = discover_root_certs(ctx.audit_hash).await?;
 
     for cert in &mut certs {
         cert.trust_score = Some(score_cert(cert));
     }
 
+    if validate {
+        validate_roots(&mut certs);
+        validate_certs_consensus(ctx, &mut certs, refresh_consensus).await?;
+    }
+
+    let worst = |cert: &i1_audit::RootCertInfo| cert.findings.iter().map(|f| f.severity).max();
+    let critical = certs
+        .iter()
+        .filter(|c| worst(c) == Some(Severity::Critical))
+        .count();
+
     if matches!(ctx.output_format, OutputFormat::Json) {
         println!("{}", serde_json::to_string_pretty(&certs)?);
-        return Ok(());
+        return critical_certs(critical);
     }
 
     let total = certs.len();
@@ -223,12 +665,33 @@ async fn audit_certs(ctx: &Context) -> Result<()> {
             expired.to_string().bright_green()
         }
     );
+    if validate {
+        let flagged = certs
+            .iter()
+            .filter(|c| worst(c).is_some_and(|s| s > Severity::Info))
+            .count();
+        println!(
+            "  {} with findings ({} critical)",
+            flagged.to_string().bright_white(),
+            if critical > 0 {
+                critical.to_string().bright_red()
+            } else {
+                critical.to_string().bright_green()
+            }
+        );
+        let findings: AnomalyReport = certs
+            .iter()
+            .flat_map(|c| c.findings.iter().cloned())
+            .collect();
+        println!("  Findings: {}", findings.counts.to_string().bright_white());
+    }
     println!();
 
-    // Sort: expired first, then by expiry date
+    // Sort: worst findings first, then expired, then by expiry date
     certs.sort_by(|a, b| {
-        b.expired
-            .cmp(&a.expired)
+        worst(b)
+            .cmp(&worst(a))
+            .then_with(|| b.expired.cmp(&a.expired))
             .then_with(|| a.not_after.cmp(&b.not_after))
     });
 
@@ -249,35 +712,293 @@ async fn audit_certs(ctx: &Context) -> Result<()> {
             subject_short.to_string()
         };
 
+        let consensus_indicator = match cert.in_consensus {
+            Some(false) => " NOT IN CONSENSUS".bright_red(),
+            _ => "".normal(),
+        };
+
         println!(
-            "  {} {} {} (expires {})",
+            "  {} {} {} (expires {}){}",
             status,
             fp_short.dimmed(),
             subject_display.bright_white(),
-            cert.not_after.format("%Y-%m-%d").to_string().dimmed()
+            cert.not_after.format("%Y-%m-%d").to_string().dimmed(),
+            consensus_indicator
         );
+        for finding in &cert.findings {
+            if finding.severity > Severity::Info || ctx.verbose {
+                println!(
+                    "           {} {}",
+                    severity_label(finding.severity),
+                    finding.description
+                );
+            }
+        }
     }
 
     println!();
+    critical_certs(critical)
+}
+
+/// Fail the command when validation found critical certs, so CI notices.
+fn critical_certs(critical: usize) -> Result<()> {
+    if critical > 0 {
+        anyhow::bail!("{critical} root certs with critical findings");
+    }
     Ok(())
 }
 
-/// Full audit: binaries + processes + certs.
-async fn audit_full(ctx: &Context, publish: bool) -> Result<()> {
-    use i1_audit::discovery::DEFAULT_BIN_PATHS;
+/// Cross-check root certs against ca.i1.is, using the on-disk consensus cache.
+async fn validate_certs_consensus(
+    ctx: &Context,
+    certs: &mut [i1_audit::RootCertInfo],
+    refresh: bool,
+) -> Result<()> {
+    use i1_audit::consensus::cache::CACHE_FILE_NAME;
+    use i1_audit::consensus::{create_resolver, query_cert_consensus_cached, ConsensusCache};
+
+    let resolver = create_resolver()?;
+    let mut cache = ConsensusCache::load(&audit_data_dir().join(CACHE_FILE_NAME));
+
+    let mut stale = 0;
+    for cert in certs.iter_mut() {
+        let result = query_cert_consensus_cached(
+            &resolver,
+            &mut cache,
+            &cert.fingerprint,
+            cert.fingerprint_algorithm,
+            refresh,
+        )
+        .await?;
+        if result.stale {
+            stale += 1;
+        }
+        cert.in_consensus = Some(result.found);
+    }
+
+    save_consensus_cache(ctx, &mut cache, stale)
+}
+
+/// Prune and save the consensus cache after a round of queries, `stale`
+/// of which were answered from expired entries.
+fn save_consensus_cache(
+    ctx: &Context,
+    cache: &mut i1_audit::consensus::ConsensusCache,
+    stale: usize,
+) -> Result<()> {
+    cache.prune(chrono::Duration::days(30));
+    cache.save()?;
+
+    if ctx.verbose {
+        let stats = cache.stats();
+        println!(
+            "  {} {} hits, {} misses, {} stale ({} cached entries)",
+            "Consensus cache:".dimmed(),
+            stats.hits.to_string().bright_white(),
+            stats.misses.to_string().bright_white(),
+            stats.stale_hits.to_string().bright_yellow(),
+            cache.len()
+        );
+    }
+    if stale > 0 {
+        tracing::warn!("{stale} results served from stale cache (network unreachable)");
+    }
+
+    Ok(())
+}
+
+/// Audit loaded kernel modules, diffing against the last published snapshot.
+async fn audit_modules(ctx: &Context) -> Result<()> {
+    use i1_audit::consensus::{compare_modules, AnomalyReport};
+    use i1_audit::discovery::discover_kernel_modules;
+    use i1_audit::scoring::score_module;
+
+    println!("{}", "  Auditing kernel modules...".bright_cyan());
+    println!();
+
+    let mut modules = discover_kernel_modules().await?;
+    for module in &mut modules {
+        module.trust = Some(score_module(module));
+    }
+
+    let previous = load_published_snapshot()
+        .map(|s| s.kernel_modules)
+        .unwrap_or_default();
+    let anomalies = AnomalyReport::new(compare_modules(&previous, &modules));
+
+    if matches!(ctx.output_format, OutputFormat::Json) {
+        println!("{}", serde_json::to_string_pretty(&modules)?);
+        return Ok(());
+    }
+
+    if modules.is_empty() {
+        println!("  {}", "No loaded kernel modules visible on this system".dimmed());
+        println!();
+        return Ok(());
+    }
+
+    let tainted = modules.iter().filter(|m| m.taint.any()).count();
+    println!(
+        "  {} modules loaded ({} tainted)",
+        modules.len().to_string().bright_white(),
+        if tainted > 0 {
+            tainted.to_string().bright_red()
+        } else {
+            tainted.to_string().bright_green()
+        }
+    );
+    println!();
+
+    // Lowest trust first
+    modules.sort_by(|a, b| {
+        let sa = a.trust.as_ref().map_or(0.0, |t| t.score);
+        let sb = b.trust.as_ref().map_or(0.0, |t| t.score);
+        sa.partial_cmp(&sb).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    for module in &modules {
+        let trust = module.trust.as_ref().map_or(0.0, |t| t.score);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let trust_pct = (trust * 100.0) as u32;
+        let trust_color = match trust_pct {
+            0..=30 => format!("{trust_pct:>3}%").bright_red(),
+            31..=60 => format!("{trust_pct:>3}%").bright_yellow(),
+            _ => format!("{trust_pct:>3}%").bright_green(),
+        };
+
+        let mut flags = Vec::new();
+        if module.taint.unsigned {
+            flags.push("UNSIGNED");
+        }
+        if module.taint.out_of_tree {
+            flags.push("OUT-OF-TREE");
+        }
+        if module.origin == i1_audit::ModuleOrigin::NotInModuleTree {
+            flags.push("NO-FILE");
+        }
+
+        println!(
+            "  {} {} {} {}",
+            trust_color,
+            module.name.bright_white(),
+            format_size(module.size).dimmed(),
+            flags.join(" ").bright_red()
+        );
+    }
+
+    print_anomalies(&anomalies);
+
+    println!();
+    Ok(())
+}
+
+/// Print anomaly counts and each anomaly, high severity as alerts.
+fn print_anomalies(anomalies: &i1_audit::consensus::AnomalyReport) {
+    use i1_audit::consensus::Severity;
+
+    if anomalies.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "  Anomalies: {}",
+        anomalies.counts.to_string().bright_white()
+    );
+    for anomaly in anomalies.anomalies() {
+        let label = if anomaly.severity >= Severity::High {
+            "ALERT".bright_red()
+        } else {
+            " WARN".bright_yellow()
+        };
+        println!("  {} {}", label, anomaly.description);
+    }
+}
+
+/// Full audit: binaries + processes + certs + modules (+ shared libraries
+/// with `libs`), graded at the end. With `consensus`, binaries are scored
+/// against bin.i1.is and the grade counts consensus coverage.
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)]
+async fn audit_full(
+    ctx: &Context,
+    publish: bool,
+    libs: bool,
+    consensus: bool,
+    refresh_consensus: bool,
+    rehash: bool,
+    profile: &RiskProfile,
+    fail_below: Option<Grade>,
+    html_path: Option<&std::path::Path>,
+) -> Result<()> {
+    use i1_audit::consensus::compare_modules;
+    use i1_audit::discovery::default_bin_paths;
     use i1_audit::scoring::offline_weights;
+    use i1_audit::{AuditSummary, TrustWeights};
 
-    let paths: Vec<&str> = DEFAULT_BIN_PATHS.to_vec();
+    let defaults = default_bin_paths();
+    let paths: Vec<&str> = defaults.iter().map(String::as_str).collect();
     let weights = offline_weights();
-    let snapshot = i1_audit::collect_snapshot(&paths, &weights).await?;
+    let mut hash_cache = open_hash_cache(rehash);
+    let mut snapshot =
+        i1_audit::collect_snapshot(&paths, &weights, ctx.audit_hash, &mut hash_cache).await?;
+    if libs {
+        snapshot.shared_libraries =
+            i1_audit::collect_shared_libraries(&weights, ctx.audit_hash, &mut hash_cache).await?;
+    }
+    close_hash_cache(ctx, &mut hash_cache)?;
+
+    let binary_anomalies = if consensus {
+        let results = query_binaries_consensus(ctx, &snapshot.binaries, refresh_consensus).await?;
+        let anomalies = i1_audit::apply_binary_consensus(
+            &mut snapshot.binaries,
+            &results,
+            &TrustWeights::default(),
+        );
+        snapshot.summary = AuditSummary::from_snapshot(
+            &snapshot.binaries,
+            &snapshot.processes,
+            &snapshot.root_certs,
+            0.5,
+        )
+        .with_modules(&snapshot.kernel_modules, 0.5);
+        anomalies
+    } else {
+        Vec::new()
+    };
+
+    // Diff against the previous snapshot before publishing replaces it.
+    let previous = load_published_snapshot();
+    let previous_modules = previous
+        .as_ref()
+        .map_or(&[][..], |s| s.kernel_modules.as_slice());
+    let report = snapshot
+        .report()
+        .with_anomalies(&compare_modules(previous_modules, &snapshot.kernel_modules))
+        .with_anomalies(&binary_anomalies);
+    // Consensus coverage only counts when binary consensus was queried.
+    let risk = RiskReport::from_snapshot(&snapshot, &report, profile, consensus);
+
+    if let Some(path) = html_path {
+        write_html_report(path, &snapshot, &report, &risk, previous.as_ref())?;
+        if !matches!(ctx.output_format, OutputFormat::Json) {
+            println!(
+                "  {} {}",
+                "Report written to".bright_green(),
+                path.display().to_string().bright_white()
+            );
+        }
+    }
 
     if publish {
         publish_audit_snapshot(&snapshot)?;
     }
 
     if matches!(ctx.output_format, OutputFormat::Json) {
-        println!("{}", serde_json::to_string_pretty(&snapshot)?);
-        return Ok(());
+        // The snapshot as before, with the report alongside for dashboards.
+        let mut json = serde_json::to_value(&snapshot)?;
+        json["report"] = serde_json::to_value(&report)?;
+        json["risk"] = serde_json::to_value(&risk)?;
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return check_grade(&risk, fail_below);
     }
 
     println!(
@@ -285,40 +1006,228 @@ async fn audit_full(ctx: &Context, publish: bool) -> Result<()> {
         "  i1 audit — Zero-Trust System Integrity Check".bright_cyan().bold()
     );
     println!();
+    print_grade(&risk);
+    print_report(&report);
 
-    audit_binaries(ctx, false, None, None).await?;
+    // The snapshot just refreshed the hash and consensus caches, so this
+    // pass reuses them.
+    audit_binaries(ctx, false, None, None, libs, consensus, false, false).await?;
     audit_processes(ctx).await?;
-    audit_certs(ctx).await?;
+    audit_certs(ctx, false, false).await?;
+    audit_modules(ctx).await?;
 
-    Ok(())
+    // Repeat the verdict below the long lists.
+    print_grade(&risk);
+    check_grade(&risk, fail_below)
 }
 
-/// Generate a verification QR code for independent TTL checking.
-async fn audit_verify(ctx: &Context, output_path: &str, url_only: bool) -> Result<()> {
-    use i1_audit::discovery::DEFAULT_BIN_PATHS;
-    use i1_audit::scoring::offline_weights;
+/// Render the audit as a self-contained HTML page at `path`.
+fn write_html_report(
+    path: &std::path::Path,
+    snapshot: &i1_audit::AuditSnapshot,
+    report: &i1_audit::report::AuditReport,
+    risk: &RiskReport,
+    previous: Option<&i1_audit::AuditSnapshot>,
+) -> Result<()> {
+    use i1_audit::html::HtmlReport;
+    use i1_audit::qr::{QrPayload, UrlMode};
     use i1_audit::verify::generate_verify_token;
-    use std::path::Path;
 
+    let qr = QrPayload::new(&generate_verify_token(snapshot), UrlMode::Auto)?;
+    let mut html = HtmlReport::new(snapshot, report, risk).with_qr(&qr);
+    if let Some(previous) = previous {
+        html = html.with_previous(previous);
+    }
+    std::fs::write(path, html.render()?)
+        .with_context(|| format!("failed to write report {}", path.display()))
+}
+
+/// Read a grading profile, or the defaults without one.
+fn load_risk_profile(path: Option<&std::path::Path>) -> Result<RiskProfile> {
+    let Some(path) = path else {
+        return Ok(RiskProfile::default());
+    };
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read profile {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("invalid profile {}", path.display()))
+}
+
+/// Print the overall grade and the items that cost the most points.
+fn print_grade(risk: &RiskReport) {
+    let grade = format!(" {} ", risk.grade);
+    let badge = match risk.grade {
+        Grade::A | Grade::B => grade.black().on_bright_green(),
+        Grade::C => grade.black().on_bright_yellow(),
+        Grade::D | Grade::F => grade.white().on_red(),
+    }
+    .bold();
     println!(
-        "{}",
-        "  Generating trust verification token...".bright_cyan()
+        "  {} {} {}",
+        "Risk grade".bright_white().bold(),
+        badge,
+        format!("({:.1} penalty points)", risk.penalty).dimmed()
     );
+    for concern in &risk.top {
+        println!("  {} {}", severity_label(concern.severity), concern.reason);
+    }
     println!();
+}
+
+/// Fail the command when the grade is worse than `--fail-below`.
+fn check_grade(risk: &RiskReport, fail_below: Option<Grade>) -> Result<()> {
+    match fail_below {
+        Some(minimum) if risk.below(minimum) => {
+            anyhow::bail!("risk grade {} is below {minimum}", risk.grade)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Findings shown in the risk summary; the rest are only counted.
+const REPORT_LIMIT: usize = 15;
+
+/// Print the risk summary: severity rollup, then the worst findings.
+fn print_report(report: &i1_audit::report::AuditReport) {
+    println!("{}", "  Risk summary".bright_white().bold());
+    if report.findings.is_empty() {
+        println!("  {}", "No findings".bright_green());
+        println!();
+        return;
+    }
+
+    let counts = &report.counts;
+    println!(
+        "  {} critical  {} high  {} medium  {} low",
+        counts.critical.to_string().bright_red(),
+        counts.high.to_string().bright_red(),
+        counts.medium.to_string().bright_yellow(),
+        counts.low.to_string().dimmed()
+    );
+    println!();
+
+    for finding in report.findings.iter().take(REPORT_LIMIT) {
+        println!("  {} {}", severity_label(finding.severity), finding.description);
+    }
+    if report.findings.len() > REPORT_LIMIT {
+        println!(
+            "  ... and {} more",
+            (report.findings.len() - REPORT_LIMIT).to_string().dimmed()
+        );
+    }
+    println!();
+}
+
+/// Fixed-width, colored severity label.
+fn severity_label(severity: i1_audit::consensus::Severity) -> colored::ColoredString {
+    use i1_audit::consensus::Severity;
+
+    match severity {
+        Severity::Critical => "CRITICAL".bright_red().bold(),
+        Severity::High => "    HIGH".bright_red(),
+        Severity::Medium => "  MEDIUM".bright_yellow(),
+        Severity::Low => "     LOW".dimmed(),
+        Severity::Info => "    INFO".dimmed(),
+    }
+}
+
+/// Generate a verification QR code for independent TTL checking, or with
+/// `check` resolve the signal record from here and report the verdict.
+#[allow(clippy::fn_params_excessive_bools)]
+async fn audit_verify(
+    ctx: &Context,
+    output_path: &str,
+    url_only: bool,
+    sign_key: Option<&str>,
+    compact: bool,
+    check: bool,
+    rehash: bool,
+) -> Result<()> {
+    use i1_audit::discovery::default_bin_paths;
+    use i1_audit::qr::{QrPayload, UrlMode};
+    use i1_audit::scoring::offline_weights;
+    use i1_audit::verify::{generate_verify_token, generate_verify_token_signed, NodeSigner};
+    use std::path::Path;
+
+    // Load the key before the (slow) snapshot so a bad path fails fast.
+    let signer = sign_key
+        .map(|path| NodeSigner::load(Path::new(path)))
+        .transpose()?;
+
+    // Keep stdout parseable for -o json.
+    if !matches!(ctx.output_format, OutputFormat::Json) {
+        println!(
+            "{}",
+            "  Generating trust verification token...".bright_cyan()
+        );
+        println!();
+    }
 
     // Collect a snapshot to compute the trust digest
-    let paths: Vec<&str> = DEFAULT_BIN_PATHS.to_vec();
+    let defaults = default_bin_paths();
+    let paths: Vec<&str> = defaults.iter().map(String::as_str).collect();
     let weights = offline_weights();
-    let snapshot = i1_audit::collect_snapshot(&paths, &weights).await?;
+    let mut hash_cache = open_hash_cache(rehash);
+    let snapshot =
+        i1_audit::collect_snapshot(&paths, &weights, ctx.audit_hash, &mut hash_cache).await?;
+    close_hash_cache(ctx, &mut hash_cache)?;
+
+    let token = match &signer {
+        Some(signer) => generate_verify_token_signed(&snapshot, signer)?,
+        None => generate_verify_token(&snapshot),
+    };
 
-    let token = generate_verify_token(&snapshot);
+    if check {
+        return check_verify_token(ctx, &token).await;
+    }
 
     if matches!(ctx.output_format, OutputFormat::Json) {
         println!("{}", serde_json::to_string_pretty(&token)?);
         return Ok(());
     }
 
-    // Print verification details
+    print_verify_token(&token, &snapshot);
+
+    let mode = if compact {
+        UrlMode::Compact
+    } else {
+        UrlMode::Auto
+    };
+    print_verify_url(&token, &QrPayload::new(&token, mode)?);
+
+    if url_only {
+        return Ok(());
+    }
+
+    // Render QR in terminal
+    println!();
+    let qr_text = i1_audit::qr::render_qr_terminal(&token, mode);
+    println!("{qr_text}");
+    println!();
+
+    // Save PNG
+    let path = Path::new(output_path);
+    i1_audit::qr::generate_qr_png(&token, path, mode)?;
+    println!(
+        "  {} {}",
+        "QR code saved:".bright_green(),
+        output_path.bright_white()
+    );
+    println!();
+    println!(
+        "  {}",
+        "Scan with your phone (on cell network) to verify DNS integrity.".dimmed()
+    );
+    println!(
+        "  {}",
+        "If your local DNS is poisoned, the phone will see different results.".dimmed()
+    );
+    println!();
+
+    Ok(())
+}
+
+/// Print a verification token's details.
+fn print_verify_token(token: &i1_audit::verify::VerifyToken, snapshot: &i1_audit::AuditSnapshot) {
     println!(
         "  {}  {}",
         "Node prefix:".dimmed(),
@@ -334,6 +1243,13 @@ async fn audit_verify(ctx: &Context, output_path: &str, url_only: bool) -> Resul
         "DNS record:".dimmed(),
         token.dns_name.bright_yellow()
     );
+    if token.signature.is_some() {
+        println!(
+            "  {}  {}",
+            "Signed:".dimmed(),
+            "yes (node key)".bright_green()
+        );
+    }
     println!(
         "  {}  {} seconds",
         "Expected TTL:".dimmed(),
@@ -350,44 +1266,90 @@ async fn audit_verify(ctx: &Context, output_path: &str, url_only: bool) -> Resul
         snapshot.root_certs.len().to_string().bright_white()
     );
     println!();
+}
+
+/// Resolve a token's signal record and report the verdict; fails the
+/// command when the record was tampered with or replayed.
+async fn check_verify_token(ctx: &Context, token: &i1_audit::verify::VerifyToken) -> Result<()> {
+    let resolver = i1_audit::consensus::create_resolver()?;
+    let result = i1_audit::verify::check_token(&resolver, token).await?;
+
+    if matches!(ctx.output_format, OutputFormat::Json) {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        print_verify_result(token, &result);
+    }
 
-    let url = token.verification_url();
+    if result.verdict.is_failure() {
+        anyhow::bail!("trust verification failed: {}", result.verdict);
+    }
+    Ok(())
+}
+
+/// Print the URL a verification QR code carries, and warn if it won't scan well.
+fn print_verify_url(token: &i1_audit::verify::VerifyToken, payload: &i1_audit::qr::QrPayload) {
     println!(
         "  {}  {}",
         "Verify URL:".dimmed(),
-        url.bright_cyan().underline()
+        payload.url.bright_cyan().underline()
     );
-
-    if url_only {
-        return Ok(());
+    if payload.compact {
+        println!(
+            "  {}  {}",
+            "Full URL:".dimmed(),
+            token.verification_url().dimmed()
+        );
     }
+    if !payload.is_scannable() {
+        tracing::warn!(
+            "the URL makes a dense QR code (version {}); try --compact",
+            payload.version
+        );
+    }
+}
 
-    // Render QR in terminal
-    println!();
-    let qr_text = i1_audit::qr::render_qr_terminal(&token);
-    println!("{qr_text}");
-    println!();
-
-    // Save PNG
-    let path = Path::new(output_path);
-    i1_audit::qr::generate_qr_png(&token, path)?;
+/// Print the outcome of checking a token against its published record.
+fn print_verify_result(token: &i1_audit::verify::VerifyToken, result: &VerifyResult) {
+    let verdict = result.verdict.to_string();
+    let verdict = match result.verdict {
+        Verdict::Ok => verdict.bright_green().bold(),
+        Verdict::NotPublished | Verdict::StaleCache => verdict.bright_yellow().bold(),
+        Verdict::Tampered | Verdict::Compromised => verdict.bright_red().bold(),
+    };
     println!(
-        "  {} {}",
-        "QR code saved:".bright_green(),
-        output_path.bright_white()
+        "  {}  {}",
+        "DNS record:".dimmed(),
+        token.dns_name.bright_yellow()
     );
-    println!();
     println!(
-        "  {}",
-        "Scan with your phone (on cell network) to verify DNS integrity.".dimmed()
+        "  {}  {}",
+        "Expected:".dimmed(),
+        result.expected_value.bright_white()
     );
     println!(
-        "  {}",
-        "If your local DNS is poisoned, the phone will see different results.".dimmed()
+        "  {}  {}",
+        "Observed:".dimmed(),
+        result
+            .observed_value
+            .as_deref()
+            .unwrap_or("(no record)")
+            .bright_white()
+    );
+    let ttl = result.observed_ttl.map_or_else(
+        || "-".to_string(),
+        |ttl| format!("{ttl}s (expected {}s)", result.expected_ttl),
+    );
+    println!(
+        "  {}  {}",
+        "TTL:".dimmed(),
+        if result.ttl_ok {
+            ttl.bright_white()
+        } else {
+            ttl.bright_red()
+        }
     );
     println!();
-
-    Ok(())
+    println!("  {}  {verdict}", "Verdict:".dimmed());
 }
 
 /// Get the audit snapshot directory path.
@@ -397,6 +1359,38 @@ fn audit_data_dir() -> std::path::PathBuf {
         .unwrap_or_else(|| std::path::PathBuf::from("."))
 }
 
+/// Load the binary hash cache from the audit data directory.
+fn open_hash_cache(rehash: bool) -> i1_audit::discovery::HashCache {
+    use i1_audit::discovery::hash_cache::HASH_CACHE_FILE_NAME;
+
+    i1_audit::discovery::HashCache::load(&audit_data_dir().join(HASH_CACHE_FILE_NAME))
+        .with_rehash(rehash)
+}
+
+/// Prune and persist the binary hash cache, reporting hits with `--verbose`.
+fn close_hash_cache(ctx: &Context, cache: &mut i1_audit::discovery::HashCache) -> Result<()> {
+    cache.prune(chrono::Duration::days(30));
+    cache.save()?;
+
+    if ctx.verbose && !matches!(ctx.output_format, OutputFormat::Json) {
+        let stats = cache.stats();
+        println!(
+            "  {} {} reused, {} hashed ({} cached entries)",
+            "Hash cache:".dimmed(),
+            stats.hits.to_string().bright_white(),
+            stats.misses.to_string().bright_white(),
+            cache.len()
+        );
+    }
+    Ok(())
+}
+
+/// Read the last published snapshot, if any.
+fn load_published_snapshot() -> Option<i1_audit::AuditSnapshot> {
+    let content = std::fs::read_to_string(audit_data_dir().join("audit_snapshot.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
 /// Write a full audit snapshot to the shared data directory for i1-srv to read.
 fn publish_audit_snapshot(snapshot: &i1_audit::AuditSnapshot) -> Result<()> {
     let audit_dir = audit_data_dir();
@@ -434,6 +1428,8 @@ fn publish_snapshot_from_binaries(binaries: &[i1_audit::BinaryInfo]) -> Result<(
         binaries: binaries.to_vec(),
         processes: vec![],
         root_certs: vec![],
+        kernel_modules: vec![],
+        shared_libraries: vec![],
         summary: AuditSummary {
             total_binaries: binaries.len(),
             total_processes: 0,
@@ -442,6 +1438,8 @@ fn publish_snapshot_from_binaries(binaries: &[i1_audit::BinaryInfo]) -> Result<(
             expired_certs: 0,
             low_trust_binaries: low_trust,
             unknown_certs: 0,
+            critical_certs: 0,
+            low_trust_modules: 0,
         },
     };
 
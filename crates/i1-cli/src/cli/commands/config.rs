@@ -1,32 +1,37 @@
 //! `i1 config` - CLI configuration management.
+//!
+//! `rotate-key` and `verify` check the Shodan key against `/api-info`,
+//! which costs no credits. Keys are only ever shown masked.
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
+use chrono::Utc;
 use colored::Colorize;
+use i1_core::{ApiInfo, I1Error};
+use std::io::IsTerminal;
 
 use super::Context;
 use crate::cli::args::{ConfigArgs, ConfigCommands};
-use crate::config::Config;
+use crate::config::{mask_secret, Config};
 use crate::output::OutputFormat;
 
+/// Environment variables that override the configured Shodan key, in
+/// the order they are read.
+const SHODAN_KEY_ENV: [&str; 2] = ["SHODAN_API_KEY", "I1_SHODAN_KEY"];
+
 pub async fn execute(ctx: Context, args: ConfigArgs) -> Result<()> {
     match args.command {
         ConfigCommands::Show => show_config(ctx).await,
         ConfigCommands::Set { key, value } => set_config(ctx, &key, &value).await,
         ConfigCommands::Path => show_path(ctx).await,
+        ConfigCommands::RotateKey => rotate_key(&ctx).await,
+        ConfigCommands::Verify => verify_key(&ctx).await,
     }
 }
 
-/// Mask an API key for display (show first 4 and last 4 chars).
+/// Mask an API key for display (show the last 4 chars).
 fn mask_key(key: &Option<String>) -> String {
-    key.as_ref()
-        .map(|k| {
-            if k.len() > 8 {
-                format!("{}...{}", &k[..4], &k[k.len() - 4..])
-            } else {
-                "****".to_string()
-            }
-        })
-        .unwrap_or_else(|| "(not set)".dimmed().to_string())
+    key.as_deref()
+        .map_or_else(|| "(not set)".dimmed().to_string(), mask_secret)
 }
 
 async fn show_config(ctx: Context) -> Result<()> {
@@ -50,6 +55,15 @@ async fn show_config(ctx: Context) -> Result<()> {
                 "shodan_key:".bold(),
                 mask_key(&config.shodan_key)
             );
+            if let Some(previous) = &config.previous_api_key {
+                println!(
+                    "  {} {} (rotated {}, kept until {})",
+                    "previous_api_key:".bold(),
+                    mask_secret(&previous.key),
+                    previous.rotated_at.format("%Y-%m-%d %H:%M UTC"),
+                    previous.expires_at().format("%Y-%m-%d")
+                );
+            }
             println!("  {} {}", "censys_id:".bold(), mask_key(&config.censys_id));
             println!(
                 "  {} {}",
@@ -63,6 +77,18 @@ async fn show_config(ctx: Context) -> Result<()> {
             );
             println!();
 
+            println!("{}", "MISP:".bold().underline());
+            println!(
+                "  {} {}",
+                "misp_url:".bold(),
+                config
+                    .misp_url
+                    .clone()
+                    .unwrap_or_else(|| "(not set)".dimmed().to_string())
+            );
+            println!("  {} {}", "misp_key:".bold(), mask_key(&config.misp_key));
+            println!();
+
             // Output format
             println!("{}", "Settings:".bold().underline());
             println!(
@@ -70,6 +96,11 @@ async fn show_config(ctx: Context) -> Result<()> {
                 "output_format:".bold(),
                 config.output_format.unwrap_or(OutputFormat::Pretty)
             );
+            println!(
+                "  {} {}",
+                "log_format:".bold(),
+                config.log_format.unwrap_or_default()
+            );
 
             // Other settings
             println!("  {} {}", "show_tips:".bold(), config.show_tips);
@@ -78,6 +109,20 @@ async fn show_config(ctx: Context) -> Result<()> {
                 "explain_by_default:".bold(),
                 config.explain_by_default
             );
+            println!(
+                "  {} {}",
+                "audit_hash:".bold(),
+                config.audit_hash.unwrap_or_default()
+            );
+            println!(
+                "  {} {}",
+                "geo_db:".bold(),
+                if config.geo_db.is_empty() {
+                    "(not set)".dimmed().to_string()
+                } else {
+                    config.geo_db.join(", ")
+                }
+            );
         }
     }
 
@@ -105,6 +150,14 @@ async fn set_config(_ctx: Context, key: &str, value: &str) -> Result<()> {
             config.criminalip_key = Some(value.to_string());
             println!("{} Criminal IP API key set.", "Success:".green().bold());
         }
+        "misp-url" | "misp_url" => {
+            config.misp_url = Some(value.to_string());
+            println!("{} MISP URL set.", "Success:".green().bold());
+        }
+        "misp-key" | "misp_key" => {
+            config.misp_key = Some(value.to_string());
+            println!("{} MISP auth key set.", "Success:".green().bold());
+        }
         // Settings
         "output_format" | "output" => {
             config.output_format = Some(value.parse()?);
@@ -114,6 +167,14 @@ async fn set_config(_ctx: Context, key: &str, value: &str) -> Result<()> {
                 value.cyan()
             );
         }
+        "log_format" | "log-format" => {
+            config.log_format = Some(value.parse()?);
+            println!(
+                "{} Log format set to {}.",
+                "Success:".green().bold(),
+                value.cyan()
+            );
+        }
         "show_tips" => {
             config.show_tips = value.parse()?;
             println!("{} show_tips set to {}.", "Success:".green().bold(), value);
@@ -126,6 +187,26 @@ async fn set_config(_ctx: Context, key: &str, value: &str) -> Result<()> {
                 value
             );
         }
+        "audit_hash" | "audit-hash" => {
+            config.audit_hash = Some(value.parse()?);
+            println!(
+                "{} audit_hash set to {}.",
+                "Success:".green().bold(),
+                value.cyan()
+            );
+        }
+        "geo_db" | "geo-db" => {
+            config.geo_db = Config::split_paths(value);
+            if config.geo_db.is_empty() {
+                println!("{} geo_db cleared.", "Success:".green().bold());
+            } else {
+                println!(
+                    "{} geo_db set to {}.",
+                    "Success:".green().bold(),
+                    config.geo_db.join(", ").cyan()
+                );
+            }
+        }
         _ => {
             anyhow::bail!(
                 "Unknown config key: {key}\n\n\
@@ -134,9 +215,14 @@ async fn set_config(_ctx: Context, key: &str, value: &str) -> Result<()> {
                  censys-id        - Censys API ID\n  \
                  censys-secret    - Censys API secret\n  \
                  criminalip-key   - Criminal IP API key\n  \
+                 misp-url         - MISP instance URL\n  \
+                 misp-key         - MISP auth key\n  \
                  output_format    - Default output format (pretty/json/csv/yaml)\n  \
+                 log_format       - Format of notes and warnings on stderr (text/json)\n  \
                  show_tips        - Show helpful tips (true/false)\n  \
-                 explain_by_default - Always explain commands (true/false)"
+                 explain_by_default - Always explain commands (true/false)\n  \
+                 audit_hash       - Audit hash algorithm (sha256/blake3)\n  \
+                 geo_db           - Local .mmdb databases, comma-separated"
             );
         }
     }
@@ -151,3 +237,169 @@ async fn show_path(_ctx: Context) -> Result<()> {
     println!("{}", path.display());
     Ok(())
 }
+
+/// Read a new key from a hidden prompt, or from stdin when it is piped.
+fn read_new_key() -> Result<String> {
+    let key = if std::io::stdin().is_terminal() {
+        dialoguer::Password::new()
+            .with_prompt("New Shodan API key")
+            .interact()?
+    } else {
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .context("Failed to read the new key from stdin")?;
+        line
+    };
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        anyhow::bail!("No key given");
+    }
+    Ok(key)
+}
+
+/// Ask Shodan about `key`, turning a rejection into a readable error.
+async fn check_key(key: &str) -> Result<ApiInfo> {
+    match i1::ShodanProvider::new(key).api_info().await {
+        Ok(info) => Ok(info),
+        Err(e) if e.is_auth() => {
+            anyhow::bail!("Shodan rejected the key {}", mask_secret(key))
+        }
+        Err(e @ I1Error::InsufficientCredits { .. }) => {
+            Err(e).with_context(|| format!("Shodan refused the key {}", mask_secret(key)))
+        }
+        Err(e) => Err(e).context("Could not reach Shodan to check the key"),
+    }
+}
+
+async fn rotate_key(ctx: &Context) -> Result<()> {
+    let key = read_new_key()?;
+    let mut config = Config::load()?;
+    if config.shodan_key.as_deref() == Some(key.as_str()) {
+        anyhow::bail!("{} is already the configured key", mask_secret(&key));
+    }
+
+    // Nothing is written unless the new key works
+    let info = check_key(&key).await?;
+    config.rotate_shodan_key(key.clone(), Utc::now());
+    config.save()?;
+
+    let overridden: Vec<&str> = SHODAN_KEY_ENV
+        .into_iter()
+        .filter(|var| std::env::var(var).is_ok_and(|v| v != key))
+        .collect();
+    for var in &overridden {
+        tracing::warn!("{var} is set and overrides the configured key; update or unset it");
+    }
+
+    if ctx.output_format == OutputFormat::Json {
+        let previous = config.previous_api_key.as_ref();
+        let record = serde_json::json!({
+            "key": mask_secret(&key),
+            "previous_key": previous.map(|p| mask_secret(&p.key)),
+            "previous_key_expires": previous.map(|p| p.expires_at().to_rfc3339()),
+            "plan": info.plan,
+            "query_credits": info.query_credits,
+            "scan_credits": info.scan_credits,
+            "overridden_by": overridden,
+        });
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Shodan API key rotated to {}.",
+        "Success:".green().bold(),
+        mask_secret(&key).cyan()
+    );
+    if let Some(previous) = &config.previous_api_key {
+        println!(
+            "  Previous key {} kept as previous_api_key until {}.",
+            mask_secret(&previous.key),
+            previous.expires_at().format("%Y-%m-%d")
+        );
+    }
+    print_plan(&info);
+    Ok(())
+}
+
+/// Where the key in use came from, in the order `Context` resolves it.
+fn key_source(key: &str, config: &Config) -> &'static str {
+    SHODAN_KEY_ENV
+        .into_iter()
+        .find(|var| std::env::var(var).is_ok_and(|v| v == key))
+        .or_else(|| (config.shodan_key.as_deref() == Some(key)).then_some("config file"))
+        .unwrap_or("--api-key")
+}
+
+async fn verify_key(ctx: &Context) -> Result<()> {
+    let key = ctx.require_shodan_key()?;
+    let config = Config::load()?;
+    let source = key_source(key, &config);
+    let env_key = std::env::var(SHODAN_KEY_ENV[0]).ok();
+    let env_differs = env_key
+        .as_deref()
+        .zip(config.shodan_key.as_deref())
+        .is_some_and(|(env, configured)| env != configured);
+    let rotated_out = config
+        .previous_api_key
+        .as_ref()
+        .filter(|previous| previous.key == key);
+
+    let info = check_key(key).await?;
+
+    if ctx.output_format == OutputFormat::Json {
+        let record = serde_json::json!({
+            "key": mask_secret(key),
+            "source": source,
+            "plan": info.plan,
+            "query_credits": info.query_credits,
+            "scan_credits": info.scan_credits,
+            "env_key": env_key.as_deref().map(mask_secret),
+            "env_differs_from_config": env_differs,
+            "rotated_out": rotated_out.map(|p| p.rotated_at.to_rfc3339()),
+        });
+        println!("{}", serde_json::to_string_pretty(&record)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Shodan key {} (from {}) works.",
+        "OK:".green().bold(),
+        mask_secret(key).cyan(),
+        source
+    );
+    print_plan(&info);
+    println!();
+    match (&env_key, &config.shodan_key) {
+        (Some(env), Some(configured)) if env_differs => println!(
+            "  {} SHODAN_API_KEY ({}) differs from the config file key ({}); \
+             the environment variable wins.",
+            "Note:".yellow().bold(),
+            mask_secret(env),
+            mask_secret(configured)
+        ),
+        (Some(_), Some(_)) => println!("  SHODAN_API_KEY matches the config file key."),
+        (Some(_), None) => println!("  SHODAN_API_KEY is set; the config file has no key."),
+        (None, _) => println!("  SHODAN_API_KEY is not set."),
+    }
+    if let Some(previous) = rotated_out {
+        println!(
+            "  {} this key was rotated out on {}; run `i1 config rotate-key` again or \
+             update the place it comes from.",
+            "Warning:".bright_yellow().bold(),
+            previous.rotated_at.format("%Y-%m-%d")
+        );
+    }
+    Ok(())
+}
+
+fn print_plan(info: &ApiInfo) {
+    println!(
+        "  {} {}",
+        "Plan:".bold(),
+        info.plan.as_deref().unwrap_or("unknown")
+    );
+    println!("  {} {}", "Query credits:".bold(), info.query_credits);
+    println!("  {} {}", "Scan credits:".bold(), info.scan_credits);
+}
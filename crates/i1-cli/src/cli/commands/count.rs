@@ -23,6 +23,9 @@ pub async fn execute(ctx: Context, args: CountArgs) -> Result<()> {
             println!("total");
             println!("{count}");
         }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
         OutputFormat::Pretty => {
             if ctx.no_color {
                 println!("Total: {count}");
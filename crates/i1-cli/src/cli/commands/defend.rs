@@ -9,6 +9,9 @@ use crate::cli::args::{
     PatrolArgs, PatrolCommands, PullArgs, PushArgs, WhitelistArgs, WhitelistCommands,
 };
 use crate::defend;
+use crate::defend::feeds::{self, FeedFormat};
+use crate::defend::lint;
+use crate::output::render::{Column, Document, Renderer};
 use crate::output::OutputFormat;
 
 pub async fn execute(ctx: Context, args: DefendArgs) -> Result<()> {
@@ -19,11 +22,19 @@ pub async fn execute(ctx: Context, args: DefendArgs) -> Result<()> {
             target,
             as_number,
             dry_run,
-        } => ban(ctx, &target, as_number, dry_run).await,
+            format,
+        } => ban(ctx, &target, as_number, dry_run, &format),
         DefendCommands::Unban { target } => unban(ctx, &target).await,
         DefendCommands::Whitelist(wl) => whitelist(ctx, wl).await,
-        DefendCommands::Export { format } => export(ctx, &format).await,
-        DefendCommands::Import { stdin, file } => import(ctx, stdin, file.as_deref()).await,
+        DefendCommands::Export { format, zone } => export(ctx, &format, &zone),
+        DefendCommands::TestRules { format } => test_rules(&ctx, &format),
+        DefendCommands::Import {
+            source,
+            format,
+            stdin,
+            file,
+            dry_run,
+        } => import(ctx, source.or(file).as_deref(), format, stdin, dry_run).await,
         DefendCommands::Undo => undo(ctx).await,
         DefendCommands::Disable => disable(ctx).await,
         DefendCommands::Push(args) => push(ctx, args).await,
@@ -48,84 +59,80 @@ async fn status(ctx: Context, quick: bool) -> Result<()> {
         return Ok(());
     }
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&state)?);
-        }
-        OutputFormat::Yaml => {
-            println!("{}", serde_yaml::to_string(&state)?);
-        }
-        _ => {
-            println!("{}", "Defense Status".bold().underline());
-            println!();
-
-            // Countries (inbound)
-            if state.blocked_countries.is_empty() {
-                println!("{} None", "Blocked Countries (inbound):".bold());
-            } else {
-                println!("{}", "Blocked Countries (inbound):".bold());
-                for code in &state.blocked_countries {
-                    let name = defend::country_name(code);
-                    println!("  {} - {}", code.to_uppercase().red(), name);
-                }
-            }
-            println!();
-
-            // Countries (outbound - honeypot mode)
-            if !state.blocked_countries_outbound.is_empty() {
-                println!(
-                    "{} {}",
-                    "Blocked Countries (outbound".bold(),
-                    "- honeypot mode):".bold()
-                );
-                for code in &state.blocked_countries_outbound {
-                    let name = defend::country_name(code);
-                    println!("  {} - {} {}", code.to_uppercase().red(), name, "(no response)".dimmed());
-                }
-                println!();
-            }
-
-            // IPs
-            println!(
-                "{} {}",
-                "Blocked IPs/Ranges:".bold(),
-                state.blocked_ips.len()
-            );
-            for ip in state.blocked_ips.iter().take(10) {
-                println!("  {}", ip.red());
-            }
-            if state.blocked_ips.len() > 10 {
-                println!("  ... and {} more", state.blocked_ips.len() - 10);
-            }
-            println!();
-
-            // ASNs
-            println!("{} {}", "Blocked ASNs:".bold(), state.blocked_asns.len());
-            for asn in state.blocked_asns.iter().take(5) {
-                println!("  {}", asn.red());
-            }
-            println!();
-
-            // Whitelist
-            println!(
-                "{} {}",
-                "Whitelisted IPs:".bold(),
-                state.whitelisted_ips.len()
-            );
-            for ip in &state.whitelisted_ips {
-                println!("  {}", ip.green());
-            }
-            println!();
+    Renderer::from_context(&ctx).print_document(&status_document(&state)?)
+}
 
-            // Tip
-            println!(
-                "{}",
-                "Use 'defend export' to generate firewall rules.".dimmed()
-            );
-        }
+/// Blocked IPs shown in pretty status output.
+const STATUS_IPS: usize = 10;
+/// Blocked ASNs shown in pretty status output.
+const STATUS_ASNS: usize = 5;
+
+/// The defense state as a document with one row.
+fn status_document(state: &defend::State) -> Result<Document> {
+    let columns = [
+        "blocked_countries",
+        "blocked_countries_outbound",
+        "blocked_ips",
+        "blocked_asns",
+        "whitelisted_ips",
+    ]
+    .into_iter()
+    .map(|key| Column::new(key, key))
+    .collect();
+    let countries = |codes: &[String], suffix: &str| -> Vec<String> {
+        codes
+            .iter()
+            .map(|code| {
+                format!(
+                    "{} - {}{suffix}",
+                    code.to_uppercase(),
+                    defend::country_name(code)
+                )
+            })
+            .collect()
+    };
+    let mut ips: Vec<String> = state.blocked_ips.iter().take(STATUS_IPS).cloned().collect();
+    if state.blocked_ips.len() > STATUS_IPS {
+        ips.push(format!(
+            "... and {} more",
+            state.blocked_ips.len() - STATUS_IPS
+        ));
     }
+    let asns = state
+        .blocked_asns
+        .iter()
+        .take(STATUS_ASNS)
+        .map(|asn| format!("{asn}{}", asn_owner(asn)))
+        .collect();
 
-    Ok(())
+    Ok(Document::new(state, columns)?
+        .fields(Some("Defense Status"), Vec::new())
+        .list(
+            "Blocked Countries (inbound):",
+            countries(&state.blocked_countries, ""),
+            Some("None"),
+        )
+        .list(
+            "Blocked Countries (outbound - honeypot mode):",
+            countries(&state.blocked_countries_outbound, " (no response)"),
+            None,
+        )
+        .list(
+            &format!("Blocked IPs/Ranges: {}", state.blocked_ips.len()),
+            ips,
+            Some(""),
+        )
+        .list(
+            &format!("Blocked ASNs: {}", state.blocked_asns.len()),
+            asns,
+            Some(""),
+        )
+        .list(
+            &format!("Whitelisted IPs: {}", state.whitelisted_ips.len()),
+            state.whitelisted_ips.clone(),
+            Some(""),
+        )
+        .note("Use 'defend export' to generate firewall rules."))
 }
 
 async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
@@ -173,8 +180,11 @@ async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
             countries,
             direction,
             dry_run,
+            format,
         } => {
-            let mut state = defend::State::load()?;
+            let firewall = parse_firewall(&format)?;
+            let (mut state, lock) = defend::State::load_for_update()?;
+            let before = state.clone();
             let mut added = Vec::new();
             let dir = direction.to_lowercase();
 
@@ -208,10 +218,11 @@ async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
             if dry_run {
                 println!("{}", "[DRY RUN]".yellow().bold());
                 println!("Would block: {}", added.join(", ").red());
+                print_rule_preview(firewall, &before, &state)?;
                 println!();
                 println!("Run without --dry-run to apply.");
             } else {
-                state.save()?;
+                state.save(&lock)?;
                 println!(
                     "{} Now blocking: {}",
                     "Success:".green().bold(),
@@ -235,7 +246,7 @@ async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
             country,
             direction,
         } => {
-            let mut state = defend::State::load()?;
+            let (mut state, lock) = defend::State::load_for_update()?;
             let normalized = country.to_lowercase();
             let dir = direction.to_lowercase();
             let mut removed = false;
@@ -263,7 +274,7 @@ async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
             }
 
             if removed {
-                state.save()?;
+                state.save(&lock)?;
                 println!(
                     "{} Removed {} from blocked countries ({}).",
                     "Success:".green().bold(),
@@ -333,10 +344,33 @@ async fn geoblock(_ctx: Context, args: GeoblockArgs) -> Result<()> {
     }
 }
 
-async fn ban(_ctx: Context, target: &str, as_number: bool, dry_run: bool) -> Result<()> {
+fn ban(
+    _ctx: Context,
+    target: &str,
+    as_number: bool,
+    dry_run: bool,
+    format: &str,
+) -> Result<()> {
+    let firewall = parse_firewall(format)?;
+
+    // IPv4 or IPv6 address or CIDR, in canonical form
+    let target = if as_number {
+        target.to_string()
+    } else {
+        defend::parse_target(target)?
+    };
+    let target = target.as_str();
+
     // Safety check: refuse to block your own SSH session
     if let Some(ssh_ip) = get_ssh_client_ip() {
-        if target == ssh_ip || target.starts_with(&format!("{}/", ssh_ip)) {
+        let covers_ssh = match (
+            defend::feeds::Network::parse(target),
+            defend::feeds::Network::parse(&ssh_ip),
+        ) {
+            (Some(blocked), Some(ssh)) => blocked.contains(&ssh),
+            _ => target == ssh_ip,
+        };
+        if covers_ssh {
             println!(
                 "{} Refusing to block {} - that's your current SSH session!",
                 "🛡️ PROTECTED:".yellow().bold(),
@@ -351,25 +385,38 @@ async fn ban(_ctx: Context, target: &str, as_number: bool, dry_run: bool) -> Res
         }
     }
 
-    let mut state = defend::State::load()?;
+    let (mut state, lock) = defend::State::load_for_update()?;
 
     if as_number {
         // Ban AS number
         let asn = target.trim_start_matches("AS").trim_start_matches("as");
         if dry_run {
             println!("{} Would block AS{}", "[DRY RUN]".yellow().bold(), asn);
+            let mut after = state.clone();
+            after.blocked_asns.push(format!("AS{asn}"));
+            print_rule_preview(firewall, &state, &after)?;
         } else {
             state.blocked_asns.push(format!("AS{asn}"));
-            state.save()?;
-            println!("{} Blocked AS{}", "Success:".green().bold(), asn.red());
+            state.save(&lock)?;
+            println!(
+                "{} Blocked AS{}{}",
+                "Success:".green().bold(),
+                asn.red(),
+                asn_owner(asn).dimmed()
+            );
         }
     } else {
         // Ban IP or CIDR
-        if dry_run {
+        if state.blocked_ips.iter().any(|ip| ip == target) {
+            tracing::info!("{target} is already blocked");
+        } else if dry_run {
             println!("{} Would block {}", "[DRY RUN]".yellow().bold(), target);
+            let mut after = state.clone();
+            after.blocked_ips.push(target.to_string());
+            print_rule_preview(firewall, &state, &after)?;
         } else {
             state.blocked_ips.push(target.to_string());
-            state.save()?;
+            state.save(&lock)?;
             println!("{} Blocked {}", "Success:".green().bold(), target.red());
         }
     }
@@ -381,7 +428,7 @@ async fn ban(_ctx: Context, target: &str, as_number: bool, dry_run: bool) -> Res
 }
 
 async fn unban(_ctx: Context, target: &str) -> Result<()> {
-    let mut state = defend::State::load()?;
+    let (mut state, lock) = defend::State::load_for_update()?;
 
     // Check if it's an ASN
     if target.to_uppercase().starts_with("AS") {
@@ -391,21 +438,27 @@ async fn unban(_ctx: Context, target: &str) -> Result<()> {
             .position(|a| a.eq_ignore_ascii_case(target))
         {
             state.blocked_asns.remove(pos);
-            state.save()?;
+            state.save(&lock)?;
             println!("{} Unblocked {}", "Success:".green().bold(), target.cyan());
             return Ok(());
         }
     }
 
-    // Check IPs
-    if let Some(pos) = state.blocked_ips.iter().position(|i| i == target) {
-        state.blocked_ips.remove(pos);
-        state.save()?;
+    // Check IPs, as typed or in the canonical form `ban` stores
+    let canonical = defend::parse_target(target).ok();
+    if let Some(pos) = state
+        .blocked_ips
+        .iter()
+        .position(|i| i == target || Some(i) == canonical.as_ref())
+    {
+        let ip = state.blocked_ips.remove(pos);
+        state.ban_details.remove(&ip);
+        state.save(&lock)?;
         println!("{} Unblocked {}", "Success:".green().bold(), target.cyan());
         return Ok(());
     }
 
-    println!("{} {} is not currently blocked.", "Note:".yellow(), target);
+    tracing::info!("{target} is not currently blocked");
     Ok(())
 }
 
@@ -424,12 +477,13 @@ async fn whitelist(_ctx: Context, args: WhitelistArgs) -> Result<()> {
             Ok(())
         }
         WhitelistCommands::Add { ip } => {
-            let mut state = defend::State::load()?;
+            let ip = defend::parse_target(&ip)?;
+            let (mut state, lock) = defend::State::load_for_update()?;
             if state.whitelisted_ips.contains(&ip) {
                 println!("{ip} is already whitelisted.");
             } else {
                 state.whitelisted_ips.push(ip.clone());
-                state.save()?;
+                state.save(&lock)?;
                 println!(
                     "{} Added {} to whitelist.",
                     "Success:".green().bold(),
@@ -439,10 +493,15 @@ async fn whitelist(_ctx: Context, args: WhitelistArgs) -> Result<()> {
             Ok(())
         }
         WhitelistCommands::Remove { ip } => {
-            let mut state = defend::State::load()?;
-            if let Some(pos) = state.whitelisted_ips.iter().position(|i| i == &ip) {
+            let (mut state, lock) = defend::State::load_for_update()?;
+            let canonical = defend::parse_target(&ip).ok();
+            if let Some(pos) = state
+                .whitelisted_ips
+                .iter()
+                .position(|i| i == &ip || Some(i) == canonical.as_ref())
+            {
                 state.whitelisted_ips.remove(pos);
-                state.save()?;
+                state.save(&lock)?;
                 println!(
                     "{} Removed {} from whitelist.",
                     "Success:".green().bold(),
@@ -456,7 +515,7 @@ async fn whitelist(_ctx: Context, args: WhitelistArgs) -> Result<()> {
     }
 }
 
-async fn export(_ctx: Context, format: &str) -> Result<()> {
+fn export(_ctx: Context, format: &str, zone: &str) -> Result<()> {
     let state = defend::State::load()?;
 
     match format.to_lowercase().as_str() {
@@ -472,13 +531,25 @@ async fn export(_ctx: Context, format: &str) -> Result<()> {
             let rules = defend::generate_pf(&state)?;
             println!("{rules}");
         }
+        "rpz" => {
+            let origin = format!("{}.", zone.trim_end_matches('.'));
+            let serial = defend::rpz::bump_serial()?;
+            print!("{}", defend::rpz::generate_rpz(&state, &origin, serial));
+            let skipped = state.blocked_countries.len() + state.blocked_asns.len();
+            if skipped > 0 {
+                tracing::info!(
+                    "{skipped} blocked countries/ASNs have no prefixes to export as RPZ triggers"
+                );
+            }
+        }
         _ => {
             anyhow::bail!(
                 "Unknown format: {format}\n\n\
                  Supported formats:\n  \
                  nftables  - Linux nftables (recommended)\n  \
                  iptables  - Legacy iptables\n  \
-                 pf        - BSD/macOS pf"
+                 pf        - BSD/macOS pf\n  \
+                 rpz       - DNS response policy zone (BIND, Unbound, Knot)"
             );
         }
     }
@@ -486,15 +557,211 @@ async fn export(_ctx: Context, format: &str) -> Result<()> {
     Ok(())
 }
 
-async fn import(_ctx: Context, stdin: bool, file: Option<&str>) -> Result<()> {
-    println!("{}", "Import feature coming soon!".yellow());
+/// Parse a firewall format name, as `export` and `test-rules` accept it.
+fn parse_firewall(format: &str) -> Result<lint::Firewall> {
+    lint::Firewall::parse(format).ok_or_else(|| {
+        anyhow::anyhow!("Unknown format: {format}\n\nFirewall formats: nftables, iptables, pf")
+    })
+}
+
+/// Print the lines `defend export` would add and remove if the state went
+/// from `before` to `after`.
+fn print_rule_preview(
+    firewall: lint::Firewall,
+    before: &defend::State,
+    after: &defend::State,
+) -> Result<()> {
+    let changes = defend::rule_changes(firewall, before, after)?;
     println!();
-    println!("This will allow importing IPs from:");
-    if stdin {
-        println!("  - Standard input (pipe from other commands)");
+    if changes.is_empty() {
+        println!(
+            "{}",
+            format!("No change to the exported {firewall} rules.").dimmed()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("Rule changes (defend export --format {firewall}):").bold()
+    );
+    for change in &changes {
+        match change {
+            defend::RuleChange::Added(line) => println!("{}", format!("+ {line}").green()),
+            defend::RuleChange::Removed(line) => println!("{}", format!("- {line}").red()),
+        }
+    }
+    Ok(())
+}
+
+fn test_rules(ctx: &Context, formats: &[String]) -> Result<()> {
+    let firewalls = if formats.is_empty() {
+        lint::Firewall::ALL.to_vec()
+    } else {
+        formats
+            .iter()
+            .map(|format| parse_firewall(format))
+            .collect::<Result<_>>()?
+    };
+
+    let state = defend::State::load()?;
+    let mut reports = Vec::new();
+    let mut sources = Vec::new();
+    for firewall in firewalls {
+        let rules = firewall.generate(&state)?;
+        reports.push(lint::check(firewall, &rules));
+        sources.push(rules);
     }
-    if let Some(f) = file {
-        println!("  - File: {f}");
+
+    match ctx.output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&reports)?),
+        OutputFormat::Csv => {
+            println!("format,checker,line,message");
+            for report in &reports {
+                for finding in &report.findings {
+                    println!(
+                        "{},{},{},\"{}\"",
+                        report.firewall,
+                        report.checker,
+                        finding.line.map(|l| l.to_string()).unwrap_or_default(),
+                        finding.message.replace('"', "\"\"")
+                    );
+                }
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => {
+            for (report, rules) in reports.iter().zip(&sources) {
+                let status = if report.is_ok() {
+                    "ok".green()
+                } else {
+                    format!("{} problem(s)", report.findings.len()).red()
+                };
+                println!(
+                    "{:10} {status}  {}",
+                    report.firewall.to_string().bold(),
+                    format!("({})", report.checker).dimmed()
+                );
+                if let Some(note) = &report.note {
+                    println!("           {}", note.dimmed());
+                }
+                for finding in &report.findings {
+                    match finding.line {
+                        Some(line) => {
+                            println!("  line {line}: {}", finding.message);
+                            if let Some(text) = rules.lines().nth(line - 1) {
+                                println!("    {}", text.trim().dimmed());
+                            }
+                        }
+                        None => println!("  {}", finding.message),
+                    }
+                }
+            }
+        }
+    }
+
+    let problems: usize = reports.iter().map(|r| r.findings.len()).sum();
+    if problems > 0 {
+        anyhow::bail!("{problems} problem(s) in the generated rules; nothing was applied");
+    }
+    Ok(())
+}
+
+async fn import(
+    _ctx: Context,
+    source: Option<&str>,
+    format: FeedFormat,
+    stdin: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let text = match source {
+        _ if stdin || source == Some("-") => std::io::read_to_string(std::io::stdin())?,
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            print!("{} Fetching {}... ", "→".cyan(), url);
+            std::io::Write::flush(&mut std::io::stdout())?;
+            let text = reqwest::get(url).await?.error_for_status()?.text().await?;
+            println!("{}", "✓".green());
+            text
+        }
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read {path}: {e}"))?,
+        None => anyhow::bail!("Specify a feed URL or file, or use --stdin"),
+    };
+
+    let parsed = feeds::parse(format, &text);
+    if parsed.networks.is_empty() {
+        anyhow::bail!(
+            "No addresses found in the feed ({} unreadable lines). Is --format right?",
+            parsed.invalid
+        );
+    }
+
+    let (mut state, lock) = defend::State::load_for_update()?;
+    let protected: Vec<std::net::IpAddr> = get_ssh_client_ip()
+        .and_then(|ip| ip.parse().ok())
+        .into_iter()
+        .collect();
+    let report = feeds::merge(&mut state, &parsed.networks, &protected);
+
+    println!(
+        "{} {} entries read",
+        "Feed:".bold(),
+        parsed.networks.len().to_string().cyan()
+    );
+    println!("  {} new", report.added.len().to_string().green());
+    println!("  {} already blocked", report.duplicate);
+    if !report.subsumed.is_empty() {
+        println!(
+            "  {} existing entries folded into wider ranges",
+            report.subsumed.len()
+        );
+    }
+    if report.protected > 0 {
+        println!(
+            "  {} skipped (whitelist or your SSH session)",
+            report.protected.to_string().yellow()
+        );
+    }
+    if report.reserved > 0 {
+        println!(
+            "  {} skipped (private, loopback or reserved space)",
+            report.reserved.to_string().yellow()
+        );
+    }
+    if parsed.invalid > 0 {
+        println!("  {} unreadable lines", parsed.invalid.to_string().yellow());
+    }
+
+    if !report.added.is_empty() {
+        println!();
+        for network in report.added.iter().take(10) {
+            println!("  {}", network.to_string().red());
+        }
+        if report.added.len() > 10 {
+            println!("  ... and {} more", report.added.len() - 10);
+        }
+    }
+
+    println!();
+    if dry_run {
+        println!(
+            "{} Would block {} new entries.",
+            "[DRY RUN]".yellow().bold(),
+            report.added.len()
+        );
+    } else if report.added.is_empty() {
+        println!("Nothing new to block.");
+    } else {
+        state.save(&lock)?;
+        println!(
+            "{} Blocked {} new entries.",
+            "Success:".green().bold(),
+            report.added.len()
+        );
+        println!("Generate rules with: {} defend export", "i1".cyan());
     }
     Ok(())
 }
@@ -618,7 +885,7 @@ async fn push(_ctx: Context, args: PushArgs) -> Result<()> {
     }
 
     if commands.is_empty() {
-        println!("{} No rules to push.", "Note:".yellow());
+        tracing::info!("No rules to push");
         return Ok(());
     }
 
@@ -867,7 +1134,7 @@ async fn pull(_ctx: Context, args: PullArgs) -> Result<()> {
     }
 
     // Load current state and merge/replace
-    let mut state = defend::State::load()?;
+    let (mut state, lock) = defend::State::load_for_update()?;
 
     if args.merge {
         // Merge with existing
@@ -889,7 +1156,7 @@ async fn pull(_ctx: Context, args: PullArgs) -> Result<()> {
         println!("{} Replaced local state with remote rules.", "✓".green());
     }
 
-    state.save()?;
+    state.save(&lock)?;
 
     println!();
     println!(
@@ -1004,11 +1271,7 @@ async fn community_contribute(fail2ban: bool, min_hits: u32, dry_run: bool) -> R
 
     if filtered.is_empty() {
         println!();
-        println!(
-            "{} No IPs meet the minimum threshold of {} hits.",
-            "Note:".yellow(),
-            min_hits
-        );
+        tracing::info!("No IPs meet the minimum threshold of {min_hits} hits");
         println!("Try lowering --min-hits or add --fail2ban to include fail2ban data.");
         return Ok(());
     }
@@ -1155,7 +1418,7 @@ async fn community_fetch(min_reports: u32, replace: bool, dry_run: bool) -> Resu
             }
 
             // Save to state
-            let mut state = defend::State::load()?;
+            let (mut state, lock) = defend::State::load_for_update()?;
 
             if replace {
                 state.blocked_ips = ips;
@@ -1173,7 +1436,7 @@ async fn community_fetch(min_reports: u32, replace: bool, dry_run: bool) -> Resu
                 println!("{} Added {} new IPs from community.", "✓".green(), added);
             }
 
-            state.save()?;
+            state.save(&lock)?;
         }
         Ok(resp) => {
             println!("{}", "✗".red());
@@ -1280,10 +1543,7 @@ async fn community_subscribe(interval: u32, remove: bool) -> Result<()> {
     if let Ok(out) = existing {
         current_crontab = String::from_utf8_lossy(&out.stdout).to_string();
         if current_crontab.contains("i1 defend community") {
-            println!(
-                "{} Cron job already exists. Use --remove to delete it first.",
-                "Note:".yellow()
-            );
+            tracing::info!("Cron job already exists. Use --remove to delete it first.");
             return Ok(());
         }
     }
@@ -1907,7 +2167,7 @@ async fn patrol_run(
     }
 
     // Ban them
-    let mut state = defend::State::load()?;
+    let (mut state, lock) = defend::State::load_for_update()?;
     let mut banned_count = 0;
 
     for attacker in &new_attackers {
@@ -1929,7 +2189,7 @@ async fn patrol_run(
         }
     }
 
-    state.save()?;
+    state.save(&lock)?;
 
     println!(
         "{} Banned {} new attacker(s){}",
@@ -2036,10 +2296,7 @@ async fn patrol_cron(interval: u32, remove: bool, threshold: u32) -> Result<()>
     if let Ok(out) = existing {
         current_crontab = String::from_utf8_lossy(&out.stdout).to_string();
         if current_crontab.contains("i1 defend patrol") {
-            println!(
-                "{} Patrol cron already exists. Use --remove to delete it first.",
-                "Note:".yellow()
-            );
+            tracing::info!("Patrol cron already exists. Use --remove to delete it first.");
             return Ok(());
         }
     }
@@ -2135,6 +2392,14 @@ fn patrol_log_entry(msg: &str) -> Result<()> {
     Ok(())
 }
 
+/// `" (operator, CC)"` for ASNs in the bundled snapshot, empty otherwise.
+fn asn_owner(asn: &str) -> String {
+    i1_core::asn::parse_asn(asn)
+        .and_then(i1_core::asn::lookup_asn)
+        .map(|info| format!(" ({}, {})", info.org, info.country))
+        .unwrap_or_default()
+}
+
 fn is_valid_ip(s: &str) -> bool {
     s.parse::<std::net::IpAddr>().is_ok()
 }
@@ -2160,3 +2425,25 @@ fn extract_source_ip(line: &str) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render::{all_formats, assert_golden};
+    use serde_json::json;
+
+    #[test]
+    fn golden_status() {
+        let state: defend::State = serde_json::from_value(json!({
+            "schema_version": 1,
+            "blocked_countries": ["cn", "ru"],
+            "blocked_countries_outbound": [],
+            "blocked_ips": ["203.0.113.0/24", "198.51.100.9"],
+            "blocked_asns": [],
+            "whitelisted_ips": ["192.0.2.1"]
+        }))
+        .unwrap();
+        let document = status_document(&state).unwrap();
+        assert_golden("defend_status.txt", &all_formats(&document, None));
+    }
+}
@@ -1,12 +1,16 @@
 //! `i1 dns` - DNS lookups.
 
-use anyhow::Result;
-use colored::Colorize;
+use anyhow::{Context as _, Result};
+use colored::{Color, Colorize};
+use futures_util::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
 
 use super::Context;
 use crate::cli::args::{DnsArgs, DnsCommands};
 use crate::output::OutputFormat;
-use i1_providers::DnsProvider;
+use i1_providers::{DnsProvider, DnsRecord, DnsRecordType, DomainDiff, TypedDomainInfo};
 
 pub async fn execute(ctx: Context, args: DnsArgs) -> Result<()> {
     let provider = ctx.shodan_provider()?;
@@ -28,6 +32,9 @@ pub async fn execute(ctx: Context, args: DnsArgs) -> Result<()> {
                         println!("{hostname},{ip}");
                     }
                 }
+                OutputFormat::Stix | OutputFormat::Ndjson => {
+                    unreachable!("STIX and NDJSON output are rejected before dispatch")
+                }
                 OutputFormat::Pretty => {
                     if ctx.no_color {
                         println!("{hostname}");
@@ -40,39 +47,370 @@ pub async fn execute(ctx: Context, args: DnsArgs) -> Result<()> {
                 }
             }
         }
-        DnsCommands::Reverse { ip } => {
-            let hostnames = provider.reverse(&ip).await?;
+        DnsCommands::Reverse {
+            ips,
+            file,
+            stdin,
+            batch_size,
+            concurrency,
+        } => {
+            let ips = collect_ips(ips, file.as_deref(), stdin)?;
+            let show_progress = ctx.output_format == OutputFormat::Pretty;
+            let hostnames = reverse_all(
+                &provider,
+                &ips,
+                batch_size.max(1),
+                concurrency.max(1),
+                show_progress,
+            )
+            .await?;
+            print_reverse(&ctx, &hostnames)?;
+        }
+        DnsCommands::Domain {
+            domain,
+            record_type,
+            history,
+            diff,
+        } => {
+            if diff {
+                let history = provider.domain_history(&domain).await?;
+                let Some(diff) = DomainDiff::latest(&history, record_type) else {
+                    anyhow::bail!("{domain} has fewer than two historical snapshots to compare");
+                };
+                print_diff(&ctx, &diff)?;
+            } else {
+                let info = if history {
+                    provider.domain_history(&domain).await?
+                } else {
+                    provider.domain_info(&domain).await?
+                };
+                print_domain(&ctx, &info.typed(record_type))?;
+            }
+        }
+    }
 
-            match ctx.output_format {
-                OutputFormat::Json => {
-                    println!("{}", serde_json::to_string_pretty(&hostnames)?);
+    Ok(())
+}
+
+/// Progress bar layout for bulk lookups (an indicatif template).
+#[allow(clippy::literal_string_with_formatting_args)]
+const PROGRESS_TEMPLATE: &str =
+    "{spinner} Resolving PTR records [{bar:30}] {pos}/{len} IPs ({elapsed})";
+
+/// Gather IPs from the arguments, a file, and stdin, skipping blank lines
+/// and `#` comments. Duplicates are dropped.
+fn collect_ips(args: Vec<String>, file: Option<&str>, stdin: bool) -> Result<Vec<IpAddr>> {
+    let mut lines = args;
+    if let Some(path) = file {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read IPs from {path}"))?;
+        lines.extend(content.lines().map(String::from));
+    }
+    if stdin {
+        for line in std::io::stdin().lines() {
+            lines.push(line?);
+        }
+    }
+
+    let mut ips = Vec::new();
+    let mut seen = BTreeSet::new();
+    for line in &lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let ip: IpAddr = line
+            .parse()
+            .with_context(|| format!("Invalid IP address: {line}"))?;
+        if seen.insert(ip) {
+            ips.push(ip);
+        }
+    }
+    if ips.is_empty() {
+        anyhow::bail!("No IP addresses given");
+    }
+    Ok(ips)
+}
+
+/// Look up PTR records in batches of `batch_size`, with up to
+/// `concurrency` requests in flight. Every IP is in the result; those
+/// without a PTR record map to an empty list.
+async fn reverse_all(
+    provider: &impl DnsProvider,
+    ips: &[IpAddr],
+    batch_size: usize,
+    concurrency: usize,
+    show_progress: bool,
+) -> Result<BTreeMap<IpAddr, Vec<String>>> {
+    let progress = if show_progress && ips.len() > batch_size {
+        let bar = ProgressBar::new(ips.len() as u64);
+        bar.set_style(ProgressStyle::with_template(PROGRESS_TEMPLATE)?);
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let mut hostnames: BTreeMap<IpAddr, Vec<String>> =
+        ips.iter().map(|ip| (*ip, Vec::new())).collect();
+    let mut batches = stream::iter(ips.chunks(batch_size))
+        .map(|chunk| async move {
+            let batch: Vec<String> = chunk.iter().map(ToString::to_string).collect();
+            (chunk.len(), provider.reverse_many(&batch).await)
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some((count, result)) = batches.next().await {
+        for (ip, names) in result? {
+            // Key by parsed address so the provider's formatting doesn't matter.
+            if let Ok(ip) = ip.parse() {
+                hostnames.insert(ip, names);
+            }
+        }
+        progress.inc(count as u64);
+    }
+    progress.finish_and_clear();
+
+    Ok(hostnames)
+}
+
+fn print_reverse(ctx: &Context, hostnames: &BTreeMap<IpAddr, Vec<String>>) -> Result<()> {
+    match ctx.output_format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(hostnames)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(hostnames)?);
+        }
+        OutputFormat::Csv => {
+            println!("ip,hostname");
+            for (ip, names) in hostnames {
+                if names.is_empty() {
+                    println!("{ip},");
                 }
-                OutputFormat::Yaml => {
-                    println!("{}", serde_yaml::to_string(&hostnames)?);
+                for hostname in names {
+                    println!("{ip},{hostname}");
                 }
-                OutputFormat::Csv => {
-                    println!("ip,hostname");
-                    for hostname in &hostnames {
-                        println!("{ip},{hostname}");
-                    }
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => {
+            for (ip, names) in hostnames {
+                if ctx.no_color {
+                    println!("{ip}");
+                } else {
+                    println!("{}", ip.to_string().cyan());
                 }
-                OutputFormat::Pretty => {
-                    if ctx.no_color {
-                        println!("{ip}");
-                    } else {
-                        println!("{}", ip.cyan());
+                if names.is_empty() {
+                    println!("  No PTR records found");
+                } else {
+                    for hostname in names {
+                        println!("  -> {hostname}");
                     }
-                    if hostnames.is_empty() {
-                        println!("  No PTR records found");
+                }
+            }
+            let missing = hostnames.values().filter(|names| names.is_empty()).count();
+            if hostnames.len() > 1 && missing > 0 {
+                println!();
+                let summary = format!("{missing} of {} IPs have no PTR record", hostnames.len());
+                if ctx.no_color {
+                    println!("{summary}");
+                } else {
+                    println!("{}", summary.yellow());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Heading for records outside [`DnsRecordType`].
+const OTHER: &str = "OTHER";
+
+fn print_domain(ctx: &Context, info: &TypedDomainInfo) -> Result<()> {
+    match ctx.output_format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(info)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(info)?);
+        }
+        OutputFormat::Csv => {
+            println!("type,name,value,last_seen");
+            for record in info.records.iter() {
+                println!(
+                    "{},{},{},{}",
+                    record.record_type.to_ascii_uppercase(),
+                    fqdn(&info.domain, &record.name),
+                    record.value,
+                    record.last_seen.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => {
+            if ctx.no_color {
+                println!("{}", info.domain);
+            } else {
+                println!("{}", info.domain.green());
+            }
+            if info.records.is_empty() {
+                println!("  No matching records found");
+            }
+            for (kind, records) in groups(info) {
+                println!();
+                if ctx.no_color {
+                    println!("  {kind}");
+                } else {
+                    println!("  {}", kind.bold());
+                }
+                for record in records {
+                    let name = fqdn(&info.domain, &record.name);
+                    let seen = record
+                        .last_seen
+                        .as_deref()
+                        .map(|seen| format!(" (last seen {})", seen.get(..10).unwrap_or(seen)))
+                        .unwrap_or_default();
+                    if kind == OTHER {
+                        println!(
+                            "    {name} -> {} {}{seen}",
+                            record.record_type, record.value
+                        );
                     } else {
-                        for hostname in &hostnames {
-                            println!("  -> {hostname}");
-                        }
+                        println!("    {name} -> {}{seen}", record.value);
                     }
                 }
             }
+            if !info.subdomains.is_empty() {
+                println!();
+                println!("  Subdomains ({})", info.subdomains.len());
+                for sub in &info.subdomains {
+                    println!("    {}", fqdn(&info.domain, sub));
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+fn print_diff(ctx: &Context, diff: &DomainDiff) -> Result<()> {
+    match ctx.output_format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(diff)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(diff)?);
+        }
+        OutputFormat::Csv => {
+            println!("change,type,name,value");
+            for sub in &diff.added_subdomains {
+                println!("subdomain_added,,{},", fqdn(&diff.domain, sub));
+            }
+            for sub in &diff.removed_subdomains {
+                println!("subdomain_removed,,{},", fqdn(&diff.domain, sub));
+            }
+            for change in &diff.changed {
+                let name = fqdn(&diff.domain, &change.name);
+                for value in &change.added {
+                    println!("record_added,{},{name},{value}", change.record_type);
+                }
+                for value in &change.removed {
+                    println!("record_removed,{},{name},{value}", change.record_type);
+                }
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => print_diff_pretty(ctx, diff),
+    }
+
+    Ok(())
+}
+
+fn print_diff_pretty(ctx: &Context, diff: &DomainDiff) {
+    let paint = |text: String, color: Color| {
+        if ctx.no_color {
+            text
+        } else {
+            text.color(color).to_string()
+        }
+    };
+
+    println!(
+        "{}  {} -> {}",
+        paint(diff.domain.clone(), Color::Green),
+        diff.from,
+        diff.to
+    );
+    if diff.is_empty() {
+        println!("  No changes between snapshots");
+        return;
+    }
+
+    // New subdomains are the strongest signal, so they lead.
+    if !diff.added_subdomains.is_empty() {
+        println!();
+        println!("  New subdomains ({})", diff.added_subdomains.len());
+        for sub in &diff.added_subdomains {
+            let line = format!("    + {}", fqdn(&diff.domain, sub));
+            if ctx.no_color {
+                println!("{line}");
+            } else {
+                println!("{}", line.green().bold());
+            }
+        }
+    }
+    if !diff.removed_subdomains.is_empty() {
+        println!();
+        println!("  Removed subdomains ({})", diff.removed_subdomains.len());
+        for sub in &diff.removed_subdomains {
+            println!(
+                "{}",
+                paint(format!("    - {}", fqdn(&diff.domain, sub)), Color::Red)
+            );
+        }
+    }
+    if !diff.changed.is_empty() {
+        println!();
+        println!("  Changed records ({})", diff.changed.len());
+        for change in &diff.changed {
+            println!(
+                "    {} {}",
+                change.record_type,
+                fqdn(&diff.domain, &change.name)
+            );
+            for value in &change.removed {
+                println!("{}", paint(format!("      - {value}"), Color::Red));
+            }
+            for value in &change.added {
+                println!("{}", paint(format!("      + {value}"), Color::Green));
+            }
+        }
+    }
+}
+
+/// Non-empty record groups in display order, unrecognised types last.
+fn groups(info: &TypedDomainInfo) -> impl Iterator<Item = (&str, &[DnsRecord])> {
+    DnsRecordType::ALL
+        .into_iter()
+        .map(|kind| (kind.as_str(), info.records.get(kind)))
+        .chain(std::iter::once((OTHER, info.records.other.as_slice())))
+        .filter(|(_, records)| !records.is_empty())
+}
+
+/// Providers report names relative to the domain; empty means the apex.
+pub(super) fn fqdn(domain: &str, name: &str) -> String {
+    if name.is_empty() {
+        domain.to_string()
+    } else if name == domain || name.ends_with(&format!(".{domain}")) {
+        name.to_string()
+    } else {
+        format!("{name}.{domain}")
+    }
+}
@@ -0,0 +1,343 @@
+//! `i1 domain` - Passive DNS, live resolution and certificate exposure in one report.
+
+use anyhow::Result;
+use colored::{Color, Colorize};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+use super::dns::fqdn;
+use super::Context;
+use crate::cli::args::DomainArgs;
+use crate::output::OutputFormat;
+use i1_providers::{DnsProvider, DomainInfo, SearchProvider};
+
+/// Live lookups in flight at once.
+#[cfg(feature = "recon")]
+const RESOLVE_CONCURRENCY: usize = 8;
+
+pub async fn execute(ctx: Context, args: DomainArgs) -> Result<()> {
+    let provider = ctx.shodan_provider()?;
+    let domain = args.domain.trim_end_matches('.').to_ascii_lowercase();
+
+    let info = provider.domain_info(&domain).await?;
+    let query = format!("ssl.cert.subject.cn:\"{domain}\"");
+    let certificates = match provider.count(&query).await {
+        Ok(count) => Some(count),
+        Err(e) => {
+            tracing::warn!(error = %e, "certificate count unavailable");
+            None
+        }
+    };
+
+    let mut report = DomainReport::new(&info, args.limit_subdomains, certificates);
+    if !args.no_live {
+        resolve_live(&mut report, args.live).await;
+    }
+
+    print_report(&ctx, &report)
+}
+
+/// How a name's live answers compare with passive DNS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    /// Same addresses
+    Match,
+    /// Some addresses in common
+    Partial,
+    /// No addresses in common: possible fast-flux or hijack
+    Diverged,
+    /// Passive DNS has addresses but the name no longer resolves: possible
+    /// dangling record and takeover
+    Dangling,
+    /// Resolves, but passive DNS has no address records for it
+    LiveOnly,
+    /// No addresses either way
+    Unresolved,
+    /// Live resolution was skipped
+    Unchecked,
+}
+
+impl Status {
+    fn classify(passive: &BTreeSet<IpAddr>, live: Option<&BTreeSet<IpAddr>>) -> Self {
+        let Some(live) = live else {
+            return Self::Unchecked;
+        };
+        match (passive.is_empty(), live.is_empty()) {
+            (true, true) => Self::Unresolved,
+            (true, false) => Self::LiveOnly,
+            (false, true) => Self::Dangling,
+            (false, false) if passive == live => Self::Match,
+            (false, false) if passive.is_disjoint(live) => Self::Diverged,
+            (false, false) => Self::Partial,
+        }
+    }
+
+    /// Whether the status is worth investigating.
+    const fn is_flagged(self) -> bool {
+        matches!(self, Self::Diverged | Self::Dangling)
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Match => "match",
+            Self::Partial => "partial",
+            Self::Diverged => "diverged",
+            Self::Dangling => "dangling",
+            Self::LiveOnly => "live_only",
+            Self::Unresolved => "unresolved",
+            Self::Unchecked => "unchecked",
+        }
+    }
+
+    const fn color(self) -> Option<Color> {
+        match self {
+            Self::Diverged | Self::Dangling => Some(Color::Red),
+            Self::Partial => Some(Color::Yellow),
+            Self::Match => Some(Color::Green),
+            Self::LiveOnly | Self::Unresolved | Self::Unchecked => None,
+        }
+    }
+}
+
+/// One name in the report: the apex or a subdomain.
+#[derive(Debug, Serialize)]
+struct HostRow {
+    name: String,
+    /// A/AAAA answers from passive DNS.
+    passive: BTreeSet<IpAddr>,
+    /// Live A/AAAA answers, when resolution ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    live: Option<BTreeSet<IpAddr>>,
+    status: Status,
+}
+
+#[derive(Debug, Serialize)]
+struct DomainReport {
+    domain: String,
+    hosts: Vec<HostRow>,
+    /// Subdomains left out by `--limit-subdomains`.
+    omitted_subdomains: usize,
+    /// Passive DNS record counts by type.
+    record_types: BTreeMap<String, usize>,
+    /// Hosts presenting a certificate for the domain, per Shodan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificates: Option<u64>,
+}
+
+impl DomainReport {
+    fn new(info: &DomainInfo, limit: usize, certificates: Option<u64>) -> Self {
+        let mut passive: BTreeMap<String, BTreeSet<IpAddr>> = BTreeMap::new();
+        let mut record_types: BTreeMap<String, usize> = BTreeMap::new();
+        for record in &info.records {
+            let kind = record.record_type.to_ascii_uppercase();
+            if kind == "A" || kind == "AAAA" {
+                if let Ok(ip) = record.value.parse() {
+                    passive
+                        .entry(fqdn(&info.domain, &record.name))
+                        .or_default()
+                        .insert(ip);
+                }
+            }
+            *record_types.entry(kind).or_default() += 1;
+        }
+
+        let subdomains: BTreeSet<String> = info
+            .subdomains
+            .iter()
+            .map(|sub| fqdn(&info.domain, sub))
+            .filter(|name| *name != info.domain)
+            .collect();
+        let omitted_subdomains = subdomains.len().saturating_sub(limit);
+        let hosts = std::iter::once(info.domain.clone())
+            .chain(subdomains.into_iter().take(limit))
+            .map(|name| {
+                let passive = passive.remove(&name).unwrap_or_default();
+                HostRow {
+                    status: Status::classify(&passive, None),
+                    name,
+                    passive,
+                    live: None,
+                }
+            })
+            .collect();
+
+        Self {
+            domain: info.domain.clone(),
+            hosts,
+            omitted_subdomains,
+            record_types,
+            certificates,
+        }
+    }
+
+    fn flagged(&self) -> usize {
+        self.hosts.iter().filter(|h| h.status.is_flagged()).count()
+    }
+}
+
+/// Resolve every name in the report and classify it against passive DNS.
+#[cfg(feature = "recon")]
+async fn resolve_live(report: &mut DomainReport, _requested: bool) {
+    use futures_util::stream::{self, StreamExt};
+
+    let names: Vec<String> = report.hosts.iter().map(|h| h.name.clone()).collect();
+    let mut answers: BTreeMap<String, BTreeSet<IpAddr>> = stream::iter(names)
+        .map(|name| async move {
+            // A name that doesn't resolve is an answer too: no addresses
+            let ips = tokio::net::lookup_host((name.as_str(), 0))
+                .await
+                .map(|addrs| addrs.map(|addr| addr.ip()).collect())
+                .unwrap_or_default();
+            (name, ips)
+        })
+        .buffer_unordered(RESOLVE_CONCURRENCY)
+        .collect()
+        .await;
+
+    for host in &mut report.hosts {
+        let live = answers.remove(&host.name).unwrap_or_default();
+        host.status = Status::classify(&host.passive, Some(&live));
+        host.live = Some(live);
+    }
+}
+
+/// Live resolution needs the `recon` feature; say so only when asked for
+/// explicitly.
+#[cfg(not(feature = "recon"))]
+#[allow(clippy::unused_async)]
+async fn resolve_live(_report: &mut DomainReport, requested: bool) {
+    if requested {
+        tracing::warn!(
+            "live resolution needs a build with the `recon` feature; showing passive DNS only"
+        );
+    }
+}
+
+fn print_report(ctx: &Context, report: &DomainReport) -> Result<()> {
+    match ctx.output_format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(report)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(report)?);
+        }
+        OutputFormat::Csv => {
+            println!("name,passive,live,status");
+            for host in &report.hosts {
+                println!(
+                    "{},{},{},{}",
+                    host.name,
+                    join(&host.passive, ";"),
+                    host.live
+                        .as_ref()
+                        .map(|live| join(live, ";"))
+                        .unwrap_or_default(),
+                    host.status.as_str()
+                );
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => print_pretty(ctx, report),
+    }
+
+    Ok(())
+}
+
+fn print_pretty(ctx: &Context, report: &DomainReport) {
+    let paint = |text: String, color: Option<Color>| match color {
+        Some(color) if !ctx.no_color => text.color(color).to_string(),
+        _ => text,
+    };
+    let checked = report.hosts.iter().any(|h| h.live.is_some());
+
+    println!("{}", paint(report.domain.clone(), Some(Color::Green)));
+    println!();
+
+    let width = report
+        .hosts
+        .iter()
+        .map(|h| h.name.len())
+        .max()
+        .unwrap_or_default()
+        .max(4);
+    let ip_width = report
+        .hosts
+        .iter()
+        .map(|h| join(&h.passive, ", ").len())
+        .max()
+        .unwrap_or_default()
+        .max(7);
+    if checked {
+        println!(
+            "  {:width$}  {:ip_width$}  {:10}  LIVE",
+            "NAME", "PASSIVE", "STATUS"
+        );
+    } else {
+        println!("  {:width$}  PASSIVE", "NAME");
+    }
+    for host in &report.hosts {
+        let passive = join(&host.passive, ", ");
+        let line = host.live.as_ref().map_or_else(
+            || format!("  {:width$}  {passive}", host.name),
+            |live| {
+                format!(
+                    "  {:width$}  {:ip_width$}  {:10}  {}",
+                    host.name,
+                    passive,
+                    host.status.as_str(),
+                    join(live, ", ")
+                )
+            },
+        );
+        println!("{}", paint(line, host.status.color()));
+    }
+    if report.omitted_subdomains > 0 {
+        println!(
+            "  ... and {} more subdomains (raise --limit-subdomains)",
+            report.omitted_subdomains
+        );
+    }
+
+    println!();
+    let summary: Vec<String> = report
+        .record_types
+        .iter()
+        .map(|(kind, count)| format!("{kind} {count}"))
+        .collect();
+    if summary.is_empty() {
+        println!("Records:       none in passive DNS");
+    } else {
+        println!("Records:       {}", summary.join(", "));
+    }
+    let certificates = report.certificates.map_or_else(
+        || "unavailable".to_string(),
+        |count| format!("~{count} hosts present a cert for this name"),
+    );
+    println!("Certificates:  {certificates}");
+
+    let flagged = report.flagged();
+    if flagged > 0 {
+        println!();
+        println!(
+            "{}",
+            paint(
+                format!(
+                    "{flagged} name(s) diverge from passive DNS: check for dangling records \
+                     (subdomain takeover) or fast-flux"
+                ),
+                Some(Color::Red)
+            )
+        );
+    }
+}
+
+fn join(ips: &BTreeSet<IpAddr>, sep: &str) -> String {
+    ips.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(sep)
+}
@@ -1,157 +1,188 @@
 //! `i1 host` - Look up information about an IP address.
 
 use anyhow::Result;
-use colored::Colorize;
-use tabled::{settings::Style, Table, Tabled};
+use chrono::Utc;
+use serde::Serialize;
 
 use super::Context;
 use crate::cli::args::HostArgs;
+use crate::output::render::{Column, Document, Renderer, Table};
 use crate::output::OutputFormat;
-use i1::HostInfo;
+use i1::stix::BundleBuilder;
+use i1::{HostInfo, PortRiskMap, RiskLevel};
+use i1_core::geo::GeoDatabase;
 
-#[derive(Tabled)]
+/// A row of the open ports table.
+#[derive(Serialize)]
 struct PortRow {
-    #[tabled(rename = "Port")]
     port: u16,
-    #[tabled(rename = "Protocol")]
     transport: String,
-    #[tabled(rename = "Service")]
     product: String,
-    #[tabled(rename = "Version")]
     version: String,
 }
 
 pub async fn execute(ctx: Context, args: HostArgs) -> Result<()> {
     let provider = ctx.host_provider()?;
 
-    let host = provider.lookup_host(&args.ip).await?;
-
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&host)?);
-        }
-        OutputFormat::Yaml => {
-            println!("{}", serde_yaml::to_string(&host)?);
-        }
-        OutputFormat::Csv => {
-            println!("ip,org,asn,country,ports");
-            let ports: Vec<String> = host
-                .ports
-                .iter()
-                .map(std::string::ToString::to_string)
-                .collect();
-            println!(
-                "{},{},{},{},\"{}\"",
-                host.ip_str,
-                host.org.as_deref().unwrap_or(""),
-                host.asn.as_deref().unwrap_or(""),
-                host.location.country_code.as_deref().unwrap_or(""),
-                ports.join(";")
-            );
-        }
-        OutputFormat::Pretty => {
-            print_host_pretty(&host, &ctx);
-        }
+    // Open the databases first so a missing file doesn't cost a lookup
+    let geo = geo_database(&ctx)?;
+    let mut host = provider.lookup_host(&args.ip).await?;
+    if let Some(db) = &geo {
+        host.enrich_geo(db);
     }
 
-    Ok(())
-}
-
-fn print_host_pretty(host: &HostInfo, ctx: &Context) {
-    // Header
-    if ctx.no_color {
-        println!("Host: {}", host.ip_str);
-    } else {
-        println!("{} {}", "Host:".bold(), host.ip_str.cyan().bold());
+    if ctx.output_format == OutputFormat::Stix {
+        let mut bundle = BundleBuilder::new(Utc::now());
+        bundle.add_host(&host);
+        println!("{}", bundle.build().to_json()?);
+        return Ok(());
     }
-    println!();
+    Renderer::from_context(&ctx).print_document(&host_document(&host, &ctx.port_risks)?)
+}
 
-    // Basic info
-    if let Some(org) = &host.org {
-        println!("  {} {}", "Organization:".bold(), org);
-    }
-    if let Some(asn) = &host.asn {
-        println!("  {} {}", "ASN:".bold(), asn);
-    }
-    if let Some(isp) = &host.isp {
-        println!("  {} {}", "ISP:".bold(), isp);
-    }
-    if let Some(os) = &host.os {
-        println!("  {} {}", "OS:".bold(), os);
+/// Open the configured `.mmdb` databases, used to fill in location and
+/// ASN the provider left out.
+fn geo_database(ctx: &Context) -> Result<Option<GeoDatabase>> {
+    if ctx.geo_db.is_empty() {
+        return Ok(None);
     }
+    Ok(Some(GeoDatabase::open_all(&ctx.geo_db)?))
+}
 
-    // Location
-    if let Some(country) = &host.location.country_name {
+/// The host as a document: one CSV row of its summary, and the pretty
+/// view of its details, open ports, risky exposures and vulnerabilities.
+fn host_document(host: &HostInfo, port_risks: &PortRiskMap) -> Result<Document> {
+    let columns = vec![
+        Column::new("ip_str", "IP"),
+        Column::new("org", "Org"),
+        Column::new("asn", "ASN"),
+        Column::new("country_code", "Country"),
+        Column::new("ports", "Ports"),
+    ];
+    let location = host.location.country_name.as_ref().map(|country| {
         let city = host.location.city.as_deref().unwrap_or("");
         let region = host.location.region_code.as_deref().unwrap_or("");
-        let location = [city, region, country.as_str()]
-            .iter()
+        [city, region, country.as_str()]
+            .into_iter()
             .filter(|s| !s.is_empty())
-            .copied()
             .collect::<Vec<_>>()
-            .join(", ");
-        println!("  {} {}", "Location:".bold(), location);
-    }
-
-    // Hostnames
-    if !host.hostnames.is_empty() {
-        println!("  {} {}", "Hostnames:".bold(), host.hostnames.join(", "));
-    }
+            .join(", ")
+    });
+    let mut document = Document::new(host, columns)?.fields(
+        Some(&format!("Host: {}", host.ip_str)),
+        vec![
+            ("Organization:", host.org.clone().unwrap_or_default()),
+            ("ASN:", host.asn.clone().unwrap_or_default()),
+            ("ISP:", host.isp.clone().unwrap_or_default()),
+            ("OS:", host.os.clone().unwrap_or_default()),
+            ("Location:", location.unwrap_or_default()),
+            ("Hostnames:", host.hostnames.join(", ")),
+        ],
+    );
 
-    // Ports table
     if !host.ports.is_empty() {
-        println!();
-        println!("{}", "Open Ports:".bold().underline());
-
-        let mut rows: Vec<PortRow> = Vec::new();
-
-        if host.data.is_empty() {
-            for port in &host.ports {
-                rows.push(PortRow {
-                    port: *port,
-                    transport: "tcp".to_string(),
-                    product: String::new(),
-                    version: String::new(),
-                });
-            }
-        } else {
-            for svc in &host.data {
-                rows.push(PortRow {
-                    port: svc.port,
-                    transport: svc.transport.to_string(),
-                    product: svc.product.clone().unwrap_or_default(),
-                    version: svc.version.clone().unwrap_or_default(),
-                });
-            }
-        }
-
-        let table = Table::new(&rows).with(Style::rounded()).to_string();
-        println!("{table}");
+        document = document.table(Some("Open Ports:"), port_table(host)?).list(
+            "Risky Exposures:",
+            exposures(host, port_risks),
+            None,
+        );
     }
+    document = document.list(
+        "Vulnerabilities:",
+        host.vulns.clone(),
+        Some("None detected"),
+    );
+    if let Some(update) = &host.last_update {
+        document = document.note(format!(
+            "Last updated: {}",
+            update.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    Ok(document)
+}
 
-    // Vulnerabilities
-    if host.vulns.is_empty() {
-        println!();
-        if ctx.no_color {
-            println!("Vulnerabilities: None detected");
-        } else {
-            println!("{} {}", "Vulnerabilities:".bold(), "None detected".green());
-        }
+/// Open ports with their services, or bare TCP ports without banners.
+fn port_table(host: &HostInfo) -> Result<Table> {
+    let rows: Vec<PortRow> = if host.data.is_empty() {
+        host.ports
+            .iter()
+            .map(|port| PortRow {
+                port: *port,
+                transport: "tcp".to_string(),
+                product: String::new(),
+                version: String::new(),
+            })
+            .collect()
     } else {
-        println!();
-        if ctx.no_color {
-            println!("Vulnerabilities:");
-        } else {
-            println!("{}", "Vulnerabilities:".bold().red());
-        }
-        for vuln in &host.vulns {
-            println!("  - {vuln}");
-        }
-    }
+        host.data
+            .iter()
+            .map(|svc| PortRow {
+                port: svc.port,
+                transport: svc.transport.to_string(),
+                product: svc.product.clone().unwrap_or_default(),
+                version: svc.version.clone().unwrap_or_default(),
+            })
+            .collect()
+    };
+    let columns = vec![
+        Column::new("port", "Port"),
+        Column::new("transport", "Protocol"),
+        Column::new("product", "Service"),
+        Column::new("version", "Version"),
+    ];
+    Table::new(columns, rows)
+}
 
-    // Last update
-    if let Some(update) = &host.last_update {
-        println!();
-        println!("{}", format!("Last updated: {update}").dimmed());
+/// Open ports in a risky category (e.g. exposed RDP or Redis).
+fn exposures(host: &HostInfo, map: &PortRiskMap) -> Vec<String> {
+    host.risk_profile(map)
+        .exposures
+        .iter()
+        .map(|exposure| {
+            let level = match exposure.category.level() {
+                RiskLevel::High => "HIGH",
+                RiskLevel::Medium => " MED",
+            };
+            let service = exposure
+                .product
+                .as_deref()
+                .map(|p| format!(" ({p})"))
+                .unwrap_or_default();
+            format!(
+                "{level} {:<5} {}{service}",
+                exposure.port,
+                exposure.category.label()
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render::{all_formats, assert_golden};
+    use serde_json::json;
+
+    #[test]
+    fn golden_host() {
+        let host: HostInfo = serde_json::from_value(json!({
+            "ip_str": "192.0.2.10",
+            "hostnames": ["mail.example.com"],
+            "org": "Example Hosting",
+            "asn": "AS64500",
+            "country_code": "NL",
+            "country_name": "Netherlands",
+            "city": "Amsterdam",
+            "ports": [22, 3389],
+            "vulns": ["CVE-2023-38408"],
+            "last_update": "2024-03-04T05:06:07.000000",
+            "data": [
+                {"port": 22, "transport": "tcp", "product": "OpenSSH", "version": "8.9p1"},
+                {"port": 3389, "transport": "tcp"}
+            ]
+        }))
+        .unwrap();
+        let document = host_document(&host, &PortRiskMap::default()).unwrap();
+        assert_golden("host.txt", &all_formats(&document, None));
     }
 }
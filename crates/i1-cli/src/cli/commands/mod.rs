@@ -1,12 +1,15 @@
 //! Command implementations.
 
+pub mod alert;
 pub mod audit;
 pub mod config;
 pub mod count;
 pub mod defend;
 pub mod dns;
+pub mod domain;
 pub mod host;
 pub mod myip;
+pub mod query;
 pub mod scan;
 pub mod search;
 pub mod threat;
@@ -28,12 +31,21 @@ pub struct Context {
     /// Criminal IP API key
     pub criminalip_key: Option<String>,
 
+    /// MISP instance URL
+    pub misp_url: Option<String>,
+
+    /// MISP auth key
+    pub misp_key: Option<String>,
+
     /// Which provider to use (auto, shodan, censys, criminalip)
     pub provider: String,
 
     /// Output format
     pub output_format: OutputFormat,
 
+    /// Keys to show (`--fields`), for commands using the renderer
+    pub fields: Option<Vec<String>>,
+
     /// Whether to show educational explanations
     pub explain: bool,
 
@@ -42,6 +54,15 @@ pub struct Context {
 
     /// Disable colors
     pub no_color: bool,
+
+    /// Hash algorithm for audit commands
+    pub audit_hash: i1_audit::HashAlgorithm,
+
+    /// Port risk categories for host lookups
+    pub port_risks: i1::PortRiskMap,
+
+    /// Local `.mmdb` databases for geo/ASN enrichment
+    pub geo_db: Vec<String>,
 }
 
 impl Context {
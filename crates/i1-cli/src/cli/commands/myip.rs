@@ -29,6 +29,9 @@ pub async fn execute(ctx: Context) -> Result<()> {
         OutputFormat::Yaml => {
             println!("ip: {ip}");
         }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
         OutputFormat::Pretty => {
             if ctx.no_color {
                 println!("Your IP: {ip}");
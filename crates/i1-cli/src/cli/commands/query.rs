@@ -0,0 +1,273 @@
+//! `i1 query` - Saved search queries.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use tabled::{settings::Style, Table, Tabled};
+
+use super::Context;
+use crate::cli::args::{QueryArgs, QueryCommands, SearchArgs};
+use crate::config::queries::{Library, SavedQuery};
+use crate::output::OutputFormat;
+
+#[derive(Tabled)]
+struct QueryRow {
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Query")]
+    query: String,
+    #[tabled(rename = "Tags")]
+    tags: String,
+    #[tabled(rename = "Last Run")]
+    last_run: String,
+    #[tabled(rename = "Results")]
+    results: String,
+}
+
+pub async fn execute(ctx: Context, args: QueryArgs) -> Result<()> {
+    match args.command {
+        QueryCommands::Save {
+            name,
+            query,
+            facets,
+            tags,
+            force,
+        } => save(&name, query, facets, tags, force),
+        QueryCommands::List { tag } => list(&ctx, tag.as_deref()),
+        QueryCommands::Show { name } => show(&ctx, &name),
+        QueryCommands::Run { name, options } => {
+            let saved = Library::load()?.get(&name)?.clone();
+            if !saved.facets.is_empty() {
+                tracing::info!(
+                    "facets ({}) are saved with the query but not requested by search",
+                    saved.facets.join(", ")
+                );
+            }
+            let args = SearchArgs {
+                query: saved.query,
+                options,
+            };
+            let total = super::search::run(&ctx, &args).await?;
+
+            // Reload, in case the library changed while the search ran
+            let mut library = Library::load()?;
+            library.record_run(&name, Utc::now(), total);
+            library.save()
+        }
+        QueryCommands::Rename { from, to } => {
+            let mut library = Library::load()?;
+            library.rename(&from, &to, Utc::now())?;
+            library.save()?;
+            println!(
+                "{} Renamed {} to {}.",
+                "Success:".green().bold(),
+                from,
+                to.cyan()
+            );
+            Ok(())
+        }
+        QueryCommands::Delete { name } => {
+            let mut library = Library::load()?;
+            library.remove(&name)?;
+            library.save()?;
+            println!("{} Deleted {}.", "Success:".green().bold(), name.cyan());
+            Ok(())
+        }
+        QueryCommands::Export { names, file } => {
+            let export = Library::load()?.subset(&names)?;
+            let content = export.to_toml()?;
+            match file {
+                Some(path) => {
+                    std::fs::write(&path, content)?;
+                    tracing::info!(
+                        "Exported {} queries to {}",
+                        export.queries.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{content}"),
+            }
+            Ok(())
+        }
+        QueryCommands::Import { file, force } => import(&file, force),
+    }
+}
+
+fn save(
+    name: &str,
+    query: String,
+    facets: Vec<String>,
+    tags: Vec<String>,
+    force: bool,
+) -> Result<()> {
+    let mut library = Library::load()?;
+    let saved = SavedQuery {
+        facets,
+        tags,
+        ..SavedQuery::new(query, Utc::now())
+    };
+    let replaced = library.insert(name, saved, force)?;
+    library.save()?;
+
+    let verb = if replaced { "Replaced" } else { "Saved" };
+    println!("{} {verb} {}.", "Success:".green().bold(), name.cyan());
+    println!("Run it with: {} query run {name}", "i1".cyan());
+    Ok(())
+}
+
+fn list(ctx: &Context, tag: Option<&str>) -> Result<()> {
+    let library = Library::load()?;
+    let queries: Vec<(&String, &SavedQuery)> = library
+        .queries
+        .iter()
+        .filter(|(_, query)| tag.map_or(true, |tag| query.has_tag(tag)))
+        .collect();
+
+    match ctx.output_format {
+        OutputFormat::Json => {
+            let queries: std::collections::BTreeMap<_, _> = queries.into_iter().collect();
+            println!("{}", serde_json::to_string_pretty(&queries)?);
+        }
+        OutputFormat::Yaml => {
+            let queries: std::collections::BTreeMap<_, _> = queries.into_iter().collect();
+            println!("{}", serde_yaml::to_string(&queries)?);
+        }
+        OutputFormat::Csv => {
+            println!("name,query,tags,last_run,last_count");
+            for (name, query) in queries {
+                println!(
+                    "{},\"{}\",{},{},{}",
+                    name,
+                    query.query.replace('"', "\"\""),
+                    query.tags.join(";"),
+                    query.last_run.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    query.last_count.map(|c| c.to_string()).unwrap_or_default()
+                );
+            }
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => {
+            if queries.is_empty() {
+                println!("No saved queries.");
+                println!("Save one with: {} query save <name> '<query>'", "i1".cyan());
+                return Ok(());
+            }
+            let now = Utc::now();
+            let rows: Vec<QueryRow> = queries
+                .into_iter()
+                .map(|(name, query)| QueryRow {
+                    name: name.clone(),
+                    query: query.query.chars().take(50).collect(),
+                    tags: query.tags.join(", "),
+                    last_run: query
+                        .last_run
+                        .map_or_else(|| "never".to_string(), |at| age(now, at)),
+                    results: query.last_count.map(|c| c.to_string()).unwrap_or_default(),
+                })
+                .collect();
+            println!("{}", Table::new(&rows).with(Style::rounded()));
+        }
+    }
+    Ok(())
+}
+
+fn show(ctx: &Context, name: &str) -> Result<()> {
+    let library = Library::load()?;
+    let query = library.get(name)?;
+
+    match ctx.output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(query)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(query)?),
+        OutputFormat::Csv => {
+            println!("name,query");
+            println!("{},\"{}\"", name, query.query.replace('"', "\"\""));
+        }
+        OutputFormat::Stix | OutputFormat::Ndjson => {
+            unreachable!("STIX and NDJSON output are rejected before dispatch")
+        }
+        OutputFormat::Pretty => {
+            println!("{} {}", "Name:".bold(), name.cyan());
+            println!("{} {}", "Query:".bold(), query.query);
+            if !query.facets.is_empty() {
+                println!("{} {}", "Facets:".bold(), query.facets.join(", "));
+            }
+            if !query.tags.is_empty() {
+                println!("{} {}", "Tags:".bold(), query.tags.join(", "));
+            }
+            println!(
+                "{} {}",
+                "Created:".bold(),
+                query.created.format("%Y-%m-%d %H:%M UTC")
+            );
+            println!(
+                "{} {}",
+                "Updated:".bold(),
+                query.updated.format("%Y-%m-%d %H:%M UTC")
+            );
+            match (query.last_run, query.last_count) {
+                (Some(at), count) => println!(
+                    "{} {} ({} results)",
+                    "Last run:".bold(),
+                    at.format("%Y-%m-%d %H:%M UTC"),
+                    count.unwrap_or_default()
+                ),
+                (None, _) => println!("{} never", "Last run:".bold()),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn import(file: &std::path::Path, force: bool) -> Result<()> {
+    let incoming = Library::from_toml(&std::fs::read_to_string(file)?)
+        .map_err(|e| anyhow::anyhow!("{}: {e}", file.display()))?;
+    let mut library = Library::load()?;
+    let summary = library.import(incoming, force);
+    library.save()?;
+
+    println!(
+        "{} Imported {} new, replaced {}.",
+        "Success:".green().bold(),
+        summary.added.len(),
+        summary.replaced.len()
+    );
+    if !summary.skipped.is_empty() {
+        println!(
+            "{} Skipped {} (names already taken; use --force to replace them).",
+            "Note:".yellow(),
+            summary.skipped.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// How long ago `then` was, coarsely (e.g. `5m ago`, `3h ago`, `12d ago`).
+fn age(now: DateTime<Utc>, then: DateTime<Utc>) -> String {
+    let elapsed = now - then;
+    if elapsed.num_minutes() < 1 {
+        "just now".to_string()
+    } else if elapsed.num_hours() < 1 {
+        format!("{}m ago", elapsed.num_minutes())
+    } else if elapsed.num_hours() < 48 {
+        format!("{}h ago", elapsed.num_hours())
+    } else {
+        format!("{}d ago", elapsed.num_days())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn ages_are_coarse() {
+        let now = Utc::now();
+        assert_eq!(age(now, now), "just now");
+        assert_eq!(age(now, now - Duration::minutes(5)), "5m ago");
+        assert_eq!(age(now, now - Duration::hours(30)), "30h ago");
+        assert_eq!(age(now, now - Duration::days(12)), "12d ago");
+    }
+}
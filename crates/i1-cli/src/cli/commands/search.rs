@@ -1,122 +1,254 @@
 //! `i1 search` - Search threat intelligence database.
 
 use anyhow::Result;
+use chrono::Utc;
 use colored::Colorize;
-use tabled::{settings::Style, Table, Tabled};
 
 use super::Context;
 use crate::cli::args::SearchArgs;
+use crate::misp;
+use crate::output::group::{self, GroupBy, GroupedResults};
+use crate::output::render::{Column, Document, Renderer};
 use crate::output::OutputFormat;
+use i1::stix::BundleBuilder;
+use i1_core::HostInfo;
+use i1_providers::{SearchProvider, SearchResults};
 
-#[derive(Tabled)]
-struct SearchRow {
-    #[tabled(rename = "IP")]
-    ip: String,
-    #[tabled(rename = "Ports")]
-    ports: String,
-    #[tabled(rename = "Org")]
-    org: String,
-    #[tabled(rename = "Country")]
-    country: String,
+pub async fn execute(ctx: Context, args: SearchArgs) -> Result<()> {
+    run(&ctx, &args).await.map(|_| ())
 }
 
-pub async fn execute(ctx: Context, args: SearchArgs) -> Result<()> {
+/// Run a search and print (or push) the results, returning the total
+/// number of matches the provider reported.
+pub async fn run(ctx: &Context, args: &SearchArgs) -> Result<u64> {
     let provider = ctx.search_provider()?;
 
-    let results = provider.search(&args.query, Some(args.page)).await?;
+    let mut results = fetch_page(provider.as_ref(), args, args.options.page).await?;
+    if args.options.all {
+        fetch_remaining(provider.as_ref(), args, &mut results).await?;
+    }
+    if args.options.unique_ips {
+        group::unique_ips(&mut results);
+    }
+    if let Some(limit) = args.options.limit {
+        results.results.truncate(limit);
+    }
 
-    match ctx.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&results)?);
+    if args.options.to_misp {
+        push_to_misp(ctx, &args.query, &results.results).await?;
+    } else {
+        print_results(ctx, args, &results)?;
+    }
+    Ok(results.total)
+}
+
+fn print_results(ctx: &Context, args: &SearchArgs, results: &SearchResults) -> Result<()> {
+    if ctx.output_format == OutputFormat::Stix {
+        let mut bundle = BundleBuilder::new(Utc::now());
+        for host in &results.results {
+            bundle.add_host(host);
         }
-        OutputFormat::Yaml => {
-            println!("{}", serde_yaml::to_string(&results)?);
+        println!("{}", bundle.build().to_json()?);
+        return Ok(());
+    }
+
+    let document = if args.options.group_by == Some(GroupBy::Ip) {
+        grouped_document(&args.query, &group::group_by_ip(results))?
+    } else {
+        results_document(&args.query, args.options.unique_ips, results)?
+    };
+    Renderer::from_context(ctx).print_document(&document)?;
+
+    if ctx.output_format == OutputFormat::Pretty
+        && args.options.group_by.is_none()
+        && args.options.page == 1
+        && results.total > 100
+    {
+        tracing::info!(
+            "Tip: Use --page 2 to see more results (page 1 of {})",
+            (results.total / 100) + 1
+        );
+    }
+    Ok(())
+}
+
+/// Pretty output shows at most this many rows.
+const PRETTY_ROWS: usize = 25;
+
+/// The results as a document with one row per match.
+fn results_document(query: &str, unique_ips: bool, results: &SearchResults) -> Result<Document> {
+    let columns = vec![
+        Column::new("ip_str", "IP"),
+        Column::new("ports", "Ports"),
+        Column::new("org", "Org"),
+        Column::new("country_code", "Country"),
+    ];
+    let document = Document::new(results, columns)?.rows_at("results");
+    let table = document
+        .rows_table()
+        .limit(PRETTY_ROWS, "results")
+        .empty("No results found.");
+    let shown = if unique_ips {
+        results.results.len().to_string()
+    } else {
+        String::new()
+    };
+    Ok(document
+        .fields(
+            None,
+            vec![
+                ("Total Results:", results.total.to_string()),
+                ("Unique IPs shown:", shown),
+                ("Query:", query.to_string()),
+            ],
+        )
+        .table(Some("Results:"), table))
+}
+
+/// Grouped results as a document with one row per host.
+fn grouped_document(query: &str, grouped: &GroupedResults) -> Result<Document> {
+    let columns = vec![
+        Column::new("ip", "IP"),
+        Column::new("ports", "Ports"),
+        Column::new("org", "Org"),
+        Column::new("country", "Country"),
+        Column::new("last_seen", "Last Seen"),
+        Column::new("matches", "Matches"),
+    ];
+    let document = Document::new(grouped, columns)?.rows_at("hosts");
+    let table = document
+        .rows_table()
+        .limit(PRETTY_ROWS, "hosts")
+        .empty("No results found.");
+    let matches = format!(
+        "{} total, {} fetched",
+        grouped.total_matches, grouped.fetched_matches
+    );
+    Ok(document
+        .fields(
+            None,
+            vec![
+                ("Matches:", matches),
+                ("Unique Hosts:", grouped.unique_hosts.to_string()),
+                ("Query:", query.to_string()),
+            ],
+        )
+        .table(None, table))
+}
+
+/// One page of results, minified when `--minify` is set.
+async fn fetch_page(
+    provider: &(dyn SearchProvider + Send + Sync),
+    args: &SearchArgs,
+    page: u32,
+) -> Result<SearchResults> {
+    if args.options.minify {
+        Ok(provider.search_min(&args.query, Some(page)).await?.into())
+    } else {
+        Ok(provider.search(&args.query, Some(page)).await?)
+    }
+}
+
+/// Append every page after `--page` to `results`, up to `--limit`.
+async fn fetch_remaining(
+    provider: &(dyn SearchProvider + Send + Sync),
+    args: &SearchArgs,
+    results: &mut SearchResults,
+) -> Result<()> {
+    let mut page = args.options.page;
+    let limit = args.options.limit.unwrap_or(usize::MAX);
+    while !results.results.is_empty()
+        && (results.results.len() as u64) < results.total
+        && results.results.len() < limit
+    {
+        page += 1;
+        let next = fetch_page(provider, args, page).await?;
+        if next.results.is_empty() {
+            break;
         }
-        OutputFormat::Csv => {
-            println!("ip,ports,org,country");
-            for host in &results.results {
-                let ports: Vec<String> = host
-                    .ports
-                    .iter()
-                    .map(std::string::ToString::to_string)
-                    .collect();
-                println!(
-                    "{},\"{}\",{},{}",
-                    host.ip_str,
-                    ports.join(";"),
-                    host.org.as_deref().unwrap_or(""),
-                    host.location.country_code.as_deref().unwrap_or("")
-                );
-            }
+        results.results.extend(next.results);
+    }
+    Ok(())
+}
+
+async fn push_to_misp(ctx: &Context, query: &str, hosts: &[HostInfo]) -> Result<()> {
+    let url = ctx.misp_url.as_deref().ok_or_else(|| {
+        anyhow::anyhow!("MISP URL not configured. Run `i1 config set misp-url <url>`.")
+    })?;
+    let key = ctx.misp_key.as_deref().ok_or_else(|| {
+        anyhow::anyhow!("MISP auth key not configured. Run `i1 config set misp-key <key>`.")
+    })?;
+    if hosts.is_empty() {
+        anyhow::bail!("No results to push to MISP");
+    }
+
+    let event = misp::Event::from_findings(format!("i1 search: {query}"), hosts);
+    let created = misp::push(url, key, &event).await?;
+
+    match ctx.output_format {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "id": created.id,
+                    "uuid": created.uuid,
+                    "hosts": hosts.len(),
+                    "attributes": event.attributes.len(),
+                })
+            );
         }
-        OutputFormat::Pretty => {
-            if ctx.no_color {
-                println!("Total Results: {}", results.total);
-            } else {
-                println!(
-                    "{} {}",
-                    "Total Results:".bold(),
-                    results.total.to_string().cyan()
-                );
-            }
-            println!("{} {}", "Query:".bold(), args.query.dimmed());
-            println!();
-
-            if results.results.is_empty() {
-                println!("No results found.");
-            } else {
-                println!("{}", "Results:".bold().underline());
-
-                let rows: Vec<SearchRow> = results
-                    .results
-                    .iter()
-                    .take(25)
-                    .map(|host| {
-                        let ports: Vec<String> = host
-                            .ports
-                            .iter()
-                            .map(std::string::ToString::to_string)
-                            .collect();
-                        SearchRow {
-                            ip: host.ip_str.clone(),
-                            ports: ports.join(", "),
-                            org: host
-                                .org
-                                .clone()
-                                .unwrap_or_default()
-                                .chars()
-                                .take(30)
-                                .collect(),
-                            country: host.location.country_code.clone().unwrap_or_default(),
-                        }
-                    })
-                    .collect();
-
-                let table = Table::new(&rows).with(Style::rounded()).to_string();
-                println!("{table}");
-
-                if results.results.len() > 25 {
-                    println!();
-                    println!(
-                        "{}",
-                        format!("... and {} more results", results.results.len() - 25).dimmed()
-                    );
-                }
-            }
-
-            println!();
-            if args.page == 1 && results.total > 100 {
-                println!(
-                    "{}",
-                    format!(
-                        "Tip: Use --page 2 to see more results (page 1 of {})",
-                        (results.total / 100) + 1
-                    )
-                    .dimmed()
-                );
-            }
+        _ => {
+            println!(
+                "{} Created MISP event {} ({} attributes from {} hosts)",
+                "Success:".green().bold(),
+                created.id.cyan(),
+                event.attributes.len(),
+                hosts.len()
+            );
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::render::{all_formats, assert_golden};
+    use serde_json::json;
+
+    fn results() -> SearchResults {
+        serde_json::from_value(json!({
+            "provider": "shodan",
+            "total": 3,
+            "page": 1,
+            "results": [
+                {"ip_str": "192.0.2.1", "ports": [443], "org": "Example, Inc.",
+                 "country_code": "AU",
+                 "data": [{"port": 443, "timestamp": "2024-01-02T00:00:00.000000"}]},
+                {"ip_str": "198.51.100.7", "ports": [22]},
+                {"ip_str": "192.0.2.1", "ports": [8443], "country_code": "AU"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn golden_results() {
+        let document = results_document("port:443", false, &results()).unwrap();
+        assert_golden("search.txt", &all_formats(&document, None));
+    }
+
+    #[test]
+    fn golden_grouped() {
+        let document = grouped_document("port:443", &group::group_by_ip(&results())).unwrap();
+        assert_golden("search_grouped.txt", &all_formats(&document, None));
+    }
+
+    #[test]
+    fn fields_pick_nested_keys() {
+        let document = results_document("port:443", false, &results()).unwrap();
+        let out = all_formats(&document, Some(&["ip_str", "data.port"]));
+        assert_golden("search_fields.txt", &out);
+    }
+}
@@ -252,7 +252,7 @@ async fn do_ban(ip: &str, args: &ThreatArgs, host_info: &Option<i1_core::HostInf
         }
     }
 
-    let mut state = State::load()?;
+    let (mut state, lock) = State::load_for_update()?;
 
     if args.execute {
         // Just show the command
@@ -309,7 +309,7 @@ async fn do_ban(ip: &str, args: &ThreatArgs, host_info: &Option<i1_core::HostInf
         }
     }
 
-    state.save()?;
+    state.save(&lock)?;
 
     println!();
     println!(
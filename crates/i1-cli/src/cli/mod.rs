@@ -4,10 +4,13 @@ pub mod args;
 pub mod commands;
 
 use anyhow::Result;
-use args::{Cli, Commands};
+use args::{
+    AlertArgs, AlertCommands, Cli, Commands, DefendArgs, DefendCommands, QueryArgs, QueryCommands,
+};
 use clap::Parser;
 
 use crate::config::Config;
+use crate::logging;
 use crate::output::OutputFormat;
 
 /// Run the CLI application.
@@ -17,8 +20,51 @@ pub async fn run() -> Result<()> {
     // Load configuration
     let config = Config::load()?;
 
+    logging::init(
+        cli.log_format.or(config.log_format).unwrap_or_default(),
+        cli.log_level
+            .as_deref()
+            .unwrap_or_else(|| logging::directives(cli.verbose, cli.quiet)),
+    )?;
+
     // Determine output format
     let output_format = cli.output.unwrap_or(OutputFormat::Pretty);
+    if output_format == OutputFormat::Stix
+        && !matches!(
+            cli.command,
+            Some(
+                Commands::Host(_)
+                    | Commands::Search(_)
+                    | Commands::Query(QueryArgs {
+                        command: QueryCommands::Run { .. }
+                    })
+            )
+        )
+    {
+        anyhow::bail!("STIX output is only available for `host`, `search` and `query run`");
+    }
+    let rendered = matches!(
+        cli.command,
+        Some(
+            Commands::Host(_)
+                | Commands::Search(_)
+                | Commands::Query(QueryArgs {
+                    command: QueryCommands::Run { .. }
+                })
+                | Commands::Defend(DefendArgs {
+                    command: DefendCommands::Status { quick: false }
+                })
+                | Commands::Alert(AlertArgs {
+                    command: AlertCommands::List { .. }
+                })
+        )
+    );
+    if (output_format == OutputFormat::Ndjson || cli.fields.is_some()) && !rendered {
+        anyhow::bail!(
+            "NDJSON output and --fields are only available for `host`, `search`, \
+             `query run`, `defend status` and `alert list`"
+        );
+    }
 
     // Get API keys from CLI, env, or config
     let shodan_key = cli
@@ -39,11 +85,24 @@ pub async fn run() -> Result<()> {
         criminalip_key: std::env::var("I1_CRIMINALIP_KEY")
             .ok()
             .or_else(|| config.criminalip_key.clone()),
+        misp_url: std::env::var("I1_MISP_URL")
+            .ok()
+            .or_else(|| config.misp_url.clone()),
+        misp_key: std::env::var("I1_MISP_KEY")
+            .ok()
+            .or_else(|| config.misp_key.clone()),
         provider: cli.provider,
         output_format,
+        fields: cli.fields,
         explain: cli.explain,
-        verbose: cli.verbose,
+        verbose: cli.verbose > 0,
         no_color: cli.no_color,
+        audit_hash: config.audit_hash.unwrap_or_default(),
+        port_risks: config.port_risk_map(),
+        geo_db: std::env::var("I1_GEO_DB").map_or_else(
+            |_| config.geo_db.clone(),
+            |paths| Config::split_paths(&paths),
+        ),
     };
 
     // Dispatch to appropriate command, or run interactive scan if none given
@@ -51,12 +110,15 @@ pub async fn run() -> Result<()> {
         Some(Commands::Host(args)) => commands::host::execute(ctx, args).await,
         Some(Commands::Search(args)) => commands::search::execute(ctx, args).await,
         Some(Commands::Count(args)) => commands::count::execute(ctx, args).await,
+        Some(Commands::Query(args)) => commands::query::execute(ctx, args).await,
         Some(Commands::Dns(args)) => commands::dns::execute(ctx, args).await,
+        Some(Commands::Domain(args)) => commands::domain::execute(ctx, args).await,
         Some(Commands::Myip) => commands::myip::execute(ctx).await,
         Some(Commands::Defend(args)) => commands::defend::execute(ctx, args).await,
         Some(Commands::Config(args)) => commands::config::execute(ctx, args).await,
         Some(Commands::Threat(args)) => commands::threat::execute(&ctx, &args).await,
         Some(Commands::Audit(args)) => commands::audit::execute(ctx, args).await,
+        Some(Commands::Alert(args)) => commands::alert::execute(ctx, args).await,
         None => commands::scan::execute(ctx).await,
     }
 }
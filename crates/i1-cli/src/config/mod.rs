@@ -1,10 +1,15 @@
 //! Configuration management.
 
+pub mod queries;
+
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::logging::LogFormat;
 use crate::output::OutputFormat;
 
 /// CLI configuration.
@@ -14,6 +19,11 @@ pub struct Config {
     #[serde(alias = "api_key")]
     pub shodan_key: Option<String>,
 
+    /// Shodan key replaced by `config rotate-key`, kept for
+    /// [`PREVIOUS_KEY_DAYS`] days to help debug the switch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_api_key: Option<PreviousKey>,
+
     /// Censys API ID.
     pub censys_id: Option<String>,
 
@@ -23,9 +33,18 @@ pub struct Config {
     /// Criminal IP API key.
     pub criminalip_key: Option<String>,
 
+    /// MISP instance URL.
+    pub misp_url: Option<String>,
+
+    /// MISP auth key.
+    pub misp_key: Option<String>,
+
     /// Default output format.
     pub output_format: Option<OutputFormat>,
 
+    /// Format of notes and warnings on stderr (text or json).
+    pub log_format: Option<LogFormat>,
+
     /// Show helpful tips after commands.
     #[serde(default = "default_true")]
     pub show_tips: bool,
@@ -33,12 +52,57 @@ pub struct Config {
     /// Always show explanations (as if --explain was passed).
     #[serde(default)]
     pub explain_by_default: bool,
+
+    /// Hash algorithm for `i1 audit` (sha256 or blake3).
+    pub audit_hash: Option<i1_audit::HashAlgorithm>,
+
+    /// Port risk categories added to or replacing the defaults, keyed by
+    /// port (e.g. `2222 = "management"`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub port_risks: BTreeMap<String, i1::PortCategory>,
+
+    /// Local `.mmdb` databases (e.g. `GeoLite2` City and ASN) used to fill
+    /// in location and ASN data providers leave out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub geo_db: Vec<String>,
 }
 
 const fn default_true() -> bool {
     true
 }
 
+/// Days a rotated-out Shodan key stays recorded.
+pub const PREVIOUS_KEY_DAYS: i64 = 7;
+
+/// A Shodan key that was rotated out, and when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousKey {
+    /// The old key.
+    pub key: String,
+
+    /// When it was replaced.
+    pub rotated_at: DateTime<Utc>,
+}
+
+impl PreviousKey {
+    /// When the record is dropped.
+    #[must_use]
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.rotated_at + Duration::days(PREVIOUS_KEY_DAYS)
+    }
+}
+
+/// Mask a secret for display, keeping only its last four characters.
+#[must_use]
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 4 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{tail}")
+}
+
 impl Config {
     /// Get the config file path.
     pub fn path() -> Result<PathBuf> {
@@ -57,11 +121,65 @@ impl Config {
         }
 
         let content = std::fs::read_to_string(&path)?;
-        let config: Self = toml::from_str(&content)?;
+        let mut config: Self = toml::from_str(&content)?;
+        // Drop the expired key from disk too, not just from this run
+        if config.expire_previous_key(Utc::now()) {
+            if let Err(e) = config.save() {
+                tracing::warn!("Could not remove the expired previous_api_key: {e:#}");
+            }
+        }
 
         Ok(config)
     }
 
+    /// Replace the Shodan key, recording the old one as `previous_api_key`.
+    pub fn rotate_shodan_key(&mut self, key: String, now: DateTime<Utc>) {
+        if let Some(old) = self.shodan_key.replace(key) {
+            self.previous_api_key = Some(PreviousKey {
+                key: old,
+                rotated_at: now,
+            });
+        }
+    }
+
+    /// Forget the previous Shodan key once it is [`PREVIOUS_KEY_DAYS`] old.
+    ///
+    /// Returns whether a key was dropped, i.e. whether the config needs saving.
+    pub fn expire_previous_key(&mut self, now: DateTime<Utc>) -> bool {
+        let expired = self
+            .previous_api_key
+            .as_ref()
+            .is_some_and(|previous| previous.expires_at() <= now);
+        if expired {
+            self.previous_api_key = None;
+        }
+        expired
+    }
+
+    /// The default port risk map with `port_risks` applied.
+    ///
+    /// Keys that aren't port numbers are ignored.
+    #[must_use]
+    pub fn port_risk_map(&self) -> i1::PortRiskMap {
+        self.port_risks
+            .iter()
+            .filter_map(|(port, category)| Some((port.parse().ok()?, *category)))
+            .fold(i1::PortRiskMap::default(), |map, (port, category)| {
+                map.with(port, category)
+            })
+    }
+
+    /// Split a comma-separated list of paths, dropping empty entries.
+    #[must_use]
+    pub fn split_paths(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
     /// Save configuration to file.
     pub fn save(&self) -> Result<()> {
         let path = Self::path()?;
@@ -77,3 +195,35 @@ impl Config {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_show_only_the_last_four_characters() {
+        assert_eq!(mask_secret("abcdefghijklmnop1234"), "****1234");
+        assert_eq!(mask_secret("1234"), "****");
+        assert_eq!(mask_secret(""), "****");
+    }
+
+    #[test]
+    fn rotated_key_is_kept_for_a_week() {
+        let start = Utc::now();
+        let mut config = Config {
+            shodan_key: Some("old-key".into()),
+            ..Config::default()
+        };
+        config.rotate_shodan_key("new-key".into(), start);
+        assert_eq!(config.shodan_key.as_deref(), Some("new-key"));
+
+        assert!(!config.expire_previous_key(start + Duration::days(PREVIOUS_KEY_DAYS - 1)));
+        let previous = config.previous_api_key.as_ref().unwrap();
+        assert_eq!(previous.key, "old-key");
+
+        let toml = toml::to_string_pretty(&config).unwrap();
+        let mut reloaded: Config = toml::from_str(&toml).unwrap();
+        assert!(reloaded.expire_previous_key(start + Duration::days(PREVIOUS_KEY_DAYS)));
+        assert!(reloaded.previous_api_key.is_none());
+    }
+}
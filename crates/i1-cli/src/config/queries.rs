@@ -0,0 +1,397 @@
+//! Saved search queries.
+//!
+//! A library of named queries, kept in `queries.toml` next to the config
+//! file. `i1 query run` replays one through `search` and records when it
+//! last ran and how many results it matched, so `i1 query list` shows how
+//! fresh each one is. `export` and `import` use the same TOML layout, so
+//! a library can be shared as a plain file.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use rustyline::completion::{Completer, Pair};
+use rustyline::{Helper, Highlighter, Hinter, Validator};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use super::Config;
+
+/// A named search query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    /// Search query string, as passed to `i1 search`.
+    pub query: String,
+
+    /// Facets to request alongside the results.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub facets: Vec<String>,
+
+    /// Free-form labels for filtering `query list`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+
+    /// When `query run` last completed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<DateTime<Utc>>,
+
+    /// Total matches reported by that run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_count: Option<u64>,
+}
+
+impl SavedQuery {
+    /// A query created at `now` that has never run.
+    pub fn new(query: impl Into<String>, now: DateTime<Utc>) -> Self {
+        Self {
+            query: query.into(),
+            facets: Vec::new(),
+            tags: Vec::new(),
+            created: now,
+            updated: now,
+            last_run: None,
+            last_count: None,
+        }
+    }
+
+    /// Whether the query carries `tag` (case-insensitive).
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// Saved queries, keyed by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Library {
+    #[serde(default)]
+    pub queries: BTreeMap<String, SavedQuery>,
+}
+
+/// What [`Library::import`] did with each incoming query.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub replaced: Vec<String>,
+    /// Names already in the library, left alone without `overwrite`.
+    pub skipped: Vec<String>,
+}
+
+impl Library {
+    /// Get the library file path, beside the config file.
+    pub fn path() -> Result<PathBuf> {
+        Ok(Config::path()?.with_file_name("queries.toml"))
+    }
+
+    /// Load the library, or an empty one if there is no file yet.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::from_toml(&std::fs::read_to_string(&path)?)
+    }
+
+    /// Save the library to file.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+
+        // Ensure parent directory exists
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&path, self.to_toml()?)?;
+
+        Ok(())
+    }
+
+    /// Parse a library (or an export) from TOML, checking every name.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let library: Self = toml::from_str(content)?;
+        for name in library.queries.keys() {
+            check_name(name)?;
+        }
+        Ok(library)
+    }
+
+    /// Render the library as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// The query called `name`, or an error naming the saved ones.
+    pub fn get(&self, name: &str) -> Result<&SavedQuery> {
+        self.queries.get(name).ok_or_else(|| self.not_found(name))
+    }
+
+    /// Store `query` as `name`. Replacing an existing query needs
+    /// `overwrite`, and keeps its creation time and run history.
+    ///
+    /// Returns whether a query was replaced.
+    pub fn insert(&mut self, name: &str, mut query: SavedQuery, overwrite: bool) -> Result<bool> {
+        check_name(name)?;
+        let Some(existing) = self.queries.get(name) else {
+            self.queries.insert(name.to_string(), query);
+            return Ok(false);
+        };
+        if !overwrite {
+            bail!("A saved query named `{name}` already exists (use --force to replace it)");
+        }
+        query.created = existing.created;
+        query.last_run = query.last_run.or(existing.last_run);
+        query.last_count = query.last_count.or(existing.last_count);
+        self.queries.insert(name.to_string(), query);
+        Ok(true)
+    }
+
+    /// Rename a query; the new name must be free.
+    pub fn rename(&mut self, from: &str, to: &str, now: DateTime<Utc>) -> Result<()> {
+        check_name(to)?;
+        if from != to && self.queries.contains_key(to) {
+            bail!("A saved query named `{to}` already exists");
+        }
+        let mut query = self
+            .queries
+            .remove(from)
+            .ok_or_else(|| self.not_found(from))?;
+        query.updated = now;
+        self.queries.insert(to.to_string(), query);
+        Ok(())
+    }
+
+    /// Delete a query, returning it.
+    pub fn remove(&mut self, name: &str) -> Result<SavedQuery> {
+        self.queries
+            .remove(name)
+            .ok_or_else(|| self.not_found(name))
+    }
+
+    /// Record a completed run of `name` that matched `count` results.
+    pub fn record_run(&mut self, name: &str, now: DateTime<Utc>, count: u64) {
+        if let Some(query) = self.queries.get_mut(name) {
+            query.last_run = Some(now);
+            query.last_count = Some(count);
+        }
+    }
+
+    /// A library holding only `names`, or everything if `names` is empty.
+    pub fn subset(&self, names: &[String]) -> Result<Self> {
+        if names.is_empty() {
+            return Ok(self.clone());
+        }
+        let queries = names
+            .iter()
+            .map(|name| Ok((name.clone(), self.get(name)?.clone())))
+            .collect::<Result<_>>()?;
+        Ok(Self { queries })
+    }
+
+    /// Merge `other` into this library. Queries whose names are taken are
+    /// skipped, or replaced with `overwrite`.
+    pub fn import(&mut self, other: Self, overwrite: bool) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for (name, query) in other.queries {
+            match self.insert(&name, query, overwrite) {
+                Ok(false) => summary.added.push(name),
+                Ok(true) => summary.replaced.push(name),
+                Err(_) => summary.skipped.push(name),
+            }
+        }
+        summary
+    }
+
+    /// Saved names starting with `prefix`, in order.
+    pub fn names_starting_with<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.queries
+            .keys()
+            .map(String::as_str)
+            .filter(move |name| name.starts_with(prefix))
+    }
+
+    fn not_found(&self, name: &str) -> anyhow::Error {
+        if self.queries.is_empty() {
+            anyhow::anyhow!(
+                "No saved query named `{name}`. Save one with: i1 query save {name} '<query>'"
+            )
+        } else {
+            let names: Vec<&str> = self.queries.keys().map(String::as_str).collect();
+            anyhow::anyhow!(
+                "No saved query named `{name}`. Saved queries: {}",
+                names.join(", ")
+            )
+        }
+    }
+}
+
+/// Names are used as shell words and TOML keys, so keep them simple.
+fn check_name(name: &str) -> Result<()> {
+    let valid = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if name.is_empty() || !valid || name.starts_with('-') {
+        bail!(
+            "Invalid query name `{name}`: use letters, digits, `-`, `_` and `.`, \
+             not starting with `-`"
+        );
+    }
+    Ok(())
+}
+
+/// Tab completion of saved query names for the interactive shell.
+///
+/// Completes the word under the cursor after `query run`, `query show`,
+/// `query rename`, `query delete` or `query export`.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+pub struct QueryNameCompleter {
+    library: Library,
+}
+
+impl QueryNameCompleter {
+    pub const fn new(library: Library) -> Self {
+        Self { library }
+    }
+}
+
+impl Completer for QueryNameCompleter {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let mut words = before[..start].split_whitespace();
+
+        let takes_name = words.next() == Some("query")
+            && matches!(
+                words.next(),
+                Some("run" | "show" | "rename" | "delete" | "rm" | "export")
+            );
+        if !takes_name {
+            return Ok((start, Vec::new()));
+        }
+        let candidates = self
+            .library
+            .names_starting_with(&before[start..])
+            .map(|name| Pair {
+                display: name.to_string(),
+                replacement: name.to_string(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    fn library() -> Library {
+        let mut library = Library::default();
+        let mut rdp = SavedQuery::new("port:3389 country:DE org:\"Example AG\"", at(1));
+        rdp.tags = vec!["recon".into()];
+        library.insert("recon-rdp", rdp, false).unwrap();
+        library
+            .insert("recon-ssh", SavedQuery::new("port:22", at(1)), false)
+            .unwrap();
+        library
+            .insert("vnc", SavedQuery::new("port:5900", at(1)), false)
+            .unwrap();
+        library
+    }
+
+    #[test]
+    fn collisions_need_overwrite() {
+        let mut library = library();
+        library.record_run("recon-rdp", at(2), 42);
+
+        let error = library
+            .insert("recon-rdp", SavedQuery::new("port:3389", at(3)), false)
+            .unwrap_err();
+        assert!(error.to_string().contains("--force"));
+
+        assert!(library
+            .insert("recon-rdp", SavedQuery::new("port:3389", at(3)), true)
+            .unwrap());
+        let rdp = library.get("recon-rdp").unwrap();
+        assert_eq!(rdp.query, "port:3389");
+        assert_eq!((rdp.created, rdp.updated), (at(1), at(3)));
+        assert_eq!((rdp.last_run, rdp.last_count), (Some(at(2)), Some(42)));
+
+        assert!(library.rename("vnc", "recon-ssh", at(4)).is_err());
+        library.rename("vnc", "recon-vnc", at(4)).unwrap();
+        assert_eq!(library.get("recon-vnc").unwrap().updated, at(4));
+        assert!(library
+            .get("vnc")
+            .unwrap_err()
+            .to_string()
+            .contains("recon-vnc"));
+
+        library.remove("recon-vnc").unwrap();
+        assert!(library.remove("recon-vnc").is_err());
+        assert!(library
+            .insert("bad name", SavedQuery::new("x", at(1)), false)
+            .is_err());
+    }
+
+    #[test]
+    fn export_round_trips_and_imports() {
+        let library = library();
+        let export = library.subset(&["recon-rdp".into()]).unwrap();
+        let parsed = Library::from_toml(&export.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, export);
+        assert!(parsed.queries["recon-rdp"].has_tag("RECON"));
+
+        let mut theirs = Library::default();
+        theirs
+            .insert("recon-rdp", SavedQuery::new("port:3389", at(5)), false)
+            .unwrap();
+        theirs
+            .insert("web", SavedQuery::new("port:443", at(5)), false)
+            .unwrap();
+
+        let mut mine = library;
+        let summary = mine.import(theirs.clone(), false);
+        assert_eq!(summary.added, ["web"]);
+        assert_eq!(summary.skipped, ["recon-rdp"]);
+        assert_ne!(mine.get("recon-rdp").unwrap().query, "port:3389");
+
+        let summary = mine.import(theirs, true);
+        assert_eq!(summary.replaced, ["recon-rdp", "web"]);
+        assert_eq!(mine.get("recon-rdp").unwrap().query, "port:3389");
+
+        assert!(Library::from_toml("[queries.\"a b\"]\nquery = \"x\"\ncreated = 2026-03-01T00:00:00Z\nupdated = 2026-03-01T00:00:00Z\n").is_err());
+    }
+
+    #[test]
+    fn completes_saved_names() {
+        let completer = QueryNameCompleter::new(library());
+        let history = rustyline::history::DefaultHistory::new();
+        let ctx = rustyline::Context::new(&history);
+        let complete = |line: &str| {
+            let (start, pairs) = completer.complete(line, line.len(), &ctx).unwrap();
+            let names: Vec<String> = pairs.into_iter().map(|p| p.replacement).collect();
+            (start, names)
+        };
+
+        assert_eq!(
+            complete("query run rec"),
+            (10, vec!["recon-rdp".into(), "recon-ssh".into()])
+        );
+        assert_eq!(complete("query show ").1.len(), 3);
+        assert_eq!(complete("query save rec").1, Vec::<String>::new());
+        assert_eq!(complete("search rec").1, Vec::<String>::new());
+    }
+}
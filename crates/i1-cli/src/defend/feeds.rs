@@ -0,0 +1,525 @@
+//! Blocklist feed parsing for `defend import`.
+//!
+//! Each supported feed is reduced to a list of normalized networks: host
+//! bits are cleared, single hosts are written as bare addresses, and
+//! `start-end` ranges are split into the fewest CIDR blocks that cover
+//! them. [`merge`] then folds them into the defense state.
+
+use clap::ValueEnum;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::State;
+
+/// Supported feed formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FeedFormat {
+    /// One address, CIDR or range per line; `#` and `;` start comments
+    #[default]
+    #[value(alias = "plain")]
+    Cidr,
+    /// Firehol `.netset`/`.ipset` files
+    Firehol,
+    /// Spamhaus DROP/EDROP, classic text or the newer JSON lines
+    #[value(alias = "drop")]
+    Spamhaus,
+    /// Abuseipdb blacklist CSV (an `ipAddress` or `ip` column, else the first)
+    Abuseipdb,
+}
+
+/// A normalized address block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    /// A block of `prefix` bits at `addr`, with host bits cleared.
+    #[must_use]
+    pub fn new(addr: IpAddr, prefix: u8) -> Self {
+        let prefix = prefix.min(width(addr));
+        Self {
+            addr: mask(addr, prefix),
+            prefix,
+        }
+    }
+
+    /// Parse `addr` or `addr/prefix`.
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let Some((addr, prefix)) = s.split_once('/') else {
+            let addr: IpAddr = s.parse().ok()?;
+            return Some(Self::new(addr, width(addr)));
+        };
+        let addr: IpAddr = addr.parse().ok()?;
+        let prefix: u8 = prefix.parse().ok()?;
+        (prefix <= width(addr)).then(|| Self::new(addr, prefix))
+    }
+
+    /// Network address.
+    #[must_use]
+    pub const fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// Prefix length in bits.
+    #[must_use]
+    pub const fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Whether `other` lies entirely within this block.
+    #[must_use]
+    pub fn contains(&self, other: &Self) -> bool {
+        self.addr.is_ipv4() == other.addr.is_ipv4()
+            && self.prefix <= other.prefix
+            && mask(other.addr, self.prefix) == self.addr
+    }
+
+    /// Whether the two blocks share any address.
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other) || other.contains(self)
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix == width(self.addr) {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+const fn width(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(a) => {
+            let bits = u32::from(a) & u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(bits))
+        }
+        IpAddr::V6(a) => {
+            let bits = u128::from(a) & u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(bits))
+        }
+    }
+}
+
+/// The fewest CIDR blocks covering `start..=end`, or `None` if the
+/// addresses are of different families or out of order.
+#[must_use]
+pub fn range(start: IpAddr, end: IpAddr) -> Option<Vec<Network>> {
+    let (mut lo, hi, bits) = match (start, end) {
+        (IpAddr::V4(s), IpAddr::V4(e)) => (u128::from(u32::from(s)), u128::from(u32::from(e)), 32),
+        (IpAddr::V6(s), IpAddr::V6(e)) => (u128::from(s), u128::from(e), 128),
+        _ => return None,
+    };
+    if lo > hi {
+        return None;
+    }
+
+    let to_addr = |n: u128| match start {
+        // Both ends are IPv4, so `n` fits in 32 bits
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(u32::try_from(n).unwrap_or_default())),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(n)),
+    };
+
+    let mut blocks = Vec::new();
+    loop {
+        // The largest block aligned at `lo` that doesn't run past `hi`
+        let span = hi - lo;
+        let fits = span.checked_add(1).map_or(128, u128::ilog2);
+        let size = lo.trailing_zeros().min(fits).min(bits);
+        let prefix = u8::try_from(bits - size).unwrap_or(0);
+        blocks.push(Network::new(to_addr(lo), prefix));
+
+        if size >= 128 {
+            break;
+        }
+        let last = lo + ((1u128 << size) - 1);
+        if last >= hi {
+            break;
+        }
+        lo = last + 1;
+    }
+    Some(blocks)
+}
+
+/// Networks read from a feed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Parsed {
+    /// Normalized networks, in feed order.
+    pub networks: Vec<Network>,
+    /// Non-comment lines that couldn't be read.
+    pub invalid: usize,
+}
+
+impl Parsed {
+    fn entry(&mut self, entry: &str) {
+        match parse_entry(entry) {
+            Some(networks) => self.networks.extend(networks),
+            None => self.invalid += 1,
+        }
+    }
+}
+
+/// An address, CIDR or `start-end` range.
+fn parse_entry(entry: &str) -> Option<Vec<Network>> {
+    match entry.split_once('-') {
+        Some((start, end)) => range(start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => Network::parse(entry).map(|network| vec![network]),
+    }
+}
+
+/// Strip a trailing comment starting with any of `markers`.
+fn strip_comment<'a>(line: &'a str, markers: &[char]) -> &'a str {
+    line.find(markers).map_or(line, |at| &line[..at]).trim()
+}
+
+/// Read a feed in `format`.
+#[must_use]
+pub fn parse(format: FeedFormat, text: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    match format {
+        FeedFormat::Cidr => {
+            for line in text.lines() {
+                let line = strip_comment(line, &['#', ';']);
+                if line.is_empty() {
+                    continue;
+                }
+                // Ranges may be written with spaces around the dash;
+                // anything else after the address is a label
+                if line.contains('-') {
+                    parsed.entry(line);
+                } else {
+                    parsed.entry(line.split_whitespace().next().unwrap_or_default());
+                }
+            }
+        }
+        FeedFormat::Firehol => {
+            for line in text.lines() {
+                let line = strip_comment(line, &['#']);
+                if let Some(entry) = line.split_whitespace().next() {
+                    parsed.entry(entry);
+                }
+            }
+        }
+        FeedFormat::Spamhaus => {
+            for line in text.lines() {
+                let line = line.trim();
+                if line.starts_with('{') {
+                    // drop_v4.json: {"cidr":"1.10.16.0/20","sblid":"SBL256894",...},
+                    // ending with a {"type":"metadata",...} line
+                    let Ok(record) = serde_json::from_str::<serde_json::Value>(line) else {
+                        parsed.invalid += 1;
+                        continue;
+                    };
+                    if let Some(cidr) = record["cidr"].as_str() {
+                        parsed.entry(cidr);
+                    } else if record["type"] != "metadata" {
+                        parsed.invalid += 1;
+                    }
+                    continue;
+                }
+                // DROP.txt: "1.10.16.0/20 ; SBL256894"
+                let line = strip_comment(line, &[';']);
+                if !line.is_empty() {
+                    parsed.entry(line);
+                }
+            }
+        }
+        FeedFormat::Abuseipdb => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .comment(Some(b'#'))
+                .from_reader(text.as_bytes());
+            let mut column = None;
+            for (row, record) in reader.records().enumerate() {
+                let Ok(record) = record else {
+                    parsed.invalid += 1;
+                    continue;
+                };
+                if row == 0 {
+                    column = record.iter().position(|field| {
+                        field.eq_ignore_ascii_case("ipAddress") || field.eq_ignore_ascii_case("ip")
+                    });
+                    if column.is_some() {
+                        continue;
+                    }
+                }
+                match record.get(column.unwrap_or(0)) {
+                    Some(ip) if !ip.trim().is_empty() => parsed.entry(ip),
+                    _ => parsed.invalid += 1,
+                }
+            }
+        }
+    }
+    parsed
+}
+
+/// Address space never imported from a feed: blocking it would cut off
+/// the host itself or its local network.
+fn reserved() -> Vec<Network> {
+    [
+        "0.0.0.0/8",
+        "10.0.0.0/8",
+        "100.64.0.0/10",
+        "127.0.0.0/8",
+        "169.254.0.0/16",
+        "172.16.0.0/12",
+        "192.168.0.0/16",
+        "224.0.0.0/3",
+        "::/127",
+        "fc00::/7",
+        "fe80::/10",
+        "ff00::/8",
+    ]
+    .iter()
+    .filter_map(|s| Network::parse(s))
+    .collect()
+}
+
+/// Outcome of merging a feed into the defense state.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Entries added to `blocked_ips`.
+    pub added: Vec<Network>,
+    /// Entries already blocked, exactly or by a wider range.
+    pub duplicate: usize,
+    /// Existing entries dropped because an added range covers them.
+    pub subsumed: Vec<String>,
+    /// Entries overlapping the whitelist or a protected address.
+    pub protected: usize,
+    /// Entries overlapping private, loopback or other reserved space.
+    pub reserved: usize,
+}
+
+/// Add `networks` to `state.blocked_ips`, skipping anything already
+/// covered, anything touching the whitelist or `protected`, and reserved
+/// space.
+///
+/// Existing entries inside an added range are removed, since nftables
+/// interval sets reject overlapping elements. `state` is left unchanged
+/// apart from those additions and removals.
+pub fn merge(state: &mut State, networks: &[Network], protected: &[IpAddr]) -> MergeReport {
+    let mut report = MergeReport::default();
+
+    let allowed: Vec<Network> = state
+        .whitelisted_ips
+        .iter()
+        .filter_map(|entry| Network::parse(entry))
+        .chain(protected.iter().map(|&ip| Network::new(ip, width(ip))))
+        .collect();
+    let reserved = reserved();
+
+    let mut covered = Coverage::default();
+    for entry in &state.blocked_ips {
+        if let Some(network) = Network::parse(entry) {
+            covered.insert(network);
+        }
+    }
+
+    // Widest first, so narrower entries inside them count as duplicates
+    let mut candidates: Vec<Network> = networks.to_vec();
+    candidates.sort_by_key(|network| (network.prefix, network.addr));
+    for network in candidates {
+        if covered.covers(&network) {
+            report.duplicate += 1;
+        } else if allowed.iter().any(|a| a.overlaps(&network)) {
+            report.protected += 1;
+        } else if reserved.iter().any(|r| r.overlaps(&network)) {
+            report.reserved += 1;
+        } else {
+            covered.insert(network);
+            report.added.push(network);
+        }
+    }
+
+    let mut added = Coverage::default();
+    for &network in &report.added {
+        added.insert(network);
+    }
+    state.blocked_ips.retain(|entry| {
+        let inside = Network::parse(entry).is_some_and(|network| added.covers(&network));
+        if inside {
+            report.subsumed.push(entry.clone());
+        }
+        !inside
+    });
+    for entry in &report.subsumed {
+        state.ban_details.remove(entry);
+    }
+    state
+        .blocked_ips
+        .extend(report.added.iter().map(ToString::to_string));
+    report
+}
+
+/// Blocks already present, for "is this covered" checks in one lookup per
+/// prefix length in use.
+#[derive(Debug, Default)]
+struct Coverage {
+    prefixes: BTreeSet<u8>,
+    networks: HashSet<Network>,
+}
+
+impl Coverage {
+    fn insert(&mut self, network: Network) {
+        self.prefixes.insert(network.prefix);
+        self.networks.insert(network);
+    }
+
+    fn covers(&self, network: &Network) -> bool {
+        self.prefixes
+            .range(..=network.prefix)
+            .any(|&prefix| self.networks.contains(&Network::new(network.addr, prefix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(networks: &[Network]) -> Vec<String> {
+        networks.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn entries_are_normalized() {
+        let parsed = parse(
+            FeedFormat::Cidr,
+            "# local list\n\
+             203.0.113.7/32\n\
+             198.51.100.77/24   ; scanner\n\
+             2001:db8::1/32\n\
+             192.0.2.10 - 192.0.2.20\n\
+             not-an-ip\n",
+        );
+        assert_eq!(
+            strings(&parsed.networks),
+            [
+                "203.0.113.7",
+                "198.51.100.0/24",
+                "2001:db8::/32",
+                "192.0.2.10/31",
+                "192.0.2.12/30",
+                "192.0.2.16/30",
+                "192.0.2.20",
+            ]
+        );
+        assert_eq!(parsed.invalid, 1);
+    }
+
+    #[test]
+    fn feed_formats_are_read() {
+        let firehol = parse(
+            FeedFormat::Firehol,
+            "#\n# firehol_level1\n#\n1.10.16.0/20\n5.188.10.180\n",
+        );
+        assert_eq!(strings(&firehol.networks), ["1.10.16.0/20", "5.188.10.180"]);
+
+        let drop = parse(
+            FeedFormat::Spamhaus,
+            "; Spamhaus DROP List 2024/01/01\n1.10.16.0/20 ; SBL256894\n\
+             {\"cidr\":\"1.19.0.0/16\",\"sblid\":\"SBL434604\",\"rir\":\"apnic\"}\n\
+             {\"type\":\"metadata\",\"timestamp\":1704067200,\"size\":2}\n",
+        );
+        assert_eq!(strings(&drop.networks), ["1.10.16.0/20", "1.19.0.0/16"]);
+        assert_eq!(drop.invalid, 0);
+
+        let abuse = parse(
+            FeedFormat::Abuseipdb,
+            "abuseConfidenceScore,ipAddress,lastReportedAt\n\
+             100,203.0.113.9,2024-01-01T00:00:00+00:00\n\
+             100,,2024-01-01T00:00:00+00:00\n",
+        );
+        assert_eq!(strings(&abuse.networks), ["203.0.113.9"]);
+        assert_eq!(abuse.invalid, 1);
+
+        let plain = parse(FeedFormat::Abuseipdb, "203.0.113.9\n2001:db8::5\n");
+        assert_eq!(strings(&plain.networks), ["203.0.113.9", "2001:db8::5"]);
+    }
+
+    #[test]
+    fn merge_dedupes_and_protects() {
+        let mut state = State {
+            blocked_ips: vec!["198.51.100.0/24".into(), "203.0.113.5".into()],
+            whitelisted_ips: vec!["192.0.2.44".into()],
+            ..State::default()
+        };
+        let networks = parse(
+            FeedFormat::Cidr,
+            "198.51.100.9\n203.0.113.5/32\n203.0.113.0/28\n203.0.113.1\n\
+             192.0.2.0/24\n10.0.0.0/8\n0.0.0.0/0\n2001:db8::/32\n",
+        )
+        .networks;
+
+        let report = merge(&mut state, &networks, &["2001:db8::22".parse().unwrap()]);
+        assert_eq!(strings(&report.added), ["203.0.113.0/28"]);
+        assert_eq!(report.duplicate, 3);
+        assert_eq!(report.protected, 3);
+        assert_eq!(report.reserved, 1);
+        assert_eq!(report.subsumed, ["203.0.113.5"]);
+        assert_eq!(state.blocked_ips, ["198.51.100.0/24", "203.0.113.0/28"]);
+    }
+
+    #[test]
+    fn merge_drops_entries_inside_a_wider_range() {
+        let mut state = State {
+            blocked_ips: vec!["198.51.7.9".into(), "203.0.113.5".into()],
+            ..State::default()
+        };
+        let networks = parse(FeedFormat::Cidr, "198.51.0.0/16\n").networks;
+
+        let report = merge(&mut state, &networks, &[]);
+        assert_eq!(strings(&report.added), ["198.51.0.0/16"]);
+        assert_eq!(report.subsumed, ["198.51.7.9"]);
+        assert_eq!(state.blocked_ips, ["203.0.113.5", "198.51.0.0/16"]);
+
+        let nft = crate::defend::generate_nftables(&state).unwrap();
+        let set = nft
+            .split("set blocked_ips {")
+            .nth(1)
+            .and_then(|rest| rest.split("elements = { ").nth(1))
+            .and_then(|rest| rest.split(" }").next())
+            .unwrap();
+        let elements: Vec<Network> = set.split(", ").filter_map(Network::parse).collect();
+        assert_eq!(elements.len(), 2);
+        for (i, a) in elements.iter().enumerate() {
+            for b in &elements[i + 1..] {
+                assert!(!a.overlaps(b), "{a} overlaps {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_split_into_cidrs() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert_eq!(
+            strings(&range(ip("0.0.0.0"), ip("255.255.255.255")).unwrap()),
+            ["0.0.0.0/0"]
+        );
+        assert_eq!(
+            strings(&range(ip("10.0.0.0"), ip("10.0.1.255")).unwrap()),
+            ["10.0.0.0/23"]
+        );
+        assert_eq!(
+            strings(&range(ip("::"), ip("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")).unwrap()),
+            ["::/0"]
+        );
+        assert!(range(ip("10.0.0.2"), ip("10.0.0.1")).is_none());
+        assert!(range(ip("10.0.0.1"), ip("::1")).is_none());
+    }
+}
@@ -0,0 +1,538 @@
+//! Syntax checks for exported firewall rules.
+//!
+//! Each ruleset is piped through its own tool in check mode, never applied:
+//! `nft -c -f -`, `pfctl -n -f -`, and `bash -n` for the iptables script.
+//! When the tool isn't installed, or can't check without root, a basic
+//! internal lint runs instead: balanced braces, addresses that parse and
+//! match the set or command family, and references to sets, tables and
+//! chains that exist. `bash -n` knows nothing about iptables itself, so the
+//! lint always runs alongside it.
+//!
+//! The lint also holds the exports to their allow-before-block guarantee
+//! (see [`super`]): an accept that follows a drop in the same chain or
+//! direction is reported, since the tools accept it as valid.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{ErrorKind, Write};
+use std::net::IpAddr;
+use std::process::{Command, Stdio};
+
+use super::feeds::Network;
+use super::State;
+
+/// Firewalls `defend export` writes rules for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Firewall {
+    Nftables,
+    Iptables,
+    Pf,
+}
+
+impl Firewall {
+    pub const ALL: [Self; 3] = [Self::Nftables, Self::Iptables, Self::Pf];
+
+    /// Parse a format name as accepted by `defend export`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "nftables" | "nft" => Some(Self::Nftables),
+            "iptables" | "ipt" => Some(Self::Iptables),
+            "pf" => Some(Self::Pf),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Nftables => "nftables",
+            Self::Iptables => "iptables",
+            Self::Pf => "pf",
+        }
+    }
+
+    /// The rules `defend export` would write for `state`.
+    pub fn generate(self, state: &State) -> Result<String> {
+        match self {
+            Self::Nftables => super::generate_nftables(state),
+            Self::Iptables => super::generate_iptables(state),
+            Self::Pf => super::generate_pf(state),
+        }
+    }
+
+    /// Program and arguments that check rules read from stdin.
+    const fn tool(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Nftables => ("nft", &["-c", "-f", "-"]),
+            Self::Iptables => ("bash", &["-n"]),
+            Self::Pf => ("pfctl", &["-n", "-f", "-"]),
+        }
+    }
+}
+
+impl fmt::Display for Firewall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem in a ruleset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// 1-based line in the rules, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Finding {
+    fn at(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+}
+
+/// The outcome of checking one ruleset.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub firewall: Firewall,
+    /// What checked the rules.
+    pub checker: String,
+    /// Why the firewall's own tool wasn't used, when it wasn't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+/// Check `rules` with the firewall's tool, falling back to the lint.
+#[must_use]
+pub fn check(firewall: Firewall, rules: &str) -> Report {
+    let (program, args) = firewall.tool();
+    match run_tool(program, args, rules) {
+        Ok(mut findings) => {
+            let mut checker = format!("{program} {}", args.join(" "));
+            if firewall == Firewall::Iptables {
+                findings.extend(lint(firewall, rules));
+                checker.push_str(" + internal lint");
+            }
+            Report {
+                firewall,
+                checker,
+                note: None,
+                findings,
+            }
+        }
+        Err(reason) => Report {
+            firewall,
+            checker: "internal lint".to_string(),
+            note: Some(reason),
+            findings: lint(firewall, rules),
+        },
+    }
+}
+
+/// Pipe `rules` into the tool; `Err` says why it couldn't be used.
+fn run_tool(program: &str, args: &[&str], rules: &str) -> Result<Vec<Finding>, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("`{program}` is not installed"),
+            _ => format!("`{program}` could not be started: {e}"),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A tool that bails early closes its stdin; its stderr says why
+        let _ = stdin.write_all(rules.as_bytes());
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("`{program}` failed: {e}"))?;
+    if output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("Operation not permitted") || stderr.contains("Permission denied") {
+        return Err(format!("`{program}` needs root to check rules"));
+    }
+    let mut findings: Vec<Finding> = stderr
+        .lines()
+        .filter_map(|message| line_number(message).map(|line| Finding::at(line, message.trim())))
+        .collect();
+    if findings.is_empty() {
+        findings.push(Finding {
+            line: None,
+            message: stderr.trim().to_string(),
+        });
+    }
+    Ok(findings)
+}
+
+/// Line number in a tool's error message: `-:5:9-20: Error: ...` (nft),
+/// `stdin:3: syntax error` (pfctl) or `bash: line 3: ...`.
+fn line_number(message: &str) -> Option<usize> {
+    message.split(':').skip(1).take(2).find_map(|field| {
+        let field = field.trim();
+        field.strip_prefix("line ").unwrap_or(field).parse().ok()
+    })
+}
+
+/// Basic internal checks for a ruleset.
+#[must_use]
+pub fn lint(firewall: Firewall, rules: &str) -> Vec<Finding> {
+    match firewall {
+        Firewall::Nftables => lint_nftables(rules),
+        Firewall::Iptables => lint_iptables(rules),
+        Firewall::Pf => lint_pf(rules),
+    }
+}
+
+/// Numbered lines with `#` comments removed, skipping blank ones.
+fn code_lines(rules: &str) -> impl Iterator<Item = (usize, &str)> {
+    rules.lines().enumerate().filter_map(|(i, line)| {
+        let code = line.split('#').next().unwrap_or_default().trim();
+        (!code.is_empty()).then_some((i + 1, code))
+    })
+}
+
+/// Message for an allow rule that a block rule before it can pre-empt.
+fn allow_after_block(block: usize) -> String {
+    format!("allow rule comes after the block rule on line {block}, so whitelisted addresses could be dropped")
+}
+
+/// Check an address or CIDR element, optionally against a family.
+fn check_address(element: &str, family: Option<&str>) -> Option<String> {
+    let Some(network) = Network::parse(element) else {
+        return Some(format!("`{element}` is not an IP address or CIDR block"));
+    };
+    match (family, network.addr()) {
+        (Some(family @ "ipv4_addr"), IpAddr::V6(_))
+        | (Some(family @ "ipv6_addr"), IpAddr::V4(_)) => {
+            Some(format!("`{element}` does not belong in a {family} set"))
+        }
+        (Some("iptables"), IpAddr::V6(_)) => Some(format!(
+            "`{element}` is IPv6; iptables only takes IPv4 (use ip6tables)"
+        )),
+        (Some("ip6tables"), IpAddr::V4(_)) => Some(format!(
+            "`{element}` is IPv4; ip6tables only takes IPv6 (use iptables)"
+        )),
+        _ => None,
+    }
+}
+
+fn lint_nftables(rules: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut open = Vec::new();
+    // Set name to its element type, once declared
+    let mut sets: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+    let mut references = Vec::new();
+    let mut current_set = None;
+    let mut set_type: Option<&str> = None;
+    let mut first_drop = None;
+
+    for (line, code) in code_lines(rules) {
+        if let Some(name) = code.strip_prefix("set ").and_then(|r| r.strip_suffix('{')) {
+            current_set = Some(name.trim());
+            sets.insert(name.trim(), None);
+            set_type = None;
+        } else if code.starts_with("chain ") {
+            current_set = None;
+            set_type = None;
+            first_drop = None;
+        } else if let Some(kind) = code.strip_prefix("type ") {
+            set_type = Some(kind.trim_end_matches(';').trim());
+            if let Some(name) = current_set {
+                sets.insert(name, set_type);
+            }
+        } else if let Some(elements) = code
+            .strip_prefix("elements = {")
+            .and_then(|r| r.strip_suffix('}'))
+        {
+            for element in elements.split(',').map(str::trim) {
+                if let Some(problem) = check_address(element, set_type) {
+                    findings.push(Finding::at(line, problem));
+                }
+            }
+        }
+
+        match code.split_whitespace().last() {
+            Some("drop") => first_drop = first_drop.or(Some(line)),
+            Some("accept") => {
+                if let Some(block) = first_drop {
+                    findings.push(Finding::at(line, allow_after_block(block)));
+                }
+            }
+            _ => {}
+        }
+        // `ip` or `ip6`, for a rule matching on addresses
+        let family = code
+            .split_whitespace()
+            .next()
+            .filter(|word| matches!(*word, "ip" | "ip6"));
+        for word in code.split_whitespace() {
+            if let Some(name) = word.strip_prefix('@') {
+                references.push((line, name, family));
+            }
+        }
+        for c in code.chars() {
+            match c {
+                '{' => open.push(line),
+                '}' if open.pop().is_none() => findings.push(Finding::at(line, "unmatched `}`")),
+                _ => {}
+            }
+        }
+    }
+
+    findings.extend(
+        open.into_iter()
+            .map(|line| Finding::at(line, "`{` is never closed")),
+    );
+    findings.extend(references.into_iter().filter_map(|(line, name, family)| {
+        let problem = match (sets.get(name), family) {
+            (None, _) => format!("set `@{name}` is not defined"),
+            (Some(Some(kind @ "ipv6_addr")), Some(rule @ "ip"))
+            | (Some(Some(kind @ "ipv4_addr")), Some(rule @ "ip6")) => {
+                format!("`{rule}` rule matches `@{name}`, which holds {kind} elements")
+            }
+            _ => return None,
+        };
+        Some(Finding::at(line, problem))
+    }));
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+fn lint_iptables(rules: &str) -> Vec<Finding> {
+    const TARGETS: [&str; 8] = [
+        "INPUT", "OUTPUT", "FORWARD", "ACCEPT", "DROP", "REJECT", "RETURN", "LOG",
+    ];
+    let commands: Vec<(usize, Vec<&str>)> = code_lines(rules)
+        .flat_map(|(line, code)| {
+            code.split([';', '|', '&'])
+                .map(|command| command.split_whitespace().collect::<Vec<_>>())
+                .filter(|words| matches!(words.first(), Some(&("iptables" | "ip6tables"))))
+                .map(move |words| (line, words))
+        })
+        .collect();
+    // iptables and ip6tables keep separate tables, so chains are per tool
+    let chains: BTreeSet<(&str, &str)> = commands
+        .iter()
+        .filter_map(|(_, words)| {
+            let at = words.iter().position(|w| *w == "-N")?;
+            Some((words[0], *words.get(at + 1)?))
+        })
+        .chain(
+            ["iptables", "ip6tables"]
+                .into_iter()
+                .flat_map(|tool| TARGETS.map(|target| (tool, target))),
+        )
+        .collect();
+
+    let mut findings = Vec::new();
+    let mut first_drop: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for (line, words) in &commands {
+        let tool = words[0];
+        let value_of = |flag: &str| {
+            let at = words.iter().position(|w| *w == flag)?;
+            words.get(at + 1).copied()
+        };
+        match (value_of("-A"), value_of("-j")) {
+            (Some(chain), Some("DROP" | "REJECT")) => {
+                first_drop.entry((tool, chain)).or_insert(*line);
+            }
+            (Some(chain), Some("ACCEPT")) => {
+                if let Some(block) = first_drop.get(&(tool, chain)) {
+                    findings.push(Finding::at(*line, allow_after_block(*block)));
+                }
+            }
+            _ => {}
+        }
+
+        for (at, flag) in words.iter().enumerate() {
+            if !matches!(*flag, "-s" | "-d" | "-j" | "-A" | "-I" | "-F" | "-N") {
+                continue;
+            }
+            let Some(value) = words.get(at + 1).filter(|v| !v.starts_with('-')) else {
+                findings.push(Finding::at(
+                    *line,
+                    format!("`{flag}` is missing its argument"),
+                ));
+                continue;
+            };
+            let problem = match *flag {
+                "-s" | "-d" if value.starts_with('$') => None,
+                "-s" | "-d" => check_address(value, Some(tool)),
+                "-N" => None,
+                _ if chains.contains(&(tool, *value)) => None,
+                _ => Some(format!("{tool} chain or target `{value}` is not defined")),
+            };
+            findings.extend(problem.map(|problem| Finding::at(*line, problem)));
+        }
+    }
+    findings
+}
+
+fn lint_pf(rules: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut tables = BTreeSet::new();
+    let mut references = Vec::new();
+    let mut first_block: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for (line, code) in code_lines(rules) {
+        let mut words = code.split_whitespace();
+        match words.next() {
+            Some("table") => {
+                let name = words.next().unwrap_or_default();
+                tables.insert(name.trim_start_matches('<').trim_end_matches('>'));
+                let elements = code
+                    .split_once('{')
+                    .and_then(|(_, rest)| rest.strip_suffix('}'));
+                let Some(elements) = elements else {
+                    findings.push(Finding::at(line, "table needs `{ ... }` on one line"));
+                    continue;
+                };
+                for element in elements.split([',', ' ']).filter(|e| !e.is_empty()) {
+                    if let Some(problem) = check_address(element, None) {
+                        findings.push(Finding::at(line, problem));
+                    }
+                }
+            }
+            Some(verb @ ("pass" | "block")) => {
+                let direction = words.next().filter(|d| matches!(*d, "in" | "out"));
+                let rest: Vec<&str> = words.collect();
+                match (verb, direction) {
+                    (_, None) => {
+                        findings.push(Finding::at(line, "rule needs a direction (`in` or `out`)"));
+                    }
+                    // Only `quick` rules stop evaluation; otherwise the last match wins
+                    (_, Some(_)) if !rest.contains(&"quick") => {}
+                    ("block", Some(direction)) => {
+                        first_block.entry(direction).or_insert(line);
+                    }
+                    (_, Some(direction)) => {
+                        if let Some(block) = first_block.get(direction) {
+                            findings.push(Finding::at(line, allow_after_block(*block)));
+                        }
+                    }
+                }
+                for word in rest {
+                    if let Some(name) = word.strip_prefix('<').and_then(|w| w.strip_suffix('>')) {
+                        references.push((line, name));
+                    }
+                }
+            }
+            Some(word) => findings.push(Finding::at(line, format!("unknown statement `{word}`"))),
+            None => {}
+        }
+    }
+
+    findings.extend(
+        references
+            .into_iter()
+            .filter(|(_, name)| !tables.contains(name))
+            .map(|(line, name)| Finding::at(line, format!("table `<{name}>` is not defined"))),
+    );
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(blocked: &[&str]) -> State {
+        State {
+            blocked_ips: blocked.iter().map(ToString::to_string).collect(),
+            whitelisted_ips: vec!["192.0.2.10".into()],
+            blocked_countries: vec!["cn".into()],
+            blocked_countries_outbound: vec!["ru".into()],
+            ..State::default()
+        }
+    }
+
+    #[test]
+    fn generated_rules_pass_the_lint() {
+        let state = state(&["1.2.3.4", "203.0.113.0/24"]);
+        for firewall in Firewall::ALL {
+            let rules = firewall.generate(&state).unwrap();
+            assert_eq!(lint(firewall, &rules), [], "{firewall}");
+        }
+    }
+
+    #[test]
+    fn lint_reports_lines() {
+        // Addresses of the wrong family for their set or command
+        let nft = "table inet t {\n    set s {\n        type ipv4_addr\n        elements = { 2001:db8::/32 }\n    }\n    chain input {\n        ip6 saddr @s drop\n    }\n}\n";
+        let nft = lint(Firewall::Nftables, nft);
+        assert_eq!(nft.len(), 2, "{nft:?}");
+        assert_eq!((nft[0].line, nft[1].line), (Some(4), Some(7)));
+        assert!(nft[0].message.contains("ipv4_addr set"));
+        assert!(nft[1].message.contains("`ip6` rule"));
+
+        // ip6tables has its own chains, so G isn't defined for iptables
+        let ipt = "ip6tables -N G\nip6tables -A G -s 10.0.0.1 -j DROP\niptables -A G -s 2001:db8::1 -j DROP\n";
+        let ipt = lint(Firewall::Iptables, ipt);
+        assert_eq!(ipt.len(), 3, "{ipt:?}");
+        assert!(ipt[0].message.contains("use iptables"));
+        assert!(ipt[1].message.contains("iptables chain"));
+        assert!(ipt[2].message.contains("use ip6tables"));
+
+        let broken = "table inet t {\n    set s {\n        elements = { 1.2.3.999 }\n    }\n    chain input {\n        ip saddr @missing drop\n}\n";
+        let lines: Vec<_> = lint(Firewall::Nftables, broken)
+            .into_iter()
+            .map(|f| f.line)
+            .collect();
+        assert_eq!(lines, [Some(1), Some(3), Some(6)]);
+
+        let pf = lint(
+            Firewall::Pf,
+            "table <a> { 10.0.0.1 }\nblock quick from <b>\n",
+        );
+        assert_eq!(pf.len(), 2);
+        assert!(pf.iter().all(|f| f.line == Some(2)));
+    }
+
+    #[test]
+    fn allow_after_block_is_reported() {
+        let nft = "table inet t {\n    chain input {\n        ip saddr 10.0.0.0/8 drop\n        ip saddr 10.0.0.1 accept\n    }\n}\n";
+        let ipt = "iptables -N G\niptables -A G -s 10.0.0.0/8 -j DROP\niptables -A G -s 10.0.0.1 -j ACCEPT\n";
+        let pf = "table <w> { 10.0.0.1 }\nblock in quick from 10.0.0.0/8\npass out quick to <w>\npass in quick from <w>\n";
+        for (firewall, rules, line) in [
+            (Firewall::Nftables, nft, 4),
+            (Firewall::Iptables, ipt, 3),
+            (Firewall::Pf, pf, 4),
+        ] {
+            let findings = lint(firewall, rules);
+            assert_eq!(findings.len(), 1, "{firewall}: {findings:?}");
+            assert_eq!(findings[0].line, Some(line));
+            assert!(findings[0].message.contains("after the block rule"));
+        }
+    }
+
+    #[test]
+    fn tool_messages_carry_line_numbers() {
+        assert_eq!(line_number("-:5:9-20: Error: syntax error"), Some(5));
+        assert_eq!(line_number("stdin:3: syntax error"), Some(3));
+        assert_eq!(
+            line_number("bash: line 12: syntax error near unexpected token `}'"),
+            Some(12)
+        );
+        assert_eq!(line_number("nft: command not found"), None);
+    }
+}
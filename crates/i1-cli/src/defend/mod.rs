@@ -1,13 +1,47 @@
 //! Defense module: geo-blocking, IP banning, firewall rule generation.
+//!
+//! # Rule ordering
+//!
+//! Every generated ruleset accepts whitelisted addresses before it drops
+//! anything, in each chain (nftables, iptables) or direction (pf), so a
+//! whitelisted IP inside a blocked range, country or ASN is never dropped.
+//! nftables and iptables stop at the first verdict in a chain, and the pf
+//! rules are all `quick`, which does the same. New block rules belong
+//! after the whitelist; `defend test-rules` reports any that come before.
+//!
+//! # Sharing the state file
+//!
+//! The honeypot sink and i1-srv's admin API update defense state files
+//! too. Every writer holds an exclusive lock on `<file>.lock` from reading
+//! the file to replacing it, and replaces it by rename, so updates are
+//! never lost and readers never see half a file.
+
+pub mod feeds;
+pub mod lint;
+pub mod rpz;
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Version of the state file layout this build writes.
+///
+/// i1-srv's collector reads this to pick a parser; bump it whenever the
+/// layout changes.
+pub const SCHEMA_VERSION: u32 = 1;
 
 /// Defense state - what's currently blocked.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
+    /// State file layout version; files from before versioning are 1.
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+
     /// Blocked country codes (ISO 2-letter, lowercase) - inbound.
     pub blocked_countries: Vec<String>,
 
@@ -24,6 +58,63 @@ pub struct State {
 
     /// Whitelisted IPs (never blocked, including outbound).
     pub whitelisted_ips: Vec<String>,
+
+    /// Why, and until when, entries in `blocked_ips` were banned, for bans
+    /// made with a reason. Older readers ignore it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub ban_details: BTreeMap<String, BanDetail>,
+}
+
+/// Exclusive lock on a defense state file, released when dropped.
+#[derive(Debug)]
+pub struct StateLock {
+    _file: File,
+}
+
+impl StateLock {
+    /// Wait for the lock on `path`, held through `<path>.lock`.
+    pub fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        fs4::fs_std::FileExt::lock_exclusive(&file)?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Reason and expiry of a ban.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BanDetail {
+    pub reason: String,
+
+    /// When the ban lapses; `None` keeps it until unbanned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+const fn legacy_schema_version() -> u32 {
+    1
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            blocked_countries: Vec::new(),
+            blocked_countries_outbound: Vec::new(),
+            blocked_ips: Vec::new(),
+            blocked_asns: Vec::new(),
+            whitelisted_ips: Vec::new(),
+            ban_details: BTreeMap::new(),
+        }
+    }
 }
 
 impl State {
@@ -35,7 +126,7 @@ impl State {
         Ok(dirs.data_dir().join("defend_state.json"))
     }
 
-    /// Load state from file.
+    /// Load state from file, for reading only.
     pub fn load() -> Result<Self> {
         let path = Self::path()?;
 
@@ -49,8 +140,17 @@ impl State {
         Ok(state)
     }
 
-    /// Save state to file.
-    pub fn save(&self) -> Result<()> {
+    /// Load state from file, locking it against other writers until the
+    /// returned [`StateLock`] is dropped.
+    pub fn load_for_update() -> Result<(Self, StateLock)> {
+        let lock = StateLock::acquire(&Self::path()?)?;
+        Ok((Self::load()?, lock))
+    }
+
+    /// Save state to file, by rename so readers never see half of it.
+    ///
+    /// `_lock` is the lock taken by [`State::load_for_update`].
+    pub fn save(&self, _lock: &StateLock) -> Result<()> {
         let path = Self::path()?;
 
         // Ensure parent directory exists
@@ -58,11 +158,47 @@ impl State {
             std::fs::create_dir_all(parent)?;
         }
 
-        let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(&path, content)?;
+        let state = Self {
+            schema_version: SCHEMA_VERSION,
+            ..self.clone()
+        };
+        let content = serde_json::to_string_pretty(&state)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &path)?;
 
         Ok(())
     }
+
+    /// Block `target` with a reason and optional expiry. Returns whether
+    /// anything changed: whitelisted targets are never blocked, and
+    /// banning an existing entry again only updates its details.
+    pub fn ban(&mut self, target: &str, detail: BanDetail) -> bool {
+        if self.whitelisted_ips.iter().any(|ip| ip == target) {
+            return false;
+        }
+        let added = !self.blocked_ips.iter().any(|ip| ip == target);
+        if added {
+            self.blocked_ips.push(target.to_string());
+        }
+        let updated = self.ban_details.insert(target.to_string(), detail.clone()) != Some(detail);
+        added || updated
+    }
+
+    /// Lift bans whose expiry has passed, returning the targets unbanned.
+    pub fn expire_bans(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        let expired: Vec<String> = self
+            .ban_details
+            .iter()
+            .filter(|(_, detail)| detail.expires.is_some_and(|at| at <= now))
+            .map(|(target, _)| target.clone())
+            .collect();
+        for target in &expired {
+            self.ban_details.remove(target);
+            self.blocked_ips.retain(|ip| ip != target);
+        }
+        expired
+    }
 }
 
 /// Geo-blocking operations.
@@ -114,9 +250,84 @@ pub fn country_name(code: &str) -> &'static str {
     }
 }
 
+/// Where `defend export` points for a country's IPv4 ranges.
+const COUNTRY_ZONES_V4: &str = "https://www.ipdeny.com/ipblocks/data/aggregated";
+
+/// Where `defend export` points for a country's IPv6 ranges.
+const COUNTRY_ZONES_V6: &str = "https://www.ipdeny.com/ipv6/ipaddresses/aggregated";
+
+/// Check that `target` is an IPv4 or IPv6 address or CIDR block, and return
+/// it in canonical form (host bits cleared, IPv6 compressed).
+pub fn parse_target(target: &str) -> Result<String> {
+    feeds::Network::parse(target)
+        .map(|network| network.to_string())
+        .ok_or_else(|| anyhow::anyhow!("`{target}` is not an IPv4 or IPv6 address or CIDR block"))
+}
+
+/// Whether a state entry is an IPv6 address or block.
+fn is_ipv6(entry: &str) -> bool {
+    feeds::Network::parse(entry).is_some_and(|network| network.addr().is_ipv6())
+}
+
+/// Split state entries into IPv4 and IPv6, keeping their order. Entries
+/// that don't parse stay with IPv4, where the lint will report them.
+fn by_family(entries: &[String]) -> (Vec<&str>, Vec<&str>) {
+    entries
+        .iter()
+        .map(String::as_str)
+        .partition(|entry| !is_ipv6(entry))
+}
+
+/// Append an interval set, or nothing if there are no elements.
+fn push_nft_set(rules: &mut String, name: &str, kind: &str, elements: &[&str]) {
+    if elements.is_empty() {
+        return;
+    }
+    let _ = writeln!(rules, "    set {name} {{");
+    let _ = writeln!(rules, "        type {kind}");
+    let _ = writeln!(rules, "        flags interval");
+    let _ = writeln!(rules, "        elements = {{ {} }}", elements.join(", "));
+    let _ = writeln!(rules, "    }}\n");
+}
+
+/// Append placeholder sets for a country's IPv4 and IPv6 ranges.
+fn push_nft_country_sets(rules: &mut String, country: &str, label: &str) {
+    let _ = writeln!(
+        rules,
+        "    # {label}: {} ({})",
+        country.to_uppercase(),
+        country_name(country)
+    );
+    let _ = writeln!(
+        rules,
+        "    # Download ranges from: {COUNTRY_ZONES_V4}/{country}-aggregated.zone"
+    );
+    let _ = writeln!(
+        rules,
+        "    #                  and: {COUNTRY_ZONES_V6}/{country}-aggregated.zone"
+    );
+    for (suffix, kind) in [("", "ipv4_addr"), ("6", "ipv6_addr")] {
+        let _ = writeln!(rules, "    set country_{country}{suffix} {{");
+        let _ = writeln!(rules, "        type {kind}");
+        let _ = writeln!(rules, "        flags interval");
+        let _ = writeln!(
+            rules,
+            "        # elements = {{ ... load from zone file ... }}"
+        );
+        let _ = writeln!(rules, "    }}\n");
+    }
+}
+
 /// Generate nftables rules from state.
+///
+/// IPv4 and IPv6 entries go in separate sets (`whitelist` and
+/// `whitelist6`, and so on), matched with `ip` and `ip6` respectively.
+/// Both chains accept the whitelists before any drop (see
+/// [Rule ordering](self#rule-ordering)).
 pub fn generate_nftables(state: &State) -> Result<String> {
     let mut rules = String::new();
+    let (whitelist_v4, whitelist_v6) = by_family(&state.whitelisted_ips);
+    let (blocked_v4, blocked_v6) = by_family(&state.blocked_ips);
 
     rules.push_str("#!/usr/sbin/nft -f\n");
     rules.push_str("# Generated by showdi1 defend export\n");
@@ -124,57 +335,18 @@ pub fn generate_nftables(state: &State) -> Result<String> {
 
     rules.push_str("table inet geoblock {\n");
 
-    // Whitelist set
-    if !state.whitelisted_ips.is_empty() {
-        rules.push_str("    set whitelist {\n");
-        rules.push_str("        type ipv4_addr\n");
-        rules.push_str("        flags interval\n");
-        rules.push_str("        elements = { ");
-        rules.push_str(&state.whitelisted_ips.join(", "));
-        rules.push_str(" }\n");
-        rules.push_str("    }\n\n");
-    }
-
-    // Blocked IPs set
-    if !state.blocked_ips.is_empty() {
-        rules.push_str("    set blocked_ips {\n");
-        rules.push_str("        type ipv4_addr\n");
-        rules.push_str("        flags interval\n");
-        rules.push_str("        elements = { ");
-        rules.push_str(&state.blocked_ips.join(", "));
-        rules.push_str(" }\n");
-        rules.push_str("    }\n\n");
-    }
+    push_nft_set(&mut rules, "whitelist", "ipv4_addr", &whitelist_v4);
+    push_nft_set(&mut rules, "whitelist6", "ipv6_addr", &whitelist_v6);
+    push_nft_set(&mut rules, "blocked_ips", "ipv4_addr", &blocked_v4);
+    push_nft_set(&mut rules, "blocked_ips6", "ipv6_addr", &blocked_v6);
 
     // Country sets (placeholder - would need IP ranges)
     for country in &state.blocked_countries {
-        rules.push_str(&format!(
-            "    # Country: {} ({})\n",
-            country.to_uppercase(),
-            country_name(country)
-        ));
-        rules.push_str(&format!("    # Download ranges from: https://www.ipdeny.com/ipblocks/data/aggregated/{country}-aggregated.zone\n"));
-        rules.push_str(&format!("    set country_{country} {{\n"));
-        rules.push_str("        type ipv4_addr\n");
-        rules.push_str("        flags interval\n");
-        rules.push_str("        # elements = { ... load from zone file ... }\n");
-        rules.push_str("    }\n\n");
+        push_nft_country_sets(&mut rules, country, "Country");
     }
-
-    // Outbound country sets
     for country in &state.blocked_countries_outbound {
         if !state.blocked_countries.contains(country) {
-            rules.push_str(&format!(
-                "    # Country (outbound): {} ({})\n",
-                country.to_uppercase(),
-                country_name(country)
-            ));
-            rules.push_str(&format!("    # Download ranges from: https://www.ipdeny.com/ipblocks/data/aggregated/{country}-aggregated.zone\n"));
-            rules.push_str(&format!("    set country_{country} {{\n"));
-            rules.push_str("        type ipv4_addr\n");
-            rules.push_str("        flags interval\n");
-            rules.push_str("        # elements = { ... load from zone file ... }\n");
-            rules.push_str("    }\n\n");
+            push_nft_country_sets(&mut rules, country, "Country (outbound)");
         }
     }
 
@@ -182,21 +354,34 @@ pub fn generate_nftables(state: &State) -> Result<String> {
     rules.push_str("    chain input {\n");
     rules.push_str("        type filter hook input priority 0; policy accept;\n\n");
 
-    // Whitelist rule
+    // Whitelist rules
     if !state.whitelisted_ips.is_empty() {
         rules.push_str("        # Allow whitelisted IPs\n");
-        rules.push_str("        ip saddr @whitelist accept\n\n");
+        if !whitelist_v4.is_empty() {
+            rules.push_str("        ip saddr @whitelist accept\n");
+        }
+        if !whitelist_v6.is_empty() {
+            rules.push_str("        ip6 saddr @whitelist6 accept\n");
+        }
+        rules.push('\n');
     }
 
     // Block rules
     if !state.blocked_ips.is_empty() {
         rules.push_str("        # Block specific IPs\n");
-        rules.push_str("        ip saddr @blocked_ips drop\n\n");
+        if !blocked_v4.is_empty() {
+            rules.push_str("        ip saddr @blocked_ips drop\n");
+        }
+        if !blocked_v6.is_empty() {
+            rules.push_str("        ip6 saddr @blocked_ips6 drop\n");
+        }
+        rules.push('\n');
     }
 
     for country in &state.blocked_countries {
-        rules.push_str(&format!("        # Block {}\n", country_name(country)));
-        rules.push_str(&format!("        ip saddr @country_{country} drop\n"));
+        let _ = writeln!(rules, "        # Block {}", country_name(country));
+        let _ = writeln!(rules, "        ip saddr @country_{country} drop");
+        let _ = writeln!(rules, "        ip6 saddr @country_{country}6 drop");
     }
 
     rules.push_str("    }\n\n");
@@ -209,15 +394,23 @@ pub fn generate_nftables(state: &State) -> Result<String> {
         // Always allow outbound to whitelisted IPs
         if !state.whitelisted_ips.is_empty() {
             rules.push_str("        # Always allow outbound to whitelisted IPs\n");
-            rules.push_str("        ip daddr @whitelist accept\n\n");
+            if !whitelist_v4.is_empty() {
+                rules.push_str("        ip daddr @whitelist accept\n");
+            }
+            if !whitelist_v6.is_empty() {
+                rules.push_str("        ip6 daddr @whitelist6 accept\n");
+            }
+            rules.push('\n');
         }
 
         for country in &state.blocked_countries_outbound {
-            rules.push_str(&format!(
-                "        # Block outbound to {} (honeypot mode)\n",
+            let _ = writeln!(
+                rules,
+                "        # Block outbound to {} (honeypot mode)",
                 country_name(country)
-            ));
-            rules.push_str(&format!("        ip daddr @country_{country} drop\n"));
+            );
+            let _ = writeln!(rules, "        ip daddr @country_{country} drop");
+            let _ = writeln!(rules, "        ip6 daddr @country_{country}6 drop");
         }
 
         rules.push_str("    }\n");
@@ -228,7 +421,36 @@ pub fn generate_nftables(state: &State) -> Result<String> {
     Ok(rules)
 }
 
+/// The iptables command for an entry's family.
+fn iptables_for(entry: &str) -> &'static str {
+    if is_ipv6(entry) {
+        "ip6tables"
+    } else {
+        "iptables"
+    }
+}
+
+/// Append commented-out commands that load a country's IPv4 and IPv6
+/// ranges into `chain`, matching on `flag` (`-s` or `-d`).
+fn push_iptables_country(rules: &mut String, country: &str, chain: &str, flag: &str) {
+    for (zones, tool) in [
+        (COUNTRY_ZONES_V4, "iptables"),
+        (COUNTRY_ZONES_V6, "ip6tables"),
+    ] {
+        let _ = writeln!(
+            rules,
+            "# Download: curl -s {zones}/{country}-aggregated.zone | while read ip; do"
+        );
+        let _ = writeln!(rules, "#   {tool} -A {chain} {flag} $ip -j DROP");
+        let _ = writeln!(rules, "# done");
+    }
+}
+
 /// Generate iptables rules from state.
+///
+/// IPv6 entries use `ip6tables`, which gets a chain of the same name.
+/// Whitelist `ACCEPT`s are appended to each chain before its `DROP`s
+/// (see [Rule ordering](self#rule-ordering)).
 pub fn generate_iptables(state: &State) -> Result<String> {
     let mut rules = String::new();
 
@@ -236,13 +458,15 @@ pub fn generate_iptables(state: &State) -> Result<String> {
     rules.push_str("# Generated by showdi1 defend export\n");
     rules.push_str("# Run as root to apply\n\n");
 
-    rules.push_str("# Create chain\n");
-    rules.push_str("iptables -N GEOBLOCK 2>/dev/null || iptables -F GEOBLOCK\n\n");
+    rules.push_str("# Create chains\n");
+    rules.push_str("iptables -N GEOBLOCK 2>/dev/null || iptables -F GEOBLOCK\n");
+    rules.push_str("ip6tables -N GEOBLOCK 2>/dev/null || ip6tables -F GEOBLOCK\n\n");
 
     // Whitelist
     for ip in &state.whitelisted_ips {
         rules.push_str(&format!(
-            "# Whitelist\niptables -A GEOBLOCK -s {ip} -j ACCEPT\n"
+            "# Whitelist\n{} -A GEOBLOCK -s {ip} -j ACCEPT\n",
+            iptables_for(ip)
         ));
     }
 
@@ -252,7 +476,7 @@ pub fn generate_iptables(state: &State) -> Result<String> {
 
     // Block IPs
     for ip in &state.blocked_ips {
-        rules.push_str(&format!("iptables -A GEOBLOCK -s {ip} -j DROP\n"));
+        let _ = writeln!(rules, "{} -A GEOBLOCK -s {ip} -j DROP", iptables_for(ip));
     }
 
     // Country blocks (placeholder)
@@ -262,24 +486,25 @@ pub fn generate_iptables(state: &State) -> Result<String> {
             country_name(country),
             country.to_uppercase()
         ));
-        rules.push_str(&format!("# Download: curl -s https://www.ipdeny.com/ipblocks/data/aggregated/{country}-aggregated.zone | while read ip; do\n"));
-        rules.push_str("#   iptables -A GEOBLOCK -s $ip -j DROP\n");
-        rules.push_str("# done\n");
+        push_iptables_country(&mut rules, country, "GEOBLOCK", "-s");
     }
 
-    rules.push_str("\n# Insert chain into INPUT\n");
+    rules.push_str("\n# Insert chains into INPUT\n");
     rules.push_str("iptables -I INPUT -j GEOBLOCK\n");
+    rules.push_str("ip6tables -I INPUT -j GEOBLOCK\n");
 
     // Outbound blocking (honeypot mode)
     if !state.blocked_countries_outbound.is_empty() {
         rules.push_str("\n# ── Outbound blocking (honeypot mode) ──\n");
         rules.push_str("# They can connect in, but nothing goes back out.\n");
-        rules.push_str("iptables -N GEOBLOCK_OUT 2>/dev/null || iptables -F GEOBLOCK_OUT\n\n");
+        rules.push_str("iptables -N GEOBLOCK_OUT 2>/dev/null || iptables -F GEOBLOCK_OUT\n");
+        rules.push_str("ip6tables -N GEOBLOCK_OUT 2>/dev/null || ip6tables -F GEOBLOCK_OUT\n\n");
 
         // Always allow outbound to whitelisted IPs
         for ip in &state.whitelisted_ips {
             rules.push_str(&format!(
-                "# Whitelist outbound\niptables -A GEOBLOCK_OUT -d {ip} -j ACCEPT\n"
+                "# Whitelist outbound\n{} -A GEOBLOCK_OUT -d {ip} -j ACCEPT\n",
+                iptables_for(ip)
             ));
         }
         if !state.whitelisted_ips.is_empty() {
@@ -292,19 +517,22 @@ pub fn generate_iptables(state: &State) -> Result<String> {
                 country_name(country),
                 country.to_uppercase()
             ));
-            rules.push_str(&format!("# Download: curl -s https://www.ipdeny.com/ipblocks/data/aggregated/{country}-aggregated.zone | while read ip; do\n"));
-            rules.push_str("#   iptables -A GEOBLOCK_OUT -d $ip -j DROP\n");
-            rules.push_str("# done\n");
+            push_iptables_country(&mut rules, country, "GEOBLOCK_OUT", "-d");
         }
 
-        rules.push_str("\n# Insert chain into OUTPUT\n");
+        rules.push_str("\n# Insert chains into OUTPUT\n");
         rules.push_str("iptables -I OUTPUT -j GEOBLOCK_OUT\n");
+        rules.push_str("ip6tables -I OUTPUT -j GEOBLOCK_OUT\n");
     }
 
     Ok(rules)
 }
 
 /// Generate pf rules for BSD/macOS.
+///
+/// pf tables hold IPv4 and IPv6 entries alike, so one table serves both.
+/// `pass quick` for the whitelist precedes every `block quick` in the same
+/// direction (see [Rule ordering](self#rule-ordering)).
 pub fn generate_pf(state: &State) -> Result<String> {
     let mut rules = String::new();
 
@@ -326,7 +554,7 @@ pub fn generate_pf(state: &State) -> Result<String> {
 
     for country in &state.blocked_countries {
         rules.push_str(&format!(
-            "# table <{country}> {{ ... load from {country}-aggregated.zone ... }}\n"
+            "# table <{country}> {{ ... load the IPv4 and IPv6 {country}-aggregated.zone ... }}\n"
         ));
     }
 
@@ -363,3 +591,265 @@ pub fn generate_pf(state: &State) -> Result<String> {
 
     Ok(rules)
 }
+
+/// A line the pending change adds to or removes from exported rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleChange {
+    Added(String),
+    Removed(String),
+}
+
+/// The lines `defend export` would add or remove for `firewall` if the
+/// state went from `before` to `after`, in rule order.
+pub fn rule_changes(
+    firewall: lint::Firewall,
+    before: &State,
+    after: &State,
+) -> Result<Vec<RuleChange>> {
+    Ok(diff_lines(
+        &firewall.generate(before)?,
+        &firewall.generate(after)?,
+    ))
+}
+
+/// Largest unmatched region (lines before times lines after) that gets a
+/// proper LCS; past that, the region is reported as replaced wholesale.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Line diff of two rulesets, skipping blank lines. The common prefix and
+/// suffix are trimmed first, so a small change to a large ruleset only runs
+/// the LCS over the few lines around it.
+fn diff_lines(before: &str, after: &str) -> Vec<RuleChange> {
+    let old: Vec<&str> = before.lines().filter(|l| !l.trim().is_empty()).collect();
+    let new: Vec<&str> = after.lines().filter(|l| !l.trim().is_empty()).collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    let removed = |line: &&str| RuleChange::Removed((*line).to_string());
+    let added = |line: &&str| RuleChange::Added((*line).to_string());
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return old
+            .iter()
+            .map(removed)
+            .chain(new.iter().map(added))
+            .collect();
+    }
+
+    // lcs[i][j]: longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(removed(&old[i]));
+            i += 1;
+        } else {
+            changes.push(added(&new[j]));
+            j += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_shared_v1_fixture() {
+        let state: State = serde_json::from_str(include_str!(
+            "../../../i1-srv/testdata/defend_state_v1.json"
+        ))
+        .unwrap();
+        assert_eq!(state.schema_version, SCHEMA_VERSION);
+        assert_eq!(state.blocked_ips, vec!["1.2.3.4", "10.0.0.0/24"]);
+        assert_eq!(state.blocked_countries, vec!["cn", "ru"]);
+
+        let legacy: State = serde_json::from_str(
+            r#"{"blocked_countries":[],"blocked_ips":[],"blocked_asns":[],"whitelisted_ips":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.schema_version, 1);
+    }
+
+    #[test]
+    fn whitelist_precedes_blocks() {
+        // A whitelisted address inside a blocked CIDR
+        let state = State {
+            blocked_ips: vec!["203.0.113.0/24".into()],
+            whitelisted_ips: vec!["203.0.113.7".into()],
+            blocked_countries: vec!["cn".into()],
+            blocked_countries_outbound: vec!["ru".into()],
+            ..State::default()
+        };
+        let position = |rules: &str, rule: &str| {
+            rules
+                .lines()
+                .position(|line| line.trim() == rule)
+                .unwrap_or_else(|| panic!("no `{rule}` in:\n{rules}"))
+        };
+
+        let nft = generate_nftables(&state).unwrap();
+        assert!(
+            position(&nft, "ip saddr @whitelist accept")
+                < position(&nft, "ip saddr @blocked_ips drop")
+        );
+        assert!(
+            position(&nft, "ip saddr @whitelist accept")
+                < position(&nft, "ip saddr @country_cn drop")
+        );
+        assert!(
+            position(&nft, "ip daddr @whitelist accept")
+                < position(&nft, "ip daddr @country_ru drop")
+        );
+
+        let ipt = generate_iptables(&state).unwrap();
+        assert!(
+            position(&ipt, "iptables -A GEOBLOCK -s 203.0.113.7 -j ACCEPT")
+                < position(&ipt, "iptables -A GEOBLOCK -s 203.0.113.0/24 -j DROP")
+        );
+
+        let pf = generate_pf(&state).unwrap();
+        assert!(
+            position(&pf, "pass in quick from <whitelist>")
+                < position(&pf, "block in quick from <blocked>")
+        );
+
+        for firewall in lint::Firewall::ALL {
+            let rules = firewall.generate(&state).unwrap();
+            assert_eq!(lint::lint(firewall, &rules), [], "{firewall}");
+        }
+    }
+
+    #[test]
+    fn exports_mixed_families() {
+        let state = State {
+            blocked_ips: vec![
+                "198.51.100.0/24".into(),
+                "2001:db8::/32".into(),
+                "2001:db8:ffff::1".into(),
+            ],
+            whitelisted_ips: vec!["2001:db8:1::7".into(), "198.51.100.7".into()],
+            blocked_countries: vec!["cn".into()],
+            blocked_countries_outbound: vec!["ru".into()],
+            ..State::default()
+        };
+
+        let nft = generate_nftables(&state).unwrap();
+        assert!(nft.contains(
+            "set blocked_ips6 {\n        type ipv6_addr\n        flags interval\n        \
+             elements = { 2001:db8::/32, 2001:db8:ffff::1 }"
+        ));
+        assert!(nft.contains("set blocked_ips {\n        type ipv4_addr"));
+        assert!(nft.contains("elements = { 198.51.100.0/24 }"));
+        assert!(nft.contains("elements = { 2001:db8:1::7 }"));
+        for rule in [
+            "ip6 saddr @whitelist6 accept",
+            "ip6 saddr @blocked_ips6 drop",
+            "ip6 saddr @country_cn6 drop",
+            "ip6 daddr @whitelist6 accept",
+            "ip6 daddr @country_ru6 drop",
+        ] {
+            assert!(nft.lines().any(|line| line.trim() == rule), "no `{rule}`");
+        }
+        assert!(nft.contains(&format!("{COUNTRY_ZONES_V6}/cn-aggregated.zone")));
+
+        let ipt = generate_iptables(&state).unwrap();
+        for rule in [
+            "ip6tables -A GEOBLOCK -s 2001:db8:1::7 -j ACCEPT",
+            "iptables -A GEOBLOCK -s 198.51.100.7 -j ACCEPT",
+            "iptables -A GEOBLOCK -s 198.51.100.0/24 -j DROP",
+            "ip6tables -A GEOBLOCK -s 2001:db8::/32 -j DROP",
+            "ip6tables -A GEOBLOCK_OUT -d 2001:db8:1::7 -j ACCEPT",
+            "ip6tables -I INPUT -j GEOBLOCK",
+        ] {
+            assert!(ipt.lines().any(|line| line == rule), "no `{rule}`");
+        }
+        assert!(!ipt.contains("iptables -A GEOBLOCK -s 2001:"));
+
+        let pf = generate_pf(&state).unwrap();
+        assert!(pf.contains("table <blocked> { 198.51.100.0/24, 2001:db8::/32, 2001:db8:ffff::1 }"));
+
+        for firewall in lint::Firewall::ALL {
+            let rules = firewall.generate(&state).unwrap();
+            assert_eq!(lint::lint(firewall, &rules), [], "{firewall}");
+        }
+    }
+
+    #[test]
+    fn dry_run_shows_rule_delta() {
+        let before = State {
+            blocked_ips: vec!["198.51.100.7".into()],
+            ..State::default()
+        };
+        let mut after = before.clone();
+        after.blocked_ips.push("2001:db8::/32".into());
+
+        let ipt = rule_changes(lint::Firewall::Iptables, &before, &after).unwrap();
+        assert_eq!(
+            ipt,
+            [RuleChange::Added(
+                "ip6tables -A GEOBLOCK -s 2001:db8::/32 -j DROP".into()
+            )]
+        );
+
+        let pf = rule_changes(lint::Firewall::Pf, &before, &after).unwrap();
+        assert_eq!(
+            pf,
+            [
+                RuleChange::Removed("table <blocked> { 198.51.100.7 }".into()),
+                RuleChange::Added("table <blocked> { 198.51.100.7, 2001:db8::/32 }".into()),
+            ]
+        );
+
+        let mut geo = before.clone();
+        geo.blocked_countries.push("cn".into());
+        let nft = rule_changes(lint::Firewall::Nftables, &before, &geo).unwrap();
+        assert!(nft.iter().all(|c| matches!(c, RuleChange::Added(_))));
+        assert!(nft.contains(&RuleChange::Added(
+            "        ip saddr @country_cn drop".into()
+        )));
+
+        assert_eq!(
+            rule_changes(lint::Firewall::Nftables, &before, &before).unwrap(),
+            []
+        );
+    }
+
+    #[test]
+    fn ban_targets_are_validated() {
+        assert_eq!(parse_target("203.0.113.9").unwrap(), "203.0.113.9");
+        assert_eq!(parse_target("203.0.113.9/24").unwrap(), "203.0.113.0/24");
+        assert_eq!(parse_target("2001:DB8:0:0::1").unwrap(), "2001:db8::1");
+        assert_eq!(parse_target("2001:db8::1/32").unwrap(), "2001:db8::/32");
+        for bad in [
+            "203.0.113.256",
+            "2001:db8::/129",
+            "example.com",
+            "10.0.0.0/",
+        ] {
+            assert!(parse_target(bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Response Policy Zone (RPZ) export of the defense state.
+//!
+//! Renders the same zone i1-srv serves as `rpz.i1.is`, so BIND, Unbound
+//! or Knot Resolver can load the blocklist from a file. Every blocked
+//! address or CIDR becomes an IP trigger (`32.4.3.2.1.rpz-ip`) rewritten
+//! to NXDOMAIN with `CNAME .`; every whitelisted one becomes an
+//! `rpz-passthru.` rule, which wins inside a blocked range because RPZ
+//! prefers the longest matching prefix.
+//!
+//! Blocked countries and ASNs have no prefixes in the state, so there is
+//! nothing to trigger on and they are left out.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::PathBuf;
+
+use super::feeds::Network;
+use super::State;
+
+/// Zone origin used by i1-srv.
+pub const DEFAULT_ORIGIN: &str = "rpz.i1.is.";
+
+/// CNAME target that exempts a match from every policy.
+const PASSTHRU: &str = "rpz-passthru.";
+
+/// TTL for policy rules, matching i1-srv's confirmed DNSBL listings.
+const RULE_TTL: u32 = 86_400;
+
+/// SOA refresh, retry, expire and minimum, as in i1-srv's zones.
+const SOA_TIMERS: (u32, u32, u32, u32) = (3600, 900, 604_800, 300);
+
+/// Render `state` as an RPZ zone file under `origin`.
+pub fn generate_rpz(state: &State, origin: &str, serial: u32) -> String {
+    let passthru = triggers(&state.whitelisted_ips);
+    let blocks = triggers(&state.blocked_ips);
+    let (refresh, retry, expire, minimum) = SOA_TIMERS;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "; i1 blocklist response policy zone");
+    let _ = writeln!(out, "$ORIGIN {origin}");
+    let _ = writeln!(out, "$TTL {RULE_TTL}");
+    let _ = writeln!(
+        out,
+        "@ IN SOA ns1.i1.is. admin.i1.is. {serial} {refresh} {retry} {expire} {minimum}"
+    );
+    let _ = writeln!(out, "@ IN NS localhost.");
+    // A prefix both blocked and whitelisted only gets the passthru
+    for trigger in blocks.iter().filter(|t| !passthru.contains(t)) {
+        let _ = writeln!(out, "{trigger} IN CNAME .");
+    }
+    for trigger in &passthru {
+        let _ = writeln!(out, "{trigger} IN CNAME {PASSTHRU}");
+    }
+    out
+}
+
+/// Unique IP triggers for address or CIDR entries, in input order.
+/// Entries that don't parse are skipped.
+fn triggers(entries: &[String]) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    entries
+        .iter()
+        .filter_map(|entry| Network::parse(entry))
+        .map(|network| ip_trigger(&network))
+        .filter(|trigger| seen.insert(trigger.clone()))
+        .collect()
+}
+
+/// IP trigger owner for a block, relative to the zone origin: the prefix
+/// length, then the network address reversed (IPv4 octets, or IPv6 groups
+/// with the longest zero run written as `zz`).
+fn ip_trigger(network: &Network) -> String {
+    let labels: Vec<String> = match network.addr() {
+        IpAddr::V4(v4) => v4.octets().iter().rev().map(u8::to_string).collect(),
+        IpAddr::V6(v6) => ipv6_labels(&v6),
+    };
+    format!("{}.{}.rpz-ip", network.prefix(), labels.join("."))
+}
+
+/// IPv6 groups, least significant first, with the longest run (of two or
+/// more) of zero groups collapsed to `zz`.
+fn ipv6_labels(v6: &Ipv6Addr) -> Vec<String> {
+    let segments = v6.segments();
+
+    let mut best = (0, 0);
+    let mut run_start = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        if *segment != 0 {
+            run_start = i + 1;
+        } else if i + 1 - run_start > best.1 {
+            best = (run_start, i + 1 - run_start);
+        }
+    }
+
+    let mut labels = Vec::new();
+    let mut i = 0;
+    while i < segments.len() {
+        if best.1 >= 2 && i == best.0 {
+            labels.push("zz".to_string());
+            i += best.1;
+        } else {
+            labels.push(format!("{:x}", segments[i]));
+            i += 1;
+        }
+    }
+    labels.reverse();
+    labels
+}
+
+/// The serial after `previous`, in `YYYYMMDDnn` form: today's first
+/// serial, or one past `previous` if that is already today's or later.
+#[must_use]
+pub fn next_serial(previous: Option<u32>, today: NaiveDate) -> u32 {
+    let first =
+        today.year().unsigned_abs() * 1_000_000 + today.month() * 10_000 + today.day() * 100;
+    previous.map_or(first, |previous| first.max(previous.wrapping_add(1)))
+}
+
+/// Where the last exported serial is kept, next to the state file.
+fn serial_path() -> Result<PathBuf> {
+    Ok(State::path()?.with_file_name("rpz_serial"))
+}
+
+/// Take the next serial and remember it, so every export supersedes the
+/// last one a resolver loaded.
+pub fn bump_serial() -> Result<u32> {
+    let path = serial_path()?;
+    let previous = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| s.trim().parse().ok());
+    let serial = next_serial(previous, chrono::Utc::now().date_naive());
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serial.to_string())?;
+    Ok(serial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_served_zone() {
+        // The fixture i1-srv renders its golden zone file from
+        let state = State {
+            blocked_ips: vec![
+                "1.2.3.4".into(),
+                "203.0.113.0/24".into(),
+                "10.0.0.0/8".into(),
+                "2001:db8::/32".into(),
+                "2001:db8:0:1::1".into(),
+                "not-an-ip".into(),
+                "1.2.3.4".into(),
+            ],
+            whitelisted_ips: vec!["10.1.2.3".into(), "203.0.113.0/24".into()],
+            blocked_countries: vec!["cn".into()],
+            blocked_asns: vec!["AS12345".into()],
+            ..State::default()
+        };
+        assert_eq!(
+            generate_rpz(&state, DEFAULT_ORIGIN, 2_026_010_101),
+            include_str!("../../../i1-srv/testdata/rpz.zone")
+        );
+    }
+
+    #[test]
+    fn serials_always_increase() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(next_serial(None, day), 2_026_101_800);
+        assert_eq!(next_serial(Some(2_026_101_800), day), 2_026_101_801);
+        assert_eq!(next_serial(Some(2_026_010_105), day), 2_026_101_800);
+        // A serial ahead of the clock keeps counting up
+        assert_eq!(next_serial(Some(2_026_120_100), day), 2_026_120_101);
+    }
+}
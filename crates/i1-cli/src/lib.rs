@@ -9,10 +9,15 @@
 //! - **Search**: Query threat intelligence databases
 //! - **Defend module**: Geo-blocking, IP banning, firewall rules
 //! - **Multiple output formats**: Pretty tables, JSON, CSV
+//! - **MISP export**: Push search findings as a MISP event
+//! - **Watch rules**: Local triggers, digests and bans for host changes
 
 pub mod cli;
 pub mod config;
 pub mod defend;
+pub mod logging;
+pub mod misp;
 pub mod output;
+pub mod watch;
 
 pub use cli::run;
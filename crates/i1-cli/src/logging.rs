@@ -0,0 +1,228 @@
+//! Diagnostic output on stderr.
+//!
+//! Command results go to stdout. Everything said along the way (notes,
+//! tips, warnings, provider retry notices) is a tracing event written to
+//! stderr, so a pipeline reading stdout never sees it. With
+//! `--log-format json` each event is one JSON object per line, for log
+//! collectors under systemd or Kubernetes.
+//!
+//! `-v` surfaces debug events from every i1 crate (provider requests,
+//! health checks, consensus lookups) and `-vv` adds trace events and the
+//! HTTP stack's debug output. `--log-level` (or `I1_LOG`) takes filter
+//! directives instead, e.g. `debug` or `warn,i1_shodan=trace`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding filter directives, read by `--log-level`.
+pub const FILTER_ENV: &str = "I1_LOG";
+
+/// How events are written to stderr.
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Plain lines, with warnings and errors labelled
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!("Unknown log format: {s}\nValid formats: text, json"),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Filter directives for the verbosity flags.
+///
+/// By default the CLI's own notes show and library crates only speak up
+/// when something goes wrong. Each `-v` lowers the level a step, and
+/// `--quiet` keeps only warnings and errors.
+#[must_use]
+pub const fn directives(verbose: u8, quiet: bool) -> &'static str {
+    match (quiet, verbose) {
+        (true, _) => "warn",
+        // Targets match by prefix, and `i1_cli` is one of `i1_client`
+        (false, 0) => "warn,i1_cli=info,i1_client=warn",
+        (false, 1) => "warn,i1=debug",
+        (false, _) => "debug,i1=trace",
+    }
+}
+
+/// Install the stderr subscriber, filtered by `directives`.
+pub fn init(format: LogFormat, directives: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| anyhow::anyhow!("Invalid log level `{directives}`: {e}"))?;
+    // Only fails if a subscriber is already set, which is harmless
+    let _ = tracing::subscriber::set_global_default(subscriber(format, filter, std::io::stderr));
+    Ok(())
+}
+
+/// A subscriber writing `format` to `writer`.
+fn subscriber<W>(
+    format: LogFormat,
+    filter: EnvFilter,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(builder.event_format(Text).finish()),
+        LogFormat::Json => Box::new(
+            builder
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .finish(),
+        ),
+    }
+}
+
+/// Text events as the CLI has always printed them: the message and its
+/// fields, labelled when it is a warning or an error.
+struct Text;
+
+impl<S, N> FormatEvent<S, N> for Text
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        match *event.metadata().level() {
+            Level::ERROR => write!(writer, "{} ", "Error:".red().bold())?,
+            Level::WARN => write!(writer, "{} ", "Warning:".yellow().bold())?,
+            Level::INFO => {}
+            Level::DEBUG => write!(writer, "{} ", "debug:".dimmed())?,
+            Level::TRACE => write!(writer, "{} ", "trace:".dimmed())?,
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Stands in for stderr.
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `emit` under a subscriber and return what it wrote.
+    fn capture(format: LogFormat, directives: &str, emit: impl FnOnce()) -> String {
+        let stderr = Capture::default();
+        let writer = stderr.clone();
+        let subscriber = subscriber(format, EnvFilter::new(directives), move || writer.clone());
+        tracing::subscriber::with_default(subscriber, emit);
+        let bytes = stderr.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn chatter() {
+        tracing::info!("Tip: Use --page 2 to see more results (page 1 of 7)");
+        tracing::warn!(target: "i1_shodan", attempt = 2, "rate limited, retrying");
+        tracing::info!(target: "i1_client", ip = "192.0.2.1", "Looking up host");
+        tracing::warn!("certificate count unavailable: \"crt.sh\" said\nno");
+        tracing::debug!(queries = 3, "loaded saved queries");
+        tracing::trace!(target: "i1_shodan", bytes = 512, "response body");
+    }
+
+    #[test]
+    fn json_lines_parse() {
+        let stderr = capture(LogFormat::Json, directives(0, false), chatter);
+        let events: Vec<serde_json::Value> = stderr
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{e}: {line}")))
+            .collect();
+
+        // The library's info and the CLI's debug event are filtered out
+        assert_eq!(events.len(), 3, "{stderr}");
+        assert_eq!(events[0]["level"], "INFO");
+        assert_eq!(
+            events[0]["message"],
+            "Tip: Use --page 2 to see more results (page 1 of 7)"
+        );
+        assert_eq!(events[1]["target"], "i1_shodan");
+        assert_eq!(events[1]["attempt"], 2);
+        assert_eq!(
+            events[2]["message"],
+            "certificate count unavailable: \"crt.sh\" said\nno"
+        );
+        assert!(events.iter().all(|event| event["timestamp"].is_string()));
+    }
+
+    #[test]
+    fn verbosity_sets_the_level() {
+        let count = |directives| {
+            capture(LogFormat::Json, directives, chatter)
+                .lines()
+                .count()
+        };
+        assert_eq!(count(directives(0, true)), 2);
+        assert_eq!(count(directives(1, false)), 5);
+        assert_eq!(count(directives(2, false)), 6);
+        assert_eq!(count(directives(3, false)), 6);
+        assert_eq!(count(directives(1, true)), 2);
+        assert_eq!(count("error"), 0);
+    }
+
+    #[test]
+    fn text_labels_warnings() {
+        let stderr = capture(LogFormat::Text, directives(0, false), chatter);
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Tip: Use --page 2 to see more results (page 1 of 7)"
+        );
+        assert!(lines[1].contains("Warning:"));
+        assert!(lines[1].ends_with(" rate limited, retrying attempt=2"));
+    }
+}
@@ -0,0 +1,194 @@
+//! MISP integration.
+//!
+//! Turns a set of findings into a single MISP event and pushes it through
+//! the MISP REST API. Each host contributes:
+//!
+//! - `ip-dst` for the address
+//! - `ip-dst|port` for each open port
+//! - `vulnerability` for each CVE, on the host or on any of its services
+//! - `hostname` and `domain` for its names
+//!
+//! Attributes are deduplicated within the event, so overlapping results
+//! (the same host from several pages, or a CVE on several services) are
+//! only sent once.
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context as _, Result};
+use i1_core::HostInfo;
+use serde::{Deserialize, Serialize};
+
+/// Share with your organisation only.
+const DISTRIBUTION_ORG: u8 = 0;
+/// Threat level "undefined"; an analyst sets the real one on review.
+const THREAT_LEVEL_UNDEFINED: u8 = 4;
+/// Analysis state "initial".
+const ANALYSIS_INITIAL: u8 = 0;
+
+/// A MISP event, ready to push.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    /// Event title
+    pub info: String,
+    distribution: u8,
+    threat_level_id: u8,
+    analysis: u8,
+    /// Deduplicated attributes
+    #[serde(rename = "Attribute")]
+    pub attributes: Vec<Attribute>,
+}
+
+/// A single MISP attribute.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Attribute {
+    /// MISP category (e.g. "Network activity")
+    pub category: &'static str,
+    /// MISP type (e.g. "ip-dst")
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// Attribute value
+    pub value: String,
+    /// Whether IDS rules should be generated from this attribute
+    pub to_ids: bool,
+}
+
+impl Attribute {
+    const fn new(category: &'static str, kind: &'static str, value: String, to_ids: bool) -> Self {
+        Self {
+            category,
+            kind,
+            value,
+            to_ids,
+        }
+    }
+}
+
+impl Event {
+    /// Build an event titled `info` from `findings`.
+    pub fn from_findings(info: impl Into<String>, findings: &[HostInfo]) -> Self {
+        let mut attributes = BTreeSet::new();
+        let mut seen = BTreeSet::new();
+
+        for host in findings {
+            let ip = host
+                .ip_addr()
+                .map_or_else(|| host.ip_str.clone(), |ip| ip.to_string());
+            if ip.is_empty() {
+                continue;
+            }
+
+            let mut add = |attribute: Attribute| {
+                // Keep the first to_ids flag seen for a value.
+                if seen.insert((attribute.kind, attribute.value.clone())) {
+                    attributes.insert(attribute);
+                }
+            };
+
+            add(Attribute::new(
+                "Network activity",
+                "ip-dst",
+                ip.clone(),
+                true,
+            ));
+            for port in &host.ports {
+                add(Attribute::new(
+                    "Network activity",
+                    "ip-dst|port",
+                    format!("{ip}|{port}"),
+                    false,
+                ));
+            }
+            let cves = host
+                .vulns
+                .iter()
+                .chain(host.data.iter().flat_map(|service| service.vulns.keys()));
+            for cve in cves {
+                add(Attribute::new(
+                    "External analysis",
+                    "vulnerability",
+                    cve.to_uppercase(),
+                    false,
+                ));
+            }
+            for hostname in &host.hostnames {
+                add(Attribute::new(
+                    "Network activity",
+                    "hostname",
+                    hostname.to_lowercase(),
+                    false,
+                ));
+            }
+            for domain in &host.domains {
+                add(Attribute::new(
+                    "Network activity",
+                    "domain",
+                    domain.to_lowercase(),
+                    false,
+                ));
+            }
+        }
+
+        Self {
+            info: info.into(),
+            distribution: DISTRIBUTION_ORG,
+            threat_level_id: THREAT_LEVEL_UNDEFINED,
+            analysis: ANALYSIS_INITIAL,
+            attributes: attributes.into_iter().collect(),
+        }
+    }
+}
+
+/// The event as stored by MISP.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatedEvent {
+    /// Numeric event ID on the MISP instance
+    pub id: String,
+    /// Event UUID
+    pub uuid: String,
+}
+
+#[derive(Serialize)]
+struct EventRequest<'a> {
+    #[serde(rename = "Event")]
+    event: &'a Event,
+}
+
+#[derive(Deserialize)]
+struct EventResponse {
+    #[serde(rename = "Event")]
+    event: CreatedEvent,
+}
+
+/// Create a MISP event from `findings` on the instance at `url`.
+pub async fn push_event(url: &str, key: &str, findings: &[HostInfo]) -> Result<CreatedEvent> {
+    let event = Event::from_findings(format!("i1 findings ({} hosts)", findings.len()), findings);
+    push(url, key, &event).await
+}
+
+/// Create `event` on the MISP instance at `url`, authenticating with `key`.
+pub async fn push(url: &str, key: &str, event: &Event) -> Result<CreatedEvent> {
+    let endpoint = format!("{}/events/add", url.trim_end_matches('/'));
+    let response = reqwest::Client::new()
+        .post(&endpoint)
+        .header(reqwest::header::AUTHORIZATION, key)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .json(&EventRequest { event })
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach MISP at {endpoint}"))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        anyhow::bail!("MISP rejected the auth key ({status}). Check misp-key.");
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("MISP returned {status}: {}", body.trim());
+    }
+
+    let created: EventResponse = response
+        .json()
+        .await
+        .context("Unexpected response from MISP")?;
+    Ok(created.event)
+}
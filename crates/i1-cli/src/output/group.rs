@@ -0,0 +1,167 @@
+//! Collapsing search matches per host.
+//!
+//! A host with a dozen services can come back as a dozen matches, and with
+//! `--all` the same host can appear on several pages. These work on the
+//! typed [`SearchResults`], so any command that produces them can group.
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use i1_core::{parse_timestamp, HostInfo};
+use i1_providers::SearchResults;
+
+/// Keys search results can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// One row per IP address
+    Ip,
+}
+
+/// Every match for one IP, collapsed into a row.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HostGroup {
+    pub ip: String,
+    /// Open ports across all matches, ascending.
+    pub ports: Vec<u16>,
+    /// First organization reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+    /// First country code reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Most recent scan or banner timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Matches folded into this row.
+    pub matches: usize,
+}
+
+/// Search results grouped per host.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupedResults {
+    pub provider: String,
+    /// Matches the provider reported for the query, across all pages.
+    pub total_matches: u64,
+    /// Matches fetched and grouped here.
+    pub fetched_matches: usize,
+    /// Distinct hosts among the fetched matches.
+    pub unique_hosts: usize,
+    pub hosts: Vec<HostGroup>,
+}
+
+/// Matches a result stands for: one per banner, at least one.
+fn match_count(host: &HostInfo) -> usize {
+    host.data.len().max(1)
+}
+
+/// Most recent of the host's scan time and its banners' timestamps.
+fn last_seen(host: &HostInfo) -> Option<DateTime<Utc>> {
+    host.data
+        .iter()
+        .filter_map(|service| service.timestamp.as_deref().and_then(parse_timestamp))
+        .chain(host.last_update)
+        .max()
+}
+
+/// Collapse `results` into one row per IP, in order of first appearance.
+#[must_use]
+pub fn group_by_ip(results: &SearchResults) -> GroupedResults {
+    let mut hosts: Vec<HostGroup> = Vec::new();
+    let mut ports: Vec<BTreeSet<u16>> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for host in &results.results {
+        let at = *index.entry(host.ip_str.as_str()).or_insert_with(|| {
+            hosts.push(HostGroup {
+                ip: host.ip_str.clone(),
+                ports: Vec::new(),
+                org: None,
+                country: None,
+                last_seen: None,
+                matches: 0,
+            });
+            ports.push(BTreeSet::new());
+            hosts.len() - 1
+        });
+        let group = &mut hosts[at];
+        ports[at].extend(&host.ports);
+        ports[at].extend(host.data.iter().map(|service| service.port));
+        group.org = group.org.take().or_else(|| host.org.clone());
+        group.country = group
+            .country
+            .take()
+            .or_else(|| host.location.country_code.clone());
+        group.last_seen = group.last_seen.max(last_seen(host));
+        group.matches += match_count(host);
+    }
+    for (group, ports) in hosts.iter_mut().zip(ports) {
+        group.ports = ports.into_iter().collect();
+    }
+
+    GroupedResults {
+        provider: results.provider.clone(),
+        total_matches: results.total,
+        fetched_matches: results.results.iter().map(match_count).sum(),
+        unique_hosts: hosts.len(),
+        hosts,
+    }
+}
+
+/// Keep only the first result for each IP.
+pub fn unique_ips(results: &mut SearchResults) {
+    let mut seen = HashSet::new();
+    results
+        .results
+        .retain(|host| seen.insert(host.ip_str.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn results() -> SearchResults {
+        serde_json::from_value(json!({
+            "provider": "shodan",
+            "total": 40,
+            "page": 1,
+            "results": [
+                {"ip_str": "1.1.1.1", "ports": [443], "org": "Example", "country_code": "AU",
+                 "data": [{"port": 443, "timestamp": "2024-01-02T00:00:00.000000"},
+                          {"port": 80, "timestamp": "2024-03-04T05:06:07.000000"}]},
+                {"ip_str": "2.2.2.2", "ports": [22]},
+                {"ip_str": "1.1.1.1", "ports": [8443], "country_code": "NZ",
+                 "last_update": "2024-02-01T00:00:00.000000"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn matches_collapse_per_ip() {
+        let grouped = group_by_ip(&results());
+        assert_eq!(grouped.total_matches, 40);
+        assert_eq!(grouped.fetched_matches, 4);
+        assert_eq!(grouped.unique_hosts, 2);
+
+        let first = &grouped.hosts[0];
+        assert_eq!(first.ip, "1.1.1.1");
+        assert_eq!(first.ports, [80, 443, 8443]);
+        assert_eq!(first.org.as_deref(), Some("Example"));
+        assert_eq!(first.country.as_deref(), Some("AU"));
+        assert_eq!(first.matches, 3);
+        assert_eq!(
+            first.last_seen.map(|t| t.to_rfc3339()),
+            Some("2024-03-04T05:06:07+00:00".into())
+        );
+        assert_eq!(grouped.hosts[1].ports, [22]);
+
+        let mut unique = results();
+        unique_ips(&mut unique);
+        let ips: Vec<_> = unique.results.iter().map(|h| h.ip_str.as_str()).collect();
+        assert_eq!(ips, ["1.1.1.1", "2.2.2.2"]);
+        assert_eq!(unique.results[0].ports, [443]);
+    }
+}
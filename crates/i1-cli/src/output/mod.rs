@@ -1,5 +1,8 @@
 //! Output formatting for different formats.
 
+pub mod group;
+pub mod render;
+
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -17,6 +20,10 @@ pub enum OutputFormat {
     Csv,
     /// YAML output
     Yaml,
+    /// Newline-delimited JSON, one row per line
+    Ndjson,
+    /// STIX 2.1 bundle (host and search)
+    Stix,
 }
 
 impl FromStr for OutputFormat {
@@ -28,9 +35,11 @@ impl FromStr for OutputFormat {
             "json" => Ok(Self::Json),
             "csv" => Ok(Self::Csv),
             "yaml" | "yml" => Ok(Self::Yaml),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            "stix" => Ok(Self::Stix),
             _ => anyhow::bail!(
                 "Unknown output format: {s}\n\
-                 Valid formats: pretty, json, csv, yaml"
+                 Valid formats: pretty, json, csv, yaml, ndjson, stix"
             ),
         }
     }
@@ -43,6 +52,8 @@ impl std::fmt::Display for OutputFormat {
             Self::Json => write!(f, "json"),
             Self::Csv => write!(f, "csv"),
             Self::Yaml => write!(f, "yaml"),
+            Self::Ndjson => write!(f, "ndjson"),
+            Self::Stix => write!(f, "stix"),
         }
     }
 }
@@ -0,0 +1,659 @@
+//! Rendering command output in every format from one description.
+//!
+//! A command serializes its typed result into a [`Document`] and lays out
+//! the pretty view as sections; [`Renderer`] emits pretty text, JSON, YAML,
+//! CSV or NDJSON from it. The machine formats all read the same serialized
+//! data, and pretty tables read the same rows as CSV, so the formats can't
+//! drift apart. `--fields` picks keys from those rows the same way in
+//! every format, with dots reaching into nested objects
+//! (`location.country_code`) and across lists (`data.port`).
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use colored::Colorize;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use serde_json::Value;
+use tabled::builder::Builder;
+use tabled::settings::peaker::PriorityMax;
+use tabled::settings::{Style, Width};
+
+use super::OutputFormat;
+use crate::cli::commands::Context;
+
+/// A column: the key its cells are read from, and its pretty heading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    /// Dotted path into each row
+    pub key: String,
+    /// Heading in pretty output
+    pub header: String,
+}
+
+impl Column {
+    pub fn new(key: impl Into<String>, header: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            header: header.into(),
+        }
+    }
+}
+
+/// Rows of serialized records, shown through `columns`.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Value>,
+    /// Pretty output shows at most this many rows, and what they were.
+    limit: Option<(usize, String)>,
+    /// Pretty output shows this instead of an empty table.
+    empty: Option<String>,
+}
+
+impl Table {
+    /// A table of `records`, serialized.
+    pub fn new<T: Serialize>(
+        columns: Vec<Column>,
+        records: impl IntoIterator<Item = T>,
+    ) -> Result<Self> {
+        let rows = records
+            .into_iter()
+            .map(|record| serde_json::to_value(record))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            columns,
+            rows,
+            limit: None,
+            empty: None,
+        })
+    }
+
+    /// Show at most `limit` rows in pretty output, then how many more
+    /// `noun` there are.
+    #[must_use]
+    pub fn limit(mut self, limit: usize, noun: &str) -> Self {
+        self.limit = Some((limit, noun.to_string()));
+        self
+    }
+
+    /// Show `message` in pretty output when there are no rows.
+    #[must_use]
+    pub fn empty(mut self, message: &str) -> Self {
+        self.empty = Some(message.to_string());
+        self
+    }
+}
+
+/// One part of a document's pretty view.
+#[derive(Debug, Clone)]
+enum Section {
+    /// `Label: value` lines, skipping empty values
+    Fields {
+        title: Option<String>,
+        fields: Vec<(String, String)>,
+    },
+    /// A table with an optional heading
+    Table { title: Option<String>, table: Table },
+    /// A heading and one `- item` line each; `Heading: empty` without items
+    List {
+        title: String,
+        items: Vec<String>,
+        empty: Option<String>,
+    },
+    /// A dimmed line
+    Note(String),
+}
+
+/// A command's result: its serialized data, the rows CSV and NDJSON emit,
+/// and how the pretty view lays it out.
+#[derive(Debug, Clone)]
+pub struct Document {
+    data: Value,
+    /// `data` as JSON and YAML, keeping its fields' declared order
+    json: String,
+    yaml: String,
+    /// Path to the array of rows in `data`; without one, the rows are
+    /// `data`'s items if it is an array, else `data` itself.
+    rows: Option<String>,
+    columns: Vec<Column>,
+    sections: Vec<Section>,
+}
+
+impl Document {
+    /// A document of `data`, whose rows (for CSV, NDJSON and `--fields`)
+    /// are `data`'s items if it is a list, else `data` itself, shown
+    /// through `columns`.
+    pub fn new(data: &impl Serialize, columns: Vec<Column>) -> Result<Self> {
+        Ok(Self {
+            data: serde_json::to_value(data)?,
+            json: format!("{}\n", serde_json::to_string_pretty(data)?),
+            yaml: serde_yaml::to_string(data)?,
+            rows: None,
+            columns,
+            sections: Vec::new(),
+        })
+    }
+
+    /// Read rows from the array at `path` in the data instead.
+    #[must_use]
+    pub fn rows_at(mut self, path: &str) -> Self {
+        self.rows = Some(path.to_string());
+        self
+    }
+
+    /// The document's rows.
+    fn rows(&self) -> Vec<Value> {
+        match (&self.rows, &self.data) {
+            (Some(path), _) => match lookup(&self.data, path) {
+                Value::Array(rows) => rows,
+                _ => Vec::new(),
+            },
+            (None, Value::Array(rows)) => rows.clone(),
+            (None, data) => vec![data.clone()],
+        }
+    }
+
+    /// The document's rows as a table, to lay out in pretty output.
+    #[must_use]
+    pub fn rows_table(&self) -> Table {
+        Table {
+            columns: self.columns.clone(),
+            rows: self.rows(),
+            limit: None,
+            empty: None,
+        }
+    }
+
+    /// Add `Label: value` lines under an optional heading.
+    #[must_use]
+    pub fn fields(mut self, title: Option<&str>, fields: Vec<(&str, String)>) -> Self {
+        self.sections.push(Section::Fields {
+            title: title.map(String::from),
+            fields: fields
+                .into_iter()
+                .map(|(label, value)| (label.to_string(), value))
+                .collect(),
+        });
+        self
+    }
+
+    /// Add a table under an optional heading.
+    #[must_use]
+    pub fn table(mut self, title: Option<&str>, table: Table) -> Self {
+        self.sections.push(Section::Table {
+            title: title.map(String::from),
+            table,
+        });
+        self
+    }
+
+    /// Add a list of items under a heading; without items, the heading
+    /// is followed by `empty`, or left out if there is none.
+    #[must_use]
+    pub fn list(mut self, title: &str, items: Vec<String>, empty: Option<&str>) -> Self {
+        self.sections.push(Section::List {
+            title: title.to_string(),
+            items,
+            empty: empty.map(String::from),
+        });
+        self
+    }
+
+    /// Add a dimmed line.
+    #[must_use]
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.sections.push(Section::Note(note.into()));
+        self
+    }
+}
+
+/// Turns documents and tables into output text.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    format: OutputFormat,
+    fields: Option<Vec<String>>,
+    color: bool,
+    /// Pretty tables are truncated to this many columns.
+    width: Option<usize>,
+}
+
+impl Renderer {
+    /// A renderer for `format`, with color, fitting pretty tables to the
+    /// terminal when stdout is one.
+    #[must_use]
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            fields: None,
+            color: true,
+            width: console::Term::stdout()
+                .size_checked()
+                .map(|(_, columns)| usize::from(columns)),
+        }
+    }
+
+    /// A renderer for the command line's `--output`, `--fields` and
+    /// `--no-color`.
+    #[must_use]
+    pub fn from_context(ctx: &Context) -> Self {
+        Self::new(ctx.output_format)
+            .with_fields(ctx.fields.clone())
+            .with_color(!ctx.no_color)
+    }
+
+    /// Only emit these keys of each row, in this order.
+    #[must_use]
+    pub fn with_fields(mut self, fields: Option<Vec<String>>) -> Self {
+        self.fields = fields.filter(|fields| !fields.is_empty());
+        self
+    }
+
+    #[must_use]
+    pub const fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Truncate pretty tables to `width` columns (None: never).
+    #[must_use]
+    pub const fn with_width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Render `document`.
+    ///
+    /// # Errors
+    ///
+    /// Fails for STIX, which commands render themselves, or if the
+    /// output can't be encoded.
+    pub fn document(&self, document: &Document) -> Result<String> {
+        let Some(fields) = &self.fields else {
+            return match self.format {
+                OutputFormat::Pretty => Ok(self.pretty_document(document)),
+                OutputFormat::Csv => csv(&document.columns, &document.rows()),
+                OutputFormat::Ndjson => ndjson(&document.rows()),
+                OutputFormat::Json => Ok(document.json.clone()),
+                OutputFormat::Yaml => Ok(document.yaml.clone()),
+                OutputFormat::Stix => self.data(&document.data),
+            };
+        };
+        let columns = field_columns(fields, &document.columns);
+        let rows = document.rows();
+        match (self.format, &document.rows, rows.first()) {
+            // A single-row document stays one object
+            (OutputFormat::Json | OutputFormat::Yaml, None, Some(row))
+                if !document.data.is_array() =>
+            {
+                self.data(&Selected {
+                    columns: &columns,
+                    row,
+                })
+            }
+            _ => self.selected(&columns, rows),
+        }
+    }
+
+    /// Render `table` on its own; JSON and YAML are the array of rows.
+    ///
+    /// # Errors
+    ///
+    /// Fails for STIX, which commands render themselves, or if the
+    /// output can't be encoded.
+    pub fn table(&self, table: &Table) -> Result<String> {
+        let Some(fields) = &self.fields else {
+            return match self.format {
+                OutputFormat::Pretty => Ok(self.pretty_table(table)),
+                OutputFormat::Csv => csv(&table.columns, &table.rows),
+                OutputFormat::Ndjson => ndjson(&table.rows),
+                _ => self.data(&table.rows),
+            };
+        };
+        let columns = field_columns(fields, &table.columns);
+        self.selected(&columns, table.rows.clone())
+    }
+
+    /// Print `document` to stdout.
+    pub fn print_document(&self, document: &Document) -> Result<()> {
+        println!("{}", self.document(document)?.trim_end());
+        Ok(())
+    }
+
+    /// Rows narrowed to `columns` in any format, as a table when pretty.
+    fn selected(&self, columns: &[Column], rows: Vec<Value>) -> Result<String> {
+        match self.format {
+            OutputFormat::Pretty => Ok(self.pretty_table(&Table {
+                columns: columns.to_vec(),
+                rows,
+                limit: None,
+                empty: None,
+            })),
+            OutputFormat::Csv => csv(columns, &rows),
+            _ => {
+                let rows: Vec<Selected> =
+                    rows.iter().map(|row| Selected { columns, row }).collect();
+                match self.format {
+                    OutputFormat::Ndjson => ndjson(&rows),
+                    _ => self.data(&rows),
+                }
+            }
+        }
+    }
+
+    /// `data` as JSON or YAML.
+    fn data(&self, data: &impl Serialize) -> Result<String> {
+        match self.format {
+            OutputFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(data)?)),
+            OutputFormat::Yaml => Ok(serde_yaml::to_string(data)?),
+            OutputFormat::Stix => anyhow::bail!("STIX output is not available here"),
+            OutputFormat::Pretty | OutputFormat::Csv | OutputFormat::Ndjson => {
+                unreachable!("only JSON and YAML are rendered as data")
+            }
+        }
+    }
+
+    fn pretty_document(&self, document: &Document) -> String {
+        let mut out = String::new();
+        for section in &document.sections {
+            let text = match section {
+                Section::Fields { title, fields } => {
+                    let mut text = title
+                        .as_deref()
+                        .map(|title| format!("{}\n", self.heading(title)))
+                        .unwrap_or_default();
+                    let indent = if title.is_some() { "  " } else { "" };
+                    for (label, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+                        let _ = writeln!(text, "{indent}{} {value}", self.label(label));
+                    }
+                    text
+                }
+                Section::Table { title, table } => {
+                    let heading = title
+                        .as_deref()
+                        .map(|title| format!("{}\n", self.heading(title)))
+                        .unwrap_or_default();
+                    format!("{heading}{}", self.pretty_table(table))
+                }
+                Section::List {
+                    title,
+                    items,
+                    empty,
+                } => match (items.is_empty(), empty) {
+                    (true, None) => String::new(),
+                    (true, Some(empty)) => {
+                        let line = format!("{} {empty}", self.label(title));
+                        format!("{}\n", line.trim_end())
+                    }
+                    (false, _) => {
+                        let mut text = format!("{}\n", self.label(title));
+                        for item in items {
+                            let _ = writeln!(text, "  - {item}");
+                        }
+                        text
+                    }
+                },
+                Section::Note(note) => format!("{}\n", self.dimmed(note)),
+            };
+            if text.is_empty() {
+                continue;
+            }
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&text);
+        }
+        out
+    }
+
+    fn pretty_table(&self, table: &Table) -> String {
+        if table.rows.is_empty() {
+            return table
+                .empty
+                .as_ref()
+                .map(|empty| format!("{empty}\n"))
+                .unwrap_or_default();
+        }
+        let shown = table.limit.as_ref().map_or(table.rows.len(), |(n, _)| *n);
+        let mut builder = Builder::default();
+        builder.push_record(table.columns.iter().map(|c| c.header.clone()));
+        for row in table.rows.iter().take(shown) {
+            builder.push_record(
+                table
+                    .columns
+                    .iter()
+                    .map(|c| display(&lookup(row, &c.key), ", ")),
+            );
+        }
+        let mut grid = builder.build();
+        grid.with(Style::rounded());
+        if let Some(width) = self.width {
+            grid.with(Width::truncate(width).suffix("…").priority(PriorityMax));
+        }
+        let mut text = format!("{grid}\n");
+        if let Some((limit, noun)) = &table.limit {
+            if table.rows.len() > *limit {
+                let more = format!("... and {} more {noun}", table.rows.len() - limit);
+                let _ = writeln!(text, "\n{}", self.dimmed(&more));
+            }
+        }
+        text
+    }
+
+    fn heading(&self, text: &str) -> String {
+        if self.color {
+            text.bold().underline().to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn label(&self, text: &str) -> String {
+        if self.color {
+            text.bold().to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn dimmed(&self, text: &str) -> String {
+        if self.color {
+            text.dimmed().to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// The value at dotted `path` in `value`; a path through a list collects
+/// the value from each element.
+fn lookup(value: &Value, path: &str) -> Value {
+    let mut current = value.clone();
+    for key in path.split('.') {
+        current = match current {
+            Value::Object(mut map) => map.remove(key).unwrap_or(Value::Null),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| lookup(item, key))
+                    .filter(|v| !v.is_null())
+                    .collect(),
+            ),
+            _ => Value::Null,
+        };
+    }
+    current
+}
+
+/// A row narrowed to `--fields`: each column's value keyed by the field
+/// as given, in the order given.
+struct Selected<'a> {
+    columns: &'a [Column],
+    row: &'a Value,
+}
+
+impl Serialize for Selected<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            map.serialize_entry(&column.key, &lookup(self.row, &column.key))?;
+        }
+        map.end()
+    }
+}
+
+/// Columns for `--fields` on selected rows, keeping known headings.
+fn field_columns(fields: &[String], known: &[Column]) -> Vec<Column> {
+    fields
+        .iter()
+        .map(|field| {
+            let header = known
+                .iter()
+                .find(|c| c.key == *field)
+                .map_or_else(|| field.clone(), |c| c.header.clone());
+            Column::new(field.clone(), header)
+        })
+        .collect()
+}
+
+/// A cell's text: strings bare, lists joined with `separator`.
+fn display(value: &Value, separator: &str) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| display(item, separator))
+            .collect::<Vec<_>>()
+            .join(separator),
+        other => other.to_string(),
+    }
+}
+
+/// Rows as CSV, with the column keys as the header and lists joined by `;`.
+fn csv(columns: &[Column], rows: &[Value]) -> Result<String> {
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns.iter().map(|c| c.key.as_str()))?;
+    for row in rows {
+        writer.write_record(columns.iter().map(|c| display(&lookup(row, &c.key), ";")))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// One compact JSON object per line.
+fn ndjson(rows: &[impl Serialize]) -> Result<String> {
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&serde_json::to_string(row)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Every format of a document, for golden tests.
+#[cfg(test)]
+pub(crate) fn all_formats(document: &Document, fields: Option<&[&str]>) -> String {
+    let fields = fields.map(|fields| fields.iter().map(ToString::to_string).collect());
+    let mut out = String::new();
+    for format in [
+        OutputFormat::Pretty,
+        OutputFormat::Json,
+        OutputFormat::Yaml,
+        OutputFormat::Csv,
+        OutputFormat::Ndjson,
+    ] {
+        let renderer = Renderer::new(format)
+            .with_color(false)
+            .with_width(Some(80))
+            .with_fields(fields.clone());
+        let _ = writeln!(out, "=== {format}");
+        out.push_str(&renderer.document(document).unwrap());
+    }
+    out
+}
+
+/// Compare `actual` with `testdata/render/<name>`; regenerate with
+/// `I1_BLESS=1` after an intended change.
+#[cfg(test)]
+pub(crate) fn assert_golden(name: &str, actual: &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("testdata/render")
+        .join(name);
+    if std::env::var_os("I1_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert_eq!(actual, expected, "{} is out of date", path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document() -> Document {
+        let data = json!({
+            "total": 2,
+            "hosts": [
+                {"ip": "192.0.2.1", "ports": [22, 443], "org": "Example, Inc.",
+                 "location": {"country_code": "NL"}},
+                {"ip": "198.51.100.7", "ports": [], "org": null,
+                 "location": {"country_code": "AU"}}
+            ]
+        });
+        let columns = vec![
+            Column::new("ip", "IP"),
+            Column::new("ports", "Ports"),
+            Column::new("org", "Org"),
+            Column::new("location.country_code", "Country"),
+        ];
+        let document = Document::new(&data, columns).unwrap().rows_at("hosts");
+        let table = document.rows_table().limit(1, "hosts");
+        document
+            .fields(
+                None,
+                vec![("Total:", "2".into()), ("Skipped:", String::new())],
+            )
+            .table(Some("Hosts:"), table)
+            .list("Tags:", Vec::new(), Some("None"))
+            .note("Use --page 2 for more")
+    }
+
+    #[test]
+    fn golden_formats() {
+        assert_golden("document.txt", &all_formats(&document(), None));
+    }
+
+    #[test]
+    fn fields_select_keys_in_every_format() {
+        let out = all_formats(&document(), Some(&["location.country_code", "ip"]));
+        assert_golden("document_fields.txt", &out);
+    }
+
+    #[test]
+    fn lookup_walks_objects_and_lists() {
+        let host = json!({"data": [{"port": 22}, {"port": 80}], "location": {"city": "Oslo"}});
+        assert_eq!(lookup(&host, "data.port"), json!([22, 80]));
+        assert_eq!(lookup(&host, "location.city"), json!("Oslo"));
+        assert_eq!(lookup(&host, "location.nope"), Value::Null);
+    }
+
+    #[test]
+    fn wide_tables_fit_the_terminal() {
+        let table = Table::new(
+            vec![Column::new("banner", "Banner")],
+            [json!({"banner": "x".repeat(200)})],
+        )
+        .unwrap();
+        let renderer = Renderer::new(OutputFormat::Pretty)
+            .with_color(false)
+            .with_width(Some(40));
+        let text = renderer.table(&table).unwrap();
+        assert!(
+            text.lines().all(|line| line.chars().count() <= 40),
+            "{text}"
+        );
+        assert!(text.contains('…'));
+    }
+}
@@ -0,0 +1,183 @@
+//! Local rules for watching hosts: what to do when one changes.
+//!
+//! Shodan's alert triggers are fixed and their thresholds can't be tuned,
+//! but two polls of a host already hold everything needed to do better.
+//! The difference between them ([`HostDiff`]) is run through rules read
+//! from a TOML file ([`rules`]), and each match notifies a webhook, runs a
+//! command or bans the host ([`notify`]). Notifications are rate limited,
+//! and low-severity rules batch their findings into a daily digest.
+
+pub mod notify;
+pub mod rules;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use i1_core::HostInfo;
+
+/// One difference between two polls of a host.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    /// A port that wasn't open before.
+    NewPort {
+        port: u16,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        product: Option<String>,
+        /// Highest CVSS score among the service's vulnerabilities.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cvss: Option<f64>,
+    },
+    /// A port that is no longer open.
+    ClosedPort { port: u16 },
+    /// A vulnerability that wasn't reported before.
+    NewVuln {
+        cve: String,
+        /// Port of the service it was reported on, if any.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cvss: Option<f64>,
+    },
+}
+
+impl Change {
+    /// Port the change concerns, if any.
+    #[must_use]
+    pub const fn port(&self) -> Option<u16> {
+        match self {
+            Self::NewPort { port, .. } | Self::ClosedPort { port } => Some(*port),
+            Self::NewVuln { port, .. } => *port,
+        }
+    }
+
+    /// CVSS score attached to the change, if any.
+    #[must_use]
+    pub const fn cvss(&self) -> Option<f64> {
+        match self {
+            Self::NewPort { cvss, .. } | Self::NewVuln { cvss, .. } => *cvss,
+            Self::ClosedPort { .. } => None,
+        }
+    }
+}
+
+/// Everything that changed on a host between two polls.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostDiff {
+    pub ip: String,
+    pub changes: Vec<Change>,
+}
+
+impl HostDiff {
+    /// Compare two polls of the same host.
+    #[must_use]
+    pub fn between(before: &HostInfo, after: &HostInfo) -> Self {
+        let old_ports = open_ports(before);
+        let new_ports = open_ports(after);
+        let old_vulns: BTreeSet<&str> = vulns(before).into_keys().collect();
+
+        let mut changes = Vec::new();
+        for &port in new_ports.difference(&old_ports) {
+            let services = after.data.iter().filter(|s| s.port == port);
+            changes.push(Change::NewPort {
+                port,
+                product: services.clone().find_map(|s| s.product.clone()),
+                cvss: max_cvss(services.flat_map(|s| s.vulns.values().map(|v| v.cvss))),
+            });
+        }
+        for &port in old_ports.difference(&new_ports) {
+            changes.push(Change::ClosedPort { port });
+        }
+        for (cve, (port, cvss)) in vulns(after) {
+            if !old_vulns.contains(cve) {
+                changes.push(Change::NewVuln {
+                    cve: cve.to_string(),
+                    port,
+                    cvss,
+                });
+            }
+        }
+
+        Self {
+            ip: after.ip_str.clone(),
+            changes,
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Ports listed for the host or seen in its banners.
+fn open_ports(host: &HostInfo) -> BTreeSet<u16> {
+    host.ports
+        .iter()
+        .copied()
+        .chain(host.data.iter().map(|s| s.port))
+        .collect()
+}
+
+/// Every CVE reported for the host, with the port and highest CVSS score
+/// it was reported with.
+fn vulns(host: &HostInfo) -> BTreeMap<&str, (Option<u16>, Option<f64>)> {
+    let mut found: BTreeMap<&str, (Option<u16>, Option<f64>)> = host
+        .vulns
+        .iter()
+        .map(|cve| (cve.as_str(), (None, None)))
+        .collect();
+    for service in &host.data {
+        for (cve, info) in &service.vulns {
+            let entry = found.entry(cve.as_str()).or_default();
+            entry.0 = entry.0.or(Some(service.port));
+            entry.1 = max_cvss([entry.1, info.cvss]);
+        }
+    }
+    found
+}
+
+fn max_cvss(scores: impl IntoIterator<Item = Option<f64>>) -> Option<f64> {
+    scores.into_iter().flatten().reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn host(value: serde_json::Value) -> HostInfo {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn diff_finds_ports_and_vulns() {
+        let before = host(json!({"ip_str": "192.0.2.1", "ports": [22, 80]}));
+        let after = host(json!({
+            "ip_str": "192.0.2.1",
+            "ports": [22, 3389],
+            "vulns": ["CVE-2019-0708"],
+            "data": [{"port": 3389, "product": "Remote Desktop",
+                      "vulns": {"CVE-2019-0708": {"cvss": 9.8}}}]
+        }));
+
+        let diff = HostDiff::between(&before, &after);
+        assert_eq!(
+            diff.changes,
+            [
+                Change::NewPort {
+                    port: 3389,
+                    product: Some("Remote Desktop".into()),
+                    cvss: Some(9.8),
+                },
+                Change::ClosedPort { port: 80 },
+                Change::NewVuln {
+                    cve: "CVE-2019-0708".into(),
+                    port: Some(3389),
+                    cvss: Some(9.8),
+                },
+            ]
+        );
+        assert!(HostDiff::between(&after, &after).is_empty());
+    }
+}
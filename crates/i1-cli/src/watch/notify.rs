@@ -0,0 +1,365 @@
+//! Turning rule matches into notifications and bans.
+//!
+//! [`Engine`] keeps what has to outlive a single poll: how many
+//! notifications each rule sent in the last hour, and the findings
+//! waiting for the daily digest. Bans are never held back; a notification
+//! over its rule's hourly limit joins the digest rather than being lost.
+
+use anyhow::{Context as _, Result};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::rules::{Action, RuleSet};
+use super::{Change, HostDiff};
+use crate::defend::{BanDetail, State};
+
+/// A change on a host that fired a rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub ip: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// An action to carry out, with the findings that called for it. This is
+/// also the JSON body webhooks and commands receive.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dispatch {
+    pub rule: String,
+    #[serde(skip)]
+    pub action: Action,
+    /// Whether this is a digest of earlier findings.
+    pub digest: bool,
+    pub findings: Vec<Finding>,
+}
+
+impl Dispatch {
+    /// Hosts the findings are about, without repeats.
+    #[must_use]
+    pub fn ips(&self) -> BTreeSet<&str> {
+        self.findings.iter().map(|f| f.ip.as_str()).collect()
+    }
+}
+
+/// Evaluates diffs against rules across polls.
+#[derive(Debug)]
+pub struct Engine {
+    rules: RuleSet,
+    /// When each rule last notified, within the past hour.
+    sent: HashMap<String, VecDeque<DateTime<Utc>>>,
+    /// Findings waiting for the digest, by rule.
+    pending: BTreeMap<String, Vec<Finding>>,
+    last_digest: DateTime<Utc>,
+}
+
+impl Engine {
+    /// An engine whose first digest is due at the next `digest_at`.
+    #[must_use]
+    pub fn new(rules: RuleSet, now: DateTime<Utc>) -> Self {
+        Self {
+            rules,
+            sent: HashMap::new(),
+            pending: BTreeMap::new(),
+            last_digest: now,
+        }
+    }
+
+    /// Run a diff through every rule, returning what to do now.
+    pub fn evaluate(&mut self, diff: &HostDiff, now: DateTime<Utc>) -> Vec<Dispatch> {
+        let mut dispatches = Vec::new();
+        for rule in &self.rules.rules {
+            let findings: Vec<Finding> = diff
+                .changes
+                .iter()
+                .filter(|change| rule.matches(change))
+                .map(|change| Finding {
+                    ip: diff.ip.clone(),
+                    change: change.clone(),
+                })
+                .collect();
+            if findings.is_empty() {
+                continue;
+            }
+
+            let now_or_later = matches!(rule.action, Action::Ban { .. })
+                || (!rule.digest
+                    && allow(
+                        self.sent.entry(rule.name.clone()).or_default(),
+                        self.rules.max_per_hour,
+                        now,
+                    ));
+            if now_or_later {
+                dispatches.push(Dispatch {
+                    rule: rule.name.clone(),
+                    action: rule.action.clone(),
+                    digest: false,
+                    findings,
+                });
+            } else {
+                self.pending
+                    .entry(rule.name.clone())
+                    .or_default()
+                    .extend(findings);
+            }
+        }
+        dispatches
+    }
+
+    /// Once a day at `digest_at`, one dispatch per rule with findings
+    /// waiting; nothing otherwise.
+    pub fn digest(&mut self, now: DateTime<Utc>) -> Vec<Dispatch> {
+        if now < next_digest(self.last_digest, self.rules.digest_at) {
+            return Vec::new();
+        }
+        self.last_digest = now;
+        let mut pending = std::mem::take(&mut self.pending);
+        self.rules
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                Some(Dispatch {
+                    rule: rule.name.clone(),
+                    action: rule.action.clone(),
+                    digest: true,
+                    findings: pending.remove(&rule.name)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Findings waiting for the digest.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+}
+
+/// Record a notification at `now` if fewer than `limit` went out in the
+/// past hour.
+fn allow(sent: &mut VecDeque<DateTime<Utc>>, limit: u32, now: DateTime<Utc>) -> bool {
+    while sent
+        .front()
+        .is_some_and(|at| now - *at >= Duration::hours(1))
+    {
+        sent.pop_front();
+    }
+    if sent.len() >= limit as usize {
+        return false;
+    }
+    sent.push_back(now);
+    true
+}
+
+/// The first `at` time of day after `after`.
+fn next_digest(after: DateTime<Utc>, at: NaiveTime) -> DateTime<Utc> {
+    let same_day = after.date_naive().and_time(at).and_utc();
+    if same_day > after {
+        same_day
+    } else {
+        same_day + Duration::days(1)
+    }
+}
+
+/// Carry out a dispatch.
+pub async fn run(dispatch: &Dispatch) -> Result<()> {
+    match &dispatch.action {
+        Action::Webhook { url } => {
+            reqwest::Client::new()
+                .post(url)
+                .json(dispatch)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Action::Exec { command } => exec(command, dispatch)?,
+        Action::Ban { reason, ttl } => {
+            let (mut state, lock) = State::load_for_update()?;
+            let expired = state.expire_bans(Utc::now());
+            let banned = ban(&mut state, dispatch, reason, *ttl, Utc::now());
+            if !expired.is_empty() || !banned.is_empty() {
+                state.save(&lock)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run `command` through the shell with the dispatch as JSON on stdin,
+/// and the rule and hosts in `I1_RULE` and `I1_IPS`.
+fn exec(command: &str, dispatch: &Dispatch) -> Result<()> {
+    let ips: Vec<&str> = dispatch.ips().into_iter().collect();
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("I1_RULE", &dispatch.rule)
+        .env("I1_IPS", ips.join(" "))
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run `{command}`"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may not read its input at all
+        let _ = stdin.write_all(&serde_json::to_vec(dispatch)?);
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("`{command}` exited with {status}");
+    }
+    Ok(())
+}
+
+/// Ban every host in the dispatch, returning those newly banned or
+/// re-banned. Whitelisted hosts are left alone.
+fn ban(
+    state: &mut State,
+    dispatch: &Dispatch,
+    reason: &str,
+    ttl: Option<Duration>,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let detail = BanDetail {
+        reason: reason.to_string(),
+        expires: ttl.map(|ttl| now + ttl),
+    };
+    dispatch
+        .ips()
+        .into_iter()
+        .filter(|ip| state.ban(ip, detail.clone()))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+[settings]
+max_per_hour = 1
+digest_at = "08:00"
+
+[[rule]]
+name = "remote-access"
+when = "new_port"
+ports = [3389]
+action = "webhook"
+url = "https://hooks.example.com/i1"
+
+[[rule]]
+name = "closed"
+when = "closed_port"
+action = "exec"
+command = "true"
+digest = true
+
+[[rule]]
+name = "critical"
+when = "new_vuln"
+min_cvss = 9.0
+action = "ban"
+reason = "critical CVE exposed"
+ttl = "1d"
+"#;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    fn diff(ip: &str, changes: Vec<Change>) -> HostDiff {
+        HostDiff {
+            ip: ip.into(),
+            changes,
+        }
+    }
+
+    fn rdp() -> Change {
+        Change::NewPort {
+            port: 3389,
+            product: None,
+            cvss: None,
+        }
+    }
+
+    #[test]
+    fn notifications_are_limited_and_digested() {
+        let mut engine = Engine::new(RuleSet::parse(RULES).unwrap(), at(2, 0));
+
+        let first = engine.evaluate(&diff("192.0.2.1", vec![rdp()]), at(3, 0));
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].rule, "remote-access");
+        assert!(!first[0].digest);
+
+        // Over the hourly limit, and a digest-only rule: both wait
+        let held = engine.evaluate(
+            &diff("192.0.2.2", vec![rdp(), Change::ClosedPort { port: 22 }]),
+            at(3, 30),
+        );
+        assert!(held.is_empty());
+        assert_eq!(engine.pending(), 2);
+
+        // An hour on, the limit has room again
+        assert_eq!(
+            engine
+                .evaluate(&diff("192.0.2.3", vec![rdp()]), at(4, 0))
+                .len(),
+            1
+        );
+
+        assert!(engine.digest(at(7, 59)).is_empty());
+        let digest = engine.digest(at(8, 0));
+        let rules: Vec<_> = digest.iter().map(|d| d.rule.as_str()).collect();
+        assert_eq!(rules, ["remote-access", "closed"]);
+        assert!(digest.iter().all(|d| d.digest && d.findings.len() == 1));
+        assert_eq!(engine.pending(), 0);
+        assert!(engine.digest(at(8, 1)).is_empty());
+    }
+
+    #[test]
+    fn ban_writes_through_the_defense_state() {
+        let mut engine = Engine::new(RuleSet::parse(RULES).unwrap(), at(0, 0));
+        let vuln = |cvss| Change::NewVuln {
+            cve: "CVE-2019-0708".into(),
+            port: Some(3389),
+            cvss: Some(cvss),
+        };
+        assert!(engine
+            .evaluate(&diff("192.0.2.9", vec![vuln(5.0)]), at(1, 0))
+            .is_empty());
+        let dispatches = engine.evaluate(&diff("192.0.2.9", vec![vuln(9.8)]), at(1, 0));
+        let Action::Ban { reason, ttl } = &dispatches[0].action else {
+            panic!("expected a ban, got {:?}", dispatches[0].action);
+        };
+
+        let mut state = State {
+            whitelisted_ips: vec!["192.0.2.10".into()],
+            ..State::default()
+        };
+        let banned = ban(&mut state, &dispatches[0], reason, *ttl, at(1, 0));
+        assert_eq!(banned, ["192.0.2.9"]);
+        assert_eq!(state.blocked_ips, ["192.0.2.9"]);
+        assert_eq!(
+            state.ban_details["192.0.2.9"],
+            BanDetail {
+                reason: "critical CVE exposed".into(),
+                expires: Some(at(1, 0) + Duration::days(1)),
+            }
+        );
+
+        let mut whitelisted = dispatches[0].clone();
+        whitelisted.findings[0].ip = "192.0.2.10".into();
+        assert!(ban(&mut state, &whitelisted, reason, *ttl, at(1, 0)).is_empty());
+
+        assert!(state.expire_bans(at(23, 0)).is_empty());
+        assert_eq!(
+            state.expire_bans(at(1, 0) + Duration::days(1)),
+            ["192.0.2.9"]
+        );
+        assert!(state.blocked_ips.is_empty() && state.ban_details.is_empty());
+    }
+}
@@ -0,0 +1,339 @@
+//! Watch rules, read from TOML.
+//!
+//! ```toml
+//! [settings]
+//! max_per_hour = 10      # notifications per rule; the rest go to the digest
+//! digest_at = "08:00"    # when the daily digest is sent (UTC)
+//!
+//! [[rule]]
+//! name = "remote-access"
+//! when = "new_port"      # new_port, closed_port, new_vuln or any
+//! ports = [3389, 445]
+//! min_cvss = 7.0
+//! action = "webhook"     # webhook (url), exec (command) or ban (reason, ttl)
+//! url = "https://hooks.example.com/i1"
+//!
+//! [[rule]]
+//! name = "noise"
+//! when = "any"
+//! action = "exec"
+//! command = "logger -t i1"
+//! digest = true          # batch into the daily digest instead
+//! ```
+//!
+//! Errors, from TOML syntax to a rule missing its action's settings, are
+//! reported with the offending line.
+
+use chrono::{Duration, NaiveTime};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::Path;
+use thiserror::Error;
+use toml::Spanned;
+
+use super::Change;
+
+/// Notifications each rule may send per hour by default.
+pub const DEFAULT_MAX_PER_HOUR: u32 = 10;
+
+/// A problem in a rules file, with the line it was found on.
+#[derive(Debug, Error)]
+#[error("line {line}: {message}\n  {line} | {text}")]
+pub struct RuleError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+    /// The offending line.
+    pub text: String,
+}
+
+impl RuleError {
+    fn new(source: &str, span: Option<Range<usize>>, message: impl Into<String>) -> Self {
+        let offset = span.map_or(0, |span| span.start.min(source.len()));
+        let line = source[..offset].matches('\n').count() + 1;
+        Self {
+            line,
+            message: message.into(),
+            text: source
+                .lines()
+                .nth(line - 1)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        }
+    }
+}
+
+/// Kinds of change a rule can fire on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    NewPort,
+    ClosedPort,
+    NewVuln,
+    Any,
+}
+
+/// What a rule does when it fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// POST the findings as JSON.
+    Webhook { url: String },
+    /// Run a shell command with the findings as JSON on stdin.
+    Exec { command: String },
+    /// Block the host in the defense state, optionally for a while.
+    Ban {
+        reason: String,
+        ttl: Option<Duration>,
+    },
+}
+
+/// One validated rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub when: Trigger,
+    /// Ports the change must concern; empty matches any.
+    pub ports: BTreeSet<u16>,
+    /// Lowest CVSS score the change must carry.
+    pub min_cvss: Option<f64>,
+    pub action: Action,
+    /// Batch matches into the daily digest instead of notifying at once.
+    pub digest: bool,
+}
+
+impl Rule {
+    /// Whether `change` fires this rule.
+    #[must_use]
+    pub fn matches(&self, change: &Change) -> bool {
+        let kind = match change {
+            Change::NewPort { .. } => Trigger::NewPort,
+            Change::ClosedPort { .. } => Trigger::ClosedPort,
+            Change::NewVuln { .. } => Trigger::NewVuln,
+        };
+        (self.when == Trigger::Any || self.when == kind)
+            && (self.ports.is_empty() || change.port().is_some_and(|p| self.ports.contains(&p)))
+            && self
+                .min_cvss
+                .map_or(true, |min| change.cvss().is_some_and(|cvss| cvss >= min))
+    }
+}
+
+/// Rules and the settings they share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+    pub max_per_hour: u32,
+    /// Time of day (UTC) the digest is sent.
+    pub digest_at: NaiveTime,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFile {
+    #[serde(default)]
+    settings: RawSettings,
+    #[serde(default, rename = "rule")]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSettings {
+    max_per_hour: Option<u32>,
+    digest_at: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    name: Spanned<String>,
+    when: Trigger,
+    #[serde(default)]
+    ports: BTreeSet<u16>,
+    min_cvss: Option<Spanned<f64>>,
+    action: Spanned<String>,
+    url: Option<String>,
+    command: Option<String>,
+    reason: Option<String>,
+    ttl: Option<Spanned<String>>,
+    #[serde(default)]
+    digest: bool,
+}
+
+impl RuleSet {
+    /// Read and validate a rules file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| anyhow::anyhow!("{}:{e}", path.display()))
+    }
+
+    /// Parse and validate rules from TOML.
+    pub fn parse(source: &str) -> Result<Self, RuleError> {
+        let raw: RawFile =
+            toml::from_str(source).map_err(|e| RuleError::new(source, e.span(), e.message()))?;
+        let error =
+            |span: Range<usize>, message: String| RuleError::new(source, Some(span), message);
+
+        let digest_at = match raw.settings.digest_at {
+            Some(at) => NaiveTime::parse_from_str(at.get_ref(), "%H:%M").map_err(|_| {
+                error(
+                    at.span(),
+                    format!("digest_at `{}` is not HH:MM", at.get_ref()),
+                )
+            })?,
+            None => NaiveTime::from_hms_opt(8, 0, 0).unwrap_or_default(),
+        };
+
+        let mut names = BTreeSet::new();
+        let mut rules = Vec::with_capacity(raw.rules.len());
+        for rule in raw.rules {
+            if !names.insert(rule.name.get_ref().clone()) {
+                let message = format!("rule `{}` is defined twice", rule.name.get_ref());
+                return Err(error(rule.name.span(), message));
+            }
+            let min_cvss = match rule.min_cvss {
+                Some(min) if !(0.0..=10.0).contains(min.get_ref()) => {
+                    return Err(error(
+                        min.span(),
+                        "min_cvss must be between 0 and 10".into(),
+                    ));
+                }
+                min => min.map(Spanned::into_inner),
+            };
+
+            let missing = |field: &str| {
+                error(
+                    rule.action.span(),
+                    format!("action `{}` needs `{field}`", rule.action.get_ref()),
+                )
+            };
+            let action = match rule.action.get_ref().as_str() {
+                "webhook" => Action::Webhook {
+                    url: rule.url.ok_or_else(|| missing("url"))?,
+                },
+                "exec" => Action::Exec {
+                    command: rule.command.ok_or_else(|| missing("command"))?,
+                },
+                "ban" => Action::Ban {
+                    reason: rule
+                        .reason
+                        .unwrap_or_else(|| format!("watch rule {}", rule.name.get_ref())),
+                    ttl: match rule.ttl {
+                        Some(ttl) => Some(parse_duration(ttl.get_ref()).ok_or_else(|| {
+                            error(
+                                ttl.span(),
+                                format!("ttl `{}` is not like 30m, 12h or 7d", ttl.get_ref()),
+                            )
+                        })?),
+                        None => None,
+                    },
+                },
+                other => {
+                    let message = format!("unknown action `{other}` (webhook, exec or ban)");
+                    return Err(error(rule.action.span(), message));
+                }
+            };
+
+            rules.push(Rule {
+                name: rule.name.into_inner(),
+                when: rule.when,
+                ports: rule.ports,
+                min_cvss,
+                action,
+                digest: rule.digest,
+            });
+        }
+
+        Ok(Self {
+            max_per_hour: raw.settings.max_per_hour.unwrap_or(DEFAULT_MAX_PER_HOUR),
+            digest_at,
+            rules,
+        })
+    }
+}
+
+/// Parse a duration like `90s`, `30m`, `12h` or `7d`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let unit = s.chars().last()?;
+    let count: i64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        's' => Some(Duration::seconds(count)),
+        'm' => Some(Duration::minutes(count)),
+        'h' => Some(Duration::hours(count)),
+        'd' => Some(Duration::days(count)),
+        _ => None,
+    }
+    .filter(|d| *d > Duration::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+[settings]
+max_per_hour = 2
+
+[[rule]]
+name = "remote-access"
+when = "new_port"
+ports = [3389, 445]
+min_cvss = 7.0
+action = "webhook"
+url = "https://hooks.example.com/i1"
+
+[[rule]]
+name = "ban-critical"
+when = "new_vuln"
+min_cvss = 9.0
+action = "ban"
+ttl = "7d"
+"#;
+
+    #[test]
+    fn rules_parse_and_match() {
+        let set = RuleSet::parse(RULES).unwrap();
+        assert_eq!(set.max_per_hour, 2);
+        assert_eq!(set.digest_at, NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(
+            set.rules[1].action,
+            Action::Ban {
+                reason: "watch rule ban-critical".into(),
+                ttl: Some(Duration::days(7)),
+            }
+        );
+
+        let rdp = |cvss| Change::NewPort {
+            port: 3389,
+            product: None,
+            cvss,
+        };
+        let remote = &set.rules[0];
+        assert!(remote.matches(&rdp(Some(9.8))));
+        assert!(!remote.matches(&rdp(Some(5.0))));
+        assert!(!remote.matches(&rdp(None)));
+        assert!(!remote.matches(&Change::ClosedPort { port: 3389 }));
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = RuleSet::parse(&RULES.replace("url = ", "uri = ")).unwrap_err();
+        assert_eq!(error.line, 11);
+        assert_eq!(error.text, r#"uri = "https://hooks.example.com/i1""#);
+
+        let error = RuleSet::parse(&RULES.replace("\"7d\"", "\"soon\"")).unwrap_err();
+        assert_eq!(error.line, 18);
+        assert!(error.message.contains("ttl"));
+
+        let error =
+            RuleSet::parse(&RULES.replace("action = \"ban\"", "action = \"page\"")).unwrap_err();
+        assert_eq!(error.line, 17);
+        assert!(error.to_string().contains("17 | action = \"page\""));
+
+        let error = RuleSet::parse("[[rule]]\nname = \"x\"\nwhen = \"sometimes\"\n").unwrap_err();
+        assert_eq!(error.line, 3);
+    }
+}
@@ -2,14 +2,29 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures_util::future::join_all;
 use i1_core::{HostInfo, I1Error, Result};
 use i1_providers::{
-    HealthStatus, HostLookup, Provider, ProviderHealth, SearchProvider, SearchResults,
+    check_health, HostLookup, MinSearchResults, Provider, ProviderHealth, SearchProvider,
+    SearchResults,
 };
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::{debug, info, instrument};
 
+/// Default cap on requests in flight across all providers
+pub const DEFAULT_MAX_CONCURRENCY: usize = 16;
+
+/// Default time each provider gets to answer a health check
+pub const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Unified i1 client that can aggregate multiple providers
+///
+/// Every request goes through a semaphore shared by all providers and all
+/// clones of the client, so a large sweep never has more than
+/// `max_concurrency` requests (and sockets) open at once. This is separate
+/// from each provider's own per-second quota, which still applies.
 pub struct I1Client {
     inner: Arc<I1ClientInner>,
 }
@@ -17,6 +32,11 @@ pub struct I1Client {
 struct I1ClientInner {
     providers: HashMap<String, Arc<dyn ProviderBox>>,
     default_provider: Option<String>,
+    max_concurrency: usize,
+    permits: Semaphore,
+    health_timeout: Duration,
+    #[cfg(feature = "geo")]
+    geo: Option<Arc<i1_core::geo::GeoDatabase>>,
 }
 
 /// Trait object wrapper for providers
@@ -42,26 +62,43 @@ impl I1Client {
         self.inner.providers.keys().map(String::as_str).collect()
     }
 
+    /// Maximum requests in flight across all providers
+    pub fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency
+    }
+
+    /// Requests that could start right now without waiting
+    pub fn available_permits(&self) -> usize {
+        self.inner.permits.available_permits()
+    }
+
+    /// Wait for a slot under the concurrency cap
+    async fn permit(&self) -> SemaphorePermit<'_> {
+        self.inner
+            .permits
+            .acquire()
+            .await
+            .expect("the concurrency semaphore is never closed")
+    }
+
     /// Check health of all providers
+    ///
+    /// Each check gets the builder's health timeout; a provider that
+    /// doesn't answer in time is reported unhealthy rather than holding
+    /// up the others.
     #[instrument(skip(self))]
     pub async fn health_check_all(&self) -> Vec<ProviderHealth> {
-        let mut results = Vec::with_capacity(self.inner.providers.len());
-
-        for (name, provider) in &self.inner.providers {
-            debug!(provider = %name, "Checking provider health");
-            match provider.health_check().await {
-                Ok(health) => results.push(health),
-                Err(e) => results.push(ProviderHealth {
-                    provider: name.clone(),
-                    status: HealthStatus::Unhealthy,
-                    latency_ms: None,
-                    credits_remaining: None,
-                    message: Some(e.to_string()),
-                }),
-            }
-        }
-
-        results
+        let checks = self
+            .inner
+            .providers
+            .iter()
+            .map(|(name, provider)| async move {
+                let _permit = self.permit().await;
+                debug!(provider = %name, "Checking provider health");
+                check_health(provider.as_ref(), self.inner.health_timeout).await
+            });
+
+        join_all(checks).await
     }
 
     /// Look up host using default provider
@@ -85,21 +122,38 @@ impl I1Client {
             .get(provider)
             .ok_or_else(|| I1Error::ProviderNotConfigured(provider.to_string()))?;
 
-        provider.lookup_host(ip).await
+        let _permit = self.permit().await;
+        provider.lookup_host(ip).await.map(|host| self.enrich(host))
     }
 
-    /// Look up host from all configured providers and merge results
+    /// Look up host from all configured providers concurrently
     #[instrument(skip(self))]
     pub async fn lookup_host_all(&self, ip: &str) -> Result<Vec<(String, Result<HostInfo>)>> {
-        let mut results = Vec::new();
+        let lookups = self
+            .inner
+            .providers
+            .iter()
+            .map(|(name, provider)| async move {
+                let _permit = self.permit().await;
+                info!(provider = %name, ip = %ip, "Looking up host");
+                let host = provider.lookup_host(ip).await.map(|host| self.enrich(host));
+                (name.clone(), host)
+            });
+
+        Ok(join_all(lookups).await)
+    }
 
-        for (name, provider) in &self.inner.providers {
-            info!(provider = %name, ip = %ip, "Looking up host");
-            let result = provider.lookup_host(ip).await;
-            results.push((name.clone(), result));
+    /// Fill in missing geo/ASN data from the local databases, if any
+    #[cfg_attr(
+        not(feature = "geo"),
+        allow(unused_mut, clippy::unused_self, clippy::missing_const_for_fn)
+    )]
+    fn enrich(&self, mut host: HostInfo) -> HostInfo {
+        #[cfg(feature = "geo")]
+        if let Some(db) = &self.inner.geo {
+            host.enrich_geo(db);
         }
-
-        Ok(results)
+        host
     }
 
     /// Search using default provider
@@ -128,9 +182,28 @@ impl I1Client {
             .get(provider)
             .ok_or_else(|| I1Error::ProviderNotConfigured(provider.to_string()))?;
 
+        let _permit = self.permit().await;
         provider.search(query, page).await
     }
 
+    /// Search using default provider, returning host summaries only
+    #[instrument(skip(self))]
+    pub async fn search_min(&self, query: &str, page: Option<u32>) -> Result<MinSearchResults> {
+        let provider_name = self
+            .inner
+            .default_provider
+            .as_deref()
+            .ok_or(I1Error::NoProviders)?;
+        let provider = self
+            .inner
+            .providers
+            .get(provider_name)
+            .ok_or_else(|| I1Error::ProviderNotConfigured(provider_name.to_string()))?;
+
+        let _permit = self.permit().await;
+        provider.search_min(query, page).await
+    }
+
     /// Count results using default provider
     #[instrument(skip(self))]
     pub async fn count(&self, query: &str) -> Result<u64> {
@@ -152,6 +225,7 @@ impl I1Client {
             .get(provider)
             .ok_or_else(|| I1Error::ProviderNotConfigured(provider.to_string()))?;
 
+        let _permit = self.permit().await;
         provider.count(query).await
     }
 }
@@ -168,6 +242,10 @@ impl Clone for I1Client {
 pub struct I1ClientBuilder {
     providers: HashMap<String, Arc<dyn ProviderBox>>,
     default_provider: Option<String>,
+    max_concurrency: usize,
+    health_timeout: Duration,
+    #[cfg(feature = "geo")]
+    geo: Option<Arc<i1_core::geo::GeoDatabase>>,
 }
 
 impl I1ClientBuilder {
@@ -176,6 +254,10 @@ impl I1ClientBuilder {
         Self {
             providers: HashMap::new(),
             default_provider: None,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            health_timeout: DEFAULT_HEALTH_TIMEOUT,
+            #[cfg(feature = "geo")]
+            geo: None,
         }
     }
 
@@ -198,12 +280,42 @@ impl I1ClientBuilder {
         self
     }
 
+    /// Cap requests in flight across all providers (default:
+    /// [`DEFAULT_MAX_CONCURRENCY`]; at least 1)
+    #[must_use]
+    pub fn max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = max.clamp(1, Semaphore::MAX_PERMITS);
+        self
+    }
+
+    /// Time each provider gets to answer a health check (default:
+    /// [`DEFAULT_HEALTH_TIMEOUT`])
+    #[must_use]
+    pub const fn health_timeout(mut self, timeout: Duration) -> Self {
+        self.health_timeout = timeout;
+        self
+    }
+
+    /// Fill in missing location and ASN on every host lookup from local
+    /// `.mmdb` databases. Values a provider supplied are kept.
+    #[cfg(feature = "geo")]
+    #[must_use]
+    pub fn geo_database(mut self, db: impl Into<Arc<i1_core::geo::GeoDatabase>>) -> Self {
+        self.geo = Some(db.into());
+        self
+    }
+
     /// Build the client
     pub fn build(self) -> I1Client {
         I1Client {
             inner: Arc::new(I1ClientInner {
                 providers: self.providers,
                 default_provider: self.default_provider,
+                max_concurrency: self.max_concurrency,
+                permits: Semaphore::new(self.max_concurrency),
+                health_timeout: self.health_timeout,
+                #[cfg(feature = "geo")]
+                geo: self.geo,
             }),
         }
     }
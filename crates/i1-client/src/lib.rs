@@ -8,6 +8,6 @@
 mod client;
 mod config;
 
-pub use client::{I1Client, I1ClientBuilder};
+pub use client::{I1Client, I1ClientBuilder, DEFAULT_HEALTH_TIMEOUT, DEFAULT_MAX_CONCURRENCY};
 pub use config::*;
 pub use i1_core::{I1Error, Result};
@@ -0,0 +1,225 @@
+//! AS number metadata: who operates an ASN and where.
+//!
+//! The defend commands and the DNS server both show ASNs to people, and an
+//! `AS4134` means little without `China Telecom, CN` next to it. A snapshot
+//! of widely seen networks is bundled, so [`lookup_asn`] works offline; for
+//! full coverage, load RIPE's `asnames.txt` into an [`AsnResolver`]:
+//!
+//! ```rust,ignore
+//! let resolver = AsnResolver::load("/var/cache/i1/asnames.txt")?;
+//! if let Some(info) = resolver.lookup(15169) {
+//!     println!("{} ({})", info.org, info.country);
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{I1Error, Result};
+
+/// Who operates an autonomous system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AsnInfo {
+    /// AS number
+    pub asn: u32,
+    /// Operator's name
+    pub org: String,
+    /// Country of registration (ISO 3166-1 alpha-2)
+    pub country: String,
+}
+
+impl std::fmt::Display for AsnInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AS{} {} ({})", self.asn, self.org, self.country)
+    }
+}
+
+/// Networks bundled with i1, sorted by AS number.
+#[rustfmt::skip]
+const BUNDLED: &[(u32, &str, &str)] = &[
+    (174, "Cogent Communications", "US"),
+    (701, "Verizon Business", "US"),
+    (714, "Apple Inc.", "US"),
+    (1299, "Arelion Sweden AB", "SE"),
+    (2856, "British Telecommunications PLC", "GB"),
+    (2914, "NTT America, Inc.", "US"),
+    (3215, "Orange S.A.", "FR"),
+    (3320, "Deutsche Telekom AG", "DE"),
+    (3356, "Level 3 Parent, LLC", "US"),
+    (3462, "Chunghwa Telecom Co., Ltd.", "TW"),
+    (4134, "China Telecom (CHINANET backbone)", "CN"),
+    (4766, "Korea Telecom", "KR"),
+    (4837, "China Unicom Backbone", "CN"),
+    (6939, "Hurricane Electric LLC", "US"),
+    (7018, "AT&T Services, Inc.", "US"),
+    (7922, "Comcast Cable Communications, LLC", "US"),
+    (8075, "Microsoft Corporation", "US"),
+    (8560, "IONOS SE", "DE"),
+    (9009, "M247 Europe SRL", "RO"),
+    (9808, "China Mobile Communications Group", "CN"),
+    (12389, "PJSC Rostelecom", "RU"),
+    (12876, "Scaleway S.A.S.", "FR"),
+    (13335, "Cloudflare, Inc.", "US"),
+    (14061, "DigitalOcean, LLC", "US"),
+    (14618, "Amazon.com, Inc.", "US"),
+    (15169, "Google LLC", "US"),
+    (16276, "OVH SAS", "FR"),
+    (16509, "Amazon.com, Inc.", "US"),
+    (17676, "SoftBank Corp.", "JP"),
+    (20473, "The Constant Company, LLC (Vultr)", "US"),
+    (20940, "Akamai International B.V.", "NL"),
+    (24940, "Hetzner Online GmbH", "DE"),
+    (32934, "Meta Platforms, Inc.", "US"),
+    (37963, "Hangzhou Alibaba Advertising Co., Ltd.", "CN"),
+    (45090, "Shenzhen Tencent Computer Systems Co., Ltd.", "CN"),
+    (45102, "Alibaba (US) Technology Co., Ltd.", "US"),
+    (49505, "Selectel Ltd.", "RU"),
+    (51167, "Contabo GmbH", "DE"),
+    (53667, "FranTech Solutions", "US"),
+    (54113, "Fastly, Inc.", "US"),
+    (60781, "LeaseWeb Netherlands B.V.", "NL"),
+    (63949, "Akamai Connected Cloud (Linode)", "US"),
+    (132_203, "Tencent Building, Kejizhongyi Avenue", "CN"),
+    (396_982, "Google LLC (Google Cloud)", "US"),
+];
+
+/// Look an ASN up in the bundled snapshot.
+#[must_use]
+pub fn lookup_asn(asn: u32) -> Option<AsnInfo> {
+    let at = BUNDLED
+        .binary_search_by_key(&asn, |(number, _, _)| *number)
+        .ok()?;
+    let (asn, org, country) = BUNDLED[at];
+    Some(AsnInfo {
+        asn,
+        org: org.into(),
+        country: country.into(),
+    })
+}
+
+/// Parse `AS64500`, `as64500` or `64500`.
+#[must_use]
+pub fn parse_asn(asn: &str) -> Option<u32> {
+    let asn = asn.trim();
+    asn.strip_prefix("AS")
+        .or_else(|| asn.strip_prefix("as"))
+        .unwrap_or(asn)
+        .parse()
+        .ok()
+}
+
+/// ASN lookups backed by a loaded table, falling back to the bundled one.
+#[derive(Debug, Clone, Default)]
+pub struct AsnResolver {
+    loaded: HashMap<u32, AsnInfo>,
+}
+
+impl AsnResolver {
+    /// A resolver that only knows the bundled snapshot.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a RIPE `asnames.txt` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`I1Error::Config`] if the file can't be read.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| I1Error::Config(format!("cannot read {}: {e}", path.display())))?;
+        Ok(Self::from_asnames(&text))
+    }
+
+    /// Parse RIPE `asnames.txt` content: one `<asn> <name>, <CC>` per line.
+    ///
+    /// Lines that don't fit the format are skipped.
+    #[must_use]
+    pub fn from_asnames(text: &str) -> Self {
+        let loaded = text
+            .lines()
+            .filter_map(|line| {
+                let (asn, rest) = line.trim().split_once(char::is_whitespace)?;
+                let (org, country) = rest.trim().rsplit_once(',')?;
+                let country = country.trim();
+                if country.len() != 2 {
+                    return None;
+                }
+                let info = AsnInfo {
+                    asn: asn.parse().ok()?,
+                    org: org.trim().to_string(),
+                    country: country.to_ascii_uppercase(),
+                };
+                Some((info.asn, info))
+            })
+            .collect();
+        Self { loaded }
+    }
+
+    /// Add or replace an entry.
+    pub fn insert(&mut self, info: AsnInfo) {
+        self.loaded.insert(info.asn, info);
+    }
+
+    /// Number of loaded entries, not counting the bundled snapshot.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// Whether nothing beyond the bundled snapshot is loaded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.loaded.is_empty()
+    }
+
+    /// Look an ASN up, preferring loaded entries over bundled ones.
+    #[must_use]
+    pub fn lookup(&self, asn: u32) -> Option<AsnInfo> {
+        self.loaded.get(&asn).cloned().or_else(|| lookup_asn(asn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_table_is_sorted_and_searchable() {
+        assert!(BUNDLED.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for (asn, _, country) in BUNDLED {
+            assert_eq!(country.len(), 2);
+            assert_eq!(lookup_asn(*asn).unwrap().asn, *asn);
+        }
+
+        let google = lookup_asn(15169).unwrap();
+        assert_eq!(google.org, "Google LLC");
+        assert_eq!(google.to_string(), "AS15169 Google LLC (US)");
+        assert!(lookup_asn(64500).is_none());
+    }
+
+    #[test]
+    fn asnames_entries_override_the_bundle() {
+        let resolver = AsnResolver::from_asnames(
+            "64500 EXAMPLE-NET Example Networks, NL\n\
+             15169 GOOGLE - Google LLC, US\n\
+             not-a-number SOMETHING, US\n\
+             64501 NO-COUNTRY\n",
+        );
+        assert_eq!(resolver.len(), 2);
+        let example = resolver.lookup(64500).unwrap();
+        assert_eq!(example.org, "EXAMPLE-NET Example Networks");
+        assert_eq!(example.country, "NL");
+        assert_eq!(resolver.lookup(15169).unwrap().org, "GOOGLE - Google LLC");
+        assert_eq!(resolver.lookup(13335).unwrap().org, "Cloudflare, Inc.");
+        assert!(resolver.lookup(64501).is_none());
+
+        assert_eq!(parse_asn("AS4134"), Some(4134));
+        assert_eq!(parse_asn(" as4134 "), Some(4134));
+        assert_eq!(parse_asn("4134"), Some(4134));
+        assert_eq!(parse_asn("ASX"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt;
 use thiserror::Error;
 
 /// Result type alias for i1 operations
@@ -44,9 +45,15 @@ pub enum I1Error {
         message: String,
     },
 
-    /// HTTP request failed
-    #[error("HTTP request failed: {0}")]
-    Http(String),
+    /// HTTP request failed below the API layer
+    #[error("HTTP request failed ({kind}): {source}")]
+    Http {
+        /// Which part of the exchange failed
+        kind: TransportErrorKind,
+        /// The underlying transport error
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
 
     /// Request timed out
     #[error("request timed out after {0} seconds")]
@@ -92,6 +99,19 @@ pub enum I1Error {
     #[error("traceroute failed: {0}")]
     Trace(String),
 
+    /// Geo database missing, unreadable, or of an unsupported kind
+    #[error("GeoIP database {path}: {reason}")]
+    GeoDatabase {
+        /// Path of the database file
+        path: String,
+        /// What went wrong
+        reason: String,
+    },
+
+    /// STIX bundle failed validation
+    #[error("invalid STIX bundle: {0}")]
+    Stix(String),
+
     /// Provider not configured
     #[error("provider '{0}' is not configured")]
     ProviderNotConfigured(String),
@@ -105,20 +125,117 @@ pub enum I1Error {
     Internal(String),
 }
 
+/// Which part of an HTTP exchange failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportErrorKind {
+    /// Couldn't connect (DNS, refused, TLS handshake)
+    Connect,
+    /// The request or response timed out
+    Timeout,
+    /// Too many or invalid redirects
+    Redirect,
+    /// The request was sent but failed in flight
+    Request,
+    /// The response body was cut off or unreadable
+    Body,
+    /// The response body couldn't be decoded
+    Decode,
+    /// Anything else, e.g. an invalid request
+    Other,
+}
+
+impl TransportErrorKind {
+    /// Returns true if the same request may succeed when retried
+    #[must_use]
+    pub const fn is_retryable(self) -> bool {
+        matches!(self, Self::Connect | Self::Timeout | Self::Body)
+    }
+}
+
+impl fmt::Display for TransportErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Connect => "connect",
+            Self::Timeout => "timeout",
+            Self::Redirect => "redirect",
+            Self::Request => "request",
+            Self::Body => "body",
+            Self::Decode => "decode",
+            Self::Other => "other",
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<&reqwest::Error> for TransportErrorKind {
+    fn from(err: &reqwest::Error) -> Self {
+        // A connect timeout is both; report it as a timeout
+        if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_connect() {
+            Self::Connect
+        } else if err.is_redirect() {
+            Self::Redirect
+        } else if err.is_body() {
+            Self::Body
+        } else if err.is_decode() {
+            Self::Decode
+        } else if err.is_request() {
+            Self::Request
+        } else {
+            Self::Other
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for I1Error {
+    /// Classify a transport error, dropping its URL (some providers pass the
+    /// API key as a query parameter)
+    fn from(err: reqwest::Error) -> Self {
+        Self::http(TransportErrorKind::from(&err), err.without_url())
+    }
+}
+
 impl I1Error {
-    /// Returns true if the error is retryable
+    /// Returns true if the same request may succeed when retried: rate
+    /// limits, timeouts, dropped connections and server-side (5xx) errors
     #[must_use]
     pub const fn is_retryable(&self) -> bool {
-        matches!(
-            self,
-            Self::RateLimited { .. } | Self::Timeout(_) | Self::Connection(_)
-        )
+        match self {
+            Self::RateLimited { .. } | Self::Timeout(_) | Self::Connection(_) => true,
+            Self::Http { kind, .. } => kind.is_retryable(),
+            Self::Provider { code, .. } => matches!(*code, 408 | 500..=599),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the error is due to authentication
+    #[must_use]
+    pub const fn is_auth(&self) -> bool {
+        match self {
+            Self::Unauthorized => true,
+            Self::Provider { code, .. } => matches!(*code, 401 | 403),
+            _ => false,
+        }
     }
 
     /// Returns true if the error is due to authentication
+    #[deprecated(note = "use `is_auth`")]
     #[must_use]
     pub const fn is_auth_error(&self) -> bool {
-        matches!(self, Self::Unauthorized)
+        self.is_auth()
+    }
+
+    /// Returns true if the account is out of credits; unlike a rate limit,
+    /// waiting won't help
+    #[must_use]
+    pub const fn is_quota(&self) -> bool {
+        match self {
+            Self::InsufficientCredits { .. } => true,
+            Self::Provider { code, .. } => *code == 402,
+            _ => false,
+        }
     }
 
     /// Returns the HTTP status code if this is a provider error
@@ -141,4 +258,77 @@ impl I1Error {
             message: message.into(),
         }
     }
+
+    /// Create a transport error
+    pub fn http(
+        kind: TransportErrorKind,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Http {
+            kind,
+            source: source.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_retryable_classification() {
+        let retryable = [
+            I1Error::RateLimited { retry_after: None },
+            I1Error::Timeout(30),
+            I1Error::http(TransportErrorKind::Connect, "connection refused"),
+            I1Error::http(TransportErrorKind::Timeout, "deadline elapsed"),
+            I1Error::provider("shodan", 503, "unavailable"),
+        ];
+        for err in &retryable {
+            assert!(err.is_retryable(), "{err}");
+        }
+
+        let fatal = [
+            I1Error::Unauthorized,
+            I1Error::InsufficientCredits {
+                required: 1,
+                available: 0,
+            },
+            I1Error::http(TransportErrorKind::Decode, "expected value"),
+            I1Error::provider("shodan", 400, "bad query"),
+            I1Error::InvalidQuery("port:".into()),
+        ];
+        for err in &fatal {
+            assert!(!err.is_retryable(), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_auth_and_quota_classification() {
+        assert!(I1Error::Unauthorized.is_auth());
+        assert!(I1Error::provider("censys", 403, "forbidden").is_auth());
+        assert!(!I1Error::RateLimited { retry_after: None }.is_auth());
+
+        assert!(I1Error::InsufficientCredits {
+            required: 1,
+            available: 0
+        }
+        .is_quota());
+        assert!(I1Error::provider("criminalip", 402, "no credits").is_quota());
+        assert!(!I1Error::RateLimited {
+            retry_after: Some(1)
+        }
+        .is_quota());
+    }
+
+    #[test]
+    fn test_http_error_keeps_its_source() {
+        let err = I1Error::http(TransportErrorKind::Connect, "connection refused");
+        assert_eq!(
+            err.to_string(),
+            "HTTP request failed (connect): connection refused"
+        );
+        assert_eq!(err.source().unwrap().to_string(), "connection refused");
+    }
 }
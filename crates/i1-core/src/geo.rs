@@ -0,0 +1,300 @@
+//! Offline geo/ASN data from local `.mmdb` databases.
+//!
+//! Providers fill [`GeoLocation`] unevenly: Censys often omits the city,
+//! Shodan the coordinates. A [`GeoDatabase`] built from `GeoLite2` (or
+//! `GeoIP2`) City/Country and ASN files fills in what is missing, without
+//! a network call:
+//!
+//! ```rust,ignore
+//! use i1_core::geo::GeoDatabase;
+//!
+//! let db = GeoDatabase::open("GeoLite2-City.mmdb")?.with_mmdb("GeoLite2-ASN.mmdb")?;
+//! host.enrich_geo(&db);
+//! ```
+//!
+//! Values a provider already supplied are always kept.
+
+use maxminddb::{geoip2, Reader};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::{GeoLocation, HostInfo, I1Error, Result};
+
+/// Environment variable listing database paths, separated by commas.
+pub const GEO_DB_ENV: &str = "I1_GEO_DB";
+
+/// Location and network details for one IP.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeoInfo {
+    /// Two-letter country code (ISO 3166-1 alpha-2)
+    pub country_code: Option<String>,
+    /// Country name (English)
+    pub country_name: Option<String>,
+    /// City name (English)
+    pub city: Option<String>,
+    /// Most specific region/state code
+    pub region_code: Option<String>,
+    /// Postal/ZIP code
+    pub postal_code: Option<String>,
+    /// Latitude coordinate
+    pub latitude: Option<f64>,
+    /// Longitude coordinate
+    pub longitude: Option<f64>,
+    /// Autonomous System Number
+    pub asn: Option<u32>,
+    /// Organization that owns the AS
+    pub as_org: Option<String>,
+}
+
+impl GeoInfo {
+    /// Returns true if no database had anything for the IP
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Local City/Country and ASN databases.
+#[derive(Debug, Default)]
+pub struct GeoDatabase {
+    location: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl GeoDatabase {
+    /// Open a City, Country, or ASN database.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`I1Error::GeoDatabase`] if the file is missing, can't be
+    /// read, or isn't a database of a supported kind.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::default().with_mmdb(path)
+    }
+
+    /// Open every database in `paths`, e.g. a City and an ASN file.
+    ///
+    /// # Errors
+    ///
+    /// As for [`GeoDatabase::open`], for the first path that fails.
+    pub fn open_all<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self> {
+        paths.into_iter().try_fold(Self::default(), Self::with_mmdb)
+    }
+
+    /// Open the databases listed in [`GEO_DB_ENV`], if it is set.
+    ///
+    /// # Errors
+    ///
+    /// As for [`GeoDatabase::open`].
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(paths) = std::env::var(GEO_DB_ENV) else {
+            return Ok(None);
+        };
+        let paths: Vec<&str> = paths
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        Self::open_all(paths).map(Some)
+    }
+
+    /// Add another database, e.g. ASN data alongside a City database.
+    /// A database of the same kind replaces the earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`I1Error::GeoDatabase`] if the file is missing, can't be
+    /// read, or isn't a database of a supported kind.
+    pub fn with_mmdb(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let error = |reason: String| I1Error::GeoDatabase {
+            path: path.display().to_string(),
+            reason,
+        };
+        if !path.exists() {
+            return Err(error(
+                "file not found (GeoLite2 City and ASN databases can be downloaded from maxmind.com)"
+                    .into(),
+            ));
+        }
+        let reader = Reader::open_readfile(path).map_err(|e| error(e.to_string()))?;
+
+        let kind = reader.metadata.database_type.clone();
+        if kind.contains("ASN") || kind.contains("ISP") {
+            self.asn = Some(reader);
+        } else if kind.contains("City") || kind.contains("Country") {
+            self.location = Some(reader);
+        } else {
+            return Err(error(format!("unsupported database type {kind}")));
+        }
+        Ok(self)
+    }
+
+    /// Look up an IP in every loaded database.
+    #[must_use]
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        let mut info = GeoInfo::default();
+
+        if let Some(city) = self
+            .location
+            .as_ref()
+            // Not in the database, or unreadable there: no data either way
+            .and_then(|reader| reader.lookup::<geoip2::City<'_>>(ip).ok())
+        {
+            if let Some(country) = city.country {
+                info.country_code = country.iso_code.map(String::from);
+                info.country_name = english(country.names);
+            }
+            info.city = city.city.and_then(|c| english(c.names));
+            info.region_code = city
+                .subdivisions
+                .and_then(|subs| subs.last().and_then(|s| s.iso_code).map(String::from));
+            info.postal_code = city.postal.and_then(|p| p.code).map(String::from);
+            if let Some(location) = city.location {
+                info.latitude = location.latitude;
+                info.longitude = location.longitude;
+            }
+        }
+
+        if let Some(asn) = self
+            .asn
+            .as_ref()
+            .and_then(|reader| reader.lookup::<geoip2::Asn<'_>>(ip).ok())
+        {
+            info.asn = asn.autonomous_system_number;
+            info.as_org = asn.autonomous_system_organization.map(String::from);
+        }
+
+        info
+    }
+}
+
+impl GeoLocation {
+    /// Fill in missing country, city, region, postal code, and coordinates
+    /// for `ip` from `db`; values already present are kept.
+    ///
+    /// Returns true if anything was added.
+    pub fn enrich_from_ip(&mut self, ip: IpAddr, db: &GeoDatabase) -> bool {
+        self.fill_from(db.lookup(ip))
+    }
+
+    fn fill_from(&mut self, info: GeoInfo) -> bool {
+        let mut added = false;
+        added |= fill(&mut self.country_code, info.country_code);
+        added |= fill(&mut self.country_name, info.country_name);
+        added |= fill(&mut self.city, info.city);
+        added |= fill(&mut self.region_code, info.region_code);
+        added |= fill(&mut self.postal_code, info.postal_code);
+        added |= fill(&mut self.latitude, info.latitude);
+        added |= fill(&mut self.longitude, info.longitude);
+        added
+    }
+}
+
+impl HostInfo {
+    /// Fill in missing location, ASN, and organization from `db`; values
+    /// the provider supplied are kept.
+    ///
+    /// Returns true if anything was added.
+    pub fn enrich_geo(&mut self, db: &GeoDatabase) -> bool {
+        let Some(ip) = self.ip_addr() else {
+            return false;
+        };
+        let mut info = db.lookup(ip);
+        let asn = info.asn.take().map(|n| format!("AS{n}"));
+        let org = info.as_org.take();
+
+        let mut added = self.location.fill_from(info);
+        added |= fill(&mut self.asn, asn);
+        added |= fill(&mut self.org, org);
+        added
+    }
+}
+
+/// Set `slot` to `value` if it is empty; returns whether it changed.
+fn fill<T>(slot: &mut Option<T>, value: Option<T>) -> bool {
+    if slot.is_none() && value.is_some() {
+        *slot = value;
+        true
+    } else {
+        false
+    }
+}
+
+fn english(names: Option<BTreeMap<&str, &str>>) -> Option<String> {
+    names?.get("en").map(|name| (*name).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Built by testdata/make_geo_mmdb.py from entries of MaxMind's test databases
+    fn db() -> GeoDatabase {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+        GeoDatabase::open_all([
+            dir.join("geo-city-test.mmdb"),
+            dir.join("geo-asn-test.mmdb"),
+        ])
+        .unwrap()
+    }
+
+    fn host(ip: &str) -> HostInfo {
+        serde_json::from_value(serde_json::json!({ "ip_str": ip })).unwrap()
+    }
+
+    #[test]
+    fn lookup_reads_city_and_asn() {
+        let info = db().lookup("89.160.20.115".parse().unwrap());
+        assert_eq!(info.country_code.as_deref(), Some("SE"));
+        assert_eq!(info.city.as_deref(), Some("Linköping"));
+        assert_eq!(info.region_code.as_deref(), Some("E"));
+        assert_eq!(info.postal_code.as_deref(), Some("587 20"));
+        assert_eq!(info.latitude, Some(58.4167));
+        assert_eq!(info.asn, Some(29518));
+        assert_eq!(info.as_org.as_deref(), Some("Bredband2 AB"));
+
+        assert!(db().lookup("192.0.2.1".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn enrichment_keeps_provider_values() {
+        let db = db();
+
+        // Censys-style: country but no city or coordinates
+        let mut london = host("81.2.69.142");
+        london.location.country_code = Some("UK".into());
+        assert!(london.enrich_geo(&db));
+        assert_eq!(london.location.country_code.as_deref(), Some("UK"));
+        assert_eq!(london.location.city.as_deref(), Some("London"));
+        assert!(london.location.has_coordinates());
+        assert_eq!(london.asn, None);
+
+        let mut telstra = host("1.128.0.1");
+        telstra.org = Some("Telstra".into());
+        assert!(telstra.enrich_geo(&db));
+        assert_eq!(telstra.asn.as_deref(), Some("AS1221"));
+        assert_eq!(telstra.org.as_deref(), Some("Telstra"));
+        assert!(!telstra.enrich_geo(&db));
+
+        let mut location = GeoLocation::default();
+        assert!(location.enrich_from_ip("202.196.224.1".parse().unwrap(), &db));
+        assert!(location.in_country("PH"));
+        assert_eq!(location.city, None);
+    }
+
+    #[test]
+    fn missing_database_is_explained() {
+        let error = GeoDatabase::open("/nonexistent/GeoLite2-City.mmdb").unwrap_err();
+        let message = error.to_string();
+        assert!(
+            message.contains("/nonexistent/GeoLite2-City.mmdb"),
+            "{message}"
+        );
+        assert!(message.contains("not found"), "{message}");
+    }
+}
@@ -4,6 +4,11 @@
 //!
 //! - **Types**: Strongly-typed representations of threat intelligence data
 //! - **Errors**: Comprehensive error handling with [`I1Error`]
+//! - **STIX**: Export of findings as STIX 2.1 bundles ([`stix`])
+//! - **ASN**: Operator and country for AS numbers ([`asn`])
+//! - **Geo**: Offline enrichment from `MaxMind` databases (`geo` feature)
+//! - **Wire**: Schema version of the TXT records i1-audit and i1-srv
+//!   exchange ([`wire`])
 //!
 //! # Example
 //!
@@ -19,8 +24,14 @@
 
 #![doc(html_root_url = "https://docs.rs/i1-core/0.1.0")]
 
+pub mod asn;
 mod error;
+#[cfg(feature = "geo")]
+pub mod geo;
+pub mod stix;
 pub mod types;
+pub mod wire;
 
-pub use error::{I1Error, Result};
+pub use error::{I1Error, Result, TransportErrorKind};
 pub use types::*;
+pub use wire::{SchemaMismatch, WIRE_SCHEMA_VERSION};
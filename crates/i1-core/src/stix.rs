@@ -0,0 +1,899 @@
+//! STIX 2.1 export of reconnaissance findings.
+//!
+//! Turns hosts and search matches into a STIX 2.1 bundle that threat-intel
+//! platforms such as MISP and `OpenCTI` ingest directly:
+//!
+//! - each IP becomes an `ipv4-addr`/`ipv6-addr` observable and an
+//!   `indicator` with a `[ipv4-addr:value = '…']` pattern
+//! - each open service becomes `observed-data` over a `network-traffic`
+//!   observable (and a `software` observable when the product is known)
+//! - each CVE becomes a `vulnerability`, linked from the IP's indicator by
+//!   a `related-to` relationship
+//!
+//! IDs are derived from content (version 5 UUIDs), so exporting the same findings
+//! twice yields the same objects and platforms deduplicate them.
+//!
+//! ```rust,ignore
+//! let mut builder = BundleBuilder::new(Utc::now());
+//! builder.add_host(&host);
+//! let json = builder.build().to_json()?;
+//! ```
+
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+use crate::{parse_timestamp, HostInfo, I1Error, Result, SearchMatch, Transport, VulnInfo};
+
+/// STIX version written on every object
+pub const SPEC_VERSION: &str = "2.1";
+
+/// Namespace the STIX spec mandates for deterministic observable IDs
+const SCO_NAMESPACE: Uuid = Uuid::from_u128(0x00ab_edb4_aa42_466c_9c01_fed2_3315_a9b7);
+
+/// Namespace for i1's own deterministic domain object IDs
+const SDO_NAMESPACE: Uuid = Uuid::from_u128(0x3c5e_91d2_7a04_5b8f_a1e6_0d4b_72c9_e813);
+
+/// A STIX 2.1 bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(rename = "type")]
+    kind: BundleType,
+    /// Bundle ID (`bundle--<uuid>`)
+    pub id: String,
+    /// Contained objects
+    pub objects: Vec<StixObject>,
+}
+
+/// The only valid `type` for a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BundleType {
+    Bundle,
+}
+
+impl Bundle {
+    /// Serialize as pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parse a bundle and check its structure
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not a bundle or fails [`Bundle::validate`].
+    pub fn from_json(json: &str) -> Result<Self> {
+        let bundle: Self = serde_json::from_str(json)?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    /// Check the structural rules consumers rely on
+    ///
+    /// Every ID must be `<type>--<uuid>` and unique, every object must be
+    /// STIX 2.1, every reference must resolve to an object in the bundle,
+    /// and observations must cover a non-empty time range.
+    ///
+    /// # Errors
+    ///
+    /// Returns `I1Error::Stix` describing the first violation.
+    pub fn validate(&self) -> Result<()> {
+        check_id(&self.id, "bundle")?;
+
+        let mut ids = HashSet::new();
+        for object in &self.objects {
+            check_id(object.id(), object.type_name())?;
+            if object.spec_version() != SPEC_VERSION {
+                return Err(I1Error::Stix(format!(
+                    "{} has spec_version {}",
+                    object.id(),
+                    object.spec_version()
+                )));
+            }
+            if !ids.insert(object.id()) {
+                return Err(I1Error::Stix(format!("duplicate id {}", object.id())));
+            }
+        }
+
+        for object in &self.objects {
+            if let Some(dangling) = object.refs().into_iter().find(|r| !ids.contains(r)) {
+                return Err(I1Error::Stix(format!(
+                    "{} references {dangling}, which is not in the bundle",
+                    object.id()
+                )));
+            }
+            if let StixObject::ObservedData(observed) = object {
+                if observed.object_refs.is_empty() || observed.number_observed == 0 {
+                    return Err(I1Error::Stix(format!("{} observes nothing", observed.id)));
+                }
+                if observed.first_observed > observed.last_observed {
+                    return Err(I1Error::Stix(format!(
+                        "{} ends before it starts",
+                        observed.id
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Any object a bundle can carry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum StixObject {
+    /// Who produced the bundle
+    Identity(Identity),
+    /// Detection pattern for an IP
+    Indicator(Indicator),
+    /// An observation of open services
+    ObservedData(ObservedData),
+    /// A CVE seen on a host
+    Vulnerability(Vulnerability),
+    /// Link between two objects
+    Relationship(Relationship),
+    /// IPv4 address observable
+    Ipv4Addr(IpAddress),
+    /// IPv6 address observable
+    Ipv6Addr(IpAddress),
+    /// Connection endpoint observable
+    NetworkTraffic(NetworkTraffic),
+    /// Product observable
+    Software(Software),
+}
+
+impl StixObject {
+    /// The STIX `type` of this object
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Identity(_) => "identity",
+            Self::Indicator(_) => "indicator",
+            Self::ObservedData(_) => "observed-data",
+            Self::Vulnerability(_) => "vulnerability",
+            Self::Relationship(_) => "relationship",
+            Self::Ipv4Addr(_) => "ipv4-addr",
+            Self::Ipv6Addr(_) => "ipv6-addr",
+            Self::NetworkTraffic(_) => "network-traffic",
+            Self::Software(_) => "software",
+        }
+    }
+
+    /// Object ID
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Identity(o) => &o.id,
+            Self::Indicator(o) => &o.id,
+            Self::ObservedData(o) => &o.id,
+            Self::Vulnerability(o) => &o.id,
+            Self::Relationship(o) => &o.id,
+            Self::Ipv4Addr(o) | Self::Ipv6Addr(o) => &o.id,
+            Self::NetworkTraffic(o) => &o.id,
+            Self::Software(o) => &o.id,
+        }
+    }
+
+    fn spec_version(&self) -> &str {
+        match self {
+            Self::Identity(o) => &o.spec_version,
+            Self::Indicator(o) => &o.spec_version,
+            Self::ObservedData(o) => &o.spec_version,
+            Self::Vulnerability(o) => &o.spec_version,
+            Self::Relationship(o) => &o.spec_version,
+            Self::Ipv4Addr(o) | Self::Ipv6Addr(o) => &o.spec_version,
+            Self::NetworkTraffic(o) => &o.spec_version,
+            Self::Software(o) => &o.spec_version,
+        }
+    }
+
+    /// IDs of the other objects this one points at
+    fn refs(&self) -> Vec<&str> {
+        match self {
+            Self::Identity(_) | Self::Ipv4Addr(_) | Self::Ipv6Addr(_) | Self::Software(_) => {
+                Vec::new()
+            }
+            Self::Indicator(o) => vec![o.created_by_ref.as_str()],
+            Self::Vulnerability(o) => vec![o.created_by_ref.as_str()],
+            Self::ObservedData(o) => std::iter::once(o.created_by_ref.as_str())
+                .chain(o.object_refs.iter().map(String::as_str))
+                .collect(),
+            Self::Relationship(o) => vec![
+                o.created_by_ref.as_str(),
+                o.source_ref.as_str(),
+                o.target_ref.as_str(),
+            ],
+            Self::NetworkTraffic(o) => vec![o.dst_ref.as_str()],
+        }
+    }
+}
+
+/// `identity` SDO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Creation time
+    #[serde(with = "stix_time")]
+    pub created: DateTime<Utc>,
+    /// Last modification time
+    #[serde(with = "stix_time")]
+    pub modified: DateTime<Utc>,
+    /// Display name
+    pub name: String,
+    /// Kind of entity (`system` for i1 itself)
+    pub identity_class: String,
+}
+
+/// `indicator` SDO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Indicator {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Creation time
+    #[serde(with = "stix_time")]
+    pub created: DateTime<Utc>,
+    /// Last modification time
+    #[serde(with = "stix_time")]
+    pub modified: DateTime<Utc>,
+    /// Producer identity
+    pub created_by_ref: String,
+    /// Display name
+    pub name: String,
+    /// Owning organization and ASN, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Indicator categories
+    pub indicator_types: Vec<String>,
+    /// Detection pattern
+    pub pattern: String,
+    /// Pattern language (`stix`)
+    pub pattern_type: String,
+    /// When the indicator became valid
+    #[serde(with = "stix_time")]
+    pub valid_from: DateTime<Utc>,
+    /// Provider tags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+/// `observed-data` SDO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservedData {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Creation time
+    #[serde(with = "stix_time")]
+    pub created: DateTime<Utc>,
+    /// Last modification time
+    #[serde(with = "stix_time")]
+    pub modified: DateTime<Utc>,
+    /// Producer identity
+    pub created_by_ref: String,
+    /// Start of the observation window
+    #[serde(with = "stix_time")]
+    pub first_observed: DateTime<Utc>,
+    /// End of the observation window
+    #[serde(with = "stix_time")]
+    pub last_observed: DateTime<Utc>,
+    /// Times the data was seen
+    pub number_observed: u32,
+    /// Observables that were seen
+    pub object_refs: Vec<String>,
+}
+
+/// `vulnerability` SDO
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vulnerability {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Creation time
+    #[serde(with = "stix_time")]
+    pub created: DateTime<Utc>,
+    /// Last modification time
+    #[serde(with = "stix_time")]
+    pub modified: DateTime<Utc>,
+    /// Producer identity
+    pub created_by_ref: String,
+    /// CVE identifier
+    pub name: String,
+    /// Provider summary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// CVE reference and provider links
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub external_references: Vec<ExternalReference>,
+    /// CVSS score reported by the provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_i1_cvss: Option<f64>,
+}
+
+/// Pointer to information outside the bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalReference {
+    /// Source (`cve` for CVE IDs)
+    pub source_name: String,
+    /// Identifier within the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    /// Link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// `relationship` SRO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relationship {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Creation time
+    #[serde(with = "stix_time")]
+    pub created: DateTime<Utc>,
+    /// Last modification time
+    #[serde(with = "stix_time")]
+    pub modified: DateTime<Utc>,
+    /// Producer identity
+    pub created_by_ref: String,
+    /// Relationship kind
+    pub relationship_type: String,
+    /// Source object
+    pub source_ref: String,
+    /// Target object
+    pub target_ref: String,
+}
+
+/// `ipv4-addr` / `ipv6-addr` SCO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpAddress {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// The address
+    pub value: String,
+}
+
+/// `network-traffic` SCO for an open port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkTraffic {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Address the service listens on
+    pub dst_ref: String,
+    /// Port the service listens on
+    pub dst_port: u16,
+    /// Transport protocol (`tcp` or `udp`)
+    pub protocols: Vec<String>,
+}
+
+/// `software` SCO for a fingerprinted product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Software {
+    /// Object ID
+    pub id: String,
+    /// STIX version
+    pub spec_version: String,
+    /// Product name
+    pub name: String,
+    /// Product version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// CPE 2.3 identifier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpe: Option<String>,
+}
+
+/// One open service, from either a host's banners or a search match
+struct ServiceView<'a> {
+    port: u16,
+    transport: Transport,
+    product: Option<&'a str>,
+    version: Option<&'a str>,
+    cpe: Option<&'a str>,
+    seen: DateTime<Utc>,
+}
+
+/// Accumulates findings into a [`Bundle`]
+///
+/// Objects are deduplicated by ID, so the same IP, service, or CVE seen
+/// in several inputs appears once.
+#[derive(Debug, Clone)]
+pub struct BundleBuilder {
+    created: DateTime<Utc>,
+    identity: String,
+    objects: Vec<StixObject>,
+    ids: HashSet<String>,
+}
+
+impl BundleBuilder {
+    /// Start a bundle; `created` stamps every object (to the millisecond)
+    #[must_use]
+    pub fn new(created: DateTime<Utc>) -> Self {
+        let created = created.trunc_subsecs(3);
+        let identity = sdo_id("identity", "i1");
+        let mut builder = Self {
+            created,
+            identity: identity.clone(),
+            objects: Vec::new(),
+            ids: HashSet::new(),
+        };
+        builder.push(StixObject::Identity(Identity {
+            id: identity,
+            spec_version: SPEC_VERSION.into(),
+            created,
+            modified: created,
+            name: "i1".into(),
+            identity_class: "system".into(),
+        }));
+        builder
+    }
+
+    /// Add a host, its services, and its vulnerabilities
+    ///
+    /// Hosts without a parseable IP are skipped.
+    pub fn add_host(&mut self, host: &HostInfo) -> &mut Self {
+        let Some(ip) = host.ip_addr() else {
+            return self;
+        };
+        let seen = host
+            .last_update
+            .map_or(self.created, |t| t.trunc_subsecs(3));
+        let (ip_ref, indicator) = self.add_ip(
+            ip,
+            host.org.as_deref(),
+            host.asn.as_deref(),
+            &host.tags,
+            seen,
+        );
+
+        for service in &host.data {
+            self.add_service(
+                &ip_ref,
+                &ServiceView {
+                    port: service.port,
+                    transport: service.transport,
+                    product: service.product.as_deref(),
+                    version: service.version.as_deref(),
+                    cpe: service.cpe.first().map(String::as_str),
+                    seen: observed_at(service.timestamp.as_deref(), seen),
+                },
+            );
+            self.add_vulns(
+                &indicator,
+                service.vulns.iter().map(|(cve, info)| (cve, Some(info))),
+            );
+        }
+        self.add_vulns(&indicator, host.vulns.iter().map(|cve| (cve, None)));
+        self
+    }
+
+    /// Add a search match and its vulnerabilities
+    pub fn add_match(&mut self, found: &SearchMatch) -> &mut Self {
+        let Some(ip) = found.ip_addr() else {
+            return self;
+        };
+        let seen = observed_at(found.timestamp.as_deref(), self.created);
+        let (ip_ref, indicator) = self.add_ip(
+            ip,
+            found.org.as_deref(),
+            found.asn.as_deref(),
+            &found.tags,
+            seen,
+        );
+
+        self.add_service(
+            &ip_ref,
+            &ServiceView {
+                port: found.port,
+                transport: found.transport,
+                product: found.product.as_deref(),
+                version: found.version.as_deref(),
+                cpe: found.cpe.first().map(String::as_str),
+                seen,
+            },
+        );
+        self.add_vulns(
+            &indicator,
+            found.vulns.iter().map(|(cve, info)| (cve, Some(info))),
+        );
+        self
+    }
+
+    /// Finish the bundle
+    #[must_use]
+    pub fn build(self) -> Bundle {
+        let mut ids: Vec<&str> = self.objects.iter().map(StixObject::id).collect();
+        ids.sort_unstable();
+        Bundle {
+            kind: BundleType::Bundle,
+            id: format!(
+                "bundle--{}",
+                Uuid::new_v5(&SDO_NAMESPACE, ids.join(",").as_bytes())
+            ),
+            objects: self.objects,
+        }
+    }
+
+    /// Add an IP observable and its indicator; returns both IDs
+    fn add_ip(
+        &mut self,
+        ip: IpAddr,
+        org: Option<&str>,
+        asn: Option<&str>,
+        tags: &[String],
+        seen: DateTime<Utc>,
+    ) -> (String, String) {
+        let kind = if ip.is_ipv4() {
+            "ipv4-addr"
+        } else {
+            "ipv6-addr"
+        };
+        let value = ip.to_string();
+        let ip_ref = sco_id(kind, &serde_json::json!({ "value": value }));
+        let address = IpAddress {
+            id: ip_ref.clone(),
+            spec_version: SPEC_VERSION.into(),
+            value: value.clone(),
+        };
+        self.push(if ip.is_ipv4() {
+            StixObject::Ipv4Addr(address)
+        } else {
+            StixObject::Ipv6Addr(address)
+        });
+
+        let indicator = sdo_id("indicator", &value);
+        let description = [org, asn]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.push(StixObject::Indicator(Indicator {
+            id: indicator.clone(),
+            spec_version: SPEC_VERSION.into(),
+            created: self.created,
+            modified: self.created,
+            created_by_ref: self.identity.clone(),
+            name: value.clone(),
+            description: (!description.is_empty()).then_some(description),
+            indicator_types: vec!["unknown".into()],
+            pattern: format!("[{kind}:value = '{value}']"),
+            pattern_type: "stix".into(),
+            valid_from: seen.min(self.created),
+            labels: tags.to_vec(),
+        }));
+        (ip_ref, indicator)
+    }
+
+    /// Add a service's traffic and software observables and the observation
+    fn add_service(&mut self, ip_ref: &str, service: &ServiceView<'_>) {
+        let protocol = service.transport.to_string();
+        let traffic = sco_id(
+            "network-traffic",
+            &serde_json::json!({
+                "dst_port": service.port,
+                "dst_ref": ip_ref,
+                "protocols": [protocol],
+            }),
+        );
+        self.push(StixObject::NetworkTraffic(NetworkTraffic {
+            id: traffic.clone(),
+            spec_version: SPEC_VERSION.into(),
+            dst_ref: ip_ref.to_string(),
+            dst_port: service.port,
+            protocols: vec![protocol],
+        }));
+
+        let mut object_refs = vec![ip_ref.to_string(), traffic.clone()];
+        if let Some(name) = service.product {
+            let software = sco_id(
+                "software",
+                &serde_json::json!({ "cpe": service.cpe, "name": name, "version": service.version }),
+            );
+            self.push(StixObject::Software(Software {
+                id: software.clone(),
+                spec_version: SPEC_VERSION.into(),
+                name: name.to_string(),
+                version: service.version.map(String::from),
+                cpe: service.cpe.map(String::from),
+            }));
+            object_refs.push(software);
+        }
+
+        self.push(StixObject::ObservedData(ObservedData {
+            id: sdo_id(
+                "observed-data",
+                &format!("{traffic}@{}", service.seen.timestamp_millis()),
+            ),
+            spec_version: SPEC_VERSION.into(),
+            created: self.created,
+            modified: self.created,
+            created_by_ref: self.identity.clone(),
+            first_observed: service.seen,
+            last_observed: service.seen,
+            number_observed: 1,
+            object_refs,
+        }));
+    }
+
+    /// Add vulnerabilities, each related to `indicator`
+    ///
+    /// The first sighting of a CVE wins, so add ones with details first.
+    fn add_vulns<'a>(
+        &mut self,
+        indicator: &str,
+        vulns: impl Iterator<Item = (&'a String, Option<&'a VulnInfo>)>,
+    ) {
+        for (cve, info) in vulns {
+            let vulnerability = sdo_id("vulnerability", cve);
+            let mut external_references = vec![ExternalReference {
+                source_name: "cve".into(),
+                external_id: Some(cve.clone()),
+                url: None,
+            }];
+            if let Some(info) = info {
+                external_references.extend(info.references.iter().map(|url| ExternalReference {
+                    source_name: "reference".into(),
+                    external_id: None,
+                    url: Some(url.clone()),
+                }));
+            }
+            self.push(StixObject::Vulnerability(Vulnerability {
+                id: vulnerability.clone(),
+                spec_version: SPEC_VERSION.into(),
+                created: self.created,
+                modified: self.created,
+                created_by_ref: self.identity.clone(),
+                name: cve.clone(),
+                description: info.and_then(|i| i.summary.clone()),
+                external_references,
+                x_i1_cvss: info.and_then(|i| i.cvss),
+            }));
+
+            self.push(StixObject::Relationship(Relationship {
+                id: sdo_id("relationship", &format!("{indicator}>{vulnerability}")),
+                spec_version: SPEC_VERSION.into(),
+                created: self.created,
+                modified: self.created,
+                created_by_ref: self.identity.clone(),
+                relationship_type: "related-to".into(),
+                source_ref: indicator.to_string(),
+                target_ref: vulnerability,
+            }));
+        }
+    }
+
+    fn push(&mut self, object: StixObject) {
+        if self.ids.insert(object.id().to_string()) {
+            self.objects.push(object);
+        }
+    }
+}
+
+/// Deterministic observable ID from its ID-contributing properties
+///
+/// `serde_json` sorts object keys, which gives the canonical form the
+/// spec hashes.
+fn sco_id(kind: &str, contributing: &serde_json::Value) -> String {
+    format!(
+        "{kind}--{}",
+        Uuid::new_v5(&SCO_NAMESPACE, contributing.to_string().as_bytes())
+    )
+}
+
+/// Deterministic domain object ID from a natural key
+fn sdo_id(kind: &str, key: &str) -> String {
+    format!(
+        "{kind}--{}",
+        Uuid::new_v5(&SDO_NAMESPACE, format!("{kind}:{key}").as_bytes())
+    )
+}
+
+/// Parse a provider timestamp, falling back to `default`
+fn observed_at(raw: Option<&str>, default: DateTime<Utc>) -> DateTime<Utc> {
+    raw.and_then(parse_timestamp)
+        .map_or(default, |t| t.trunc_subsecs(3))
+}
+
+fn check_id(id: &str, kind: &str) -> Result<()> {
+    let uuid = id
+        .strip_prefix(kind)
+        .and_then(|rest| rest.strip_prefix("--"))
+        .ok_or_else(|| I1Error::Stix(format!("id {id} is not a {kind} id")))?;
+    Uuid::parse_str(uuid).map_err(|e| I1Error::Stix(format!("id {id}: {e}")))?;
+    Ok(())
+}
+
+/// STIX timestamps: RFC 3339 in UTC with millisecond precision
+mod stix_time {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::Millis, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&raw)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeoLocation, Service};
+    use chrono::TimeZone;
+
+    fn host() -> HostInfo {
+        let mut host: HostInfo = serde_json::from_value(serde_json::json!({
+            "ip_str": "203.0.113.7",
+            "org": "Example Hosting",
+            "asn": "AS64500",
+            "tags": ["self-signed"],
+            "vulns": ["CVE-2023-0001", "CVE-2021-44228"],
+        }))
+        .unwrap();
+        host.last_update = Some(Utc.with_ymd_and_hms(2024, 1, 15, 8, 23, 45).unwrap());
+        host.data = vec![
+            Service::new(443, Transport::Tcp)
+                .with_product("nginx")
+                .with_version("1.18.0")
+                .with_cpe("cpe:2.3:a:f5:nginx:1.18.0:*:*:*:*:*:*:*")
+                .with_timestamp("2024-01-14T10:00:00.123456")
+                .with_vuln(
+                    "CVE-2021-44228",
+                    VulnInfo {
+                        cve: Some("CVE-2021-44228".into()),
+                        verified: false,
+                        cvss: Some(10.0),
+                        summary: Some("Log4Shell".into()),
+                        references: vec!["https://nvd.nist.gov/vuln/detail/CVE-2021-44228".into()],
+                    },
+                ),
+            Service::new(53, Transport::Udp),
+        ];
+        host
+    }
+
+    fn created() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap()
+    }
+
+    fn count(bundle: &Bundle, kind: &str) -> usize {
+        bundle
+            .objects
+            .iter()
+            .filter(|o| o.type_name() == kind)
+            .count()
+    }
+
+    #[test]
+    fn test_host_bundle_round_trips() {
+        let mut builder = BundleBuilder::new(created());
+        builder.add_host(&host());
+        let bundle = builder.build();
+        bundle.validate().unwrap();
+
+        let json = bundle.to_json().unwrap();
+        assert_eq!(Bundle::from_json(&json).unwrap(), bundle);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["type"], "bundle");
+        assert!(json.contains("\"pattern\": \"[ipv4-addr:value = '203.0.113.7']\""));
+        assert!(json.contains("\"first_observed\": \"2024-01-14T10:00:00.123Z\""));
+
+        assert_eq!(count(&bundle, "identity"), 1);
+        assert_eq!(count(&bundle, "ipv4-addr"), 1);
+        assert_eq!(count(&bundle, "indicator"), 1);
+        assert_eq!(count(&bundle, "network-traffic"), 2);
+        assert_eq!(count(&bundle, "observed-data"), 2);
+        assert_eq!(count(&bundle, "software"), 1);
+        assert_eq!(count(&bundle, "vulnerability"), 2);
+        assert_eq!(count(&bundle, "relationship"), 2);
+
+        // The service's detailed CVE wins over the bare host-level one.
+        let log4shell = bundle.objects.iter().find_map(|o| match o {
+            StixObject::Vulnerability(v) if v.name == "CVE-2021-44228" => Some(v),
+            _ => None,
+        });
+        assert_eq!(log4shell.and_then(|v| v.x_i1_cvss), Some(10.0));
+    }
+
+    #[test]
+    fn test_ids_are_deterministic() {
+        let build = |at| {
+            let mut builder = BundleBuilder::new(at);
+            builder.add_host(&host()).add_host(&host());
+            builder.build()
+        };
+        let first = build(created());
+        let second = build(created() + chrono::Duration::hours(1));
+
+        let ids = |b: &Bundle| {
+            b.objects
+                .iter()
+                .map(|o| o.id().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&first), ids(&second));
+        assert_eq!(first.id, second.id);
+    }
+
+    #[test]
+    fn test_search_match_uses_ipv6_pattern() {
+        let found = SearchMatch {
+            ip: None,
+            ip_str: "2001:db8::1".into(),
+            port: 22,
+            transport: Transport::Tcp,
+            hostnames: Vec::new(),
+            domains: Vec::new(),
+            org: None,
+            asn: None,
+            isp: None,
+            os: None,
+            product: Some("OpenSSH".into()),
+            version: None,
+            cpe: Vec::new(),
+            data: None,
+            location: GeoLocation::default(),
+            timestamp: Some("2024-01-10T00:00:00Z".into()),
+            http: None,
+            ssl: None,
+            tags: Vec::new(),
+            vulns: std::collections::HashMap::new(),
+        };
+        let mut builder = BundleBuilder::new(created());
+        builder.add_match(&found);
+        let bundle = Bundle::from_json(&builder.build().to_json().unwrap()).unwrap();
+
+        let pattern = bundle.objects.iter().find_map(|o| match o {
+            StixObject::Indicator(i) => Some(i.pattern.as_str()),
+            _ => None,
+        });
+        assert_eq!(pattern, Some("[ipv6-addr:value = '2001:db8::1']"));
+        assert_eq!(count(&bundle, "ipv6-addr"), 1);
+    }
+
+    #[test]
+    fn test_validate_rejects_dangling_refs() {
+        let mut builder = BundleBuilder::new(created());
+        builder.add_host(&host());
+        let mut bundle = builder.build();
+        bundle
+            .objects
+            .retain(|o| !matches!(o, StixObject::Ipv4Addr(_)));
+
+        let err = Bundle::from_json(&bundle.to_json().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("not in the bundle"), "{err}");
+
+        let wrong_type = r#"{"type": "report", "id": "bundle--00000000-0000-0000-0000-000000000000", "objects": []}"#;
+        assert!(Bundle::from_json(wrong_type).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -30,6 +31,10 @@ pub struct Alert {
     #[serde(default)]
     pub expires: Option<i64>,
 
+    /// Expiration timestamp (absent for alerts that never expire)
+    #[serde(default, deserialize_with = "super::deserialize_timestamp")]
+    pub expiration: Option<DateTime<Utc>>,
+
     /// Whether the alert has expired
     #[serde(default)]
     pub expired: bool,
@@ -46,6 +51,15 @@ impl Alert {
         !self.expired
     }
 
+    /// Returns true if the alert is active and expires within `days` of `now`
+    #[must_use]
+    pub fn expires_within(&self, days: u32, now: DateTime<Utc>) -> bool {
+        self.is_active()
+            && self
+                .expiration
+                .is_some_and(|at| at > now && at <= now + Duration::days(i64::from(days)))
+    }
+
     /// Returns true if this trigger is enabled
     #[must_use]
     pub fn has_trigger(&self, trigger: &str) -> bool {
@@ -53,6 +67,81 @@ impl Alert {
     }
 }
 
+/// Client-side filtering for alert lists
+///
+/// The alert API returns every alert in one response, so these run
+/// locally over the fetched list.
+pub trait AlertListExt: Sized {
+    /// Keep only alerts that have not expired
+    #[must_use]
+    fn active_only(self) -> Self;
+
+    /// Keep only active alerts that expire within `days` from now
+    #[must_use]
+    fn expiring_within(self, days: u32) -> Self;
+}
+
+impl AlertListExt for Vec<Alert> {
+    fn active_only(mut self) -> Self {
+        self.retain(Alert::is_active);
+        self
+    }
+
+    fn expiring_within(mut self, days: u32) -> Self {
+        let now = Utc::now();
+        self.retain(|alert| alert.expires_within(days, now));
+        self
+    }
+}
+
+/// Page of an alert list (1-based)
+///
+/// Applied client-side today; if the API grows server-side paging the
+/// same parameters can be passed through unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlertPage {
+    /// Page number, starting at 1
+    pub page: u32,
+
+    /// Alerts per page
+    pub per_page: u32,
+}
+
+impl AlertPage {
+    /// Default page size
+    pub const DEFAULT_PER_PAGE: u32 = 50;
+
+    /// Create a page request (page 0 is treated as page 1)
+    #[must_use]
+    pub const fn new(page: u32, per_page: u32) -> Self {
+        Self { page, per_page }
+    }
+
+    /// Slice the alerts belonging to this page
+    #[must_use]
+    pub fn apply<'a>(&self, alerts: &'a [Alert]) -> &'a [Alert] {
+        let per_page = self.per_page as usize;
+        let start = (self.page.max(1) as usize - 1).saturating_mul(per_page);
+        let end = start.saturating_add(per_page).min(alerts.len());
+        alerts.get(start..end).unwrap_or_default()
+    }
+
+    /// Number of pages needed for `total` alerts
+    #[must_use]
+    pub const fn page_count(&self, total: usize) -> usize {
+        if self.per_page == 0 {
+            return 0;
+        }
+        total.div_ceil(self.per_page as usize)
+    }
+}
+
+impl Default for AlertPage {
+    fn default() -> Self {
+        Self::new(1, Self::DEFAULT_PER_PAGE)
+    }
+}
+
 /// Alert IP filters
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AlertFilters {
@@ -142,3 +231,71 @@ impl WhitelistEntry {
         format!("{}:{}", self.ip, self.port)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(id: &str, expired: bool, expiration: Option<DateTime<Utc>>) -> Alert {
+        Alert {
+            id: id.into(),
+            name: id.into(),
+            filters: AlertFilters::default(),
+            triggers: HashMap::new(),
+            notifiers: Vec::new(),
+            created: None,
+            expires: None,
+            expiration,
+            expired,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn expiry_filters() {
+        let now = Utc::now();
+        let alerts = vec![
+            alert("soon", false, Some(now + Duration::days(3))),
+            alert("later", false, Some(now + Duration::days(30))),
+            alert("never", false, None),
+            alert("gone", true, Some(now - Duration::days(1))),
+        ];
+
+        let active: Vec<_> = alerts
+            .clone()
+            .active_only()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(active, ["soon", "later", "never"]);
+
+        let expiring: Vec<_> = alerts
+            .expiring_within(7)
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(expiring, ["soon"]);
+    }
+
+    #[test]
+    fn parses_expiration() {
+        let json = r#"{"id": "A1", "name": "office", "expires": 0, "expiration": "2026-03-01T00:00:00.000000"}"#;
+        let alert: Alert = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            alert.expiration,
+            crate::types::parse_timestamp("2026-03-01")
+        );
+    }
+
+    #[test]
+    fn pagination() {
+        let alerts: Vec<_> = (0..5).map(|i| alert(&i.to_string(), false, None)).collect();
+        let page = AlertPage::new(2, 2);
+        let ids: Vec<_> = page.apply(&alerts).iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["2", "3"]);
+        assert_eq!(AlertPage::new(3, 2).apply(&alerts).len(), 1);
+        assert!(AlertPage::new(4, 2).apply(&alerts).is_empty());
+        assert_eq!(AlertPage::new(0, 2).apply(&alerts).len(), 2);
+        assert_eq!(page.page_count(alerts.len()), 3);
+    }
+}
@@ -56,8 +56,33 @@ impl GeoLocation {
             _ => None,
         }
     }
+
+    /// Returns true if the country code is `code` (case-insensitive)
+    #[must_use]
+    pub fn in_country(&self, code: &str) -> bool {
+        self.country_code
+            .as_deref()
+            .is_some_and(|country| country.eq_ignore_ascii_case(code))
+    }
+
+    /// Great-circle distance to `other` in kilometres, if both have coordinates
+    #[must_use]
+    pub fn distance_km(&self, other: &Self) -> Option<f64> {
+        let (lat1, lon1) = self.coordinates()?;
+        let (lat2, lon2) = other.coordinates()?;
+        let half_lat = (lat2 - lat1).to_radians() / 2.0;
+        let half_lon = (lon2 - lon1).to_radians() / 2.0;
+        let a = half_lon
+            .sin()
+            .powi(2)
+            .mul_add(lat1.to_radians().cos() * lat2.to_radians().cos(), half_lat.sin().powi(2));
+        Some(2.0 * EARTH_RADIUS_KM * a.sqrt().asin())
+    }
 }
 
+/// Mean radius of the Earth in kilometres
+const EARTH_RADIUS_KM: f64 = 6371.0088;
+
 /// Transport protocol for a service
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -125,3 +150,30 @@ impl From<&str> for NetworkSpec {
         Self::Cidr(s.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(country: &str, latitude: f64, longitude: f64) -> GeoLocation {
+        GeoLocation {
+            country_code: Some(country.into()),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..GeoLocation::default()
+        }
+    }
+
+    #[test]
+    fn proximity_helpers() {
+        let london = at("GB", 51.5074, -0.1278);
+        let paris = at("FR", 48.8566, 2.3522);
+        let km = london.distance_km(&paris).unwrap();
+        assert!((km - 343.6).abs() < 0.5, "{km}");
+        assert!(london.distance_km(&GeoLocation::default()).is_none());
+
+        assert!(london.in_country("gb"));
+        assert!(!london.in_country("FR"));
+        assert!(!GeoLocation::default().in_country("GB"));
+    }
+}
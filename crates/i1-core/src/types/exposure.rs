@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::HostInfo;
+
+/// Kind of risk an exposed port carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortCategory {
+    /// Remote administration (SSH, RDP, VNC, `WinRM`)
+    Management,
+    /// Databases and caches that should never face the internet
+    Database,
+    /// File sharing (SMB, NFS)
+    FileSharing,
+    /// Protocols that send credentials in cleartext (FTP, Telnet, HTTP)
+    Plaintext,
+}
+
+impl PortCategory {
+    /// How serious an exposure in this category is
+    #[must_use]
+    pub const fn level(self) -> RiskLevel {
+        match self {
+            Self::Management | Self::Database | Self::FileSharing => RiskLevel::High,
+            Self::Plaintext => RiskLevel::Medium,
+        }
+    }
+
+    /// Human-readable label
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Management => "management",
+            Self::Database => "database",
+            Self::FileSharing => "file sharing",
+            Self::Plaintext => "plaintext",
+        }
+    }
+}
+
+/// Severity of a host's exposure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Should be fixed
+    Medium,
+    /// Should not be reachable from the internet
+    High,
+}
+
+/// Which ports fall into which [`PortCategory`]
+///
+/// [`PortRiskMap::default`] covers well-known services; [`PortRiskMap::with`]
+/// and [`PortRiskMap::without`] adjust it, e.g. for a site that runs SSH on
+/// a non-standard port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRiskMap {
+    ports: BTreeMap<u16, PortCategory>,
+}
+
+impl Default for PortRiskMap {
+    fn default() -> Self {
+        use PortCategory::{Database, FileSharing, Management, Plaintext};
+
+        Self::empty()
+            // Remote administration
+            .with(22, Management)
+            .with(3389, Management)
+            .with(5900, Management)
+            .with(5985, Management)
+            .with(5986, Management)
+            // Databases and caches
+            .with(1433, Database)
+            .with(1521, Database)
+            .with(3306, Database)
+            .with(5432, Database)
+            .with(6379, Database)
+            .with(9200, Database)
+            .with(11211, Database)
+            .with(27017, Database)
+            // File sharing
+            .with(139, FileSharing)
+            .with(445, FileSharing)
+            .with(2049, FileSharing)
+            // Cleartext protocols
+            .with(21, Plaintext)
+            .with(23, Plaintext)
+            .with(80, Plaintext)
+            .with(110, Plaintext)
+            .with(143, Plaintext)
+    }
+}
+
+impl PortRiskMap {
+    /// A map with no ports classified
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            ports: BTreeMap::new(),
+        }
+    }
+
+    /// Classify `port` as `category`, replacing any existing entry
+    #[must_use]
+    pub fn with(mut self, port: u16, category: PortCategory) -> Self {
+        self.ports.insert(port, category);
+        self
+    }
+
+    /// Stop classifying `port`
+    #[must_use]
+    pub fn without(mut self, port: u16) -> Self {
+        self.ports.remove(&port);
+        self
+    }
+
+    /// Category of `port`, if it is classified
+    #[must_use]
+    pub fn category(&self, port: u16) -> Option<PortCategory> {
+        self.ports.get(&port).copied()
+    }
+}
+
+/// A classified open port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortExposure {
+    /// Port number
+    pub port: u16,
+
+    /// Risk category
+    pub category: PortCategory,
+
+    /// Product detected on the port (e.g., "Redis")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+}
+
+/// Summary of the risky services a host exposes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskProfile {
+    /// Classified ports, most severe category first
+    pub exposures: Vec<PortExposure>,
+
+    /// Open ports that no category covers
+    pub unclassified: Vec<u16>,
+
+    /// Overall level: the worst exposure's, or `None` with no exposures
+    pub level: Option<RiskLevel>,
+}
+
+impl RiskProfile {
+    /// Exposures in `category`
+    pub fn in_category(&self, category: PortCategory) -> impl Iterator<Item = &PortExposure> {
+        self.exposures
+            .iter()
+            .filter(move |e| e.category == category)
+    }
+
+    /// Returns true if any exposure is [`RiskLevel::High`]
+    #[must_use]
+    pub fn is_high_risk(&self) -> bool {
+        self.level == Some(RiskLevel::High)
+    }
+}
+
+impl HostInfo {
+    /// Classify this host's open ports with `map`
+    ///
+    /// Ports come from both `ports` and the service banners in `data`, so
+    /// a product name is attached wherever a banner identified one.
+    #[must_use]
+    pub fn risk_profile(&self, map: &PortRiskMap) -> RiskProfile {
+        let mut ports: BTreeMap<u16, Option<String>> =
+            self.ports.iter().map(|&port| (port, None)).collect();
+        for service in &self.data {
+            let product = ports.entry(service.port).or_default();
+            if product.is_none() {
+                product.clone_from(&service.product);
+            }
+        }
+
+        let mut profile = RiskProfile::default();
+        for (port, product) in ports {
+            match map.category(port) {
+                Some(category) => profile.exposures.push(PortExposure {
+                    port,
+                    category,
+                    product,
+                }),
+                None => profile.unclassified.push(port),
+            }
+        }
+        profile
+            .exposures
+            .sort_by_key(|e| (std::cmp::Reverse(e.category.level()), e.category, e.port));
+        profile.level = profile.exposures.first().map(|e| e.category.level());
+        profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Service;
+
+    fn host(ports: &[u16]) -> HostInfo {
+        serde_json::from_value(serde_json::json!({
+            "ip_str": "192.0.2.10",
+            "ports": ports,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn classifies_default_ports() {
+        let mut host = host(&[80, 443, 6379, 3389]);
+        host.data.push(Service {
+            product: Some("Redis".into()),
+            ..Service::new(6379, crate::Transport::Tcp)
+        });
+
+        let profile = host.risk_profile(&PortRiskMap::default());
+        let ports: Vec<_> = profile.exposures.iter().map(|e| e.port).collect();
+        assert_eq!(ports, [3389, 6379, 80]);
+        assert_eq!(profile.exposures[1].product.as_deref(), Some("Redis"));
+        assert_eq!(profile.unclassified, [443]);
+        assert_eq!(profile.level, Some(RiskLevel::High));
+        assert!(profile.is_high_risk());
+        assert_eq!(profile.in_category(PortCategory::Database).count(), 1);
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let map = PortRiskMap::default()
+            .with(2222, PortCategory::Management)
+            .without(80);
+
+        let profile = host(&[80, 2222]).risk_profile(&map);
+        assert_eq!(profile.exposures.len(), 1);
+        assert_eq!(profile.exposures[0].category, PortCategory::Management);
+        assert_eq!(profile.unclassified, [80]);
+    }
+
+    #[test]
+    fn no_exposures_has_no_level() {
+        let profile = host(&[443]).risk_profile(&PortRiskMap::default());
+        assert!(profile.exposures.is_empty());
+        assert_eq!(profile.level, None);
+
+        let json = serde_json::to_value(host(&[21]).risk_profile(&PortRiskMap::default())).unwrap();
+        assert_eq!(json["level"], "medium");
+        assert_eq!(json["exposures"][0]["category"], "plaintext");
+    }
+}
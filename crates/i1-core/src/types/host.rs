@@ -1,4 +1,5 @@
 use super::{GeoLocation, Transport};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -57,9 +58,13 @@ pub struct HostInfo {
     #[serde(default)]
     pub data: Vec<Service>,
 
-    /// Last time the host was scanned
-    #[serde(default)]
-    pub last_update: Option<String>,
+    /// Last time the host was scanned (parsed from the provider's format)
+    #[serde(default, deserialize_with = "super::deserialize_timestamp")]
+    pub last_update: Option<DateTime<Utc>>,
+
+    /// Provider's original `last_update` string, when an adapter kept it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_update_raw: Option<String>,
 }
 
 impl HostInfo {
@@ -80,10 +85,144 @@ impl HostInfo {
     pub fn service_count(&self) -> usize {
         self.data.len()
     }
+
+    /// Banners grouped by port, oldest first
+    ///
+    /// With `--history` a port can carry many observations. Banners
+    /// without a parsable timestamp sort before the rest; ties keep their
+    /// order in `data`.
+    #[must_use]
+    pub fn banners_by_port(&self) -> HashMap<u16, Vec<&Service>> {
+        let mut ports: HashMap<u16, Vec<&Service>> = HashMap::new();
+        for service in &self.data {
+            ports.entry(service.port).or_default().push(service);
+        }
+        for banners in ports.values_mut() {
+            banners.sort_by_key(|service| service.observed_at());
+        }
+        ports
+    }
+
+    /// The most recent banner on each port
+    #[must_use]
+    pub fn latest_per_port(&self) -> HashMap<u16, &Service> {
+        self.banners_by_port()
+            .into_iter()
+            .filter_map(|(port, banners)| banners.last().map(|latest| (port, *latest)))
+            .collect()
+    }
 }
 
-/// Individual service/banner information
+/// Host summary without banners, as returned with Shodan's `minify=true`
+///
+/// Bulk searches rarely need the per-service `data`, and skipping it keeps
+/// a `search --all` run small. Converts losslessly to and from
+/// [`HostInfo`], apart from the banners and the raw `last_update`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinHostInfo {
+    /// IP address as string
+    pub ip_str: String,
+
+    /// Hostnames associated with this IP
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+
+    /// Domains associated with this IP
+    #[serde(default)]
+    pub domains: Vec<String>,
+
+    /// Organization that owns the IP
+    #[serde(default)]
+    pub org: Option<String>,
+
+    /// Autonomous System Number
+    #[serde(default)]
+    pub asn: Option<String>,
+
+    /// Internet Service Provider
+    #[serde(default)]
+    pub isp: Option<String>,
+
+    /// Operating system (if detected)
+    #[serde(default)]
+    pub os: Option<String>,
+
+    /// Open ports detected
+    #[serde(default)]
+    pub ports: Vec<u16>,
+
+    /// Known vulnerabilities (CVE IDs)
+    #[serde(default)]
+    pub vulns: Vec<String>,
+
+    /// Tags assigned to this host
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Geographic location
+    #[serde(flatten, default)]
+    pub location: GeoLocation,
+
+    /// Last time the host was scanned
+    #[serde(default, deserialize_with = "super::deserialize_timestamp")]
+    pub last_update: Option<DateTime<Utc>>,
+}
+
+impl MinHostInfo {
+    /// Returns the IP address parsed from `ip_str`
+    #[must_use]
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.ip_str.parse().ok()
+    }
+}
+
+impl From<HostInfo> for MinHostInfo {
+    fn from(host: HostInfo) -> Self {
+        Self {
+            ip_str: host.ip_str,
+            hostnames: host.hostnames,
+            domains: host.domains,
+            org: host.org,
+            asn: host.asn,
+            isp: host.isp,
+            os: host.os,
+            ports: host.ports,
+            vulns: host.vulns,
+            tags: host.tags,
+            location: host.location,
+            last_update: host.last_update,
+        }
+    }
+}
+
+impl From<MinHostInfo> for HostInfo {
+    fn from(host: MinHostInfo) -> Self {
+        Self {
+            ip: host.ip_addr(),
+            ip_str: host.ip_str,
+            hostnames: host.hostnames,
+            domains: host.domains,
+            org: host.org,
+            asn: host.asn,
+            isp: host.isp,
+            os: host.os,
+            ports: host.ports,
+            vulns: host.vulns,
+            tags: host.tags,
+            location: host.location,
+            data: Vec::new(),
+            last_update: host.last_update,
+            last_update_raw: None,
+        }
+    }
+}
+
+/// Individual service/banner information
+///
+/// Build with [`Service::new`] and the `with_*` methods, or struct update
+/// syntax (`Service { product, ..Service::new(port, transport) }`) when
+/// mapping optional provider fields, so new fields don't break adapters.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Service {
     /// Port number
     pub port: u16,
@@ -149,6 +288,115 @@ pub struct Service {
     pub os: Option<String>,
 }
 
+impl Service {
+    /// When the banner was collected, if its timestamp parses
+    #[must_use]
+    pub fn observed_at(&self) -> Option<DateTime<Utc>> {
+        self.timestamp.as_deref().and_then(super::parse_timestamp)
+    }
+
+    /// Create a service with only port and transport set
+    #[must_use]
+    pub fn new(port: u16, transport: Transport) -> Self {
+        Self {
+            port,
+            transport,
+            ..Self::default()
+        }
+    }
+
+    /// Set the product name
+    #[must_use]
+    pub fn with_product(mut self, product: impl Into<String>) -> Self {
+        self.product = Some(product.into());
+        self
+    }
+
+    /// Set the product version
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Set the raw banner data
+    #[must_use]
+    pub fn with_banner(mut self, banner: impl Into<String>) -> Self {
+        self.data = Some(banner.into());
+        self
+    }
+
+    /// Set the collection timestamp
+    #[must_use]
+    pub fn with_timestamp(mut self, timestamp: impl Into<String>) -> Self {
+        self.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Add a CPE identifier
+    #[must_use]
+    pub fn with_cpe(mut self, cpe: impl Into<String>) -> Self {
+        self.cpe.push(cpe.into());
+        self
+    }
+
+    /// Add a tag
+    #[must_use]
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Add a vulnerability
+    #[must_use]
+    pub fn with_vuln(mut self, cve: impl Into<String>, info: VulnInfo) -> Self {
+        self.vulns.insert(cve.into(), info);
+        self
+    }
+
+    /// Set HTTP-specific data
+    #[must_use]
+    pub fn with_http(mut self, http: HttpData) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Set SSL/TLS-specific data
+    #[must_use]
+    pub fn with_ssl(mut self, ssl: SslData) -> Self {
+        self.ssl = Some(ssl);
+        self
+    }
+
+    /// Set SSH-specific data
+    #[must_use]
+    pub fn with_ssh(mut self, ssh: SshData) -> Self {
+        self.ssh = Some(ssh);
+        self
+    }
+
+    /// Set the device type
+    #[must_use]
+    pub fn with_devicetype(mut self, devicetype: impl Into<String>) -> Self {
+        self.devicetype = Some(devicetype.into());
+        self
+    }
+
+    /// Set the module-provided service info
+    #[must_use]
+    pub fn with_info(mut self, info: impl Into<String>) -> Self {
+        self.info = Some(info.into());
+        self
+    }
+
+    /// Set the operating system
+    #[must_use]
+    pub fn with_os(mut self, os: impl Into<String>) -> Self {
+        self.os = Some(os.into());
+        self
+    }
+}
+
 /// Shodan crawler module information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShodanModule {
@@ -480,3 +728,147 @@ pub struct VulnInfo {
     #[serde(default)]
     pub references: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn banners_group_by_port_in_time_order() {
+        let mut host: HostInfo = serde_json::from_str(r#"{"ip_str": "1.2.3.4"}"#).unwrap();
+        host.data = vec![
+            Service::new(22, Transport::Tcp)
+                .with_timestamp("2024-03-01T00:00:00.000000")
+                .with_version("9.6"),
+            Service::new(80, Transport::Tcp).with_timestamp("2024-01-01T00:00:00Z"),
+            Service::new(22, Transport::Tcp)
+                .with_timestamp("2023-06-01T00:00:00.000000")
+                .with_version("8.2"),
+            Service::new(22, Transport::Tcp).with_version("unknown"),
+        ];
+
+        let ports = host.banners_by_port();
+        assert_eq!(ports.len(), 2);
+        let versions: Vec<_> = ports[&22]
+            .iter()
+            .map(|service| service.version.as_deref())
+            .collect();
+        assert_eq!(versions, [Some("unknown"), Some("8.2"), Some("9.6")]);
+
+        let latest = host.latest_per_port();
+        assert_eq!(latest[&22].version.as_deref(), Some("9.6"));
+        assert_eq!(latest[&80].port, 80);
+        assert!(!latest.contains_key(&443));
+    }
+
+    #[test]
+    fn new_service_sets_only_port_and_transport() {
+        let service = Service::new(53, Transport::Udp);
+        assert_eq!(service.port, 53);
+        assert_eq!(service.transport, Transport::Udp);
+        assert!(service.product.is_none());
+        assert!(service.version.is_none());
+        assert!(service.cpe.is_empty());
+        assert!(service.data.is_none());
+        assert!(service.timestamp.is_none());
+        assert!(service.shodan_module.is_none());
+        assert!(service.http.is_none());
+        assert!(service.ssl.is_none());
+        assert!(service.ssh.is_none());
+        assert!(service.vulns.is_empty());
+        assert!(service.tags.is_empty());
+        assert!(service.devicetype.is_none());
+        assert!(service.info.is_none());
+        assert!(service.os.is_none());
+        assert!(service.observed_at().is_none());
+    }
+
+    #[test]
+    fn service_builders_set_their_fields() {
+        let http: HttpData = serde_json::from_str(r#"{"status": 200}"#).unwrap();
+        let ssl: SslData = serde_json::from_str(r#"{"jarm": "2ad2ad16d2ad"}"#).unwrap();
+        let ssh: SshData = serde_json::from_str(r#"{"hassh": "ec7378c1"}"#).unwrap();
+        let vuln: VulnInfo = serde_json::from_str(r#"{"cvss": 8.1}"#).unwrap();
+
+        let service = Service::new(443, Transport::Tcp)
+            .with_product("nginx")
+            .with_version("1.25.3")
+            .with_banner("HTTP/1.1 200 OK")
+            .with_timestamp("2024-01-01T00:00:00Z")
+            .with_cpe("cpe:/a:nginx:nginx")
+            .with_cpe("cpe:/o:linux:linux_kernel")
+            .with_tag("cdn")
+            .with_tag("cloud")
+            .with_vuln("CVE-2023-44487", vuln)
+            .with_http(http)
+            .with_ssl(ssl)
+            .with_ssh(ssh)
+            .with_devicetype("load balancer")
+            .with_info("reverse proxy")
+            .with_os("Linux");
+
+        assert_eq!(service.port, 443);
+        assert_eq!(service.transport, Transport::Tcp);
+        assert_eq!(service.product.as_deref(), Some("nginx"));
+        assert_eq!(service.version.as_deref(), Some("1.25.3"));
+        assert_eq!(service.data.as_deref(), Some("HTTP/1.1 200 OK"));
+        assert_eq!(service.timestamp.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert!(service.observed_at().is_some());
+        assert_eq!(
+            service.cpe,
+            ["cpe:/a:nginx:nginx", "cpe:/o:linux:linux_kernel"]
+        );
+        assert_eq!(service.tags, ["cdn", "cloud"]);
+        assert_eq!(service.vulns.len(), 1);
+        assert_eq!(service.vulns["CVE-2023-44487"].cvss, Some(8.1));
+        assert_eq!(service.http.unwrap().status, Some(200));
+        assert_eq!(service.ssl.unwrap().jarm.as_deref(), Some("2ad2ad16d2ad"));
+        assert_eq!(service.ssh.unwrap().hassh.as_deref(), Some("ec7378c1"));
+        assert_eq!(service.devicetype.as_deref(), Some("load balancer"));
+        assert_eq!(service.info.as_deref(), Some("reverse proxy"));
+        assert_eq!(service.os.as_deref(), Some("Linux"));
+        assert!(service.shodan_module.is_none());
+    }
+
+    #[test]
+    fn service_builders_replace_single_values() {
+        let mut second: VulnInfo = serde_json::from_str(r#"{"cvss": 9.8}"#).unwrap();
+        second.verified = true;
+        let service = Service::new(22, Transport::Tcp)
+            .with_product("dropbear")
+            .with_product("OpenSSH")
+            .with_os("FreeBSD")
+            .with_os("Linux")
+            .with_vuln("CVE-2024-6387", serde_json::from_str("{}").unwrap())
+            .with_vuln("CVE-2024-6387", second);
+
+        assert_eq!(service.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(service.os.as_deref(), Some("Linux"));
+        assert_eq!(service.vulns.len(), 1);
+        assert!(service.vulns["CVE-2024-6387"].verified);
+    }
+
+    #[test]
+    fn minified_host_round_trips_through_host_info() {
+        let host: MinHostInfo =
+            serde_json::from_str(include_str!("../../testdata/shodan_host_minified.json")).unwrap();
+        assert_eq!(host.ip_str, "198.51.100.23");
+        assert_eq!(host.ports, [22, 80, 443]);
+        assert_eq!(host.vulns, ["CVE-2023-38408"]);
+        assert_eq!(host.location.country_code.as_deref(), Some("NL"));
+        assert_eq!(host.location.latitude, Some(52.374));
+        assert_eq!(
+            host.last_update.unwrap().to_rfc3339(),
+            "2024-05-02T11:04:37.512+00:00"
+        );
+
+        let full = HostInfo::from(host);
+        assert_eq!(full.ip, "198.51.100.23".parse().ok());
+        assert!(full.data.is_empty());
+        assert_eq!(full.org.as_deref(), Some("Example Hosting B.V."));
+
+        let back = MinHostInfo::from(full);
+        assert_eq!(back.hostnames, ["web-23.example.net"]);
+        assert_eq!(back.location.city.as_deref(), Some("Amsterdam"));
+    }
+}
@@ -2,16 +2,20 @@ mod account;
 mod alert;
 mod common;
 mod dns;
+mod exposure;
 mod host;
 mod notifier;
 mod scan;
 mod search;
+mod timestamp;
 
 pub use account::*;
 pub use alert::*;
 pub use common::*;
 pub use dns::*;
+pub use exposure::*;
 pub use host::*;
 pub use notifier::*;
 pub use scan::*;
 pub use search::*;
+pub use timestamp::*;
@@ -0,0 +1,88 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+
+/// Naive formats seen from providers, interpreted as UTC
+const NAIVE_FORMATS: &[&str] = &[
+    // Shodan: "2024-01-15T08:23:45.123456"
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+];
+
+/// Parse a provider timestamp into UTC
+///
+/// Accepts RFC 3339 (Censys, e.g. `2024-01-15T08:23:45Z` or with an
+/// offset), Shodan's offset-less `2024-01-15T08:23:45.123456`, the same
+/// with a space separator, and bare dates. Returns `None` for anything else.
+#[must_use]
+pub fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    for format in NAIVE_FORMATS {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(naive.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|naive| naive.and_utc())
+}
+
+/// Deserialize an optional timestamp leniently via [`parse_timestamp`]
+///
+/// Unparsable values become `None` rather than failing the whole record.
+///
+/// # Errors
+///
+/// Returns an error only if the value is not a string or null.
+pub fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw = Option::<String>::deserialize(deserializer)?;
+    Ok(raw.as_deref().and_then(parse_timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn parses_shodan_format() {
+        let dt = parse_timestamp("2024-01-15T08:23:45.123456").unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2024, 1, 15));
+        assert_eq!((dt.hour(), dt.minute(), dt.second()), (8, 23, 45));
+    }
+
+    #[test]
+    fn parses_censys_rfc3339() {
+        let zulu = parse_timestamp("2024-01-15T08:23:45Z").unwrap();
+        let offset = parse_timestamp("2024-01-15T10:23:45.5+02:00").unwrap();
+        assert_eq!(zulu.hour(), 8);
+        assert_eq!(offset.hour(), 8);
+        assert!(offset > zulu);
+    }
+
+    #[test]
+    fn parses_space_separated_and_bare_dates() {
+        assert!(parse_timestamp("2024-01-15 08:23:45").is_some());
+        assert_eq!(parse_timestamp("2024-01-15").unwrap().hour(), 0);
+        assert!(parse_timestamp("last tuesday").is_none());
+    }
+
+    #[test]
+    fn host_deserializes_leniently() {
+        let host: crate::HostInfo = serde_json::from_str(
+            r#"{"ip_str":"1.2.3.4","last_update":"2024-01-15T08:23:45.123456"}"#,
+        )
+        .unwrap();
+        assert_eq!(host.last_update.unwrap().day(), 15);
+
+        let host: crate::HostInfo =
+            serde_json::from_str(r#"{"ip_str":"1.2.3.4","last_update":"garbage"}"#).unwrap();
+        assert!(host.last_update.is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! Versioning for the TXT record formats shared between i1 crates.
+//!
+//! i1-audit writes the binary, certificate, attestation and node signal
+//! records that i1-srv publishes, and reads them back from DNS; i1-srv
+//! writes the zone signal records clients poll. Each of these `k=v;k=v`
+//! records carries a `v=` field holding [`WIRE_SCHEMA_VERSION`], and their
+//! decoders call [`check`] so a record from a build with another schema
+//! fails with [`SchemaMismatch`] instead of being misread.
+//!
+//! Records without the field predate it and are read as version 1.
+
+use thiserror::Error;
+
+/// Version of the shared TXT record schemas. Bump it when a field changes
+/// meaning or a required field is added.
+pub const WIRE_SCHEMA_VERSION: u32 = 1;
+
+/// Key of the version field.
+pub const VERSION_KEY: &str = "v";
+
+/// A record written with a schema version this build doesn't read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("record uses wire schema v{found}, this build reads v{expected}")]
+pub struct SchemaMismatch {
+    /// [`WIRE_SCHEMA_VERSION`] of this build.
+    pub expected: u32,
+    /// Version the record carries (0 when it isn't a number).
+    pub found: u32,
+}
+
+/// The `v=` field for this build's records.
+#[must_use]
+pub fn version_field() -> String {
+    format!("{VERSION_KEY}={WIRE_SCHEMA_VERSION}")
+}
+
+/// Check the version field of a `;`-separated record, if it has one.
+///
+/// # Errors
+///
+/// Returns [`SchemaMismatch`] when the record names another version.
+pub fn check(txt: &str) -> Result<(), SchemaMismatch> {
+    txt.split(';')
+        .find_map(|field| field.trim().strip_prefix(VERSION_KEY)?.strip_prefix('='))
+        .map_or(Ok(()), |version| match version.parse() {
+            Ok(WIRE_SCHEMA_VERSION) => Ok(()),
+            parsed => Err(SchemaMismatch {
+                expected: WIRE_SCHEMA_VERSION,
+                found: parsed.unwrap_or(0),
+            }),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_field_is_checked() {
+        assert_eq!(version_field(), "v=1");
+        assert_eq!(check("hash=abc;nodes=3;v=1"), Ok(()));
+        assert_eq!(check("hash=abc;nodes=3"), Ok(()));
+        // Keys that merely start with `v` aren't the version
+        assert_eq!(check("vendor=x;val=2"), Ok(()));
+
+        assert_eq!(
+            check("v=2;hash=abc"),
+            Err(SchemaMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+        assert_eq!(check("hash=abc;v=two").unwrap_err().found, 0);
+        assert_eq!(
+            check("v=7").unwrap_err().to_string(),
+            "record uses wire schema v7, this build reads v1"
+        );
+    }
+}
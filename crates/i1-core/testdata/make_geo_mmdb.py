@@ -0,0 +1,130 @@
+#!/usr/bin/env python3
+"""Write the small MaxMind DB files the `geo` tests read.
+
+The records mirror a few entries of MaxMind's published test databases
+(GeoIP2-City-Test, GeoLite2-ASN-Test) so the tests can run offline.
+Rerun from this directory after changing them:
+
+    python3 make_geo_mmdb.py
+"""
+
+import struct
+
+CITY = [
+    ("81.2.69.128/26", {
+        "city": {"names": {"en": "London"}},
+        "country": {"iso_code": "GB", "names": {"en": "United Kingdom"}},
+        "location": {"latitude": 51.5142, "longitude": -0.0931},
+        "subdivisions": [{"iso_code": "ENG", "names": {"en": "England"}}],
+    }),
+    ("89.160.20.112/28", {
+        "city": {"names": {"en": "Linköping"}},
+        "country": {"iso_code": "SE", "names": {"en": "Sweden"}},
+        "location": {"latitude": 58.4167, "longitude": 15.6167},
+        "postal": {"code": "587 20"},
+        "subdivisions": [{"iso_code": "E", "names": {"en": "Östergötland County"}}],
+    }),
+    ("202.196.224.0/20", {
+        "country": {"iso_code": "PH", "names": {"en": "Philippines"}},
+    }),
+]
+
+ASN = [
+    ("1.128.0.0/11", {
+        "autonomous_system_number": ("uint32", 1221),
+        "autonomous_system_organization": "Telstra Pty Ltd",
+    }),
+    ("89.160.20.112/28", {
+        "autonomous_system_number": ("uint32", 29518),
+        "autonomous_system_organization": "Bredband2 AB",
+    }),
+]
+
+
+def control(kind, size):
+    """Control byte(s) for a field of `kind` (1-15) and `size`."""
+    if size < 29:
+        head, extra = size, b""
+    elif size < 285:
+        head, extra = 29, bytes([size - 29])
+    elif size < 65821:
+        head, extra = 30, (size - 285).to_bytes(2, "big")
+    else:
+        head, extra = 31, (size - 65821).to_bytes(3, "big")
+    if kind <= 7:
+        return bytes([(kind << 5) | head]) + extra
+    return bytes([head, kind - 7]) + extra
+
+
+def uint(kind, value):
+    raw = value.to_bytes((value.bit_length() + 7) // 8, "big") if value else b""
+    return control(kind, len(raw)) + raw
+
+
+def encode(value):
+    if isinstance(value, tuple):
+        kind, number = value
+        return uint({"uint16": 5, "uint32": 6, "uint64": 9}[kind], number)
+    if isinstance(value, str):
+        raw = value.encode()
+        return control(2, len(raw)) + raw
+    if isinstance(value, float):
+        return control(3, 8) + struct.pack(">d", value)
+    if isinstance(value, dict):
+        out = control(7, len(value))
+        for key, item in value.items():
+            out += encode(key) + encode(item)
+        return out
+    if isinstance(value, list):
+        return control(11, len(value)) + b"".join(encode(item) for item in value)
+    raise TypeError(value)
+
+
+def write(path, database_type, records):
+    data = b""
+    nodes = [[None, None]]
+    for network, record in records:
+        offset = len(data)
+        data += encode(record)
+        address, prefix = network.split("/")
+        bits = int.from_bytes(bytes(int(part) for part in address.split(".")), "big")
+        node = 0
+        for depth in range(int(prefix)):
+            bit = (bits >> (31 - depth)) & 1
+            if depth == int(prefix) - 1:
+                nodes[node][bit] = ("data", offset)
+            else:
+                if nodes[node][bit] is None:
+                    nodes.append([None, None])
+                    nodes[node][bit] = ("node", len(nodes) - 1)
+                node = nodes[node][bit][1]
+
+    count = len(nodes)
+
+    def record_value(entry):
+        if entry is None:
+            return count
+        kind, value = entry
+        return value if kind == "node" else count + 16 + value
+
+    tree = b"".join(
+        record_value(left).to_bytes(3, "big") + record_value(right).to_bytes(3, "big")
+        for left, right in nodes
+    )
+    metadata = encode({
+        "binary_format_major_version": ("uint16", 2),
+        "binary_format_minor_version": ("uint16", 0),
+        "build_epoch": ("uint64", 1_700_000_000),
+        "database_type": database_type,
+        "description": {"en": "i1 test database"},
+        "ip_version": ("uint16", 4),
+        "languages": ["en"],
+        "node_count": ("uint32", count),
+        "record_size": ("uint16", 24),
+    })
+    with open(path, "wb") as out:
+        out.write(tree + bytes(16) + data + b"\xab\xcd\xefMaxMind.com" + metadata)
+
+
+write("geo-city-test.mmdb", "GeoLite2-City", CITY)
+write("geo-asn-test.mmdb", "GeoLite2-ASN", ASN)
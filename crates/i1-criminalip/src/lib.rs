@@ -93,7 +93,7 @@ impl CriminalIpProvider {
             .header("x-api-key", &self.inner.api_key)
             .send()
             .await
-            .map_err(|e| I1Error::Http(e.to_string()))?;
+            .map_err(I1Error::from)?;
 
         let status = response.status();
         if !status.is_success() {
@@ -110,10 +110,7 @@ impl CriminalIpProvider {
             };
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))
+        response.json().await.map_err(I1Error::from)
     }
 
     /// Convert Criminal IP response to i1 `HostInfo`
@@ -122,22 +119,10 @@ impl CriminalIpProvider {
             .port
             .into_iter()
             .map(|p| Service {
-                port: p.open_port_no as u16,
-                transport: i1_core::Transport::Tcp,
                 product: p.app_name,
                 version: p.app_version,
-                cpe: vec![],
                 data: p.banner,
-                timestamp: None,
-                shodan_module: None,
-                http: None,
-                ssl: None,
-                ssh: None,
-                vulns: std::collections::HashMap::new(),
-                tags: vec![],
-                devicetype: None,
-                info: None,
-                os: None,
+                ..Service::new(p.open_port_no as u16, i1_core::Transport::Tcp)
             })
             .collect();
 
@@ -191,6 +176,7 @@ impl CriminalIpProvider {
             },
             data: services,
             last_update: None,
+            last_update_raw: None,
         }
     }
 }
@@ -317,6 +303,7 @@ impl SearchProvider for CriminalIpProvider {
                 },
                 data: vec![],
                 last_update: None,
+                last_update_raw: None,
             })
             .collect();
 
@@ -0,0 +1,132 @@
+//! Curated BIN table for honeypot cards.
+//!
+//! A bare network prefix ("4", "51") is flagged by any fraud tool, so cards
+//! start from a 6-8 digit BIN with issuer details a fake checkout page can
+//! render. Every BIN comes from a card network or payment gateway test
+//! range, so no real issuer's cards are imitated. The banks are made up,
+//! except for American Express and Discover, which issue their own cards.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::card::CardNetwork;
+use CardNetwork::{Amex, Discover, Mastercard, Visa};
+use CardTier::{Business, Classic, Debit, Gold, Platinum};
+
+/// Product tier printed on a card.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardTier {
+    #[default]
+    Classic,
+    Gold,
+    Platinum,
+    Business,
+    Debit,
+}
+
+impl std::fmt::Display for CardTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardTier::Classic => write!(f, "Classic"),
+            CardTier::Gold => write!(f, "Gold"),
+            CardTier::Platinum => write!(f, "Platinum"),
+            CardTier::Business => write!(f, "Business"),
+            CardTier::Debit => write!(f, "Debit"),
+        }
+    }
+}
+
+/// One BIN and the issuer details that go with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinInfo {
+    /// Leading digits of every number issued under it.
+    pub bin: &'static str,
+    pub network: CardNetwork,
+    /// Issuing bank's display name.
+    pub issuer: &'static str,
+    pub tier: CardTier,
+    /// ISO 3166-1 alpha-2 country of issue.
+    pub country: &'static str,
+    /// Relative weight among the network's BINs.
+    pub weight: u32,
+}
+
+const fn bin(
+    bin: &'static str,
+    network: CardNetwork,
+    issuer: &'static str,
+    tier: CardTier,
+    country: &'static str,
+    weight: u32,
+) -> BinInfo {
+    BinInfo {
+        bin,
+        network,
+        issuer,
+        tier,
+        country,
+        weight,
+    }
+}
+
+/// The BINs cards are generated from.
+#[rustfmt::skip]
+pub const BIN_TABLE: &[BinInfo] = &[
+    bin("411111", Visa, "Harborview Federal Bank", Classic, "US", 5),
+    bin("424242", Visa, "First Meridian Bank", Platinum, "US", 4),
+    bin("40000566", Visa, "Cobalt Credit Union", Debit, "US", 3),
+    bin("401288", Visa, "Northgate Savings", Gold, "US", 2),
+    bin("400000", Visa, "Albion & Strand Bank", Classic, "GB", 2),
+    bin("450875", Visa, "Lakeshore Trust", Business, "CA", 1),
+    bin("555555", Mastercard, "Summit National Bank", Classic, "US", 4),
+    bin("520082", Mastercard, "Cobalt Credit Union", Debit, "US", 3),
+    bin("510510", Mastercard, "Keystone Commerce Bank", Business, "US", 2),
+    bin("545454", Mastercard, "Thames Valley Bank", Gold, "GB", 2),
+    bin("22230031", Mastercard, "First Meridian Bank", Platinum, "US", 2),
+    bin("222240", Mastercard, "Rheinland Privatbank", Classic, "DE", 1),
+    bin("378282", Amex, "American Express", Platinum, "US", 3),
+    bin("371449", Amex, "American Express", Gold, "US", 3),
+    bin("378734", Amex, "American Express", Business, "US", 1),
+    bin("601111", Discover, "Discover Bank", Classic, "US", 3),
+    bin("601100", Discover, "Discover Bank", Gold, "US", 2),
+    bin("644564", Discover, "Discover Bank", Classic, "US", 1),
+];
+
+/// Approximate share of card payments by network (percent).
+const MARKET_SHARE: [(CardNetwork, u32); 4] =
+    [(Visa, 52), (Mastercard, 31), (Amex, 11), (Discover, 6)];
+
+/// A network, weighted by market share.
+pub(crate) fn pick_network(rng: &mut impl Rng) -> CardNetwork {
+    let total: u32 = MARKET_SHARE.iter().map(|(_, share)| share).sum();
+    let mut roll = rng.gen_range(0..total);
+    for (network, share) in MARKET_SHARE {
+        if roll < share {
+            return network;
+        }
+        roll -= share;
+    }
+    Visa
+}
+
+/// One of `network`'s BINs, by weight.
+pub(crate) fn pick(network: CardNetwork, rng: &mut impl Rng) -> &'static BinInfo {
+    let entries = || BIN_TABLE.iter().filter(move |info| info.network == network);
+    let total: u32 = entries().map(|info| info.weight).sum();
+    let mut roll = rng.gen_range(0..total);
+    for info in entries() {
+        if roll < info.weight {
+            return info;
+        }
+        roll -= info.weight;
+    }
+    unreachable!("every network has a BIN")
+}
+
+/// The table entry `digits` falls under, longest BIN first.
+pub(crate) fn lookup(digits: &str) -> Option<&'static BinInfo> {
+    BIN_TABLE
+        .iter()
+        .filter(|info| digits.starts_with(info.bin))
+        .max_by_key(|info| info.bin.len())
+}
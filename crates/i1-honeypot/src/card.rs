@@ -1,12 +1,23 @@
 //! LUHN-valid credit card generation for honeypots.
-
-use chrono::{Datelike, Utc};
-use rand::Rng;
+//!
+//! Cards are random by default. A deployment that has to plant the same
+//! cards again after a redeploy generates them from a seed instead: the
+//! seed and a card's index fix everything on it, and expiry dates count
+//! from an as-of date rather than today, so regenerating months later
+//! still gives the cards the registry knows.
+
+use chrono::{Datelike, NaiveDate, Utc};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::bins::{self, BinInfo, CardTier};
+use crate::error::HoneypotError;
+use crate::identity::Persona;
+
 /// Credit card network prefixes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CardNetwork {
     Visa,
     Mastercard,
@@ -15,13 +26,17 @@ pub enum CardNetwork {
 }
 
 impl CardNetwork {
-    /// Get the IIN/BIN prefix for this network.
-    fn prefix(&self) -> &str {
-        match self {
-            CardNetwork::Visa => "4",
-            CardNetwork::Mastercard => "51",
-            CardNetwork::Amex => "34",
-            CardNetwork::Discover => "6011",
+    /// The network a card number or BIN belongs to, from its leading digits.
+    pub fn from_bin(digits: &str) -> Option<Self> {
+        let prefix = |len: usize| digits.get(..len).and_then(|p| p.parse::<u32>().ok());
+        match (prefix(1), prefix(2), prefix(3), prefix(4)) {
+            (Some(4), ..) => Some(CardNetwork::Visa),
+            (_, Some(34 | 37), ..) => Some(CardNetwork::Amex),
+            (_, Some(51..=55), ..) | (.., Some(2221..=2720)) => Some(CardNetwork::Mastercard),
+            (_, Some(65), ..) | (_, _, Some(644..=649), _) | (.., Some(6011)) => {
+                Some(CardNetwork::Discover)
+            }
+            _ => None,
         }
     }
 
@@ -62,22 +77,107 @@ pub struct HoneypotCard {
     pub holder_name: String,
     /// Formatted number for display (with spaces)
     pub display_number: String,
+    /// BIN the number was generated under
+    #[serde(default)]
+    pub bin: String,
+    /// Issuing bank's display name
+    #[serde(default)]
+    pub issuer: String,
+    /// Product tier
+    #[serde(default)]
+    pub tier: CardTier,
+    /// Country of issue (ISO 3166-1 alpha-2)
+    #[serde(default)]
+    pub country: String,
 }
 
 impl HoneypotCard {
     /// Generate a new honeypot card for the given network.
     pub fn generate(network: CardNetwork) -> Self {
-        let number = generate_luhn_valid(network.prefix(), network.length());
+        let mut rng = rand::thread_rng();
+        let info = bins::pick(network, &mut rng);
+        Self::build(info.bin, info, &mut rng, Utc::now().date_naive())
+    }
+
+    /// Card number `index` of the batch `seed` stands for on `network`.
+    ///
+    /// The seed and index fix the BIN, number, expiry, CVV, holder name and
+    /// ID; expiry is 1-4 years after `as_of`.
+    pub fn generate_seeded(
+        network: CardNetwork,
+        seed: [u8; 32],
+        index: u64,
+        as_of: NaiveDate,
+    ) -> Self {
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(index);
+        let info = bins::pick(network, &mut rng);
+        Self::build(info.bin, info, &mut rng, as_of)
+    }
+
+    /// Generate a card on a network picked by real-world market share.
+    pub fn generate_any() -> Self {
+        Self::generate(bins::pick_network(&mut rand::thread_rng()))
+    }
+
+    /// Generate a card under a specific 6-8 digit BIN.
+    ///
+    /// Issuer details come from the BIN table when the BIN is in it, and
+    /// are generic for the network otherwise.
+    pub fn from_bin(bin: &str) -> Result<Self, HoneypotError> {
+        if !(6..=8).contains(&bin.len()) || !bin.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(HoneypotError::InvalidConfig(format!(
+                "BIN must be 6-8 digits, got '{bin}'"
+            )));
+        }
+        let network = CardNetwork::from_bin(bin).ok_or_else(|| {
+            HoneypotError::InvalidConfig(format!("BIN {bin} belongs to no supported network"))
+        })?;
+        let generic = BinInfo {
+            bin: "",
+            network,
+            issuer: "",
+            tier: CardTier::Classic,
+            country: "US",
+            weight: 0,
+        };
+        let info = bins::lookup(bin).filter(|info| info.network == network);
+        let today = Utc::now().date_naive();
+        Ok(Self::build(bin, info.unwrap_or(&generic), &mut rand::thread_rng(), today))
+    }
+
+    /// Put `persona`'s name on the card, so it matches the persona's documents.
+    #[must_use]
+    pub fn with_persona(mut self, persona: &Persona) -> Self {
+        self.holder_name = persona.card_holder_name();
+        self
+    }
+
+    fn build(bin: &str, info: &BinInfo, rng: &mut impl Rng, as_of: NaiveDate) -> Self {
+        let network = info.network;
+        let issuer = if info.issuer.is_empty() {
+            network.to_string()
+        } else {
+            info.issuer.to_string()
+        };
+        let number = luhn_valid_from(rng, bin, network.length());
         let display_number = format_card_number(&number);
+        let expiry = generate_expiry(rng, as_of);
+        let cvv = generate_cvv(rng, network);
+        let holder_name = generate_holder_name(rng);
 
         Self {
-            id: Uuid::new_v4(),
+            id: uuid::Builder::from_random_bytes(rng.gen()).into_uuid(),
             network,
-            number: number.clone(),
-            expiry: generate_expiry(),
-            cvv: generate_cvv(network),
-            holder_name: generate_holder_name(),
+            number,
+            expiry,
+            cvv,
+            holder_name,
             display_number,
+            bin: bin.to_string(),
+            issuer,
+            tier: info.tier,
+            country: info.country.to_string(),
         }
     }
 
@@ -87,10 +187,92 @@ impl HoneypotCard {
     }
 }
 
+/// A reproducible batch of seeded cards.
+///
+/// Card `i` of the batch is [`HoneypotCard::generate_seeded`] with index
+/// `i`, on a network dealt out by weighted round-robin over `network_mix`,
+/// so each network gets its share of the batch in a fixed order.
+#[derive(Debug, Clone)]
+pub struct CardBatch {
+    seed: [u8; 32],
+    mix: Vec<(CardNetwork, u32)>,
+    credit: Vec<i64>,
+    as_of: NaiveDate,
+    next: u64,
+    count: u64,
+}
+
+impl CardBatch {
+    /// `count` cards from `seed`, split between networks by the weights in
+    /// `network_mix`, with expiry dates counted from `as_of`.
+    pub fn new(
+        seed: [u8; 32],
+        network_mix: &[(CardNetwork, u32)],
+        count: u64,
+        as_of: NaiveDate,
+    ) -> Result<Self, HoneypotError> {
+        let mix: Vec<_> = network_mix
+            .iter()
+            .copied()
+            .filter(|&(_, weight)| weight > 0)
+            .collect();
+        if mix.is_empty() {
+            return Err(HoneypotError::InvalidConfig(
+                "network mix needs at least one network with a weight".into(),
+            ));
+        }
+        Ok(Self {
+            seed,
+            credit: vec![0; mix.len()],
+            mix,
+            as_of,
+            next: 0,
+            count,
+        })
+    }
+
+    /// The next network in the mix (smooth weighted round-robin).
+    fn next_network(&mut self) -> CardNetwork {
+        let total: i64 = self.mix.iter().map(|&(_, weight)| i64::from(weight)).sum();
+        let mut best = 0;
+        for (i, &(_, weight)) in self.mix.iter().enumerate() {
+            self.credit[i] += i64::from(weight);
+            if self.credit[i] > self.credit[best] {
+                best = i;
+            }
+        }
+        self.credit[best] -= total;
+        self.mix[best].0
+    }
+}
+
+impl Iterator for CardBatch {
+    type Item = HoneypotCard;
+
+    fn next(&mut self) -> Option<HoneypotCard> {
+        if self.next >= self.count {
+            return None;
+        }
+        let network = self.next_network();
+        let card = HoneypotCard::generate_seeded(network, self.seed, self.next, self.as_of);
+        self.next += 1;
+        Some(card)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = usize::try_from(self.count - self.next).unwrap_or(usize::MAX);
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for CardBatch {}
+
 /// Generate a LUHN-valid card number with the given prefix.
 pub fn generate_luhn_valid(prefix: &str, length: usize) -> String {
-    let mut rng = rand::thread_rng();
+    luhn_valid_from(&mut rand::thread_rng(), prefix, length)
+}
 
+fn luhn_valid_from(rng: &mut impl Rng, prefix: &str, length: usize) -> String {
     // Start with prefix
     let mut digits: Vec<u8> = prefix.chars().map(|c| c.to_digit(10).unwrap() as u8).collect();
 
@@ -177,18 +359,15 @@ fn format_card_number(number: &str) -> String {
     }
 }
 
-/// Generate a realistic expiration date (1-4 years from now).
-fn generate_expiry() -> String {
-    let mut rng = rand::thread_rng();
-    let now = Utc::now();
-    let year = now.year() + rng.gen_range(1..=4);
+/// Generate a realistic expiration date (1-4 years after `as_of`).
+fn generate_expiry(rng: &mut impl Rng, as_of: NaiveDate) -> String {
+    let year = as_of.year() + rng.gen_range(1..=4);
     let month = rng.gen_range(1..=12);
     format!("{:02}/{}", month, year % 100)
 }
 
 /// Generate a CVV/CVC code.
-fn generate_cvv(network: CardNetwork) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_cvv(rng: &mut impl Rng, network: CardNetwork) -> String {
     let length = match network {
         CardNetwork::Amex => 4,
         _ => 3,
@@ -199,9 +378,7 @@ fn generate_cvv(network: CardNetwork) -> String {
 }
 
 /// Generate a realistic cardholder name.
-fn generate_holder_name() -> String {
-    let mut rng = rand::thread_rng();
-
+fn generate_holder_name(rng: &mut impl Rng) -> String {
     let first_names = [
         "JAMES", "MARY", "JOHN", "PATRICIA", "ROBERT", "JENNIFER", "MICHAEL", "LINDA",
         "WILLIAM", "ELIZABETH", "DAVID", "BARBARA", "RICHARD", "SUSAN", "JOSEPH", "JESSICA",
@@ -222,6 +399,7 @@ fn generate_holder_name() -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bins::BIN_TABLE;
 
     #[test]
     fn test_luhn_valid_visa() {
@@ -274,4 +452,140 @@ mod tests {
         let card = HoneypotCard::generate(CardNetwork::Amex);
         assert_eq!(card.cvv.len(), 4);
     }
+
+    #[test]
+    fn test_every_bin_yields_valid_numbers() {
+        for info in BIN_TABLE {
+            assert_eq!(
+                CardNetwork::from_bin(info.bin),
+                Some(info.network),
+                "{}",
+                info.bin
+            );
+            for _ in 0..20 {
+                let card = HoneypotCard::from_bin(info.bin).unwrap();
+                assert!(card.is_valid(), "{}", card.number);
+                assert!(card.number.starts_with(info.bin));
+                assert_eq!(card.number.len(), info.network.length());
+                assert_eq!(card.issuer, info.issuer);
+                assert_eq!(card.tier, info.tier);
+                assert_eq!(card.country, info.country);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_uses_table_bins() {
+        for network in [
+            CardNetwork::Visa,
+            CardNetwork::Mastercard,
+            CardNetwork::Amex,
+            CardNetwork::Discover,
+        ] {
+            let card = HoneypotCard::generate(network);
+            let info = BIN_TABLE.iter().find(|info| info.bin == card.bin).unwrap();
+            assert_eq!(info.network, network);
+            assert!(card.number.starts_with(&card.bin));
+            assert!(!card.issuer.is_empty());
+        }
+        assert!(BIN_TABLE
+            .iter()
+            .any(|info| info.network == CardNetwork::Mastercard && info.bin.starts_with('2')));
+    }
+
+    #[test]
+    fn test_from_bin_outside_table() {
+        let card = HoneypotCard::from_bin("27200012").unwrap();
+        assert_eq!(card.network, CardNetwork::Mastercard);
+        assert_eq!(card.issuer, "Mastercard");
+        assert!(card.is_valid());
+
+        assert!(HoneypotCard::from_bin("4242").is_err());
+        assert!(HoneypotCard::from_bin("42424x").is_err());
+        assert!(HoneypotCard::from_bin("272100").is_err());
+        assert!(HoneypotCard::from_bin("999999").is_err());
+    }
+
+    #[test]
+    fn test_network_weighting() {
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..2000 {
+            *counts
+                .entry(HoneypotCard::generate_any().network)
+                .or_insert(0) += 1;
+        }
+        assert!(counts[&CardNetwork::Visa] > counts[&CardNetwork::Mastercard]);
+        assert!(counts[&CardNetwork::Mastercard] > counts[&CardNetwork::Amex]);
+        assert!(counts.contains_key(&CardNetwork::Discover));
+    }
+
+    fn as_of() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+    }
+
+    #[test]
+    fn test_seeded_cards_are_reproducible() {
+        let card = HoneypotCard::generate_seeded(CardNetwork::Visa, [7; 32], 3, as_of());
+        let again = HoneypotCard::generate_seeded(CardNetwork::Visa, [7; 32], 3, as_of());
+        assert_eq!(
+            serde_json::to_value(&card).unwrap(),
+            serde_json::to_value(&again).unwrap()
+        );
+        // Pinned, so a change to the generator can't silently re-key planted cards.
+        assert_eq!(card.number, "4000001949244248");
+        assert_eq!(card.expiry, "08/28");
+        assert_eq!(card.cvv, "671");
+        assert_eq!(card.holder_name, "JENNIFER RODRIGUEZ");
+        assert_eq!(card.id.to_string(), "1f8e2dbb-207a-450d-a2a5-c5506167891d");
+        assert!(card.is_valid());
+
+        let other = HoneypotCard::generate_seeded(CardNetwork::Visa, [8; 32], 3, as_of());
+        assert_ne!(card.number, other.number);
+    }
+
+    #[test]
+    fn test_seeded_indices_give_distinct_valid_cards() {
+        let mut numbers = std::collections::HashSet::new();
+        for network in [CardNetwork::Amex, CardNetwork::Discover] {
+            for index in 0..200 {
+                let card = HoneypotCard::generate_seeded(network, [1; 32], index, as_of());
+                assert!(card.is_valid(), "{}", card.number);
+                assert_eq!(card.network, network);
+                let year: i32 = card.expiry[3..].parse().unwrap();
+                assert!((27..=30).contains(&year), "{}", card.expiry);
+                assert!(numbers.insert(card.number));
+            }
+        }
+    }
+
+    #[test]
+    fn test_card_batch() {
+        let mix = [(CardNetwork::Visa, 2), (CardNetwork::Amex, 1), (CardNetwork::Discover, 0)];
+        let batch = CardBatch::new([9; 32], &mix, 6, as_of()).unwrap();
+        assert_eq!(batch.len(), 6);
+        let cards: Vec<_> = batch.collect();
+        let networks: Vec<_> = cards.iter().map(|card| card.network).collect();
+        assert_eq!(
+            networks,
+            [
+                CardNetwork::Visa,
+                CardNetwork::Amex,
+                CardNetwork::Visa,
+                CardNetwork::Visa,
+                CardNetwork::Amex,
+                CardNetwork::Visa,
+            ]
+        );
+        for (index, card) in (0..).zip(&cards) {
+            let alone = HoneypotCard::generate_seeded(card.network, [9; 32], index, as_of());
+            assert_eq!(card.number, alone.number);
+            assert_eq!(card.id, alone.id);
+        }
+
+        let again: Vec<_> = CardBatch::new([9; 32], &mix, 6, as_of()).unwrap().collect();
+        assert!(cards.iter().zip(&again).all(|(a, b)| a.number == b.number));
+
+        assert!(CardBatch::new([9; 32], &[(CardNetwork::Visa, 0)], 6, as_of()).is_err());
+        assert!(CardBatch::new([9; 32], &[], 6, as_of()).is_err());
+    }
 }
@@ -17,6 +17,14 @@ pub enum HoneypotError {
     #[error("Failed to generate document: {0}")]
     DocumentGeneration(String),
 
+    /// Card number is already registered to another deployment.
+    #[error("Card already registered as {0}")]
+    AlreadyRegistered(uuid::Uuid),
+
+    /// i1-srv's admin API refused or didn't answer a request.
+    #[error("i1-srv admin API error: {0}")]
+    AdminApi(String),
+
     /// IO error.
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
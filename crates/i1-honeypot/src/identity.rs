@@ -0,0 +1,529 @@
+//! Fake identities for honeypot personas.
+//!
+//! A scammer who is handed a card will often ask for the billing address,
+//! phone and date of birth too, and a persona only holds up if those agree
+//! with each other: the ZIP code belongs to the state, the area code to
+//! the city, and the email looks like the name. Personas are generated
+//! from a seed, so a session keeps the same one however often it asks.
+//!
+//! Phone numbers come from the ranges set aside for fiction (`555-01xx` in
+//! the US, Ofcom's drama numbers in the UK), so a scammer calling one never
+//! reaches a real person.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::HoneypotError;
+
+/// Which country's conventions a persona follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "en-US")]
+    EnUs,
+    #[serde(rename = "en-GB")]
+    EnGb,
+}
+
+impl Locale {
+    /// BCP 47 tag.
+    pub const fn tag(self) -> &'static str {
+        match self {
+            Locale::EnUs => "en-US",
+            Locale::EnGb => "en-GB",
+        }
+    }
+
+    /// ISO 3166-1 alpha-2 country.
+    pub const fn country(self) -> &'static str {
+        match self {
+            Locale::EnUs => "US",
+            Locale::EnGb => "GB",
+        }
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = HoneypotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('_', "-").to_ascii_lowercase().as_str() {
+            "en-us" => Ok(Locale::EnUs),
+            "en-gb" => Ok(Locale::EnGb),
+            _ => Err(HoneypotError::InvalidConfig(format!(
+                "unsupported locale '{s}' (expected en-US or en-GB)"
+            ))),
+        }
+    }
+}
+
+/// A postal address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    /// House number and street
+    pub street: String,
+    /// City or town
+    pub city: String,
+    /// State code (US) or county (UK)
+    pub region: String,
+    /// ZIP code or postcode
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country
+    pub country: String,
+}
+
+/// A consistent fake identity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Persona {
+    /// Seed the persona was generated from
+    pub seed: u64,
+    /// Conventions it follows
+    pub locale: Locale,
+    /// Given name
+    pub first_name: String,
+    /// Family name
+    pub last_name: String,
+    /// Email address derived from the name
+    pub email: String,
+    /// Phone number in the locale's national format
+    pub phone: String,
+    /// Date of birth
+    pub date_of_birth: NaiveDate,
+    /// Home address
+    pub address: Address,
+}
+
+impl Persona {
+    /// The persona `seed` stands for in `locale`.
+    ///
+    /// The same locale and seed always give the same persona.
+    pub fn generate(locale: Locale, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let (first_name, last_name, address, phone) = match locale {
+            Locale::EnUs => {
+                let (first, last) = pick_name(&mut rng, US_FIRST_NAMES, US_LAST_NAMES);
+                let (address, phone) = us_address(&mut rng);
+                (first, last, address, phone)
+            }
+            Locale::EnGb => {
+                let (first, last) = pick_name(&mut rng, GB_FIRST_NAMES, GB_LAST_NAMES);
+                let (address, phone) = gb_address(&mut rng);
+                (first, last, address, phone)
+            }
+        };
+        let date_of_birth = date_of_birth(&mut rng);
+        let email = email(&mut rng, locale, first_name, last_name, date_of_birth);
+
+        Self {
+            seed,
+            locale,
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+            email,
+            phone,
+            date_of_birth,
+            address,
+        }
+    }
+
+    /// "First Last".
+    pub fn full_name(&self) -> String {
+        format!("{} {}", self.first_name, self.last_name)
+    }
+
+    /// The name as embossed on a card: "FIRST LAST".
+    pub fn card_holder_name(&self) -> String {
+        self.full_name().to_uppercase()
+    }
+
+    /// Values keyed by HTML `autocomplete` token, for filling in forms.
+    pub fn form_fields(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([
+            ("name", self.full_name()),
+            ("given-name", self.first_name.clone()),
+            ("family-name", self.last_name.clone()),
+            ("email", self.email.clone()),
+            ("tel", self.phone.clone()),
+            ("bday", self.date_of_birth.to_string()),
+            ("street-address", self.address.street.clone()),
+            ("address-level2", self.address.city.clone()),
+            ("address-level1", self.address.region.clone()),
+            ("postal-code", self.address.postal_code.clone()),
+            ("country", self.address.country.clone()),
+        ])
+    }
+}
+
+#[rustfmt::skip]
+const US_FIRST_NAMES: &[&str] = &[
+    "James", "Mary", "John", "Patricia", "Robert", "Jennifer", "Michael", "Linda",
+    "William", "Elizabeth", "David", "Barbara", "Richard", "Susan", "Joseph", "Karen",
+    "Thomas", "Nancy", "Charles", "Donna", "Gary", "Carol", "Kenneth", "Sandra",
+];
+
+#[rustfmt::skip]
+const US_LAST_NAMES: &[&str] = &[
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis",
+    "Rodriguez", "Martinez", "Wilson", "Anderson", "Taylor", "Thomas", "Moore", "Martin",
+    "Jackson", "Thompson", "White", "Harris", "Clark", "Lewis", "Walker", "O'Brien",
+];
+
+#[rustfmt::skip]
+const GB_FIRST_NAMES: &[&str] = &[
+    "Oliver", "Amelia", "George", "Emily", "Harry", "Sophie", "Jack", "Charlotte",
+    "Thomas", "Lucy", "Daniel", "Hannah", "James", "Olivia", "William", "Jessica",
+    "Samuel", "Chloe", "Peter", "Margaret", "Graham", "Susan", "Ian", "Pauline",
+];
+
+#[rustfmt::skip]
+const GB_LAST_NAMES: &[&str] = &[
+    "Smith", "Jones", "Taylor", "Brown", "Williams", "Wilson", "Johnson", "Davies",
+    "Robinson", "Wright", "Thompson", "Evans", "Walker", "White", "Roberts", "Green",
+    "Hall", "Wood", "Jackson", "Clarke", "Hughes", "Edwards", "Turner", "MacDonald",
+];
+
+#[rustfmt::skip]
+const US_STREETS: &[&str] = &[
+    "Maple", "Oak", "Washington", "Lake", "Hill", "Park", "Cedar", "Elm", "Pine",
+    "Lincoln", "Jackson", "Sunset", "Highland", "Franklin", "Church", "Walnut",
+];
+
+const US_STREET_SUFFIXES: &[&str] = &["St", "Ave", "Rd", "Dr", "Ln", "Blvd", "Ct", "Way"];
+
+#[rustfmt::skip]
+const GB_STREETS: &[&str] = &[
+    "Victoria", "Church", "Station", "Park", "Mill", "Queens", "Kings", "Manor",
+    "Albert", "Grange", "Windsor", "Chester", "York", "Springfield", "Beech", "Orchard",
+];
+
+const GB_STREET_SUFFIXES: &[&str] = &[
+    "Road", "Street", "Lane", "Avenue", "Close", "Gardens", "Crescent", "Drive",
+];
+
+/// A US city: state, ZIP range and the area codes that serve it.
+struct UsCity {
+    name: &'static str,
+    state: &'static str,
+    zips: (u32, u32),
+    area_codes: &'static [u16],
+}
+
+const fn us(
+    name: &'static str,
+    state: &'static str,
+    zips: (u32, u32),
+    area_codes: &'static [u16],
+) -> UsCity {
+    UsCity {
+        name,
+        state,
+        zips,
+        area_codes,
+    }
+}
+
+#[rustfmt::skip]
+const US_CITIES: &[UsCity] = &[
+    us("New York", "NY", (10001, 10128), &[212, 646, 917]),
+    us("Los Angeles", "CA", (90001, 90089), &[213, 323, 310]),
+    us("Chicago", "IL", (60601, 60661), &[312, 773]),
+    us("Houston", "TX", (77001, 77099), &[713, 832, 281]),
+    us("Phoenix", "AZ", (85003, 85054), &[602, 480]),
+    us("Philadelphia", "PA", (19102, 19154), &[215, 267]),
+    us("San Antonio", "TX", (78201, 78266), &[210]),
+    us("Dallas", "TX", (75201, 75287), &[214, 469, 972]),
+    us("Seattle", "WA", (98101, 98199), &[206]),
+    us("Denver", "CO", (80202, 80249), &[303, 720]),
+    us("Boston", "MA", (2108, 2137), &[617, 857]),
+    us("Atlanta", "GA", (30303, 30350), &[404, 678, 470]),
+    us("Miami", "FL", (33125, 33199), &[305, 786]),
+    us("Columbus", "OH", (43201, 43235), &[614]),
+    us("Portland", "OR", (97201, 97239), &[503, 971]),
+];
+
+/// A UK city: county, postcode districts and its drama-range phone prefix.
+struct GbCity {
+    name: &'static str,
+    county: &'static str,
+    districts: &'static [&'static str],
+    /// Dialling code and the fixed part of the local number
+    phone: (&'static str, &'static str),
+}
+
+const fn gb(
+    name: &'static str,
+    county: &'static str,
+    districts: &'static [&'static str],
+    phone: (&'static str, &'static str),
+) -> GbCity {
+    GbCity {
+        name,
+        county,
+        districts,
+        phone,
+    }
+}
+
+#[rustfmt::skip]
+const GB_CITIES: &[GbCity] = &[
+    gb("London", "Greater London", &["E1", "N1", "SE1", "SW4", "W2", "NW1"], ("020", "7946 0")),
+    gb("Manchester", "Greater Manchester", &["M1", "M4", "M14", "M20"], ("0161", "496 0")),
+    gb("Leeds", "West Yorkshire", &["LS1", "LS6", "LS11"], ("0113", "496 0")),
+    gb("Birmingham", "West Midlands", &["B1", "B5", "B15"], ("0121", "496 0")),
+    gb("Bristol", "Bristol", &["BS1", "BS6", "BS8"], ("0117", "496 0")),
+    gb("Glasgow", "Glasgow City", &["G1", "G3", "G12"], ("0141", "496 0")),
+    gb("Edinburgh", "City of Edinburgh", &["EH1", "EH3", "EH10"], ("0131", "496 0")),
+    gb("Liverpool", "Merseyside", &["L1", "L8", "L17"], ("0151", "496 0")),
+    gb("Sheffield", "South Yorkshire", &["S1", "S10", "S11"], ("0114", "496 0")),
+    gb("Nottingham", "Nottinghamshire", &["NG1", "NG5", "NG7"], ("0115", "496 0")),
+    gb("Cardiff", "Cardiff", &["CF10", "CF11", "CF24"], ("029", "2018 0")),
+    gb("Newcastle upon Tyne", "Tyne and Wear", &["NE1", "NE2", "NE6"], ("0191", "498 0")),
+];
+
+/// Letters used in the last two characters of a postcode.
+const POSTCODE_LETTERS: &[u8] = b"ABDEFGHJLNPQRSTUWXYZ";
+
+const US_EMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "yahoo.com",
+    "outlook.com",
+    "aol.com",
+    "icloud.com",
+];
+
+const GB_EMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "yahoo.co.uk",
+    "outlook.com",
+    "btinternet.com",
+    "hotmail.co.uk",
+];
+
+fn pick<'a, T>(rng: &mut impl Rng, items: &'a [T]) -> &'a T {
+    &items[rng.gen_range(0..items.len())]
+}
+
+fn pick_name(
+    rng: &mut impl Rng,
+    first: &[&'static str],
+    last: &[&'static str],
+) -> (&'static str, &'static str) {
+    (*pick(rng, first), *pick(rng, last))
+}
+
+fn us_address(rng: &mut impl Rng) -> (Address, String) {
+    let city = pick(rng, US_CITIES);
+    let street = format!(
+        "{} {} {}",
+        rng.gen_range(100..9900),
+        pick(rng, US_STREETS),
+        pick(rng, US_STREET_SUFFIXES)
+    );
+    let zip = rng.gen_range(city.zips.0..=city.zips.1);
+    let phone = format!(
+        "({}) 555-01{:02}",
+        pick(rng, city.area_codes),
+        rng.gen_range(0..100)
+    );
+    let address = Address {
+        street,
+        city: city.name.into(),
+        region: city.state.into(),
+        postal_code: format!("{zip:05}"),
+        country: "US".into(),
+    };
+    (address, phone)
+}
+
+fn gb_address(rng: &mut impl Rng) -> (Address, String) {
+    let city = pick(rng, GB_CITIES);
+    let street = format!(
+        "{} {} {}",
+        rng.gen_range(1..240),
+        pick(rng, GB_STREETS),
+        pick(rng, GB_STREET_SUFFIXES)
+    );
+    let postcode = format!(
+        "{} {}{}{}",
+        pick(rng, city.districts),
+        rng.gen_range(1..10),
+        char::from(*pick(rng, POSTCODE_LETTERS)),
+        char::from(*pick(rng, POSTCODE_LETTERS))
+    );
+    let phone = if rng.gen_bool(0.5) {
+        format!("07700 900{:03}", rng.gen_range(0..1000))
+    } else {
+        let (code, local) = city.phone;
+        format!("{code} {local}{:03}", rng.gen_range(0..1000))
+    };
+    let address = Address {
+        street,
+        city: city.name.into(),
+        region: city.county.into(),
+        postal_code: postcode,
+        country: "GB".into(),
+    };
+    (address, phone)
+}
+
+/// A birthday between 1946 and 2000, fixed by the seed rather than today's
+/// date so a persona doesn't change on New Year's Day.
+fn date_of_birth(rng: &mut impl Rng) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(1946, 1, 1).unwrap_or_default();
+    let last = NaiveDate::from_ymd_opt(2000, 12, 31).unwrap_or_default();
+    let days = rng.gen_range(0..=(last - first).num_days());
+    first + chrono::Duration::days(days)
+}
+
+fn email(
+    rng: &mut impl Rng,
+    locale: Locale,
+    first: &str,
+    last: &str,
+    date_of_birth: NaiveDate,
+) -> String {
+    let clean = |name: &str| -> String {
+        name.chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    };
+    let (first, last) = (clean(first), clean(last));
+    let initial = &first[..1];
+    let year = date_of_birth.year() % 100;
+    let local = match rng.gen_range(0..5) {
+        0 => format!("{first}.{last}"),
+        1 => format!("{first}{last}"),
+        2 => format!("{initial}{last}"),
+        3 => format!("{first}.{last}{year:02}"),
+        _ => format!("{first}{last}{}", rng.gen_range(1..100)),
+    };
+    let domains = match locale {
+        Locale::EnUs => US_EMAIL_DOMAINS,
+        Locale::EnGb => GB_EMAIL_DOMAINS,
+    };
+    format!("{local}@{}", pick(rng, domains))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use regex::Regex;
+
+    /// First three ZIP digits each state uses, per the USPS prefix table.
+    fn state_zip_prefixes(state: &str) -> &'static [(u32, u32)] {
+        match state {
+            "NY" => &[(100, 149)],
+            "CA" => &[(900, 961)],
+            "IL" => &[(600, 629)],
+            "TX" => &[(750, 799), (885, 885)],
+            "AZ" => &[(850, 865)],
+            "PA" => &[(150, 196)],
+            "WA" => &[(980, 994)],
+            "CO" => &[(800, 816)],
+            "MA" => &[(10, 27)],
+            "GA" => &[(300, 319), (398, 399)],
+            "FL" => &[(320, 349)],
+            "OH" => &[(430, 459)],
+            "OR" => &[(970, 979)],
+            _ => &[],
+        }
+    }
+
+    fn locale() -> impl Strategy<Value = Locale> {
+        prop_oneof![Just(Locale::EnUs), Just(Locale::EnGb)]
+    }
+
+    proptest! {
+        #[test]
+        fn zip_codes_belong_to_their_state(seed: u64) {
+            let persona = Persona::generate(Locale::EnUs, seed);
+            let address = &persona.address;
+            prop_assert_eq!(address.postal_code.len(), 5);
+            let prefix: u32 = address.postal_code[..3].parse().unwrap();
+            let ranges = state_zip_prefixes(&address.region);
+            prop_assert!(
+                ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&prefix)),
+                "{} is not a {} ZIP", address.postal_code, address.region
+            );
+        }
+
+        #[test]
+        fn postcodes_belong_to_their_city(seed: u64) {
+            let persona = Persona::generate(Locale::EnGb, seed);
+            let postcode = Regex::new(r"^([A-Z]{1,2}[0-9]{1,2}) [0-9][ABD-HJLNP-UW-Z]{2}$").unwrap();
+            let district = postcode
+                .captures(&persona.address.postal_code)
+                .map(|caps| caps[1].to_string());
+            prop_assert!(district.is_some(), "bad postcode {}", persona.address.postal_code);
+            let city = GB_CITIES.iter().find(|city| city.name == persona.address.city).unwrap();
+            prop_assert!(city.districts.contains(&district.unwrap().as_str()));
+        }
+
+        #[test]
+        fn phones_are_valid_and_local(seed: u64) {
+            let us = Persona::generate(Locale::EnUs, seed);
+            let format = Regex::new(r"^\(([2-9][0-9]{2})\) 555-01[0-9]{2}$").unwrap();
+            let area: u16 = format.captures(&us.phone).unwrap()[1].parse().unwrap();
+            let city = US_CITIES.iter().find(|city| city.name == us.address.city).unwrap();
+            prop_assert!(city.area_codes.contains(&area));
+
+            let gb = Persona::generate(Locale::EnGb, seed);
+            let mobile = Regex::new(r"^07700 900[0-9]{3}$").unwrap();
+            let landline = Regex::new(r"^(0[0-9]{2,3}) ([0-9]{3,4} [0-9]{4})$").unwrap();
+            if !mobile.is_match(&gb.phone) {
+                let caps = landline.captures(&gb.phone);
+                prop_assert!(caps.is_some(), "bad UK number {}", gb.phone);
+                let digits = gb.phone.chars().filter(char::is_ascii_digit).count();
+                prop_assert_eq!(digits, 11);
+                let city = GB_CITIES.iter().find(|city| city.name == gb.address.city).unwrap();
+                prop_assert_eq!(&caps.unwrap()[1], city.phone.0);
+            }
+        }
+
+        #[test]
+        fn personas_are_deterministic(locale in locale(), seed: u64) {
+            let persona = Persona::generate(locale, seed);
+            prop_assert_eq!(&persona, &Persona::generate(locale, seed));
+
+            let local = persona.email.split('@').next().unwrap();
+            let last: String = persona
+                .last_name
+                .to_ascii_lowercase()
+                .chars()
+                .filter(char::is_ascii_alphabetic)
+                .collect();
+            prop_assert!(local.contains(&last), "{} from {}", persona.email, persona.full_name());
+
+            let json = serde_json::to_string(&persona).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Persona>(&json).unwrap(), persona);
+        }
+    }
+
+    #[test]
+    fn seeds_map_to_the_same_persona_across_runs() {
+        let persona = Persona::generate(Locale::EnGb, 42);
+        let pinned = serde_json::to_value(&persona).unwrap();
+        assert_eq!(pinned["locale"], "en-GB");
+        assert_eq!(persona.full_name(), "Samuel Thompson");
+        assert_eq!(persona.address.city, "Liverpool");
+        assert_eq!(persona.form_fields()["country"], "GB");
+
+        let card = crate::HoneypotCard::generate_any().with_persona(&persona);
+        assert_eq!(card.holder_name, "SAMUEL THOMPSON");
+
+        assert_eq!("en_gb".parse::<Locale>().unwrap(), Locale::EnGb);
+        assert!("fr-FR".parse::<Locale>().is_err());
+    }
+}
@@ -5,9 +5,16 @@
 //! ## Features
 //!
 //! - LUHN-valid credit cards that trigger alerts when used
+//! - A registry tracing card sightings back to where each card was planted
 //! - Fake cryptocurrency wallets with trackable addresses
 //! - Decoy credentials and password files
+//! - Canary AWS keys, GitHub tokens and API keys recognizable per deployment
 //! - Trap documents that phone home when opened
+//! - Consistent fake personas to go with them ([`identity`])
+//! - SSH and HTTP listeners that record login attempts and block their
+//!   sources ([`listener`], `ssh` and `http` features)
+//! - Policy-driven bans from listener events, optionally pushed to a local
+//!   i1-srv ([`sink`], `push` feature)
 //!
 //! ## Example
 //!
@@ -20,17 +27,29 @@
 //! // Any attempt to charge it = instant notification + scammer tracking
 //! ```
 
+mod bins;
 mod card;
 mod credentials;
 mod crypto;
 mod documents;
 mod error;
-
-pub use card::{CardNetwork, HoneypotCard, generate_luhn_valid};
-pub use credentials::{CredentialType, HoneypotCredential};
+pub mod identity;
+pub mod listener;
+mod registry;
+pub mod sink;
+
+pub use bins::{BinInfo, CardTier, BIN_TABLE};
+pub use card::{CardBatch, CardNetwork, HoneypotCard, generate_luhn_valid};
+pub use credentials::{
+    CanaryGenerator, CredentialType, HoneypotCredential, PasswordMix, PasswordStrength,
+};
 pub use crypto::{CryptoNetwork, HoneypotWallet};
 pub use documents::{DocumentType, TrapDocument};
 pub use error::HoneypotError;
+pub use identity::{Locale, Persona};
+pub use registry::{
+    CardHit, CardRegistry, CardingReputation, Deployment, DeploymentContext, CARDING_PATTERN,
+};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
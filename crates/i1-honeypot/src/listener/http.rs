@@ -0,0 +1,733 @@
+//! Web pages that look like forgotten admin logins and backups.
+//!
+//! Each [`Template`] adds a surface scanners go looking for: the
+//! phpMyAdmin and WordPress logins, a generic admin panel, and an open
+//! `/backup/` directory. Every request is reported with its headers, every
+//! login form submission as a [`HoneypotEvent::Credential`], and anything
+//! else gets a 404.
+//!
+//! The directory holds `backup.sql`, built fresh for each download: the
+//! cards in it are registered in a [`CardRegistry`] under a token unique to
+//! that download, which is also written into the dump, and its API and AWS
+//! keys come from a [`CanaryGenerator`]. When one of them turns up later,
+//! [`CanaryTracker`] says who downloaded it.
+//!
+//! Listen addresses and templates come from TOML:
+//!
+//! ```toml
+//! listen = ["0.0.0.0:8080"]
+//! site = "shop.example.com"
+//! server = "Apache/2.4.41 (Ubuntu)"
+//! templates = ["phpmyadmin", "wordpress", "admin_panel", "open_directory"]
+//! ```
+
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Form, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, SERVER, USER_AGENT};
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode, Uri};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Router};
+use chrono::{Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::{
+    CanaryDownload, CredentialAttempt, EventLimiter, HoneypotEvent, HttpRequest, Reporter,
+};
+use crate::{
+    CanaryGenerator, CardRegistry, CredentialType, DeploymentContext, HoneypotCard, HoneypotError,
+};
+
+/// Cards planted in each `backup.sql`.
+const CANARY_CARDS: usize = 3;
+
+/// A fake surface the HTTP listener can present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Template {
+    /// phpMyAdmin login at `/phpmyadmin/`
+    #[serde(rename = "phpmyadmin")]
+    PhpMyAdmin,
+    /// WordPress login at `/wp-login.php`
+    #[serde(rename = "wordpress")]
+    WordPress,
+    /// Generic admin login at `/admin/`
+    #[serde(rename = "admin_panel")]
+    AdminPanel,
+    /// Directory listing at `/backup/` with a canary `backup.sql`
+    #[serde(rename = "open_directory")]
+    OpenDirectory,
+}
+
+impl Template {
+    /// Every template.
+    pub const ALL: [Self; 4] = [
+        Self::PhpMyAdmin,
+        Self::WordPress,
+        Self::AdminPanel,
+        Self::OpenDirectory,
+    ];
+
+    /// Name used in configuration and event service names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PhpMyAdmin => "phpmyadmin",
+            Self::WordPress => "wordpress",
+            Self::AdminPanel => "admin_panel",
+            Self::OpenDirectory => "open_directory",
+        }
+    }
+
+    fn login(self) -> Option<LoginForm> {
+        let form = match self {
+            Self::PhpMyAdmin => LoginForm {
+                page: "/phpmyadmin/",
+                action: "/phpmyadmin/index.php",
+                user_field: "pma_username",
+                pass_field: "pma_password",
+                title: "phpMyAdmin",
+                error: "mysqli::real_connect(): (HY000/1045): Access denied",
+            },
+            Self::WordPress => LoginForm {
+                page: "/wp-login.php",
+                action: "/wp-login.php",
+                user_field: "log",
+                pass_field: "pwd",
+                title: "Log In &lsaquo; WordPress",
+                error: "<strong>Error:</strong> The username or password you entered is incorrect.",
+            },
+            Self::AdminPanel => LoginForm {
+                page: "/admin/",
+                action: "/admin/login",
+                user_field: "username",
+                pass_field: "password",
+                title: "Administration",
+                error: "Invalid username or password.",
+            },
+            Self::OpenDirectory => return None,
+        };
+        Some(form)
+    }
+
+    fn routes(self) -> Router<Arc<Shared>> {
+        if let Some(form) = self.login() {
+            let router = if form.page == form.action {
+                Router::new().route(form.page, get(show_login).post(submit_login))
+            } else {
+                Router::new()
+                    .route(form.page, get(show_login))
+                    .route(form.action, post(submit_login))
+            };
+            return router.layer(Extension(self));
+        }
+        Router::new()
+            .route("/backup", get(listing))
+            .route("/backup/", get(listing))
+            .route("/backup/backup.sql", get(backup_sql))
+    }
+}
+
+/// Where a login template lives and what its form looks like.
+struct LoginForm {
+    page: &'static str,
+    action: &'static str,
+    user_field: &'static str,
+    pass_field: &'static str,
+    title: &'static str,
+    error: &'static str,
+}
+
+impl LoginForm {
+    fn render(&self, failed: bool) -> String {
+        let error = if failed {
+            format!("<div class=\"error\">{}</div>\n", self.error)
+        } else {
+            String::new()
+        };
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+             <body>\n<h1>{title}</h1>\n{error}\
+             <form method=\"post\" action=\"{action}\">\n\
+             <label>Username <input type=\"text\" name=\"{user}\"></label>\n\
+             <label>Password <input type=\"password\" name=\"{pass}\"></label>\n\
+             <input type=\"submit\" value=\"Log In\">\n</form>\n</body></html>\n",
+            title = self.title,
+            action = self.action,
+            user = self.user_field,
+            pass = self.pass_field,
+        )
+    }
+}
+
+/// Where the HTTP listener listens and what it shows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Addresses to listen on
+    pub listen: Vec<SocketAddr>,
+    /// Site name recorded with planted cards
+    pub site: String,
+    /// `Server` header sent with every response
+    pub server: String,
+    /// Surfaces to present
+    pub templates: Vec<Template>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec![SocketAddr::from(([0, 0, 0, 0], 8080))],
+            site: "http-honeypot".into(),
+            server: "Apache/2.4.41 (Ubuntu)".into(),
+            templates: Template::ALL.to_vec(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Parse a TOML configuration; missing keys take their defaults.
+    pub fn from_toml(text: &str) -> Result<Self, HoneypotError> {
+        toml::from_str(text).map_err(|e| HoneypotError::InvalidConfig(e.to_string()))
+    }
+
+    /// Read a TOML configuration file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, HoneypotError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Builds canary files and traces what's found in them back to a download.
+///
+/// Downloads are remembered for the life of the process; the
+/// [`HoneypotEvent::Canary`] events are the durable record, and the
+/// registry keeps each card's token.
+#[derive(Clone)]
+pub struct CanaryTracker {
+    inner: Arc<Canaries>,
+}
+
+struct Canaries {
+    site: String,
+    generator: CanaryGenerator,
+    registry: Mutex<CardRegistry>,
+    downloads: Mutex<HashMap<String, CanaryDownload>>,
+}
+
+impl CanaryTracker {
+    /// Plant cards in `registry` and keys from `canary_secret`, recording
+    /// `site` as where they were found.
+    pub fn new(site: impl Into<String>, registry: CardRegistry, canary_secret: &[u8]) -> Self {
+        Self {
+            inner: Arc::new(Canaries {
+                site: site.into(),
+                generator: CanaryGenerator::new(canary_secret),
+                registry: Mutex::new(registry),
+                downloads: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// The download a token was issued for.
+    pub fn download(&self, token: &str) -> Option<CanaryDownload> {
+        self.downloads().get(token).cloned()
+    }
+
+    /// The download a card number was planted in.
+    pub fn trace_card(&self, pan: &str) -> Option<CanaryDownload> {
+        let token = {
+            let registry = self
+                .inner
+                .registry
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            registry.lookup_by_number(pan)?.context.session_id.clone()?
+        };
+        self.download(&token)
+    }
+
+    /// The download an API or AWS key was planted in.
+    pub fn trace_credential(&self, candidate: &str) -> Option<CanaryDownload> {
+        let id = self.inner.generator.is_ours(candidate)?;
+        self.downloads()
+            .values()
+            .find(|download| download.credentials.contains(&id))
+            .cloned()
+    }
+
+    fn downloads(&self) -> std::sync::MutexGuard<'_, HashMap<String, CanaryDownload>> {
+        self.inner
+            .downloads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Build a `backup.sql` for `peer`, returning the download and the dump.
+    fn issue(
+        &self,
+        peer: SocketAddr,
+        user_agent: Option<String>,
+        resource: &str,
+    ) -> Result<(CanaryDownload, String), HoneypotError> {
+        let token = Uuid::new_v4().simple().to_string();
+        let generator = &self.inner.generator;
+        let customers: Vec<_> = (0..CANARY_CARDS)
+            .map(|_| {
+                (
+                    generator.generate(CredentialType::Shopping),
+                    generator.generate(CredentialType::ApiKey),
+                    HoneypotCard::generate_any(),
+                )
+            })
+            .collect();
+        let aws = generator.generate(CredentialType::AwsAccessKey);
+
+        {
+            let mut registry = self
+                .inner
+                .registry
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            for (_, _, card) in &customers {
+                let context = DeploymentContext::new(&self.inner.site).with_session(&token);
+                registry.register(card, context)?;
+            }
+            if registry.path().is_some() {
+                registry.save()?;
+            }
+        }
+
+        let download = CanaryDownload {
+            token: token.clone(),
+            peer,
+            user_agent,
+            resource: resource.into(),
+            cards: customers.iter().map(|(_, _, card)| card.id).collect(),
+            credentials: customers
+                .iter()
+                .map(|(_, api_key, _)| api_key.id)
+                .chain([aws.id])
+                .collect(),
+            at: Utc::now(),
+        };
+
+        let mut dump = String::from(
+            "-- MySQL dump 10.13  Distrib 8.0.36, for Linux (x86_64)\n--\n\
+             -- Host: localhost    Database: shop\n\
+             -- ------------------------------------------------------\n\
+             -- Server version\t8.0.36-0ubuntu0.22.04.1\n\n\
+             CREATE TABLE `settings` (\n  `name` varchar(64) NOT NULL,\n  \
+             `value` text NOT NULL,\n  PRIMARY KEY (`name`)\n) ENGINE=InnoDB;\n\n",
+        );
+        let _ = writeln!(
+            dump,
+            "INSERT INTO `settings` VALUES ('install_id',{}),('aws_access_key_id',{}),\
+             ('aws_secret_access_key',{});\n",
+            sql_str(&token),
+            sql_str(&aws.username),
+            sql_str(&aws.password),
+        );
+        dump.push_str(
+            "CREATE TABLE `customers` (\n  `id` int NOT NULL AUTO_INCREMENT,\n  \
+             `email` varchar(255) NOT NULL,\n  `password` varchar(255) NOT NULL,\n  \
+             `api_key` varchar(64) DEFAULT NULL,\n  `card_number` varchar(19) DEFAULT NULL,\n  \
+             `card_expiry` char(5) DEFAULT NULL,\n  `card_cvv` varchar(4) DEFAULT NULL,\n  \
+             `card_holder` varchar(255) DEFAULT NULL,\n  PRIMARY KEY (`id`)\n) ENGINE=InnoDB;\n\n\
+             INSERT INTO `customers` VALUES ",
+        );
+        for (i, (login, api_key, card)) in customers.iter().enumerate() {
+            if i > 0 {
+                dump.push(',');
+            }
+            let _ = write!(
+                dump,
+                "({},{},{},{},{},{},{},{})",
+                i + 1,
+                sql_str(&login.username),
+                sql_str(&login.password),
+                sql_str(&api_key.password),
+                sql_str(&card.number),
+                sql_str(&card.expiry),
+                sql_str(&card.cvv),
+                sql_str(&card.holder_name),
+            );
+        }
+        let _ = writeln!(
+            dump,
+            ";\n\n-- Dump completed on {}",
+            (download.at - ChronoDuration::days(3)).format("%Y-%m-%d %H:%M:%S")
+        );
+
+        self.downloads().insert(token, download.clone());
+        Ok((download, dump))
+    }
+}
+
+/// A MySQL string literal.
+fn sql_str(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// An HTTP honeypot bound to its configured addresses.
+pub struct HttpListener {
+    listeners: Vec<TcpListener>,
+    config: HttpConfig,
+    canaries: CanaryTracker,
+    limiter: Arc<Mutex<EventLimiter>>,
+}
+
+impl HttpListener {
+    /// Bind every address in `config.listen`.
+    pub async fn bind(config: HttpConfig, canaries: CanaryTracker) -> Result<Self, HoneypotError> {
+        if config.listen.is_empty() {
+            return Err(HoneypotError::InvalidConfig("no listen addresses".into()));
+        }
+        let mut listeners = Vec::with_capacity(config.listen.len());
+        for addr in &config.listen {
+            listeners.push(TcpListener::bind(addr).await?);
+        }
+        Ok(Self {
+            listeners,
+            config,
+            canaries,
+            limiter: Arc::new(Mutex::new(EventLimiter::default())),
+        })
+    }
+
+    /// Limit the events each source address produces.
+    #[must_use]
+    pub fn with_limiter(mut self, limiter: EventLimiter) -> Self {
+        self.limiter = Arc::new(Mutex::new(limiter));
+        self
+    }
+
+    /// Addresses the listener is bound to.
+    pub fn local_addrs(&self) -> Result<Vec<SocketAddr>, HoneypotError> {
+        Ok(self
+            .listeners
+            .iter()
+            .map(TcpListener::local_addr)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Serve until a listener fails, sending what each visitor does to
+    /// `events`.
+    ///
+    /// Events over the limiter's budget are dropped, and a visitor over
+    /// budget gets a 429 instead of a fresh canary file.
+    pub async fn run(self, events: mpsc::Sender<HoneypotEvent>) -> Result<(), HoneypotError> {
+        let shared = Arc::new(Shared {
+            server: HeaderValue::from_str(&self.config.server)
+                .map_err(|e| HoneypotError::InvalidConfig(format!("invalid server header: {e}")))?,
+            reporter: Reporter::new(events, self.limiter),
+            canaries: self.canaries,
+        });
+
+        let mut router = Router::new();
+        let mut added = Vec::new();
+        for template in self.config.templates {
+            if !added.contains(&template) {
+                router = router.merge(template.routes());
+                added.push(template);
+            }
+        }
+        let router = router
+            .fallback(not_found)
+            .layer(middleware::from_fn_with_state(Arc::clone(&shared), record))
+            .with_state(shared);
+
+        let servers: Vec<_> = self
+            .listeners
+            .into_iter()
+            .map(|listener| {
+                let service = router
+                    .clone()
+                    .into_make_service_with_connect_info::<SocketAddr>();
+                tokio::spawn(axum::serve(listener, service).into_future())
+            })
+            .collect();
+        for server in servers {
+            server.await.map_err(std::io::Error::other)??;
+        }
+        Ok(())
+    }
+}
+
+/// State every handler sees.
+struct Shared {
+    server: HeaderValue,
+    reporter: Reporter,
+    canaries: CanaryTracker,
+}
+
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(USER_AGENT)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+}
+
+/// Report every request, and dress every response as the configured server.
+async fn record(
+    State(shared): State<Arc<Shared>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let event = HttpRequest {
+        peer,
+        method: request.method().to_string(),
+        path: request
+            .uri()
+            .path_and_query()
+            .map_or_else(|| request.uri().path().to_string(), ToString::to_string),
+        user_agent: user_agent(headers),
+        headers: headers
+            .iter()
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.as_str().to_string(), value)
+            })
+            .collect(),
+        at: Utc::now(),
+    };
+    shared.reporter.emit(HoneypotEvent::Request(event)).await;
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(SERVER, shared.server.clone());
+    response
+}
+
+async fn show_login(Extension(template): Extension<Template>) -> Html<String> {
+    Html(
+        template
+            .login()
+            .map(|form| form.render(false))
+            .unwrap_or_default(),
+    )
+}
+
+async fn submit_login(
+    State(shared): State<Arc<Shared>>,
+    Extension(template): Extension<Template>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(fields): Form<HashMap<String, String>>,
+) -> Html<String> {
+    let Some(form) = template.login() else {
+        return Html(String::new());
+    };
+    let attempt = CredentialAttempt {
+        service: format!("http/{}", template.name()),
+        peer,
+        client_banner: user_agent(&headers).unwrap_or_default(),
+        username: fields.get(form.user_field).cloned().unwrap_or_default(),
+        password: fields.get(form.pass_field).cloned().unwrap_or_default(),
+        at: Utc::now(),
+        // Each HTTP request stands alone; there's no session to time.
+        elapsed_ms: 0,
+    };
+    shared
+        .reporter
+        .emit(HoneypotEvent::Credential(attempt))
+        .await;
+    Html(form.render(true))
+}
+
+async fn listing(State(shared): State<Arc<Shared>>) -> Html<String> {
+    let modified = (Utc::now() - ChronoDuration::days(3)).format("%Y-%m-%d %H:%M");
+    Html(format!(
+        "<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 3.2 Final//EN\">\n<html>\n <head>\n  \
+         <title>Index of /backup</title>\n </head>\n <body>\n<h1>Index of /backup</h1>\n  <table>\n   \
+         <tr><th>Name</th><th>Last modified</th><th>Size</th></tr>\n   \
+         <tr><td><a href=\"/\">Parent Directory</a></td><td>&nbsp;</td><td align=\"right\">  - </td></tr>\n   \
+         <tr><td><a href=\"backup.sql\">backup.sql</a></td><td align=\"right\">{modified}  </td>\
+         <td align=\"right\">2.1K</td></tr>\n  </table>\n<address>{}</address>\n</body></html>\n",
+        shared.server.to_str().unwrap_or_default(),
+    ))
+}
+
+async fn backup_sql(
+    State(shared): State<Arc<Shared>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    if !shared.reporter.admit(peer.ip(), false) {
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+    match shared
+        .canaries
+        .issue(peer, user_agent(&headers), uri.path())
+    {
+        Ok((download, dump)) => {
+            shared.reporter.send(HoneypotEvent::Canary(download)).await;
+            (
+                [
+                    (CONTENT_TYPE, "application/sql"),
+                    (CONTENT_DISPOSITION, "attachment; filename=\"backup.sql\""),
+                ],
+                dump,
+            )
+                .into_response()
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+async fn not_found() -> (StatusCode, Html<&'static str>) {
+    (
+        StatusCode::NOT_FOUND,
+        Html(
+            "<!DOCTYPE HTML PUBLIC \"-//IETF//DTD HTML 2.0//EN\">\n<html><head>\n\
+             <title>404 Not Found</title>\n</head><body>\n<h1>Not Found</h1>\n\
+             <p>The requested URL was not found on this server.</p>\n</body></html>\n",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_reads_toml_with_defaults() {
+        let config = HttpConfig::from_toml(
+            r#"
+            listen = ["127.0.0.1:8081", "[::1]:8081"]
+            templates = ["wordpress", "open_directory"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.listen.len(), 2);
+        assert_eq!(
+            config.templates,
+            [Template::WordPress, Template::OpenDirectory]
+        );
+        assert_eq!(config.server, HttpConfig::default().server);
+
+        assert_eq!(HttpConfig::from_toml("").unwrap(), HttpConfig::default());
+        assert!(HttpConfig::from_toml("templates = [\"cpanel\"]").is_err());
+        assert!(HttpConfig::from_toml("listne = []").is_err());
+    }
+
+    #[tokio::test]
+    async fn visitors_are_reported_and_canaries_traced() {
+        let config = HttpConfig::from_toml(
+            r#"
+            listen = ["127.0.0.1:0"]
+            site = "shop.example.com"
+            templates = ["wordpress", "open_directory"]
+            "#,
+        )
+        .unwrap();
+        let tracker = CanaryTracker::new(&config.site, CardRegistry::new(b"registry"), b"canary");
+        let listener = HttpListener::bind(config, tracker.clone()).await.unwrap();
+        let base = format!("http://{}", listener.local_addrs().unwrap()[0]);
+        let (tx, mut rx) = mpsc::channel(64);
+        tokio::spawn(listener.run(tx));
+        let client = reqwest::Client::builder()
+            .user_agent("sqlmap/1.8")
+            .build()
+            .unwrap();
+
+        let page = client
+            .get(format!("{base}/wp-login.php"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(page.status(), 200);
+        assert_eq!(page.headers()["server"], "Apache/2.4.41 (Ubuntu)");
+        assert!(page.text().await.unwrap().contains("name=\"pwd\""));
+
+        let login = client
+            .post(format!("{base}/wp-login.php"))
+            .form(&[("log", "admin"), ("pwd", "letmein")])
+            .send()
+            .await
+            .unwrap();
+        assert!(login.text().await.unwrap().contains("incorrect"));
+
+        for probe in ["/.env", "/phpmyadmin/"] {
+            let response = client.get(format!("{base}{probe}")).send().await.unwrap();
+            assert_eq!(response.status(), 404);
+        }
+
+        let mut dumps = Vec::new();
+        for _ in 0..2 {
+            let response = client
+                .get(format!("{base}/backup/backup.sql"))
+                .send()
+                .await
+                .unwrap();
+            dumps.push(response.text().await.unwrap());
+        }
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        let requests: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                HoneypotEvent::Request(request) => Some(request),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(requests.len(), 6);
+        let probe = requests.iter().find(|r| r.path == "/.env").unwrap();
+        assert_eq!(probe.method, "GET");
+        assert_eq!(probe.user_agent.as_deref(), Some("sqlmap/1.8"));
+        assert!(probe
+            .headers
+            .contains(&("user-agent".to_string(), "sqlmap/1.8".to_string())));
+        assert!(probe.peer.ip().is_loopback());
+
+        let credentials: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                HoneypotEvent::Credential(attempt) => Some(attempt),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].service, "http/wordpress");
+        assert_eq!(credentials[0].username, "admin");
+        assert_eq!(credentials[0].password, "letmein");
+        assert_eq!(credentials[0].client_banner, "sqlmap/1.8");
+
+        let downloads: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                HoneypotEvent::Canary(download) => Some(download),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(downloads.len(), 2);
+        assert_ne!(downloads[0].token, downloads[1].token);
+        assert!(dumps[0].contains(&downloads[0].token));
+        assert!(!dumps[0].contains(&downloads[1].token));
+        assert!(dumps[1].contains(&downloads[1].token));
+
+        let pan = regex::Regex::new(r"'(\d{15,16})'").unwrap();
+        let aws = regex::Regex::new(r"'(AKIA[A-Z2-7]{16})'").unwrap();
+        for (dump, download) in dumps.iter().zip(&downloads) {
+            let cards: Vec<_> = pan.captures_iter(dump).map(|c| c[1].to_string()).collect();
+            assert_eq!(cards.len(), CANARY_CARDS);
+            for card in cards {
+                assert_eq!(tracker.trace_card(&card).unwrap().token, download.token);
+            }
+            let key = &aws.captures(dump).unwrap()[1];
+            assert_eq!(tracker.trace_credential(key).unwrap().token, download.token);
+        }
+        assert!(tracker.trace_card("4111111111111111").is_none());
+    }
+}
@@ -0,0 +1,411 @@
+//! Listeners that pose as a real service and report who knocks.
+//!
+//! A listener never lets anyone in: it plays along long enough to capture
+//! what an attacker tries, then hands each observation to the caller as a
+//! [`HoneypotEvent`]. [`DefendSink`] turns those events into blocks that
+//! i1-srv serves, [`DefenseSink`](crate::sink::DefenseSink) does so by
+//! policy, and [`EventLimiter`] keeps one noisy scanner from flooding
+//! either.
+//!
+//! The SSH listener lives in [`ssh`] behind the `ssh` feature, and the
+//! web one in [`http`] behind `http`; both report through the same channel:
+//!
+//! ```rust,ignore
+//! use i1_honeypot::listener::{ssh::{SshConfig, SshListener}, DefendSink, HoneypotEvent};
+//!
+//! let listener = SshListener::bind("0.0.0.0:2222", SshConfig::default()).await?;
+//! let (tx, mut rx) = tokio::sync::mpsc::channel(256);
+//! tokio::spawn(listener.run(tx));
+//!
+//! let sink = DefendSink::new("/var/lib/i1/admin_blocks.json", Duration::from_secs(86_400));
+//! while let Some(event) = rx.recv().await {
+//!     sink.handle(&event)?;
+//! }
+//! ```
+
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "ssh")]
+pub mod ssh;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::{sink, HoneypotError};
+
+/// Block reason recorded for SSH listener offenders.
+pub const SSH_HONEYPOT_REASON: &str = "ssh-honeypot";
+
+/// Block reason for offenders caught by the HTTP listener.
+pub const HTTP_HONEYPOT_REASON: &str = "http-honeypot";
+
+/// A login tried against a listener.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialAttempt {
+    /// Service the listener was posing as (e.g. "ssh")
+    pub service: String,
+    /// Where the attempt came from
+    pub peer: SocketAddr,
+    /// Version string the client announced (its User-Agent over HTTP)
+    pub client_banner: String,
+    /// Username tried
+    pub username: String,
+    /// Password tried
+    pub password: String,
+    /// When the attempt arrived
+    pub at: DateTime<Utc>,
+    /// Milliseconds between connecting and this attempt
+    pub elapsed_ms: u64,
+}
+
+/// A request made to the HTTP listener.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    /// Where the request came from
+    pub peer: SocketAddr,
+    /// Request method
+    pub method: String,
+    /// Path and query requested
+    pub path: String,
+    /// User-Agent header, if sent
+    pub user_agent: Option<String>,
+    /// Every header sent, names lowercased, in order
+    pub headers: Vec<(String, String)>,
+    /// When it arrived
+    pub at: DateTime<Utc>,
+}
+
+/// A canary file handed to a visitor.
+///
+/// The token is written into the file and recorded as the session of
+/// every card planted in it, so a card or credential from the file seen
+/// anywhere later leads back to this download.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanaryDownload {
+    /// Token unique to this download
+    pub token: String,
+    /// Who downloaded it
+    pub peer: SocketAddr,
+    /// Their User-Agent, if sent
+    pub user_agent: Option<String>,
+    /// Path of the file served
+    pub resource: String,
+    /// Tracking ids of the cards in the file
+    pub cards: Vec<Uuid>,
+    /// Tracking ids of the credentials in the file
+    pub credentials: Vec<Uuid>,
+    /// When it was served
+    pub at: DateTime<Utc>,
+}
+
+/// Something a listener observed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HoneypotEvent {
+    /// A client connected.
+    Connected {
+        /// Where the connection came from
+        peer: SocketAddr,
+        /// When it was accepted
+        at: DateTime<Utc>,
+    },
+    /// A client tried to log in.
+    Credential(CredentialAttempt),
+    /// A client made an HTTP request.
+    Request(HttpRequest),
+    /// A client downloaded a canary file.
+    Canary(CanaryDownload),
+    /// A client went away, or was sent away.
+    Disconnected {
+        /// Where the connection came from
+        peer: SocketAddr,
+        /// When it closed
+        at: DateTime<Utc>,
+        /// Logins tried over the connection
+        attempts: u32,
+    },
+}
+
+impl HoneypotEvent {
+    /// Address of the client the event is about.
+    #[must_use]
+    pub fn peer(&self) -> SocketAddr {
+        match self {
+            Self::Connected { peer, .. } | Self::Disconnected { peer, .. } => *peer,
+            Self::Credential(attempt) => attempt.peer,
+            Self::Request(request) => request.peer,
+            Self::Canary(download) => download.peer,
+        }
+    }
+
+    /// When the event happened.
+    #[must_use]
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Self::Connected { at, .. } | Self::Disconnected { at, .. } => *at,
+            Self::Credential(attempt) => attempt.at,
+            Self::Request(request) => request.at,
+            Self::Canary(download) => download.at,
+        }
+    }
+}
+
+/// Caps how many events each source address may produce per window.
+#[derive(Debug, Clone)]
+pub struct EventLimiter {
+    max_events: u32,
+    window: Duration,
+    seen: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl EventLimiter {
+    /// Allow up to `max_events` per source address in every `window`.
+    #[must_use]
+    pub fn new(max_events: u32, window: Duration) -> Self {
+        Self {
+            max_events,
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Count an event from `ip` at `now`; `false` once it's over its limit.
+    pub fn allow(&mut self, ip: IpAddr, now: Instant) -> bool {
+        let (started, count) = self.seen.entry(ip).or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(1);
+        *count <= self.max_events
+    }
+
+    /// Forget sources whose window has passed.
+    pub fn prune(&mut self, now: Instant) {
+        let window = self.window;
+        self.seen
+            .retain(|_, (started, _)| now.duration_since(*started) < window);
+    }
+}
+
+impl Default for EventLimiter {
+    /// 30 events a minute per source.
+    fn default() -> Self {
+        Self::new(30, Duration::from_secs(60))
+    }
+}
+
+/// Sends a listener's events that are within the limiter's budget.
+#[cfg(any(feature = "ssh", feature = "http"))]
+pub(crate) struct Reporter {
+    events: tokio::sync::mpsc::Sender<HoneypotEvent>,
+    limiter: std::sync::Arc<std::sync::Mutex<EventLimiter>>,
+}
+
+#[cfg(any(feature = "ssh", feature = "http"))]
+impl Reporter {
+    pub(crate) fn new(
+        events: tokio::sync::mpsc::Sender<HoneypotEvent>,
+        limiter: std::sync::Arc<std::sync::Mutex<EventLimiter>>,
+    ) -> Self {
+        Self { events, limiter }
+    }
+
+    /// Count an event from `ip` against the limiter; `false` if it's
+    /// over budget.
+    pub(crate) fn admit(&self, ip: IpAddr, prune: bool) -> bool {
+        let mut limiter = self
+            .limiter
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let now = Instant::now();
+        if prune {
+            limiter.prune(now);
+        }
+        limiter.allow(ip, now)
+    }
+
+    /// Send `event` if its source is within budget.
+    pub(crate) async fn emit(&self, event: HoneypotEvent) {
+        let prune = matches!(
+            event,
+            HoneypotEvent::Connected { .. } | HoneypotEvent::Request(_)
+        );
+        if self.admit(event.peer().ip(), prune) {
+            self.send(event).await;
+        }
+    }
+
+    /// Send an event already admitted.
+    pub(crate) async fn send(&self, event: HoneypotEvent) {
+        // Nobody listening is the caller's choice, not an error.
+        let _ = self.events.send(event).await;
+    }
+}
+
+/// Writes listener offenders into a defense state file.
+///
+/// Entries use the detailed `blocked_ips` form i1-srv's collector reads
+/// (`ip`, `threat`, `hits`, `first_seen`, `last_seen`, `reason`, `ttl`), so
+/// point this at the server's `admin.blocks_path` or another file it
+/// loads. The `i1 defend` state file itself is still schema 1, which only
+/// holds plain addresses and has nowhere to put the TTL. Whatever else the
+/// file holds is kept as is.
+#[derive(Debug, Clone)]
+pub struct DefendSink {
+    path: PathBuf,
+    ttl: Duration,
+    reason: String,
+}
+
+impl DefendSink {
+    /// Block offenders in `path` for `ttl` after they were first seen.
+    pub fn new(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            path: path.into(),
+            ttl,
+            reason: SSH_HONEYPOT_REASON.into(),
+        }
+    }
+
+    /// Record blocks with a different reason.
+    #[must_use]
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = reason.into();
+        self
+    }
+
+    /// File the sink writes to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Block the source of a credential attempt; other events are ignored.
+    ///
+    /// Returns whether the file changed.
+    pub fn handle(&self, event: &HoneypotEvent) -> Result<bool, HoneypotError> {
+        match event {
+            HoneypotEvent::Credential(attempt) => self.record(attempt.peer.ip(), attempt.at),
+            _ => Ok(false),
+        }
+    }
+
+    /// Add `ip` to the file, or count another hit on its entry.
+    ///
+    /// An address already listed without details is left alone: someone
+    /// blocked it by hand, for good.
+    pub fn record(&self, ip: IpAddr, at: DateTime<Utc>) -> Result<bool, HoneypotError> {
+        let _lock = sink::lock_state(&self.path)?;
+        let mut state = sink::read_state(&self.path)?;
+        let entries = sink::blocked_ips(&mut state)?;
+
+        let ip = ip.to_string();
+        let at = at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        match entries
+            .iter_mut()
+            .find(|entry| sink::listed_ip(entry) == Some(&ip))
+        {
+            Some(Value::Object(entry)) => {
+                let hits = entry.get("hits").and_then(Value::as_u64).unwrap_or(0);
+                entry.insert("hits".into(), json!(hits + 1));
+                entry.insert("last_seen".into(), json!(at));
+            }
+            Some(_) => return Ok(false),
+            None => entries.push(json!({
+                "ip": ip,
+                "threat": "high",
+                "hits": 1,
+                "first_seen": at,
+                "last_seen": at,
+                "reason": self.reason,
+                "ttl": self.ttl.as_secs(),
+            })),
+        }
+
+        sink::write_state(&self.path, &state)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(peer: &str, at: &str) -> HoneypotEvent {
+        HoneypotEvent::Credential(CredentialAttempt {
+            service: "ssh".into(),
+            peer: peer.parse().unwrap(),
+            client_banner: "SSH-2.0-libssh_0.9.6".into(),
+            username: "root".into(),
+            password: "123456".into(),
+            at: at.parse().unwrap(),
+            elapsed_ms: 120,
+        })
+    }
+
+    #[test]
+    fn limiter_resets_each_window() {
+        let mut limiter = EventLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        let noisy: IpAddr = "203.0.113.9".parse().unwrap();
+        let quiet: IpAddr = "198.51.100.4".parse().unwrap();
+
+        assert!(limiter.allow(noisy, start));
+        assert!(limiter.allow(noisy, start));
+        assert!(!limiter.allow(noisy, start + Duration::from_secs(30)));
+        assert!(limiter.allow(quiet, start + Duration::from_secs(30)));
+        assert!(limiter.allow(noisy, start + Duration::from_secs(60)));
+
+        limiter.prune(start + Duration::from_secs(95));
+        assert_eq!(limiter.seen.len(), 1);
+    }
+
+    #[test]
+    fn sink_adds_and_counts_offenders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.json");
+        std::fs::write(
+            &path,
+            r#"{"blocked_ips": ["198.51.100.4"], "blocked_countries": ["cn"]}"#,
+        )
+        .unwrap();
+        let sink = DefendSink::new(&path, Duration::from_secs(86_400));
+
+        let peer = "203.0.113.9:50122";
+        assert!(sink.handle(&attempt(peer, "2026-03-01T10:00:00Z")).unwrap());
+        assert!(sink.handle(&attempt(peer, "2026-03-01T10:05:00Z")).unwrap());
+        assert!(!sink
+            .handle(&attempt("198.51.100.4:22", "2026-03-01T10:06:00Z"))
+            .unwrap());
+        let connected = HoneypotEvent::Connected {
+            peer: peer.parse().unwrap(),
+            at: Utc::now(),
+        };
+        assert!(!sink.handle(&connected).unwrap());
+
+        let state: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(state["blocked_countries"], json!(["cn"]));
+        assert_eq!(
+            state["blocked_ips"],
+            json!([
+                "198.51.100.4",
+                {
+                    "ip": "203.0.113.9",
+                    "threat": "high",
+                    "hits": 2,
+                    "first_seen": "2026-03-01T10:00:00Z",
+                    "last_seen": "2026-03-01T10:05:00Z",
+                    "reason": "ssh-honeypot",
+                    "ttl": 86400
+                }
+            ])
+        );
+    }
+}
@@ -0,0 +1,795 @@
+//! A minimal SSH server that completes the handshake and refuses every login.
+//!
+//! It speaks one algorithm suite, which OpenSSH since 6.5 and the common
+//! brute-forcing libraries all offer: `curve25519-sha256` key exchange, an
+//! `ssh-ed25519` host key and `chacha20-poly1305@openssh.com`. Clients that
+//! can't agree on it are disconnected during key exchange. Once keys are
+//! in place it offers password authentication, reports every username and
+//! password as a [`HoneypotEvent::Credential`], and fails them all until
+//! the client gives up or reaches [`SshConfig::max_auth_attempts`].
+
+use chrono::Utc;
+use ring::aead::chacha20_poly1305_openssh::{OpeningKey, SealingKey, KEY_LEN, TAG_LEN};
+use ring::agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519};
+use ring::digest::{self, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc;
+
+use super::{CredentialAttempt, EventLimiter, HoneypotEvent, Reporter};
+use crate::HoneypotError;
+
+const MSG_DISCONNECT: u8 = 1;
+const MSG_IGNORE: u8 = 2;
+const MSG_UNIMPLEMENTED: u8 = 3;
+const MSG_DEBUG: u8 = 4;
+const MSG_SERVICE_REQUEST: u8 = 5;
+const MSG_SERVICE_ACCEPT: u8 = 6;
+const MSG_KEXINIT: u8 = 20;
+const MSG_NEWKEYS: u8 = 21;
+const MSG_KEX_ECDH_INIT: u8 = 30;
+const MSG_KEX_ECDH_REPLY: u8 = 31;
+const MSG_USERAUTH_REQUEST: u8 = 50;
+const MSG_USERAUTH_FAILURE: u8 = 51;
+
+const DISCONNECT_KEY_EXCHANGE_FAILED: u32 = 3;
+const DISCONNECT_SERVICE_NOT_AVAILABLE: u32 = 7;
+const DISCONNECT_NO_MORE_AUTH_METHODS: u32 = 14;
+
+const KEX_ALGORITHMS: &[&str] = &["curve25519-sha256", "curve25519-sha256@libssh.org"];
+const HOST_KEY_ALGORITHM: &str = "ssh-ed25519";
+const CIPHER: &str = "chacha20-poly1305@openssh.com";
+
+/// Largest packet accepted from a client; RFC 4253 only requires 35000.
+const MAX_PACKET_LEN: usize = 35_000;
+/// Longest version line, CR LF included (RFC 4253 §4.2).
+const MAX_VERSION_LEN: usize = 255;
+/// Lines a client may send before its version string.
+const MAX_PREAMBLE_LINES: usize = 10;
+
+/// How the SSH listener presents itself.
+#[derive(Debug, Clone)]
+pub struct SshConfig {
+    /// Version string sent to clients, without the trailing CR LF
+    pub banner: String,
+    /// Password attempts allowed per connection before disconnecting
+    pub max_auth_attempts: u32,
+    /// How long to wait for each client packet
+    pub idle_timeout: Duration,
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            banner: "SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.10".into(),
+            max_auth_attempts: 6,
+            idle_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// An SSH honeypot bound to a port.
+pub struct SshListener {
+    listener: TcpListener,
+    config: Arc<SshConfig>,
+    host_key: Arc<Ed25519KeyPair>,
+    limiter: Arc<Mutex<EventLimiter>>,
+}
+
+impl SshListener {
+    /// Bind to `addr` with a freshly generated host key.
+    pub async fn bind(addr: impl ToSocketAddrs, config: SshConfig) -> Result<Self, HoneypotError> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| HoneypotError::InvalidConfig("failed to generate host key".into()))?;
+        Self::bind_with_host_key(addr, config, pkcs8.as_ref()).await
+    }
+
+    /// Bind to `addr` with an Ed25519 host key in PKCS#8 form, so the
+    /// fingerprint stays the same across restarts.
+    pub async fn bind_with_host_key(
+        addr: impl ToSocketAddrs,
+        config: SshConfig,
+        pkcs8: &[u8],
+    ) -> Result<Self, HoneypotError> {
+        let host_key = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8)
+            .map_err(|e| HoneypotError::InvalidConfig(format!("invalid host key: {e}")))?;
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            config: Arc::new(config),
+            host_key: Arc::new(host_key),
+            limiter: Arc::new(Mutex::new(EventLimiter::default())),
+        })
+    }
+
+    /// Limit the events each source address produces.
+    #[must_use]
+    pub fn with_limiter(mut self, limiter: EventLimiter) -> Self {
+        self.limiter = Arc::new(Mutex::new(limiter));
+        self
+    }
+
+    /// Address the listener is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, HoneypotError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accept connections until the listener fails, sending what each
+    /// client does to `events`.
+    ///
+    /// Events over the limiter's budget are dropped; a closed channel
+    /// doesn't stop the listener.
+    pub async fn run(self, events: mpsc::Sender<HoneypotEvent>) -> Result<(), HoneypotError> {
+        let reporter = Arc::new(Reporter::new(events, self.limiter));
+        loop {
+            let (stream, peer) = self.listener.accept().await?;
+            let session = Session {
+                peer,
+                connected: Instant::now(),
+                config: Arc::clone(&self.config),
+                host_key: Arc::clone(&self.host_key),
+                reporter: Arc::clone(&reporter),
+                attempts: 0,
+            };
+            tokio::spawn(session.serve(stream));
+        }
+    }
+}
+
+/// One client connection.
+struct Session {
+    peer: SocketAddr,
+    connected: Instant,
+    config: Arc<SshConfig>,
+    host_key: Arc<Ed25519KeyPair>,
+    reporter: Arc<Reporter>,
+    attempts: u32,
+}
+
+impl Session {
+    async fn serve(mut self, stream: TcpStream) {
+        self.reporter
+            .emit(HoneypotEvent::Connected {
+                peer: self.peer,
+                at: Utc::now(),
+            })
+            .await;
+        let mut transport = Transport::new(stream, self.config.idle_timeout);
+        // Protocol errors and dropped connections end the session the same way.
+        let _ = self.converse(&mut transport).await;
+        self.reporter
+            .emit(HoneypotEvent::Disconnected {
+                peer: self.peer,
+                at: Utc::now(),
+                attempts: self.attempts,
+            })
+            .await;
+    }
+
+    async fn converse(&mut self, transport: &mut Transport) -> io::Result<()> {
+        let client_banner = transport.exchange_versions(&self.config.banner).await?;
+        transport
+            .key_exchange(&self.config.banner, &client_banner, &self.host_key)
+            .await?;
+
+        loop {
+            let message = transport.next_message().await?;
+            let mut reader = Reader(&message[1..]);
+            match message[0] {
+                MSG_SERVICE_REQUEST => {
+                    if reader.string()? != b"ssh-userauth" {
+                        return transport
+                            .disconnect(DISCONNECT_SERVICE_NOT_AVAILABLE, "Service not available")
+                            .await;
+                    }
+                    let mut accept = vec![MSG_SERVICE_ACCEPT];
+                    put_string(&mut accept, b"ssh-userauth");
+                    transport.write_packet(&accept).await?;
+                }
+                MSG_USERAUTH_REQUEST => {
+                    let username = reader.text()?;
+                    let _service = reader.string()?;
+                    if reader.string()? == b"password" {
+                        let _change = reader.byte()?;
+                        let password = reader.text()?;
+                        self.attempts += 1;
+                        self.report(&client_banner, username, password).await;
+                    }
+                    let mut failure = vec![MSG_USERAUTH_FAILURE];
+                    put_string(&mut failure, b"password");
+                    failure.push(0);
+                    transport.write_packet(&failure).await?;
+                    if self.attempts >= self.config.max_auth_attempts {
+                        return transport
+                            .disconnect(
+                                DISCONNECT_NO_MORE_AUTH_METHODS,
+                                "Too many authentication failures",
+                            )
+                            .await;
+                    }
+                }
+                _ => {
+                    let mut unimplemented = vec![MSG_UNIMPLEMENTED];
+                    put_u32(&mut unimplemented, transport.last_recv_seq);
+                    transport.write_packet(&unimplemented).await?;
+                }
+            }
+        }
+    }
+
+    async fn report(&self, client_banner: &str, username: String, password: String) {
+        let attempt = CredentialAttempt {
+            service: "ssh".into(),
+            peer: self.peer,
+            client_banner: client_banner.into(),
+            username,
+            password,
+            at: Utc::now(),
+            elapsed_ms: u64::try_from(self.connected.elapsed().as_millis()).unwrap_or(u64::MAX),
+        };
+        self.reporter.emit(HoneypotEvent::Credential(attempt)).await;
+    }
+}
+
+/// SSH binary packet framing, in the clear until keys are exchanged.
+struct Transport<S = TcpStream> {
+    stream: BufReader<S>,
+    idle_timeout: Duration,
+    rng: SystemRandom,
+    send_seq: u32,
+    recv_seq: u32,
+    last_recv_seq: u32,
+    sealing: Option<SealingKey>,
+    opening: Option<OpeningKey>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Transport<S> {
+    fn new(stream: S, idle_timeout: Duration) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            idle_timeout,
+            rng: SystemRandom::new(),
+            send_seq: 0,
+            recv_seq: 0,
+            last_recv_seq: 0,
+            sealing: None,
+            opening: None,
+        }
+    }
+
+    /// Send our version line and read the client's, skipping any lines
+    /// before it.
+    async fn exchange_versions(&mut self, banner: &str) -> io::Result<String> {
+        let line = format!("{banner}\r\n");
+        self.stream.get_mut().write_all(line.as_bytes()).await?;
+
+        for _ in 0..MAX_PREAMBLE_LINES {
+            let mut line = Vec::new();
+            let mut limited = (&mut self.stream).take(MAX_VERSION_LEN as u64);
+            let read = limited.read_until(b'\n', &mut line);
+            if tokio::time::timeout(self.idle_timeout, read).await?? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if line.starts_with(b"SSH-") {
+                let line = String::from_utf8_lossy(&line);
+                return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+            }
+        }
+        Err(malformed("no SSH version line"))
+    }
+
+    /// Run a curve25519-sha256 key exchange and switch to the new keys.
+    async fn key_exchange(
+        &mut self,
+        server_banner: &str,
+        client_banner: &str,
+        host_key: &Ed25519KeyPair,
+    ) -> io::Result<()> {
+        let server_kexinit = self.kexinit()?;
+        self.write_packet(&server_kexinit).await?;
+        let client_kexinit = self.expect(MSG_KEXINIT).await?;
+        if !supports_our_algorithms(&client_kexinit)? {
+            return self
+                .disconnect(DISCONNECT_KEY_EXCHANGE_FAILED, "No matching algorithms")
+                .await;
+        }
+
+        let init = self.expect(MSG_KEX_ECDH_INIT).await?;
+        let client_public = Reader(&init[1..]).string()?.to_vec();
+        let private = EphemeralPrivateKey::generate(&X25519, &self.rng)
+            .map_err(|_| malformed("key generation failed"))?;
+        let server_public = private
+            .compute_public_key()
+            .map_err(|_| malformed("key generation failed"))?;
+        let shared = agreement::agree_ephemeral(
+            private,
+            &UnparsedPublicKey::new(&X25519, &client_public),
+            <[u8]>::to_vec,
+        )
+        .map_err(|_| malformed("invalid client public key"))?;
+        let mut secret = Vec::new();
+        put_mpint(&mut secret, &shared);
+
+        let mut host_key_blob = Vec::new();
+        put_string(&mut host_key_blob, HOST_KEY_ALGORITHM.as_bytes());
+        put_string(&mut host_key_blob, host_key.public_key().as_ref());
+
+        let mut exchange = Vec::new();
+        put_string(&mut exchange, client_banner.as_bytes());
+        put_string(&mut exchange, server_banner.as_bytes());
+        put_string(&mut exchange, &client_kexinit);
+        put_string(&mut exchange, &server_kexinit);
+        put_string(&mut exchange, &host_key_blob);
+        put_string(&mut exchange, &client_public);
+        put_string(&mut exchange, server_public.as_ref());
+        exchange.extend_from_slice(&secret);
+        let hash = digest::digest(&SHA256, &exchange);
+
+        let mut signature = Vec::new();
+        put_string(&mut signature, HOST_KEY_ALGORITHM.as_bytes());
+        put_string(&mut signature, host_key.sign(hash.as_ref()).as_ref());
+
+        let mut reply = vec![MSG_KEX_ECDH_REPLY];
+        put_string(&mut reply, &host_key_blob);
+        put_string(&mut reply, server_public.as_ref());
+        put_string(&mut reply, &signature);
+        self.write_packet(&reply).await?;
+
+        // With a single key exchange the session identifier is its hash.
+        let hash = hash.as_ref();
+        self.write_packet(&[MSG_NEWKEYS]).await?;
+        self.sealing = Some(SealingKey::new(&derive_key(&secret, hash, b'D')));
+        self.expect(MSG_NEWKEYS).await?;
+        self.opening = Some(OpeningKey::new(&derive_key(&secret, hash, b'C')));
+        Ok(())
+    }
+
+    fn kexinit(&self) -> io::Result<Vec<u8>> {
+        let mut cookie = [0u8; 16];
+        self.rng
+            .fill(&mut cookie)
+            .map_err(|_| malformed("random generation failed"))?;
+        let mut payload = vec![MSG_KEXINIT];
+        payload.extend_from_slice(&cookie);
+        let kex = KEX_ALGORITHMS.join(",");
+        #[rustfmt::skip]
+        let lists = [
+            kex.as_str(), HOST_KEY_ALGORITHM,
+            CIPHER, CIPHER,
+            "hmac-sha2-256", "hmac-sha2-256",
+            "none", "none",
+            "", "",
+        ];
+        for list in lists {
+            put_string(&mut payload, list.as_bytes());
+        }
+        payload.push(0);
+        put_u32(&mut payload, 0);
+        Ok(payload)
+    }
+
+    /// Next message that isn't transport chatter.
+    async fn next_message(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            let payload = self.read_packet().await?;
+            match payload.first() {
+                None => return Err(malformed("empty packet")),
+                Some(&MSG_DISCONNECT) => return Err(io::ErrorKind::ConnectionAborted.into()),
+                Some(&(MSG_IGNORE | MSG_DEBUG | MSG_UNIMPLEMENTED)) => {}
+                Some(_) => return Ok(payload),
+            }
+        }
+    }
+
+    async fn expect(&mut self, kind: u8) -> io::Result<Vec<u8>> {
+        let message = self.next_message().await?;
+        if message[0] == kind {
+            Ok(message)
+        } else {
+            Err(malformed("unexpected message"))
+        }
+    }
+
+    async fn disconnect(&mut self, reason: u32, description: &str) -> io::Result<()> {
+        let mut message = vec![MSG_DISCONNECT];
+        put_u32(&mut message, reason);
+        put_string(&mut message, description.as_bytes());
+        put_string(&mut message, b"");
+        self.write_packet(&message).await
+    }
+
+    async fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        tokio::time::timeout(self.idle_timeout, self.stream.read_exact(buf)).await??;
+        Ok(())
+    }
+
+    async fn read_packet(&mut self) -> io::Result<Vec<u8>> {
+        let mut length = [0u8; 4];
+        self.read_exact(&mut length).await?;
+        let plain_length = match &self.opening {
+            Some(key) => key.decrypt_packet_length(self.recv_seq, length),
+            None => length,
+        };
+        let body_len = u32::from_be_bytes(plain_length) as usize;
+        if !(5..=MAX_PACKET_LEN).contains(&body_len) {
+            return Err(malformed("bad packet length"));
+        }
+
+        let mut packet = vec![0u8; 4 + body_len];
+        packet[..4].copy_from_slice(&length);
+        self.read_exact(&mut packet[4..]).await?;
+        let body = if self.opening.is_some() {
+            let mut tag = [0u8; TAG_LEN];
+            self.read_exact(&mut tag).await?;
+            let key = self.opening.as_ref().expect("checked above");
+            key.open_in_place(self.recv_seq, &mut packet, &tag)
+                .map_err(|_| malformed("packet failed authentication"))?
+        } else {
+            &packet[4..]
+        };
+        self.last_recv_seq = self.recv_seq;
+        self.recv_seq = self.recv_seq.wrapping_add(1);
+
+        let padding = usize::from(body[0]);
+        if padding + 1 >= body.len() {
+            return Err(malformed("bad padding length"));
+        }
+        Ok(body[1..body.len() - padding].to_vec())
+    }
+
+    async fn write_packet(&mut self, payload: &[u8]) -> io::Result<()> {
+        // The AEAD cipher leaves the length field out of block alignment.
+        let aligned = if self.sealing.is_some() { 1 } else { 5 } + payload.len();
+        let mut padding = 8 - aligned % 8;
+        if padding < 4 {
+            padding += 8;
+        }
+        let body_len =
+            u32::try_from(1 + payload.len() + padding).map_err(|_| malformed("packet too long"))?;
+
+        let mut packet = Vec::with_capacity(4 + body_len as usize + TAG_LEN);
+        put_u32(&mut packet, body_len);
+        #[allow(clippy::cast_possible_truncation)]
+        packet.push(padding as u8);
+        packet.extend_from_slice(payload);
+        let mut random = [0u8; 16];
+        self.rng
+            .fill(&mut random[..padding])
+            .map_err(|_| malformed("random generation failed"))?;
+        packet.extend_from_slice(&random[..padding]);
+        if let Some(key) = &self.sealing {
+            let mut tag = [0u8; TAG_LEN];
+            key.seal_in_place(self.send_seq, &mut packet, &mut tag);
+            packet.extend_from_slice(&tag);
+        }
+        self.send_seq = self.send_seq.wrapping_add(1);
+        self.stream.get_mut().write_all(&packet).await
+    }
+}
+
+/// Whether a client's KEXINIT offers everything we speak.
+fn supports_our_algorithms(kexinit: &[u8]) -> io::Result<bool> {
+    let mut reader = Reader(
+        kexinit
+            .get(17..)
+            .ok_or_else(|| malformed("short KEXINIT"))?,
+    );
+    let kex = reader.string()?;
+    let host_key = reader.string()?;
+    let cipher_c2s = reader.string()?;
+    let cipher_s2c = reader.string()?;
+    let _mac_c2s = reader.string()?;
+    let _mac_s2c = reader.string()?;
+    let compression_c2s = reader.string()?;
+    let compression_s2c = reader.string()?;
+
+    let offers = |list: &[u8], name: &str| list.split(|&b| b == b',').any(|n| n == name.as_bytes());
+    Ok(KEX_ALGORITHMS.iter().any(|name| offers(kex, name))
+        && offers(host_key, HOST_KEY_ALGORITHM)
+        && offers(cipher_c2s, CIPHER)
+        && offers(cipher_s2c, CIPHER)
+        && offers(compression_c2s, "none")
+        && offers(compression_s2c, "none"))
+}
+
+/// RFC 4253 §7.2 key derivation, stretched to the 64 bytes chacha20-poly1305
+/// needs.
+fn derive_key(secret: &[u8], hash: &[u8], letter: u8) -> [u8; KEY_LEN] {
+    let mut first = digest::Context::new(&SHA256);
+    first.update(secret);
+    first.update(hash);
+    first.update(&[letter]);
+    first.update(hash);
+    let first = first.finish();
+
+    let mut second = digest::Context::new(&SHA256);
+    second.update(secret);
+    second.update(hash);
+    second.update(first.as_ref());
+    let second = second.finish();
+
+    let mut key = [0u8; KEY_LEN];
+    key[..32].copy_from_slice(first.as_ref());
+    key[32..].copy_from_slice(second.as_ref());
+    key
+}
+
+fn malformed(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn put_string(out: &mut Vec<u8>, value: &[u8]) {
+    put_u32(out, value.len() as u32);
+    out.extend_from_slice(value);
+}
+
+/// An unsigned big-endian integer as an SSH `mpint`.
+fn put_mpint(out: &mut Vec<u8>, value: &[u8]) {
+    let start = value.iter().position(|&b| b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    if value.first().is_some_and(|&b| b & 0x80 != 0) {
+        let mut padded = vec![0];
+        padded.extend_from_slice(value);
+        put_string(out, &padded);
+    } else {
+        put_string(out, value);
+    }
+}
+
+/// Reads SSH wire types off the front of a message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(malformed("truncated message"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn string(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.take(4)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        self.take(len)
+    }
+
+    fn text(&mut self) -> io::Result<String> {
+        Ok(String::from_utf8_lossy(self.string()?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn mpints_are_minimal_and_unsigned() {
+        let mut out = Vec::new();
+        put_mpint(&mut out, &[0, 0, 0x7f, 1]);
+        put_mpint(&mut out, &[0x80, 0]);
+        put_mpint(&mut out, &[0, 0]);
+        assert_eq!(
+            out,
+            [0, 0, 0, 2, 0x7f, 1, 0, 0, 0, 3, 0, 0x80, 0, 0, 0, 0, 0]
+        );
+    }
+
+    /// Feed `bytes` to a fresh transport and read one packet.
+    fn read_one(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            let mut transport = Transport::new(server, Duration::from_secs(5));
+            let mut client = client;
+            client.write_all(bytes).await?;
+            drop(client);
+            transport.read_packet().await
+        })
+    }
+
+    /// A cleartext packet as a client would frame it.
+    fn frame(payload: &[u8], padding: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        put_u32(&mut packet, (1 + payload.len() + padding.len()) as u32);
+        packet.push(padding.len() as u8);
+        packet.extend_from_slice(payload);
+        packet.extend_from_slice(padding);
+        packet
+    }
+
+    /// A KEXINIT whose name-lists are `lists`, in wire order.
+    fn kexinit(lists: &[&[u8]]) -> Vec<u8> {
+        let mut payload = vec![MSG_KEXINIT];
+        payload.extend_from_slice(&[0; 16]);
+        for list in lists {
+            put_string(&mut payload, list);
+        }
+        payload
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_input_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+            if let Ok(payload) = read_one(&bytes) {
+                prop_assert!(payload.len() + 5 <= bytes.len());
+            }
+        }
+
+        #[test]
+        fn packets_round_trip(
+            payload in prop::collection::vec(any::<u8>(), 1..256),
+            padding in prop::collection::vec(any::<u8>(), 4..=255),
+        ) {
+            prop_assert_eq!(read_one(&frame(&payload, &padding)).unwrap(), payload);
+        }
+
+        #[test]
+        fn truncated_packets_are_rejected(
+            payload in prop::collection::vec(any::<u8>(), 1..256),
+            cut in any::<prop::sample::Index>(),
+        ) {
+            let packet = frame(&payload, &[0; 4]);
+            let truncated = &packet[..cut.index(packet.len())];
+            let err = read_one(truncated).unwrap_err();
+            prop_assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        #[test]
+        fn oversized_lengths_are_rejected(
+            length in prop_oneof![0..5u32, (MAX_PACKET_LEN as u32 + 1)..=u32::MAX],
+            rest in prop::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let mut packet = length.to_be_bytes().to_vec();
+            packet.extend_from_slice(&rest);
+            let err = read_one(&packet).unwrap_err();
+            prop_assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            prop_assert_eq!(err.to_string(), "bad packet length");
+        }
+
+        #[test]
+        fn padding_past_the_payload_is_rejected(
+            rest in prop::collection::vec(any::<u8>(), 4..64),
+            padding: u8,
+        ) {
+            // Padding that leaves no room for a message type byte.
+            prop_assume!(usize::from(padding) >= rest.len());
+            let mut packet = Vec::new();
+            put_u32(&mut packet, (rest.len() + 1) as u32);
+            packet.push(padding);
+            packet.extend_from_slice(&rest);
+            let err = read_one(&packet).unwrap_err();
+            prop_assert_eq!(err.to_string(), "bad padding length");
+        }
+
+        #[test]
+        fn malformed_kexinit_never_panics(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+            let _ = supports_our_algorithms(&bytes);
+            let mut reader = Reader(&bytes);
+            while reader.string().is_ok() {}
+        }
+
+        #[test]
+        fn truncated_name_lists_are_rejected(cut in any::<prop::sample::Index>()) {
+            let full = kexinit(&[
+                b"curve25519-sha256", b"ssh-ed25519",
+                CIPHER.as_bytes(), CIPHER.as_bytes(),
+                b"hmac-sha2-256", b"hmac-sha2-256",
+                b"none", b"none",
+            ]);
+            prop_assert!(supports_our_algorithms(&full).unwrap());
+            let truncated = &full[..cut.index(full.len())];
+            prop_assert!(supports_our_algorithms(truncated).is_err());
+        }
+
+        #[test]
+        fn name_lists_match_whole_names(
+            kex in "[a-z0-9@.,-]{0,40}",
+            cipher in "[a-z0-9@.,-]{0,40}",
+        ) {
+            let offered = kexinit(&[
+                kex.as_bytes(), b"ssh-ed25519",
+                cipher.as_bytes(), cipher.as_bytes(),
+                b"", b"",
+                b"none", b"none",
+            ]);
+            let names = |list: &str| list.split(',').map(str::to_string).collect::<Vec<_>>();
+            let expected = KEX_ALGORITHMS.iter().any(|name| names(&kex).iter().any(|n| n == name))
+                && names(&cipher).iter().any(|n| n == CIPHER);
+            prop_assert_eq!(supports_our_algorithms(&offered).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn name_list_length_past_the_message_is_rejected() {
+        let mut message = kexinit(&[]);
+        put_u32(&mut message, u32::MAX);
+        message.extend_from_slice(b"curve25519-sha256");
+        let err = supports_our_algorithms(&message).unwrap_err();
+        assert_eq!(err.to_string(), "truncated message");
+    }
+
+    /// Needs an OpenSSH client on the PATH.
+    #[tokio::test]
+    #[ignore = "needs an OpenSSH client; run with --ignored"]
+    async fn openssh_client_credentials_are_captured() {
+        let listener = SshListener::bind("127.0.0.1:0", SshConfig::default())
+            .await
+            .unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, mut rx) = mpsc::channel(16);
+        tokio::spawn(listener.run(tx));
+
+        let dir = tempfile::tempdir().unwrap();
+        let askpass = dir.path().join("askpass.sh");
+        std::fs::write(&askpass, "#!/bin/sh\necho 'hunter2'\n").unwrap();
+        std::fs::set_permissions(&askpass, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let client = tokio::task::spawn_blocking(move || {
+            Command::new("ssh")
+                .args(["-F", "/dev/null", "-p", &port.to_string()])
+                .args(["-o", "StrictHostKeyChecking=no"])
+                .args(["-o", "UserKnownHostsFile=/dev/null"])
+                .args(["-o", "PreferredAuthentications=password"])
+                .args(["-o", "NumberOfPasswordPrompts=1"])
+                .args(["-o", "ConnectTimeout=5"])
+                .args(["admin@127.0.0.1", "true"])
+                .env("SSH_ASKPASS", &askpass)
+                .env("SSH_ASKPASS_REQUIRE", "force")
+                .env("DISPLAY", ":0")
+                .stdin(Stdio::null())
+                .output()
+                .unwrap()
+        });
+        let output = tokio::time::timeout(Duration::from_secs(20), client)
+            .await
+            .unwrap()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(stderr.contains("Permission denied"), "{stderr}");
+
+        let mut events = Vec::new();
+        while let Ok(Some(event)) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+            let done = matches!(event, HoneypotEvent::Disconnected { .. });
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        assert!(matches!(events[0], HoneypotEvent::Connected { .. }));
+        let HoneypotEvent::Credential(attempt) = &events[1] else {
+            panic!("expected a credential, got {events:?}");
+        };
+        assert_eq!(attempt.service, "ssh");
+        assert_eq!(attempt.username, "admin");
+        assert_eq!(attempt.password, "hunter2");
+        assert!(attempt.client_banner.starts_with("SSH-2.0-OpenSSH"));
+        assert!(attempt.peer.ip().is_loopback());
+        assert!(matches!(
+            events[2],
+            HoneypotEvent::Disconnected { attempts: 1, .. }
+        ));
+    }
+}
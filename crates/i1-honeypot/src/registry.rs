@@ -0,0 +1,415 @@
+//! Card deployment registry.
+//!
+//! Records where each honeypot card was planted so a later sighting - a
+//! charge attempt, a paste on a carding forum - can be traced back to the
+//! site and conversation that leaked it. Numbers are stored only as an
+//! HMAC keyed by the deployment secret; the file on disk never holds a PAN.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::card::{CardNetwork, HoneypotCard};
+use crate::error::HoneypotError;
+
+/// Attack pattern exported for IPs that used a honeypot card.
+pub const CARDING_PATTERN: &str = "carding";
+
+/// Where and when a card was planted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentContext {
+    /// Honeypot site the card was shown on
+    pub site: String,
+    /// Visitor session that saw the card
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Scammer conversation the card was handed out in
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// When the card was handed out
+    pub deployed_at: DateTime<Utc>,
+}
+
+impl DeploymentContext {
+    /// A card planted on `site` just now.
+    pub fn new(site: impl Into<String>) -> Self {
+        Self {
+            site: site.into(),
+            session_id: None,
+            conversation_id: None,
+            deployed_at: Utc::now(),
+        }
+    }
+
+    /// Set the visitor session.
+    #[must_use]
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Set the scammer conversation.
+    #[must_use]
+    pub fn with_conversation(mut self, conversation_id: impl Into<String>) -> Self {
+        self.conversation_id = Some(conversation_id.into());
+        self
+    }
+}
+
+/// One sighting of a planted card.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardHit {
+    /// When the card was seen
+    pub observed_at: DateTime<Utc>,
+    /// Who reported the sighting (payment gateway, forum monitor, ...)
+    pub source: String,
+    /// Address the card was used from, when known
+    #[serde(default)]
+    pub ip: Option<IpAddr>,
+}
+
+/// A registered card and its sightings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    /// The card's tracking id
+    pub card_id: Uuid,
+    /// Card network
+    pub network: CardNetwork,
+    /// Last four digits, for humans reading the registry
+    pub last4: String,
+    /// Where the card was planted
+    pub context: DeploymentContext,
+    /// Sightings, oldest first
+    #[serde(default)]
+    pub hits: Vec<CardHit>,
+}
+
+/// Reputation for an address that used honeypot cards.
+///
+/// Field names and units match i1-srv's `ReputationData`, so a record
+/// deserializes into it directly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardingReputation {
+    /// Threat level name
+    pub threat: String,
+    /// Always [`CARDING_PATTERN`]
+    pub pattern: String,
+    /// Number of card uses seen from the address
+    pub hits: u32,
+    /// Which cards were used
+    pub reason: String,
+    /// First use (epoch seconds)
+    pub first_seen: i64,
+    /// Latest use (epoch seconds)
+    pub last_seen: i64,
+}
+
+/// On-disk layout: deployments keyed by the hex HMAC of their PAN.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    cards: BTreeMap<String, Deployment>,
+}
+
+/// JSON-backed registry of planted cards.
+///
+/// Changes stay in memory until [`save`](Self::save).
+pub struct CardRegistry {
+    key: hmac::Key,
+    path: Option<PathBuf>,
+    file: RegistryFile,
+}
+
+impl CardRegistry {
+    /// An empty in-memory registry keyed by the deployment's secret.
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+            path: None,
+            file: RegistryFile::default(),
+        }
+    }
+
+    /// Open the registry at `path`, starting empty if the file doesn't exist.
+    ///
+    /// The secret must be the one the registry was created with, or no
+    /// lookup will match.
+    pub fn open(path: impl Into<PathBuf>, secret: &[u8]) -> Result<Self, HoneypotError> {
+        let path = path.into();
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => RegistryFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            file,
+            ..Self::new(secret)
+        })
+    }
+
+    /// File the registry was opened from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the registry back to the file it was opened from.
+    pub fn save(&self) -> Result<(), HoneypotError> {
+        let path = self.path.as_deref().ok_or_else(|| {
+            HoneypotError::InvalidConfig("registry was not opened from a file".into())
+        })?;
+        self.save_to(path)
+    }
+
+    /// Write the registry to `path`, replacing it atomically.
+    pub fn save_to(&self, path: &Path) -> Result<(), HoneypotError> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&self.file)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Record that `card` was planted in `context`.
+    ///
+    /// Fails with [`HoneypotError::AlreadyRegistered`] if the number is
+    /// already in the registry, so one card can't point at two sites.
+    pub fn register(
+        &mut self,
+        card: &HoneypotCard,
+        context: DeploymentContext,
+    ) -> Result<&Deployment, HoneypotError> {
+        let digits = digits(&card.number);
+        let key = self.pan_key(&digits);
+        if let Some(existing) = self.file.cards.get(&key) {
+            return Err(HoneypotError::AlreadyRegistered(existing.card_id));
+        }
+        let deployment = Deployment {
+            card_id: card.id,
+            network: card.network,
+            last4: digits[digits.len().saturating_sub(4)..].to_string(),
+            context,
+            hits: Vec::new(),
+        };
+        Ok(self.file.cards.entry(key).or_insert(deployment))
+    }
+
+    /// The deployment a card number was planted in.
+    ///
+    /// Spaces and dashes in `pan` are ignored.
+    pub fn lookup_by_number(&self, pan: &str) -> Option<&Deployment> {
+        self.file.cards.get(&self.pan_key(&digits(pan)))
+    }
+
+    /// Add a sighting to a card's timeline.
+    ///
+    /// Returns the updated deployment, or `None` if the number isn't one
+    /// of ours.
+    pub fn record_hit(
+        &mut self,
+        pan: &str,
+        observed_at: DateTime<Utc>,
+        source: impl Into<String>,
+        ip: Option<IpAddr>,
+    ) -> Option<&Deployment> {
+        let key = self.pan_key(&digits(pan));
+        let deployment = self.file.cards.get_mut(&key)?;
+        let at = deployment
+            .hits
+            .partition_point(|hit| hit.observed_at <= observed_at);
+        deployment.hits.insert(
+            at,
+            CardHit {
+                observed_at,
+                source: source.into(),
+                ip,
+            },
+        );
+        Some(deployment)
+    }
+
+    /// Every registered deployment.
+    pub fn deployments(&self) -> impl Iterator<Item = &Deployment> {
+        self.file.cards.values()
+    }
+
+    /// Number of registered cards.
+    pub fn len(&self) -> usize {
+        self.file.cards.len()
+    }
+
+    /// Whether no card has been registered.
+    pub fn is_empty(&self) -> bool {
+        self.file.cards.is_empty()
+    }
+
+    /// Reputation for every address a card was used from.
+    ///
+    /// Hits without an IP are left out; an address that used several
+    /// cards gets one record covering all of them.
+    pub fn reputation_export(&self) -> BTreeMap<IpAddr, CardingReputation> {
+        let mut by_ip: BTreeMap<IpAddr, Vec<(&Deployment, &CardHit)>> = BTreeMap::new();
+        for deployment in self.file.cards.values() {
+            for hit in &deployment.hits {
+                if let Some(ip) = hit.ip {
+                    by_ip.entry(ip).or_default().push((deployment, hit));
+                }
+            }
+        }
+
+        by_ip
+            .into_iter()
+            .map(|(ip, hits)| {
+                let first = hits.iter().map(|(_, hit)| hit.observed_at).min();
+                let last = hits.iter().map(|(_, hit)| hit.observed_at).max();
+                let mut cards: Vec<String> = hits
+                    .iter()
+                    .map(|(deployment, _)| {
+                        format!("card {} from {}", deployment.last4, deployment.context.site)
+                    })
+                    .collect();
+                cards.sort();
+                cards.dedup();
+                let reputation = CardingReputation {
+                    threat: "high".into(),
+                    pattern: CARDING_PATTERN.into(),
+                    hits: u32::try_from(hits.len()).unwrap_or(u32::MAX),
+                    reason: format!("used honeypot {}", cards.join(", ")),
+                    first_seen: first.map_or(0, |t| t.timestamp()),
+                    last_seen: last.map_or(0, |t| t.timestamp()),
+                };
+                (ip, reputation)
+            })
+            .collect()
+    }
+
+    /// Hex HMAC of a normalized card number.
+    fn pan_key(&self, digits: &str) -> String {
+        let tag = hmac::sign(&self.key, digits.as_bytes());
+        tag.as_ref()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
+
+impl std::fmt::Debug for CardRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CardRegistry")
+            .field("path", &self.path)
+            .field("cards", &self.file.cards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The digits of a card number, dropping separators.
+fn digits(pan: &str) -> String {
+    pan.chars().filter(char::is_ascii_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const SECRET: &[u8] = b"registry test secret";
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 14, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn numbers_are_stored_hashed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cards.json");
+        let card = HoneypotCard::generate(CardNetwork::Visa);
+
+        let mut registry = CardRegistry::open(&path, SECRET).unwrap();
+        registry
+            .register(&card, DeploymentContext::new("shop.example"))
+            .unwrap();
+        registry.save().unwrap();
+
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains(&card.number));
+        assert!(!on_disk.contains(&card.display_number));
+        assert!(on_disk.contains(&card.id.to_string()));
+
+        let reopened = CardRegistry::open(&path, SECRET).unwrap();
+        let found = reopened.lookup_by_number(&card.display_number).unwrap();
+        assert_eq!(found.card_id, card.id);
+        assert_eq!(found.last4, card.number[card.number.len() - 4..]);
+
+        let other_key = CardRegistry::open(&path, b"another secret").unwrap();
+        assert!(other_key.lookup_by_number(&card.number).is_none());
+    }
+
+    #[test]
+    fn a_card_registers_once() {
+        let card = HoneypotCard::generate(CardNetwork::Mastercard);
+        let mut registry = CardRegistry::new(SECRET);
+        registry
+            .register(
+                &card,
+                DeploymentContext::new("shop.example").with_session("s1"),
+            )
+            .unwrap();
+
+        let again = registry.register(&card, DeploymentContext::new("other.example"));
+        assert!(matches!(again, Err(HoneypotError::AlreadyRegistered(id)) if id == card.id));
+        assert_eq!(registry.len(), 1);
+        let kept = registry.lookup_by_number(&card.number).unwrap();
+        assert_eq!(kept.context.site, "shop.example");
+    }
+
+    #[test]
+    fn hits_build_a_timeline_and_aggregate_by_ip() {
+        let first = HoneypotCard::generate(CardNetwork::Visa);
+        let second = HoneypotCard::generate(CardNetwork::Amex);
+        let mut registry = CardRegistry::new(SECRET);
+        registry
+            .register(&first, DeploymentContext::new("shop.example"))
+            .unwrap();
+        registry
+            .register(
+                &second,
+                DeploymentContext::new("bank.example").with_conversation("chat-7"),
+            )
+            .unwrap();
+
+        let carder: IpAddr = "203.0.113.9".parse().unwrap();
+        let other: IpAddr = "198.51.100.4".parse().unwrap();
+        registry.record_hit(&first.number, at(12), "gateway", Some(carder));
+        registry.record_hit(&first.number, at(9), "forum", None);
+        registry.record_hit(&second.number, at(15), "gateway", Some(carder));
+        registry.record_hit(&second.number, at(10), "gateway", Some(other));
+        assert!(registry
+            .record_hit("4000 0000 0000 0002", at(11), "gateway", Some(carder))
+            .is_none());
+
+        let timeline: Vec<_> = registry
+            .lookup_by_number(&first.number)
+            .unwrap()
+            .hits
+            .iter()
+            .map(|hit| (hit.observed_at, hit.source.as_str()))
+            .collect();
+        assert_eq!(timeline, [(at(9), "forum"), (at(12), "gateway")]);
+
+        let export = registry.reputation_export();
+        assert_eq!(export.len(), 2);
+        let rep = &export[&carder];
+        assert_eq!(rep.pattern, CARDING_PATTERN);
+        assert_eq!(rep.hits, 2);
+        assert_eq!(rep.first_seen, at(12).timestamp());
+        assert_eq!(rep.last_seen, at(15).timestamp());
+        assert!(rep.reason.contains("shop.example") && rep.reason.contains("bank.example"));
+        assert_eq!(export[&other].hits, 1);
+    }
+}
@@ -0,0 +1,747 @@
+//! Turning listener events into bans.
+//!
+//! [`DefenseSink`] watches the [`HoneypotEvent`]s a listener reports and
+//! bans a source once a [`DefensePolicy`] says so: on any credential it
+//! submits, or once it produces too many events within a window. Bans are
+//! written to a defense state file as detailed `blocked_ips` entries, the
+//! form i1-srv's collector reads (`ip`, `threat`, `hits`, `first_seen`,
+//! `last_seen`, `reason`, `pattern`, `ttl`), so point the sink at the
+//! server's `admin.blocks_path` or another file it loads. Addresses on the
+//! policy's whitelist, or in the file's `whitelisted_ips`, are never banned.
+//!
+//! The file may be shared with `i1 defend` and the admin API, so every
+//! writer holds an exclusive lock on `<file>.lock` from reading the file
+//! to replacing it by rename: no ban is lost to a concurrent update, and a
+//! rebuild never reads half of the file. With the `push` feature,
+//! [`AdminPush`] also hands each ban to a local i1-srv's admin API, which
+//! serves it over DNS at once instead of at the next rebuild:
+//!
+//! ```rust,ignore
+//! use i1_honeypot::sink::{AdminPush, DefensePolicy, DefenseSink};
+//!
+//! let mut sink = DefenseSink::new("/var/lib/i1/admin_blocks.json", DefensePolicy::default())?;
+//! let token = std::fs::read_to_string("/var/lib/i1/admin_token")?;
+//! let push = AdminPush::new("http://127.0.0.1:8953").with_token(token);
+//! while let Some(event) = rx.recv().await {
+//!     if let Some(ban) = sink.handle(&event)? {
+//!         push.send(&ban).await?;
+//!     }
+//! }
+//! ```
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::listener::HoneypotEvent;
+use crate::HoneypotError;
+
+/// When the sink bans a source.
+#[derive(Debug, Clone)]
+pub struct DefensePolicy {
+    /// Events from one address within `burst_window` that earn a ban; 0
+    /// turns burst bans off
+    pub burst_events: u32,
+    /// Window the burst is counted over
+    pub burst_window: Duration,
+    /// Ban any address that submits a credential
+    pub ban_credentials: bool,
+    /// How long a ban lasts from the first event counted towards it
+    pub ttl: Duration,
+    /// Addresses and CIDR ranges never banned
+    pub whitelist: Vec<String>,
+}
+
+impl Default for DefensePolicy {
+    /// A credential, or 20 events in 5 minutes, bans for a day.
+    fn default() -> Self {
+        Self {
+            burst_events: 20,
+            burst_window: Duration::from_secs(300),
+            ban_credentials: true,
+            ttl: Duration::from_secs(86_400),
+            whitelist: Vec::new(),
+        }
+    }
+}
+
+/// The policy that led to a ban.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BanTrigger {
+    /// The source submitted a credential.
+    Credential,
+    /// The source produced too many events within the window.
+    Burst,
+}
+
+/// A source the sink banned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ban {
+    /// Address banned
+    pub ip: IpAddr,
+    /// Policy that banned it
+    pub trigger: BanTrigger,
+    /// Threat level name recorded with the ban
+    pub threat: String,
+    /// What it was caught doing (e.g. "ssh", "web-scan")
+    pub pattern: String,
+    /// Why it was banned
+    pub reason: String,
+    /// Events from the address counted towards the ban
+    pub hits: u32,
+    /// First of those events
+    pub first_seen: DateTime<Utc>,
+    /// The event that led to the ban
+    pub last_seen: DateTime<Utc>,
+    /// How long the ban lasts from `first_seen`
+    pub ttl: Duration,
+}
+
+/// What a [`DefenseSink`] has seen and done so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SinkCounters {
+    /// Events handled
+    pub events: u64,
+    /// Bans for submitting a credential
+    pub credential_bans: u64,
+    /// Bans for a burst of events
+    pub burst_bans: u64,
+    /// Events from whitelisted sources, which are never banned
+    pub whitelisted: u64,
+    /// Events from sources already banned or blocked by hand
+    pub already_banned: u64,
+}
+
+/// Bans listener offenders in a defense state file.
+#[derive(Debug)]
+pub struct DefenseSink {
+    path: PathBuf,
+    policy: DefensePolicy,
+    whitelist: Vec<IpRange>,
+    dry_run: bool,
+    recent: HashMap<IpAddr, VecDeque<DateTime<Utc>>>,
+    banned: HashMap<IpAddr, DateTime<Utc>>,
+    counters: SinkCounters,
+    pruned_at: u64,
+}
+
+/// How often, in events, the sink forgets sources gone quiet.
+const PRUNE_EVERY: u64 = 1_024;
+
+impl DefenseSink {
+    /// Ban offenders in `path` according to `policy`.
+    ///
+    /// Fails if a whitelist entry isn't an address or CIDR range.
+    pub fn new(path: impl Into<PathBuf>, policy: DefensePolicy) -> Result<Self, HoneypotError> {
+        let whitelist = policy
+            .whitelist
+            .iter()
+            .map(|entry| {
+                IpRange::parse(entry).ok_or_else(|| {
+                    HoneypotError::InvalidConfig(format!(
+                        "whitelist entry '{entry}' is not an address or range"
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            path: path.into(),
+            policy,
+            whitelist,
+            dry_run: false,
+            recent: HashMap::new(),
+            banned: HashMap::new(),
+            counters: SinkCounters::default(),
+            pruned_at: 0,
+        })
+    }
+
+    /// Decide bans as usual, but leave the file alone.
+    #[must_use]
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Whether bans are only reported, not written.
+    #[must_use]
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// File the sink writes to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Counts so far.
+    #[must_use]
+    pub fn counters(&self) -> SinkCounters {
+        self.counters
+    }
+
+    /// Count `event` against its source, and ban the source if a policy
+    /// says so.
+    ///
+    /// Returns the ban, also in a dry run, where the file is left alone.
+    /// A source stays banned, and its further events ignored, until the
+    /// ban's TTL has passed in event time. Disconnects don't count towards
+    /// a burst: they close a connection already counted.
+    pub fn handle(&mut self, event: &HoneypotEvent) -> Result<Option<Ban>, HoneypotError> {
+        self.counters.events += 1;
+        let ip = event.peer().ip();
+        let at = event.at();
+        if self.counters.events - self.pruned_at >= PRUNE_EVERY {
+            self.pruned_at = self.counters.events;
+            self.prune(at);
+        }
+
+        if self.whitelist.iter().any(|range| range.contains(ip)) {
+            self.counters.whitelisted += 1;
+            return Ok(None);
+        }
+        if self.banned.get(&ip).is_some_and(|until| at < *until) {
+            self.counters.already_banned += 1;
+            return Ok(None);
+        }
+        if matches!(event, HoneypotEvent::Disconnected { .. }) {
+            return Ok(None);
+        }
+
+        let cutoff = self.window_start(at);
+        let recent = self.recent.entry(ip).or_default();
+        recent.push_back(at);
+        while recent
+            .front()
+            .zip(cutoff)
+            .is_some_and(|(first, cutoff)| *first <= cutoff)
+        {
+            recent.pop_front();
+        }
+
+        let trigger = if self.policy.ban_credentials
+            && matches!(event, HoneypotEvent::Credential(_))
+        {
+            BanTrigger::Credential
+        } else if self.policy.burst_events > 0 && recent.len() >= self.policy.burst_events as usize
+        {
+            BanTrigger::Burst
+        } else {
+            return Ok(None);
+        };
+        let hits = u32::try_from(recent.len()).unwrap_or(u32::MAX);
+        let ban = Ban {
+            ip,
+            trigger,
+            threat: match trigger {
+                BanTrigger::Credential => "high",
+                BanTrigger::Burst => "medium",
+            }
+            .into(),
+            pattern: pattern(event).into(),
+            reason: match (trigger, event) {
+                (BanTrigger::Credential, HoneypotEvent::Credential(attempt)) => {
+                    let service = attempt.service.split('/').next().unwrap_or_default();
+                    format!("{service}-honeypot login")
+                }
+                _ => format!(
+                    "{hits} honeypot events in {} min",
+                    self.policy.burst_window.as_secs().div_ceil(60)
+                ),
+            },
+            hits,
+            first_seen: recent.front().copied().unwrap_or(at),
+            last_seen: at,
+            ttl: self.policy.ttl,
+        };
+
+        self.recent.remove(&ip);
+        let until = chrono::Duration::from_std(ban.ttl)
+            .ok()
+            .and_then(|ttl| ban.first_seen.checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.banned.insert(ip, until);
+        match self.apply(&ban)? {
+            Applied::Banned => {}
+            Applied::Whitelisted => {
+                self.banned.remove(&ip);
+                self.counters.whitelisted += 1;
+                return Ok(None);
+            }
+            Applied::AlreadyBlocked => {
+                self.counters.already_banned += 1;
+                return Ok(None);
+            }
+        }
+        match trigger {
+            BanTrigger::Credential => self.counters.credential_bans += 1,
+            BanTrigger::Burst => self.counters.burst_bans += 1,
+        }
+        Ok(Some(ban))
+    }
+
+    /// Forget sources with no events in the window, and bans that have
+    /// run out, as of `now`.
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        if let Some(cutoff) = self.window_start(now) {
+            self.recent
+                .retain(|_, times| times.back().is_some_and(|last| *last > cutoff));
+        }
+        self.banned.retain(|_, until| *until > now);
+    }
+
+    /// Events at or before this fall outside a burst window ending `now`.
+    fn window_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        chrono::Duration::from_std(self.policy.burst_window)
+            .ok()
+            .and_then(|window| now.checked_sub_signed(window))
+    }
+
+    /// Write `ban` into the file, unless the file whitelists or already
+    /// blocks the address for good.
+    fn apply(&self, ban: &Ban) -> Result<Applied, HoneypotError> {
+        let _lock = if self.dry_run {
+            None
+        } else {
+            Some(lock_state(&self.path)?)
+        };
+        let mut state = read_state(&self.path)?;
+        let whitelisted = state
+            .get("whitelisted_ips")
+            .and_then(Value::as_array)
+            .is_some_and(|list| {
+                list.iter()
+                    .filter_map(Value::as_str)
+                    .filter_map(IpRange::parse)
+                    .any(|range| range.contains(ban.ip))
+            });
+        if whitelisted {
+            return Ok(Applied::Whitelisted);
+        }
+
+        let entries = blocked_ips(&mut state)?;
+        let ip = ban.ip.to_string();
+        let entry = json!({
+            "ip": ip,
+            "threat": ban.threat,
+            "hits": ban.hits,
+            "first_seen": ban.first_seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "last_seen": ban.last_seen.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "reason": ban.reason,
+            "pattern": ban.pattern,
+            "ttl": ban.ttl.as_secs(),
+        });
+        match entries
+            .iter()
+            .position(|entry| listed_ip(entry) == Some(&ip))
+        {
+            Some(pos) if entries[pos].is_string() => return Ok(Applied::AlreadyBlocked),
+            Some(pos) => entries[pos] = entry,
+            None => entries.push(entry),
+        }
+        if !self.dry_run {
+            write_state(&self.path, &state)?;
+        }
+        Ok(Applied::Banned)
+    }
+}
+
+/// What writing a ban came to.
+enum Applied {
+    Banned,
+    Whitelisted,
+    AlreadyBlocked,
+}
+
+/// Reputation pattern for what `event` shows its source doing.
+fn pattern(event: &HoneypotEvent) -> &'static str {
+    match event {
+        HoneypotEvent::Credential(attempt) if attempt.service.starts_with("http") => "web-login",
+        HoneypotEvent::Credential(attempt) if attempt.service == "ssh" => "ssh",
+        HoneypotEvent::Credential(_) => "login",
+        HoneypotEvent::Request(_) | HoneypotEvent::Canary(_) => "web-scan",
+        HoneypotEvent::Connected { .. } | HoneypotEvent::Disconnected { .. } => "scan",
+    }
+}
+
+/// Lock a defense state file against other writers until the returned
+/// file is dropped.
+pub(crate) fn lock_state(path: &Path) -> Result<File, HoneypotError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+    fs4::fs_std::FileExt::lock_exclusive(&file)?;
+    Ok(file)
+}
+
+/// Read a defense state file; a missing one reads as an empty schema 2
+/// file.
+pub(crate) fn read_state(path: &Path) -> Result<Value, HoneypotError> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(json!({ "schema_version": 2 })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The state's `blocked_ips` list, created if missing.
+pub(crate) fn blocked_ips(state: &mut Value) -> Result<&mut Vec<Value>, HoneypotError> {
+    state
+        .as_object_mut()
+        .ok_or_else(|| HoneypotError::InvalidConfig("state file is not an object".into()))?
+        .entry("blocked_ips")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .ok_or_else(|| HoneypotError::InvalidConfig("blocked_ips is not a list".into()))
+}
+
+/// Replace a defense state file, by rename so readers never see half of it.
+///
+/// Hold [`lock_state`] from reading the file until this returns.
+pub(crate) fn write_state(path: &Path, state: &Value) -> Result<(), HoneypotError> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(state)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Address of a `blocked_ips` entry in either form.
+pub(crate) fn listed_ip(entry: &Value) -> Option<&str> {
+    match entry {
+        Value::String(ip) => Some(ip),
+        Value::Object(entry) => entry.get("ip").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+/// An address, or a CIDR range of them.
+#[derive(Debug, Clone, Copy)]
+struct IpRange {
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl IpRange {
+    fn parse(entry: &str) -> Option<Self> {
+        let (addr, prefix) = entry
+            .split_once('/')
+            .map_or((entry, None), |(a, p)| (a, Some(p)));
+        let network: IpAddr = addr.trim().parse().ok()?;
+        let bits = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)?,
+            None => bits,
+        };
+        Some(Self { network, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Hands bans to a local i1-srv's admin API.
+///
+/// Each ban goes to `PUT /v1/reputation/{ip}` as the address's reputation,
+/// which the server stores with its blocks and serves from the next
+/// serial. Don't send bans from a dry run.
+#[cfg(feature = "push")]
+#[derive(Debug, Clone)]
+pub struct AdminPush {
+    client: reqwest::Client,
+    base: String,
+    token: Option<String>,
+}
+
+#[cfg(feature = "push")]
+impl AdminPush {
+    /// Push to the admin API at `base`, e.g. `http://127.0.0.1:8953`.
+    pub fn new(base: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base: base.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Present `token`, the contents of the server's `admin.token_path`,
+    /// as a loopback API requires.
+    #[must_use]
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into().trim().to_string());
+        self
+    }
+
+    /// Send `ban` to the server.
+    pub async fn send(&self, ban: &Ban) -> Result<(), HoneypotError> {
+        #[derive(Serialize)]
+        struct Reputation<'a> {
+            threat: &'a str,
+            pattern: &'a str,
+            hits: u32,
+            reason: &'a str,
+            first_seen: i64,
+            last_seen: i64,
+        }
+
+        let url = format!("{}/v1/reputation/{}", self.base, ban.ip);
+        let mut request = self.client.put(url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .query(&[("ttl", ban.ttl.as_secs())])
+            .json(&Reputation {
+                threat: &ban.threat,
+                pattern: &ban.pattern,
+                hits: ban.hits,
+                reason: &ban.reason,
+                first_seen: ban.first_seen.timestamp(),
+                last_seen: ban.last_seen.timestamp(),
+            })
+            .send()
+            .await
+            .map_err(|e| HoneypotError::AdminApi(e.to_string()))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(HoneypotError::AdminApi(format!("{status}: {body}")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::listener::{CredentialAttempt, HttpRequest};
+
+    fn request(peer: &str, at: DateTime<Utc>) -> HoneypotEvent {
+        HoneypotEvent::Request(HttpRequest {
+            peer: peer.parse().unwrap(),
+            method: "GET".into(),
+            path: "/wp-login.php".into(),
+            user_agent: Some("zgrab/0.x".into()),
+            headers: Vec::new(),
+            at,
+        })
+    }
+
+    fn credential(peer: &str, at: DateTime<Utc>) -> HoneypotEvent {
+        HoneypotEvent::Credential(CredentialAttempt {
+            service: "ssh".into(),
+            peer: peer.parse().unwrap(),
+            client_banner: "SSH-2.0-Go".into(),
+            username: "root".into(),
+            password: "admin".into(),
+            at,
+            elapsed_ms: 80,
+        })
+    }
+
+    fn policy() -> DefensePolicy {
+        DefensePolicy {
+            burst_events: 5,
+            burst_window: Duration::from_secs(60),
+            whitelist: vec!["198.51.100.0/24".into()],
+            ..DefensePolicy::default()
+        }
+    }
+
+    fn state(path: &Path) -> Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn burst_bans_once_with_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.json");
+        std::fs::write(
+            &path,
+            r#"{"blocked_ips": ["192.0.2.1"], "blocked_countries": ["cn"]}"#,
+        )
+        .unwrap();
+        let mut sink = DefenseSink::new(&path, policy()).unwrap();
+
+        let start: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+        let mut bans = Vec::new();
+        for i in 0..30 {
+            let at = start + chrono::Duration::seconds(i * 2);
+            bans.extend(sink.handle(&request("203.0.113.9:40000", at)).unwrap());
+            // A slow scanner stays under the limit; a whitelisted one is
+            // never counted.
+            if i % 10 == 0 {
+                assert!(sink
+                    .handle(&request("203.0.113.50:40000", at))
+                    .unwrap()
+                    .is_none());
+            }
+            assert!(sink
+                .handle(&request("198.51.100.7:40000", at))
+                .unwrap()
+                .is_none());
+        }
+
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0].trigger, BanTrigger::Burst);
+        assert_eq!(bans[0].hits, 5);
+        let state = state(&path);
+        assert_eq!(state["blocked_countries"], json!(["cn"]));
+        assert_eq!(
+            state["blocked_ips"],
+            json!([
+                "192.0.2.1",
+                {
+                    "ip": "203.0.113.9",
+                    "threat": "medium",
+                    "hits": 5,
+                    "first_seen": "2026-03-01T10:00:00Z",
+                    "last_seen": "2026-03-01T10:00:08Z",
+                    "reason": "5 honeypot events in 1 min",
+                    "pattern": "web-scan",
+                    "ttl": 86400
+                }
+            ])
+        );
+        assert_eq!(
+            sink.counters(),
+            SinkCounters {
+                events: 63,
+                credential_bans: 0,
+                burst_bans: 1,
+                whitelisted: 30,
+                already_banned: 25,
+            }
+        );
+
+        // Once the ban has run out, the source can earn another.
+        let later = start + chrono::Duration::days(1);
+        assert!(sink
+            .handle(&credential("203.0.113.9:40001", later))
+            .unwrap()
+            .is_some());
+        assert_eq!(sink.counters().credential_bans, 1);
+    }
+
+    #[test]
+    fn credentials_ban_unless_whitelisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.json");
+        std::fs::write(
+            &path,
+            r#"{"blocked_ips": ["192.0.2.1"], "whitelisted_ips": ["2001:db8::/32"]}"#,
+        )
+        .unwrap();
+        let mut sink = DefenseSink::new(&path, policy()).unwrap();
+        let at: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+
+        let ban = sink
+            .handle(&credential("203.0.113.9:50122", at))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ban.trigger, BanTrigger::Credential);
+        assert_eq!(ban.pattern, "ssh");
+        assert_eq!(ban.reason, "ssh-honeypot login");
+        assert!(sink
+            .handle(&credential("[2001:db8::5]:50122", at))
+            .unwrap()
+            .is_none());
+        // Blocked by hand already.
+        assert!(sink
+            .handle(&credential("192.0.2.1:50122", at))
+            .unwrap()
+            .is_none());
+
+        let blocked = state(&path)["blocked_ips"].as_array().unwrap().len();
+        assert_eq!(blocked, 2);
+        let counters = sink.counters();
+        assert_eq!(
+            (
+                counters.credential_bans,
+                counters.whitelisted,
+                counters.already_banned
+            ),
+            (1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn concurrent_sinks_keep_every_ban() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.json");
+        let at: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+
+        std::thread::scope(|scope| {
+            for net in 0..4 {
+                let path = &path;
+                scope.spawn(move || {
+                    let mut sink = DefenseSink::new(path, policy()).unwrap();
+                    for host in 1..=25 {
+                        let peer = format!("203.0.{net}.{host}:50122");
+                        assert!(sink.handle(&credential(&peer, at)).unwrap().is_some());
+                    }
+                });
+            }
+        });
+
+        let blocked = state(&path)["blocked_ips"].as_array().unwrap().len();
+        assert_eq!(blocked, 100);
+    }
+
+    #[test]
+    fn dry_run_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.json");
+        let mut sink = DefenseSink::new(&path, policy())
+            .unwrap()
+            .with_dry_run(true);
+        let at: DateTime<Utc> = "2026-03-01T10:00:00Z".parse().unwrap();
+
+        assert!(sink
+            .handle(&credential("203.0.113.9:50122", at))
+            .unwrap()
+            .is_some());
+        assert!(!path.exists());
+        assert_eq!(sink.counters().credential_bans, 1);
+
+        assert!(DefenseSink::new(
+            &path,
+            DefensePolicy {
+                whitelist: vec!["not-an-ip".into()],
+                ..DefensePolicy::default()
+            }
+        )
+        .is_err());
+    }
+}
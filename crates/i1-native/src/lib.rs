@@ -103,10 +103,7 @@ impl NativeProvider {
             request = request.bearer_auth(token);
         }
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))?;
+        let response = request.send().await.map_err(I1Error::from)?;
 
         let status = response.status();
         if !status.is_success() {
@@ -123,10 +120,7 @@ impl NativeProvider {
             };
         }
 
-        response
-            .json()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))
+        response.json().await.map_err(I1Error::from)
     }
 
     /// Perform a direct WHOIS lookup (local, no API)
@@ -289,6 +283,7 @@ impl HostLookup for NativeProvider {
                     },
                     data: vec![],
                     last_update: None,
+                    last_update_raw: None,
                 })
             }
             Err(e) => Err(e),
@@ -352,6 +347,7 @@ impl DnsProvider for NativeProvider {
                             name: domain.to_string(),
                             value: ip.to_string(),
                             ttl: None,
+                            last_seen: None,
                         })
                         .collect(),
                     registrar: None,
@@ -5,11 +5,15 @@
 //! This crate defines the core traits that all providers (Shodan, Censys,
 //! Criminal IP, i1 Native, etc.) must implement.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::net::IpAddr;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use i1_core::{HostInfo, Result};
+use futures_util::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use i1_core::{HostInfo, I1Error, MinHostInfo, Result};
 use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 
 pub mod auth;
 pub mod types;
@@ -61,10 +65,41 @@ pub trait SearchProvider: Provider {
     /// Count results without fetching (saves API credits)
     async fn count(&self, query: &str) -> Result<u64>;
 
+    /// Search for hosts, keeping only the summary of each (no banners)
+    ///
+    /// Providers with a compact response format should override this; the
+    /// default runs a full [`search`](Self::search) and drops the banners.
+    async fn search_min(&self, query: &str, page: Option<u32>) -> Result<MinSearchResults> {
+        self.search(query, page).await.map(MinSearchResults::from)
+    }
+
     /// Get available search filters/facets
     async fn filters(&self) -> Result<Vec<String>> {
         Ok(vec![])
     }
+
+    /// Every host matching a query, fetching pages as the stream is polled
+    ///
+    /// The default requests pages 1, 2, ... until one comes back empty or
+    /// `total` hosts have been seen; cursor-paginated providers override
+    /// it. The stream ends after the first error.
+    fn search_all<'a>(&'a self, query: &'a str) -> BoxStream<'a, Result<HostInfo>> {
+        stream::try_unfold(Some((1, 0)), move |next| async move {
+            let Some((page, seen)) = next else {
+                return Ok::<_, I1Error>(None);
+            };
+            let results = self.search(query, Some(page)).await?;
+            if results.results.is_empty() {
+                return Ok(None);
+            }
+            let seen = seen + results.results.len() as u64;
+            let next = (seen < results.total).then_some((page + 1, seen));
+            Ok(Some((results.results, next)))
+        })
+        .map_ok(|hosts| stream::iter(hosts.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
 }
 
 /// DNS lookup capability
@@ -76,8 +111,30 @@ pub trait DnsProvider: Provider {
     /// Reverse DNS lookup
     async fn reverse(&self, ip: &str) -> Result<Vec<String>>;
 
+    /// Reverse DNS lookup for several IPs, keyed by IP. IPs without a PTR
+    /// record map to an empty list.
+    ///
+    /// The default looks each IP up in turn; providers with a batch
+    /// endpoint send them together. Callers chunk large lists.
+    async fn reverse_many(&self, ips: &[String]) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut hostnames = BTreeMap::new();
+        for ip in ips {
+            hostnames.insert(ip.clone(), self.reverse(ip).await?);
+        }
+        Ok(hostnames)
+    }
+
     /// Get domain information (subdomains, records, etc.)
     async fn domain_info(&self, domain: &str) -> Result<DomainInfo>;
+
+    /// Get domain information including historical records, each with
+    /// the time it was last seen.
+    async fn domain_history(&self, domain: &str) -> Result<DomainInfo> {
+        Err(I1Error::Dns(format!(
+            "{} does not provide DNS history for {domain}",
+            self.name()
+        )))
+    }
 }
 
 /// WHOIS lookup capability
@@ -107,6 +164,40 @@ pub struct ProviderHealth {
     pub message: Option<String>,
 }
 
+impl ProviderHealth {
+    /// An unhealthy status for `provider`, explained by `message`
+    pub fn unhealthy(provider: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            status: HealthStatus::Unhealthy,
+            latency_ms: None,
+            credits_remaining: None,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Health check bounded by `timeout`, for aggregators polling several
+/// providers
+///
+/// A check that fails or doesn't answer in time is reported as
+/// [`HealthStatus::Unhealthy`] (with a "timeout" message in the latter
+/// case) instead of an error, so one slow backend can't hold up the rest.
+pub async fn check_health<P: Provider + ?Sized>(provider: &P, timeout: Duration) -> ProviderHealth {
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, provider.health_check()).await {
+        Ok(Ok(health)) => health,
+        Ok(Err(e)) => ProviderHealth::unhealthy(provider.name(), e.to_string()),
+        Err(_) => ProviderHealth {
+            latency_ms: Some(u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)),
+            ..ProviderHealth::unhealthy(
+                provider.name(),
+                format!("timeout: no answer within {}s", timeout.as_secs_f64()),
+            )
+        },
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HealthStatus {
@@ -127,6 +218,41 @@ pub struct SearchResults {
     pub facets: Option<serde_json::Value>,
 }
 
+/// Search results with [`MinHostInfo`] summaries instead of full hosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinSearchResults {
+    pub provider: String,
+    pub total: u64,
+    pub page: u32,
+    pub results: Vec<MinHostInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<serde_json::Value>,
+}
+
+impl From<SearchResults> for MinSearchResults {
+    fn from(results: SearchResults) -> Self {
+        Self {
+            provider: results.provider,
+            total: results.total,
+            page: results.page,
+            results: results.results.into_iter().map(MinHostInfo::from).collect(),
+            facets: results.facets,
+        }
+    }
+}
+
+impl From<MinSearchResults> for SearchResults {
+    fn from(results: MinSearchResults) -> Self {
+        Self {
+            provider: results.provider,
+            total: results.total,
+            page: results.page,
+            results: results.results.into_iter().map(HostInfo::from).collect(),
+            facets: results.facets,
+        }
+    }
+}
+
 /// Domain information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainInfo {
@@ -141,6 +267,63 @@ pub struct DomainInfo {
     pub expires: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+impl DomainInfo {
+    /// Split historical records into one snapshot per day they were last
+    /// seen, newest first. Records without a `last_seen` are left out.
+    ///
+    /// Each snapshot's subdomains are the record names seen that day.
+    #[must_use]
+    pub fn snapshots(&self) -> Vec<DomainSnapshot> {
+        let mut days: BTreeMap<&str, Vec<DnsRecord>> = BTreeMap::new();
+        for record in &self.records {
+            if let Some(day) = record.last_seen.as_deref().and_then(|seen| seen.get(..10)) {
+                days.entry(day).or_default().push(record.clone());
+            }
+        }
+        days.into_iter()
+            .rev()
+            .map(|(day, records)| {
+                let subdomains: BTreeSet<String> = records
+                    .iter()
+                    .filter(|r| !r.name.is_empty() && r.name != self.domain)
+                    .map(|r| r.name.clone())
+                    .collect();
+                DomainSnapshot {
+                    date: day.to_string(),
+                    info: Self {
+                        domain: self.domain.clone(),
+                        subdomains: subdomains.into_iter().collect(),
+                        records,
+                        registrar: None,
+                        created: None,
+                        expires: None,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Group records by type, keeping only `only` when given.
+    ///
+    /// Filtering happens here rather than in the provider request, so it
+    /// behaves the same for every provider.
+    #[must_use]
+    pub fn typed(self, only: Option<DnsRecordType>) -> TypedDomainInfo {
+        let mut records = TypedRecords::default();
+        for record in self.records {
+            let kind = record.kind();
+            if only.is_none() || kind == only {
+                records.push(kind, record);
+            }
+        }
+        TypedDomainInfo {
+            domain: self.domain,
+            subdomains: self.subdomains,
+            records,
+        }
+    }
+}
+
 /// DNS record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsRecord {
@@ -149,6 +332,258 @@ pub struct DnsRecord {
     pub value: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<u32>,
+    /// When the record was last observed (ISO 8601), for historical records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+}
+
+impl DnsRecord {
+    /// Parsed record type, or `None` for types outside [`DnsRecordType`].
+    #[must_use]
+    pub fn kind(&self) -> Option<DnsRecordType> {
+        self.record_type.parse().ok()
+    }
+}
+
+/// Record types domain lookups are grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Mx,
+    Ns,
+    Txt,
+    Soa,
+    Cname,
+}
+
+impl DnsRecordType {
+    /// Every type, in display order.
+    pub const ALL: [Self; 7] = [
+        Self::A,
+        Self::Aaaa,
+        Self::Mx,
+        Self::Ns,
+        Self::Txt,
+        Self::Soa,
+        Self::Cname,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Mx => "MX",
+            Self::Ns => "NS",
+            Self::Txt => "TXT",
+            Self::Soa => "SOA",
+            Self::Cname => "CNAME",
+        }
+    }
+}
+
+impl std::fmt::Display for DnsRecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DnsRecordType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown record type '{s}' (expected A, AAAA, MX, NS, TXT, SOA or CNAME)")
+            })
+    }
+}
+
+/// Domain records split by type.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TypedRecords {
+    #[serde(rename = "A", default, skip_serializing_if = "Vec::is_empty")]
+    pub a: Vec<DnsRecord>,
+    #[serde(rename = "AAAA", default, skip_serializing_if = "Vec::is_empty")]
+    pub aaaa: Vec<DnsRecord>,
+    #[serde(rename = "MX", default, skip_serializing_if = "Vec::is_empty")]
+    pub mx: Vec<DnsRecord>,
+    #[serde(rename = "NS", default, skip_serializing_if = "Vec::is_empty")]
+    pub ns: Vec<DnsRecord>,
+    #[serde(rename = "TXT", default, skip_serializing_if = "Vec::is_empty")]
+    pub txt: Vec<DnsRecord>,
+    #[serde(rename = "SOA", default, skip_serializing_if = "Vec::is_empty")]
+    pub soa: Vec<DnsRecord>,
+    #[serde(rename = "CNAME", default, skip_serializing_if = "Vec::is_empty")]
+    pub cname: Vec<DnsRecord>,
+    /// Records of any other type (SRV, CAA, ...).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other: Vec<DnsRecord>,
+}
+
+impl TypedRecords {
+    /// Records of one type.
+    #[must_use]
+    pub fn get(&self, kind: DnsRecordType) -> &[DnsRecord] {
+        match kind {
+            DnsRecordType::A => &self.a,
+            DnsRecordType::Aaaa => &self.aaaa,
+            DnsRecordType::Mx => &self.mx,
+            DnsRecordType::Ns => &self.ns,
+            DnsRecordType::Txt => &self.txt,
+            DnsRecordType::Soa => &self.soa,
+            DnsRecordType::Cname => &self.cname,
+        }
+    }
+
+    /// Total record count, `other` included.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        DnsRecordType::ALL
+            .iter()
+            .map(|kind| self.get(*kind).len())
+            .sum::<usize>()
+            + self.other.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every record, grouped in display order, `other` last.
+    pub fn iter(&self) -> impl Iterator<Item = &DnsRecord> {
+        DnsRecordType::ALL
+            .into_iter()
+            .flat_map(|kind| self.get(kind))
+            .chain(&self.other)
+    }
+
+    fn push(&mut self, kind: Option<DnsRecordType>, record: DnsRecord) {
+        let group = match kind {
+            Some(DnsRecordType::A) => &mut self.a,
+            Some(DnsRecordType::Aaaa) => &mut self.aaaa,
+            Some(DnsRecordType::Mx) => &mut self.mx,
+            Some(DnsRecordType::Ns) => &mut self.ns,
+            Some(DnsRecordType::Txt) => &mut self.txt,
+            Some(DnsRecordType::Soa) => &mut self.soa,
+            Some(DnsRecordType::Cname) => &mut self.cname,
+            None => &mut self.other,
+        };
+        group.push(record);
+    }
+}
+
+/// Domain information with records grouped by type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedDomainInfo {
+    pub domain: String,
+    pub subdomains: Vec<String>,
+    pub records: TypedRecords,
+}
+
+/// Records of a domain last seen on one day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainSnapshot {
+    /// Day the records were last seen (`YYYY-MM-DD`).
+    pub date: String,
+    pub info: DomainInfo,
+}
+
+/// Differences between two snapshots of a domain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DomainDiff {
+    pub domain: String,
+    /// Date of the older snapshot.
+    pub from: String,
+    /// Date of the newer snapshot.
+    pub to: String,
+    pub added_subdomains: Vec<String>,
+    pub removed_subdomains: Vec<String>,
+    /// Names whose values for a record type changed.
+    pub changed: Vec<RecordChange>,
+}
+
+/// Value changes for one record type at one name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordChange {
+    pub record_type: String,
+    pub name: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl DomainDiff {
+    /// Compare the two most recent snapshots in `history`, keeping only
+    /// records of type `only` when given. `None` when there are fewer than
+    /// two snapshots.
+    #[must_use]
+    pub fn latest(history: &DomainInfo, only: Option<DnsRecordType>) -> Option<Self> {
+        let mut snapshots = history.snapshots().into_iter();
+        let newer = snapshots.next()?;
+        let older = snapshots.next()?;
+        Some(Self {
+            from: older.date,
+            to: newer.date,
+            ..Self::between(&older.info.typed(only), &newer.info.typed(only))
+        })
+    }
+
+    /// Compare two typed views of the same domain.
+    #[must_use]
+    pub fn between(older: &TypedDomainInfo, newer: &TypedDomainInfo) -> Self {
+        let before: BTreeSet<&String> = older.subdomains.iter().collect();
+        let after: BTreeSet<&String> = newer.subdomains.iter().collect();
+
+        let old_values = record_values(&older.records);
+        let new_values = record_values(&newer.records);
+        let keys: BTreeSet<_> = old_values.keys().chain(new_values.keys()).collect();
+        let empty = BTreeSet::new();
+        let changed = keys
+            .into_iter()
+            .filter_map(|key| {
+                let old = old_values.get(key).unwrap_or(&empty);
+                let new = new_values.get(key).unwrap_or(&empty);
+                (old != new).then(|| RecordChange {
+                    record_type: key.0.clone(),
+                    name: key.1.clone(),
+                    added: new.difference(old).map(|v| (*v).clone()).collect(),
+                    removed: old.difference(new).map(|v| (*v).clone()).collect(),
+                })
+            })
+            .collect();
+
+        Self {
+            domain: newer.domain.clone(),
+            added_subdomains: after.difference(&before).map(|s| (*s).clone()).collect(),
+            removed_subdomains: before.difference(&after).map(|s| (*s).clone()).collect(),
+            changed,
+            ..Self::default()
+        }
+    }
+
+    /// True when the snapshots match.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added_subdomains.is_empty()
+            && self.removed_subdomains.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// Values per (record type, name).
+fn record_values(records: &TypedRecords) -> BTreeMap<(String, String), BTreeSet<&String>> {
+    let mut values: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for record in records.iter() {
+        values
+            .entry((record.record_type.to_ascii_uppercase(), record.name.clone()))
+            .or_default()
+            .insert(&record.value);
+    }
+    values
 }
 
 /// WHOIS information
@@ -180,3 +615,56 @@ pub struct VulnInfo {
     pub references: Option<Vec<String>>,
     pub verified: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A provider whose health check never answers, or fails at once
+    struct Stuck {
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Provider for Stuck {
+        fn name(&self) -> &'static str {
+            "stuck"
+        }
+
+        fn display_name(&self) -> &'static str {
+            "Stuck"
+        }
+
+        fn base_url(&self) -> &'static str {
+            "http://127.0.0.1:9"
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+
+        async fn health_check(&self) -> Result<ProviderHealth> {
+            if self.fail {
+                return Err(I1Error::Unauthorized);
+            }
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_checks_time_out_as_unhealthy() {
+        let health = check_health(&Stuck { fail: false }, Duration::from_secs(5)).await;
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+        assert_eq!(health.provider, "stuck");
+        assert!(health.message.unwrap().starts_with("timeout"));
+        assert_eq!(health.latency_ms, Some(5000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_checks_are_unhealthy() {
+        let health = check_health(&Stuck { fail: true }, Duration::from_secs(5)).await;
+        assert_eq!(health.status, HealthStatus::Unhealthy);
+        assert_eq!(health.message, Some(I1Error::Unauthorized.to_string()));
+        assert_eq!(health.latency_ms, None);
+    }
+}
@@ -0,0 +1,115 @@
+//! Run 10,000 IPs through an enrichment pipeline with mock stages.
+//!
+//! Run with: `cargo run -p i1-recon --example enrichment_pipeline`
+//!
+//! The stages stand in for a provider lookup and a port scan, and the sink
+//! is deliberately slower than the stages, so the run is bound by the sink.
+//! The source is only read as records leave, and the number of records in
+//! flight stays flat however many IPs there are. No network access is
+//! needed.
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use i1_core::HostInfo;
+use i1_recon::enrichment::{Enricher, Pipeline, Sink, Source, StageOptions};
+use i1_recon::{ReconError, ReconResult};
+
+const TARGETS: u32 = 10_000;
+
+/// Pretends to be a provider lookup: slow, and fails now and then.
+struct MockLookup;
+
+#[async_trait]
+impl Enricher for MockLookup {
+    fn name(&self) -> &'static str {
+        "lookup"
+    }
+
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        if host.ip_str.ends_with(".13") {
+            return Err(ReconError::Timeout);
+        }
+        host.org = Some("Example Networks".into());
+        host.ports.extend([22, 443]);
+        Ok(())
+    }
+}
+
+/// Pretends to be a port scan.
+struct MockScan;
+
+#[async_trait]
+impl Enricher for MockScan {
+    fn name(&self) -> &'static str {
+        "scan"
+    }
+
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        host.ports.push(8080);
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> ReconResult<()> {
+    let sourced = Arc::new(AtomicU64::new(0));
+    let drained = Arc::new(AtomicU64::new(0));
+    let peak = Arc::new(AtomicU64::new(0));
+
+    let source = {
+        let sourced = Arc::clone(&sourced);
+        let ips = (0..TARGETS).map(|n| IpAddr::V4(Ipv4Addr::from(0xc633_6400 + n)));
+        Source::iter(ips.inspect(move |_| {
+            sourced.fetch_add(1, Ordering::Relaxed);
+        }))
+    };
+    let sink = {
+        let (sourced, drained, peak) = (
+            Arc::clone(&sourced),
+            Arc::clone(&drained),
+            Arc::clone(&peak),
+        );
+        Sink::callback(move |_record| {
+            // A sink slower than the stages: 50µs per record
+            std::thread::sleep(Duration::from_micros(50));
+            let done = drained.fetch_add(1, Ordering::Relaxed) + 1;
+            let in_flight = sourced.load(Ordering::Relaxed) - done;
+            peak.fetch_max(in_flight, Ordering::Relaxed);
+        })
+    };
+
+    let summary = Pipeline::new(source)
+        .capacity(32)
+        .stage(
+            MockLookup,
+            StageOptions::new()
+                .concurrency(64)
+                .credits(1)
+                .max_credits(9_000),
+        )
+        .stage(MockScan, StageOptions::new().concurrency(32))
+        .run(sink)
+        .await?;
+
+    println!(
+        "{} targets read, {} records written in {:.1?}",
+        summary.read, summary.written, summary.duration
+    );
+    println!(
+        "at most {} records in flight at once",
+        peak.load(Ordering::Relaxed)
+    );
+    for stage in &summary.stages {
+        println!(
+            "  {:<8} {:>6} ok {:>5} failed {:>5} skipped {:>6} credits  {:.1?} busy",
+            stage.name, stage.succeeded, stage.failed, stage.skipped, stage.credits, stage.busy
+        );
+    }
+    Ok(())
+}
@@ -1,7 +1,201 @@
 //! Host enrichment by combining data from multiple sources.
+//!
+//! Each source implements [`Enricher`]; an [`EnrichmentChain`] runs several
+//! of them over a host in order, and a [`Pipeline`] runs them over a stream
+//! of IPs with bounded concurrency. With the `scanner` feature,
+//! [`surface_drift`] compares a local scan with a provider's view of the
+//! same host.
 
+use async_trait::async_trait;
 use i1_core::HostInfo;
+use i1_providers::HostLookup;
 use std::net::IpAddr;
+use tracing::warn;
+
+use crate::error::{ReconError, ReconResult};
+
+pub mod pipeline;
+
+pub use pipeline::{Pipeline, PipelineSummary, Record, Sink, Source, StageOptions};
+
+/// Open ports and services from a local port scan
+#[cfg(feature = "scanner")]
+pub use crate::scanner::ScanEnricher;
+
+/// Offline geo/ASN enrichment from local `.mmdb` databases
+#[cfg(feature = "geoip")]
+pub use crate::geoip::{GeoInfo, GeoIpEnricher};
+
+/// Organization, ASN, and country from IP WHOIS
+#[cfg(feature = "whois")]
+pub use crate::whois::WhoisEnricher;
+
+/// A source that adds data to a host in place.
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    /// Short name used in logs and [`ChainReport`]s
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Add what this source knows about `host`.
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()>;
+}
+
+/// Runs enrichers over a host, one after another.
+///
+/// Enrichers run in the order they were added, and each sees the changes
+/// made by the ones before it. A failing enricher does not stop the chain:
+/// the error is logged and recorded in the [`ChainReport`], anything it
+/// changed before failing is kept, and the next enricher runs.
+#[derive(Default)]
+pub struct EnrichmentChain {
+    enrichers: Vec<Box<dyn Enricher>>,
+}
+
+impl EnrichmentChain {
+    /// Create an empty chain
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an enricher
+    #[must_use]
+    pub fn with(mut self, enricher: impl Enricher + 'static) -> Self {
+        self.push(Box::new(enricher));
+        self
+    }
+
+    /// Append a boxed enricher
+    pub fn push(&mut self, enricher: Box<dyn Enricher>) {
+        self.enrichers.push(enricher);
+    }
+
+    /// Number of enrichers in the chain
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.enrichers.len()
+    }
+
+    /// Returns true if the chain has no enrichers
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.enrichers.is_empty()
+    }
+
+    /// Run every enricher over `host`
+    pub async fn enrich(&self, host: &mut HostInfo) -> ChainReport {
+        let mut report = ChainReport::default();
+        for enricher in &self.enrichers {
+            let name = enricher.name().to_string();
+            match enricher.enrich(host).await {
+                Ok(()) => report.succeeded.push(name),
+                Err(e) => {
+                    warn!(enricher = %name, ip = %host.ip_str, error = %e, "enrichment failed");
+                    report.failed.push((name, e));
+                }
+            }
+        }
+        report
+    }
+
+    /// Run the chain over several hosts concurrently
+    pub async fn enrich_all(&self, hosts: &mut [HostInfo]) -> Vec<ChainReport> {
+        let futures: Vec<_> = hosts.iter_mut().map(|host| self.enrich(host)).collect();
+        futures_util::future::join_all(futures).await
+    }
+}
+
+impl std::fmt::Debug for EnrichmentChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.enrichers.iter().map(|e| e.name()))
+            .finish()
+    }
+}
+
+/// What happened when a chain ran over one host
+#[derive(Debug, Default)]
+pub struct ChainReport {
+    /// Enrichers that completed, in order
+    pub succeeded: Vec<String>,
+    /// Enrichers that failed, with their errors, in order
+    pub failed: Vec<(String, ReconError)>,
+}
+
+impl ChainReport {
+    /// Returns true if no enricher failed
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Provider data for a host, from any [`HostLookup`] provider
+///
+/// Fields the host already has are kept; ports, hostnames, tags and
+/// vulnerabilities are merged and banners appended. Named after the
+/// provider, so several lookups in one chain stay apart in reports.
+pub struct LookupEnricher<P> {
+    provider: P,
+}
+
+impl<P: HostLookup> LookupEnricher<P> {
+    /// Look hosts up with `provider`
+    pub const fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: HostLookup> Enricher for LookupEnricher<P> {
+    fn name(&self) -> &str {
+        self.provider.name()
+    }
+
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        let found = self.provider.lookup_host(&host.ip_str).await?;
+        merge(host, found);
+        Ok(())
+    }
+}
+
+/// Merge `found` into `host`, keeping what `host` already has.
+fn merge(host: &mut HostInfo, found: HostInfo) {
+    fn union<T: PartialEq>(into: &mut Vec<T>, from: Vec<T>) {
+        for item in from {
+            if !into.contains(&item) {
+                into.push(item);
+            }
+        }
+    }
+
+    fill(&mut host.org, found.org);
+    fill(&mut host.asn, found.asn);
+    fill(&mut host.isp, found.isp);
+    fill(&mut host.os, found.os);
+    fill(&mut host.location.country_code, found.location.country_code);
+    fill(&mut host.location.country_name, found.location.country_name);
+    fill(&mut host.location.city, found.location.city);
+    fill(&mut host.last_update, found.last_update);
+    union(&mut host.hostnames, found.hostnames);
+    union(&mut host.domains, found.domains);
+    union(&mut host.ports, found.ports);
+    union(&mut host.vulns, found.vulns);
+    union(&mut host.tags, found.tags);
+    host.data.extend(found.data);
+}
+
+/// Set `slot` to `value` if it is empty; returns whether it changed.
+pub(crate) fn fill<T>(slot: &mut Option<T>, value: Option<T>) -> bool {
+    if slot.is_none() && value.is_some() {
+        *slot = value;
+        true
+    } else {
+        false
+    }
+}
 
 /// Combined intelligence from all configured sources
 #[derive(Debug, Clone, Default)]
@@ -52,8 +246,15 @@ impl HostEnricher {
     }
 
     /// Enrich a single IP address with all configured local sources
-    pub async fn enrich(&self, _ip: IpAddr) -> EnrichedHost {
-        let result = EnrichedHost::default();
+    ///
+    /// Without the `scanner` and `whois` features there is nothing to await,
+    /// but the signature stays async so callers don't depend on features.
+    #[cfg_attr(
+        not(any(feature = "scanner", feature = "whois")),
+        allow(unused_variables, unused_mut, clippy::unused_async)
+    )]
+    pub async fn enrich(&self, ip: IpAddr) -> EnrichedHost {
+        let mut result = EnrichedHost::default();
 
         // Run port scan if configured
         #[cfg(feature = "scanner")]
@@ -80,3 +281,114 @@ impl HostEnricher {
         futures_util::future::join_all(futures).await
     }
 }
+
+/// An open port seen by only one side of a [`SurfaceDrift`]
+#[cfg(feature = "scanner")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftedPort {
+    /// Port number
+    pub port: u16,
+    /// Product reported for the port, if any
+    pub product: Option<String>,
+}
+
+/// Difference between a local scan and a provider's record of a host
+#[cfg(feature = "scanner")]
+#[derive(Debug, Clone)]
+pub struct SurfaceDrift {
+    /// Host compared
+    pub target: IpAddr,
+    /// When the provider last crawled the host
+    pub indexed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Open locally but unknown to the provider: new, or not yet indexed
+    pub unindexed: Vec<DriftedPort>,
+    /// Listed by the provider but not open locally: stale, or filtered
+    /// from where we scanned
+    pub unreachable: Vec<DriftedPort>,
+    /// Listed by the provider but outside the ports we scanned (see
+    /// [`SurfaceDrift::scanned_only`])
+    pub not_scanned: Vec<u16>,
+    /// Open locally and listed by the provider
+    pub confirmed: Vec<u16>,
+}
+
+#[cfg(feature = "scanner")]
+impl SurfaceDrift {
+    /// Returns true if both sides agree on the open ports
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.unindexed.is_empty() && self.unreachable.is_empty()
+    }
+
+    /// Move provider ports the scan never probed out of `unreachable`
+    ///
+    /// A port outside `scanned` says nothing about reachability, so it
+    /// goes to `not_scanned` instead.
+    #[must_use]
+    pub fn scanned_only(mut self, scanned: &crate::scanner::PortSpec) -> Self {
+        let scanned: std::collections::HashSet<u16> = scanned.to_ports().into_iter().collect();
+        let (kept, skipped) = self
+            .unreachable
+            .into_iter()
+            .partition(|p| scanned.contains(&p.port));
+        self.unreachable = kept;
+        self.not_scanned
+            .extend(skipped.into_iter().map(|p: DriftedPort| p.port));
+        self.not_scanned.sort_unstable();
+        self
+    }
+}
+
+/// Compare a local scan with the provider's record of the same host
+///
+/// Ports come from the provider's `ports` list and its service banners.
+/// Every provider port the scan didn't find open is reported as
+/// unreachable; call [`SurfaceDrift::scanned_only`] when the scan covered
+/// fewer ports than the provider indexes.
+#[cfg(feature = "scanner")]
+#[must_use]
+pub fn surface_drift(scan: &crate::scanner::ScanResult, host: &HostInfo) -> SurfaceDrift {
+    use std::collections::BTreeMap;
+
+    let mut indexed: BTreeMap<u16, Option<String>> =
+        host.ports.iter().map(|&port| (port, None)).collect();
+    for service in &host.data {
+        let product = indexed.entry(service.port).or_default();
+        if product.is_none() {
+            product.clone_from(&service.product);
+        }
+    }
+
+    let mut drift = SurfaceDrift {
+        target: scan.target,
+        indexed_at: host.last_update,
+        unindexed: Vec::new(),
+        unreachable: Vec::new(),
+        not_scanned: Vec::new(),
+        confirmed: Vec::new(),
+    };
+    let mut local: BTreeMap<u16, Option<String>> = BTreeMap::new();
+    for info in &scan.open_ports {
+        if info.state == crate::scanner::PortState::Open {
+            let product = info.service.as_ref().and_then(|s| s.product.clone());
+            local.insert(info.port, product);
+        }
+    }
+
+    for (&port, product) in &local {
+        if indexed.contains_key(&port) {
+            drift.confirmed.push(port);
+        } else {
+            drift.unindexed.push(DriftedPort {
+                port,
+                product: product.clone(),
+            });
+        }
+    }
+    for (port, product) in indexed {
+        if !local.contains_key(&port) {
+            drift.unreachable.push(DriftedPort { port, product });
+        }
+    }
+    drift
+}
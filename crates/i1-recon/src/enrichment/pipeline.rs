@@ -0,0 +1,734 @@
+//! Enrichment over a stream of IPs with bounded memory.
+//!
+//! A [`Pipeline`] reads IPs from a [`Source`], runs each one through its
+//! stages in order, and hands the results to a [`Sink`]. A stage is any
+//! [`Enricher`] (provider lookups, scans, WHOIS, your own) with its own
+//! concurrency, rate and credit budget ([`StageOptions`]).
+//!
+//! Stages are joined by bounded channels. When the sink falls behind, the
+//! channels in front of it fill, each stage waits to hand its results on,
+//! and the source stops being read: a run holds at most about
+//! `capacity + concurrency` records per stage however many IPs it reads.
+//! Records leave a stage as they finish, so their order is not kept.
+//!
+//! A failing stage doesn't end the run. Its error is attached to the
+//! record ([`Record::errors`]) and the record moves on to the next stage.
+//!
+//! ```rust,ignore
+//! use i1_recon::enrichment::{LookupEnricher, Pipeline, Sink, Source, StageOptions};
+//!
+//! let summary = Pipeline::new(Source::File("targets.txt".into()))
+//!     .stage(
+//!         LookupEnricher::new(shodan),
+//!         StageOptions::new().concurrency(4).per_second(1).credits(1).max_credits(500),
+//!     )
+//!     .stage(WhoisEnricher::new(WhoisClient::new()?), StageOptions::new().concurrency(8))
+//!     .run(Sink::ndjson(tokio::io::stdout()))
+//!     .await?;
+//! eprintln!("{} records, {} credits", summary.written, summary.credits());
+//! ```
+
+use std::net::IpAddr;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use futures_util::{stream, StreamExt};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use i1_core::{GeoLocation, HostInfo};
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use super::Enricher;
+use crate::error::{ReconError, ReconResult};
+
+/// Channel capacity between stages unless [`Pipeline::capacity`] is set
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// A host on its way through a [`Pipeline`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Record {
+    /// Everything the stages found, merged
+    #[serde(flatten)]
+    pub host: HostInfo,
+    /// Stages that failed or were skipped for this host, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<StageError>,
+}
+
+impl Record {
+    /// A record with nothing known about `ip` yet
+    #[must_use]
+    pub fn new(ip: IpAddr) -> Self {
+        Self {
+            host: HostInfo {
+                ip: Some(ip),
+                ip_str: ip.to_string(),
+                hostnames: Vec::new(),
+                domains: Vec::new(),
+                org: None,
+                asn: None,
+                isp: None,
+                os: None,
+                ports: Vec::new(),
+                vulns: Vec::new(),
+                tags: Vec::new(),
+                location: GeoLocation::default(),
+                data: Vec::new(),
+                last_update: None,
+                last_update_raw: None,
+            },
+            errors: Vec::new(),
+        }
+    }
+
+    /// Returns true if every stage ran and succeeded
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Why a stage left a record unchanged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageError {
+    /// Stage name ([`Enricher::name`])
+    pub stage: String,
+    /// What went wrong
+    pub error: String,
+}
+
+/// Where a pipeline reads IPs from
+///
+/// File and stdin sources take one IP per line; blank lines and `#`
+/// comments are skipped, and other lines are logged and counted in
+/// [`PipelineSummary::invalid`].
+pub enum Source {
+    /// IPs from an iterator, read as the pipeline has room
+    Iter(Box<dyn Iterator<Item = IpAddr> + Send>),
+    /// IPs from a file
+    File(PathBuf),
+    /// IPs from standard input
+    Stdin,
+}
+
+impl Source {
+    /// IPs from anything iterable
+    pub fn iter<I>(ips: I) -> Self
+    where
+        I: IntoIterator<Item = IpAddr>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::Iter(Box::new(ips.into_iter()))
+    }
+
+    /// Send every IP down `tx`, stopping early if the pipeline shuts down
+    async fn feed(self, tx: mpsc::Sender<Record>) -> ReconResult<Counts> {
+        let mut counts = Counts::default();
+        match self {
+            Self::Iter(ips) => {
+                for ip in ips {
+                    if tx.send(Record::new(ip)).await.is_err() {
+                        break;
+                    }
+                    counts.read += 1;
+                }
+            }
+            Self::File(path) => {
+                let file = tokio::fs::File::open(&path)
+                    .await
+                    .map_err(|e| ReconError::Pipeline(format!("{}: {e}", path.display())))?;
+                feed_lines(BufReader::new(file), &tx, &mut counts).await?;
+            }
+            Self::Stdin => {
+                feed_lines(BufReader::new(tokio::io::stdin()), &tx, &mut counts).await?;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+/// IPs read and lines skipped by a source
+#[derive(Debug, Default)]
+struct Counts {
+    read: u64,
+    invalid: u64,
+}
+
+async fn feed_lines<R: AsyncBufRead + Unpin>(
+    reader: R,
+    tx: &mpsc::Sender<Record>,
+    counts: &mut Counts,
+) -> ReconResult<()> {
+    let mut lines = reader.lines();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| ReconError::Pipeline(format!("reading targets: {e}")))?
+    {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Ok(ip) = line.parse() else {
+            warn!(line, "skipping target that isn't an IP address");
+            counts.invalid += 1;
+            continue;
+        };
+        if tx.send(Record::new(ip)).await.is_err() {
+            break;
+        }
+        counts.read += 1;
+    }
+    Ok(())
+}
+
+/// Where a pipeline's records go
+///
+/// Every sink takes records one at a time, so a sink that blocks holds
+/// the whole pipeline back.
+pub enum Sink {
+    /// One JSON object per line
+    Ndjson(Box<dyn AsyncWrite + Send + Unpin>),
+    /// Send each record on a channel
+    Channel(mpsc::Sender<Record>),
+    /// Call a function with each record
+    Callback(Box<dyn FnMut(Record) + Send>),
+}
+
+impl Sink {
+    /// Write NDJSON to `out`
+    pub fn ndjson(out: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        Self::Ndjson(Box::new(out))
+    }
+
+    /// Call `f` with each record
+    pub fn callback(f: impl FnMut(Record) + Send + 'static) -> Self {
+        Self::Callback(Box::new(f))
+    }
+
+    /// Take records until the last stage finishes; returns how many.
+    ///
+    /// A dropped channel receiver ends the run early without an error.
+    async fn drain(self, mut rx: mpsc::Receiver<Record>) -> ReconResult<u64> {
+        let write_failed =
+            |e: std::io::Error| ReconError::Pipeline(format!("writing records: {e}"));
+        let mut written = 0;
+        match self {
+            Self::Ndjson(mut out) => {
+                while let Some(record) = rx.recv().await {
+                    let mut line = serde_json::to_vec(&record)
+                        .map_err(|e| ReconError::Pipeline(format!("encoding record: {e}")))?;
+                    line.push(b'\n');
+                    out.write_all(&line).await.map_err(write_failed)?;
+                    written += 1;
+                }
+                out.flush().await.map_err(write_failed)?;
+            }
+            Self::Channel(tx) => {
+                while let Some(record) = rx.recv().await {
+                    if tx.send(record).await.is_err() {
+                        break;
+                    }
+                    written += 1;
+                }
+            }
+            Self::Callback(mut f) => {
+                while let Some(record) = rx.recv().await {
+                    f(record);
+                    written += 1;
+                }
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// Limits for one stage
+#[derive(Debug, Clone, Copy)]
+pub struct StageOptions {
+    concurrency: usize,
+    per_second: Option<NonZeroU32>,
+    credits_per_call: u64,
+    max_credits: Option<u64>,
+}
+
+impl Default for StageOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            per_second: None,
+            credits_per_call: 0,
+            max_credits: None,
+        }
+    }
+}
+
+impl StageOptions {
+    /// One call at a time, no rate limit, no credits
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run up to `n` calls at once (at least one)
+    #[must_use]
+    pub const fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = if n == 0 { 1 } else { n };
+        self
+    }
+
+    /// Start at most `n` calls per second; 0 removes the limit
+    #[must_use]
+    pub const fn per_second(mut self, n: u32) -> Self {
+        self.per_second = NonZeroU32::new(n);
+        self
+    }
+
+    /// Each call spends `credits` of the provider's quota
+    #[must_use]
+    pub const fn credits(mut self, credits: u64) -> Self {
+        self.credits_per_call = credits;
+        self
+    }
+
+    /// Stop calling once `max` credits are spent; later records skip
+    /// the stage with an error attached
+    #[must_use]
+    pub const fn max_credits(mut self, max: u64) -> Self {
+        self.max_credits = Some(max);
+        self
+    }
+
+    /// Reserve credits for one call; false once the budget is spent.
+    fn reserve(&self, summary: &mut StageSummary) -> bool {
+        let spent = summary.credits + self.credits_per_call;
+        if self.max_credits.is_some_and(|max| spent > max) {
+            return false;
+        }
+        summary.credits = spent;
+        true
+    }
+}
+
+/// What one stage did over a run
+#[derive(Debug, Clone, Default)]
+pub struct StageSummary {
+    /// Stage name ([`Enricher::name`])
+    pub name: String,
+    /// Records the stage enriched
+    pub succeeded: u64,
+    /// Records the stage failed on
+    pub failed: u64,
+    /// Records that skipped the stage once its credit budget was spent
+    pub skipped: u64,
+    /// Time spent in calls, summed (exceeds wall time when concurrent)
+    pub busy: Duration,
+    /// Credits spent
+    pub credits: u64,
+}
+
+/// What a pipeline run did
+#[derive(Debug, Clone, Default)]
+pub struct PipelineSummary {
+    /// IPs read from the source
+    pub read: u64,
+    /// Source lines that weren't IPs
+    pub invalid: u64,
+    /// Records handed to the sink
+    pub written: u64,
+    /// Each stage, in order
+    pub stages: Vec<StageSummary>,
+    /// Wall time of the run
+    pub duration: Duration,
+}
+
+impl PipelineSummary {
+    /// Credits spent across every stage
+    #[must_use]
+    pub fn credits(&self) -> u64 {
+        self.stages.iter().map(|stage| stage.credits).sum()
+    }
+}
+
+struct Stage {
+    enricher: Box<dyn Enricher>,
+    options: StageOptions,
+}
+
+/// Builder and runner for an enrichment pipeline
+///
+/// See the [module docs](self) for how records flow.
+pub struct Pipeline {
+    source: Source,
+    stages: Vec<Stage>,
+    capacity: usize,
+}
+
+impl Pipeline {
+    /// A pipeline reading from `source`, with no stages yet
+    #[must_use]
+    pub const fn new(source: Source) -> Self {
+        Self {
+            source,
+            stages: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Append a stage
+    #[must_use]
+    pub fn stage(mut self, enricher: impl Enricher + 'static, options: StageOptions) -> Self {
+        self.stages.push(Stage {
+            enricher: Box::new(enricher),
+            options,
+        });
+        self
+    }
+
+    /// Records each channel between stages holds (at least one)
+    #[must_use]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Run every IP from the source through the stages into `sink`
+    ///
+    /// Must be called within a Tokio runtime; the source and each stage
+    /// run as their own task.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReconError::Pipeline` if the source can't be read or the
+    /// sink can't be written. Stage failures are attached to records
+    /// instead.
+    pub async fn run(self, sink: Sink) -> ReconResult<PipelineSummary> {
+        let started = Instant::now();
+        let (tx, mut rx) = mpsc::channel(self.capacity);
+        let source = tokio::spawn(self.source.feed(tx));
+        let mut stages = Vec::with_capacity(self.stages.len());
+        for stage in self.stages {
+            let (tx, next) = mpsc::channel(self.capacity);
+            stages.push(tokio::spawn(run_stage(stage, rx, tx)));
+            rx = next;
+        }
+
+        // The sink drives the run; when it stops, everything upstream does
+        let written = sink.drain(rx).await;
+        let counts = join(source).await;
+        let mut summary = PipelineSummary::default();
+        for stage in stages {
+            summary.stages.push(join(stage).await);
+        }
+        let counts = counts?;
+        summary.written = written?;
+        summary.read = counts.read;
+        summary.invalid = counts.invalid;
+        summary.duration = started.elapsed();
+        debug!(
+            read = summary.read,
+            written = summary.written,
+            credits = summary.credits(),
+            "enrichment pipeline finished"
+        );
+        Ok(summary)
+    }
+}
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field(
+                "stages",
+                &self
+                    .stages
+                    .iter()
+                    .map(|stage| stage.enricher.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wait for a task, re-raising its panic.
+async fn join<T>(task: JoinHandle<T>) -> T {
+    task.await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Move records from `rx` through one stage to `tx`.
+async fn run_stage(
+    stage: Stage,
+    rx: mpsc::Receiver<Record>,
+    tx: mpsc::Sender<Record>,
+) -> StageSummary {
+    let Stage { enricher, options } = stage;
+    let limiter = options
+        .per_second
+        .map(|n| RateLimiter::direct(Quota::per_second(n)));
+    let summary = Mutex::new(StageSummary {
+        name: enricher.name().to_string(),
+        ..StageSummary::default()
+    });
+
+    let records = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|record| (record, rx))
+    });
+    {
+        let mut done = std::pin::pin!(records
+            .map(|record| enrich(
+                enricher.as_ref(),
+                options,
+                limiter.as_ref(),
+                &summary,
+                record
+            ))
+            .buffer_unordered(options.concurrency));
+        while let Some(record) = done.next().await {
+            if tx.send(record).await.is_err() {
+                break;
+            }
+        }
+    }
+    summary.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Run one enricher over one record, within the stage's budgets.
+async fn enrich(
+    enricher: &dyn Enricher,
+    options: StageOptions,
+    limiter: Option<&DefaultDirectRateLimiter>,
+    summary: &Mutex<StageSummary>,
+    mut record: Record,
+) -> Record {
+    let stage = enricher.name();
+    let reserved = {
+        let mut summary = summary.lock().unwrap_or_else(PoisonError::into_inner);
+        let reserved = options.reserve(&mut summary);
+        summary.skipped += u64::from(!reserved);
+        reserved
+    };
+    if !reserved {
+        record.errors.push(StageError {
+            stage: stage.to_string(),
+            error: "credit budget spent".into(),
+        });
+        return record;
+    }
+
+    if let Some(limiter) = limiter {
+        limiter.until_ready().await;
+    }
+    let started = Instant::now();
+    let result = enricher.enrich(&mut record.host).await;
+    let elapsed = started.elapsed();
+
+    {
+        let mut summary = summary.lock().unwrap_or_else(PoisonError::into_inner);
+        summary.busy += elapsed;
+        if result.is_ok() {
+            summary.succeeded += 1;
+        } else {
+            summary.failed += 1;
+        }
+    }
+    if let Err(e) = result {
+        debug!(stage, ip = %record.host.ip_str, error = %e, "enrichment stage failed");
+        record.errors.push(StageError {
+            stage: stage.to_string(),
+            error: e.to_string(),
+        });
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    /// Tags every host.
+    struct Tag(&'static str);
+
+    #[async_trait]
+    impl Enricher for Tag {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+            tokio::task::yield_now().await;
+            host.tags.push(self.0.to_string());
+            Ok(())
+        }
+    }
+
+    /// Fails on hosts whose last octet is odd.
+    struct FailOdd;
+
+    #[async_trait]
+    impl Enricher for FailOdd {
+        fn name(&self) -> &'static str {
+            "odd"
+        }
+
+        async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+            match host.ip_addr() {
+                Some(IpAddr::V4(ip)) if ip.octets()[3] % 2 == 1 => Err(ReconError::Timeout),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn ips(count: u32) -> impl Iterator<Item = IpAddr> + Send {
+        (0..count).map(|n| std::net::Ipv4Addr::from(0x0a00_0000_u32 + n).into())
+    }
+
+    fn collect() -> (Sink, Arc<Mutex<Vec<Record>>>) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let records = Arc::clone(&records);
+            Sink::callback(move |record| records.lock().unwrap().push(record))
+        };
+        (sink, records)
+    }
+
+    #[tokio::test]
+    async fn failures_attach_to_records() {
+        let (sink, records) = collect();
+        let summary = Pipeline::new(Source::iter(ips(10)))
+            .stage(Tag("first"), StageOptions::new().concurrency(3))
+            .stage(FailOdd, StageOptions::new())
+            .stage(Tag("last"), StageOptions::new())
+            .run(sink)
+            .await
+            .unwrap();
+
+        assert_eq!((summary.read, summary.written), (10, 10));
+        let odd = &summary.stages[1];
+        assert_eq!((odd.succeeded, odd.failed), (5, 5));
+        assert_eq!(summary.stages[2].succeeded, 10);
+
+        let records = records.lock().unwrap();
+        for record in records.iter() {
+            assert_eq!(record.host.tags, ["first", "last"]);
+            let IpAddr::V4(ip) = record.host.ip.unwrap() else {
+                unreachable!()
+            };
+            if ip.octets()[3] % 2 == 1 {
+                assert_eq!(record.errors[0].stage, "odd");
+                assert_eq!(record.errors[0].error, "operation timed out");
+            } else {
+                assert!(record.is_ok());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn credit_budget_skips_the_rest() {
+        let (sink, records) = collect();
+        let summary = Pipeline::new(Source::iter(ips(10)))
+            .stage(
+                Tag("paid"),
+                StageOptions::new().concurrency(4).credits(2).max_credits(7),
+            )
+            .run(sink)
+            .await
+            .unwrap();
+
+        let paid = &summary.stages[0];
+        assert_eq!((paid.succeeded, paid.skipped, paid.credits), (3, 7, 6));
+        assert_eq!(summary.credits(), 6);
+        let skipped = records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| {
+                record
+                    .errors
+                    .iter()
+                    .any(|e| e.error == "credit budget spent")
+            })
+            .count();
+        assert_eq!(skipped, 7);
+    }
+
+    #[tokio::test]
+    async fn slow_sink_holds_the_source_back() {
+        const CAPACITY: usize = 4;
+        let sourced = Arc::new(AtomicU64::new(0));
+        let source = {
+            let sourced = Arc::clone(&sourced);
+            Source::iter(ips(10_000).inspect(move |_| {
+                sourced.fetch_add(1, Ordering::Relaxed);
+            }))
+        };
+        let (tx, mut rx) = mpsc::channel(1);
+        let run = tokio::spawn(
+            Pipeline::new(source)
+                .capacity(CAPACITY)
+                .stage(Tag("a"), StageOptions::new().concurrency(8))
+                .stage(Tag("b"), StageOptions::new().concurrency(8))
+                .run(Sink::Channel(tx)),
+        );
+
+        for _ in 0..10 {
+            rx.recv().await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // Three channels, two stages' worth of calls, and a few records
+        // held between a receive and a send
+        let bound = 3 * CAPACITY + 2 * 8 + 10 + 8;
+        let read = sourced.load(Ordering::Relaxed);
+        assert!(
+            read <= bound as u64,
+            "read {read} of 10000 ahead of the sink"
+        );
+
+        let mut received = 10;
+        while rx.recv().await.is_some() {
+            received += 1;
+        }
+        let summary = run.await.unwrap().unwrap();
+        assert_eq!(received, 10_000);
+        assert_eq!((summary.read, summary.written), (10_000, 10_000));
+    }
+
+    #[tokio::test]
+    async fn file_source_to_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        let targets = dir.path().join("targets.txt");
+        std::fs::write(&targets, "192.0.2.1\n# lab\n\nnot-an-ip\n  192.0.2.2  \n").unwrap();
+        let out = dir.path().join("out.ndjson");
+
+        let summary = Pipeline::new(Source::File(targets))
+            .stage(FailOdd, StageOptions::new())
+            .run(Sink::ndjson(tokio::fs::File::create(&out).await.unwrap()))
+            .await
+            .unwrap();
+        assert_eq!((summary.read, summary.invalid, summary.written), (2, 1, 2));
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["ip_str"], "192.0.2.1");
+        assert_eq!(lines[0]["errors"][0]["stage"], "odd");
+        assert_eq!(lines[1]["ip_str"], "192.0.2.2");
+        assert!(lines[1].get("errors").is_none());
+
+        let missing = Pipeline::new(Source::File(dir.path().join("nope")))
+            .run(Sink::callback(drop))
+            .await;
+        assert!(matches!(missing, Err(ReconError::Pipeline(_))));
+    }
+}
@@ -37,6 +37,18 @@ pub enum ReconError {
     /// Permission denied (requires root/admin)
     #[error("permission denied: {0}")]
     PermissionDenied(String),
+
+    /// Geolocation database error
+    #[error("GeoIP error: {0}")]
+    GeoIp(String),
+
+    /// Provider lookup error
+    #[error("lookup error: {0}")]
+    Lookup(#[from] i1_core::I1Error),
+
+    /// Enrichment pipeline couldn't read targets or write records
+    #[error("pipeline error: {0}")]
+    Pipeline(String),
 }
 
 impl From<ReconError> for i1_core::I1Error {
@@ -49,7 +61,10 @@ impl From<ReconError> for i1_core::I1Error {
             ReconError::Network(e) => Self::Connection(e.to_string()),
             ReconError::InvalidIp(ip) => Self::InvalidIp(ip),
             ReconError::Timeout => Self::Timeout(0),
-            ReconError::PermissionDenied(msg) => Self::Internal(msg),
+            ReconError::Lookup(e) => e,
+            ReconError::PermissionDenied(msg)
+            | ReconError::GeoIp(msg)
+            | ReconError::Pipeline(msg) => Self::Internal(msg),
         }
     }
 }
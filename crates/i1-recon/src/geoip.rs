@@ -0,0 +1,94 @@
+//! Offline geo/ASN enrichment from local `.mmdb` databases.
+//!
+//! Annotates hosts with country, city, and ASN from `GeoLite2` (or `GeoIP2`)
+//! `.mmdb` files, so large local datasets can be enriched without spending
+//! provider credits. City/Country and ASN data ship as separate databases;
+//! load one with [`GeoIpEnricher::from_mmdb`] and add the other with
+//! [`GeoIpEnricher::with_mmdb`]. The databases are read by
+//! [`i1_core::geo::GeoDatabase`]; this adapts it to an [`Enricher`].
+
+use async_trait::async_trait;
+use i1_core::geo::GeoDatabase;
+use i1_core::HostInfo;
+use std::net::IpAddr;
+use std::path::Path;
+
+pub use i1_core::geo::GeoInfo;
+
+use crate::enrichment::Enricher;
+use crate::error::{ReconError, ReconResult};
+
+/// Enriches hosts from local `.mmdb` databases.
+#[derive(Debug, Default)]
+pub struct GeoIpEnricher {
+    db: GeoDatabase,
+}
+
+impl GeoIpEnricher {
+    /// Open a City, Country, or ASN database.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReconError::GeoIp` if the file can't be read or isn't a
+    /// database this enricher understands.
+    pub fn from_mmdb(path: impl AsRef<Path>) -> ReconResult<Self> {
+        Self::default().with_mmdb(path)
+    }
+
+    /// Add another database, e.g. ASN data alongside a City database.
+    /// A database of the same kind replaces the earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReconError::GeoIp` if the file can't be read or isn't a
+    /// database this enricher understands.
+    pub fn with_mmdb(self, path: impl AsRef<Path>) -> ReconResult<Self> {
+        let db = self
+            .db
+            .with_mmdb(path)
+            .map_err(|e| ReconError::GeoIp(e.to_string()))?;
+        Ok(Self { db })
+    }
+
+    /// The underlying databases.
+    #[must_use]
+    pub const fn database(&self) -> &GeoDatabase {
+        &self.db
+    }
+
+    /// Look up an IP in every loaded database.
+    #[must_use]
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        self.db.lookup(ip)
+    }
+
+    /// Fill in a host's location, ASN, and organization from the local
+    /// databases. Values the provider already supplied are kept.
+    ///
+    /// Returns true if anything was added.
+    pub fn enrich(&self, host: &mut HostInfo) -> bool {
+        host.enrich_geo(&self.db)
+    }
+
+    /// Enrich many hosts; returns how many gained data.
+    pub fn enrich_all<'a>(&self, hosts: impl IntoIterator<Item = &'a mut HostInfo>) -> usize {
+        hosts
+            .into_iter()
+            .map(|host| self.enrich(host))
+            .filter(|added| *added)
+            .count()
+    }
+}
+
+#[async_trait]
+impl Enricher for GeoIpEnricher {
+    fn name(&self) -> &'static str {
+        "geoip"
+    }
+
+    /// Never fails: an IP missing from the databases is left as it was.
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        Self::enrich(self, host);
+        Ok(())
+    }
+}
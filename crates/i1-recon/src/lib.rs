@@ -13,6 +13,9 @@ pub mod scanner;
 #[cfg(feature = "whois")]
 pub mod whois;
 
+#[cfg(feature = "geoip")]
+pub mod geoip;
+
 // Temporarily disabled due to API changes
 // #[cfg(feature = "dns")]
 // pub mod dns;
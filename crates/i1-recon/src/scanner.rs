@@ -1,6 +1,9 @@
 //! Port scanning integration using pistol.
 
-use crate::error::ReconResult;
+use crate::enrichment::Enricher;
+use crate::error::{ReconError, ReconResult};
+use async_trait::async_trait;
+use i1_core::{HostInfo, Service, Transport};
 use std::net::IpAddr;
 use std::time::Duration;
 
@@ -224,6 +227,56 @@ impl Scanner {
     }
 }
 
+/// Adds the ports a local scan finds open to a host
+///
+/// Open ports join `ports`; ports with detected service details that the
+/// host has no banner for gain one.
+pub struct ScanEnricher {
+    scanner: Scanner,
+}
+
+impl ScanEnricher {
+    /// Enrich with `scanner`
+    #[must_use]
+    pub const fn new(scanner: Scanner) -> Self {
+        Self { scanner }
+    }
+}
+
+#[async_trait]
+impl Enricher for ScanEnricher {
+    fn name(&self) -> &'static str {
+        "scan"
+    }
+
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        let ip = host
+            .ip_addr()
+            .ok_or_else(|| ReconError::InvalidIp(host.ip_str.clone()))?;
+        let scan = self.scanner.scan(ip).await?;
+
+        for info in scan.open_ports {
+            if info.state != PortState::Open {
+                continue;
+            }
+            if !host.ports.contains(&info.port) {
+                host.ports.push(info.port);
+            }
+            let known = host.data.iter().any(|s| s.port == info.port);
+            if let (Some(service), false) = (info.service, known) {
+                host.data.push(Service {
+                    product: service.product,
+                    version: service.version,
+                    data: service.banner,
+                    ..Service::new(info.port, Transport::Tcp)
+                });
+            }
+        }
+        host.ports.sort_unstable();
+        Ok(())
+    }
+}
+
 // Top 100 most common ports
 const TOP_100_PORTS: [u16; 100] = [
     21, 22, 23, 25, 26, 53, 80, 81, 110, 111, 113, 135, 139, 143, 179, 199, 443, 445, 465, 514,
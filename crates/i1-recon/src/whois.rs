@@ -1,6 +1,9 @@
 //! WHOIS lookup integration using whois-rs.
 
+use crate::enrichment::{fill, Enricher};
 use crate::error::{ReconError, ReconResult};
+use async_trait::async_trait;
+use i1_core::HostInfo;
 use std::net::IpAddr;
 
 /// WHOIS lookup result
@@ -20,6 +23,8 @@ pub struct WhoisInfo {
     pub name_servers: Vec<String>,
     /// Domain status codes
     pub status: Vec<String>,
+    /// Origin AS of an IP allocation (e.g. "AS15169")
+    pub origin_as: Option<String>,
 }
 
 /// Registrant information from WHOIS
@@ -35,6 +40,17 @@ pub struct RegistrantInfo {
     pub country: Option<String>,
 }
 
+impl RegistrantInfo {
+    const fn empty() -> Self {
+        Self {
+            name: None,
+            organization: None,
+            email: None,
+            country: None,
+        }
+    }
+}
+
 /// WHOIS client
 pub struct WhoisClient {
     whois: whois_rs::WhoIs,
@@ -74,6 +90,42 @@ impl WhoisClient {
     }
 }
 
+/// Fills a host's organization, ASN, and country from IP WHOIS.
+///
+/// Only fields the provider left empty are filled, so run it after sources
+/// you trust more.
+pub struct WhoisEnricher {
+    client: WhoisClient,
+}
+
+impl WhoisEnricher {
+    /// Enrich through `client`
+    pub const fn new(client: WhoisClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Enricher for WhoisEnricher {
+    fn name(&self) -> &'static str {
+        "whois"
+    }
+
+    async fn enrich(&self, host: &mut HostInfo) -> ReconResult<()> {
+        let ip = host
+            .ip_addr()
+            .ok_or_else(|| ReconError::InvalidIp(host.ip_str.clone()))?;
+        let info = self.client.lookup_ip(ip).await?;
+
+        if let Some(registrant) = info.registrant {
+            fill(&mut host.org, registrant.organization);
+            fill(&mut host.location.country_code, registrant.country);
+        }
+        fill(&mut host.asn, info.origin_as);
+        Ok(())
+    }
+}
+
 /// Parse raw WHOIS response into structured data
 fn parse_whois_response(raw: &str) -> WhoisInfo {
     let mut info = WhoisInfo {
@@ -84,6 +136,7 @@ fn parse_whois_response(raw: &str) -> WhoisInfo {
         expiration_date: None,
         name_servers: Vec::new(),
         status: Vec::new(),
+        origin_as: None,
     };
 
     // Simple line-based parsing
@@ -109,6 +162,21 @@ fn parse_whois_response(raw: &str) -> WhoisInfo {
                         info.status.push(value);
                     }
                 }
+                // IP allocations (ARIN, RIPE and friends)
+                "orgname" | "org-name" | "organization" | "registrant organization" => {
+                    let registrant = info.registrant.get_or_insert_with(RegistrantInfo::empty);
+                    fill(&mut registrant.organization, Some(value));
+                }
+                "country" | "registrant country" => {
+                    let registrant = info.registrant.get_or_insert_with(RegistrantInfo::empty);
+                    fill(&mut registrant.country, Some(value.to_uppercase()));
+                }
+                "originas" | "origin" => {
+                    let asn = value.trim_start_matches("AS").trim_start_matches("as");
+                    if asn.parse::<u32>().is_ok() {
+                        fill(&mut info.origin_as, Some(format!("AS{asn}")));
+                    }
+                }
                 _ => {}
             }
         }
@@ -0,0 +1,227 @@
+//! Shodan's network alert API.
+//!
+//! An alert watches a set of IPs or networks, and its triggers fire when
+//! Shodan sees something change there. These calls manage the alerts
+//! themselves; [`ShodanStream::alerts`](crate::ShodanStream::alerts)
+//! follows the banners they match.
+
+use i1_core::{Alert, AlertFilters, CreateAlertRequest, I1Error, Result, Trigger};
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use tracing::{debug, instrument};
+
+use crate::ShodanProvider;
+
+/// Entry point to the alert API, from [`ShodanProvider::alerts`]
+pub struct ShodanAlerts<'a> {
+    provider: &'a ShodanProvider,
+}
+
+impl ShodanProvider {
+    /// Manage the account's network alerts with this provider's key
+    #[must_use]
+    pub const fn alerts(&self) -> ShodanAlerts<'_> {
+        ShodanAlerts { provider: self }
+    }
+}
+
+impl<'a> ShodanAlerts<'a> {
+    /// Every alert on the account, from `/shodan/alert/info`
+    ///
+    /// The API has no paging; narrow the list with
+    /// [`AlertListExt`](i1_core::AlertListExt) and
+    /// [`AlertPage`](i1_core::AlertPage).
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn list(&self) -> Result<Vec<Alert>> {
+        self.provider.get("/shodan/alert/info").await
+    }
+
+    /// One alert by ID
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn get(&self, id: &str) -> Result<Alert> {
+        self.provider.get(&format!("/shodan/alert/{id}/info")).await
+    }
+
+    /// Start a new alert called `name`; add networks, then
+    /// [`send`](CreateAlert::send) it
+    pub fn create(&self, name: impl Into<String>) -> CreateAlert<'a> {
+        CreateAlert {
+            alerts: ShodanAlerts {
+                provider: self.provider,
+            },
+            request: CreateAlertRequest {
+                name: name.into(),
+                filters: AlertFilters::default(),
+                expires: None,
+            },
+        }
+    }
+
+    /// Delete an alert by ID
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        let endpoint = format!("/shodan/alert/{id}");
+        let request = self.provider.inner.http.delete(self.url(&endpoint));
+        self.send::<serde_json::Value>(request, &endpoint)
+            .await
+            .map(drop)
+    }
+
+    /// Triggers an alert can enable, from `/shodan/alert/triggers`
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn triggers(&self) -> Result<Vec<Trigger>> {
+        self.provider.get("/shodan/alert/triggers").await
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}{}", self.provider.inner.base_url, endpoint)
+    }
+
+    /// Send a request that isn't a plain GET, with the key and rate limit
+    /// the provider's own requests use
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        endpoint: &str,
+    ) -> Result<T> {
+        let inner = &self.provider.inner;
+        inner.rate_limiter.until_ready().await;
+        debug!(endpoint, "Shodan API request");
+
+        let response = request
+            .query(&[("key", &inner.api_key)])
+            .send()
+            .await
+            .map_err(I1Error::from)?;
+        let response = ShodanProvider::check_status(response, endpoint).await?;
+        response.json().await.map_err(I1Error::from)
+    }
+}
+
+/// A new alert being built, from [`ShodanAlerts::create`]
+pub struct CreateAlert<'a> {
+    alerts: ShodanAlerts<'a>,
+    request: CreateAlertRequest,
+}
+
+impl CreateAlert<'_> {
+    /// Watch an IP address or CIDR network
+    #[must_use]
+    pub fn ip(mut self, ip: impl Into<String>) -> Self {
+        self.request.filters.ip.push(ip.into());
+        self
+    }
+
+    /// Expire the alert after `days` (0 never expires, the default)
+    #[must_use]
+    pub fn expires_in_days(mut self, days: u32) -> Self {
+        self.request.expires = (days > 0).then_some(days);
+        self
+    }
+
+    /// Create the alert, returning it as Shodan stored it
+    #[instrument(skip(self), fields(provider = "shodan", name = %self.request.name))]
+    pub async fn send(self) -> Result<Alert> {
+        let endpoint = "/shodan/alert";
+        let request = self
+            .alerts
+            .provider
+            .inner
+            .http
+            .post(self.alerts.url(endpoint))
+            .json(&self.request);
+        self.alerts.send(request, endpoint).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> ShodanProvider {
+        let mut provider = ShodanProvider::new("secret-key");
+        std::sync::Arc::get_mut(&mut provider.inner)
+            .expect("provider not shared yet")
+            .base_url = server.uri();
+        provider
+    }
+
+    #[tokio::test]
+    async fn alerts_are_listed_and_fetched() {
+        let server = MockServer::start().await;
+        let alert = json!({
+            "id": "ABC123",
+            "name": "office",
+            "filters": {"ip": ["198.51.100.0/24"]},
+            "expiration": "2026-11-01T00:00:00.000000",
+            "expires": 14,
+            "size": 256
+        });
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert/info"))
+            .and(query_param("key", "secret-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([alert])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert/ABC123/info"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&alert))
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        let alerts = provider.alerts().list().await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].filters.ip, ["198.51.100.0/24"]);
+        assert!(alerts[0].expiration.is_some());
+        let alert = provider.alerts().get("ABC123").await.unwrap();
+        assert_eq!(alert.name, "office");
+
+        let missing = provider.alerts().get("NOPE").await.unwrap_err();
+        assert!(matches!(missing, I1Error::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn alerts_are_created_and_deleted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/shodan/alert"))
+            .and(query_param("key", "secret-key"))
+            .and(body_json(json!({
+                "name": "office",
+                "filters": {"ip": ["198.51.100.7", "203.0.113.0/24"]},
+                "expires": 30
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "NEW1",
+                "name": "office",
+                "filters": {"ip": ["198.51.100.7", "203.0.113.0/24"]},
+                "expires": 30
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/shodan/alert/NEW1"))
+            .and(query_param("key", "secret-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"success": true})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = provider(&server);
+        let alert = provider
+            .alerts()
+            .create("office")
+            .ip("198.51.100.7")
+            .ip("203.0.113.0/24")
+            .expires_in_days(30)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(alert.id, "NEW1");
+        provider.alerts().delete(&alert.id).await.unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! Forgiving decoding for responses with malformed fields.
+//!
+//! Shodan occasionally returns a banner with a field of an unexpected type,
+//! which fails strict deserialization of the whole response. In lenient
+//! mode the response is read as a [`serde_json::Value`] first; each value
+//! that doesn't fit is removed, recorded as a [`FieldWarning`], and decoding
+//! is retried. A bad array element (a banner in `data`, a port in `ports`)
+//! is dropped on its own; any other bad field falls back to its default.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_path_to_error::{Path, Segment};
+
+/// Most values removed from one record before giving up on it.
+const MAX_PRUNED: usize = 32;
+
+/// A value removed while decoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldWarning {
+    /// Where the value was, e.g. `data[2].port`.
+    pub path: String,
+    /// Why it didn't decode.
+    pub message: String,
+}
+
+/// Decode `value` as `T`, removing the values that don't fit.
+///
+/// Returns the error that pruning can't get past, such as a missing
+/// required field or a record that isn't an object.
+pub fn decode<T: DeserializeOwned>(
+    mut value: Value,
+) -> Result<(T, Vec<FieldWarning>), serde_json::Error> {
+    let mut warnings = Vec::new();
+    loop {
+        let err = match serde_path_to_error::deserialize(&value) {
+            Ok(decoded) => return Ok((decoded, warnings)),
+            Err(err) => err,
+        };
+        if warnings.len() >= MAX_PRUNED || !prune(&mut value, err.path()) {
+            return Err(err.into_inner());
+        }
+        warnings.push(FieldWarning {
+            path: err.path().to_string(),
+            message: err.inner().to_string(),
+        });
+    }
+}
+
+/// Remove the value at `path`, or the array element it lies in.
+///
+/// Returns false when there's nothing to remove (the error is about the
+/// record itself) or the path can't be followed.
+fn prune(value: &mut Value, path: &Path) -> bool {
+    let mut segments = path.iter().peekable();
+    let mut node = value;
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        match (segment, node) {
+            (Segment::Seq { index }, Value::Array(items)) if *index < items.len() => {
+                items.remove(*index);
+                return true;
+            }
+            (Segment::Map { key }, Value::Object(fields)) if last => {
+                return fields.remove(key).is_some();
+            }
+            (Segment::Map { key }, Value::Object(fields)) => match fields.get_mut(key) {
+                Some(child) => node = child,
+                None => return false,
+            },
+            _ => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i1_core::HostInfo;
+    use serde_json::json;
+
+    #[test]
+    fn drops_bad_fields_and_banners() {
+        let value = json!({
+            "ip_str": "1.2.3.4",
+            "org": 42,
+            "ports": [22, "http", 443],
+            "data": [
+                {"port": 22, "transport": "tcp"},
+                {"port": "eighty", "transport": "tcp"},
+                {"port": 443, "transport": "tcp"}
+            ]
+        });
+        let (host, warnings) = decode::<HostInfo>(value).unwrap();
+        assert_eq!(host.ip_str, "1.2.3.4");
+        assert_eq!(host.org, None);
+        assert_eq!(host.ports, [22, 443]);
+        assert_eq!(host.data.len(), 2);
+        let mut paths: Vec<_> = warnings.iter().map(|w| w.path.as_str()).collect();
+        paths.sort_unstable();
+        assert_eq!(paths, ["data[1].port", "org", "ports[1]"]);
+    }
+
+    #[test]
+    fn unrecoverable_errors_are_returned() {
+        assert!(decode::<HostInfo>(json!({"ip_str": 5})).is_err());
+        assert!(decode::<HostInfo>(json!([1, 2])).is_err());
+    }
+}
@@ -15,24 +15,62 @@
 //! let host = provider.lookup_host("8.8.8.8").await?;
 //! println!("Organization: {:?}", host.org);
 //! ```
+//!
+//! Shodan occasionally returns a banner with a field of an unexpected type.
+//! By default that fails the whole response; build the provider with
+//! [`ShodanBuilder::lenient`] to drop the bad values instead:
+//!
+//! ```rust,ignore
+//! let provider = ShodanProvider::builder("your-api-key").lenient(true).build();
+//! ```
+//!
+//! When the banners aren't needed, [`ShodanProvider::lookup_host_min`] and
+//! [`SearchProvider::search_min`] request Shodan's `minify=true` shape and
+//! decode it into the smaller [`MinHostInfo`].
+//!
+//! [`ShodanProvider::stream`] follows the streaming API, e.g. banners for
+//! the networks covered by your alerts as Shodan sees them:
+//!
+//! ```rust,ignore
+//! let mut banners = provider.stream().alerts().await?;
+//! while let Some(banner) = banners.next().await {
+//!     let banner = banner?;
+//!     println!("{}:{}", banner.ip_str, banner.service.port);
+//! }
+//! ```
+//!
+//! The alerts themselves are managed through [`ShodanProvider::alerts`]:
+//!
+//! ```rust,ignore
+//! let alert = provider.alerts().create("office").ip("198.51.100.0/24").send().await?;
+//! for alert in provider.alerts().list().await? {
+//!     println!("{} {}", alert.id, alert.name);
+//! }
+//! ```
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Instant;
 
 use async_trait::async_trait;
 use governor::{Quota, RateLimiter};
-use i1_core::{HostInfo, I1Error, Result};
+use i1_core::{ApiInfo, HostInfo, I1Error, MinHostInfo, Result};
 use i1_providers::{
-    AuthConfig, DnsProvider, DomainInfo, HealthStatus, HostLookup, Provider, ProviderHealth,
-    RateLimitConfig, SearchProvider, SearchResults,
+    AuthConfig, DnsProvider, DomainInfo, HealthStatus, HostLookup, MinSearchResults, Provider,
+    ProviderHealth, RateLimitConfig, SearchProvider, SearchResults,
 };
-use reqwest::Client;
+use reqwest::{Client, Response};
 use serde::de::DeserializeOwned;
 use std::net::IpAddr;
 use std::num::NonZeroU32;
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 
+mod alerts;
+mod lenient;
+mod stream;
 mod types;
+pub use alerts::{CreateAlert, ShodanAlerts};
+pub use stream::{ShodanStream, DEFAULT_HEARTBEAT, DEFAULT_STREAM_URL};
 pub use types::*;
 
 const DEFAULT_BASE_URL: &str = "https://api.shodan.io";
@@ -46,6 +84,8 @@ struct ShodanInner {
     http: Client,
     api_key: String,
     base_url: String,
+    stream_url: String,
+    lenient: bool,
     rate_limiter: RateLimiter<
         governor::state::NotKeyed,
         governor::state::InMemoryState,
@@ -61,20 +101,12 @@ impl ShodanProvider {
 
     /// Create with custom rate limit config
     pub fn with_config(api_key: impl Into<String>, rate_limit: RateLimitConfig) -> Self {
-        let quota = Quota::per_second(
-            NonZeroU32::new(rate_limit.requests_per_second.max(1.0) as u32)
-                .unwrap_or(NonZeroU32::MIN),
-        )
-        .allow_burst(NonZeroU32::new(rate_limit.burst_size).unwrap_or(NonZeroU32::MIN));
+        ShodanBuilder::new(api_key).rate_limit(rate_limit).build()
+    }
 
-        Self {
-            inner: Arc::new(ShodanInner {
-                http: Client::new(),
-                api_key: api_key.into(),
-                base_url: DEFAULT_BASE_URL.to_string(),
-                rate_limiter: RateLimiter::direct(quota),
-            }),
-        }
+    /// Create a builder for lenient decoding or a custom base URL
+    pub fn builder(api_key: impl Into<String>) -> ShodanBuilder {
+        ShodanBuilder::new(api_key)
     }
 
     /// Create with paid tier rate limits
@@ -87,6 +119,66 @@ impl ShodanProvider {
         AuthConfig::shodan(&self.inner.api_key)
     }
 
+    /// Plan and credits for this key, from `/api-info` (uses no credits)
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn api_info(&self) -> Result<ApiInfo> {
+        self.get("/api-info").await
+    }
+
+    /// Look up a host without its banners, using `minify=true`
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    pub async fn lookup_host_min(&self, ip: &str) -> Result<MinHostInfo> {
+        let endpoint = format!("/shodan/host/{ip}");
+        let query = [("minify", "true")];
+        if self.inner.lenient {
+            let value = self.get_with_query(&endpoint, &query).await?;
+            return Ok(Self::decode_lenient(value, ip)?);
+        }
+        self.get_with_query(&endpoint, &query).await
+    }
+
+    /// Fetch one page of `/shodan/host/search`
+    async fn search_page(
+        &self,
+        query: &str,
+        page: Option<u32>,
+        minify: bool,
+    ) -> Result<ShodanSearchResponse> {
+        let page_str = page.unwrap_or(1).to_string();
+        let mut query_params: Vec<(&str, &str)> = vec![("query", query), ("page", &page_str)];
+        if minify {
+            query_params.push(("minify", "true"));
+        }
+
+        if self.inner.lenient {
+            let value = self
+                .get_with_query("/shodan/host/search", &query_params)
+                .await?;
+            Self::decode_search_lenient(value)
+        } else {
+            self.get_with_query("/shodan/host/search", &query_params)
+                .await
+        }
+    }
+
+    /// Aggregate matches by IP - search returns one match per service/port,
+    /// but we want one `HostInfo` per IP with all ports collected.
+    fn group_matches(matches: Vec<ShodanSearchMatch>) -> Vec<HostInfo> {
+        let mut ip_map: std::collections::HashMap<String, HostInfo> =
+            std::collections::HashMap::new();
+
+        for m in matches {
+            let port = m.port;
+            let ip_key = m.ip_str.clone();
+            let entry = ip_map.entry(ip_key).or_insert_with(|| m.into_host_info());
+            if !entry.ports.contains(&port) {
+                entry.ports.push(port);
+            }
+        }
+
+        ip_map.into_values().collect()
+    }
+
     /// Make a GET request to the Shodan API
     async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
         self.get_with_query(endpoint, &[]).await
@@ -115,34 +207,148 @@ impl ShodanProvider {
             request = request.query(query);
         }
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))?;
+        let response = request.send().await.map_err(I1Error::from)?;
+        let response = Self::check_status(response, endpoint).await?;
+
+        response.json().await.map_err(I1Error::from)
+    }
 
+    /// Turn an unsuccessful response into the matching error
+    async fn check_status(response: Response, endpoint: &str) -> Result<Response> {
         let status = response.status();
-        if !status.is_success() {
-            let code = status.as_u16();
-            let message = response.text().await.unwrap_or_default();
-
-            return match code {
-                401 => Err(I1Error::Unauthorized),
-                402 => Err(I1Error::InsufficientCredits {
-                    required: 1,
-                    available: 0,
-                }),
-                429 => Err(I1Error::RateLimited { retry_after: None }),
-                404 => Err(I1Error::NotFound {
-                    resource: endpoint.to_string(),
-                }),
-                _ => Err(I1Error::provider("shodan", code, message)),
-            };
+        if status.is_success() {
+            return Ok(response);
+        }
+        let code = status.as_u16();
+        let message = response.text().await.unwrap_or_default();
+
+        match code {
+            401 => Err(I1Error::Unauthorized),
+            402 => Err(I1Error::InsufficientCredits {
+                required: 1,
+                available: 0,
+            }),
+            429 => Err(I1Error::RateLimited { retry_after: None }),
+            404 => Err(I1Error::NotFound {
+                resource: endpoint.to_string(),
+            }),
+            _ => Err(I1Error::provider("shodan", code, message)),
         }
+    }
 
-        response
-            .json()
-            .await
-            .map_err(|e| I1Error::Http(e.to_string()))
+    /// Decode one record in lenient mode, logging the values dropped from it
+    fn decode_lenient<T: DeserializeOwned>(
+        value: serde_json::Value,
+        record: &str,
+    ) -> std::result::Result<T, serde_json::Error> {
+        let (decoded, warnings) = lenient::decode(value)?;
+        for warning in warnings {
+            warn!(
+                record,
+                path = %warning.path,
+                error = %warning.message,
+                "dropped malformed Shodan field"
+            );
+        }
+        Ok(decoded)
+    }
+
+    /// Decode a search page in lenient mode, skipping matches that can't be read
+    fn decode_search_lenient(mut value: serde_json::Value) -> Result<ShodanSearchResponse> {
+        let matches = value
+            .as_object_mut()
+            .and_then(|page| page.insert("matches".into(), serde_json::Value::Array(Vec::new())))
+            .and_then(|matches| match matches {
+                serde_json::Value::Array(matches) => Some(matches),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut page: ShodanSearchResponse = Self::decode_lenient(value, "search")?;
+
+        for (index, item) in matches.into_iter().enumerate() {
+            let record = format!("matches[{index}]");
+            match Self::decode_lenient(item, &record) {
+                Ok(m) => page.matches.push(m),
+                Err(e) => warn!(record, error = %e, "skipped malformed Shodan search match"),
+            }
+        }
+        Ok(page)
+    }
+}
+
+/// Builder for a [`ShodanProvider`] with custom settings
+pub struct ShodanBuilder {
+    api_key: String,
+    base_url: String,
+    stream_url: String,
+    rate_limit: RateLimitConfig,
+    lenient: bool,
+}
+
+impl ShodanBuilder {
+    /// Create a new builder with an API key
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            stream_url: DEFAULT_STREAM_URL.to_string(),
+            rate_limit: RateLimitConfig::shodan_free(),
+            lenient: false,
+        }
+    }
+
+    /// Set the rate limit config
+    #[must_use]
+    pub const fn rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    /// Override the API base URL (proxies, gateways, testing)
+    #[must_use]
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Override the streaming API root (default: [`DEFAULT_STREAM_URL`])
+    #[must_use]
+    pub fn stream_url(mut self, stream_url: impl Into<String>) -> Self {
+        self.stream_url = stream_url.into();
+        self
+    }
+
+    /// Drop malformed values from host and search responses instead of
+    /// failing them
+    ///
+    /// Off by default. When on, a field with an unexpected type falls back
+    /// to its default, a malformed banner or list item is dropped, and a
+    /// search match that still can't be read is skipped. Each is logged as
+    /// a `WARN` event under the request's span.
+    #[must_use]
+    pub const fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Build the provider
+    pub fn build(self) -> ShodanProvider {
+        let quota = Quota::per_second(
+            NonZeroU32::new(self.rate_limit.requests_per_second.max(1.0) as u32)
+                .unwrap_or(NonZeroU32::MIN),
+        )
+        .allow_burst(NonZeroU32::new(self.rate_limit.burst_size).unwrap_or(NonZeroU32::MIN));
+
+        ShodanProvider {
+            inner: Arc::new(ShodanInner {
+                http: Client::new(),
+                api_key: self.api_key,
+                base_url: self.base_url,
+                stream_url: self.stream_url,
+                lenient: self.lenient,
+                rate_limiter: RateLimiter::direct(quota),
+            }),
+        }
     }
 }
 
@@ -212,6 +418,10 @@ impl HostLookup for ShodanProvider {
     #[instrument(skip(self), fields(provider = "shodan"))]
     async fn lookup_host(&self, ip: &str) -> Result<HostInfo> {
         let endpoint = format!("/shodan/host/{ip}");
+        if self.inner.lenient {
+            let value = self.get(&endpoint).await?;
+            return Ok(Self::decode_lenient(value, ip)?);
+        }
         self.get(&endpoint).await
     }
 }
@@ -220,36 +430,29 @@ impl HostLookup for ShodanProvider {
 impl SearchProvider for ShodanProvider {
     #[instrument(skip(self), fields(provider = "shodan"))]
     async fn search(&self, query: &str, page: Option<u32>) -> Result<SearchResults> {
-        let page_str = page.unwrap_or(1).to_string();
-        let query_params: Vec<(&str, &str)> = vec![("query", query), ("page", &page_str)];
-
-        let response: ShodanSearchResponse = self
-            .get_with_query("/shodan/host/search", &query_params)
-            .await?;
-
-        // Aggregate matches by IP - search returns one match per service/port,
-        // but we want one HostInfo per IP with all ports collected.
-        let mut ip_map: std::collections::HashMap<String, HostInfo> =
-            std::collections::HashMap::new();
+        let response = self.search_page(query, page, false).await?;
 
-        for m in response.matches {
-            let port = m.port;
-            let ip_key = m.ip_str.clone();
-            let entry = ip_map
-                .entry(ip_key)
-                .or_insert_with(|| m.into_host_info());
-            if !entry.ports.contains(&port) {
-                entry.ports.push(port);
-            }
-        }
+        Ok(SearchResults {
+            provider: "shodan".to_string(),
+            total: response.total,
+            page: page.unwrap_or(1),
+            results: Self::group_matches(response.matches),
+            facets: response.facets,
+        })
+    }
 
-        let results: Vec<HostInfo> = ip_map.into_values().collect();
+    #[instrument(skip(self), fields(provider = "shodan"))]
+    async fn search_min(&self, query: &str, page: Option<u32>) -> Result<MinSearchResults> {
+        let response = self.search_page(query, page, true).await?;
 
-        Ok(SearchResults {
+        Ok(MinSearchResults {
             provider: "shodan".to_string(),
             total: response.total,
             page: page.unwrap_or(1),
-            results,
+            results: Self::group_matches(response.matches)
+                .into_iter()
+                .map(MinHostInfo::from)
+                .collect(),
             facets: response.facets,
         })
     }
@@ -303,26 +506,37 @@ impl DnsProvider for ShodanProvider {
             .collect())
     }
 
+    async fn reverse_many(&self, ips: &[String]) -> Result<BTreeMap<String, Vec<String>>> {
+        let response: ShodanDnsReverse = self
+            .get_with_query("/dns/reverse", &[("ips", &ips.join(","))])
+            .await?;
+
+        let mut hostnames: BTreeMap<String, Vec<String>> =
+            ips.iter().map(|ip| (ip.clone(), Vec::new())).collect();
+        for (ip, names) in response.0 {
+            let names = names
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|s| s.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            hostnames.insert(ip, names);
+        }
+        Ok(hostnames)
+    }
+
     async fn domain_info(&self, domain: &str) -> Result<DomainInfo> {
         let response: ShodanDomainInfo = self.get(&format!("/dns/domain/{domain}")).await?;
+        Ok(response.into())
+    }
 
-        Ok(DomainInfo {
-            domain: response.domain,
-            subdomains: response.subdomains,
-            records: response
-                .data
-                .into_iter()
-                .map(|r| i1_providers::DnsRecord {
-                    record_type: r.record_type,
-                    name: r.subdomain.unwrap_or_default(),
-                    value: r.value,
-                    ttl: None,
-                })
-                .collect(),
-            registrar: None,
-            created: None,
-            expires: None,
-        })
+    async fn domain_history(&self, domain: &str) -> Result<DomainInfo> {
+        let response: ShodanDomainInfo = self
+            .get_with_query(&format!("/dns/domain/{domain}"), &[("history", "true")])
+            .await?;
+        Ok(response.into())
     }
 }
 
@@ -423,6 +637,7 @@ impl ShodanSearchMatch {
             },
             data: Vec::new(),
             last_update: None,
+            last_update_raw: None,
         }
     }
 }
@@ -460,4 +675,140 @@ struct ShodanDnsRecord {
     record_type: String,
     subdomain: Option<String>,
     value: String,
+    #[serde(default)]
+    last_seen: Option<String>,
+}
+
+impl From<ShodanDomainInfo> for DomainInfo {
+    fn from(response: ShodanDomainInfo) -> Self {
+        Self {
+            domain: response.domain,
+            subdomains: response.subdomains,
+            records: response
+                .data
+                .into_iter()
+                .map(|r| i1_providers::DnsRecord {
+                    record_type: r.record_type,
+                    name: r.subdomain.unwrap_or_default(),
+                    value: r.value,
+                    ttl: None,
+                    last_seen: r.last_seen,
+                })
+                .collect(),
+            registrar: None,
+            created: None,
+            expires: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i1_core::TransportErrorKind;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn lenient_search_keeps_the_rest_of_the_page() {
+        let page = json!({
+            "total": 3,
+            "matches": [
+                {"ip_str": "1.1.1.1", "port": 80},
+                {"ip_str": "2.2.2.2", "port": "http"},
+                {"ip_str": "3.3.3.3", "port": 443, "hostnames": "one.example"},
+                "not a banner"
+            ]
+        });
+        assert!(serde_json::from_value::<ShodanSearchResponse>(page.clone()).is_err());
+
+        let response = ShodanProvider::decode_search_lenient(page).unwrap();
+        assert_eq!(response.total, 3);
+        let ports: Vec<_> = response
+            .matches
+            .iter()
+            .map(|m| (m.ip_str.as_str(), m.port))
+            .collect();
+        assert_eq!(ports, [("1.1.1.1", 80), ("2.2.2.2", 0), ("3.3.3.3", 443)]);
+        assert!(response.matches[2].hostnames.is_empty());
+    }
+
+    #[test]
+    fn minified_matches_group_into_one_summary_per_host() {
+        let page: ShodanSearchResponse = serde_json::from_value(json!({
+            "total": 3,
+            "matches": [
+                {"ip_str": "1.1.1.1", "port": 80, "org": "Example", "location": {"country_code": "AU"}},
+                {"ip_str": "1.1.1.1", "port": 443, "org": "Example"},
+                {"ip_str": "2.2.2.2", "port": 22}
+            ]
+        }))
+        .unwrap();
+
+        let mut hosts: Vec<MinHostInfo> = ShodanProvider::group_matches(page.matches)
+            .into_iter()
+            .map(MinHostInfo::from)
+            .collect();
+        hosts.sort_by(|a, b| a.ip_str.cmp(&b.ip_str));
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].ports, [80, 443]);
+        assert_eq!(hosts[0].location.country_code.as_deref(), Some("AU"));
+        assert_eq!(hosts[1].ports, [22]);
+    }
+
+    #[tokio::test]
+    async fn failures_are_classified() {
+        let server = MockServer::start().await;
+        for (ip, status) in [("1.1.1.1", 401), ("2.2.2.2", 402), ("3.3.3.3", 503)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/shodan/host/{ip}")))
+                .respond_with(ResponseTemplate::new(status))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/shodan/host/4.4.4.4"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+
+        let lookup = |base_url: String, ip: &'static str| async move {
+            ShodanProvider::builder("secret-key")
+                .base_url(base_url)
+                .build()
+                .lookup_host_min(ip)
+                .await
+                .unwrap_err()
+        };
+
+        let err = lookup(server.uri(), "1.1.1.1").await;
+        assert!(err.is_auth() && !err.is_retryable());
+        let err = lookup(server.uri(), "2.2.2.2").await;
+        assert!(err.is_quota() && !err.is_retryable());
+        let err = lookup(server.uri(), "3.3.3.3").await;
+        assert!(err.is_retryable() && err.status_code() == Some(503));
+
+        let err = lookup(server.uri(), "4.4.4.4").await;
+        assert!(matches!(
+            err,
+            I1Error::Http {
+                kind: TransportErrorKind::Decode,
+                ..
+            }
+        ));
+        assert!(!err.is_retryable());
+
+        // Nothing listens on port 9 (discard) here
+        let err = lookup("http://127.0.0.1:9".into(), "1.1.1.1").await;
+        assert!(matches!(
+            err,
+            I1Error::Http {
+                kind: TransportErrorKind::Connect,
+                ..
+            }
+        ));
+        assert!(err.is_retryable());
+        assert!(!err.to_string().contains("secret-key"));
+    }
 }
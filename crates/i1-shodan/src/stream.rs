@@ -0,0 +1,217 @@
+//! Shodan's streaming API.
+//!
+//! A stream is one long-lived HTTP response carrying a JSON banner per
+//! line, with blank lines sent as keep-alives while nothing happens. The
+//! API has no cursor, so banners sent while disconnected are lost; callers
+//! that reconnect should record the gap.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::stream::{self, BoxStream, StreamExt};
+use i1_core::{I1Error, Result};
+use reqwest::Response;
+use tracing::debug;
+
+use crate::{Banner, ShodanInner, ShodanProvider};
+
+/// Default root of the streaming API
+pub const DEFAULT_STREAM_URL: &str = "https://stream.shodan.io";
+
+/// How long a stream may go without data or a keep-alive by default
+pub const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(90);
+
+/// Entry point to the streaming API, from [`ShodanProvider::stream`]
+pub struct ShodanStream {
+    inner: Arc<ShodanInner>,
+    heartbeat: Duration,
+}
+
+impl ShodanProvider {
+    /// Access Shodan's streaming API with this provider's key
+    #[must_use]
+    pub fn stream(&self) -> ShodanStream {
+        ShodanStream {
+            inner: Arc::clone(&self.inner),
+            heartbeat: DEFAULT_HEARTBEAT,
+        }
+    }
+}
+
+impl ShodanStream {
+    /// Fail the stream with [`I1Error::Timeout`] when neither a banner nor
+    /// a keep-alive arrives for this long (default: [`DEFAULT_HEARTBEAT`])
+    #[must_use]
+    pub const fn heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Banners for every network covered by the account's alerts
+    ///
+    /// Resolves once Shodan accepts the connection. The stream ends after
+    /// its first error, or when Shodan closes the connection.
+    pub async fn alerts(&self) -> Result<BoxStream<'static, Result<Banner>>> {
+        self.open("/shodan/alert").await
+    }
+
+    /// Banners for the networks covered by one alert
+    pub async fn alerts_for(&self, alert_id: &str) -> Result<BoxStream<'static, Result<Banner>>> {
+        self.open(&format!("/shodan/alert/{alert_id}")).await
+    }
+
+    async fn open(&self, endpoint: &str) -> Result<BoxStream<'static, Result<Banner>>> {
+        let url = format!("{}{}", self.inner.stream_url, endpoint);
+        debug!(url = %url, "Shodan stream connect");
+
+        let request = self
+            .inner
+            .http
+            .get(&url)
+            .query(&[("key", &self.inner.api_key)])
+            .send();
+        let response = tokio::time::timeout(self.heartbeat, request)
+            .await
+            .map_err(|_| I1Error::Timeout(self.heartbeat.as_secs()))?
+            .map_err(I1Error::from)?;
+        let response = ShodanProvider::check_status(response, endpoint).await?;
+
+        let lines = Lines {
+            response,
+            heartbeat: self.heartbeat,
+            buf: Vec::new(),
+            done: false,
+        };
+        Ok(stream::unfold(lines, |mut lines| async move {
+            let item = lines.next().await?;
+            Some((item, lines))
+        })
+        .boxed())
+    }
+}
+
+/// Splits a streaming response into banners
+struct Lines {
+    response: Response,
+    heartbeat: Duration,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl Lines {
+    async fn next(&mut self) -> Option<Result<Banner>> {
+        if self.done {
+            return None;
+        }
+        let item = self.read().await;
+        self.done = !matches!(item, Some(Ok(_)));
+        item
+    }
+
+    async fn read(&mut self) -> Option<Result<Banner>> {
+        loop {
+            while let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Some(serde_json::from_slice(&line).map_err(I1Error::from));
+            }
+
+            match tokio::time::timeout(self.heartbeat, self.response.chunk()).await {
+                Err(_) => return Some(Err(I1Error::Timeout(self.heartbeat.as_secs()))),
+                Ok(Err(e)) => return Some(Err(e.into())),
+                Ok(Ok(None)) => return None,
+                Ok(Ok(Some(chunk))) => self.buf.extend_from_slice(&chunk),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(server: &MockServer) -> ShodanProvider {
+        ShodanProvider::builder("secret-key")
+            .stream_url(server.uri())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn banners_are_read_line_by_line() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            r#"{"ip_str": "198.51.100.7", "port": 22, "product": "OpenSSH"}"#,
+            "\n\n\n",
+            r#"{"ip_str": "198.51.100.8", "port": 443, "transport": "tcp"}"#,
+            "\n",
+        );
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert/ABC123"))
+            .and(query_param("key", "secret-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let banners: Vec<Banner> = provider(&server)
+            .stream()
+            .alerts_for("ABC123")
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(banners.len(), 2);
+        assert_eq!(banners[0].ip_str, "198.51.100.7");
+        assert_eq!(banners[0].service.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(banners[1].service.port, 443);
+    }
+
+    #[tokio::test]
+    async fn stream_ends_after_a_bad_line() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "{\"ip_str\": \"198.51.100.7\", \"port\": 22}\nnot json\n{\"ip_str\": \"x\"}\n",
+            ))
+            .mount(&server)
+            .await;
+
+        let items: Vec<_> = provider(&server)
+            .stream()
+            .alerts()
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(matches!(items[1], Err(I1Error::Json(_))));
+    }
+
+    #[tokio::test]
+    async fn connect_failures_are_classified() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/shodan/alert/SLOW"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let stream = provider(&server).stream();
+        assert!(matches!(stream.alerts().await, Err(I1Error::Unauthorized)));
+
+        let silent = stream.heartbeat(Duration::from_millis(100));
+        let err = silent.alerts_for("SLOW").await.err().unwrap();
+        assert!(matches!(err, I1Error::Timeout(_)) && err.is_retryable());
+    }
+}
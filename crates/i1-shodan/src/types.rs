@@ -1,7 +1,28 @@
 //! Shodan-specific types.
 
+use i1_core::Service;
 use serde::{Deserialize, Serialize};
 
+/// A service banner from the streaming API, with the host it was seen on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Banner {
+    /// IP address as string
+    #[serde(default)]
+    pub ip_str: String,
+    /// Hostnames for the IP
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+    /// Organization that owns the IP
+    #[serde(default)]
+    pub org: Option<String>,
+    /// Autonomous System Number
+    #[serde(default)]
+    pub asn: Option<String>,
+    /// The service itself
+    #[serde(flatten)]
+    pub service: Service,
+}
+
 /// Shodan account information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShodanAccount {
@@ -0,0 +1,112 @@
+//! Record encoding benchmarks.
+//!
+//! Every reputation and intel answer goes through `txt_intel`, and every
+//! DNSBL lookup through the reversed-name helpers, so these run once per
+//! zone entry on each rebuild.
+//!
+//! ```text
+//! cargo bench -p i1-srv --bench encoding
+//! ```
+
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+
+use chrono::{TimeZone, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use i1_srv::encoding::dnsbl::{build_query_name, parse_query_name, reverse_ipv4};
+use i1_srv::encoding::txt_intel::{self, ComplexIntel, ReputationData, Tlp};
+
+/// A typical blocklisted address: fits the simple k=v format.
+fn simple_record() -> ReputationData {
+    ReputationData {
+        cc: Some("cn".into()),
+        asn: Some("AS4134".into()),
+        org: Some("Chinanet".into()),
+        ports: vec![22, 80, 443],
+        threat: Some("high".into()),
+        pattern: Some("ssh".into()),
+        hits: Some(1742),
+        reason: Some("ssh brute force".into()),
+        first_seen: Some(1_767_225_600),
+        last_seen: Some(1_768_435_200),
+        origin: Some("node-a".into()),
+        extra: BTreeMap::new(),
+    }
+}
+
+/// A record too large for k=v, so it overflows to CBOR.
+fn overflow_record() -> ReputationData {
+    let mut data = simple_record();
+    data.org = Some("Example Hosting International Limited Liability Company".into());
+    data.ports = (8000..8040).collect();
+    data.reason = Some("credential stuffing against webmail, then ssh brute force".into());
+    data.extra = (0..6)
+        .map(|i| (format!("note{i}"), format!("observed by sensor-{i:02}")))
+        .collect();
+    data
+}
+
+fn complex_record() -> ComplexIntel {
+    ComplexIntel {
+        malware_family: Some("mirai".into()),
+        campaigns: vec!["2026-q1-iot-sweep".into(), "telnet-wave-7".into()],
+        tags: vec!["botnet".into(), "c2".into(), "iot".into()],
+        tlp: Some(Tlp::Green),
+        confidence: Some(85),
+        first_seen: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).single(),
+        last_seen: Utc.with_ymd_and_hms(2026, 1, 15, 0, 0, 0).single(),
+        references: vec!["https://example.org/reports/mirai-2026".into()],
+        extra: BTreeMap::new(),
+    }
+}
+
+fn txt_intel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("txt_intel");
+    group.throughput(Throughput::Elements(1));
+
+    for (name, data) in [("simple", simple_record()), ("cbor", overflow_record())] {
+        let txt = txt_intel::encode(&data).expect("encode");
+        assert_eq!(txt.starts_with("cbor:"), name == "cbor");
+        group.bench_with_input(BenchmarkId::new("encode", name), &data, |b, data| {
+            b.iter(|| txt_intel::encode(black_box(data)));
+        });
+        group.bench_with_input(BenchmarkId::new("decode", name), &txt, |b, txt| {
+            b.iter(|| txt_intel::decode(black_box(txt)));
+        });
+    }
+
+    // Borrowed decoding, for read-only consumers of simple records.
+    let txt = txt_intel::encode(&simple_record()).expect("encode");
+    group.bench_function("decode_ref/simple", |b| {
+        b.iter(|| txt_intel::decode_ref(black_box(&txt)).map(|data| data.threat));
+    });
+
+    let intel = complex_record();
+    let txt = txt_intel::encode_complex(&intel).expect("encode_complex");
+    group.bench_function("encode_complex", |b| {
+        b.iter(|| txt_intel::encode_complex(black_box(&intel)));
+    });
+    group.bench_function("decode_complex", |b| {
+        b.iter(|| txt_intel::decode_complex(black_box(&txt)));
+    });
+    group.finish();
+}
+
+fn dnsbl(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dnsbl");
+    group.throughput(Throughput::Elements(1));
+
+    let ip = Ipv4Addr::new(203, 0, 113, 42);
+    group.bench_function("reverse_ipv4", |b| b.iter(|| reverse_ipv4(black_box(&ip))));
+    group.bench_function("build_query_name", |b| {
+        b.iter(|| build_query_name(black_box("203.0.113.42"), black_box("bl.i1.is")));
+    });
+    let query = build_query_name("203.0.113.42", "bl.i1.is").expect("query name");
+    group.bench_function("parse_query_name", |b| {
+        b.iter(|| parse_query_name(black_box(&query), black_box("bl.i1.is")));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, txt_intel, dnsbl);
+criterion_main!(benches);
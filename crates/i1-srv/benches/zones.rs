@@ -0,0 +1,102 @@
+//! Zone rebuild benchmarks.
+//!
+//! `build_zones` runs on every defense state change, so its cost grows
+//! with the blocklist. The snapshots here mix single addresses with
+//! metadata, CIDR ranges and structured intel the way a busy node's
+//! state does.
+//!
+//! ```text
+//! cargo bench -p i1-srv --bench zones
+//! ```
+
+use std::net::Ipv4Addr;
+
+use chrono::{Duration, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use i1_srv::authority::ttl_policy::ThreatLevel;
+use i1_srv::authority::zone_builder::{build_zones, BlockEntry, DefenseSnapshot};
+use i1_srv::config::ZoneConfig;
+use i1_srv::encoding::txt_intel::ComplexIntel;
+
+/// Blocklist sizes to build; the largest shows how rebuilds scale.
+const SIZES: [u32; 3] = [1_000, 10_000, 50_000];
+
+/// One in this many entries is a /24 instead of a single address.
+const CIDR_EVERY: u32 = 50;
+
+/// One in this many addresses also carries structured intel.
+const INTEL_EVERY: u32 = 10;
+
+const LEVELS: [ThreatLevel; 4] = [
+    ThreatLevel::Low,
+    ThreatLevel::Medium,
+    ThreatLevel::High,
+    ThreatLevel::Critical,
+];
+
+/// A defense snapshot with `size` blocklist entries.
+fn snapshot(size: u32) -> DefenseSnapshot {
+    let now = Utc::now();
+    let mut snapshot = DefenseSnapshot {
+        blocked_countries: vec!["kp".into(), "ru".into()],
+        blocked_asns: vec!["AS4134".into(), "AS9009".into()],
+        ..DefenseSnapshot::default()
+    };
+
+    for i in 0..size {
+        // Spread over 10.0.0.0/8 so names don't share long suffixes.
+        let ip = Ipv4Addr::from(0x0a00_0000 + i.wrapping_mul(2_654_435_761) % 0x00ff_ffff);
+        if i % CIDR_EVERY == 0 {
+            let [a, b, c, _] = ip.octets();
+            snapshot.blocked_ips.push(format!("{a}.{b}.{c}.0/24"));
+            continue;
+        }
+        let ip = ip.to_string();
+        snapshot.block_entries.insert(
+            ip.clone(),
+            BlockEntry {
+                threat: Some(LEVELS[(i % 4) as usize]),
+                hits: i % 500 + 1,
+                last_seen: Some(now - Duration::minutes(i64::from(i % 600))),
+                pattern: Some("ssh".into()),
+                ..BlockEntry::default()
+            },
+        );
+        if i % INTEL_EVERY == 0 {
+            snapshot.intel.insert(
+                ip.clone(),
+                ComplexIntel {
+                    malware_family: Some("mirai".into()),
+                    tags: vec!["botnet".into()],
+                    confidence: Some(80),
+                    ..ComplexIntel::default()
+                },
+            );
+        }
+        snapshot.blocked_ips.push(ip);
+    }
+    snapshot
+}
+
+fn zones(c: &mut Criterion) {
+    let config = ZoneConfig::default();
+    let mut group = c.benchmark_group("build_zones");
+    // Large builds take a while; fewer samples keep the run short.
+    group.sample_size(10);
+
+    for size in SIZES {
+        let snapshot = snapshot(size);
+        group.throughput(Throughput::Elements(u64::from(size)));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &snapshot,
+            |b, snapshot| {
+                b.iter(|| build_zones(snapshot, &config, 1).expect("build_zones"));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, zones);
+criterion_main!(benches);
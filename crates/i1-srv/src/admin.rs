@@ -0,0 +1,1328 @@
+//! Admin HTTP API.
+//!
+//! Lets an operator add an emergency block, or see what a node is serving,
+//! without editing the defense state file and waiting for a rebuild:
+//!
+//! | Request                            | Does                                   |
+//! |------------------------------------|----------------------------------------|
+//! | `POST /v1/blocklist/{ip}`          | block an address                       |
+//! | `DELETE /v1/blocklist/{ip}`        | lift a block added here                |
+//! | `GET /v1/zones/{zone}/records`     | list records, `?prefix=` to narrow     |
+//! | `GET /v1/status`                   | serial, counts, peers, TTL findings    |
+//! | `POST /v1/rebuild`                 | rebuild the zones now                  |
+//! | `PUT /v1/signals/{prefix}`         | publish another node's signal record   |
+//! | `PUT /v1/reputation/{ip}`          | block an address with its reputation   |
+//! | `GET /v1/openapi.json`             | this API, described in `OpenAPI` 3     |
+//!
+//! Blocks are kept in their own file, in the state file's format (see
+//! [`SnapshotSource::blocks_path`]), and applied by a forced rebuild. They
+//! take the same path as a change to the state file: a new serial, the
+//! zone store, IXFR to secondaries. Secondaries refuse mutations, since
+//! their zones come from the primary. A reputation update stores the
+//! address's threat, pattern, hits and sightings with the block, so its
+//! `rep.i1.is` record carries them from the next serial on. Updates hold
+//! an exclusive lock on `<file>.lock`, which a honeypot sink writing to
+//! the same file takes too.
+//!
+//! Signal records are checked and kept by
+//! [`NodeSignals`](crate::trust::node_signals::NodeSignals), in memory
+//! only: a node republishes its digest well within the maximum age anyway.
+//!
+//! The API listens on loopback and requires the [`AdminToken`] kept in a
+//! file only its owner can read, or with [`ClientAuth`] requires an i1-ca
+//! node certificate. Loopback alone would let any web page the operator
+//! visits post to the API through their browser, so requests carrying an
+//! `Origin` header are refused as well. Every mutation is logged with the
+//! caller: the local address, or the certificate's node name (its
+//! fingerprint when any node may call).
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, ORIGIN, WWW_AUTHENTICATE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Extension, Json, Router};
+use hickory_proto::rr::{LowerName, RecordType};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use ring::rand::{SecureRandom, SystemRandom};
+use rustls_pki_types::{CertificateDer, ServerName};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use crate::authority::transfer::TransferZone;
+use crate::authority::ttl_policy;
+use crate::authority::zone_builder;
+use crate::encoding::txt_intel::ReputationData;
+use crate::node::identity::NodeIdentity;
+use crate::server::ServedZones;
+use crate::sync::collector;
+use crate::sync::gossip::transport::GossipTls;
+use crate::sync::gossip::GossipNode;
+use crate::sync::reload::{RebuildHandle, SnapshotSource};
+use crate::trust::mesh;
+use crate::trust::node_signals::{NodeSignals, SignalRejected};
+use crate::trust::ttl_monitor::{ResolverReport, TtlReports};
+
+/// `OpenAPI` description of the API, served at `/v1/openapi.json`.
+pub const OPENAPI: &str = include_str!("admin_openapi.json");
+
+/// Records listed when the request gives no limit.
+const DEFAULT_RECORDS: usize = 1_000;
+
+/// Most records one listing returns.
+const MAX_RECORDS: usize = 10_000;
+
+/// How long a client gets to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Who made a request, as logged with every mutation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller(String);
+
+impl std::fmt::Display for Caller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The state behind the admin routes.
+///
+/// Clones share the zones and the lock that keeps mutations in order.
+#[derive(Clone)]
+pub struct AdminApi {
+    node: String,
+    zones: ServedZones,
+    source: SnapshotSource,
+    rebuild: Option<RebuildHandle>,
+    gossip: Option<GossipNode>,
+    ttl_reports: Option<TtlReports>,
+    started: Instant,
+    writes: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl std::fmt::Debug for AdminApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminApi")
+            .field("node", &self.node)
+            .field("primary", &self.rebuild.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AdminApi {
+    /// Report on `zones` as `node`; blocks go to `source.blocks_path`.
+    #[must_use]
+    pub fn new(node: &str, zones: ServedZones, source: SnapshotSource) -> Self {
+        Self {
+            node: node.to_string(),
+            zones,
+            source,
+            rebuild: None,
+            gossip: None,
+            ttl_reports: None,
+            started: Instant::now(),
+            writes: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Accept mutations, applying them through `rebuild`. Without one the
+    /// node is a secondary and refuses them.
+    #[must_use]
+    pub fn with_rebuild(mut self, rebuild: RebuildHandle) -> Self {
+        self.rebuild = Some(rebuild);
+        self
+    }
+
+    /// Report `gossip`'s members as peers.
+    #[must_use]
+    pub fn with_gossip(mut self, gossip: GossipNode) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Report the TTL monitor's findings per resolver.
+    #[must_use]
+    pub fn with_ttl_reports(mut self, reports: TtlReports) -> Self {
+        self.ttl_reports = Some(reports);
+        self
+    }
+
+    /// The API's routes.
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/v1/blocklist/{ip}", post(block).delete(unblock))
+            .route("/v1/zones/{zone}/records", get(records))
+            .route("/v1/status", get(status))
+            .route("/v1/rebuild", post(rebuild))
+            .route("/v1/signals/{prefix}", put(publish_signal))
+            .route("/v1/reputation/{ip}", put(publish_reputation))
+            .route("/v1/openapi.json", get(openapi))
+            .with_state(self)
+    }
+
+    /// The rebuild handle and blocks file, or why this node can't change.
+    fn writable(&self) -> Result<(&RebuildHandle, &std::path::Path), ApiError> {
+        let rebuild = self.primary()?;
+        let path = self.source.blocks_path.as_deref().ok_or_else(|| {
+            ApiError::new(StatusCode::CONFLICT, "no admin blocks file is configured")
+        })?;
+        Ok((rebuild, path))
+    }
+
+    /// The rebuild handle, or a conflict on a secondary.
+    fn primary(&self) -> Result<&RebuildHandle, ApiError> {
+        self.rebuild.as_ref().ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "this node is a secondary; make changes on its primary",
+            )
+        })
+    }
+
+    /// The rebuild handle and signal records, or why this node takes none.
+    fn signals(&self) -> Result<(&RebuildHandle, &NodeSignals), ApiError> {
+        let rebuild = self.primary()?;
+        let signals = self.source.node_signals.as_ref().ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "this node does not publish signal records for other nodes",
+            )
+        })?;
+        Ok((rebuild, signals))
+    }
+
+    fn serial(&self) -> Option<u32> {
+        self.zones.signal.serial()
+    }
+}
+
+/// An error response: a status and a JSON `{"error": ...}` body.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<crate::SrvError> for ApiError {
+    fn from(e: crate::SrvError) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl From<SignalRejected> for ApiError {
+    fn from(e: SignalRejected) -> Self {
+        let status = match e {
+            SignalRejected::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.message });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BlockResult {
+    ip: String,
+    changed: bool,
+    serial: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct RebuildResult {
+    serial: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignalUpdate {
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SignalResult {
+    name: String,
+    value: String,
+    ttl: u32,
+    serial: u32,
+}
+
+/// A `blocked_ips` entry with its metadata, as the collector reads it.
+#[derive(Debug, Serialize)]
+struct DetailedBlock<'a> {
+    ip: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    threat: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hits: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_seen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_seen: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReputationQuery {
+    ttl: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordQuery {
+    #[serde(default)]
+    prefix: String,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct RecordView {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    ttl: u32,
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ZoneRecords {
+    zone: String,
+    serial: Option<u32>,
+    records: Vec<RecordView>,
+    truncated: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ZoneStatus {
+    name: String,
+    serial: Option<u32>,
+    records: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Peer {
+    name: String,
+    addr: SocketAddr,
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    node: String,
+    role: &'static str,
+    serial: Option<u32>,
+    entries: u32,
+    zones: Vec<ZoneStatus>,
+    peers: Vec<Peer>,
+    ttl_monitor: Vec<ResolverReport>,
+    uptime_secs: u64,
+}
+
+/// `POST /v1/blocklist/{ip}`
+async fn block(
+    State(api): State<AdminApi>,
+    Extension(caller): Extension<Caller>,
+    Path(ip): Path<String>,
+) -> Result<Json<BlockResult>, ApiError> {
+    let ip = parse_ip(&ip)?;
+    let (rebuild, path) = api.writable()?;
+    let _writing = api.writes.lock().await;
+    let _locked = lock_blocks(path).await?;
+
+    let mut blocks = read_blocks(path)?;
+    if blocks.iter().any(|entry| listed_ip(entry) == Some(&ip)) {
+        return Ok(Json(BlockResult {
+            ip,
+            changed: false,
+            serial: api.serial(),
+        }));
+    }
+    blocks.push(serde_json::Value::String(ip.clone()));
+    write_blocks(path, &blocks)?;
+    info!(%caller, %ip, "admin API blocked address");
+    let serial = rebuild.rebuild().await?;
+    Ok(Json(BlockResult {
+        ip,
+        changed: true,
+        serial: Some(serial),
+    }))
+}
+
+/// `DELETE /v1/blocklist/{ip}`
+async fn unblock(
+    State(api): State<AdminApi>,
+    Extension(caller): Extension<Caller>,
+    Path(ip): Path<String>,
+) -> Result<Json<BlockResult>, ApiError> {
+    let ip = parse_ip(&ip)?;
+    let (rebuild, path) = api.writable()?;
+    let _writing = api.writes.lock().await;
+    let _locked = lock_blocks(path).await?;
+
+    let mut blocks = read_blocks(path)?;
+    let Some(pos) = blocks
+        .iter()
+        .position(|entry| listed_ip(entry) == Some(&ip))
+    else {
+        let in_state = match &api.source.state_path {
+            Some(state) => collector::load_snapshot(state)?.blocked_ips.contains(&ip),
+            None => false,
+        };
+        return Err(if in_state {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                format!("{ip} is blocked by the defense state file; lift it with `i1 defend`"),
+            )
+        } else {
+            ApiError::new(StatusCode::NOT_FOUND, format!("{ip} is not blocked"))
+        });
+    };
+    blocks.remove(pos);
+    write_blocks(path, &blocks)?;
+    info!(%caller, %ip, "admin API unblocked address");
+    let serial = rebuild.rebuild().await?;
+    Ok(Json(BlockResult {
+        ip,
+        changed: true,
+        serial: Some(serial),
+    }))
+}
+
+/// `GET /v1/zones/{zone}/records?prefix=&limit=`
+async fn records(
+    State(api): State<AdminApi>,
+    Path(zone): Path<String>,
+    Query(query): Query<RecordQuery>,
+) -> Result<Json<ZoneRecords>, ApiError> {
+    let wanted = zone.trim_end_matches('.');
+    let zone = api
+        .zones
+        .transfer_zones()
+        .into_iter()
+        .find(|z| same_name(z.name(), wanted))
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("not serving {wanted}")))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_RECORDS).min(MAX_RECORDS);
+    let prefix = query.prefix.to_ascii_lowercase();
+    let mut matching = zone.records().into_iter().filter(|record| {
+        record
+            .name()
+            .to_lowercase()
+            .to_string()
+            .starts_with(&prefix)
+    });
+    let records: Vec<RecordView> = matching
+        .by_ref()
+        .take(limit)
+        .map(|record| RecordView {
+            name: record.name().to_string(),
+            record_type: record.record_type().to_string(),
+            ttl: record.ttl(),
+            data: record.data().to_string(),
+        })
+        .collect();
+    let truncated = matching.next().is_some();
+
+    Ok(Json(ZoneRecords {
+        zone: zone.name().to_string(),
+        serial: zone
+            .soa_record()
+            .and_then(|soa| crate::authority::transfer::soa_serial(&soa)),
+        records,
+        truncated,
+    }))
+}
+
+/// `GET /v1/status`
+async fn status(State(api): State<AdminApi>) -> Json<Status> {
+    let zones = api
+        .zones
+        .transfer_zones()
+        .iter()
+        .map(|zone| {
+            let records = zone.records();
+            ZoneStatus {
+                name: zone.name().to_string(),
+                serial: records
+                    .first()
+                    .and_then(crate::authority::transfer::soa_serial),
+                records: records
+                    .iter()
+                    .filter(|record| record.record_type() != RecordType::SOA)
+                    .count(),
+            }
+        })
+        .collect();
+    let peers = api.gossip.as_ref().map_or_else(Vec::new, |gossip| {
+        gossip
+            .members()
+            .into_iter()
+            .filter(|member| member.name != gossip.name())
+            .map(|member| Peer {
+                name: member.name,
+                addr: member.addr,
+            })
+            .collect()
+    });
+    let entries =
+        zone_builder::read_signal(&api.zones.signal.records()).map_or(0, |signal| signal.entries);
+
+    Json(Status {
+        node: api.node.clone(),
+        role: if api.rebuild.is_some() {
+            "primary"
+        } else {
+            "secondary"
+        },
+        serial: api.serial(),
+        entries,
+        zones,
+        peers,
+        ttl_monitor: api
+            .ttl_reports
+            .as_ref()
+            .map_or_else(Vec::new, TtlReports::snapshot),
+        uptime_secs: api.started.elapsed().as_secs(),
+    })
+}
+
+/// `POST /v1/rebuild`
+async fn rebuild(
+    State(api): State<AdminApi>,
+    Extension(caller): Extension<Caller>,
+) -> Result<Json<RebuildResult>, ApiError> {
+    let (rebuild, _) = api.writable()?;
+    info!(%caller, "admin API forced a zone rebuild");
+    let serial = rebuild.rebuild().await?;
+    Ok(Json(RebuildResult { serial }))
+}
+
+/// `PUT /v1/signals/{prefix}`
+async fn publish_signal(
+    State(api): State<AdminApi>,
+    Extension(caller): Extension<Caller>,
+    Path(prefix): Path<String>,
+    Json(update): Json<SignalUpdate>,
+) -> Result<Json<SignalResult>, ApiError> {
+    let (rebuild, signals) = api.signals()?;
+    let signal = signals.upsert(&prefix, &update.value, chrono::Utc::now())?;
+    info!(%caller, prefix = %signal.prefix, "admin API published node signal record");
+    let serial = rebuild.rebuild().await?;
+    Ok(Json(SignalResult {
+        name: format!("{}.{}", signal.prefix, api.zones.signal.name()),
+        value: signal.value,
+        ttl: ttl_policy::SIGNAL_TTL,
+        serial,
+    }))
+}
+
+/// `PUT /v1/reputation/{ip}?ttl=`
+async fn publish_reputation(
+    State(api): State<AdminApi>,
+    Extension(caller): Extension<Caller>,
+    Path(ip): Path<String>,
+    Query(query): Query<ReputationQuery>,
+    Json(data): Json<ReputationData>,
+) -> Result<Json<BlockResult>, ApiError> {
+    let ip = parse_ip(&ip)?;
+    let entry = reputation_entry(&ip, &data, query.ttl)?;
+    let (rebuild, path) = api.writable()?;
+    let _writing = api.writes.lock().await;
+    let _locked = lock_blocks(path).await?;
+
+    let mut blocks = read_blocks(path)?;
+    match blocks
+        .iter()
+        .position(|entry| listed_ip(entry) == Some(&ip))
+    {
+        // Blocked by hand, for good: a reputation doesn't give it a TTL.
+        Some(pos) if blocks[pos].is_string() => {
+            return Ok(Json(BlockResult {
+                ip,
+                changed: false,
+                serial: api.serial(),
+            }));
+        }
+        Some(pos) => blocks[pos] = entry,
+        None => blocks.push(entry),
+    }
+    write_blocks(path, &blocks)?;
+    info!(%caller, %ip, pattern = ?data.pattern, "admin API recorded address reputation");
+    let serial = rebuild.rebuild().await?;
+    Ok(Json(BlockResult {
+        ip,
+        changed: true,
+        serial: Some(serial),
+    }))
+}
+
+/// `GET /v1/openapi.json`
+async fn openapi() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "application/json")], OPENAPI)
+}
+
+fn parse_ip(ip: &str) -> Result<String, ApiError> {
+    ip.parse::<IpAddr>().map(|ip| ip.to_string()).map_err(|_| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("'{ip}' is not an IP address"),
+        )
+    })
+}
+
+/// A detailed `blocked_ips` entry for `ip` from its reputation.
+fn reputation_entry(
+    ip: &str,
+    data: &ReputationData,
+    ttl: Option<u64>,
+) -> Result<serde_json::Value, ApiError> {
+    let seen = |secs: Option<i64>, field: &str| {
+        secs.map(|secs| {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+                .ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::BAD_REQUEST,
+                        format!("{field} {secs} is out of range"),
+                    )
+                })
+        })
+        .transpose()
+    };
+    let entry = DetailedBlock {
+        ip,
+        threat: data.threat.as_deref(),
+        pattern: data.pattern.as_deref(),
+        hits: data.hits,
+        reason: data.reason.as_deref(),
+        first_seen: seen(data.first_seen, "first_seen")?,
+        last_seen: seen(data.last_seen, "last_seen")?,
+        ttl,
+    };
+    serde_json::to_value(entry).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("failed to encode block: {e}"),
+        )
+    })
+}
+
+/// Address of a `blocked_ips` entry in either form.
+fn listed_ip(entry: &serde_json::Value) -> Option<&str> {
+    match entry {
+        serde_json::Value::String(ip) => Some(ip),
+        serde_json::Value::Object(entry) => entry.get("ip").and_then(serde_json::Value::as_str),
+        _ => None,
+    }
+}
+
+fn same_name(origin: &LowerName, wanted: &str) -> bool {
+    origin
+        .to_string()
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(wanted)
+}
+
+/// The blocks file's entries, plain or detailed, less those whose TTL has
+/// run out.
+fn read_blocks(path: &std::path::Path) -> crate::Result<Vec<serde_json::Value>> {
+    #[derive(Deserialize)]
+    struct BlocksFile {
+        #[serde(default)]
+        blocked_ips: Vec<serde_json::Value>,
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let file: BlocksFile = serde_json::from_str(&content)
+        .map_err(|e| crate::SrvError::State(format!("failed to parse admin blocks: {e}")))?;
+    let now = chrono::Utc::now();
+    Ok(file
+        .blocked_ips
+        .into_iter()
+        .filter(|entry| {
+            !(entry.is_object()
+                && serde_json::from_value::<zone_builder::BlockEntry>(entry.clone())
+                    .is_ok_and(|details| details.expired(now)))
+        })
+        .collect())
+}
+
+/// Lock the blocks file against writers in other processes, such as a
+/// honeypot sink, until the returned file is dropped.
+///
+/// Waits on the blocking pool while another process holds it, so the
+/// runtime's workers stay free.
+async fn lock_blocks(path: &std::path::Path) -> crate::Result<std::fs::File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(std::path::Path::to_path_buf);
+    tokio::task::spawn_blocking(move || {
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)?;
+        fs4::fs_std::FileExt::lock_exclusive(&file)?;
+        Ok(file)
+    })
+    .await
+    .map_err(|e| crate::SrvError::Io(std::io::Error::other(e)))?
+}
+
+/// Replace the blocks file, by rename so a rebuild never reads half of it.
+fn write_blocks(path: &std::path::Path, blocks: &[serde_json::Value]) -> crate::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let body = serde_json::to_vec_pretty(&serde_json::json!({
+        "schema_version": collector::SCHEMA_VERSION,
+        "blocked_ips": blocks,
+    }))
+    .map_err(|e| crate::SrvError::State(format!("failed to encode admin blocks: {e}")))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, body)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Bearer token loopback callers present in `Authorization`.
+#[derive(Clone)]
+pub struct AdminToken(String);
+
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AdminToken(..)")
+    }
+}
+
+impl AdminToken {
+    /// A fresh random token.
+    pub fn generate() -> crate::Result<Self> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| crate::SrvError::Config("system RNG failed".into()))?;
+        Ok(Self(bytes.iter().fold(
+            String::with_capacity(64),
+            |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            },
+        )))
+    }
+
+    /// Read the token from `path`, creating the file with a fresh token,
+    /// readable by its owner only, when it's missing.
+    ///
+    /// Refuses an empty file, or one other users can read.
+    pub fn load_or_create(path: &std::path::Path) -> crate::Result<Self> {
+        let err = |e: std::io::Error| {
+            crate::SrvError::Config(format!("admin token {}: {e}", path.display()))
+        };
+        let mut options = std::fs::File::options();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(err)?;
+        }
+        match options.open(path) {
+            Ok(mut file) => {
+                let token = Self::generate()?;
+                std::io::Write::write_all(&mut file, token.0.as_bytes()).map_err(err)?;
+                info!(path = %path.display(), "created admin API token");
+                return Ok(token);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(err(e)),
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(path).map_err(err)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                return Err(crate::SrvError::Config(format!(
+                    "admin token {} is readable by other users; chmod 600 it",
+                    path.display()
+                )));
+            }
+        }
+        let token = std::fs::read_to_string(path).map_err(err)?;
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(crate::SrvError::Config(format!(
+                "admin token {} is empty",
+                path.display()
+            )));
+        }
+        Ok(Self(token.to_string()))
+    }
+
+    /// The token, as callers send it: `Authorization: Bearer <token>`.
+    #[must_use]
+    pub fn secret(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether `header` is this token as a bearer credential, compared in
+    /// constant time.
+    fn accepts(&self, header: &[u8]) -> bool {
+        let Some(presented) = header.strip_prefix(b"Bearer ") else {
+            return false;
+        };
+        presented.len() == self.0.len()
+            && presented
+                .iter()
+                .zip(self.0.as_bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// How the API knows its callers.
+#[derive(Debug)]
+pub enum AdminAuth {
+    /// Loopback connections, each request presenting the token.
+    Token(AdminToken),
+    /// Connections presenting an allowed node certificate.
+    Mtls(ClientAuth),
+}
+
+/// Client certificate checks for an API served beyond loopback.
+pub struct ClientAuth {
+    acceptor: TlsAcceptor,
+    clients: Vec<ServerName<'static>>,
+}
+
+impl std::fmt::Debug for ClientAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientAuth")
+            .field("clients", &self.clients)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientAuth {
+    /// Present `identity` and accept clients trusted the way gossip peers
+    /// are; when `clients` is non-empty, only certificates issued to one
+    /// of those node names.
+    pub fn new(
+        tls: &GossipTls,
+        identity: &NodeIdentity,
+        clients: &[String],
+    ) -> crate::Result<Self> {
+        let clients = clients
+            .iter()
+            .map(|name| {
+                ServerName::try_from(name.clone()).map_err(|e| {
+                    crate::SrvError::Config(format!("invalid admin client name {name}: {e}"))
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Self {
+            acceptor: TlsAcceptor::from(tls.client_auth_config(identity, &[b"http/1.1"])?),
+            clients,
+        })
+    }
+
+    /// Who `leaf` belongs to, if it may call the API.
+    fn caller(&self, leaf: &CertificateDer<'_>) -> Option<Caller> {
+        if self.clients.is_empty() {
+            let fingerprint =
+                mesh::tlsa_hash(leaf)
+                    .iter()
+                    .fold(String::with_capacity(64), |mut hex, byte| {
+                        let _ = write!(hex, "{byte:02x}");
+                        hex
+                    });
+            return Some(Caller(format!("sha256:{fingerprint}")));
+        }
+        let cert = webpki::EndEntityCert::try_from(leaf).ok()?;
+        self.clients
+            .iter()
+            .find(|name| cert.verify_is_valid_for_subject_name(name).is_ok())
+            .map(|name| Caller(name.to_str().into_owned()))
+    }
+}
+
+/// Serve the admin API on `listener` until the task is dropped.
+///
+/// With [`AdminAuth::Token`] only loopback connections are accepted.
+pub async fn serve(listener: TcpListener, router: Router, auth: AdminAuth) {
+    let (token, auth) = match auth {
+        AdminAuth::Token(token) => (Some(Arc::new(token)), None),
+        AdminAuth::Mtls(auth) => (None, Some(Arc::new(auth))),
+    };
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "admin accept failed");
+                continue;
+            }
+        };
+        let router = router.clone();
+        let Some(auth) = auth.clone() else {
+            if !peer.ip().is_loopback() {
+                warn!(%peer, "refused admin connection from beyond loopback");
+                continue;
+            }
+            let caller = Caller(format!("local {peer}"));
+            tokio::spawn(serve_connection(
+                stream,
+                peer,
+                caller,
+                token.clone(),
+                router,
+            ));
+            continue;
+        };
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, auth.acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        debug!(%peer, error = %e, "admin TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        debug!(%peer, "admin TLS handshake timed out");
+                        return;
+                    }
+                };
+            let leaf = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(<[_]>::first);
+            let Some(caller) = leaf.and_then(|leaf| auth.caller(leaf)) else {
+                warn!(%peer, "admin client certificate is not an allowed caller");
+                return;
+            };
+            serve_connection(stream, peer, caller, None, router).await;
+        });
+    }
+}
+
+/// Serve one connection, tagging its requests with `caller`, and
+/// refusing those without `token` when one is required.
+async fn serve_connection<S>(
+    stream: S,
+    peer: SocketAddr,
+    caller: Caller,
+    token: Option<Arc<AdminToken>>,
+    router: Router,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |mut request: hyper::Request<Incoming>| {
+        let refused = refusal(&request, token.as_deref());
+        request.extensions_mut().insert(caller.clone());
+        let router = router.clone();
+        async move {
+            match refused {
+                Some(response) => Ok(response),
+                None => router.oneshot(request).await,
+            }
+        }
+    });
+    if let Err(e) = auto::Builder::new(TokioExecutor::new())
+        .serve_connection(TokioIo::new(stream), service)
+        .await
+    {
+        debug!(%peer, error = %e, "admin connection closed with error");
+    }
+}
+
+/// Why `request` is refused before routing, if it is: browsers send an
+/// `Origin`, which the API's callers don't, and loopback callers must
+/// present `token`.
+fn refusal<B>(request: &hyper::Request<B>, token: Option<&AdminToken>) -> Option<Response> {
+    if request.headers().contains_key(ORIGIN) {
+        return Some(
+            ApiError::new(StatusCode::FORBIDDEN, "cross-origin requests are refused")
+                .into_response(),
+        );
+    }
+    let token = token?;
+    let presented = request.headers().get(AUTHORIZATION);
+    if presented.is_some_and(|header| token.accepts(header.as_bytes())) {
+        return None;
+    }
+    let mut response =
+        ApiError::new(StatusCode::UNAUTHORIZED, "admin token required").into_response();
+    response.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        axum::http::HeaderValue::from_static("Bearer"),
+    );
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::zone_builder::DefenseSnapshot;
+    use crate::config::ZoneConfig;
+    use crate::node::identity::{CERT_FILE, KEY_FILE};
+    use crate::sync::reload::{RebuildOptions, ZoneRebuilder};
+    use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+    use serde_json::Value;
+    use std::net::Ipv4Addr;
+    use tempfile::TempDir;
+
+    /// A primary whose state file blocks 1.2.3.4, rebuilding in the background.
+    fn primary(dir: &TempDir) -> AdminApi {
+        let state_path = dir.path().join("defend_state.json");
+        std::fs::write(&state_path, r#"{"blocked_ips": ["1.2.3.4"]}"#).unwrap();
+        let source = SnapshotSource {
+            state_path: Some(state_path),
+            blocks_path: Some(dir.path().join("admin").join("blocks.json")),
+            ..Default::default()
+        };
+        let mut rebuilder = ZoneRebuilder::new(
+            ZoneConfig::default(),
+            source.clone(),
+            RebuildOptions::default(),
+        );
+        let zones = ServedZones::new(rebuilder.build(&source.load().unwrap()).unwrap(), 4);
+        let handle = rebuilder.handle();
+        let forever = Duration::from_secs(3600);
+        tokio::spawn(rebuilder.run(zones.clone(), forever, forever));
+        AdminApi::new("node1.srv.i1.is", zones, source).with_rebuild(handle)
+    }
+
+    /// Serve `api` on loopback, returning its URL and its token.
+    async fn serve_local(api: AdminApi) -> (String, AdminToken) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = AdminToken::generate().unwrap();
+        tokio::spawn(serve(
+            listener,
+            api.router(),
+            AdminAuth::Token(token.clone()),
+        ));
+        (format!("http://{addr}"), token)
+    }
+
+    /// A client presenting `token`.
+    fn authorized(token: &AdminToken) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        let bearer = format!("Bearer {}", token.secret());
+        headers.insert(AUTHORIZATION, bearer.parse().unwrap());
+        reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> (StatusCode, Value) {
+        let response = request.send().await.unwrap();
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        (status, response.json().await.unwrap())
+    }
+
+    async fn listed(client: &reqwest::Client, base: &str, prefix: &str) -> Vec<Value> {
+        let url = format!("{base}/v1/zones/bl.i1.is/records?prefix={prefix}");
+        let (status, body) = send(client.get(url)).await;
+        assert_eq!(status, StatusCode::OK);
+        body["records"].as_array().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_block_and_unblock() {
+        let dir = TempDir::new().unwrap();
+        let api = primary(&dir);
+        let first = api.serial().unwrap();
+        let (base, token) = serve_local(api).await;
+        let client = authorized(&token);
+        assert!(listed(&client, &base, "8.7.6.5.").await.is_empty());
+
+        let (status, body) = send(client.post(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["changed"], true);
+        assert!(body["serial"].as_u64().unwrap() > u64::from(first));
+        let records = listed(&client, &base, "8.7.6.5.").await;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["type"], "A");
+        assert_eq!(records[0]["data"], "127.0.0.1");
+
+        // Blocking again is a no-op.
+        let (_, body) = send(client.post(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        assert_eq!(body["changed"], false);
+
+        let (status, _) = send(client.delete(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(listed(&client, &base, "8.7.6.5.").await.is_empty());
+        let (status, _) = send(client.delete(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // The state file's blocks belong to `i1 defend`.
+        let (status, _) = send(client.delete(format!("{base}/v1/blocklist/1.2.3.4"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(listed(&client, &base, "4.3.2.1.").await.len(), 1);
+
+        let (status, _) = send(client.post(format!("{base}/v1/blocklist/not-an-ip"))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(client.get(format!("{base}/v1/zones/nope.example/records"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_loopback_requires_the_token() {
+        let dir = TempDir::new().unwrap();
+        let (base, token) = serve_local(primary(&dir)).await;
+        let url = format!("{base}/v1/blocklist/5.6.7.8");
+
+        let (status, _) = send(reqwest::Client::new().post(&url)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(reqwest::Client::new().post(&url).bearer_auth("guess")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // A page in the operator's browser can't post here, token or not.
+        let from_page = authorized(&token)
+            .post(&url)
+            .header(ORIGIN, "https://evil.example");
+        let (status, _) = send(from_page).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(authorized(&token).post(&url)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_token_file_is_private() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("i1").join("admin_token");
+        let created = AdminToken::load_or_create(&path).unwrap();
+        assert_eq!(created.secret().len(), 64);
+        let loaded = AdminToken::load_or_create(&path).unwrap();
+        assert_eq!(loaded.secret(), created.secret());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(AdminToken::load_or_create(&path).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocks_survive_rebuilds() {
+        let dir = TempDir::new().unwrap();
+        let api = primary(&dir);
+        let source = api.source.clone();
+        let (base, token) = serve_local(api).await;
+        let client = authorized(&token);
+
+        send(client.post(format!("{base}/v1/blocklist/2001:db8::1"))).await;
+        let snapshot: DefenseSnapshot = source.load().unwrap();
+        assert!(snapshot.blocked_ips.contains(&"1.2.3.4".to_string()));
+        assert!(snapshot.blocked_ips.contains(&"2001:db8::1".to_string()));
+
+        let (status, body) = send(client.post(format!("{base}/v1/rebuild"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, status_body) = send(client.get(format!("{base}/v1/status"))).await;
+        assert_eq!(status_body["serial"], body["serial"]);
+        assert_eq!(status_body["role"], "primary");
+        assert_eq!(status_body["entries"], 2);
+        assert_eq!(status_body["zones"].as_array().unwrap().len(), 8);
+        assert!(status_body["peers"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_secondary_refuses_mutations() {
+        let dir = TempDir::new().unwrap();
+        let api = primary(&dir);
+        let secondary = AdminApi::new("node2.srv.i1.is", api.zones, api.source);
+        let (base, token) = serve_local(secondary).await;
+        let client = authorized(&token);
+
+        let (status, _) = send(client.post(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = send(client.post(format!("{base}/v1/rebuild"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, body) = send(client.get(format!("{base}/v1/status"))).await;
+        assert_eq!(body["role"], "secondary");
+
+        let (status, spec) = send(client.get(format!("{base}/v1/openapi.json"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(spec["paths"]["/v1/blocklist/{ip}"]["post"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_honeypot_burst_reaches_reputation() {
+        use i1_honeypot::listener::{HoneypotEvent, HttpRequest};
+        use i1_honeypot::sink::{AdminPush, DefensePolicy, DefenseSink};
+
+        let dir = TempDir::new().unwrap();
+        let api = primary(&dir);
+        let blocks_path = api.source.blocks_path.clone().unwrap();
+        let (base, token) = serve_local(api).await;
+        let client = authorized(&token);
+
+        let policy = DefensePolicy {
+            burst_events: 5,
+            burst_window: Duration::from_secs(60),
+            ..DefensePolicy::default()
+        };
+        let mut sink = DefenseSink::new(dir.path().join("honeypot.json"), policy).unwrap();
+        let push = AdminPush::new(&base).with_token(token.secret());
+        let start = chrono::Utc::now() - chrono::Duration::minutes(5);
+        for i in 0..20 {
+            let event = HoneypotEvent::Request(HttpRequest {
+                peer: "203.0.113.9:40000".parse().unwrap(),
+                method: "GET".into(),
+                path: format!("/backup-{i}.sql"),
+                user_agent: None,
+                headers: Vec::new(),
+                at: start + chrono::Duration::seconds(i),
+            });
+            if let Some(ban) = sink.handle(&event).unwrap() {
+                push.send(&ban).await.unwrap();
+            }
+        }
+        assert_eq!(sink.counters().burst_bans, 1);
+
+        // A later plain block keeps the reputation's details.
+        send(client.post(format!("{base}/v1/blocklist/5.6.7.8"))).await;
+        let blocks: Value = serde_json::from_slice(&std::fs::read(&blocks_path).unwrap()).unwrap();
+        let entries = blocks["blocked_ips"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        let ban = &entries[0];
+        assert_eq!(ban["ip"], "203.0.113.9");
+        assert_eq!(ban["threat"], "medium");
+        assert_eq!(ban["pattern"], "web-scan");
+        assert_eq!(ban["hits"], 5);
+        assert_eq!(ban["reason"], "5 honeypot events in 1 min");
+        assert_eq!(ban["ttl"], 86_400);
+        let seen = |field: &str| {
+            ban[field]
+                .as_str()
+                .unwrap()
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap()
+                .timestamp()
+        };
+        assert_eq!(seen("first_seen"), start.timestamp());
+        assert_eq!(seen("last_seen"), start.timestamp() + 4);
+
+        let url = format!("{base}/v1/zones/rep.i1.is/records?prefix=9.113.0.203.");
+        let (status, body) = send(client.get(url)).await;
+        assert_eq!(status, StatusCode::OK);
+        let txt = body["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|record| record["type"] == "TXT")
+            .unwrap()["data"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(txt.contains("pattern=web-scan"), "{txt}");
+        assert!(txt.contains("reason=5 honeypot events"), "{txt}");
+
+        let (status, _) = send(
+            client
+                .put(format!("{base}/v1/reputation/203.0.113.9"))
+                .json(&serde_json::json!({ "first_seen": i64::MAX })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    /// Issue an i1-ca style node certificate under `ca`: the node's
+    /// identity, its certificate and key as one PEM, and the directory
+    /// holding them.
+    fn issue(ca: &(rcgen::Certificate, KeyPair), name: &str) -> (NodeIdentity, String, TempDir) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        params.extended_key_usages = vec![
+            ExtendedKeyUsagePurpose::ServerAuth,
+            ExtendedKeyUsagePurpose::ClientAuth,
+        ];
+        let cert = params.signed_by(&key, &ca.0, &ca.1).unwrap();
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(CERT_FILE), cert.pem()).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), key.serialize_pem()).unwrap();
+        let pem = format!("{}{}", cert.pem(), key.serialize_pem());
+        (NodeIdentity::load_dir(dir.path()).unwrap(), pem, dir)
+    }
+
+    #[tokio::test]
+    async fn test_mtls_requires_an_allowed_client() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = (params.self_signed(&ca_key).unwrap(), ca_key);
+        let (server, _, _server_dir) = issue(&ca, "node1.srv.i1.is");
+        let (_, admin_pem, _admin_dir) = issue(&ca, "ops.srv.i1.is");
+        let (_, other_pem, _other_dir) = issue(&ca, "node2.srv.i1.is");
+
+        let tls = GossipTls::new(&server, vec![ca.0.der().clone()], Vec::new()).unwrap();
+        let auth = ClientAuth::new(&tls, &server, &["ops.srv.i1.is".to_string()]).unwrap();
+        let dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(
+            listener,
+            primary(&dir).router(),
+            AdminAuth::Mtls(auth),
+        ));
+
+        let root = reqwest::Certificate::from_der(ca.0.der()).unwrap();
+        let client = |pem: Option<&str>| {
+            let mut builder = reqwest::Client::builder()
+                .use_rustls_tls()
+                .add_root_certificate(root.clone())
+                .resolve("node1.srv.i1.is", addr);
+            if let Some(pem) = pem {
+                builder = builder.identity(reqwest::Identity::from_pem(pem.as_bytes()).unwrap());
+            }
+            builder.build().unwrap()
+        };
+        let url = format!("https://node1.srv.i1.is:{}/v1/rebuild", addr.port());
+
+        let (status, _) = send(client(Some(&admin_pem)).post(&url)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(client(Some(&other_pem)).post(&url).send().await.is_err());
+        assert!(client(None).post(&url).send().await.is_err());
+    }
+}
@@ -0,0 +1,673 @@
+//! Blocklist authority: exact-IP DNSBL records plus blocked CIDR ranges.
+//!
+//! Expanding a blocked range like `203.0.113.0/24` into one record per
+//! address is wasteful for IPv4 and impossible for IPv6 prefixes. Instead
+//! the blocklist zone keeps blocked prefixes in a bit trie and synthesizes
+//! the A record at query time when the queried reversed IP falls inside one.
+//!
+//! Exact-IP records in the backing [`InMemoryAuthority`] always win, so a
+//! more specific classification (e.g. `Malicious`) is never masked by the
+//! range code. SOA/NS answers and NXDOMAIN for unlisted IPs come straight
+//! from the store.
+//!
+//! NXDOMAIN names are remembered in a [`NegativeCache`], so a repeated
+//! query for an unlisted IP is answered without either lookup. The cache
+//! carries over when the zone is swapped, emptied so new listings show at
+//! once.
+//!
+//! In a signed zone the NSEC chain can't cover synthesized names, so
+//! those answers are signed at query time with the zone's keys.
+//!
+//! Each blocked range is also published as a TXT record at
+//! `_cidr.<zone>` (`cidr=203.0.113.0/24;code=1`), so the ranges travel
+//! with zone transfers and a secondary can rebuild its trie from them.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hickory_proto::dnssec::SigSigner;
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::rdata::{A, TXT};
+use hickory_proto::rr::{LowerName, Name, RData, Record, RecordSet, RecordType};
+use hickory_server::authority::{
+    AuthLookup, Authority, LookupControlFlow, LookupError, LookupOptions, LookupRecords,
+    MessageRequest, Nsec3QueryInfo, UpdateResult, ZoneType,
+};
+use hickory_server::dnssec::NxProofKind;
+use hickory_server::server::RequestInfo;
+use hickory_server::store::in_memory::InMemoryAuthority;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::authority::dnssec::{self, ZoneSigningKeys};
+use crate::authority::threat_authority::{self, NegativeCache};
+use crate::encoding::dnsbl::{self, DnsblCode};
+
+/// A CIDR block such as `203.0.113.0/24` or `2001:db8::/32`.
+///
+/// Host bits below the prefix are ignored, so `203.0.113.7/24` is the
+/// same block as `203.0.113.0/24`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Parse `addr/len` notation.
+    pub fn parse(s: &str) -> crate::Result<Self> {
+        let (addr, len) = s
+            .split_once('/')
+            .ok_or_else(|| crate::SrvError::Zone(format!("CIDR '{s}' has no prefix length")))?;
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|e| crate::SrvError::Zone(format!("invalid CIDR address '{s}': {e}")))?;
+        let prefix_len: u8 = len
+            .parse()
+            .map_err(|e| crate::SrvError::Zone(format!("invalid CIDR length '{s}': {e}")))?;
+        if prefix_len > max_prefix_len(&addr) {
+            return Err(crate::SrvError::Zone(format!(
+                "CIDR prefix length out of range: '{s}'"
+            )));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// Network address (host bits cleared).
+    #[must_use]
+    pub fn network(&self) -> IpAddr {
+        let keep = self.prefix_len;
+        match self.addr {
+            IpAddr::V4(v4) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(keep)).unwrap_or(0);
+                IpAddr::V4((u32::from(v4) & mask).into())
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(keep)).unwrap_or(0);
+                IpAddr::V6((u128::from(v6) & mask).into())
+            }
+        }
+    }
+
+    /// Prefix length in bits.
+    #[must_use]
+    pub const fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Whether `ip` falls inside this block.
+    #[must_use]
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        if self.addr.is_ipv4() != ip.is_ipv4() {
+            return false;
+        }
+        // Keys are left-aligned, so the mask is the same for both families.
+        let mask = u128::MAX
+            .checked_shl(128 - u32::from(self.prefix_len))
+            .unwrap_or(0);
+        address_bits(ip) & mask == address_bits(&self.addr) & mask
+    }
+}
+
+impl From<IpAddr> for Cidr {
+    /// A single-address block (`/32` or `/128`).
+    fn from(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            prefix_len: max_prefix_len(&addr),
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network(), self.prefix_len)
+    }
+}
+
+const fn max_prefix_len(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Address as a left-aligned 128-bit key (IPv4 occupies the top 32 bits).
+fn address_bits(addr: &IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(v4) => u128::from(u32::from(*v4)) << 96,
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+/// One node of the binary prefix trie.
+#[derive(Debug, Default, Clone)]
+struct TrieNode {
+    children: [Option<usize>; 2],
+    code: Option<DnsblCode>,
+}
+
+/// Binary trie of blocked prefixes for one address family.
+///
+/// Lookups return the code of the longest matching prefix.
+#[derive(Debug, Clone)]
+struct PrefixTrie {
+    nodes: Vec<TrieNode>,
+}
+
+impl Default for PrefixTrie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl PrefixTrie {
+    fn insert(&mut self, bits: u128, prefix_len: u8, code: DnsblCode) {
+        let mut node = 0;
+        for depth in 0..prefix_len {
+            let bit = usize::from(bits >> (127 - depth) & 1 == 1);
+            node = if let Some(child) = self.nodes[node].children[bit] {
+                child
+            } else {
+                self.nodes.push(TrieNode::default());
+                let child = self.nodes.len() - 1;
+                self.nodes[node].children[bit] = Some(child);
+                child
+            };
+        }
+        self.nodes[node].code = Some(code);
+    }
+
+    fn longest_match(&self, bits: u128, max_len: u8) -> Option<DnsblCode> {
+        let mut node = 0;
+        let mut best = self.nodes[0].code;
+        for depth in 0..max_len {
+            let bit = usize::from(bits >> (127 - depth) & 1 == 1);
+            let Some(child) = self.nodes[node].children[bit] else {
+                break;
+            };
+            node = child;
+            best = self.nodes[node].code.or(best);
+        }
+        best
+    }
+}
+
+/// Blocklist zone authority with CIDR-aware answers.
+pub struct BlocklistAuthority {
+    store: InMemoryAuthority,
+    origin: Name,
+    v4: PrefixTrie,
+    v6: PrefixTrie,
+    cidr_count: usize,
+    signers: Vec<SigSigner>,
+    negative: Arc<NegativeCache>,
+}
+
+impl BlocklistAuthority {
+    /// Wrap an exact-IP store (as built by [`threat_authority::create_zone`]).
+    ///
+    /// Ranges already published under `_cidr.<zone>` (e.g. in a transferred
+    /// zone) are loaded into the trie.
+    #[must_use]
+    pub fn new(mut store: InMemoryAuthority) -> Self {
+        let origin = Name::from(store.origin().clone());
+        let published: Vec<(Cidr, DnsblCode)> = cidr_record_name(&origin)
+            .map(|name| {
+                store
+                    .records_get_mut()
+                    .values()
+                    .filter(|rrset| rrset.name() == &name && rrset.record_type() == RecordType::TXT)
+                    .flat_map(|rrset| rrset.records_without_rrsigs())
+                    .filter_map(|r| decode_cidr_txt(&r.data().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut authority = Self {
+            store,
+            origin,
+            v4: PrefixTrie::default(),
+            v6: PrefixTrie::default(),
+            cidr_count: 0,
+            signers: Vec::new(),
+            negative: Arc::default(),
+        };
+        for (cidr, code) in published {
+            authority.insert_prefix(cidr, code);
+        }
+        authority
+    }
+
+    /// Mutable access to the exact-IP record store.
+    pub const fn store_mut(&mut self) -> &mut InMemoryAuthority {
+        &mut self.store
+    }
+
+    /// Sign the stored zone and keep the keys for synthesized answers.
+    pub fn sign_with(&mut self, keys: &ZoneSigningKeys) -> crate::Result<()> {
+        keys.sign_zone(&mut self.store)?;
+        self.signers = keys.signers(self.store.origin())?;
+        Ok(())
+    }
+
+    /// Block every address inside `cidr` with the given code.
+    pub fn insert_cidr(&mut self, cidr: Cidr, code: DnsblCode, serial: u32) -> crate::Result<()> {
+        let name = cidr_record_name(&self.origin)?;
+        self.store.upsert_mut(
+            Record::from_rdata(
+                name,
+                threat_authority::dnsbl_ttl(code),
+                RData::TXT(TXT::new(vec![encode_cidr_txt(cidr, code)])),
+            ),
+            serial,
+        );
+        self.insert_prefix(cidr, code);
+        Ok(())
+    }
+
+    fn insert_prefix(&mut self, cidr: Cidr, code: DnsblCode) {
+        let bits = address_bits(&cidr.network());
+        match cidr.addr {
+            IpAddr::V4(_) => self.v4.insert(bits, cidr.prefix_len, code),
+            IpAddr::V6(_) => self.v6.insert(bits, cidr.prefix_len, code),
+        }
+        self.cidr_count += 1;
+    }
+
+    /// Cache of recent NXDOMAIN names, shared with earlier and later
+    /// versions of the zone.
+    #[must_use]
+    pub const fn negative_cache(&self) -> &Arc<NegativeCache> {
+        &self.negative
+    }
+
+    /// Take over `previous`'s negative cache, emptied: names it held may
+    /// be listed now.
+    pub fn inherit_negative_cache(&mut self, previous: &Self) {
+        previous.negative.clear();
+        self.negative = Arc::clone(&previous.negative);
+    }
+
+    /// Number of CIDR blocks inserted.
+    #[must_use]
+    pub const fn cidr_count(&self) -> usize {
+        self.cidr_count
+    }
+
+    /// Code of the longest blocked prefix containing `ip`, if any.
+    #[must_use]
+    pub fn cidr_code(&self, ip: &IpAddr) -> Option<DnsblCode> {
+        let bits = address_bits(ip);
+        match ip {
+            IpAddr::V4(_) => self.v4.longest_match(bits, 32),
+            IpAddr::V6(_) => self.v6.longest_match(bits, 128),
+        }
+    }
+
+    /// Map a query name to a blocked prefix code.
+    ///
+    /// Names that aren't a full reversed IPv4/IPv6 address never match.
+    fn match_query(&self, name: &LowerName) -> Option<DnsblCode> {
+        let ip: IpAddr = dnsbl::parse_query_name(&name.to_string(), &self.origin.to_string())
+            .ok()?
+            .parse()
+            .ok()?;
+        self.cidr_code(&ip)
+    }
+
+    /// Build the A record set answering a query inside a blocked prefix,
+    /// signed when the zone is.
+    fn synthesize(&self, name: &LowerName, code: DnsblCode) -> crate::Result<RecordSet> {
+        let mut rrset = RecordSet::with_ttl(
+            Name::from(name.clone()),
+            RecordType::A,
+            threat_authority::dnsbl_ttl(code),
+        );
+        rrset.add_rdata(RData::A(A::from(code.to_ipv4())));
+        if !self.signers.is_empty() {
+            dnssec::sign_rrset(&mut rrset, &self.signers, Utc::now())?;
+        }
+        Ok(rrset)
+    }
+}
+
+/// Owner name of the published range records (`_cidr.<zone>`).
+fn cidr_record_name(origin: &Name) -> crate::Result<Name> {
+    Name::parse("_cidr", Some(origin))
+        .map_err(|e| crate::SrvError::Zone(format!("invalid CIDR record name: {e}")))
+}
+
+fn encode_cidr_txt(cidr: Cidr, code: DnsblCode) -> String {
+    format!("cidr={cidr};code={}", code as u8)
+}
+
+fn decode_cidr_txt(txt: &str) -> Option<(Cidr, DnsblCode)> {
+    let mut cidr = None;
+    let mut code = None;
+    for pair in txt.split(';') {
+        match pair.split_once('=')? {
+            ("cidr", v) => cidr = Cidr::parse(v).ok(),
+            ("code", v) => code = v.parse().ok().and_then(DnsblCode::from_u8),
+            _ => {}
+        }
+    }
+    Some((cidr?, code?))
+}
+
+#[async_trait]
+impl Authority for BlocklistAuthority {
+    type Lookup = AuthLookup;
+
+    fn zone_type(&self) -> ZoneType {
+        self.store.zone_type()
+    }
+
+    fn is_axfr_allowed(&self) -> bool {
+        self.store.is_axfr_allowed()
+    }
+
+    async fn update(&self, update: &MessageRequest) -> UpdateResult<bool> {
+        let result = self.store.update(update).await;
+        if matches!(result, Ok(true)) {
+            self.negative.clear();
+        }
+        result
+    }
+
+    fn origin(&self) -> &LowerName {
+        self.store.origin()
+    }
+
+    async fn lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        if self.negative.contains(name) {
+            return LookupControlFlow::Continue(Err(LookupError::ResponseCode(
+                ResponseCode::NXDomain,
+            )));
+        }
+        let result = self.store.lookup(name, rtype, lookup_options).await;
+
+        // Only names the store has never heard of fall through to CIDRs.
+        if !matches!(
+            result,
+            LookupControlFlow::Continue(Err(LookupError::ResponseCode(ResponseCode::NXDomain)))
+        ) {
+            return result;
+        }
+        let Some(code) = self.match_query(name) else {
+            self.negative.insert(name);
+            return result;
+        };
+
+        if rtype == RecordType::A {
+            match self.synthesize(name, code) {
+                Ok(rrset) => LookupControlFlow::Continue(Ok(AuthLookup::answers(
+                    LookupRecords::new(lookup_options, Arc::new(rrset)),
+                    None,
+                ))),
+                Err(e) => {
+                    warn!(%name, error = %e, "failed to sign CIDR answer");
+                    LookupControlFlow::Continue(Err(LookupError::ResponseCode(
+                        ResponseCode::ServFail,
+                    )))
+                }
+            }
+        } else {
+            LookupControlFlow::Continue(Err(LookupError::NameExists))
+        }
+    }
+
+    async fn search(
+        &self,
+        request_info: RequestInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        match request_info.query.query_type() {
+            // Zone-level queries never involve CIDR synthesis.
+            RecordType::SOA | RecordType::AXFR => {
+                self.store.search(request_info, lookup_options).await
+            }
+            rtype => {
+                self.lookup(request_info.query.name(), rtype, lookup_options)
+                    .await
+            }
+        }
+    }
+
+    async fn get_nsec_records(
+        &self,
+        name: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.store.get_nsec_records(name, lookup_options).await
+    }
+
+    async fn get_nsec3_records(
+        &self,
+        info: Nsec3QueryInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.store.get_nsec3_records(info, lookup_options).await
+    }
+
+    fn nx_proof_kind(&self) -> Option<&NxProofKind> {
+        self.store.nx_proof_kind()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZONE: &str = "bl.i1.is.";
+
+    fn authority_with(cidrs: &[&str]) -> BlocklistAuthority {
+        let origin = Name::parse(ZONE, None).unwrap();
+        let store = threat_authority::create_zone(&origin, 1).unwrap();
+        let mut authority = BlocklistAuthority::new(store);
+        for cidr in cidrs {
+            authority
+                .insert_cidr(Cidr::parse(cidr).unwrap(), DnsblCode::Listed, 1)
+                .unwrap();
+        }
+        authority
+    }
+
+    /// Resolve an IP through the authority, returning the A answer.
+    async fn query(authority: &BlocklistAuthority, ip: &str) -> Option<RData> {
+        let name = dnsbl::build_query_name(ip, ZONE).unwrap();
+        let name = LowerName::from(Name::parse(&name, None).unwrap());
+        match authority
+            .lookup(&name, RecordType::A, LookupOptions::default())
+            .await
+        {
+            LookupControlFlow::Continue(Ok(lookup)) => {
+                lookup.iter().next().map(|r| r.data().clone())
+            }
+            LookupControlFlow::Continue(Err(e)) => {
+                assert!(e.is_nx_domain(), "unexpected lookup error: {e}");
+                None
+            }
+            other => panic!("unexpected control flow: {other}"),
+        }
+    }
+
+    const LISTED: Option<RData> = Some(RData::A(A(DnsblCode::Listed.to_ipv4())));
+
+    #[test]
+    fn test_cidr_parse() {
+        let cidr = Cidr::parse("203.0.113.77/24").unwrap();
+        assert_eq!(cidr.to_string(), "203.0.113.0/24");
+        assert_eq!(
+            Cidr::parse("2001:db8::1/32").unwrap().to_string(),
+            "2001:db8::/32"
+        );
+        assert_eq!(Cidr::parse("0.0.0.0/0").unwrap().prefix_len(), 0);
+        assert!(Cidr::parse("203.0.113.0").is_err());
+        assert!(Cidr::parse("203.0.113.0/33").is_err());
+        assert!(Cidr::parse("nope/24").is_err());
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let cidr = Cidr::parse("198.51.96.0/20").unwrap();
+        assert!(cidr.contains(&"198.51.111.255".parse().unwrap()));
+        assert!(!cidr.contains(&"198.51.112.0".parse().unwrap()));
+        assert!(!cidr.contains(&"::1".parse().unwrap()));
+
+        let host = Cidr::from("10.0.0.1".parse::<IpAddr>().unwrap());
+        assert!(host.contains(&"10.0.0.1".parse().unwrap()));
+        assert!(!host.contains(&"10.0.0.2".parse().unwrap()));
+        assert!(Cidr::parse("::/0")
+            .unwrap()
+            .contains(&"2001:db8::1".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_ranges_rebuilt_from_published_records() {
+        let mut authority = authority_with(&["203.0.113.0/24", "2001:db8::/32"]);
+        let records: Vec<Record> = authority
+            .store_mut()
+            .records_get_mut()
+            .values()
+            .flat_map(|rrset| rrset.records_without_rrsigs().cloned().collect::<Vec<_>>())
+            .collect();
+
+        let origin = Name::parse(ZONE, None).unwrap();
+        let mut store = InMemoryAuthority::empty(origin, ZoneType::Secondary, false, None);
+        for record in records {
+            store.upsert_mut(record, 1);
+        }
+        let rebuilt = BlocklistAuthority::new(store);
+        assert_eq!(rebuilt.cidr_count(), 2);
+        assert_eq!(query(&rebuilt, "203.0.113.50").await, LISTED);
+        assert_eq!(query(&rebuilt, "2001:db8::1").await, LISTED);
+    }
+
+    #[tokio::test]
+    async fn test_slash24_boundaries() {
+        let authority = authority_with(&["203.0.113.0/24"]);
+        assert_eq!(query(&authority, "203.0.113.0").await, LISTED);
+        assert_eq!(query(&authority, "203.0.113.128").await, LISTED);
+        assert_eq!(query(&authority, "203.0.113.255").await, LISTED);
+        assert_eq!(query(&authority, "203.0.112.255").await, None);
+        assert_eq!(query(&authority, "203.0.114.0").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_slash20_boundaries() {
+        let authority = authority_with(&["198.51.96.0/20"]);
+        assert_eq!(query(&authority, "198.51.96.0").await, LISTED);
+        assert_eq!(query(&authority, "198.51.100.42").await, LISTED);
+        assert_eq!(query(&authority, "198.51.111.255").await, LISTED);
+        assert_eq!(query(&authority, "198.51.95.255").await, None);
+        assert_eq!(query(&authority, "198.51.112.0").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_ipv6_prefix() {
+        let authority = authority_with(&["2001:db8:abcd::/48"]);
+        assert_eq!(query(&authority, "2001:db8:abcd::").await, LISTED);
+        assert_eq!(
+            query(&authority, "2001:db8:abcd:ffff:ffff:ffff:ffff:ffff").await,
+            LISTED
+        );
+        assert_eq!(query(&authority, "2001:db8:abce::").await, None);
+        // v4 and v6 tries are separate.
+        assert_eq!(query(&authority, "32.1.13.184").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_exact_records_take_precedence() {
+        let mut authority = authority_with(&["203.0.113.0/24"]);
+        threat_authority::insert_dnsbl_record(
+            authority.store_mut(),
+            &"203.0.113.9".parse().unwrap(),
+            DnsblCode::Malicious,
+            ZONE,
+            1,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query(&authority, "203.0.113.9").await,
+            Some(RData::A(A::from(DnsblCode::Malicious.to_ipv4())))
+        );
+        assert_eq!(query(&authority, "203.0.113.10").await, LISTED);
+    }
+
+    #[tokio::test]
+    async fn test_misses_are_cached_until_swap() {
+        use crate::authority::transfer::ServedZone;
+
+        let slot = ServedZone::new(authority_with(&["203.0.113.0/24"]), 4);
+        let authority = slot.authority();
+        let cache = authority.negative_cache().clone();
+        assert_eq!(query(&authority, "192.0.2.1").await, None);
+        assert_eq!(query(&authority, "192.0.2.1").await, None);
+        // Listed addresses aren't cached.
+        assert_eq!(query(&authority, "203.0.113.5").await, LISTED);
+        assert_eq!(query(&authority, "203.0.113.5").await, LISTED);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.stats(), (1, 3));
+
+        // The next version lists the address; the shared cache is emptied.
+        let mut next = authority_with(&["203.0.113.0/24"]);
+        threat_authority::insert_dnsbl_record(
+            next.store_mut(),
+            &"192.0.2.1".parse().unwrap(),
+            DnsblCode::Malicious,
+            ZONE,
+            2,
+        )
+        .unwrap();
+        slot.replace(next);
+        let authority = slot.authority();
+        assert!(Arc::ptr_eq(authority.negative_cache(), &cache));
+        assert_eq!(
+            query(&authority, "192.0.2.1").await,
+            Some(RData::A(A::from(DnsblCode::Malicious.to_ipv4())))
+        );
+        assert_eq!(cache.stats(), (1, 4));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_non_a_query_inside_prefix_is_nodata() {
+        let authority = authority_with(&["203.0.113.0/24"]);
+        let name = LowerName::from(Name::parse("1.113.0.203.bl.i1.is.", None).unwrap());
+        let result = authority
+            .lookup(&name, RecordType::TXT, LookupOptions::default())
+            .await;
+        assert!(matches!(
+            result,
+            LookupControlFlow::Continue(Err(LookupError::NameExists))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_soa_unaffected() {
+        let authority = authority_with(&["0.0.0.0/0"]);
+        let soa = authority.soa().await.unwrap();
+        let record = soa.iter().next().unwrap();
+        assert_eq!(record.record_type(), RecordType::SOA);
+
+        // Partial reversed names are not addresses, so no synthesis.
+        let name = LowerName::from(Name::parse("113.0.203.bl.i1.is.", None).unwrap());
+        let result = authority
+            .lookup(&name, RecordType::A, LookupOptions::default())
+            .await;
+        assert!(matches!(
+            result,
+            LookupControlFlow::Continue(Err(LookupError::ResponseCode(ResponseCode::NXDomain)))
+        ));
+    }
+}
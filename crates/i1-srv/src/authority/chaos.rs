@@ -0,0 +1,161 @@
+//! CHAOS-class identity records (RFC 4892) and NSID (RFC 5001).
+//!
+//! `dig CH TXT version.bind @node` and friends are how operators and
+//! monitoring tell servers apart without touching the threat zones.
+//! [`ChaosResponder`] answers `version.bind`, `hostname.bind` and
+//! `id.server`, and [`NsidResponse`] adds the node's name to responses
+//! whose request carried an empty NSID option. With `hide` set, CHAOS
+//! queries are refused and no NSID is sent.
+
+use async_trait::async_trait;
+use hickory_proto::op::{Edns, Header, ResponseCode};
+use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+use hickory_proto::rr::rdata::TXT;
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_server::authority::{MessageResponse, MessageResponseBuilder};
+use hickory_server::server::{Request, ResponseHandler, ResponseInfo};
+use tracing::warn;
+
+use super::transfer::{send_error, serve_failed};
+use crate::config::ServerConfig;
+
+/// Names answered in the CHAOS class.
+const VERSION_BIND: &str = "version.bind.";
+const HOSTNAME_BIND: &str = "hostname.bind.";
+const ID_SERVER: &str = "id.server.";
+
+/// Answers CHAOS-class TXT queries about the node.
+#[derive(Debug, Clone)]
+pub struct ChaosResponder {
+    version: String,
+    hostname: String,
+    hide: bool,
+}
+
+impl ChaosResponder {
+    /// The answers configured in `config.chaos`, falling back to the crate
+    /// version and the node prefix (the first label of `node_name`).
+    #[must_use]
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            version: config
+                .chaos
+                .version
+                .clone()
+                .unwrap_or_else(|| format!("i1-srv {}", env!("CARGO_PKG_VERSION"))),
+            hostname: config
+                .chaos
+                .hostname
+                .clone()
+                .unwrap_or_else(|| node_prefix(&config.node_name).to_string()),
+            hide: config.chaos.hide,
+        }
+    }
+
+    /// The TXT answer for a CHAOS `name`, if it is one we know.
+    fn text(&self, name: &str) -> Option<&str> {
+        match name.to_ascii_lowercase().as_str() {
+            VERSION_BIND => Some(&self.version),
+            HOSTNAME_BIND | ID_SERVER => Some(&self.hostname),
+            _ => None,
+        }
+    }
+
+    /// The NSID to send back, when `request` asked for one.
+    #[must_use]
+    pub fn nsid(&self, request: &Request) -> Option<Vec<u8>> {
+        let asked = request
+            .edns()
+            .is_some_and(|edns| edns.option(EdnsCode::NSID).is_some());
+        (asked && !self.hide).then(|| self.hostname.as_bytes().to_vec())
+    }
+
+    /// Answer a CHAOS-class query: the TXT record for a known name, no
+    /// data for other types of it, and REFUSED for anything else or
+    /// when hidden.
+    pub async fn answer<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+    ) -> ResponseInfo {
+        let query = &request.queries()[0];
+        let name = Name::from(query.name());
+        let text = match self.text(&name.to_string()) {
+            Some(text) if !self.hide => text,
+            _ => return send_error(request, response_handle, ResponseCode::Refused).await,
+        };
+
+        let mut answers = Vec::new();
+        if matches!(query.query_type(), RecordType::TXT | RecordType::ANY) {
+            let mut record =
+                Record::from_rdata(name, 0, RData::TXT(TXT::new(vec![text.to_string()])));
+            record.set_dns_class(DNSClass::CH);
+            answers.push(record);
+        }
+
+        let mut header = Header::response_from_request(request.header());
+        header.set_authoritative(true);
+        let mut builder = MessageResponseBuilder::from_message_request(request);
+        if let Some(edns) = request.edns() {
+            let mut response_edns = Edns::new();
+            response_edns.set_max_payload(edns.max_payload().max(512));
+            builder.edns(response_edns);
+        }
+        let response = builder.build(
+            header,
+            answers.iter(),
+            std::iter::empty(),
+            std::iter::empty(),
+            std::iter::empty(),
+        );
+        response_handle
+            .send_response(response)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(src = %request.src(), error = %e, "failed to send CHAOS answer");
+                serve_failed()
+            })
+    }
+}
+
+/// The first label of a node name: `node1` for `node1.srv.i1.is`.
+fn node_prefix(node_name: &str) -> &str {
+    node_name.split('.').next().unwrap_or(node_name)
+}
+
+/// A response handler that adds an NSID option to what it sends.
+#[derive(Clone)]
+pub struct NsidResponse<R> {
+    inner: R,
+    nsid: Option<Vec<u8>>,
+}
+
+impl<R> NsidResponse<R> {
+    /// Send through `inner`, adding `nsid` when there is one.
+    pub const fn new(inner: R, nsid: Option<Vec<u8>>) -> Self {
+        Self { inner, nsid }
+    }
+}
+
+#[async_trait]
+impl<R: ResponseHandler> ResponseHandler for NsidResponse<R> {
+    async fn send_response<'a>(
+        &mut self,
+        mut response: MessageResponse<
+            '_,
+            'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+        >,
+    ) -> std::io::Result<ResponseInfo> {
+        if let Some(nsid) = &self.nsid {
+            let mut edns = response.get_edns().clone().unwrap_or_default();
+            edns.options_mut()
+                .insert(EdnsOption::Unknown(EdnsCode::NSID.into(), nsid.clone()));
+            response.set_edns(edns);
+        }
+        self.inner.send_response(response).await
+    }
+}
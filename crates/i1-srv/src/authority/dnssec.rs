@@ -0,0 +1,399 @@
+//! DNSSEC signing for the threat intelligence zones.
+//!
+//! Each zone is signed with one or more ECDSA P-256 keys (algorithm 13)
+//! stored as PKCS#8 PEM files under `<key_dir>/<zone>/`; a zone with no
+//! key gets one generated on first start. Hickory signs every record set with
+//! every loaded key, so each key serves as a combined KSK/ZSK and its
+//! DNSKEY carries the SEP flag. Zones are re-signed on every rebuild,
+//! which keeps RRSIG validity windows short.
+//!
+//! Names inside blocked CIDRs are synthesized at query time, so the
+//! blocklist authority holds signers and signs those answers online.
+//! Secondaries serve the RRSIGs they receive by transfer but hold no
+//! keys, so their CIDR answers go out unsigned.
+//!
+//! ## Key rollover
+//!
+//! 1. Add a second key file to the zone's directory (`i1-srv` generates
+//!    one if you move the old key aside, start, and move it back).
+//! 2. Restart: both DNSKEYs are published and both sign every record set.
+//! 3. Publish the new key's DS record (logged at startup) at the parent
+//!    and remove the old one.
+//! 4. Once the old DS has expired from caches, delete the old key file.
+
+use chrono::{DateTime, Utc};
+use hickory_proto::dnssec::crypto::EcdsaSigningKey;
+use hickory_proto::dnssec::rdata::{DNSSECRData, DNSKEY, DS, RRSIG};
+use hickory_proto::dnssec::{Algorithm, DigestType, SigSigner, SigningKey, TBS};
+use hickory_proto::rr::{DNSClass, LowerName, Name, RData, Record, RecordSet, RecordType};
+use hickory_server::authority::Authority;
+use hickory_server::store::in_memory::InMemoryAuthority;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tracing::info;
+
+/// PEM tag for PKCS#8 private keys.
+const PEM_TAG: &str = "PRIVATE KEY";
+
+/// Signing keys for a set of zones.
+pub struct ZoneSigningKeys {
+    zones: HashMap<LowerName, Vec<ZoneKey>>,
+    validity: Duration,
+}
+
+impl std::fmt::Debug for ZoneSigningKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZoneSigningKeys")
+            .field("zones", &self.zones.len())
+            .field("validity", &self.validity)
+            .finish_non_exhaustive()
+    }
+}
+
+struct ZoneKey {
+    pkcs8: Vec<u8>,
+    dnskey: DNSKEY,
+}
+
+impl ZoneKey {
+    fn from_pkcs8(pkcs8: Vec<u8>) -> crate::Result<Self> {
+        let public = signing_key(&pkcs8)?
+            .to_public_key()
+            .map_err(|e| crate::SrvError::Identity(format!("invalid DNSSEC key: {e}")))?;
+        Ok(Self {
+            dnskey: DNSKEY::from_key(&public),
+            pkcs8,
+        })
+    }
+
+    fn generate() -> crate::Result<Self> {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+                .map_err(|e| {
+                    crate::SrvError::Identity(format!("DNSSEC key generation failed: {e}"))
+                })?;
+        Self::from_pkcs8(pkcs8.as_ref().to_vec())
+    }
+
+    fn key_tag(&self) -> crate::Result<u16> {
+        self.dnskey.calculate_key_tag().map_err(zone_err)
+    }
+
+    fn signer(&self, origin: &Name, validity: Duration) -> crate::Result<SigSigner> {
+        Ok(SigSigner::dnssec(
+            self.dnskey.clone(),
+            Box::new(signing_key(&self.pkcs8)?),
+            origin.clone(),
+            validity,
+        ))
+    }
+}
+
+impl ZoneSigningKeys {
+    /// Load each zone's keys from `<dir>/<zone>/*.pem`, generating and
+    /// saving a key for zones that have none.
+    ///
+    /// `validity` is how long each RRSIG stays valid after signing.
+    pub fn load_or_generate(
+        dir: &Path,
+        origins: &[Name],
+        validity: Duration,
+    ) -> crate::Result<Self> {
+        let mut zones = HashMap::new();
+        for origin in origins {
+            let zone_dir = dir.join(origin.to_string().trim_end_matches('.'));
+            let mut keys = load_keys(&zone_dir)?;
+            if keys.is_empty() {
+                let key = ZoneKey::generate()?;
+                let path = zone_dir.join(format!("k{}.pem", key.key_tag()?));
+                write_key(&path, &key.pkcs8)?;
+                info!(zone = %origin, path = %path.display(), "generated DNSSEC key");
+                keys.push(key);
+            }
+            zones.insert(LowerName::from(origin), keys);
+        }
+        Ok(Self { zones, validity })
+    }
+
+    /// Generate one in-memory key per zone (nothing is written to disk).
+    pub fn generate(origins: &[Name], validity: Duration) -> crate::Result<Self> {
+        let zones = origins
+            .iter()
+            .map(|origin| Ok((LowerName::from(origin), vec![ZoneKey::generate()?])))
+            .collect::<crate::Result<_>>()?;
+        Ok(Self { zones, validity })
+    }
+
+    /// Signers for `origin` (empty if the zone has no keys).
+    pub fn signers(&self, origin: &LowerName) -> crate::Result<Vec<SigSigner>> {
+        let name = Name::from(origin);
+        self.zones
+            .get(origin)
+            .into_iter()
+            .flatten()
+            .map(|key| key.signer(&name, self.validity))
+            .collect()
+    }
+
+    /// Published DNSKEYs for `origin`.
+    pub fn dnskeys(&self, origin: &LowerName) -> Vec<DNSKEY> {
+        self.zones
+            .get(origin)
+            .into_iter()
+            .flatten()
+            .map(|key| key.dnskey.clone())
+            .collect()
+    }
+
+    /// Publish DNSKEYs, generate the NSEC chain, and sign every record set.
+    ///
+    /// Hickory bumps the SOA serial by one while signing. Zones without
+    /// keys are left unsigned.
+    pub fn sign_zone(&self, authority: &mut InMemoryAuthority) -> crate::Result<()> {
+        let signers = self.signers(authority.origin())?;
+        if signers.is_empty() {
+            return Ok(());
+        }
+        for signer in signers {
+            authority
+                .add_zone_signing_key_mut(signer)
+                .map_err(zone_err)?;
+        }
+        authority.secure_zone_mut().map_err(zone_err)
+    }
+
+    /// SHA-256 DS records for every key, to publish in the parent zones.
+    pub fn ds_records(&self) -> crate::Result<Vec<Record>> {
+        let mut records = Vec::new();
+        for (origin, keys) in &self.zones {
+            let name = Name::from(origin);
+            for key in keys {
+                let digest = key
+                    .dnskey
+                    .to_digest(&name, DigestType::SHA256)
+                    .map_err(zone_err)?;
+                let ds = DS::new(
+                    key.key_tag()?,
+                    Algorithm::ECDSAP256SHA256,
+                    DigestType::SHA256,
+                    digest.as_ref().to_vec(),
+                );
+                records.push(Record::from_rdata(
+                    name.clone(),
+                    crate::authority::ttl_policy::NS_TTL,
+                    RData::DNSSEC(DNSSECRData::DS(ds)),
+                ));
+            }
+        }
+        records.sort_by(|a, b| a.name().cmp(b.name()));
+        Ok(records)
+    }
+}
+
+/// Sign a record set built at query time, replacing any existing RRSIGs.
+pub fn sign_rrset(
+    rrset: &mut RecordSet,
+    signers: &[SigSigner],
+    now: DateTime<Utc>,
+) -> crate::Result<()> {
+    rrset.clear_rrsigs();
+    let inception = epoch_secs(now);
+    for signer in signers {
+        let validity = u32::try_from(signer.sig_duration().as_secs()).unwrap_or(u32::MAX);
+        let expiration = inception.wrapping_add(validity);
+        let key_tag = signer.calculate_key_tag().map_err(zone_err)?;
+        let rrsig = |sig| {
+            RRSIG::new(
+                rrset.record_type(),
+                signer.key().algorithm(),
+                rrset.name().num_labels(),
+                rrset.ttl(),
+                expiration,
+                inception,
+                key_tag,
+                signer.signer_name().clone(),
+                sig,
+            )
+        };
+        let unsigned = rrsig(Vec::new());
+        let tbs = TBS::from_sig(
+            rrset.name(),
+            DNSClass::IN,
+            &unsigned,
+            rrset.records_without_rrsigs(),
+        )
+        .map_err(zone_err)?;
+        let sig = signer.sign(&tbs).map_err(zone_err)?;
+        let signed = Record::from_rdata(
+            rrset.name().clone(),
+            rrset.ttl(),
+            RData::DNSSEC(DNSSECRData::RRSIG(rrsig(sig))),
+        );
+        rrset.insert_rrsig(signed);
+    }
+    Ok(())
+}
+
+/// Whether `record` is DNSSEC signing material rather than zone data.
+#[must_use]
+pub fn is_rrsig(record: &Record) -> bool {
+    record.record_type() == RecordType::RRSIG
+}
+
+fn signing_key(pkcs8: &[u8]) -> crate::Result<EcdsaSigningKey> {
+    let key_pair = EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_FIXED_SIGNING,
+        pkcs8,
+        &SystemRandom::new(),
+    )
+    .map_err(|e| crate::SrvError::Identity(format!("unsupported DNSSEC key: {e}")))?;
+    Ok(EcdsaSigningKey::from_ecdsa(
+        key_pair,
+        Algorithm::ECDSAP256SHA256,
+    ))
+}
+
+fn load_keys(zone_dir: &Path) -> crate::Result<Vec<ZoneKey>> {
+    let Ok(entries) = std::fs::read_dir(zone_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "pem"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                crate::SrvError::Identity(format!("failed to read {}: {e}", path.display()))
+            })?;
+            let parsed = pem::parse(content).map_err(|e| {
+                crate::SrvError::Identity(format!("invalid key PEM {}: {e}", path.display()))
+            })?;
+            ZoneKey::from_pkcs8(parsed.into_contents())
+        })
+        .collect()
+}
+
+fn write_key(path: &Path, pkcs8: &[u8]) -> crate::Result<()> {
+    let io_err = |e: std::io::Error| {
+        crate::SrvError::Identity(format!("failed to write {}: {e}", path.display()))
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_err)?;
+    }
+    let pem = pem::encode(&pem::Pem::new(PEM_TAG, pkcs8));
+    std::fs::write(path, pem).map_err(io_err)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io_err)?;
+    }
+    Ok(())
+}
+
+fn epoch_secs(now: DateTime<Utc>) -> u32 {
+    u32::try_from(now.timestamp().max(0)).unwrap_or(u32::MAX)
+}
+
+fn zone_err(e: impl std::fmt::Display) -> crate::SrvError {
+    crate::SrvError::Zone(format!("dnssec: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::threat_authority;
+    use hickory_proto::dnssec::PublicKey;
+    use std::net::Ipv4Addr;
+    use tempfile::TempDir;
+
+    fn origin() -> Name {
+        Name::parse("bl.i1.is.", None).unwrap()
+    }
+
+    #[test]
+    fn test_keys_persist_across_loads() {
+        let dir = TempDir::new().unwrap();
+        let validity = Duration::from_secs(3600);
+        let first = ZoneSigningKeys::load_or_generate(dir.path(), &[origin()], validity).unwrap();
+        let second = ZoneSigningKeys::load_or_generate(dir.path(), &[origin()], validity).unwrap();
+
+        let lower = LowerName::from(origin());
+        assert_eq!(first.dnskeys(&lower), second.dnskeys(&lower));
+        assert!(dir.path().join("bl.i1.is").is_dir());
+
+        // A second key file (rollover) publishes both.
+        std::fs::write(
+            dir.path().join("bl.i1.is").join("next.pem"),
+            pem::encode(&pem::Pem::new(PEM_TAG, ZoneKey::generate().unwrap().pkcs8)),
+        )
+        .unwrap();
+        let rolled = ZoneSigningKeys::load_or_generate(dir.path(), &[origin()], validity).unwrap();
+        assert_eq!(rolled.dnskeys(&lower).len(), 2);
+        assert_eq!(rolled.ds_records().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_ds_matches_dnskey() {
+        let keys = ZoneSigningKeys::generate(&[origin()], Duration::from_secs(3600)).unwrap();
+        let dnskey = keys.dnskeys(&LowerName::from(origin())).remove(0);
+        let ds = keys.ds_records().unwrap().remove(0);
+        let RData::DNSSEC(DNSSECRData::DS(ds)) = ds.data() else {
+            panic!("expected DS");
+        };
+        assert!(ds.covers(&origin(), &dnskey).unwrap());
+        assert!(dnskey.secure_entry_point());
+    }
+
+    #[test]
+    fn test_sign_zone_adds_rrsigs() {
+        let keys = ZoneSigningKeys::generate(&[origin()], Duration::from_secs(3600)).unwrap();
+        let mut zone = threat_authority::create_zone(&origin(), 7).unwrap();
+        keys.sign_zone(&mut zone).unwrap();
+
+        let rrsets = zone.records_get_mut();
+        let types: Vec<RecordType> = rrsets.values().map(|r| r.record_type()).collect();
+        assert!(types.contains(&RecordType::DNSKEY));
+        assert!(types.contains(&RecordType::NSEC));
+        assert!(rrsets.values().all(|r| !r.rrsigs().is_empty()));
+    }
+
+    #[test]
+    fn test_online_signature_verifies() {
+        let keys = ZoneSigningKeys::generate(&[origin()], Duration::from_secs(3600)).unwrap();
+        let lower = LowerName::from(origin());
+        let signers = keys.signers(&lower).unwrap();
+        let dnskey = keys.dnskeys(&lower).remove(0);
+
+        let name = Name::parse("1.113.0.203.bl.i1.is.", None).unwrap();
+        let mut rrset = RecordSet::new(name.clone(), RecordType::A, 0);
+        rrset.insert(
+            Record::from_rdata(name, 300, RData::A(Ipv4Addr::LOCALHOST.into())),
+            0,
+        );
+        sign_rrset(&mut rrset, &signers, Utc::now()).unwrap();
+
+        let rrsig = &rrset.rrsigs()[0];
+        let RData::DNSSEC(DNSSECRData::RRSIG(rrsig)) = rrsig.data() else {
+            panic!("expected RRSIG");
+        };
+        let tbs = TBS::from_sig(
+            rrset.name(),
+            DNSClass::IN,
+            rrsig,
+            rrset.records_without_rrsigs(),
+        )
+        .unwrap();
+        dnskey
+            .public_key()
+            .verify(tbs.as_ref(), rrsig.sig())
+            .unwrap();
+    }
+}
@@ -1,8 +1,18 @@
 //! DNS authority modules for serving threat intelligence zones.
 //!
 //! Each zone (bl.i1.is, rep.i1.is, etc.) has its own authority backed by
-//! an in-memory record store that gets rebuilt from defense state.
+//! an in-memory record store that gets rebuilt from defense state. The
+//! blocklist zone wraps its store to also answer for blocked CIDR ranges.
+//! A copy of every served zone is kept on disk so restarts don't start
+//! empty (see [`persist`]).
 
+pub mod blocklist_authority;
+pub mod chaos;
+pub mod dnssec;
+pub mod persist;
+pub mod rpz;
+pub mod serial;
 pub mod threat_authority;
+pub mod transfer;
 pub mod ttl_policy;
 pub mod zone_builder;
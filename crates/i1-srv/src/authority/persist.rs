@@ -0,0 +1,402 @@
+//! On-disk zone store.
+//!
+//! Served zones live in memory, so without a copy on disk a restarted node
+//! answers NXDOMAIN for everything until its zones are rebuilt or
+//! transferred again. [`ZoneDb`] keeps that copy in a redb database: each
+//! swap of a [`ServedZone`](crate::authority::transfer::ServedZone)
+//! appends the records it removed and added to a journal as it happens,
+//! and startup loads the saved zones and replays the journal before the
+//! listeners open. [`ZoneDb::compact`] folds the journal into the saved
+//! zones and gives the space back; the server runs it periodically.
+//!
+//! Records are kept in wire format with their SOA, so a restored zone
+//! resumes at the serial it was last served with. A database that cannot
+//! be opened or read is moved aside and replaced by an empty one: a node
+//! that starts empty still catches up, one that refuses to start does not.
+
+use hickory_proto::rr::{LowerName, Name, Record};
+use hickory_proto::serialize::binary::{BinDecodable, BinEncodable};
+use redb::{Database, DatabaseError, ReadableTable, ReadableTableMetadata, TableDefinition};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::authority::transfer::soa_serial;
+
+/// Saved records: (zone, record) -> ().
+const RECORDS: TableDefinition<(&str, &[u8]), ()> = TableDefinition::new("records");
+/// Saved SOA per zone.
+const SOAS: TableDefinition<&str, &[u8]> = TableDefinition::new("soa");
+/// Changes since the last compaction: sequence -> (zone, op, record).
+const JOURNAL: TableDefinition<u64, (&str, u8, &[u8])> = TableDefinition::new("journal");
+
+/// Journal operations.
+const OP_REMOVE: u8 = 0;
+const OP_ADD: u8 = 1;
+const OP_SOA: u8 = 2;
+
+/// A zone as it was last saved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SavedZone {
+    /// Zone SOA, which carries the serial.
+    pub soa: Option<Record>,
+    /// Every other record, RRSIGs included.
+    pub records: BTreeSet<Record>,
+}
+
+impl SavedZone {
+    /// Serial of the saved SOA.
+    #[must_use]
+    pub fn serial(&self) -> Option<u32> {
+        self.soa.as_ref().and_then(soa_serial)
+    }
+
+    /// All records, SOA first.
+    #[must_use]
+    pub fn into_records(self) -> Vec<Record> {
+        self.soa.into_iter().chain(self.records).collect()
+    }
+
+    fn apply(&mut self, op: u8, record: Record) -> crate::Result<()> {
+        match op {
+            OP_REMOVE => {
+                self.records.remove(&record);
+            }
+            OP_ADD => {
+                self.records.insert(record);
+            }
+            OP_SOA => self.soa = Some(record),
+            _ => return Err(store_error(format!("unknown journal op {op}"))),
+        }
+        Ok(())
+    }
+}
+
+/// Saved zones, by origin.
+pub type SavedZones = BTreeMap<LowerName, SavedZone>;
+
+/// The on-disk copy of the served zones.
+pub struct ZoneDb {
+    path: PathBuf,
+    /// Compaction needs the database to itself.
+    db: Mutex<Database>,
+}
+
+impl std::fmt::Debug for ZoneDb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZoneDb")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ZoneDb {
+    /// Open (or create) the store at `path` and load the saved zones.
+    ///
+    /// A store that cannot be opened or read is moved to `<path>.corrupt`
+    /// with a warning and replaced by an empty one. Errors when another
+    /// process has the store open, or the empty store cannot be created.
+    pub fn open(path: &Path) -> crate::Result<(Self, SavedZones)> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let opened = match Database::create(path) {
+            Err(DatabaseError::DatabaseAlreadyOpen) => {
+                return Err(store_error(format!("{} is in use", path.display())));
+            }
+            Ok(db) => {
+                let db = Self::new(path, db);
+                db.load().map(|zones| (db, zones))
+            }
+            Err(e) => Err(store_error(e)),
+        };
+        match opened {
+            Ok(opened) => Ok(opened),
+            Err(e) => {
+                let aside = path.with_extension("corrupt");
+                warn!(
+                    path = %path.display(),
+                    moved_to = %aside.display(),
+                    error = %e,
+                    "zone store unreadable, starting with empty zones"
+                );
+                if path.exists() {
+                    std::fs::rename(path, &aside)?;
+                }
+                let db = Database::create(path).map_err(store_error)?;
+                Ok((Self::new(path, db), SavedZones::new()))
+            }
+        }
+    }
+
+    fn new(path: &Path, db: Database) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            db: Mutex::new(db),
+        }
+    }
+
+    /// Save a zone in full, replacing whatever was saved for it.
+    pub fn save_zone(
+        &self,
+        origin: &LowerName,
+        soa: Option<&Record>,
+        records: &BTreeSet<Record>,
+    ) -> crate::Result<()> {
+        let zone = origin.to_string();
+        let db = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        let txn = db.begin_write().map_err(store_error)?;
+        {
+            let mut saved = txn.open_table(RECORDS).map_err(store_error)?;
+            let mut soas = txn.open_table(SOAS).map_err(store_error)?;
+            let mut journal = txn.open_table(JOURNAL).map_err(store_error)?;
+            saved
+                .retain(|(name, _), ()| name != zone)
+                .map_err(store_error)?;
+            journal
+                .retain(|_, (name, _, _)| name != zone)
+                .map_err(store_error)?;
+            for record in records {
+                saved
+                    .insert((zone.as_str(), encode(record)?.as_slice()), ())
+                    .map_err(store_error)?;
+            }
+            match soa {
+                Some(soa) => soas.insert(zone.as_str(), encode(soa)?.as_slice()),
+                None => soas.remove(zone.as_str()),
+            }
+            .map_err(store_error)?;
+        }
+        txn.commit().map_err(store_error)?;
+        drop(db);
+        debug!(zone = %origin, records = records.len(), "saved zone");
+        Ok(())
+    }
+
+    /// Journal one change to a zone: the records it lost and gained, and
+    /// its new SOA.
+    pub fn journal(
+        &self,
+        origin: &LowerName,
+        soa: Option<&Record>,
+        removed: &[Record],
+        added: &[Record],
+    ) -> crate::Result<()> {
+        let zone = origin.to_string();
+        let db = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        let txn = db.begin_write().map_err(store_error)?;
+        {
+            let mut journal = txn.open_table(JOURNAL).map_err(store_error)?;
+            let next = journal
+                .last()
+                .map_err(store_error)?
+                .map_or(0, |(seq, _)| seq.value() + 1);
+            let ops = removed
+                .iter()
+                .map(|r| (OP_REMOVE, r))
+                .chain(added.iter().map(|r| (OP_ADD, r)))
+                .chain(soa.map(|r| (OP_SOA, r)));
+            for (seq, (op, record)) in (next..).zip(ops) {
+                journal
+                    .insert(seq, (zone.as_str(), op, encode(record)?.as_slice()))
+                    .map_err(store_error)?;
+            }
+        }
+        txn.commit().map_err(store_error)?;
+        drop(db);
+        Ok(())
+    }
+
+    /// Fold the journal into the saved zones and shrink the file.
+    ///
+    /// Returns the number of journal entries folded.
+    pub fn compact(&self) -> crate::Result<u64> {
+        let mut db = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        let txn = db.begin_write().map_err(store_error)?;
+        let folded = {
+            let mut saved = txn.open_table(RECORDS).map_err(store_error)?;
+            let mut soas = txn.open_table(SOAS).map_err(store_error)?;
+            let mut journal = txn.open_table(JOURNAL).map_err(store_error)?;
+            let folded = journal.len().map_err(store_error)?;
+            for entry in journal.iter().map_err(store_error)? {
+                let (_, value) = entry.map_err(store_error)?;
+                let (zone, op, record) = value.value();
+                match op {
+                    OP_REMOVE => saved.remove((zone, record)).map(drop),
+                    OP_ADD => saved.insert((zone, record), ()).map(drop),
+                    _ => soas.insert(zone, record).map(drop),
+                }
+                .map_err(store_error)?;
+            }
+            journal.retain(|_, _| false).map_err(store_error)?;
+            folded
+        };
+        txn.commit().map_err(store_error)?;
+        db.compact().map_err(store_error)?;
+        drop(db);
+        Ok(folded)
+    }
+
+    /// Compact every `every`. Runs forever.
+    pub async fn run_compaction(self: Arc<Self>, every: Duration) {
+        let mut ticks = tokio::time::interval(every);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            match self.compact() {
+                Ok(folded) => debug!(folded, "compacted zone store"),
+                Err(e) => warn!(error = %e, "zone store compaction failed"),
+            }
+        }
+    }
+
+    /// Read the saved zones and replay the journal over them.
+    fn load(&self) -> crate::Result<SavedZones> {
+        let db = self.db.lock().unwrap_or_else(PoisonError::into_inner);
+        let txn = db.begin_read().map_err(store_error)?;
+        let mut zones = SavedZones::new();
+
+        // Tables only exist once something was written.
+        if let Ok(saved) = txn.open_table(RECORDS) {
+            for entry in saved.iter().map_err(store_error)? {
+                let (key, _) = entry.map_err(store_error)?;
+                let (zone, record) = key.value();
+                zone_entry(&mut zones, zone)?
+                    .records
+                    .insert(decode(record)?);
+            }
+        }
+        if let Ok(soas) = txn.open_table(SOAS) {
+            for entry in soas.iter().map_err(store_error)? {
+                let (zone, record) = entry.map_err(store_error)?;
+                zone_entry(&mut zones, zone.value())?.soa = Some(decode(record.value())?);
+            }
+        }
+        let mut replayed = 0_usize;
+        if let Ok(journal) = txn.open_table(JOURNAL) {
+            for entry in journal.iter().map_err(store_error)? {
+                let (_, value) = entry.map_err(store_error)?;
+                let (zone, op, record) = value.value();
+                zone_entry(&mut zones, zone)?.apply(op, decode(record)?)?;
+                replayed += 1;
+            }
+        }
+        drop(txn);
+        drop(db);
+
+        if !zones.is_empty() {
+            info!(
+                path = %self.path.display(),
+                zones = zones.len(),
+                journal = replayed,
+                "loaded saved zones"
+            );
+        }
+        Ok(zones)
+    }
+}
+
+fn zone_entry<'a>(zones: &'a mut SavedZones, zone: &str) -> crate::Result<&'a mut SavedZone> {
+    let origin = Name::from_ascii(zone)
+        .map_err(|e| store_error(format!("invalid zone name '{zone}': {e}")))?;
+    Ok(zones.entry(LowerName::from(origin)).or_default())
+}
+
+fn encode(record: &Record) -> crate::Result<Vec<u8>> {
+    record.to_bytes().map_err(store_error)
+}
+
+fn decode(bytes: &[u8]) -> crate::Result<Record> {
+    Record::from_bytes(bytes).map_err(store_error)
+}
+
+fn store_error(e: impl std::fmt::Display) -> crate::SrvError {
+    crate::SrvError::Store(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::threat_authority;
+    use crate::authority::transfer::ZoneStore;
+    use crate::encoding::dnsbl::DnsblCode;
+
+    fn zone(serial: u32, ips: &[&str]) -> (LowerName, SavedZone) {
+        let origin = Name::parse("bl.i1.is.", None).unwrap();
+        let mut authority = threat_authority::create_zone(&origin, serial).unwrap();
+        for ip in ips {
+            threat_authority::insert_dnsbl_record(
+                &mut authority,
+                &ip.parse().unwrap(),
+                DnsblCode::Listed,
+                "bl.i1.is.",
+                serial,
+            )
+            .unwrap();
+        }
+        let (soa, records): (Vec<_>, Vec<_>) = authority
+            .zone_records()
+            .into_iter()
+            .partition(|r| soa_serial(r).is_some());
+        let saved = SavedZone {
+            soa: soa.into_iter().next(),
+            records: records.into_iter().collect(),
+        };
+        (LowerName::from(origin), saved)
+    }
+
+    fn journal_change(db: &ZoneDb, origin: &LowerName, from: &SavedZone, to: &SavedZone) {
+        let removed: Vec<Record> = from.records.difference(&to.records).cloned().collect();
+        let added: Vec<Record> = to.records.difference(&from.records).cloned().collect();
+        db.journal(origin, to.soa.as_ref(), &removed, &added)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_journal_replays_and_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zones.redb");
+        let (origin, v1) = zone(10, &["1.2.3.4", "5.6.7.8"]);
+        let (_, v2) = zone(11, &["1.2.3.4", "9.9.9.9"]);
+        let (_, v3) = zone(12, &["9.9.9.9"]);
+
+        let (db, saved) = ZoneDb::open(&path).unwrap();
+        assert!(saved.is_empty());
+        db.save_zone(&origin, v1.soa.as_ref(), &v1.records).unwrap();
+        journal_change(&db, &origin, &v1, &v2);
+        drop(db);
+
+        // The journal is replayed over the saved zone.
+        let (db, saved) = ZoneDb::open(&path).unwrap();
+        assert_eq!(saved[&origin], v2);
+        assert_eq!(saved[&origin].serial(), Some(11));
+
+        // Compaction folds it in without changing what loads.
+        journal_change(&db, &origin, &v2, &v3);
+        assert_eq!(db.compact().unwrap(), 5);
+        assert_eq!(db.compact().unwrap(), 0);
+        drop(db);
+        let (_, saved) = ZoneDb::open(&path).unwrap();
+        assert_eq!(saved[&origin], v3);
+    }
+
+    #[test]
+    fn test_corrupt_store_starts_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zones.redb");
+        std::fs::write(&path, b"definitely not a redb file").unwrap();
+
+        let (db, saved) = ZoneDb::open(&path).unwrap();
+        assert!(saved.is_empty());
+        assert!(path.with_extension("corrupt").exists());
+
+        // The replacement store works.
+        let (origin, v1) = zone(10, &["1.2.3.4"]);
+        db.save_zone(&origin, v1.soa.as_ref(), &v1.records).unwrap();
+        drop(db);
+        assert_eq!(ZoneDb::open(&path).unwrap().1[&origin], v1);
+    }
+}
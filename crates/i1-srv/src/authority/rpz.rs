@@ -0,0 +1,291 @@
+//! Response Policy Zone (RPZ) export of the blocklist.
+//!
+//! Resolvers that speak RPZ (BIND, Unbound, the `PowerDNS` Recursor, Knot
+//! Resolver) can enforce the blocklist themselves, without clients
+//! pointing at this server. Every blocked address or CIDR becomes an IP
+//! trigger (`32.4.3.2.1.rpz-ip`) rewritten to NXDOMAIN with `CNAME .`.
+//! Every whitelisted one becomes an `rpz-passthru.` rule. A whitelisted
+//! address inside a blocked range still resolves, because RPZ prefers the
+//! longest matching prefix.
+//!
+//! Blocked countries and ASNs are not exported: the snapshot carries only
+//! their codes, not the prefixes they announce, so there is nothing to
+//! trigger on.
+//!
+//! The zone is unsigned even when DNSSEC is on; RPZ consumers take it by
+//! zone transfer or from the file, not by validated lookup.
+
+use hickory_proto::rr::rdata::{CNAME, NS};
+use hickory_proto::rr::{Name, RData, Record};
+use hickory_server::store::in_memory::InMemoryAuthority;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::Path;
+use tracing::warn;
+
+use crate::authority::blocklist_authority::Cidr;
+use crate::authority::zone_builder::DefenseSnapshot;
+use crate::authority::{threat_authority, ttl_policy};
+
+/// Label under the zone origin holding IP triggers.
+pub const RPZ_IP: &str = "rpz-ip";
+
+/// CNAME target that exempts a match from every policy.
+pub const PASSTHRU: &str = "rpz-passthru.";
+
+/// Name server placed at the apex; RPZ zones are never delegated to.
+const APEX_NS: &str = "localhost.";
+
+/// TTL for policy rules, matching confirmed DNSBL listings.
+const RULE_TTL: u32 = ttl_policy::BLOCKLIST_CONFIRMED_TTL;
+
+/// What a resolver does when a rule matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Answer NXDOMAIN (`CNAME .`).
+    Nxdomain,
+    /// Answer normally (`CNAME rpz-passthru.`).
+    Passthru,
+}
+
+impl Action {
+    const fn target(self) -> &'static str {
+        match self {
+            Self::Nxdomain => ".",
+            Self::Passthru => PASSTHRU,
+        }
+    }
+}
+
+/// One policy rule: an owner name relative to the zone origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Trigger, e.g. `24.0.113.0.203.rpz-ip`.
+    pub trigger: String,
+    /// Policy applied on a match.
+    pub action: Action,
+}
+
+/// Policy rules for a snapshot: blocks first, then passthrus.
+///
+/// A prefix that is both blocked and whitelisted only gets the passthru.
+/// Unparseable entries are skipped with a warning, as in the blocklist zone.
+#[must_use]
+pub fn rules(snapshot: &DefenseSnapshot) -> Vec<Rule> {
+    let passthru = triggers(&snapshot.whitelisted_ips);
+    let mut rules: Vec<Rule> = triggers(&snapshot.blocked_ips)
+        .into_iter()
+        .filter(|trigger| !passthru.contains(trigger))
+        .map(|trigger| Rule {
+            trigger,
+            action: Action::Nxdomain,
+        })
+        .collect();
+    rules.extend(passthru.into_iter().map(|trigger| Rule {
+        trigger,
+        action: Action::Passthru,
+    }));
+    rules
+}
+
+/// Unique IP triggers for address or CIDR entries, in input order.
+fn triggers(entries: &[String]) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let cidr = if entry.contains('/') {
+                Cidr::parse(entry)
+            } else {
+                entry.parse::<IpAddr>().map(Cidr::from).map_err(|e| {
+                    crate::SrvError::Zone(format!("invalid IP address '{entry}': {e}"))
+                })
+            };
+            match cidr {
+                Ok(cidr) => Some(ip_trigger(&cidr)),
+                Err(e) => {
+                    warn!(entry = %entry, error = %e, "skipping invalid RPZ entry");
+                    None
+                }
+            }
+        })
+        .filter(|trigger| seen.insert(trigger.clone()))
+        .collect()
+}
+
+/// IP trigger owner for a block, relative to the zone origin.
+///
+/// The prefix length leads, followed by the network address reversed:
+/// IPv4 octets, or IPv6 16-bit groups in hex with the longest run of zero
+/// groups written as `zz`.
+#[must_use]
+pub fn ip_trigger(cidr: &Cidr) -> String {
+    let labels = match cidr.network() {
+        IpAddr::V4(v4) => v4.octets().iter().rev().map(u8::to_string).collect(),
+        IpAddr::V6(v6) => ipv6_labels(&v6),
+    };
+    format!("{}.{}.{RPZ_IP}", cidr.prefix_len(), labels.join("."))
+}
+
+/// IPv6 groups, least significant first, with the longest run (of two or
+/// more) of zero groups collapsed to `zz`, as `::` would be.
+fn ipv6_labels(v6: &Ipv6Addr) -> Vec<String> {
+    let segments = v6.segments();
+
+    let mut best = (0, 0);
+    let mut run_start = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        if *segment != 0 {
+            run_start = i + 1;
+        } else if i + 1 - run_start > best.1 {
+            best = (run_start, i + 1 - run_start);
+        }
+    }
+
+    let mut labels = Vec::new();
+    let mut i = 0;
+    while i < segments.len() {
+        if best.1 >= 2 && i == best.0 {
+            labels.push("zz".to_string());
+            i += best.1;
+        } else {
+            labels.push(format!("{:x}", segments[i]));
+            i += 1;
+        }
+    }
+    labels.reverse();
+    labels
+}
+
+/// Render the snapshot as an RPZ zone file.
+pub fn render(snapshot: &DefenseSnapshot, origin: &str, serial: u32) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "; i1 blocklist response policy zone");
+    let _ = writeln!(out, "$ORIGIN {origin}");
+    let _ = writeln!(out, "$TTL {RULE_TTL}");
+    let _ = writeln!(
+        out,
+        "@ IN SOA ns1.i1.is. admin.i1.is. {serial} {} {} {} {}",
+        ttl_policy::SOA_REFRESH,
+        ttl_policy::SOA_RETRY,
+        ttl_policy::SOA_EXPIRE,
+        ttl_policy::SOA_MINIMUM_TTL
+    );
+    let _ = writeln!(out, "@ IN NS {APEX_NS}");
+    for rule in rules(snapshot) {
+        let _ = writeln!(out, "{} IN CNAME {}", rule.trigger, rule.action.target());
+    }
+    out
+}
+
+/// Build the RPZ as a zone that can be served and transferred.
+pub fn build_zone(
+    snapshot: &DefenseSnapshot,
+    origin: &str,
+    serial: u32,
+) -> crate::Result<InMemoryAuthority> {
+    let origin_name = parse_name(origin)?;
+    let mut zone = threat_authority::create_zone(&origin_name, serial)?;
+    zone.upsert_mut(
+        Record::from_rdata(
+            origin_name,
+            ttl_policy::NS_TTL,
+            RData::NS(NS(parse_name(APEX_NS)?)),
+        ),
+        serial,
+    );
+    for rule in rules(snapshot) {
+        let owner = parse_name(&format!("{}.{origin}", rule.trigger))?;
+        let target = parse_name(rule.action.target())?;
+        zone.upsert_mut(
+            Record::from_rdata(owner, RULE_TTL, RData::CNAME(CNAME(target))),
+            serial,
+        );
+    }
+    Ok(zone)
+}
+
+/// Replace the zone file at `path`, so a resolver never reads a partial one.
+pub fn write_zone_file(path: &Path, contents: &str) -> crate::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| crate::SrvError::Zone(format!("failed to write {}: {e}", path.display())))
+}
+
+fn parse_name(name: &str) -> crate::Result<Name> {
+    Name::parse(name, None)
+        .map_err(|e| crate::SrvError::Zone(format!("invalid RPZ name '{name}': {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::rr::{LowerName, RecordType};
+    use hickory_server::authority::{Authority, LookupOptions};
+
+    fn fixture() -> DefenseSnapshot {
+        DefenseSnapshot {
+            blocked_ips: vec![
+                "1.2.3.4".into(),
+                "203.0.113.0/24".into(),
+                "10.0.0.0/8".into(),
+                "2001:db8::/32".into(),
+                "2001:db8:0:1::1".into(),
+                "not-an-ip".into(),
+                "1.2.3.4".into(),
+            ],
+            whitelisted_ips: vec!["10.1.2.3".into(), "203.0.113.0/24".into()],
+            blocked_countries: vec!["cn".into()],
+            blocked_asns: vec!["AS12345".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_zone_file_matches_golden() {
+        let rendered = render(&fixture(), "rpz.i1.is.", 2_026_010_101);
+        assert_eq!(rendered, include_str!("../../testdata/rpz.zone"));
+    }
+
+    #[test]
+    fn test_ipv6_triggers() {
+        let trigger = |s: &str| ip_trigger(&Cidr::parse(s).unwrap());
+        assert_eq!(trigger("2001:db8::/32"), "32.zz.db8.2001.rpz-ip");
+        assert_eq!(trigger("2001:db8::1/128"), "128.1.zz.db8.2001.rpz-ip");
+        // Only the longest zero run collapses; a lone zero group stays.
+        assert_eq!(
+            trigger("2001:0:1:0:0:0:2:3/128"),
+            "128.3.2.zz.1.0.2001.rpz-ip"
+        );
+        assert_eq!(trigger("::/0"), "0.zz.rpz-ip");
+    }
+
+    #[tokio::test]
+    async fn test_served_zone_answers_rules() {
+        let zone = build_zone(&fixture(), "rpz.i1.is.", 1).unwrap();
+        let lookup = |name: &str| {
+            let name = LowerName::from(Name::from_ascii(name).unwrap());
+            let zone = &zone;
+            async move {
+                zone.lookup(&name, RecordType::CNAME, LookupOptions::default())
+                    .await
+                    .map_result()
+                    .unwrap()
+                    .unwrap()
+                    .iter()
+                    .next()
+                    .and_then(|record| record.data().as_cname().map(|c| c.0.to_string()))
+            }
+        };
+        assert_eq!(
+            lookup("32.4.3.2.1.rpz-ip.rpz.i1.is.").await.as_deref(),
+            Some(".")
+        );
+        assert_eq!(
+            lookup("32.3.2.1.10.rpz-ip.rpz.i1.is.").await.as_deref(),
+            Some(PASSTHRU)
+        );
+    }
+}
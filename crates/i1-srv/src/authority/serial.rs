@@ -0,0 +1,83 @@
+//! Zone serial management.
+//!
+//! Secondaries only pull a zone (and IXFR only makes sense) when the
+//! primary's SOA serial moves forward, so every rebuild must produce a
+//! serial greater than the last one under RFC 1982 serial arithmetic.
+//! Serials are seconds since the Unix epoch, which stays monotonic across
+//! restarts; rebuilds within the same second bump the previous serial.
+
+use chrono::{DateTime, Utc};
+
+/// Issues monotonically increasing zone serials.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZoneSerial {
+    last: Option<u32>,
+}
+
+impl ZoneSerial {
+    /// Continue from a serial already published (e.g., a transferred zone).
+    #[must_use]
+    pub const fn starting_after(last: u32) -> Self {
+        Self { last: Some(last) }
+    }
+
+    /// Next serial for a rebuild at `now`.
+    pub fn next(&mut self, now: DateTime<Utc>) -> u32 {
+        // Unix time fits in u32 until 2106; clamp rather than wrap.
+        let candidate = u32::try_from(now.timestamp().max(0)).unwrap_or(u32::MAX);
+        let serial = match self.last {
+            Some(last) if !serial_gt(candidate, last) => last.wrapping_add(1),
+            _ => candidate,
+        };
+        self.last = Some(serial);
+        serial
+    }
+
+    /// The most recently issued serial.
+    #[must_use]
+    pub const fn last(&self) -> Option<u32> {
+        self.last
+    }
+}
+
+/// RFC 1982 comparison: `a` is newer than `b`.
+#[must_use]
+pub const fn serial_gt(a: u32, b: u32) -> bool {
+    a != b && a.wrapping_sub(b) < (1 << 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_serials_are_monotonic() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let mut serial = ZoneSerial::default();
+        let first = serial.next(now);
+        assert_eq!(i64::from(first), now.timestamp());
+
+        // Same second, and a clock that stepped backwards, still advance.
+        let second = serial.next(now);
+        let third = serial.next(now - chrono::Duration::hours(1));
+        assert!(serial_gt(second, first));
+        assert!(serial_gt(third, second));
+
+        // A later clock jumps ahead again.
+        let later = serial.next(now + chrono::Duration::hours(1));
+        assert_eq!(
+            i64::from(later),
+            (now + chrono::Duration::hours(1)).timestamp()
+        );
+    }
+
+    #[test]
+    fn test_serial_gt_wraps() {
+        assert!(serial_gt(1, 0));
+        assert!(!serial_gt(0, 1));
+        assert!(!serial_gt(5, 5));
+        assert!(serial_gt(0, u32::MAX));
+        assert!(serial_gt(10, u32::MAX - 10));
+    }
+}
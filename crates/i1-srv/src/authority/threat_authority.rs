@@ -2,15 +2,33 @@
 //!
 //! Manages the DNS records that represent our threat intelligence data.
 //! Records are rebuilt periodically from defense state.
+//!
+//! Most DNSBL queries are for addresses that aren't listed. A
+//! [`NegativeCache`] remembers recent NXDOMAIN names so repeated misses,
+//! as in a scan or flood, skip the zone and range lookups. Entries live
+//! for [`NEGATIVE_CACHE_TTL`] at most, so a newly listed address is never
+//! masked for long.
 
 use hickory_proto::rr::rdata::{A, SOA, TXT};
-use hickory_proto::rr::{Name, RData, Record};
+use hickory_proto::rr::{LowerName, Name, RData, Record};
 use hickory_server::authority::ZoneType;
+use hickory_server::dnssec::NxProofKind;
 use hickory_server::store::in_memory::InMemoryAuthority;
-use std::net::Ipv4Addr;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 use crate::authority::ttl_policy;
 use crate::encoding::dnsbl::DnsblCode;
+use crate::metrics::{Collector, Exposition, Kind};
+
+/// Most names a [`NegativeCache`] holds; the least recently used go first.
+pub const NEGATIVE_CACHE_CAPACITY: usize = 16_384;
+
+/// How long a miss is remembered.
+pub const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(5);
 
 /// Creates an empty in-memory authority for a zone with a proper SOA record.
 ///
@@ -20,7 +38,8 @@ pub fn create_zone(origin: &Name, serial: u32) -> crate::Result<InMemoryAuthorit
     let mut authority = InMemoryAuthority::empty(
         origin.clone(),
         ZoneType::Primary,
-        false, // no AXFR
+        false,                   // no AXFR
+        Some(NxProofKind::Nsec), // only takes effect once signed
     );
 
     // Every zone needs a SOA record.
@@ -46,25 +65,32 @@ pub fn create_zone(origin: &Name, serial: u32) -> crate::Result<InMemoryAuthorit
 
 /// Insert a DNSBL A record into the blocklist zone.
 ///
-/// Maps a reversed IP (e.g., `4.3.2.1`) to a 127.0.0.X response code
-/// under the given zone origin.
+/// Maps a reversed IP (e.g., `4.3.2.1`, or 32 nibbles for IPv6) to a
+/// 127.0.0.X response code under the given zone origin. IPv6 listings
+/// use the same A record codes.
 pub fn insert_dnsbl_record(
     authority: &mut InMemoryAuthority,
-    ip: &Ipv4Addr,
+    ip: &IpAddr,
     code: DnsblCode,
     zone_origin: &str,
     serial: u32,
 ) -> crate::Result<()> {
-    let reversed = crate::encoding::dnsbl::reverse_ipv4(ip);
+    insert_dnsbl_record_with_ttl(authority, ip, code, zone_origin, dnsbl_ttl(code), serial)
+}
+
+/// Insert a DNSBL A record with an explicit TTL.
+pub fn insert_dnsbl_record_with_ttl(
+    authority: &mut InMemoryAuthority,
+    ip: &IpAddr,
+    code: DnsblCode,
+    zone_origin: &str,
+    ttl: u32,
+    serial: u32,
+) -> crate::Result<()> {
+    let reversed = crate::encoding::dnsbl::reverse_ip(ip);
     let name = Name::parse(&format!("{reversed}.{zone_origin}"), None)
         .map_err(|e| crate::SrvError::Zone(format!("invalid DNSBL name: {e}")))?;
 
-    let ttl = ttl_policy::ttl_for_threat_class(match code {
-        DnsblCode::Suspicious => ttl_policy::ThreatClass::Suspicious,
-        DnsblCode::Community => ttl_policy::ThreatClass::Community,
-        _ => ttl_policy::ThreatClass::Confirmed,
-    });
-
     authority.upsert_mut(
         Record::from_rdata(name, ttl, RData::A(A::from(code.to_ipv4()))),
         serial,
@@ -73,7 +99,22 @@ pub fn insert_dnsbl_record(
     Ok(())
 }
 
-/// Insert a TXT reputation record into the reputation zone.
+/// TTL for a DNSBL answer with the given code.
+pub const fn dnsbl_ttl(code: DnsblCode) -> u32 {
+    ttl_policy::ttl_for_threat_class(match code {
+        DnsblCode::Suspicious => ttl_policy::ThreatClass::Suspicious,
+        DnsblCode::Community => ttl_policy::ThreatClass::Community,
+        _ => ttl_policy::ThreatClass::Confirmed,
+    })
+}
+
+/// Maximum bytes in a single TXT character-string.
+const TXT_STRING_MAX: usize = 255;
+
+/// Insert a TXT record (reputation or CBOR intel) into a zone.
+///
+/// Values longer than 255 bytes are split across multiple character-strings
+/// in the same record; resolvers hand them back for concatenation.
 pub fn insert_txt_record(
     authority: &mut InMemoryAuthority,
     name: &Name,
@@ -85,12 +126,30 @@ pub fn insert_txt_record(
         Record::from_rdata(
             name.clone(),
             ttl,
-            RData::TXT(TXT::new(vec![txt_data.to_string()])),
+            RData::TXT(TXT::new(split_txt_strings(txt_data))),
         ),
         serial,
     );
 }
 
+/// Split a TXT value into <=255-byte character-strings on char boundaries.
+fn split_txt_strings(txt: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for ch in txt.chars() {
+        if current.len() + ch.len_utf8() > TXT_STRING_MAX {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(ch);
+    }
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
 /// Insert a signal/version record into the signal zone.
 pub fn insert_signal_record(
     authority: &mut InMemoryAuthority,
@@ -98,8 +157,11 @@ pub fn insert_signal_record(
     signal_txt: &str,
     serial: u32,
 ) -> crate::Result<()> {
-    let name = Name::parse(&crate::encoding::signal::SignalData::query_name(zone_origin), None)
-        .map_err(|e| crate::SrvError::Zone(format!("invalid signal name: {e}")))?;
+    let name = Name::parse(
+        &crate::encoding::signal::SignalData::query_name(zone_origin),
+        None,
+    )
+    .map_err(|e| crate::SrvError::Zone(format!("invalid signal name: {e}")))?;
 
     authority.upsert_mut(
         Record::from_rdata(
@@ -113,11 +175,204 @@ pub fn insert_signal_record(
     Ok(())
 }
 
+/// Recent NXDOMAIN names, bounded and least-recently-used first out.
+#[derive(Debug)]
+pub struct NegativeCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<LruEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    /// Name -> (expiry, last use).
+    names: HashMap<LowerName, (Instant, u64)>,
+    /// Last use -> name, oldest first.
+    order: BTreeMap<u64, LowerName>,
+    clock: u64,
+}
+
+impl LruEntries {
+    fn remove(&mut self, name: &LowerName) {
+        if let Some((_, used)) = self.names.remove(name) {
+            self.order.remove(&used);
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl Default for NegativeCache {
+    fn default() -> Self {
+        Self::new(NEGATIVE_CACHE_CAPACITY, NEGATIVE_CACHE_TTL)
+    }
+}
+
+impl NegativeCache {
+    /// A cache of at most `capacity` names, each kept for `ttl`.
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl,
+            entries: Mutex::new(LruEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether `name` was a miss within the TTL; counts a hit or a miss.
+    pub fn contains(&self, name: &LowerName) -> bool {
+        let now = Instant::now();
+        let mut entries = self.lock();
+        let hit = match entries.names.get(name) {
+            Some(&(expires, _)) if expires > now => {
+                let used = entries.tick();
+                if let Some((_, last)) = entries.names.get_mut(name) {
+                    let previous = std::mem::replace(last, used);
+                    entries.order.remove(&previous);
+                    entries.order.insert(used, name.clone());
+                }
+                true
+            }
+            Some(_) => {
+                entries.remove(name);
+                false
+            }
+            None => false,
+        };
+        drop(entries);
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
+    }
+
+    /// Remember `name` as a miss, evicting the least recently used name
+    /// when full.
+    pub fn insert(&self, name: &LowerName) {
+        let expires = Instant::now() + self.ttl;
+        let mut entries = self.lock();
+        entries.remove(name);
+        if entries.names.len() >= self.capacity {
+            if let Some((_, oldest)) = entries.order.pop_first() {
+                entries.names.remove(&oldest);
+            }
+        }
+        let used = entries.tick();
+        entries.order.insert(used, name.clone());
+        entries.names.insert(name.clone(), (expires, used));
+    }
+
+    /// Forget every name; the hit and miss counts are kept.
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.names.clear();
+        entries.order.clear();
+    }
+
+    /// Names currently held, expired ones included until next used.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().names.len()
+    }
+
+    /// Whether no names are held.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookups answered from the cache, and lookups that weren't.
+    #[must_use]
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LruEntries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Collector for NegativeCache {
+    fn collect(&self, out: &mut Exposition) {
+        let (hits, misses) = self.stats();
+        let name = "i1_dnsbl_negative_cache_lookups_total";
+        out.family(
+            name,
+            Kind::Counter,
+            "Blocklist lookups checked against the negative cache, by outcome.",
+        )
+        .sample(name, &[("result", "hit")], hits)
+        .sample(name, &[("result", "miss")], misses);
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = if hits + misses == 0 {
+            0.0
+        } else {
+            hits as f64 / (hits + misses) as f64
+        };
+        out.family(
+            "i1_dnsbl_negative_cache_hit_ratio",
+            Kind::Gauge,
+            "Share of blocklist lookups answered from the negative cache.",
+        )
+        .sample("i1_dnsbl_negative_cache_hit_ratio", &[], ratio);
+        out.family(
+            "i1_dnsbl_negative_cache_entries",
+            Kind::Gauge,
+            "Names held in the negative cache.",
+        )
+        .sample("i1_dnsbl_negative_cache_entries", &[], self.len());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use hickory_server::authority::Authority;
 
+    fn name(ip: &str) -> LowerName {
+        let name = crate::encoding::dnsbl::build_query_name(ip, "bl.i1.is.").unwrap();
+        LowerName::from(Name::parse(&name, None).unwrap())
+    }
+
+    #[test]
+    fn test_negative_cache_evicts_least_recently_used() {
+        let cache = NegativeCache::new(2, Duration::from_secs(60));
+        let (a, b, c) = (name("192.0.2.1"), name("192.0.2.2"), name("192.0.2.3"));
+        assert!(!cache.contains(&a));
+        cache.insert(&a);
+        cache.insert(&b);
+        assert!(cache.contains(&a));
+        // b is now the least recently used.
+        cache.insert(&c);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&a));
+        assert!(!cache.contains(&b));
+        assert!(cache.contains(&c));
+        assert_eq!(cache.stats(), (3, 2));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), (3, 2));
+    }
+
+    #[test]
+    fn test_negative_cache_entries_expire() {
+        let cache = NegativeCache::new(8, Duration::ZERO);
+        let a = name("192.0.2.1");
+        cache.insert(&a);
+        assert!(!cache.contains(&a));
+        assert!(cache.is_empty());
+    }
+
     #[test]
     fn test_create_zone() {
         let origin = Name::parse("bl.i1.is.", None).unwrap();
@@ -133,11 +388,37 @@ mod tests {
     fn test_insert_dnsbl_record() {
         let origin = Name::parse("bl.i1.is.", None).unwrap();
         let mut authority = create_zone(&origin, 1).unwrap();
-        let ip = Ipv4Addr::new(1, 2, 3, 4);
+        let ip = IpAddr::from([1, 2, 3, 4]);
         insert_dnsbl_record(&mut authority, &ip, DnsblCode::Malicious, "bl.i1.is.", 1).unwrap();
         // Record was inserted (no panic, no error).
     }
 
+    #[test]
+    fn test_insert_dnsbl_record_ipv6() {
+        use hickory_proto::rr::RecordType;
+
+        let origin = Name::parse("bl.i1.is.", None).unwrap();
+        let mut authority = create_zone(&origin, 1).unwrap();
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        insert_dnsbl_record(&mut authority, &ip, DnsblCode::Malicious, "bl.i1.is.", 1).unwrap();
+
+        let name = Name::parse(
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.bl.i1.is.",
+            None,
+        )
+        .unwrap();
+        let answer = authority
+            .records_get_mut()
+            .values()
+            .find(|rrset| rrset.name() == &name && rrset.record_type() == RecordType::A)
+            .and_then(|rrset| rrset.records_without_rrsigs().next().cloned())
+            .expect("A record at reversed-nibble name");
+        assert_eq!(
+            answer.data(),
+            &RData::A(A::from(std::net::Ipv4Addr::new(127, 0, 0, 2)))
+        );
+    }
+
     #[test]
     fn test_insert_txt_record() {
         let origin = Name::parse("rep.i1.is.", None).unwrap();
@@ -146,4 +427,16 @@ mod tests {
         insert_txt_record(&mut authority, &name, "cc=cn;threat=high", 7200, 1);
         // Record was inserted (no panic).
     }
+
+    #[test]
+    fn test_split_txt_strings() {
+        assert_eq!(split_txt_strings("cc=cn"), vec!["cc=cn".to_string()]);
+        assert_eq!(split_txt_strings(""), vec![String::new()]);
+
+        let long = "a".repeat(600);
+        let chunks = split_txt_strings(&long);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.len() <= TXT_STRING_MAX));
+        assert_eq!(chunks.concat(), long);
+    }
 }
@@ -0,0 +1,755 @@
+//! Zone transfer serving (AXFR, RFC 5936; IXFR, RFC 1995).
+//!
+//! Each zone is held in a [`ServedZone`] slot that can be swapped for a
+//! rebuilt or transferred version while the server runs. Every swap diffs
+//! the old and new record sets into a journal entry, which is what IXFR
+//! replays; with a [`ZoneDb`] attached, the same difference is written to
+//! disk. [`TransferHandler`] sits in front of the catalog, answers
+//! transfer queries from allowed sources, and passes everything else on.
+//! With a [`Health`] attached it counts the queries it is answering, and
+//! refuses new ones once shutdown starts. With a [`ChaosResponder`]
+//! attached it answers CHAOS-class identity queries and NSID requests.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hickory_proto::dnssec::rdata::DNSSECRData;
+use hickory_proto::op::{Header, MessageType, OpCode, ResponseCode};
+use hickory_proto::rr::{DNSClass, LowerName, Name, RData, Record, RecordType, RrKey};
+use hickory_proto::serialize::binary::BinEncodable;
+use hickory_proto::xfer::Protocol;
+use hickory_server::authority::{
+    AuthLookup, Authority, Catalog, LookupControlFlow, LookupOptions, MessageRequest,
+    MessageResponseBuilder, Nsec3QueryInfo, UpdateResult, ZoneType,
+};
+use hickory_server::dnssec::NxProofKind;
+use hickory_server::server::{Request, RequestHandler, RequestInfo, ResponseHandler, ResponseInfo};
+use hickory_server::store::in_memory::InMemoryAuthority;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+use crate::authority::blocklist_authority::{BlocklistAuthority, Cidr};
+use crate::authority::chaos::{ChaosResponder, NsidResponse};
+use crate::authority::persist::ZoneDb;
+use crate::authority::serial::serial_gt;
+use crate::health::Health;
+use crate::query_log::QueryLog;
+
+/// Per-message budget for transfer responses (TCP messages max out at 64 KiB).
+const MESSAGE_BUDGET: usize = 60_000;
+
+/// An authority whose contents can be listed and rebuilt from records.
+pub trait ZoneStore: Authority<Lookup = AuthLookup> + Sized + 'static {
+    /// Every record in the zone, SOA and RRSIGs included.
+    fn zone_records(&mut self) -> Vec<Record>;
+
+    /// Build the zone from a transferred record set.
+    fn from_records(origin: &Name, records: Vec<Record>) -> Self;
+
+    /// Carry over whatever should outlive a swap from the version being
+    /// replaced.
+    fn inherit(&mut self, _previous: &Self) {}
+}
+
+impl ZoneStore for InMemoryAuthority {
+    fn zone_records(&mut self) -> Vec<Record> {
+        self.records_get_mut()
+            .values()
+            .flat_map(|rrset| rrset.records_with_rrsigs())
+            .cloned()
+            .collect()
+    }
+
+    fn from_records(origin: &Name, records: Vec<Record>) -> Self {
+        let serial = records.iter().find_map(soa_serial).unwrap_or_default();
+        let (rrsigs, records): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .partition(|r| r.record_type() == RecordType::RRSIG);
+        let nx_proof_kind = records
+            .iter()
+            .any(|r| r.record_type() == RecordType::NSEC)
+            .then_some(NxProofKind::Nsec);
+
+        let mut authority = Self::empty(origin.clone(), ZoneType::Secondary, false, nx_proof_kind);
+        for record in records {
+            authority.upsert_mut(record, serial);
+        }
+
+        // Signatures travel as separate records; reattach each to its RRset.
+        let rrsets = authority.records_get_mut();
+        for rrsig in rrsigs {
+            let RData::DNSSEC(DNSSECRData::RRSIG(sig)) = rrsig.data() else {
+                continue;
+            };
+            let key = RrKey::new(rrsig.name().into(), sig.type_covered());
+            if let Some(rrset) = rrsets.get_mut(&key) {
+                Arc::make_mut(rrset).insert_rrsig(rrsig);
+            }
+        }
+        authority
+    }
+}
+
+impl ZoneStore for BlocklistAuthority {
+    fn zone_records(&mut self) -> Vec<Record> {
+        self.store_mut().zone_records()
+    }
+
+    fn from_records(origin: &Name, records: Vec<Record>) -> Self {
+        Self::new(InMemoryAuthority::from_records(origin, records))
+    }
+
+    fn inherit(&mut self, previous: &Self) {
+        self.inherit_negative_cache(previous);
+    }
+}
+
+/// Serial of an SOA record, `None` for any other record.
+#[must_use]
+pub fn soa_serial(record: &Record) -> Option<u32> {
+    match record.data() {
+        RData::SOA(soa) => Some(soa.serial()),
+        _ => None,
+    }
+}
+
+/// Changes between two consecutive versions of a zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneDelta {
+    /// SOA of the version the change applies to.
+    pub from: Record,
+    /// SOA of the version the change produces.
+    pub to: Record,
+    /// Records no longer in the zone.
+    pub removed: Vec<Record>,
+    /// Records new to the zone.
+    pub added: Vec<Record>,
+}
+
+/// Type-erased transfer view of a [`ServedZone`].
+///
+/// Used by [`TransferHandler`] to answer transfers and by the sync client
+/// to load what it received.
+pub trait TransferZone: Send + Sync {
+    /// Zone origin.
+    fn name(&self) -> &LowerName;
+
+    /// Current SOA record, if the zone has one.
+    fn soa_record(&self) -> Option<Record>;
+
+    /// All records, SOA first.
+    fn records(&self) -> Vec<Record>;
+
+    /// Full transfer: SOA, every other record, SOA.
+    fn axfr(&self) -> Vec<Record>;
+
+    /// Incremental transfer from `since`, or `None` when the journal does
+    /// not reach back that far and the client needs a full transfer.
+    fn ixfr(&self, since: u32) -> Option<Vec<Record>>;
+
+    /// Replace the zone with a transferred record set.
+    fn load(&self, records: Vec<Record>);
+}
+
+struct ZoneState<A> {
+    authority: Arc<A>,
+    soa: Option<Record>,
+    records: BTreeSet<Record>,
+    journal: VecDeque<ZoneDelta>,
+    /// When this version started being served.
+    since: DateTime<Utc>,
+}
+
+/// A swappable zone slot with an IXFR journal.
+pub struct ServedZone<A> {
+    origin: LowerName,
+    nx_proof_kind: Option<NxProofKind>,
+    journal_len: usize,
+    store: Option<Arc<ZoneDb>>,
+    /// Held across a swap and its store write, so writes land in order.
+    swap: Mutex<()>,
+    state: RwLock<ZoneState<A>>,
+}
+
+impl<A: ZoneStore> ServedZone<A> {
+    /// Serve `authority`, keeping up to `journal_len` versions for IXFR.
+    pub fn new(mut authority: A, journal_len: usize) -> Self {
+        let origin = authority.origin().clone();
+        let nx_proof_kind = authority.nx_proof_kind().cloned();
+        let (soa, records) = split_soa(authority.zone_records());
+        Self {
+            origin,
+            nx_proof_kind,
+            journal_len,
+            store: None,
+            swap: Mutex::new(()),
+            state: RwLock::new(ZoneState {
+                authority: Arc::new(authority),
+                soa,
+                records,
+                journal: VecDeque::new(),
+                since: Utc::now(),
+            }),
+        }
+    }
+
+    /// Save the zone to `store` now, and every change to it from then on.
+    ///
+    /// Store failures are logged; the zone is served either way.
+    #[must_use]
+    pub fn persisted(mut self, store: Arc<ZoneDb>) -> Self {
+        let state = self.read();
+        if let Err(e) = store.save_zone(&self.origin, state.soa.as_ref(), &state.records) {
+            warn!(zone = %self.origin, error = %e, "failed to save zone");
+        }
+        drop(state);
+        self.store = Some(store);
+        self
+    }
+
+    /// The authority currently being served.
+    pub fn authority(&self) -> Arc<A> {
+        Arc::clone(&self.read().authority)
+    }
+
+    /// Current SOA serial.
+    pub fn serial(&self) -> Option<u32> {
+        self.read().soa.as_ref().and_then(soa_serial)
+    }
+
+    /// When the current version was loaded or swapped in.
+    pub fn served_since(&self) -> DateTime<Utc> {
+        self.read().since
+    }
+
+    /// Swap in a new version of the zone, journaling the difference.
+    ///
+    /// The journal is reset when the new serial does not move forward,
+    /// since IXFR clients could not apply it.
+    pub fn replace(&self, mut authority: A) {
+        let (soa, records) = split_soa(authority.zone_records());
+        let swap = self.swap.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        let removed: Vec<Record> = state.records.difference(&records).cloned().collect();
+        let added: Vec<Record> = records.difference(&state.records).cloned().collect();
+
+        match (&state.soa, &soa) {
+            (Some(from), Some(to)) if is_newer(to, from) => {
+                let delta = ZoneDelta {
+                    from: from.clone(),
+                    to: to.clone(),
+                    removed: removed.clone(),
+                    added: added.clone(),
+                };
+                debug!(
+                    zone = %self.origin,
+                    removed = delta.removed.len(),
+                    added = delta.added.len(),
+                    "journaled zone change"
+                );
+                state.journal.push_back(delta);
+                while state.journal.len() > self.journal_len {
+                    state.journal.pop_front();
+                }
+            }
+            _ => state.journal.clear(),
+        }
+
+        authority.inherit(&state.authority);
+        state.authority = Arc::new(authority);
+        state.soa = soa;
+        state.records = records;
+        state.since = Utc::now();
+        let soa = state.soa.clone();
+        drop(state);
+
+        // Queries only wait for the swap, not the disk.
+        if let Some(store) = &self.store {
+            if let Err(e) = store.journal(&self.origin, soa.as_ref(), &removed, &added) {
+                warn!(zone = %self.origin, error = %e, "failed to journal zone change");
+            }
+        }
+        drop(swap);
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, ZoneState<A>> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<A: ZoneStore> TransferZone for ServedZone<A> {
+    fn name(&self) -> &LowerName {
+        &self.origin
+    }
+
+    fn soa_record(&self) -> Option<Record> {
+        self.read().soa.clone()
+    }
+
+    fn records(&self) -> Vec<Record> {
+        let state = self.read();
+        state
+            .soa
+            .iter()
+            .chain(state.records.iter())
+            .cloned()
+            .collect()
+    }
+
+    fn axfr(&self) -> Vec<Record> {
+        let state = self.read();
+        let Some(soa) = &state.soa else {
+            return Vec::new();
+        };
+        std::iter::once(soa)
+            .chain(state.records.iter())
+            .chain(std::iter::once(soa))
+            .cloned()
+            .collect()
+    }
+
+    fn ixfr(&self, since: u32) -> Option<Vec<Record>> {
+        let state = self.read();
+        let soa = state.soa.clone()?;
+        let current = soa_serial(&soa)?;
+
+        // Up to date: the lone SOA says so. A client ahead of us (the
+        // primary's serial went backwards) needs the full zone.
+        if current == since {
+            return Some(vec![soa]);
+        }
+        if !serial_gt(current, since) {
+            return None;
+        }
+
+        let start = state
+            .journal
+            .iter()
+            .position(|d| soa_serial(&d.from) == Some(since))?;
+        let mut records = vec![soa.clone()];
+        for delta in state.journal.iter().skip(start) {
+            records.push(delta.from.clone());
+            records.extend(delta.removed.iter().cloned());
+            records.push(delta.to.clone());
+            records.extend(delta.added.iter().cloned());
+        }
+        drop(state);
+        records.push(soa);
+        Some(records)
+    }
+
+    fn load(&self, records: Vec<Record>) {
+        self.replace(A::from_records(&Name::from(self.origin.clone()), records));
+    }
+}
+
+#[async_trait]
+impl<A: ZoneStore> Authority for ServedZone<A> {
+    type Lookup = AuthLookup;
+
+    fn zone_type(&self) -> ZoneType {
+        self.read().authority.zone_type()
+    }
+
+    /// Transfers are answered by [`TransferHandler`], never the catalog.
+    fn is_axfr_allowed(&self) -> bool {
+        false
+    }
+
+    async fn update(&self, update: &MessageRequest) -> UpdateResult<bool> {
+        self.authority().update(update).await
+    }
+
+    fn origin(&self) -> &LowerName {
+        &self.origin
+    }
+
+    async fn lookup(
+        &self,
+        name: &LowerName,
+        rtype: RecordType,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.authority().lookup(name, rtype, lookup_options).await
+    }
+
+    async fn search(
+        &self,
+        request_info: RequestInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.authority().search(request_info, lookup_options).await
+    }
+
+    async fn get_nsec_records(
+        &self,
+        name: &LowerName,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.authority()
+            .get_nsec_records(name, lookup_options)
+            .await
+    }
+
+    async fn get_nsec3_records(
+        &self,
+        info: Nsec3QueryInfo<'_>,
+        lookup_options: LookupOptions,
+    ) -> LookupControlFlow<Self::Lookup> {
+        self.authority()
+            .get_nsec3_records(info, lookup_options)
+            .await
+    }
+
+    /// Fixed when the slot is created; every version of a zone uses the
+    /// same denial scheme.
+    fn nx_proof_kind(&self) -> Option<&NxProofKind> {
+        self.nx_proof_kind.as_ref()
+    }
+}
+
+/// Source addresses allowed to transfer zones.
+#[derive(Debug, Clone, Default)]
+pub struct TransferAcl {
+    allowed: Vec<Cidr>,
+}
+
+impl TransferAcl {
+    /// Parse IPs and CIDRs (as in `TransferConfig::allow_from`).
+    pub fn parse(entries: &[String]) -> crate::Result<Self> {
+        let allowed = entries
+            .iter()
+            .map(|entry| {
+                if entry.contains('/') {
+                    Cidr::parse(entry)
+                } else {
+                    entry.parse::<IpAddr>().map(Cidr::from).map_err(|e| {
+                        crate::SrvError::Config(format!("invalid transfer peer '{entry}': {e}"))
+                    })
+                }
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self { allowed })
+    }
+
+    /// Whether `ip` may transfer zones.
+    #[must_use]
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.allowed.iter().any(|cidr| cidr.contains(&ip))
+    }
+}
+
+/// Request handler that serves AXFR/IXFR and delegates the rest to a catalog.
+pub struct TransferHandler {
+    catalog: Catalog,
+    zones: HashMap<LowerName, Arc<dyn TransferZone>>,
+    acl: TransferAcl,
+    query_log: Option<QueryLog>,
+    health: Option<Health>,
+    chaos: Option<ChaosResponder>,
+}
+
+impl TransferHandler {
+    /// Serve transfers of `zones` to peers allowed by `acl`.
+    #[must_use]
+    pub fn new(catalog: Catalog, zones: Vec<Arc<dyn TransferZone>>, acl: TransferAcl) -> Self {
+        Self {
+            catalog,
+            zones: zones
+                .into_iter()
+                .map(|zone| (zone.name().clone(), zone))
+                .collect(),
+            acl,
+            query_log: None,
+            health: None,
+            chaos: None,
+        }
+    }
+
+    /// Record answered queries in `log`.
+    #[must_use]
+    pub fn with_query_log(mut self, log: QueryLog) -> Self {
+        self.query_log = Some(log);
+        self
+    }
+
+    /// Count queries as in flight in `health`, and refuse them once it is
+    /// draining so resolvers move on to another server.
+    #[must_use]
+    pub fn with_health(mut self, health: Health) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Answer CHAOS-class queries and NSID requests with `chaos`.
+    #[must_use]
+    pub fn with_chaos(mut self, chaos: ChaosResponder) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    async fn transfer<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+    ) -> ResponseInfo {
+        let query = &request.queries()[0];
+        let src = request.src();
+
+        let Some(zone) = self.zones.get(query.name()) else {
+            return send_error(request, response_handle, ResponseCode::NotAuth).await;
+        };
+        if !self.acl.allows(src.ip()) {
+            warn!(%src, zone = %query.name(), "refused zone transfer from unlisted peer");
+            return send_error(request, response_handle, ResponseCode::Refused).await;
+        }
+
+        let mut records = match query.query_type() {
+            RecordType::IXFR => request
+                .name_servers()
+                .iter()
+                .find_map(soa_serial)
+                .and_then(|since| zone.ixfr(since))
+                .unwrap_or_else(|| zone.axfr()),
+            _ => zone.axfr(),
+        };
+        if records.is_empty() {
+            return send_error(request, response_handle, ResponseCode::ServFail).await;
+        }
+        if request.protocol() == Protocol::Udp {
+            // Transfers don't fit in UDP: a lone SOA tells IXFR clients to
+            // retry over TCP (RFC 1995 section 2).
+            if query.query_type() != RecordType::IXFR {
+                return send_error(request, response_handle, ResponseCode::Refused).await;
+            }
+            records.truncate(1);
+        }
+
+        debug!(%src, zone = %query.name(), kind = %query.query_type(), records = records.len(), "serving zone transfer");
+
+        let mut info = None;
+        for chunk in chunk_records(&records) {
+            let mut header = Header::response_from_request(request.header());
+            header.set_authoritative(true);
+            let response = MessageResponseBuilder::from_message_request(request).build(
+                header,
+                chunk.iter(),
+                std::iter::empty(),
+                std::iter::empty(),
+                std::iter::empty(),
+            );
+            match response_handle.send_response(response).await {
+                Ok(sent) => info = Some(sent),
+                Err(e) => {
+                    warn!(%src, error = %e, "zone transfer aborted");
+                    return serve_failed();
+                }
+            }
+        }
+        info.unwrap_or_else(serve_failed)
+    }
+}
+
+#[async_trait]
+impl RequestHandler for TransferHandler {
+    async fn handle_request<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        response_handle: R,
+    ) -> ResponseInfo {
+        let is_query = request.message_type() == MessageType::Query
+            && request.op_code() == OpCode::Query
+            && request.queries().len() == 1;
+        let is_transfer = is_query
+            && matches!(
+                request.queries()[0].query_type(),
+                RecordType::AXFR | RecordType::IXFR
+            );
+        let chaos = self
+            .chaos
+            .as_ref()
+            .filter(|_| is_query && request.queries()[0].query_class() == DNSClass::CH);
+        let nsid = self.chaos.as_ref().and_then(|chaos| chaos.nsid(request));
+        let response_handle = NsidResponse::new(response_handle, nsid);
+
+        let started = Instant::now();
+        let _in_flight = self.health.as_ref().map(Health::track);
+        let info = if self.health.as_ref().is_some_and(Health::is_draining) {
+            send_error(request, response_handle, ResponseCode::Refused).await
+        } else if is_transfer {
+            self.transfer(request, response_handle).await
+        } else if let Some(chaos) = chaos {
+            chaos.answer(request, response_handle).await
+        } else {
+            self.catalog.handle_request(request, response_handle).await
+        };
+        if let Some(log) = &self.query_log {
+            log.record(request, &info, started.elapsed());
+        }
+        info
+    }
+}
+
+/// Split off the SOA from the rest of a zone's records.
+fn split_soa(records: Vec<Record>) -> (Option<Record>, BTreeSet<Record>) {
+    let (soa, rest): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .partition(|r| r.record_type() == RecordType::SOA);
+    (soa.into_iter().next(), rest.into_iter().collect())
+}
+
+fn is_newer(to: &Record, from: &Record) -> bool {
+    matches!((soa_serial(to), soa_serial(from)), (Some(to), Some(from)) if serial_gt(to, from))
+}
+
+/// Group records into messages that stay under [`MESSAGE_BUDGET`].
+fn chunk_records(records: &[Record]) -> Vec<&[Record]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (i, record) in records.iter().enumerate() {
+        let len = record.to_bytes().map_or(512, |b| b.len());
+        if size + len > MESSAGE_BUDGET && i > start {
+            chunks.push(&records[start..i]);
+            start = i;
+            size = 0;
+        }
+        size += len;
+    }
+    if start < records.len() {
+        chunks.push(&records[start..]);
+    }
+    chunks
+}
+
+pub(super) async fn send_error<R: ResponseHandler>(
+    request: &Request,
+    mut response_handle: R,
+    code: ResponseCode,
+) -> ResponseInfo {
+    let response =
+        MessageResponseBuilder::from_message_request(request).error_msg(request.header(), code);
+    response_handle
+        .send_response(response)
+        .await
+        .unwrap_or_else(|_| serve_failed())
+}
+
+pub(super) fn serve_failed() -> ResponseInfo {
+    let mut header = Header::new();
+    header.set_response_code(ResponseCode::ServFail);
+    header.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::dnssec::ZoneSigningKeys;
+    use crate::authority::threat_authority;
+    use crate::encoding::dnsbl::DnsblCode;
+    use std::time::Duration;
+
+    fn zone(serial: u32, ips: &[&str]) -> InMemoryAuthority {
+        let origin = Name::parse("bl.i1.is.", None).unwrap();
+        let mut authority = threat_authority::create_zone(&origin, serial).unwrap();
+        for ip in ips {
+            threat_authority::insert_dnsbl_record(
+                &mut authority,
+                &ip.parse().unwrap(),
+                DnsblCode::Listed,
+                "bl.i1.is.",
+                serial,
+            )
+            .unwrap();
+        }
+        authority
+    }
+
+    #[test]
+    fn test_axfr_brackets_records_with_soa() {
+        let served = ServedZone::new(zone(10, &["1.2.3.4", "5.6.7.8"]), 4);
+        let records = served.axfr();
+        assert_eq!(records.len(), 4);
+        assert_eq!(soa_serial(&records[0]), Some(10));
+        assert_eq!(soa_serial(&records[3]), Some(10));
+        assert_eq!(served.serial(), Some(10));
+    }
+
+    #[test]
+    fn test_ixfr_replays_journal() {
+        let served = ServedZone::new(zone(10, &["1.2.3.4", "5.6.7.8"]), 4);
+        served.replace(zone(11, &["1.2.3.4", "9.9.9.9"]));
+        served.replace(zone(12, &["1.2.3.4"]));
+
+        // From 11: one delta removing 9.9.9.9.
+        let records = served.ixfr(11).unwrap();
+        let serials: Vec<Option<u32>> = records.iter().map(soa_serial).collect();
+        assert_eq!(serials, vec![Some(12), Some(11), None, Some(12), Some(12)]);
+
+        // From 10: two deltas chained.
+        let records = served.ixfr(10).unwrap();
+        assert_eq!(records.iter().filter_map(soa_serial).count(), 6);
+
+        // Current or unknown serials.
+        assert_eq!(served.ixfr(12).unwrap().len(), 1);
+        assert!(served.ixfr(7).is_none());
+    }
+
+    #[test]
+    fn test_journal_is_bounded_and_reset() {
+        let served = ServedZone::new(zone(10, &[]), 1);
+        served.replace(zone(11, &["1.2.3.4"]));
+        served.replace(zone(12, &["5.6.7.8"]));
+        assert!(served.ixfr(10).is_none());
+        assert!(served.ixfr(11).is_some());
+
+        // A serial that goes backwards invalidates the journal.
+        served.replace(zone(5, &[]));
+        assert!(served.ixfr(11).is_none());
+    }
+
+    #[test]
+    fn test_signed_zone_round_trips() {
+        let origin = Name::parse("bl.i1.is.", None).unwrap();
+        let keys =
+            ZoneSigningKeys::generate(std::slice::from_ref(&origin), Duration::from_secs(3600))
+                .unwrap();
+        let mut signed = zone(10, &["1.2.3.4"]);
+        keys.sign_zone(&mut signed).unwrap();
+        let records = signed.zone_records();
+        assert!(records.iter().any(|r| r.record_type() == RecordType::RRSIG));
+
+        // RRSIGs land back on the RRsets they cover, not as RRsets of their own.
+        let mut copy = InMemoryAuthority::from_records(&origin, records.clone());
+        assert!(copy.nx_proof_kind().is_some());
+        assert!(!copy
+            .records_get_mut()
+            .keys()
+            .any(|key| key.record_type == RecordType::RRSIG));
+        assert_eq!(copy.zone_records().len(), records.len());
+    }
+
+    #[test]
+    fn test_acl() {
+        let acl = TransferAcl::parse(&["10.0.0.0/8".into(), "2001:db8::5".into()]).unwrap();
+        assert!(acl.allows("10.1.2.3".parse().unwrap()));
+        assert!(acl.allows("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(acl.allows("2001:db8::5".parse().unwrap()));
+        assert!(!acl.allows("192.0.2.1".parse().unwrap()));
+        assert!(!TransferAcl::default().allows("127.0.0.1".parse().unwrap()));
+        assert!(TransferAcl::parse(&["nope".into()]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_records() {
+        let served = ServedZone::new(zone(1, &[]), 0);
+        let soa = served.soa_record().unwrap();
+        let records = vec![soa; 2_000];
+        let chunks = chunk_records(&records);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 2_000);
+    }
+}
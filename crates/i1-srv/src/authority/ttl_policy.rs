@@ -3,6 +3,19 @@
 //! TTLs are set high for resilience: cached records provide protection
 //! even when i1-srv nodes are unreachable. Signal records use near-zero
 //! TTLs so clients can detect when their cache is stale.
+//!
+//! Entries that carry threat metadata get a TTL scaled within a per-zone
+//! [`TtlBand`] instead ([`entry_ttl`]): a freshly detected, active attacker
+//! gets the band's minimum so an unban propagates fast, while a year-old
+//! entry that has gone quiet is cached for up to the maximum. Every
+//! rebuild also decays that metadata ([`decay`]), so stale intel ages out
+//! instead of being served forever.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::authority::zone_builder::BlockEntry;
+use crate::config::{TtlBand, TtlConfig};
 
 /// TTL for NS referral records (maximum practical).
 /// Keeps zone delegation alive in caches for 24 hours.
@@ -36,10 +49,18 @@ pub const BLOCKLIST_COMMUNITY_TTL: u32 = 43200;
 /// Changes moderately as new intel arrives.
 pub const REPUTATION_TTL: u32 = 7200;
 
+/// TTL for structured intel (CBOR) TXT records.
+/// Same cadence as reputation data.
+pub const INTEL_TTL: u32 = 7200;
+
 /// TTL for signal/version-check records.
 /// Near-zero for fast staleness detection.
 pub const SIGNAL_TTL: u32 = 30;
 
+/// TTL for the TTL monitor's canary records.
+/// As long as a confirmed block, so resolvers that clamp those show up.
+pub const TTLCHECK_TTL: u32 = 86400;
+
 /// TTL for geo/ASN block records.
 /// Country and ASN blocks are stable data.
 pub const GEO_ASN_TTL: u32 = 86400;
@@ -66,6 +87,150 @@ pub const SOA_RETRY: i32 = 900;
 /// SOA expire interval.
 pub const SOA_EXPIRE: i32 = 604_800;
 
+/// Quiet days after which an entry counts as settled.
+const SETTLED_DAYS: f64 = 30.0;
+
+/// Listed days after which an entry counts as established.
+const ESTABLISHED_DAYS: f64 = 365.0;
+
+/// Hits at which an entry counts as a confirmed repeat offender.
+const REPEAT_OFFENDER_HITS: f64 = 100.0;
+
+/// Threat level of an entry, least severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ThreatLevel {
+    /// Seen once, or long quiet.
+    Low,
+    /// Suspicious activity.
+    Medium,
+    /// Confirmed malicious.
+    High,
+    /// Active, confirmed and damaging.
+    Critical,
+}
+
+impl ThreatLevel {
+    /// Parse a level name; unrecognized names count as medium.
+    #[must_use]
+    pub fn parse(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "low" | "info" => Self::Low,
+            "high" | "blocked" | "malicious" => Self::High,
+            "critical" => Self::Critical,
+            _ => Self::Medium,
+        }
+    }
+
+    /// Level name, as published in reputation records.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+
+    /// The next level down, or `None` below low.
+    #[must_use]
+    pub const fn lower(self) -> Option<Self> {
+        match self {
+            Self::Low => None,
+            Self::Medium => Some(Self::Low),
+            Self::High => Some(Self::Medium),
+            Self::Critical => Some(Self::High),
+        }
+    }
+
+    /// Confidence the level lends an entry, 0-1.
+    const fn weight(self) -> f64 {
+        match self {
+            Self::Low => 0.25,
+            Self::Medium => 0.5,
+            Self::High => 0.75,
+            Self::Critical => 1.0,
+        }
+    }
+}
+
+impl From<String> for ThreatLevel {
+    fn from(name: String) -> Self {
+        Self::parse(&name)
+    }
+}
+
+impl From<ThreatLevel> for String {
+    fn from(level: ThreatLevel) -> Self {
+        level.as_str().to_string()
+    }
+}
+
+/// TTL for an entry within `band`.
+///
+/// Entries without timestamps get the band's maximum, as they did before
+/// TTLs were dynamic. Otherwise the TTL grows from the minimum with how
+/// long the entry has been listed (fully after a year) and how long it has
+/// been quiet (fully after a month), scaled by confidence: its threat
+/// level, pulled toward full by repeated hits.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn entry_ttl(entry: &BlockEntry, band: TtlBand, now: DateTime<Utc>) -> u32 {
+    let (min, max) = (band.min.min(band.max), band.max.max(band.min));
+    let Some(last_seen) = entry.last_seen.or(entry.first_seen) else {
+        return max;
+    };
+    let first_seen = entry.first_seen.unwrap_or(last_seen);
+
+    let quiet = (days_since(last_seen, now) / SETTLED_DAYS).clamp(0.0, 1.0);
+    let established = (days_since(first_seen, now) / ESTABLISHED_DAYS).clamp(0.0, 1.0);
+    let weight = entry.threat.map_or(1.0, ThreatLevel::weight);
+    let repeat = (f64::from(entry.hits) / REPEAT_OFFENDER_HITS).min(1.0);
+    let confidence = (1.0 - weight).mul_add(repeat, weight);
+
+    let scale = (quiet + established) / 2.0 * confidence;
+    min + (f64::from(max - min) * scale).round() as u32
+}
+
+/// Age an entry's metadata to `now`; `None` once it has aged out.
+///
+/// Hits halve every `hits_half_life_days` of quiet since the entry was
+/// last seen, and its threat level drops a step every `level_step_days`
+/// (an entry without a level starts from medium). An entry stepped below
+/// low has aged out. Entries without timestamps don't decay.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn decay(entry: &BlockEntry, config: &TtlConfig, now: DateTime<Utc>) -> Option<BlockEntry> {
+    let Some(last_seen) = entry.last_seen.or(entry.first_seen) else {
+        return Some(entry.clone());
+    };
+    let quiet = days_since(last_seen, now);
+
+    let mut decayed = entry.clone();
+    if config.hits_half_life_days > 0 {
+        let half_lives = quiet / f64::from(config.hits_half_life_days);
+        decayed.hits = (f64::from(entry.hits) * 0.5_f64.powf(half_lives)).floor() as u32;
+    }
+    if config.level_step_days > 0 {
+        let steps = (quiet / f64::from(config.level_step_days)).floor() as u32;
+        let mut level = entry.threat.unwrap_or(ThreatLevel::Medium);
+        for _ in 0..steps {
+            level = level.lower()?;
+        }
+        if steps > 0 || entry.threat.is_some() {
+            decayed.threat = Some(level);
+        }
+    }
+    Some(decayed)
+}
+
+/// Whole and fractional days from `then` to `now`, 0 if `then` is later.
+#[allow(clippy::cast_precision_loss)]
+fn days_since(then: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    (now - then).num_seconds().max(0) as f64 / 86_400.0
+}
+
 /// Select the appropriate TTL based on threat classification.
 pub const fn ttl_for_threat_class(class: ThreatClass) -> u32 {
     match class {
@@ -103,6 +268,114 @@ mod tests {
         assert!(SOA_MINIMUM_TTL <= 600);
     }
 
+    fn entry(threat: Option<ThreatLevel>, hits: u32, listed: i64, quiet: i64) -> BlockEntry {
+        let now = NOW.parse::<DateTime<Utc>>().unwrap();
+        BlockEntry {
+            threat,
+            hits,
+            first_seen: Some(now - chrono::Duration::days(listed)),
+            last_seen: Some(now - chrono::Duration::days(quiet)),
+            ..BlockEntry::default()
+        }
+    }
+
+    const NOW: &str = "2026-06-01T00:00:00Z";
+
+    const BAND: TtlBand = TtlBand {
+        min: 300,
+        max: 86_400,
+    };
+
+    #[test]
+    fn test_entry_ttl_banding() {
+        let now = NOW.parse().unwrap();
+        // Without timestamps an entry keeps the fixed maximum.
+        assert_eq!(entry_ttl(&BlockEntry::default(), BAND, now), 86_400);
+
+        // A fresh, active attacker gets the minimum...
+        let fresh = entry(Some(ThreatLevel::Critical), 500, 0, 0);
+        assert_eq!(entry_ttl(&fresh, BAND, now), 300);
+        // ...a year-old, quiet one the maximum...
+        let settled = entry(Some(ThreatLevel::Critical), 0, 400, 60);
+        assert_eq!(entry_ttl(&settled, BAND, now), 86_400);
+        // ...and a year-old one still active is cached for hours.
+        let persistent = entry(Some(ThreatLevel::Critical), 0, 365, 0);
+        assert_eq!(entry_ttl(&persistent, BAND, now), 43_350);
+
+        // Lower levels scale down, and hits pull them back up.
+        let low = entry(Some(ThreatLevel::Low), 0, 400, 60);
+        assert_eq!(entry_ttl(&low, BAND, now), 21_825);
+        let repeat = entry(Some(ThreatLevel::Low), 100, 400, 60);
+        assert_eq!(entry_ttl(&repeat, BAND, now), 86_400);
+
+        // TTLs always stay within the band, whichever way it is written.
+        let inverted = TtlBand { min: 600, max: 60 };
+        for e in [&fresh, &settled, &persistent, &low] {
+            let ttl = entry_ttl(e, inverted, now);
+            assert!((60..=600).contains(&ttl), "{ttl}");
+        }
+    }
+
+    #[test]
+    fn test_decay_curve() {
+        let config = TtlConfig::default();
+        let now = NOW.parse().unwrap();
+
+        // Hits halve every week of quiet.
+        let hits: Vec<u32> = [0, 7, 14, 21]
+            .iter()
+            .map(|quiet| {
+                decay(
+                    &entry(Some(ThreatLevel::High), 80, 100, *quiet),
+                    &config,
+                    now,
+                )
+                .unwrap()
+                .hits
+            })
+            .collect();
+        assert_eq!(hits, [80, 40, 20, 10]);
+
+        // The level steps down every 30 quiet days, then ages out.
+        let level = |quiet| {
+            decay(
+                &entry(Some(ThreatLevel::Critical), 0, 200, quiet),
+                &config,
+                now,
+            )
+            .map(|e| e.threat.unwrap())
+        };
+        assert_eq!(level(29), Some(ThreatLevel::Critical));
+        assert_eq!(level(30), Some(ThreatLevel::High));
+        assert_eq!(level(90), Some(ThreatLevel::Low));
+        assert_eq!(level(120), None);
+
+        // Unleveled entries start from medium; undated ones never decay.
+        let unleveled = decay(&entry(None, 0, 40, 31), &config, now).unwrap();
+        assert_eq!(unleveled.threat, Some(ThreatLevel::Low));
+        assert!(decay(&entry(None, 0, 90, 61), &config, now).is_none());
+        let undated = BlockEntry {
+            hits: 9,
+            ..BlockEntry::default()
+        };
+        assert_eq!(decay(&undated, &config, now).unwrap().hits, 9);
+
+        // Disabled stepping keeps entries forever.
+        let forever = TtlConfig {
+            level_step_days: 0,
+            ..TtlConfig::default()
+        };
+        assert!(decay(&entry(None, 0, 900, 900), &forever, now).is_some());
+    }
+
+    #[test]
+    fn test_threat_level_names() {
+        assert_eq!(ThreatLevel::parse("blocked"), ThreatLevel::High);
+        assert_eq!(ThreatLevel::parse("CRITICAL"), ThreatLevel::Critical);
+        assert_eq!(ThreatLevel::parse("whatever"), ThreatLevel::Medium);
+        assert!(ThreatLevel::Low < ThreatLevel::Critical);
+    }
+
     #[test]
     fn test_ttl_for_threat_class() {
         assert_eq!(ttl_for_threat_class(ThreatClass::Confirmed), 86400);
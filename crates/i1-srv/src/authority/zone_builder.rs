@@ -3,19 +3,35 @@
 //! Reads blocked IPs, countries, ASNs from i1-cli's `defend::State`
 //! and builds the corresponding DNS zone records for each authority.
 //! Also builds binary consensus (`bin.i1.is`) and certificate consensus
-//! (`ca.i1.is`) zones from published audit snapshots.
+//! (`ca.i1.is`) zones from published audit snapshots, and the structured
+//! intel zone (`intel.i1.is`) from CBOR-encoded [`txt_intel::ComplexIntel`].
 
+use chrono::{DateTime, Utc};
 use hickory_proto::rr::rdata::TXT;
-use hickory_proto::rr::{Name, RData, Record};
+use hickory_proto::rr::{LowerName, Name, RData, Record};
 use hickory_server::store::in_memory::InMemoryAuthority;
-use std::net::Ipv4Addr;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr};
 use tracing::warn;
 
-use crate::authority::{threat_authority, ttl_policy};
-use crate::config::ZoneConfig;
+use i1_audit::Attestation;
+
+use crate::authority::blocklist_authority::{BlocklistAuthority, Cidr};
+use crate::authority::dnssec::ZoneSigningKeys;
+use crate::authority::persist::SavedZones;
+use crate::authority::serial::serial_gt;
+use crate::authority::transfer::ZoneStore;
+use crate::authority::ttl_policy::ThreatLevel;
+use crate::authority::{rpz, threat_authority, ttl_policy};
+use crate::config::{TtlConfig, ZoneConfig};
+use crate::encoding::asn_prefixes::{self, PrefixChunk, PrefixIndex};
 use crate::encoding::dnsbl::DnsblCode;
 use crate::encoding::signal::SignalData;
 use crate::encoding::txt_intel;
+use crate::sync::prefixes::AsnPrefixes;
+use crate::trust::node_signals::NodeSignal;
+use crate::trust::ttl_monitor::{self, ResolverReport};
 
 /// Defense state data needed to build zones.
 ///
@@ -35,6 +51,56 @@ pub struct DefenseSnapshot {
     pub whitelisted_ips: Vec<String>,
     /// Audit data from published snapshots (binaries + certs).
     pub audit: Option<AuditData>,
+    /// Structured intel keyed by IP address.
+    pub intel: BTreeMap<String, txt_intel::ComplexIntel>,
+    /// Threat metadata for entries in `blocked_ips`, keyed the same way.
+    /// Entries without any are published with the blocklist defaults.
+    pub block_entries: BTreeMap<String, BlockEntry>,
+    /// Announced prefixes for blocked ASNs, keyed by AS number.
+    pub asn_prefixes: BTreeMap<u32, AsnPrefixes>,
+    /// Signal records published for other nodes through the admin API.
+    pub node_signals: Vec<NodeSignal>,
+}
+
+/// Threat metadata recorded for a blocked address or range.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct BlockEntry {
+    /// How severe the threat is.
+    #[serde(default)]
+    pub threat: Option<ThreatLevel>,
+    /// Number of hits/detections.
+    #[serde(default)]
+    pub hits: u32,
+    /// When the entry was first detected.
+    #[serde(default)]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// When the entry was last active.
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Why the entry was blocked.
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// What the address was caught doing (e.g. "ssh", "web-scan").
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// How long the block lasts from `first_seen` (seconds); an entry
+    /// without either is blocked until removed.
+    #[serde(default)]
+    pub ttl: Option<u64>,
+    /// Node that observed the entry (e.g. `node-a.srv.i1.is`); its first
+    /// label is published in the reputation record.
+    #[serde(default)]
+    pub origin: Option<String>,
+}
+
+impl BlockEntry {
+    /// Has the block's `ttl` run out by `now`?
+    #[must_use]
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.first_seen.zip(self.ttl).is_some_and(|(since, ttl)| {
+            i64::try_from(ttl).is_ok_and(|ttl| (now - since).num_seconds() >= ttl)
+        })
+    }
 }
 
 /// Audit data extracted from a published `AuditSnapshot`.
@@ -46,12 +112,14 @@ pub struct AuditData {
     pub root_certs: Vec<i1_audit::RootCertInfo>,
     /// Node identifier (machine-id or hostname).
     pub node_id: String,
+    /// When the snapshot was collected (this node's trust digest time).
+    pub collected_at: DateTime<Utc>,
 }
 
 /// Result of building all zones from a defense snapshot.
 pub struct BuiltZones {
-    /// DNSBL zone (bl.i1.is) - reversed-IP -> A record.
-    pub blocklist: InMemoryAuthority,
+    /// DNSBL zone (bl.i1.is) - reversed-IP -> A record, CIDR-aware.
+    pub blocklist: BlocklistAuthority,
     /// Reputation zone (rep.i1.is) - reversed-IP -> TXT record.
     pub reputation: InMemoryAuthority,
     /// Geo zone (geo.i1.is) - country codes -> TXT status.
@@ -64,35 +132,79 @@ pub struct BuiltZones {
     pub binary: InMemoryAuthority,
     /// Certificate consensus zone (ca.i1.is) - fingerprint prefix -> TXT.
     pub cert: InMemoryAuthority,
+    /// Structured intel zone (intel.i1.is) - reversed-IP -> CBOR TXT.
+    pub intel: InMemoryAuthority,
+    /// Response policy zone, when it is served (unsigned).
+    pub rpz: Option<InMemoryAuthority>,
     /// Zone serial used.
     pub serial: u32,
     /// Total entry count across all zones.
     pub entry_count: u32,
 }
 
+/// Optional behavior for zone building.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BuildOptions<'a> {
+    /// Sign reputation and intel TXT payloads with this node key.
+    pub signer: Option<&'a i1_ca::NodeSigner>,
+    /// This node's public address, published as the network bucket in
+    /// binary attestations so clients can weight consensus by diversity.
+    pub public_ip: Option<IpAddr>,
+    /// Sign every zone with DNSSEC once it is populated.
+    pub dnssec: Option<&'a ZoneSigningKeys>,
+    /// Also build the blocklist as a response policy zone at this origin.
+    pub rpz: Option<&'a str>,
+    /// TTL bands and decay; the defaults when unset.
+    pub ttl: Option<&'a TtlConfig>,
+    /// TTL monitor findings to publish in the signal zone.
+    pub ttl_reports: &'a [ResolverReport],
+}
+
 /// Build all DNS zones from a defense state snapshot.
 pub fn build_zones(
     snapshot: &DefenseSnapshot,
     zones: &ZoneConfig,
     serial: u32,
 ) -> crate::Result<BuiltZones> {
-    let mut blocklist = threat_authority::create_zone(&parse_name(&zones.blocklist)?, serial)?;
+    build_zones_with(snapshot, zones, serial, BuildOptions::default())
+}
+
+/// Build all DNS zones with explicit [`BuildOptions`].
+pub fn build_zones_with(
+    snapshot: &DefenseSnapshot,
+    zones: &ZoneConfig,
+    serial: u32,
+    options: BuildOptions<'_>,
+) -> crate::Result<BuiltZones> {
+    let mut blocklist = BlocklistAuthority::new(threat_authority::create_zone(
+        &parse_name(&zones.blocklist)?,
+        serial,
+    )?);
     let mut reputation = threat_authority::create_zone(&parse_name(&zones.reputation)?, serial)?;
     let mut geo = threat_authority::create_zone(&parse_name(&zones.geo)?, serial)?;
     let mut asn = threat_authority::create_zone(&parse_name(&zones.asn)?, serial)?;
     let mut signal = threat_authority::create_zone(&parse_name(&zones.signal)?, serial)?;
     let mut binary = threat_authority::create_zone(&parse_name(&zones.binary)?, serial)?;
     let mut cert = threat_authority::create_zone(&parse_name(&zones.cert)?, serial)?;
+    let mut intel = threat_authority::create_zone(&parse_name(&zones.intel)?, serial)?;
 
     let mut entry_count: u32 = 0;
 
-    entry_count += populate_ip_records(&mut blocklist, &mut reputation, snapshot, zones, serial)?;
+    entry_count += populate_ip_records(
+        &mut blocklist,
+        &mut reputation,
+        snapshot,
+        zones,
+        serial,
+        options,
+    )?;
     entry_count += populate_geo_records(&mut geo, snapshot, zones, serial)?;
     entry_count += populate_asn_records(&mut asn, snapshot, zones, serial)?;
+    entry_count += populate_intel_records(&mut intel, snapshot, zones, serial, options)?;
 
     // Populate audit zones if audit data is available.
     if let Some(audit) = &snapshot.audit {
-        entry_count += populate_binary_records(&mut binary, audit, zones, serial)?;
+        entry_count += populate_binary_records(&mut binary, audit, zones, serial, options)?;
         entry_count += populate_cert_records(&mut cert, audit, zones, serial)?;
     }
 
@@ -104,6 +216,30 @@ pub fn build_zones(
         &signal_data.to_txt(),
         serial,
     )?;
+    populate_ttl_canaries(&mut signal, zones, serial, options)?;
+    populate_node_signals(&mut signal, snapshot, zones, serial)?;
+
+    let rpz = options
+        .rpz
+        .map(|origin| rpz::build_zone(snapshot, origin, serial))
+        .transpose()?;
+
+    // Signing must come last: it covers whatever is in the zone, and
+    // hickory bumps each signed zone's SOA serial by one.
+    if let Some(keys) = options.dnssec {
+        blocklist.sign_with(keys)?;
+        for zone in [
+            &mut reputation,
+            &mut geo,
+            &mut asn,
+            &mut signal,
+            &mut binary,
+            &mut cert,
+            &mut intel,
+        ] {
+            keys.sign_zone(zone)?;
+        }
+    }
 
     Ok(BuiltZones {
         blocklist,
@@ -113,55 +249,158 @@ pub fn build_zones(
         signal,
         binary,
         cert,
+        intel,
+        rpz,
+        serial,
+        entry_count,
+    })
+}
+
+/// Rebuild the zones saved in a [`ZoneDb`](crate::authority::persist::ZoneDb).
+///
+/// Returns `None` when none of the configured zones were saved. Zones
+/// missing from `saved` start empty at serial 0; the serial reported is
+/// the newest saved one, and the entry count is the saved signal's.
+pub fn restore_zones(
+    saved: &mut SavedZones,
+    zones: &ZoneConfig,
+    rpz: Option<&str>,
+) -> crate::Result<Option<BuiltZones>> {
+    let mut serial: Option<u32> = None;
+    let mut restore = |origin: &str| -> crate::Result<InMemoryAuthority> {
+        let name = parse_name(origin)?;
+        let Some(zone) = saved.remove(&LowerName::from(&name)) else {
+            return threat_authority::create_zone(&name, 0);
+        };
+        if let Some(zone_serial) = zone.serial() {
+            if serial.map_or(true, |newest| serial_gt(zone_serial, newest)) {
+                serial = Some(zone_serial);
+            }
+        }
+        Ok(InMemoryAuthority::from_records(&name, zone.into_records()))
+    };
+
+    let blocklist = BlocklistAuthority::new(restore(&zones.blocklist)?);
+    let reputation = restore(&zones.reputation)?;
+    let geo = restore(&zones.geo)?;
+    let asn = restore(&zones.asn)?;
+    let mut signal = restore(&zones.signal)?;
+    let binary = restore(&zones.binary)?;
+    let cert = restore(&zones.cert)?;
+    let intel = restore(&zones.intel)?;
+    let rpz = rpz.map(&mut restore).transpose()?;
+
+    let Some(serial) = serial else {
+        return Ok(None);
+    };
+    let entry_count = read_signal(&signal.zone_records()).map_or(0, |signal| signal.entries);
+
+    Ok(Some(BuiltZones {
+        blocklist,
+        reputation,
+        geo,
+        asn,
+        signal,
+        binary,
+        cert,
+        intel,
+        rpz,
         serial,
         entry_count,
+    }))
+}
+
+/// The signal TXT among a signal zone's records.
+#[must_use]
+pub fn read_signal(records: &[Record]) -> Option<SignalData> {
+    records.iter().find_map(|record| match record.data() {
+        RData::TXT(txt) => {
+            let value: Vec<u8> = txt.txt_data().concat();
+            SignalData::from_txt(&String::from_utf8_lossy(&value)).ok()
+        }
+        _ => None,
     })
 }
 
 /// Populate DNSBL and reputation records from blocked IPs.
+///
+/// CIDR entries are added to the blocklist's prefix trie and answered at
+/// query time; they get no per-address reputation records. Entry metadata
+/// is decayed first, and entries that have aged out are left out.
 fn populate_ip_records(
-    blocklist: &mut InMemoryAuthority,
+    blocklist: &mut BlocklistAuthority,
     reputation: &mut InMemoryAuthority,
     snapshot: &DefenseSnapshot,
     zones: &ZoneConfig,
     serial: u32,
+    options: BuildOptions<'_>,
 ) -> crate::Result<u32> {
     let mut count = 0;
+    let default_ttl = TtlConfig::default();
+    let ttl = options.ttl.unwrap_or(&default_ttl);
+    let now = Utc::now();
 
     for ip_str in &snapshot.blocked_ips {
-        // Skip CIDRs (DNSBL is per-IP; CIDRs need expansion).
+        let entry = snapshot
+            .block_entries
+            .get(ip_str)
+            .cloned()
+            .unwrap_or_default();
+        let Some(entry) = ttl_policy::decay(&entry, ttl, now) else {
+            continue;
+        };
+
         if ip_str.contains('/') {
+            match Cidr::parse(ip_str) {
+                Ok(cidr) => {
+                    blocklist.insert_cidr(cidr, DnsblCode::Listed, serial)?;
+                    count += 1;
+                }
+                Err(e) => warn!(cidr = %ip_str, error = %e, "skipping invalid CIDR"),
+            }
             continue;
         }
 
-        if let Ok(ip) = ip_str.parse::<Ipv4Addr>() {
-            threat_authority::insert_dnsbl_record(
-                blocklist,
+        if let Ok(ip) = ip_str.parse::<IpAddr>() {
+            threat_authority::insert_dnsbl_record_with_ttl(
+                blocklist.store_mut(),
                 &ip,
                 DnsblCode::Listed,
                 &zones.blocklist,
+                ttl_policy::entry_ttl(&entry, ttl.blocklist, now),
                 serial,
             )?;
 
             let rep_data = txt_intel::ReputationData {
-                threat: Some("blocked".into()),
+                threat: Some(entry.threat.map_or("blocked", ThreatLevel::as_str).into()),
+                hits: (entry.hits > 0).then_some(entry.hits),
+                // `;` separates the k=v fields.
+                reason: entry.reason.as_ref().map(|reason| reason.replace(';', ",")),
+                pattern: entry
+                    .pattern
+                    .as_ref()
+                    .map(|pattern| pattern.replace(';', ",")),
+                first_seen: entry.first_seen.map(|t| t.timestamp()),
+                last_seen: entry.last_seen.map(|t| t.timestamp()),
+                origin: entry
+                    .origin
+                    .as_deref()
+                    .map(|origin| txt_intel::origin_prefix(origin).replace(';', ",")),
                 ..txt_intel::ReputationData::empty()
             };
-            if let Ok(txt) = txt_intel::encode(&rep_data) {
-                let name = Name::parse(
-                    &format!(
-                        "{}.{}",
-                        crate::encoding::dnsbl::reverse_ipv4(&ip),
-                        &zones.reputation
-                    ),
-                    None,
-                )
-                .map_err(|e| crate::SrvError::Zone(format!("invalid rep name: {e}")))?;
+            let owner = format!(
+                "{}.{}",
+                crate::encoding::dnsbl::reverse_ip(&ip),
+                &zones.reputation
+            );
+            if let Ok(txt) = txt_intel::encode_with(&rep_data, &owner, options.signer) {
+                let name = Name::parse(&owner, None)
+                    .map_err(|e| crate::SrvError::Zone(format!("invalid rep name: {e}")))?;
                 threat_authority::insert_txt_record(
                     reputation,
                     &name,
                     &txt,
-                    ttl_policy::REPUTATION_TTL,
+                    ttl_policy::entry_ttl(&entry, ttl.reputation, now),
                     serial,
                 );
             }
@@ -173,6 +412,58 @@ fn populate_ip_records(
     Ok(count)
 }
 
+/// Add the TTL monitor's `ttlcheck` canary and published findings to the
+/// signal zone.
+fn populate_ttl_canaries(
+    signal: &mut InMemoryAuthority,
+    zones: &ZoneConfig,
+    serial: u32,
+    options: BuildOptions<'_>,
+) -> crate::Result<()> {
+    let parse = |name: &str| {
+        Name::parse(name, None)
+            .map_err(|e| crate::SrvError::Zone(format!("invalid TTL monitor name: {e}")))
+    };
+    threat_authority::insert_txt_record(
+        signal,
+        &parse(&ttl_monitor::ttlcheck_wildcard(&zones.signal))?,
+        &ttl_monitor::ttlcheck_value(),
+        ttl_policy::TTLCHECK_TTL,
+        serial,
+    );
+    for report in options.ttl_reports {
+        threat_authority::insert_txt_record(
+            signal,
+            &parse(&ttl_monitor::report_name(report.resolver, &zones.signal))?,
+            &report.to_txt(),
+            ttl_policy::SIGNAL_TTL,
+            serial,
+        );
+    }
+    Ok(())
+}
+
+/// Publish other nodes' signal records as `<prefix>.<signal zone>` TXT.
+fn populate_node_signals(
+    signal: &mut InMemoryAuthority,
+    snapshot: &DefenseSnapshot,
+    zones: &ZoneConfig,
+    serial: u32,
+) -> crate::Result<()> {
+    for node in &snapshot.node_signals {
+        let name = Name::parse(&format!("{}.{}", node.prefix, zones.signal), None)
+            .map_err(|e| crate::SrvError::Zone(format!("invalid node signal name: {e}")))?;
+        threat_authority::insert_txt_record(
+            signal,
+            &name,
+            &node.value,
+            ttl_policy::SIGNAL_TTL,
+            serial,
+        );
+    }
+    Ok(())
+}
+
 /// Populate geo zone records from blocked countries.
 fn populate_geo_records(
     geo: &mut InMemoryAuthority,
@@ -190,7 +481,7 @@ fn populate_geo_records(
                 name,
                 ttl_policy::GEO_ASN_TTL,
                 RData::TXT(TXT::new(vec![
-                    "status=blocked;direction=inbound".to_string(),
+                    "status=blocked;direction=inbound".to_string()
                 ])),
             ),
             serial,
@@ -223,6 +514,8 @@ fn populate_geo_records(
 }
 
 /// Populate ASN zone records from blocked ASNs.
+///
+/// ASNs in the bundled snapshot also get their operator and country.
 fn populate_asn_records(
     asn: &mut InMemoryAuthority,
     snapshot: &DefenseSnapshot,
@@ -238,38 +531,160 @@ fn populate_asn_records(
             .unwrap_or(asn_str);
         let name = Name::parse(&format!("{num}.{}", &zones.asn), None)
             .map_err(|e| crate::SrvError::Zone(format!("invalid ASN name: {e}")))?;
+        let number = asn_prefixes::parse_asn(asn_str);
+        // `;` separates the k=v fields.
+        let owner = number
+            .and_then(i1_core::asn::lookup_asn)
+            .map(|info| format!(";org={};cc={}", info.org.replace(';', ","), info.country))
+            .unwrap_or_default();
+        let txt = format!("status=blocked;asn={asn_str}{owner}");
         asn.upsert_mut(
             Record::from_rdata(
                 name,
                 ttl_policy::GEO_ASN_TTL,
-                RData::TXT(TXT::new(vec![format!("status=blocked;asn={asn_str}")])),
+                RData::TXT(TXT::new(vec![txt])),
             ),
             serial,
         );
         count += 1;
+
+        let prefixes =
+            number.and_then(|number| Some((number, snapshot.asn_prefixes.get(&number)?)));
+        if let Some((number, prefixes)) = prefixes {
+            populate_prefix_records(asn, number, prefixes, zones, serial)?;
+        }
     }
 
     Ok(count)
 }
 
+/// Publish an ASN's prefix list as an index record plus CBOR chunks.
+fn populate_prefix_records(
+    asn: &mut InMemoryAuthority,
+    number: u32,
+    list: &AsnPrefixes,
+    zones: &ZoneConfig,
+    serial: u32,
+) -> crate::Result<()> {
+    let parse = |name: String| {
+        Name::parse(&name, None)
+            .map_err(|e| crate::SrvError::Zone(format!("invalid prefix list name: {e}")))
+    };
+    let chunks = asn_prefixes::chunk_prefixes(&list.prefixes);
+    let index = PrefixIndex {
+        asn: number,
+        prefixes: list.prefixes.len(),
+        chunks: chunks.len(),
+        fetched: list.fetched,
+        source: list.source.clone(),
+        stale: list.stale,
+    };
+    threat_authority::insert_txt_record(
+        asn,
+        &parse(asn_prefixes::index_name(number, &zones.asn))?,
+        &index.to_txt(),
+        ttl_policy::GEO_ASN_TTL,
+        serial,
+    );
+    for (i, prefixes) in chunks.into_iter().enumerate() {
+        let chunk = PrefixChunk {
+            asn: number,
+            chunk: u32::try_from(i).unwrap_or(u32::MAX),
+            prefixes: prefixes.to_vec(),
+        };
+        threat_authority::insert_txt_record(
+            asn,
+            &parse(asn_prefixes::chunk_name(number, i, &zones.asn))?,
+            &asn_prefixes::encode_chunk(&chunk)?,
+            ttl_policy::GEO_ASN_TTL,
+            serial,
+        );
+    }
+    Ok(())
+}
+
+/// Populate structured intel zone records.
+///
+/// Creates CBOR TXT records at `{reversed-ip}.intel.i1.is.`. Entries whose
+/// key is not an IPv4 address are skipped. TTLs come from the intel band,
+/// scaled by each entry's first and last sighting.
+fn populate_intel_records(
+    intel: &mut InMemoryAuthority,
+    snapshot: &DefenseSnapshot,
+    zones: &ZoneConfig,
+    serial: u32,
+    options: BuildOptions<'_>,
+) -> crate::Result<u32> {
+    let mut count = 0;
+    let band = options
+        .ttl
+        .map_or_else(|| TtlConfig::default().intel, |ttl| ttl.intel);
+    let now = Utc::now();
+
+    for (ip_str, data) in &snapshot.intel {
+        let Ok(ip) = ip_str.parse::<Ipv4Addr>() else {
+            warn!(key = %ip_str, "skipping intel entry with non-IPv4 key");
+            continue;
+        };
+
+        let owner = format!(
+            "{}.{}",
+            crate::encoding::dnsbl::reverse_ipv4(&ip),
+            &zones.intel
+        );
+        match txt_intel::encode_complex_with(data, &owner, options.signer) {
+            Ok(txt) => {
+                let name = Name::parse(&owner, None)
+                    .map_err(|e| crate::SrvError::Zone(format!("invalid intel name: {e}")))?;
+                threat_authority::insert_txt_record(
+                    intel,
+                    &name,
+                    &txt,
+                    ttl_policy::entry_ttl(&intel_entry(data), band, now),
+                    serial,
+                );
+                count += 1;
+            }
+            Err(e) => {
+                warn!(ip = %ip_str, error = %e, "skipping intel encode error");
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// TTL metadata for an intel record: just its sightings.
+fn intel_entry(data: &txt_intel::ComplexIntel) -> BlockEntry {
+    BlockEntry {
+        first_seen: data.first_seen,
+        last_seen: data.last_seen,
+        ..BlockEntry::default()
+    }
+}
+
 /// Populate binary consensus zone from audit data.
 ///
-/// Creates TXT records at `{hash_prefix}.bin.i1.is.` with encoded
-/// binary metadata (hash, name, size, trust score, node count).
+/// Creates TXT records at `{hash_prefix}.bin.i1.is.` (`{hash_prefix}.b3.bin.i1.is.`
+/// for BLAKE3 hashes) with encoded binary metadata (hash, name, size, trust score, node count), plus an
+/// attestation record describing this node as the reporter.
 fn populate_binary_records(
     binary: &mut InMemoryAuthority,
     audit: &AuditData,
     zones: &ZoneConfig,
     serial: u32,
+    options: BuildOptions<'_>,
 ) -> crate::Result<u32> {
     let mut count = 0;
+    let node = Attestation::node_prefix(&audit.node_id);
+    let network = options.public_ip.map(Attestation::network_of);
 
     for bin in &audit.binaries {
-        if bin.sha256.len() < 12 {
+        if bin.hash.len() < 12 {
             continue;
         }
-        let prefix = &bin.sha256[..12];
-        let name = Name::parse(&format!("{prefix}.{}", &zones.binary), None)
+        let labels = i1_audit::encoding::hash_labels(&bin.hash, bin.hash_algorithm);
+        let name = Name::parse(&format!("{labels}.{}", &zones.binary), None)
             .map_err(|e| crate::SrvError::Zone(format!("invalid bin name: {e}")))?;
 
         // node_count starts at 1 (this node).
@@ -277,12 +692,30 @@ fn populate_binary_records(
             Ok(txt) => {
                 binary.upsert_mut(
                     Record::from_rdata(
-                        name,
+                        name.clone(),
                         ttl_policy::BINARY_CONSENSUS_TTL,
                         RData::TXT(TXT::new(vec![txt])),
                     ),
                     serial,
                 );
+
+                // The binary can't have been seen before it appeared on disk.
+                let attestation = Attestation {
+                    node: node.clone(),
+                    network: network.clone(),
+                    first_seen: bin.create_date.min(audit.collected_at),
+                    digest_at: audit.collected_at,
+                };
+                binary.upsert_mut(
+                    Record::from_rdata(
+                        name,
+                        ttl_policy::BINARY_CONSENSUS_TTL,
+                        RData::TXT(TXT::new(vec![i1_audit::encoding::encode_attestation_txt(
+                            &attestation,
+                        )])),
+                    ),
+                    serial,
+                );
                 count += 1;
             }
             Err(e) => {
@@ -296,8 +729,9 @@ fn populate_binary_records(
 
 /// Populate certificate consensus zone from audit data.
 ///
-/// Creates TXT records at `{fp_prefix}.ca.i1.is.` with encoded
-/// certificate metadata (fingerprint, issuer, expiry, node count).
+/// Creates TXT records at `{fp_prefix}.ca.i1.is.` (`{fp_prefix}.b3.ca.i1.is.`
+/// for BLAKE3 fingerprints) with encoded certificate metadata
+/// (fingerprint, issuer, expiry, node count).
 fn populate_cert_records(
     cert: &mut InMemoryAuthority,
     audit: &AuditData,
@@ -310,8 +744,11 @@ fn populate_cert_records(
         if root_cert.fingerprint.len() < 12 {
             continue;
         }
-        let prefix = &root_cert.fingerprint[..12];
-        let name = Name::parse(&format!("{prefix}.{}", &zones.cert), None)
+        let labels = i1_audit::encoding::hash_labels(
+            &root_cert.fingerprint,
+            root_cert.fingerprint_algorithm,
+        );
+        let name = Name::parse(&format!("{labels}.{}", &zones.cert), None)
             .map_err(|e| crate::SrvError::Zone(format!("invalid cert name: {e}")))?;
 
         let txt = i1_audit::encoding::encode_cert_txt(root_cert, 1);
@@ -329,6 +766,11 @@ fn populate_cert_records(
     Ok(count)
 }
 
+/// Parsed origins of every configured zone.
+pub fn zone_origins(zones: &ZoneConfig) -> crate::Result<Vec<Name>> {
+    zones.origins().into_iter().map(parse_name).collect()
+}
+
 /// Helper to parse a zone name string.
 fn parse_name(zone: &str) -> crate::Result<Name> {
     Name::parse(zone, None)
@@ -385,26 +827,180 @@ mod tests {
     }
 
     #[test]
-    fn test_cidrs_are_skipped() {
+    fn test_known_asns_name_their_operator() {
+        let snapshot = DefenseSnapshot {
+            blocked_asns: vec!["AS13335".into(), "AS64500".into()],
+            ..Default::default()
+        };
+        let mut built = build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap();
+
+        let mut txts: Vec<String> = built
+            .asn
+            .zone_records()
+            .into_iter()
+            .filter_map(|record| match record.data() {
+                RData::TXT(txt) => {
+                    Some(String::from_utf8_lossy(&txt.txt_data().concat()).into_owned())
+                }
+                _ => None,
+            })
+            .collect();
+        txts.sort();
+        assert_eq!(
+            txts,
+            [
+                "status=blocked;asn=AS13335;org=Cloudflare, Inc.;cc=US",
+                "status=blocked;asn=AS64500",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_with_ipv6_blocked_ips() {
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["2001:db8::1".into(), "1.2.3.4".into()],
+            ..Default::default()
+        };
+        let zones = ZoneConfig::default();
+        let mut built = build_zones(&snapshot, &zones, 1).unwrap();
+        assert_eq!(built.entry_count, 2);
+
+        let v6_name = Name::parse(
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.bl.i1.is.",
+            None,
+        )
+        .unwrap();
+        assert!(built
+            .blocklist
+            .store_mut()
+            .records_get_mut()
+            .values()
+            .any(|rrset| rrset.name() == &v6_name));
+    }
+
+    #[test]
+    fn test_entry_ttls_and_aging() {
+        let now = Utc::now();
+        let fresh = BlockEntry {
+            threat: Some(ThreatLevel::Critical),
+            hits: 50,
+            first_seen: Some(now),
+            last_seen: Some(now),
+            ..BlockEntry::default()
+        };
+        let stale = BlockEntry {
+            first_seen: Some(now - chrono::Duration::days(400)),
+            last_seen: Some(now - chrono::Duration::days(200)),
+            ..fresh.clone()
+        };
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into(), "5.6.7.8".into(), "9.9.9.9".into()],
+            block_entries: BTreeMap::from([
+                ("1.2.3.4".to_string(), fresh),
+                ("5.6.7.8".to_string(), stale),
+            ]),
+            ..Default::default()
+        };
+        let mut built = build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap();
+        // The stale entry has aged out.
+        assert_eq!(built.entry_count, 2);
+
+        let mut ttl = |name: &str| {
+            let name = Name::parse(name, None).unwrap();
+            built
+                .blocklist
+                .store_mut()
+                .records_get_mut()
+                .values()
+                .find(|rrset| rrset.name() == &name)
+                .map(|rrset| rrset.ttl())
+        };
+        assert_eq!(ttl("4.3.2.1.bl.i1.is."), Some(ttl_policy::SOA_MINIMUM_TTL));
+        assert_eq!(ttl("8.7.6.5.bl.i1.is."), None);
+        // Entries without metadata keep the fixed TTL.
+        assert_eq!(
+            ttl("9.9.9.9.bl.i1.is."),
+            Some(ttl_policy::BLOCKLIST_CONFIRMED_TTL)
+        );
+    }
+
+    #[test]
+    fn test_block_metadata_in_reputation() {
+        let first_seen = Utc::now() - chrono::Duration::hours(2);
+        let entry = BlockEntry {
+            threat: Some(ThreatLevel::High),
+            hits: 3,
+            first_seen: Some(first_seen),
+            last_seen: Some(first_seen + chrono::Duration::hours(1)),
+            reason: Some("ssh brute force; 3 tries".into()),
+            pattern: Some("ssh".into()),
+            ttl: Some(86_400),
+            origin: Some("node-a.srv.i1.is".into()),
+        };
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into()],
+            block_entries: BTreeMap::from([("1.2.3.4".to_string(), entry)]),
+            ..Default::default()
+        };
+        let mut built = build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap();
+
+        let name = Name::from_ascii("4.3.2.1.rep.i1.is.").unwrap();
+        let txt = built
+            .reputation
+            .zone_records()
+            .into_iter()
+            .find(|record| *record.name() == name)
+            .and_then(|record| match record.data() {
+                RData::TXT(txt) => {
+                    Some(String::from_utf8_lossy(&txt.txt_data().concat()).into_owned())
+                }
+                _ => None,
+            })
+            .unwrap();
+        let data = txt_intel::decode(&txt).unwrap();
+        assert_eq!(data.reason.as_deref(), Some("ssh brute force, 3 tries"));
+        assert_eq!(data.pattern.as_deref(), Some("ssh"));
+        assert_eq!(data.first_seen, Some(first_seen.timestamp()));
+        assert_eq!(
+            data.last_seen,
+            Some((first_seen + chrono::Duration::hours(1)).timestamp())
+        );
+        assert_eq!(data.origin.as_deref(), Some("node-a"));
+    }
+
+    #[test]
+    fn test_cidrs_are_served() {
         let snapshot = DefenseSnapshot {
-            blocked_ips: vec!["1.2.3.0/24".into(), "10.0.0.1".into()],
+            blocked_ips: vec![
+                "1.2.3.0/24".into(),
+                "2001:db8::/32".into(),
+                "10.0.0.1".into(),
+                "bogus/99".into(),
+            ],
             ..Default::default()
         };
         let zones = ZoneConfig::default();
         let built = build_zones(&snapshot, &zones, 1).unwrap();
-        // CIDR skipped, only the single IP counts.
-        assert_eq!(built.entry_count, 1);
+        // Two CIDRs plus the single IP; the invalid CIDR is skipped.
+        assert_eq!(built.entry_count, 3);
+        assert_eq!(built.blocklist.cidr_count(), 2);
+        assert_eq!(
+            built.blocklist.cidr_code(&"1.2.3.200".parse().unwrap()),
+            Some(DnsblCode::Listed)
+        );
     }
 
     #[test]
     fn test_build_with_audit_data() {
         use chrono::Utc;
         use i1_audit::types::{BinaryInfo, FileIdentity, RootCertInfo};
+        use i1_audit::HashAlgorithm;
 
         let audit = AuditData {
             binaries: vec![BinaryInfo {
                 path: "/usr/bin/sshd".into(),
-                sha256: "a".repeat(64),
+                hash: "a".repeat(64),
+                hash_algorithm: HashAlgorithm::Sha256,
                 create_date: Utc::now(),
                 modify_date: Utc::now(),
                 identity: FileIdentity {
@@ -419,6 +1015,7 @@ mod tests {
             root_certs: vec![RootCertInfo {
                 path: "/etc/ssl/certs/ca.pem".into(),
                 fingerprint: "b".repeat(64),
+                fingerprint_algorithm: HashAlgorithm::Blake3,
                 issuer: "CN=DigiCert Global Root G2, O=DigiCert Inc".into(),
                 subject: "CN=DigiCert Global Root G2".into(),
                 serial: "0a1234".into(),
@@ -427,8 +1024,11 @@ mod tests {
                 expired: false,
                 in_consensus: None,
                 trust_score: None,
+                findings: Vec::new(),
+                der: Vec::new(),
             }],
             node_id: "test-node".into(),
+            collected_at: Utc::now(),
         };
 
         let snapshot = DefenseSnapshot {
@@ -436,9 +1036,100 @@ mod tests {
             ..Default::default()
         };
         let zones = ZoneConfig::default();
-        let built = build_zones(&snapshot, &zones, 1).unwrap();
+        let mut built = build_zones(&snapshot, &zones, 1).unwrap();
         // 1 binary + 1 cert = 2 entries.
         assert_eq!(built.entry_count, 2);
+
+        // BLAKE3 fingerprints are published in their own namespace.
+        let name = Name::parse("bbbbbbbbbbbb.b3.ca.i1.is.", None).unwrap();
+        assert!(built
+            .cert
+            .records_get_mut()
+            .values()
+            .any(|rrset| rrset.name() == &name));
+    }
+
+    #[test]
+    fn test_binary_attestations_decode_on_audit_side() {
+        use i1_audit::consensus::parse_consensus_records;
+        use i1_audit::types::{BinaryInfo, FileIdentity};
+
+        let collected_at = Utc::now() - chrono::Duration::days(2);
+        let audit = AuditData {
+            binaries: vec![BinaryInfo {
+                path: "/usr/bin/sshd".into(),
+                hash: "c".repeat(64),
+                hash_algorithm: i1_audit::HashAlgorithm::Sha256,
+                create_date: Utc::now() - chrono::Duration::days(400),
+                modify_date: Utc::now() - chrono::Duration::days(400),
+                identity: FileIdentity {
+                    inode: 1,
+                    device_id: 1,
+                },
+                size: 1_047_552,
+                running: true,
+                process_names: vec!["sshd".into()],
+                trust_score: None,
+            }],
+            root_certs: vec![],
+            node_id: "test-node".into(),
+            collected_at,
+        };
+        let snapshot = DefenseSnapshot {
+            audit: Some(audit),
+            ..Default::default()
+        };
+        let options = BuildOptions {
+            public_ip: Some("203.0.113.7".parse().unwrap()),
+            ..Default::default()
+        };
+        let mut built = build_zones_with(&snapshot, &ZoneConfig::default(), 1, options).unwrap();
+        assert_eq!(built.entry_count, 1);
+
+        let name = Name::parse("cccccccccccc.bin.i1.is.", None).unwrap();
+        let txts: Vec<String> = built
+            .binary
+            .records_get_mut()
+            .values()
+            .filter(|rrset| rrset.name() == &name)
+            .flat_map(|rrset| rrset.records_without_rrsigs())
+            .map(|r| r.data().to_string())
+            .collect();
+        assert_eq!(txts.len(), 2);
+
+        let result = parse_consensus_records(&"c".repeat(64), &txts, 3600).unwrap();
+        assert!(result.found);
+        assert_eq!(result.node_count, 1);
+        assert_eq!(result.attestations.len(), 1);
+        let att = &result.attestations[0];
+        assert_eq!(att.node, Attestation::node_prefix("test-node"));
+        assert_eq!(att.network.as_deref(), Some("203.0"));
+        assert_eq!(att.digest_at.timestamp(), collected_at.timestamp());
+        assert!(att.first_seen < att.digest_at);
+    }
+
+    #[test]
+    fn test_build_with_intel() {
+        let mut intel = BTreeMap::new();
+        intel.insert(
+            "1.2.3.4".to_string(),
+            txt_intel::ComplexIntel {
+                malware_family: Some("mirai".into()),
+                tags: vec!["botnet".into()],
+                tlp: Some(txt_intel::Tlp::Green),
+                ..Default::default()
+            },
+        );
+        intel.insert("not-an-ip".to_string(), txt_intel::ComplexIntel::default());
+
+        let snapshot = DefenseSnapshot {
+            intel,
+            ..Default::default()
+        };
+        let zones = ZoneConfig::default();
+        let built = build_zones(&snapshot, &zones, 1).unwrap();
+        // Only the IPv4-keyed entry is published.
+        assert_eq!(built.entry_count, 1);
     }
 
     #[test]
@@ -1,9 +1,13 @@
 //! Server configuration for i1-srv nodes.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
+use crate::authority::ttl_policy;
+
 /// Configuration for an i1-srv DNS threat intelligence node.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -22,13 +26,646 @@ pub struct ServerConfig {
     /// Path to audit snapshot file (default: auto-detect).
     pub audit_path: Option<PathBuf>,
 
-    /// How often to reload defense state (seconds).
+    /// How often to poll the defense state for changes the file watcher
+    /// missed (seconds).
     #[serde(default = "default_reload_interval")]
     pub reload_interval_secs: u64,
 
-    /// Gossip/sync peers (other i1-srv node addresses).
+    /// Quiet period after a defense state change before zones are rebuilt,
+    /// so a burst of writes costs one rebuild (milliseconds).
+    #[serde(default = "default_reload_debounce")]
+    pub reload_debounce_ms: u64,
+
+    /// How long shutdown waits for queries being answered before closing
+    /// the listeners anyway (seconds).
+    #[serde(default = "default_drain_timeout")]
+    pub drain_timeout_secs: u64,
+
+    /// Gossip seed peers as `name@addr`, e.g. `node2@198.51.100.2:7946`.
+    /// Short names are taken to be under srv.i1.is.
     #[serde(default)]
     pub peers: Vec<String>,
+
+    /// PKCS#8 PEM key (i1-ca node key) for signing intel TXT records.
+    /// Records are published unsigned when unset.
+    #[serde(default)]
+    pub intel_signing_key: Option<PathBuf>,
+
+    /// Public address of this node, published as the network bucket in
+    /// binary consensus attestations. Falls back to `listen` when that is
+    /// a routable address.
+    #[serde(default)]
+    pub public_ip: Option<IpAddr>,
+
+    /// Zone transfer (AXFR/IXFR) settings.
+    #[serde(default)]
+    pub transfer: TransferConfig,
+
+    /// DNSSEC signing settings.
+    #[serde(default)]
+    pub dnssec: DnssecConfig,
+
+    /// DNS-over-TLS / DNS-over-HTTPS listener settings.
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Response Policy Zone export of the blocklist.
+    #[serde(default)]
+    pub rpz: RpzConfig,
+
+    /// Authenticated gossip with `peers`.
+    #[serde(default)]
+    pub gossip: GossipConfig,
+
+    /// Prometheus metrics endpoint.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    /// On-disk copy of the served zones, restored at startup.
+    #[serde(default)]
+    pub store: StoreConfig,
+
+    /// Admin HTTP API for runtime blocks and inspection.
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    /// Per-entry TTL bands and intel decay.
+    #[serde(default)]
+    pub ttl: TtlConfig,
+
+    /// Active probes for resolvers that rewrite our TTLs.
+    #[serde(default)]
+    pub ttl_monitor: TtlMonitorConfig,
+
+    /// DANE checks on gossip peers and the transfer primary.
+    #[serde(default)]
+    pub dane: DaneConfig,
+
+    /// Sampled NDJSON log of answered queries.
+    #[serde(default)]
+    pub query_log: QueryLogConfig,
+
+    /// Prefix lists published for blocked ASNs.
+    #[serde(default)]
+    pub asn_prefixes: AsnPrefixConfig,
+
+    /// Trust digests other nodes publish through the admin API.
+    #[serde(default)]
+    pub node_signals: NodeSignalConfig,
+
+    /// TSIG keys for DNS UPDATE registration with i1-dns.
+    #[serde(default)]
+    pub registration: RegistrationConfig,
+
+    /// Answers to CHAOS-class identity queries and NSID.
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+
+    /// Format and level of the node's own logs.
+    #[serde(default)]
+    pub log: LogConfig,
+}
+
+/// Gossip between nodes over mutual TLS with i1-ca node certificates.
+///
+/// The node presents its identity from `<data_dir>/i1/node` and checks
+/// each peer's certificate against the peer's TLSA record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GossipConfig {
+    /// Run the gossip listener and exchange with peers.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Gossip listen address (default: 0.0.0.0:7946).
+    #[serde(default = "default_gossip_listen")]
+    pub listen: SocketAddr,
+
+    /// Address announced to peers (default: `listen`).
+    #[serde(default)]
+    pub advertise: Option<SocketAddr>,
+
+    /// PEM i1-ca root every node certificate must chain to.
+    #[serde(default)]
+    pub root_ca: Option<PathBuf>,
+
+    /// i1-ca CRLs (PEM or DER); peers with revoked certificates are refused.
+    #[serde(default)]
+    pub crls: Vec<PathBuf>,
+
+    /// Seconds between gossip rounds.
+    #[serde(default = "default_gossip_interval")]
+    pub interval_secs: u64,
+
+    /// Seconds between anti-entropy rounds with a random peer (the first
+    /// runs on join).
+    #[serde(default = "default_anti_entropy_interval")]
+    pub anti_entropy_secs: u64,
+}
+
+/// Prometheus metrics, served over plain HTTP at `/metrics`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Serve the metrics endpoint.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Metrics listen address (default: 127.0.0.1:9153).
+    #[serde(default = "default_metrics_listen")]
+    pub listen: SocketAddr,
+}
+
+/// Admin HTTP API.
+///
+/// Without `mtls` the API only listens on loopback, and callers present
+/// the bearer token in `token_path`; with it, callers present an i1-ca
+/// node certificate chaining to `gossip.root_ca`, and the node presents
+/// its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// Serve the admin API.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Admin listen address (default: 127.0.0.1:8953).
+    #[serde(default = "default_admin_listen")]
+    pub listen: SocketAddr,
+
+    /// Require client certificates; needed to listen beyond loopback.
+    #[serde(default)]
+    pub mtls: bool,
+
+    /// Node names allowed to call the API over mTLS (default: any node
+    /// certificate under the root).
+    #[serde(default)]
+    pub clients: Vec<String>,
+
+    /// Blocks added through the API (default: `<data_dir>/i1/admin_blocks.json`).
+    #[serde(default)]
+    pub blocks_path: Option<PathBuf>,
+
+    /// Bearer token loopback callers present, created with mode 0600 when
+    /// missing (default: `<data_dir>/i1/admin_token`).
+    #[serde(default)]
+    pub token_path: Option<PathBuf>,
+}
+
+impl AdminConfig {
+    /// Configured blocks file, else the per-user default.
+    #[must_use]
+    pub fn blocks_path(&self) -> Option<PathBuf> {
+        self.blocks_path
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("i1").join("admin_blocks.json")))
+    }
+
+    /// Configured token file, else the per-user default.
+    #[must_use]
+    pub fn token_path(&self) -> Option<PathBuf> {
+        self.token_path
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("i1").join("admin_token")))
+    }
+}
+
+/// TTLs for entries that carry threat metadata, and how that metadata
+/// decays (see [`crate::authority::ttl_policy`]).
+///
+/// The signal zone keeps its near-zero TTL whatever is set here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtlConfig {
+    /// DNSBL answers (default: 300-86400 seconds).
+    #[serde(default = "default_blocklist_band")]
+    pub blocklist: TtlBand,
+
+    /// Reputation TXT records (default: 300-7200 seconds).
+    #[serde(default = "default_reputation_band")]
+    pub reputation: TtlBand,
+
+    /// Structured intel TXT records (default: 300-7200 seconds).
+    #[serde(default = "default_intel_band")]
+    pub intel: TtlBand,
+
+    /// Days of quiet that halve an entry's hit count.
+    #[serde(default = "default_hits_half_life")]
+    pub hits_half_life_days: u32,
+
+    /// Days of quiet that lower an entry's threat level one step; an entry
+    /// stepped below `low` is no longer served. 0 disables the stepping.
+    #[serde(default = "default_level_step")]
+    pub level_step_days: u32,
+}
+
+/// The range a zone's entry TTLs are scaled within (seconds).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TtlBand {
+    /// TTL of a fresh, active entry.
+    pub min: u32,
+    /// TTL of a long-established, quiet one.
+    pub max: u32,
+}
+
+/// DANE verification of sync peers (see [`crate::trust::mesh`]).
+///
+/// Gossip peers are checked against the TLSA record for their gossip
+/// port; the transfer primary, when `transfer.primary_name` names it,
+/// against the one for `port`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaneConfig {
+    /// Port whose TLSA record (`_<port>._tcp.<node>`) pins the primary's
+    /// DNS-over-TLS certificate (default: 853).
+    #[serde(default = "default_dane_port")]
+    pub port: u16,
+
+    /// Resolvers for TLSA lookups (default: the system resolver). These
+    /// should not be the peers being checked.
+    #[serde(default)]
+    pub resolvers: Vec<SocketAddr>,
+
+    /// Accept peers that publish no TLSA record, logging and counting
+    /// them, instead of refusing them.
+    #[serde(default)]
+    pub fail_open: bool,
+}
+
+/// Query log (see [`crate::query_log`]).
+///
+/// Each zone logs `sample_rate` of its queries unless `zones` gives it its
+/// own rate, keyed by origin (`sig.i1.is`) or first label (`sig`).
+/// Answers other than NOERROR and NXDOMAIN are always logged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryLogConfig {
+    /// Write the log.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Log file (default: `<data_dir>/i1/queries.ndjson`).
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// Rotate once the file reaches this many bytes (default: 64 MiB).
+    #[serde(default = "default_query_log_max_bytes")]
+    pub max_bytes: u64,
+
+    /// Rotated files kept beside the live one (default: 4).
+    #[serde(default = "default_query_log_keep")]
+    pub keep: usize,
+
+    /// Fraction of queries logged in zones without their own rate
+    /// (default: 0.01).
+    #[serde(default = "default_query_log_sample_rate")]
+    pub sample_rate: f64,
+
+    /// Per-zone sample rates, e.g. `{ sig = 1.0, bl = 0.01 }`.
+    #[serde(default)]
+    pub zones: BTreeMap<String, f64>,
+
+    /// Prefix client IPv4 addresses are truncated to (default: 24).
+    #[serde(default = "default_query_log_ipv4_prefix")]
+    pub ipv4_prefix: u8,
+
+    /// Prefix client IPv6 addresses are truncated to (default: 48).
+    #[serde(default = "default_query_log_ipv6_prefix")]
+    pub ipv6_prefix: u8,
+}
+
+impl QueryLogConfig {
+    /// Configured log file, else the per-user default.
+    #[must_use]
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("i1").join("queries.ndjson")))
+    }
+
+    /// Sample rate for the zone at `origin`.
+    #[must_use]
+    pub fn zone_rate(&self, origin: &str) -> f64 {
+        let origin = origin.trim_end_matches('.');
+        let label = origin.split('.').next().unwrap_or(origin);
+        self.zones
+            .iter()
+            .find(|(zone, _)| zone.trim_end_matches('.').eq_ignore_ascii_case(origin))
+            .or_else(|| {
+                self.zones
+                    .iter()
+                    .find(|(zone, _)| zone.eq_ignore_ascii_case(label))
+            })
+            .map_or(self.sample_rate, |(_, rate)| *rate)
+    }
+}
+
+/// Prefix lists for blocked ASNs (see [`crate::sync::prefixes`]).
+///
+/// Lists come from `RIPEstat` unless `rib_path` names a local RIB dump,
+/// which keeps a node that can't reach `RIPEstat` working offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsnPrefixConfig {
+    /// Look up and publish prefix lists.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// `RIPEstat` announced-prefixes endpoint.
+    #[serde(default = "default_ripestat_url")]
+    pub ripestat_url: String,
+
+    /// RIB dump to read instead of asking `RIPEstat`: `<prefix> <asn>` lines
+    /// or `bgpdump -m` output.
+    #[serde(default)]
+    pub rib_path: Option<PathBuf>,
+
+    /// Refetch each list after this many seconds (default: 1 day).
+    #[serde(default = "default_asn_prefix_refresh")]
+    pub refresh_secs: u64,
+}
+
+/// DNS UPDATE registration (see [`crate::node::registration`]).
+///
+/// The first key in `tsig_keys` signs updates. The others are still
+/// accepted until their `accept_until`, so a secret can be rotated
+/// without a window where either side refuses the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrationConfig {
+    /// TSIG keys, newest first.
+    #[serde(default)]
+    pub tsig_keys: Vec<TsigKeyConfig>,
+
+    /// Clock difference allowed between signer and verifier (seconds).
+    #[serde(default = "default_tsig_fudge")]
+    pub tsig_fudge_secs: u16,
+}
+
+/// The node's own logs, written to stderr.
+///
+/// `I1_LOG`, when set, replaces `level`, as it does for the CLI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// `text` (default) or `json`, one object per line for systemd or
+    /// Kubernetes log collectors.
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Filter directives, e.g. `debug` or `info,hickory_server=warn`
+    /// (default: `info`).
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+/// How log events are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines with a timestamp, level and target.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// One TSIG key, as shared with the i1-dns servers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TsigKeyConfig {
+    /// Key name, e.g. `node1.srv.i1.is`. Must match the servers' name.
+    pub name: String,
+
+    /// MAC algorithm: `hmac-sha256` (default), `hmac-sha384` or
+    /// `hmac-sha512`.
+    #[serde(default = "default_tsig_algorithm")]
+    pub algorithm: String,
+
+    /// Base64 secret. Use `secret_file` to keep it out of the config.
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    /// File holding the base64 secret.
+    #[serde(default)]
+    pub secret_file: Option<PathBuf>,
+
+    /// Stop accepting the key after this time (RFC 3339). Unset keys are
+    /// accepted until removed.
+    #[serde(default)]
+    pub accept_until: Option<DateTime<Utc>>,
+}
+
+/// Signal records published on other nodes' behalf (see
+/// [`crate::trust::node_signals`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSignalConfig {
+    /// Accept `PUT /v1/signals/{prefix}` on the admin API.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Least time between two updates of one node's record (seconds).
+    #[serde(default = "default_signal_min_interval")]
+    pub min_interval_secs: u64,
+
+    /// Oldest record timestamp accepted; older records are dropped from
+    /// the zone (seconds).
+    #[serde(default = "default_signal_max_age")]
+    pub max_age_secs: u64,
+}
+
+/// What the node says about itself to `version.bind`, `hostname.bind`
+/// and `id.server` CH TXT queries, and in the NSID EDNS option (see
+/// [`crate::authority::chaos`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    /// Answer to `version.bind` (default: `i1-srv <version>`).
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// Answer to `hostname.bind` and `id.server`, and the NSID
+    /// (default: the first label of `node_name`).
+    #[serde(default)]
+    pub hostname: Option<String>,
+
+    /// Refuse CHAOS queries and never send an NSID.
+    #[serde(default)]
+    pub hide: bool,
+}
+
+/// TTL monitor: probes the signal zone's canary records through public
+/// resolvers (see [`crate::trust::ttl_monitor`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtlMonitorConfig {
+    /// Run the probes.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Probe 1.1.1.1, 8.8.8.8 and 9.9.9.9 as well as `resolvers`.
+    #[serde(default = "default_public_resolvers")]
+    pub public_resolvers: bool,
+
+    /// Further resolvers to probe.
+    #[serde(default)]
+    pub resolvers: Vec<SocketAddr>,
+
+    /// Seconds between probe rounds.
+    #[serde(default = "default_ttl_monitor_interval")]
+    pub interval_secs: u64,
+
+    /// Seconds between the two queries of a probe.
+    #[serde(default = "default_ttl_monitor_recheck")]
+    pub recheck_secs: u64,
+
+    /// Publish each resolver's findings as TXT in the signal zone.
+    #[serde(default)]
+    pub publish: bool,
+}
+
+impl TtlMonitorConfig {
+    /// Resolvers to probe, well-known public ones first.
+    #[must_use]
+    pub fn resolvers(&self) -> Vec<SocketAddr> {
+        let public = [[1, 1, 1, 1], [8, 8, 8, 8], [9, 9, 9, 9]]
+            .into_iter()
+            .filter(|_| self.public_resolvers)
+            .map(|ip| SocketAddr::from((ip, 53)));
+        let mut resolvers: Vec<SocketAddr> = public.collect();
+        for resolver in &self.resolvers {
+            if !resolvers.contains(resolver) {
+                resolvers.push(*resolver);
+            }
+        }
+        resolvers
+    }
+}
+
+/// On-disk zone store.
+///
+/// Every zone change is journaled to disk, so a restarted node serves its
+/// last zones immediately instead of waiting for a rebuild or transfer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreConfig {
+    /// Keep the zones on disk and restore them at startup.
+    #[serde(default = "default_store_enabled")]
+    pub enabled: bool,
+
+    /// Database file (default: `<data_dir>/i1/zones.redb`).
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// How often the journal is folded into the saved zones (seconds).
+    #[serde(default = "default_store_compact")]
+    pub compact_secs: u64,
+}
+
+impl StoreConfig {
+    /// Configured database file, else the per-user default.
+    #[must_use]
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("i1").join("zones.redb")))
+    }
+}
+
+/// Response Policy Zone (RPZ) export, for resolvers that enforce the
+/// blocklist themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpzConfig {
+    /// RPZ zone origin (default: rpz.i1.is).
+    #[serde(default = "default_rpz_zone")]
+    pub zone: String,
+
+    /// Write the zone file here on every rebuild.
+    #[serde(default)]
+    pub zone_file: Option<PathBuf>,
+
+    /// Also serve the zone, so resolvers can AXFR/IXFR it.
+    #[serde(default)]
+    pub serve: bool,
+}
+
+/// Encrypted DNS listeners, serving the same zones as UDP/TCP.
+///
+/// Both listeners present the same certificate: `cert_path`/`key_path`
+/// when set, otherwise the node identity issued by i1-ca.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// Serve DNS-over-TLS (RFC 7858).
+    #[serde(default)]
+    pub dot_enabled: bool,
+
+    /// DNS-over-TLS listen address (default: 0.0.0.0:853).
+    #[serde(default = "default_dot_listen")]
+    pub dot_listen: SocketAddr,
+
+    /// Serve DNS-over-HTTPS (RFC 8484) at `/dns-query`.
+    #[serde(default)]
+    pub doh_enabled: bool,
+
+    /// DNS-over-HTTPS listen address (default: 0.0.0.0:443).
+    #[serde(default = "default_doh_listen")]
+    pub doh_listen: SocketAddr,
+
+    /// PEM certificate chain (leaf first). Unset uses the node identity.
+    #[serde(default)]
+    pub cert_path: Option<PathBuf>,
+
+    /// PKCS#8 PEM private key for `cert_path`.
+    #[serde(default)]
+    pub key_path: Option<PathBuf>,
+}
+
+/// DNSSEC signing for the served zones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnssecConfig {
+    /// Sign all zones and publish DNSKEYs.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Directory holding one subdirectory of PEM keys per zone
+    /// (default: `<data_dir>/i1/dnssec`).
+    #[serde(default)]
+    pub key_dir: Option<PathBuf>,
+
+    /// How long each RRSIG stays valid after signing (seconds).
+    #[serde(default = "default_signature_validity")]
+    pub signature_validity_secs: u64,
+}
+
+impl DnssecConfig {
+    /// Configured key directory, else the per-user default.
+    #[must_use]
+    pub fn key_dir(&self) -> Option<PathBuf> {
+        self.key_dir
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("i1").join("dnssec")))
+    }
+}
+
+/// Zone transfer settings for primary and secondary nodes.
+///
+/// A primary authorizes transfers by source address only (no TSIG), so
+/// `allow_from` should list the secondaries' addresses exactly. A
+/// secondary authenticates its primary only with `primary_name`; without
+/// it, transfers run over plain TCP and are taken on trust.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferConfig {
+    /// Source IPs or CIDRs allowed to AXFR/IXFR our zones.
+    /// Empty refuses all transfers.
+    #[serde(default)]
+    pub allow_from: Vec<String>,
+
+    /// Primary to pull zones from. When set, this node runs as a
+    /// secondary and serves transferred zones instead of local state.
+    #[serde(default)]
+    pub primary: Option<SocketAddr>,
+
+    /// Node name of the primary. When set, zones are transferred over
+    /// DNS-over-TLS from the primary's address on `dane.port`, on
+    /// connections whose certificate matched the primary's TLSA record.
+    /// Short names are taken to be under srv.i1.is.
+    #[serde(default)]
+    pub primary_name: Option<String>,
+
+    /// How often a secondary checks the primary for changes (seconds).
+    #[serde(default = "default_transfer_refresh")]
+    pub refresh_secs: u64,
+
+    /// Zone versions kept for IXFR; older clients fall back to AXFR.
+    #[serde(default = "default_journal_len")]
+    pub journal_len: usize,
 }
 
 /// Zone origins and their delegation configuration.
@@ -61,6 +698,10 @@ pub struct ZoneConfig {
     /// Certificate consensus zone origin (default: ca.i1.is).
     #[serde(default = "default_ca_zone")]
     pub cert: String,
+
+    /// Structured intel zone origin (default: intel.i1.is).
+    #[serde(default = "default_intel_zone")]
+    pub intel: String,
 }
 
 impl Default for ServerConfig {
@@ -72,7 +713,208 @@ impl Default for ServerConfig {
             state_path: None,
             audit_path: None,
             reload_interval_secs: default_reload_interval(),
+            reload_debounce_ms: default_reload_debounce(),
+            drain_timeout_secs: default_drain_timeout(),
             peers: Vec::new(),
+            intel_signing_key: None,
+            public_ip: None,
+            transfer: TransferConfig::default(),
+            dnssec: DnssecConfig::default(),
+            tls: TlsConfig::default(),
+            rpz: RpzConfig::default(),
+            gossip: GossipConfig::default(),
+            metrics: MetricsConfig::default(),
+            store: StoreConfig::default(),
+            admin: AdminConfig::default(),
+            ttl: TtlConfig::default(),
+            ttl_monitor: TtlMonitorConfig::default(),
+            dane: DaneConfig::default(),
+            query_log: QueryLogConfig::default(),
+            asn_prefixes: AsnPrefixConfig::default(),
+            node_signals: NodeSignalConfig::default(),
+            registration: RegistrationConfig::default(),
+            chaos: ChaosConfig::default(),
+            log: LogConfig::default(),
+        }
+    }
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            allow_from: Vec::new(),
+            primary: None,
+            primary_name: None,
+            refresh_secs: default_transfer_refresh(),
+            journal_len: default_journal_len(),
+        }
+    }
+}
+
+impl Default for DnssecConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_dir: None,
+            signature_validity_secs: default_signature_validity(),
+        }
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            dot_enabled: false,
+            dot_listen: default_dot_listen(),
+            doh_enabled: false,
+            doh_listen: default_doh_listen(),
+            cert_path: None,
+            key_path: None,
+        }
+    }
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_gossip_listen(),
+            advertise: None,
+            root_ca: None,
+            crls: Vec::new(),
+            interval_secs: default_gossip_interval(),
+            anti_entropy_secs: default_anti_entropy_interval(),
+        }
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_metrics_listen(),
+        }
+    }
+}
+
+impl Default for TtlConfig {
+    fn default() -> Self {
+        Self {
+            blocklist: default_blocklist_band(),
+            reputation: default_reputation_band(),
+            intel: default_intel_band(),
+            hits_half_life_days: default_hits_half_life(),
+            level_step_days: default_level_step(),
+        }
+    }
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_admin_listen(),
+            mtls: false,
+            clients: Vec::new(),
+            blocks_path: None,
+            token_path: None,
+        }
+    }
+}
+
+impl Default for DaneConfig {
+    fn default() -> Self {
+        Self {
+            port: default_dane_port(),
+            resolvers: Vec::new(),
+            fail_open: false,
+        }
+    }
+}
+
+impl Default for QueryLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            max_bytes: default_query_log_max_bytes(),
+            keep: default_query_log_keep(),
+            sample_rate: default_query_log_sample_rate(),
+            zones: BTreeMap::new(),
+            ipv4_prefix: default_query_log_ipv4_prefix(),
+            ipv6_prefix: default_query_log_ipv6_prefix(),
+        }
+    }
+}
+
+impl Default for AsnPrefixConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ripestat_url: default_ripestat_url(),
+            rib_path: None,
+            refresh_secs: default_asn_prefix_refresh(),
+        }
+    }
+}
+
+impl Default for NodeSignalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_interval_secs: default_signal_min_interval(),
+            max_age_secs: default_signal_max_age(),
+        }
+    }
+}
+
+impl Default for TtlMonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            public_resolvers: default_public_resolvers(),
+            resolvers: Vec::new(),
+            interval_secs: default_ttl_monitor_interval(),
+            recheck_secs: default_ttl_monitor_recheck(),
+            publish: false,
+        }
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_store_enabled(),
+            path: None,
+            compact_secs: default_store_compact(),
+        }
+    }
+}
+
+impl Default for RpzConfig {
+    fn default() -> Self {
+        Self {
+            zone: default_rpz_zone(),
+            zone_file: None,
+            serve: false,
+        }
+    }
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        Self {
+            tsig_keys: Vec::new(),
+            tsig_fudge_secs: default_tsig_fudge(),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_log_level(),
         }
     }
 }
@@ -87,11 +929,319 @@ impl Default for ZoneConfig {
             signal: default_sig_zone(),
             binary: default_bin_zone(),
             cert: default_ca_zone(),
+            intel: default_intel_zone(),
         }
     }
 }
 
+impl ZoneConfig {
+    /// Every zone origin, blocklist first.
+    #[must_use]
+    pub fn origins(&self) -> [&str; 8] {
+        [
+            &self.blocklist,
+            &self.reputation,
+            &self.geo,
+            &self.asn,
+            &self.signal,
+            &self.binary,
+            &self.cert,
+            &self.intel,
+        ]
+    }
+}
+
 impl ServerConfig {
+    /// Address to publish in attestations: `public_ip`, else a routable `listen` IP.
+    #[must_use]
+    pub fn attestation_ip(&self) -> Option<IpAddr> {
+        self.public_ip.or_else(|| {
+            let ip = self.listen.ip();
+            (!ip.is_unspecified() && !ip.is_loopback()).then_some(ip)
+        })
+    }
+
+    /// Fully qualified node name, as issued in the node certificate.
+    #[must_use]
+    pub fn node_fqdn(&self) -> String {
+        qualify_node(&self.node_name)
+    }
+
+    /// The transfer primary's fully qualified name and DNS-over-TLS
+    /// address, when it is to be DANE-verified.
+    #[must_use]
+    pub fn primary_pin(&self) -> Option<(String, SocketAddr)> {
+        let primary = self.transfer.primary?;
+        let name = self.transfer.primary_name.as_deref()?;
+        Some((
+            qualify_node(name),
+            SocketAddr::new(primary.ip(), self.dane.port),
+        ))
+    }
+
+    /// Parse `peers` into gossip seeds (fully qualified name, address).
+    pub fn gossip_seeds(&self) -> crate::Result<Vec<(String, SocketAddr)>> {
+        self.peers.iter().map(|peer| parse_peer(peer)).collect()
+    }
+
+    /// Check the config without binding or loading anything, failing with
+    /// every problem found (see [`ServerConfig::problems`]).
+    pub fn validate(&self) -> crate::Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::SrvError::Config(problems.join("; ")))
+        }
+    }
+
+    /// Everything wrong with the config, each prefixed with its setting:
+    /// zone and node names that don't parse, empty or inverted TTL bands,
+    /// zero intervals, listeners that would collide, malformed peers and
+    /// transfer ACLs, out-of-range sample rates, missing key files and bad
+    /// TSIG keys.
+    #[must_use]
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.check_names(&mut problems);
+        self.check_timing(&mut problems);
+        self.check_listeners(&mut problems);
+
+        for peer in &self.peers {
+            if let Err(e) = parse_peer(peer) {
+                problems.push(format!("peers: {}", config_message(e)));
+            }
+        }
+        for entry in &self.transfer.allow_from {
+            let parsed =
+                crate::authority::transfer::TransferAcl::parse(std::slice::from_ref(entry));
+            if let Err(e) = parsed {
+                problems.push(format!("transfer.allow_from: {}", config_message(e)));
+            }
+        }
+        if self.transfer.primary_name.is_some() && self.transfer.primary.is_none() {
+            problems.push("transfer.primary_name: set without transfer.primary".into());
+        }
+        let dane_set = !self.dane.resolvers.is_empty()
+            || self.dane.fail_open
+            || self.dane.port != default_dane_port();
+        if dane_set
+            && !self.gossip.enabled
+            && self.transfer.primary.is_some()
+            && self.transfer.primary_name.is_none()
+        {
+            problems.push(
+                "transfer.primary_name: required for dane to verify the transfer primary".into(),
+            );
+        }
+
+        let rates = std::iter::once(("sample_rate".to_string(), self.query_log.sample_rate)).chain(
+            self.query_log
+                .zones
+                .iter()
+                .map(|(zone, rate)| (format!("zones.{zone}"), *rate)),
+        );
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                problems.push(format!("query_log.{name}: {rate} is not between 0 and 1"));
+            }
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log.level) {
+            problems.push(format!("log.level: {e}"));
+        }
+
+        let files = [
+            ("intel_signing_key", self.intel_signing_key.as_ref()),
+            ("tls.cert_path", self.tls.cert_path.as_ref()),
+            ("tls.key_path", self.tls.key_path.as_ref()),
+            ("gossip.root_ca", self.gossip.root_ca.as_ref()),
+        ];
+        let crls = self
+            .gossip
+            .crls
+            .iter()
+            .map(|crl| ("gossip.crls", Some(crl)));
+        for (name, path) in files.into_iter().chain(crls) {
+            if let Some(path) = path.filter(|path| !path.is_file()) {
+                problems.push(format!("{name}: {} is not a file", path.display()));
+            }
+        }
+        if self.tls.cert_path.is_some() != self.tls.key_path.is_some() {
+            problems.push("tls: cert_path and key_path must be set together".into());
+        }
+
+        if !self.registration.tsig_keys.is_empty() {
+            if let Err(e) = crate::node::registration::TsigKeyring::from_config(&self.registration)
+            {
+                problems.push(format!("registration: {e}"));
+            }
+        }
+        problems
+    }
+
+    /// Zone origins and the node name must be DNS names, origins distinct.
+    fn check_names(&self, problems: &mut Vec<String>) {
+        let mut origins: Vec<(&str, &str)> = vec![
+            ("blocklist", &self.zones.blocklist),
+            ("reputation", &self.zones.reputation),
+            ("geo", &self.zones.geo),
+            ("asn", &self.zones.asn),
+            ("signal", &self.zones.signal),
+            ("binary", &self.zones.binary),
+            ("cert", &self.zones.cert),
+            ("intel", &self.zones.intel),
+        ];
+        if self.rpz.serve {
+            origins.push(("rpz", &self.rpz.zone));
+        }
+        let mut seen: Vec<(String, String)> = Vec::new();
+        for (field, origin) in origins {
+            let field = if field == "rpz" {
+                "rpz.zone".to_string()
+            } else {
+                format!("zones.{field}")
+            };
+            if let Err(e) = dns_name(origin) {
+                problems.push(format!("{field}: {e}"));
+                continue;
+            }
+            let key = origin.trim_end_matches('.').to_ascii_lowercase();
+            if let Some((other, _)) = seen.iter().find(|(_, seen)| *seen == key) {
+                problems.push(format!("{field}: '{origin}' is also {other}"));
+            } else {
+                seen.push((field, key));
+            }
+        }
+        if let Err(e) = dns_name(&self.node_fqdn()) {
+            problems.push(format!("node_name: {e}"));
+        }
+    }
+
+    /// TTL bands must be non-empty and ordered, and periodic tasks need a
+    /// non-zero period.
+    fn check_timing(&self, problems: &mut Vec<String>) {
+        let bands = [
+            ("blocklist", self.ttl.blocklist),
+            ("reputation", self.ttl.reputation),
+            ("intel", self.ttl.intel),
+        ];
+        for (name, band) in bands {
+            if band.min == 0 || band.min > band.max {
+                problems.push(format!(
+                    "ttl.{name}: band {}-{} must have 0 < min <= max",
+                    band.min, band.max
+                ));
+            } else if band.max > MAX_TTL {
+                problems.push(format!(
+                    "ttl.{name}: max {} is over a week ({MAX_TTL}s), which resolvers cap",
+                    band.max
+                ));
+            }
+        }
+
+        let intervals = [
+            ("reload_interval_secs", self.reload_interval_secs, true),
+            (
+                "ttl.hits_half_life_days",
+                self.ttl.hits_half_life_days.into(),
+                true,
+            ),
+            (
+                "dnssec.signature_validity_secs",
+                self.dnssec.signature_validity_secs,
+                self.dnssec.enabled,
+            ),
+            (
+                "transfer.refresh_secs",
+                self.transfer.refresh_secs,
+                self.transfer.primary.is_some(),
+            ),
+            (
+                "gossip.interval_secs",
+                self.gossip.interval_secs,
+                self.gossip.enabled,
+            ),
+            (
+                "gossip.anti_entropy_secs",
+                self.gossip.anti_entropy_secs,
+                self.gossip.enabled,
+            ),
+            (
+                "store.compact_secs",
+                self.store.compact_secs,
+                self.store.enabled,
+            ),
+            (
+                "ttl_monitor.interval_secs",
+                self.ttl_monitor.interval_secs,
+                self.ttl_monitor.enabled,
+            ),
+        ];
+        for (name, value, used) in intervals {
+            if used && value == 0 {
+                problems.push(format!("{name}: must be greater than 0"));
+            }
+        }
+    }
+
+    /// Enabled TCP listeners must not share a port on overlapping
+    /// addresses, and the admin API and gossip need what they run on.
+    fn check_listeners(&self, problems: &mut Vec<String>) {
+        let listeners = [
+            ("listen", Some(self.listen)),
+            (
+                "tls.dot_listen",
+                self.tls.dot_enabled.then_some(self.tls.dot_listen),
+            ),
+            (
+                "tls.doh_listen",
+                self.tls.doh_enabled.then_some(self.tls.doh_listen),
+            ),
+            (
+                "gossip.listen",
+                self.gossip.enabled.then_some(self.gossip.listen),
+            ),
+            (
+                "metrics.listen",
+                self.metrics.enabled.then_some(self.metrics.listen),
+            ),
+            (
+                "admin.listen",
+                self.admin.enabled.then_some(self.admin.listen),
+            ),
+        ];
+        let enabled: Vec<_> = listeners
+            .into_iter()
+            .filter_map(|(name, addr)| Some((name, addr?)))
+            .collect();
+        for (i, (name, addr)) in enabled.iter().enumerate() {
+            if addr.port() == 0 {
+                problems.push(format!("{name}: {addr} has no port"));
+            }
+            let clash = enabled[..i].iter().find(|(_, other)| {
+                other.port() == addr.port()
+                    && other.is_ipv4() == addr.is_ipv4()
+                    && (other.ip() == addr.ip()
+                        || other.ip().is_unspecified()
+                        || addr.ip().is_unspecified())
+            });
+            if let Some((other_name, other)) = clash {
+                problems.push(format!("{name}: {addr} overlaps {other_name} ({other})"));
+            }
+        }
+
+        if self.admin.enabled && !self.admin.mtls && !self.admin.listen.ip().is_loopback() {
+            problems.push("admin.listen: beyond loopback requires admin.mtls".into());
+        }
+        if self.admin.enabled && self.admin.mtls && self.gossip.root_ca.is_none() {
+            problems.push("admin.mtls: requires gossip.root_ca".into());
+        }
+        if self.gossip.enabled && self.gossip.root_ca.is_none() {
+            problems.push("gossip.root_ca: required when gossip is enabled".into());
+        }
+    }
+
     /// Load config from a TOML file, falling back to defaults.
     pub fn load(path: &std::path::Path) -> crate::Result<Self> {
         if path.exists() {
@@ -103,11 +1253,209 @@ impl ServerConfig {
     }
 }
 
+/// Longest TTL worth configuring; resolvers cap cached records at a week.
+const MAX_TTL: u32 = 7 * 24 * 60 * 60;
+
+/// Parse a `name@addr` peer into its fully qualified name and address.
+fn parse_peer(peer: &str) -> crate::Result<(String, SocketAddr)> {
+    let (name, addr) = peer
+        .split_once('@')
+        .ok_or_else(|| crate::SrvError::Config(format!("peer '{peer}' is not name@addr")))?;
+    let addr = addr
+        .parse()
+        .map_err(|e| crate::SrvError::Config(format!("peer '{peer}' has a bad address: {e}")))?;
+    let name = qualify_node(name);
+    dns_name(&name)
+        .map_err(|e| crate::SrvError::Config(format!("peer '{peer}' has a bad name: {e}")))?;
+    Ok((name, addr))
+}
+
+/// Check that `name` is a usable DNS name.
+fn dns_name(name: &str) -> Result<(), String> {
+    if name.trim_end_matches('.').is_empty() {
+        return Err("empty name".into());
+    }
+    hickory_proto::rr::Name::from_ascii(name)
+        .map(drop)
+        .map_err(|e| format!("'{name}' is not a DNS name: {e}"))
+}
+
+/// The message of a config error, without the "config error:" prefix.
+fn config_message(error: crate::SrvError) -> String {
+    match error {
+        crate::SrvError::Config(message) => message,
+        other => other.to_string(),
+    }
+}
+
+/// Put a short node name under srv.i1.is.
+fn qualify_node(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    if name.contains('.') {
+        name.to_string()
+    } else {
+        format!("{name}.srv.i1.is")
+    }
+}
+
 // Default value functions for serde.
 const fn default_reload_interval() -> u64 {
     60
 }
 
+const fn default_reload_debounce() -> u64 {
+    500
+}
+
+const fn default_drain_timeout() -> u64 {
+    10
+}
+
+const fn default_transfer_refresh() -> u64 {
+    60
+}
+
+const fn default_journal_len() -> usize {
+    16
+}
+
+const fn default_signature_validity() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+const fn default_tsig_fudge() -> u16 {
+    300
+}
+
+fn default_tsig_algorithm() -> String {
+    "hmac-sha256".into()
+}
+
+fn default_dot_listen() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 853))
+}
+
+fn default_doh_listen() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 443))
+}
+
+fn default_gossip_listen() -> SocketAddr {
+    SocketAddr::from(([0, 0, 0, 0], 7946))
+}
+
+const fn default_gossip_interval() -> u64 {
+    10
+}
+
+const fn default_anti_entropy_interval() -> u64 {
+    60
+}
+
+fn default_metrics_listen() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 9153))
+}
+
+const fn default_blocklist_band() -> TtlBand {
+    TtlBand {
+        min: ttl_policy::SOA_MINIMUM_TTL,
+        max: ttl_policy::BLOCKLIST_CONFIRMED_TTL,
+    }
+}
+
+const fn default_reputation_band() -> TtlBand {
+    TtlBand {
+        min: ttl_policy::SOA_MINIMUM_TTL,
+        max: ttl_policy::REPUTATION_TTL,
+    }
+}
+
+const fn default_intel_band() -> TtlBand {
+    TtlBand {
+        min: ttl_policy::SOA_MINIMUM_TTL,
+        max: ttl_policy::INTEL_TTL,
+    }
+}
+
+const fn default_hits_half_life() -> u32 {
+    7
+}
+
+const fn default_level_step() -> u32 {
+    30
+}
+
+fn default_admin_listen() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 8953))
+}
+
+const fn default_public_resolvers() -> bool {
+    true
+}
+
+const fn default_dane_port() -> u16 {
+    853
+}
+
+fn default_log_level() -> String {
+    "info".into()
+}
+
+const fn default_query_log_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+const fn default_query_log_keep() -> usize {
+    4
+}
+
+const fn default_query_log_sample_rate() -> f64 {
+    0.01
+}
+
+const fn default_query_log_ipv4_prefix() -> u8 {
+    24
+}
+
+const fn default_query_log_ipv6_prefix() -> u8 {
+    48
+}
+
+fn default_ripestat_url() -> String {
+    "https://stat.ripe.net/data/announced-prefixes/data.json".into()
+}
+
+const fn default_asn_prefix_refresh() -> u64 {
+    24 * 60 * 60
+}
+
+const fn default_signal_min_interval() -> u64 {
+    30
+}
+
+const fn default_signal_max_age() -> u64 {
+    60 * 60
+}
+
+const fn default_ttl_monitor_interval() -> u64 {
+    5 * 60
+}
+
+const fn default_ttl_monitor_recheck() -> u64 {
+    2
+}
+
+const fn default_store_enabled() -> bool {
+    true
+}
+
+const fn default_store_compact() -> u64 {
+    60 * 60
+}
+
+fn default_rpz_zone() -> String {
+    String::from("rpz.i1.is.")
+}
+
 fn default_bl_zone() -> String {
     String::from("bl.i1.is.")
 }
@@ -136,6 +1484,10 @@ fn default_ca_zone() -> String {
     String::from("ca.i1.is.")
 }
 
+fn default_intel_zone() -> String {
+    String::from("intel.i1.is.")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,9 +1500,158 @@ mod tests {
         assert_eq!(config.zones.reputation, "rep.i1.is.");
         assert_eq!(config.zones.binary, "bin.i1.is.");
         assert_eq!(config.zones.cert, "ca.i1.is.");
+        assert_eq!(config.zones.intel, "intel.i1.is.");
         assert_eq!(config.reload_interval_secs, 60);
+        assert_eq!(config.drain_timeout_secs, 10);
         assert!(config.peers.is_empty());
         assert!(config.audit_path.is_none());
+        assert!(config.transfer.allow_from.is_empty());
+        assert!(config.transfer.primary.is_none());
+        assert!(!config.dnssec.enabled);
+        assert_eq!(config.dnssec.signature_validity_secs, 604_800);
+        assert!(!config.tls.dot_enabled && !config.tls.doh_enabled);
+        assert_eq!(config.tls.dot_listen.port(), 853);
+        assert_eq!(config.rpz.zone, "rpz.i1.is.");
+        assert!(config.rpz.zone_file.is_none() && !config.rpz.serve);
+        assert!(!config.gossip.enabled);
+        assert_eq!(config.gossip.listen.port(), 7946);
+        assert_eq!(config.gossip.anti_entropy_secs, 60);
+        assert!(!config.metrics.enabled);
+        assert_eq!(config.metrics.listen.port(), 9153);
+        assert!(config.store.enabled && config.store.path.is_none());
+        assert_eq!(config.store.compact_secs, 3600);
+        assert!(!config.admin.enabled && !config.admin.mtls);
+        assert!(config.admin.listen.ip().is_loopback());
+        assert_eq!(config.ttl.blocklist.max, 86400);
+        assert_eq!(config.ttl.level_step_days, 30);
+        assert!(!config.ttl_monitor.enabled);
+        assert_eq!(config.ttl_monitor.resolvers().len(), 3);
+        assert_eq!(config.dane.port, 853);
+        assert!(!config.dane.fail_open);
+        assert!(config.primary_pin().is_none());
+        assert!(!config.query_log.enabled);
+        assert_eq!(config.query_log.ipv4_prefix, 24);
+        assert!((config.query_log.sample_rate - 0.01).abs() < f64::EPSILON);
+        assert!(!config.asn_prefixes.enabled);
+        assert_eq!(config.asn_prefixes.refresh_secs, 86400);
+        assert!(!config.node_signals.enabled);
+        assert_eq!(config.node_signals.max_age_secs, 3600);
+        assert_eq!(config.log.format, LogFormat::Text);
+        assert_eq!(config.log.level, "info");
+        assert_eq!(config.node_fqdn(), "node1.srv.i1.is");
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        assert_eq!(ServerConfig::default().problems(), Vec::<String>::new());
+        assert!(ServerConfig::default().validate().is_ok());
+
+        let config: ServerConfig = toml::from_str(
+            r#"
+            listen = "0.0.0.0:53"
+            node_name = "node1"
+            peers = ["node2@198.51.100.2:7946", "198.51.100.3:7946", "bad..name@198.51.100.4:7946"]
+
+            [zones]
+            blocklist = "bl.i1.is"
+            reputation = "BL.i1.is."
+            geo = "geo..i1.is"
+            asn = "asn.i1.is"
+            signal = "sig.i1.is"
+            binary = "bin.i1.is"
+            cert = "ca.i1.is"
+            intel = "intel.i1.is"
+
+            [ttl.reputation]
+            min = 7200
+            max = 300
+
+            [tls]
+            dot_enabled = true
+            dot_listen = "127.0.0.1:53"
+            key_path = "/nonexistent/key.pem"
+
+            [admin]
+            enabled = true
+            listen = "0.0.0.0:8953"
+
+            [transfer]
+            allow_from = ["198.51.100.0/24", "not-an-ip"]
+            primary = "198.51.100.1:53"
+
+            [dane]
+            fail_open = true
+
+            [query_log]
+            sample_rate = 1.5
+
+            [log]
+            format = "json"
+            level = "info,hickory_server=loud"
+            "#,
+        )
+        .unwrap();
+        let problems = config.problems();
+        let expected = [
+            "zones.reputation: 'BL.i1.is.' is also zones.blocklist",
+            "zones.geo: ",
+            "ttl.reputation: band 7200-300",
+            "tls.dot_listen: 127.0.0.1:53 overlaps listen (0.0.0.0:53)",
+            "admin.listen: beyond loopback requires admin.mtls",
+            "peers: peer '198.51.100.3:7946' is not name@addr",
+            "peers: peer 'bad..name@198.51.100.4:7946' has a bad name",
+            "transfer.allow_from: invalid transfer peer 'not-an-ip'",
+            "transfer.primary_name: required for dane",
+            "query_log.sample_rate: 1.5 is not between 0 and 1",
+            "log.level: ",
+            "tls.key_path: /nonexistent/key.pem is not a file",
+            "tls: cert_path and key_path must be set together",
+        ];
+        for want in expected {
+            assert!(
+                problems.iter().any(|p| p.starts_with(want)),
+                "missing '{want}' in {problems:#?}"
+            );
+        }
+        assert_eq!(problems.len(), expected.len(), "{problems:#?}");
+
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("zones.geo") && err.contains("query_log.sample_rate"));
+    }
+
+    #[test]
+    fn test_gossip_seeds() {
+        let config = ServerConfig {
+            peers: vec![
+                "node2@198.51.100.2:7946".into(),
+                "edge.example.net@[2001:db8::2]:7946".into(),
+            ],
+            ..ServerConfig::default()
+        };
+        let seeds = config.gossip_seeds().unwrap();
+        assert_eq!(seeds[0].0, "node2.srv.i1.is");
+        assert_eq!(seeds[1].0, "edge.example.net");
+        assert_eq!(seeds[1].1.port(), 7946);
+
+        let bad = ServerConfig {
+            peers: vec!["198.51.100.2:7946".into()],
+            ..ServerConfig::default()
+        };
+        assert!(bad.gossip_seeds().is_err());
+    }
+
+    #[test]
+    fn test_query_log_zone_rates() {
+        let config: QueryLogConfig = toml::from_str(
+            r#"
+            sample_rate = 0.5
+            zones = { sig = 1.0, "bl.i1.is." = 0.01 }
+            "#,
+        )
+        .unwrap();
+        assert!((config.zone_rate("sig.i1.is.") - 1.0).abs() < f64::EPSILON);
+        assert!((config.zone_rate("bl.i1.is.") - 0.01).abs() < f64::EPSILON);
+        assert!((config.zone_rate("rep.i1.is.") - 0.5).abs() < f64::EPSILON);
     }
 
     #[test]
@@ -0,0 +1,360 @@
+//! Config hot reload.
+//!
+//! On SIGHUP the config file is read and validated again, and the settings
+//! that can change under a running server are applied in place:
+//!
+//! - `ttl`: handed to the zone rebuilder, which rebuilds with the new
+//!   bands right away (a secondary serves its primary's TTLs regardless).
+//! - `query_log.sample_rate` and `query_log.zones`: swapped into the
+//!   query log.
+//! - `log.level`: swapped into the log filter, unless `I1_LOG` overrides
+//!   it.
+//! - `peers`: handed to gossip as its seeds from the next round on;
+//!   members already learned stay known.
+//!
+//! Any other change (listen addresses, zone names, TLS, gossip, ...) is
+//! logged as needing a restart and otherwise ignored, so the server keeps
+//! running as configured at startup. A file that fails to parse or
+//! validate is rejected whole.
+//!
+//! SIGHUP also rebuilds the zones from the defense state. That is
+//! [`crate::sync::reload`]'s job and happens whether or not the config
+//! changed.
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::config::ServerConfig;
+use crate::query_log::QueryLog;
+use crate::sync::gossip::GossipNode;
+use crate::sync::reload::RebuildHandle;
+
+/// Settings applied without a restart, as dotted paths; a change anywhere
+/// below one of these counts as that setting.
+pub const LIVE_SETTINGS: &[&str] = &[
+    "ttl",
+    "query_log.sample_rate",
+    "query_log.zones",
+    "log.level",
+    "peers",
+];
+
+/// Handle to the installed log filter, for swapping in a new level.
+pub type LogFilter = reload::Handle<EnvFilter, Registry>;
+
+/// What changed between the running config and the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+    /// Changed settings that take effect immediately
+    pub applied: Vec<String>,
+    /// Changed settings that take effect on the next restart
+    pub need_restart: Vec<String>,
+}
+
+impl ConfigChanges {
+    /// Compare two configs setting by setting.
+    #[must_use]
+    pub fn between(running: &ServerConfig, file: &ServerConfig) -> Self {
+        let mut changed = Vec::new();
+        // Both serialize from the same type, so this can't fail in practice.
+        if let (Ok(old), Ok(new)) = (serde_json::to_value(running), serde_json::to_value(file)) {
+            changed_paths("", &old, &new, &mut changed);
+        }
+        let (applied, need_restart) = changed.into_iter().partition(|path| is_live(path));
+        Self {
+            applied,
+            need_restart,
+        }
+    }
+
+    /// Whether nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.need_restart.is_empty()
+    }
+
+    fn touches(&self, setting: &str) -> bool {
+        self.applied.iter().any(|path| within(path, setting))
+    }
+}
+
+/// Collect the dotted paths of the leaves that differ between `old` and `new`.
+fn changed_paths(prefix: &str, old: &Value, new: &Value, out: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                let get = |map: &serde_json::Map<String, Value>| map.get(key).cloned();
+                changed_paths(
+                    &path,
+                    &get(old).unwrap_or(Value::Null),
+                    &get(new).unwrap_or(Value::Null),
+                    out,
+                );
+            }
+        }
+        _ if old != new => out.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+fn is_live(path: &str) -> bool {
+    LIVE_SETTINGS.iter().any(|setting| within(path, setting))
+}
+
+/// Whether `path` is `setting` or below it.
+fn within(path: &str, setting: &str) -> bool {
+    path.strip_prefix(setting)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Re-reads the config file and applies what it can to the running server.
+pub struct ConfigReloader {
+    path: PathBuf,
+    running: ServerConfig,
+    rebuild: Option<RebuildHandle>,
+    query_log: Option<QueryLog>,
+    log_filter: Option<LogFilter>,
+    gossip: Option<GossipNode>,
+}
+
+impl ConfigReloader {
+    /// Reload `path` into a server started with `running`.
+    #[must_use]
+    pub fn new(path: &Path, running: ServerConfig) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            running,
+            rebuild: None,
+            query_log: None,
+            log_filter: None,
+            gossip: None,
+        }
+    }
+
+    /// Apply TTL changes through this primary's zone rebuilder.
+    #[must_use]
+    pub fn with_rebuild(mut self, rebuild: RebuildHandle) -> Self {
+        self.rebuild = Some(rebuild);
+        self
+    }
+
+    /// Apply sample rate changes to this query log.
+    #[must_use]
+    pub fn with_query_log(mut self, query_log: QueryLog) -> Self {
+        self.query_log = Some(query_log);
+        self
+    }
+
+    /// Apply level changes to this log filter.
+    #[must_use]
+    pub fn with_log_filter(mut self, filter: LogFilter) -> Self {
+        self.log_filter = Some(filter);
+        self
+    }
+
+    /// Apply peer list changes to this gossip node.
+    #[must_use]
+    pub fn with_gossip(mut self, gossip: GossipNode) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// The config as currently applied.
+    #[must_use]
+    pub const fn running(&self) -> &ServerConfig {
+        &self.running
+    }
+
+    /// Read the file again and apply the live settings that changed.
+    ///
+    /// Settings that need a restart are reported but keep their running
+    /// values, so they are reported again until the restart happens.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SrvError::Config`](crate::SrvError::Config) if the file is
+    /// missing, doesn't parse or doesn't validate; nothing is applied then.
+    pub fn reload(&mut self) -> crate::Result<ConfigChanges> {
+        if !self.path.is_file() {
+            return Err(crate::SrvError::Config(format!(
+                "{}: no such config file",
+                self.path.display()
+            )));
+        }
+        let file = ServerConfig::load(&self.path)?;
+        file.validate()?;
+
+        let changes = ConfigChanges::between(&self.running, &file);
+        if changes.touches("peers") {
+            if let Some(gossip) = &self.gossip {
+                gossip.set_seeds(file.gossip_seeds()?);
+            }
+            self.running.peers.clone_from(&file.peers);
+        }
+        if changes.touches("query_log") {
+            if let Some(query_log) = &self.query_log {
+                query_log.set_rates(&file.query_log)?;
+            }
+            self.running.query_log.sample_rate = file.query_log.sample_rate;
+            self.running.query_log.zones = file.query_log.zones;
+        }
+        if changes.touches("ttl") {
+            if let Some(rebuild) = &self.rebuild {
+                rebuild.set_ttl(file.ttl.clone());
+            }
+            self.running.ttl = file.ttl;
+        }
+        if changes.touches("log.level") {
+            if let Some(filter) = &self.log_filter {
+                let level = EnvFilter::try_new(&file.log.level)
+                    .map_err(|e| crate::SrvError::Config(format!("log.level: {e}")))?;
+                filter
+                    .reload(level)
+                    .map_err(|e| crate::SrvError::Config(format!("log.level: {e}")))?;
+            }
+            self.running.log.level = file.log.level;
+        }
+        Ok(changes)
+    }
+
+    /// Reload on every SIGHUP, logging the outcome. Runs forever.
+    ///
+    /// SIGHUP is listened for as soon as this is called, so one sent
+    /// before the future is first polled still counts.
+    #[cfg(unix)]
+    pub fn run(mut self) -> impl std::future::Future<Output = ()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let hangup = signal(SignalKind::hangup());
+        async move {
+            let mut hangup = match hangup {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    warn!(error = %e, "cannot listen for SIGHUP, config reload disabled");
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!(path = %self.path.display(), "SIGHUP received, reloading config");
+                match self.reload() {
+                    Ok(changes) if changes.is_empty() => info!("config unchanged"),
+                    Ok(changes) => {
+                        if !changes.applied.is_empty() {
+                            info!(settings = ?changes.applied, "applied config changes");
+                        }
+                        if !changes.need_restart.is_empty() {
+                            warn!(
+                                settings = ?changes.need_restart,
+                                "config changes need a restart to take effect"
+                            );
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "config reload failed, keeping running config"),
+                }
+            }
+        }
+    }
+
+    /// SIGHUP doesn't exist here; the config is only read at startup.
+    #[cfg(not(unix))]
+    pub async fn run(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TtlBand;
+
+    fn write(path: &Path, config: &ServerConfig) {
+        std::fs::write(path, toml::to_string(config).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_changes_are_classified() {
+        let running = ServerConfig::default();
+        assert!(ConfigChanges::between(&running, &running).is_empty());
+
+        let mut file = running.clone();
+        file.ttl.blocklist = TtlBand { min: 60, max: 600 };
+        file.query_log.zones.insert("sig".into(), 1.0);
+        file.listen = "127.0.0.1:5353".parse().unwrap();
+        file.zones.geo = "geo.example.".into();
+        let changes = ConfigChanges::between(&running, &file);
+        assert_eq!(
+            changes.applied,
+            [
+                "query_log.zones.sig",
+                "ttl.blocklist.max",
+                "ttl.blocklist.min"
+            ]
+        );
+        assert_eq!(changes.need_restart, ["listen", "zones.geo"]);
+        assert!(changes.touches("ttl"));
+        assert!(!changes.touches("ttl_monitor"));
+    }
+
+    #[test]
+    fn test_reload_applies_live_settings_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("i1-srv.toml");
+        let running = ServerConfig::default();
+        write(&path, &running);
+        let mut reloader = ConfigReloader::new(&path, running.clone());
+        assert!(reloader.reload().unwrap().is_empty());
+
+        let mut file = running.clone();
+        file.query_log.sample_rate = 0.5;
+        file.reload_interval_secs += 1;
+        write(&path, &file);
+        let changes = reloader.reload().unwrap();
+        assert_eq!(changes.applied, ["query_log.sample_rate"]);
+        assert_eq!(changes.need_restart, ["reload_interval_secs"]);
+        assert!((reloader.running().query_log.sample_rate - 0.5).abs() < f64::EPSILON);
+        assert_eq!(
+            reloader.running().reload_interval_secs,
+            running.reload_interval_secs
+        );
+        // Still pending until a restart.
+        assert_eq!(
+            reloader.reload().unwrap().need_restart,
+            ["reload_interval_secs"]
+        );
+
+        // An invalid file changes nothing.
+        file.ttl.reputation = TtlBand { min: 0, max: 60 };
+        write(&path, &file);
+        assert!(reloader.reload().is_err());
+        assert_eq!(reloader.running().ttl.reputation, running.ttl.reputation);
+    }
+
+    #[test]
+    fn test_reload_swaps_log_level_and_peers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("i1-srv.toml");
+        let running = ServerConfig::default();
+        write(&path, &running);
+        // The handle only works while the layer is alive.
+        let (_layer, filter) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let mut reloader =
+            ConfigReloader::new(&path, running.clone()).with_log_filter(filter.clone());
+
+        let mut file = running;
+        file.log.level = "debug".into();
+        file.peers = vec!["node2@198.51.100.2:7946".into()];
+        write(&path, &file);
+        let changes = reloader.reload().unwrap();
+        assert_eq!(changes.applied, ["log.level", "peers"]);
+        assert!(changes.need_restart.is_empty());
+        assert_eq!(filter.with_current(ToString::to_string).unwrap(), "debug");
+        assert_eq!(reloader.running().peers, file.peers);
+    }
+}
@@ -0,0 +1,209 @@
+//! Prefix lists for blocked ASNs.
+//!
+//! `<asn>.asn.i1.is` only says that an ASN is blocked; a client enforcing
+//! the block also needs the prefixes it announces. Those are published
+//! under `<asn>.prefixes.asn.i1.is`:
+//!
+//! ```text
+//! 64500.prefixes.asn.i1.is    TXT "asn=AS64500;prefixes=5000;chunks=132;fetched=2026-10-18T06:00:00Z;source=ripestat"
+//! 0.64500.prefixes.asn.i1.is  TXT "cbor:o2Nhc24Z..."
+//! 1.64500.prefixes.asn.i1.is  TXT "cbor:o2Nhc24Z..."
+//! ```
+//!
+//! The index record says how many chunks to fetch and when the list was
+//! fetched from its source; `stale=1` marks a list whose last refresh
+//! failed, so clients can decide how far to trust it. Each chunk is a
+//! [`PrefixChunk`] in the `cbor:` overflow encoding of
+//! [`txt_intel`](super::txt_intel), small enough to answer over UDP.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::txt_intel;
+
+/// Label the prefix lists sit under in the ASN zone.
+pub const PREFIXES_LABEL: &str = "prefixes";
+
+/// CBOR bytes per chunk; about 800 characters once base64-encoded.
+const CHUNK_BUDGET: usize = 600;
+
+/// The index record for an ASN's prefix list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixIndex {
+    /// AS number.
+    pub asn: u32,
+    /// Prefixes across all chunks.
+    pub prefixes: usize,
+    /// Chunk records, numbered from 0.
+    pub chunks: usize,
+    /// When the list was fetched from its source.
+    pub fetched: DateTime<Utc>,
+    /// Where the list came from (`ripestat`, `rib`).
+    pub source: String,
+    /// The last refresh failed and this is the list from before it.
+    pub stale: bool,
+}
+
+impl PrefixIndex {
+    /// Encode as a TXT record value string.
+    #[must_use]
+    pub fn to_txt(&self) -> String {
+        let mut txt = format!(
+            "asn=AS{};prefixes={};chunks={};fetched={};source={}",
+            self.asn,
+            self.prefixes,
+            self.chunks,
+            self.fetched.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.source
+        );
+        if self.stale {
+            txt.push_str(";stale=1");
+        }
+        txt
+    }
+
+    /// Parse from a TXT record value string.
+    pub fn from_txt(txt: &str) -> crate::Result<Self> {
+        let field = |key: &str| {
+            txt.split(';')
+                .filter_map(|part| part.split_once('='))
+                .find_map(|(k, v)| (k == key).then_some(v))
+                .ok_or_else(|| crate::SrvError::Encoding(format!("missing {key} field")))
+        };
+        let invalid = |key: &str| crate::SrvError::Encoding(format!("invalid {key} field"));
+
+        Ok(Self {
+            asn: parse_asn(field("asn")?).ok_or_else(|| invalid("asn"))?,
+            prefixes: field("prefixes")?
+                .parse()
+                .map_err(|_| invalid("prefixes"))?,
+            chunks: field("chunks")?.parse().map_err(|_| invalid("chunks"))?,
+            fetched: DateTime::parse_from_rfc3339(field("fetched")?)
+                .map_err(|_| invalid("fetched"))?
+                .with_timezone(&Utc),
+            source: field("source")?.to_string(),
+            stale: field("stale").is_ok_and(|v| v == "1"),
+        })
+    }
+}
+
+/// One chunk of an ASN's prefix list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixChunk {
+    /// AS number, so a misplaced chunk can't be mistaken for another's.
+    pub asn: u32,
+    /// Position of this chunk, from 0.
+    pub chunk: u32,
+    /// Prefixes in CIDR notation.
+    pub prefixes: Vec<String>,
+}
+
+/// Encode a chunk as a `cbor:` TXT record string.
+pub fn encode_chunk(chunk: &PrefixChunk) -> crate::Result<String> {
+    txt_intel::encode_cbor(chunk)
+}
+
+/// Decode a `cbor:` TXT record string into a chunk.
+pub fn decode_chunk(txt: &str) -> crate::Result<PrefixChunk> {
+    let b64 = txt
+        .strip_prefix(txt_intel::CBOR_PREFIX)
+        .ok_or_else(|| crate::SrvError::Encoding("prefix chunk missing 'cbor:' prefix".into()))?;
+    txt_intel::decode_cbor(b64)
+}
+
+/// Split a prefix list into chunks that each encode to a small record.
+#[must_use]
+pub fn chunk_prefixes(prefixes: &[String]) -> Vec<&[String]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+    for (i, prefix) in prefixes.iter().enumerate() {
+        // A CBOR text string is its bytes plus a short header.
+        let len = prefix.len() + 2;
+        if size + len > CHUNK_BUDGET && i > start {
+            chunks.push(&prefixes[start..i]);
+            start = i;
+            size = 0;
+        }
+        size += len;
+    }
+    if start < prefixes.len() {
+        chunks.push(&prefixes[start..]);
+    }
+    chunks
+}
+
+pub use i1_core::asn::parse_asn;
+
+/// Name of the index record for `asn` in the ASN zone `zone`.
+#[must_use]
+pub fn index_name(asn: u32, zone: &str) -> String {
+    format!("{asn}.{PREFIXES_LABEL}.{zone}")
+}
+
+/// Name of chunk `chunk` of `asn`'s prefix list in the ASN zone `zone`.
+#[must_use]
+pub fn chunk_name(asn: u32, chunk: usize, zone: &str) -> String {
+    format!("{chunk}.{}", index_name(asn, zone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_roundtrip() {
+        let index = PrefixIndex {
+            asn: 64500,
+            prefixes: 5000,
+            chunks: 132,
+            fetched: DateTime::parse_from_rfc3339("2026-10-18T06:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            source: "ripestat".into(),
+            stale: true,
+        };
+        let txt = index.to_txt();
+        assert_eq!(
+            txt,
+            "asn=AS64500;prefixes=5000;chunks=132;fetched=2026-10-18T06:00:00Z;source=ripestat;stale=1"
+        );
+        assert_eq!(PrefixIndex::from_txt(&txt).unwrap(), index);
+        assert!(PrefixIndex::from_txt("asn=AS64500;prefixes=1").is_err());
+    }
+
+    #[test]
+    fn test_chunks_fit_budget() {
+        let prefixes: Vec<String> = (0..5000)
+            .map(|i| format!("10.{}.{}.0/24", i / 256, i % 256))
+            .collect();
+        let chunks = chunk_prefixes(&prefixes);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.iter().map(|c| c.len()).sum::<usize>(), 5000);
+
+        for (i, prefixes) in chunks.iter().enumerate() {
+            let chunk = PrefixChunk {
+                asn: 64500,
+                chunk: u32::try_from(i).unwrap(),
+                prefixes: prefixes.to_vec(),
+            };
+            let txt = encode_chunk(&chunk).unwrap();
+            assert!(txt.len() < 1000, "chunk {i} is {} bytes", txt.len());
+            assert_eq!(decode_chunk(&txt).unwrap(), chunk);
+        }
+        assert!(chunk_prefixes(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(parse_asn("AS64500"), Some(64500));
+        assert_eq!(parse_asn("as64500"), Some(64500));
+        assert_eq!(parse_asn("64500"), Some(64500));
+        assert_eq!(parse_asn("ASx"), None);
+        assert_eq!(index_name(64500, "asn.i1.is."), "64500.prefixes.asn.i1.is.");
+        assert_eq!(
+            chunk_name(64500, 3, "asn.i1.is."),
+            "3.64500.prefixes.asn.i1.is."
+        );
+    }
+}
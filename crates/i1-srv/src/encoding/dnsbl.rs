@@ -3,6 +3,12 @@
 //! Standard DNSBL pattern: reverse the IP octets and query under the zone.
 //! Example: checking 1.2.3.4 queries `4.3.2.1.bl.i1.is`
 //!
+//! IPv6 uses the reversed-nibble form (RFC 5782 section 2.4): all 32 hex
+//! nibbles, least significant first. Example: checking `2001:db8::1`
+//! queries `1.0.0.0.(...).8.b.d.0.1.0.0.2.bl.i1.is`. IPv6 listings are
+//! answered with the same 127.0.0.X A records as IPv4, since DNSBL
+//! clients only look at the A answer.
+//!
 //! Return codes (A record values):
 //! - 127.0.0.1 = Listed (generic block)
 //! - 127.0.0.2 = Malicious (confirmed attacker)
@@ -12,9 +18,12 @@
 //! - 127.0.0.10 = Community reported
 //! - NXDOMAIN = Clean (not listed)
 
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 /// DNSBL return codes indicating threat classification.
+///
+/// Returned as A records for both IPv4 and IPv6 listings; there is no
+/// AAAA convention, so a v6 lookup yields the same 127.0.0.X address.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum DnsblCode {
@@ -39,6 +48,20 @@ impl DnsblCode {
         Ipv4Addr::new(127, 0, 0, self as u8)
     }
 
+    /// Parse a code from its 127.0.0.X last octet.
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Listed),
+            2 => Some(Self::Malicious),
+            3 => Some(Self::Suspicious),
+            4 => Some(Self::WebScanner),
+            5 => Some(Self::BruteForce),
+            10 => Some(Self::Community),
+            _ => None,
+        }
+    }
+
     /// Human-readable label for this code.
     #[must_use]
     pub const fn label(self) -> &'static str {
@@ -62,20 +85,47 @@ pub fn reverse_ipv4(ip: &Ipv4Addr) -> String {
     format!("{}.{}.{}.{}", octets[3], octets[2], octets[1], octets[0])
 }
 
+/// Reverse an IPv6 address for DNSBL lookup.
+///
+/// Expands to all 32 nibbles and reverses them, e.g. `2001:db8::1` into
+/// `1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2`.
+#[must_use]
+pub fn reverse_ipv6(ip: &Ipv6Addr) -> String {
+    let nibbles: Vec<String> = ip
+        .octets()
+        .iter()
+        .rev()
+        .flat_map(|b| [b & 0x0f, b >> 4])
+        .map(|n| format!("{n:x}"))
+        .collect();
+    nibbles.join(".")
+}
+
+/// Reverse an IPv4 or IPv6 address for DNSBL lookup.
+#[must_use]
+pub fn reverse_ip(ip: &IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => reverse_ipv4(v4),
+        IpAddr::V6(v6) => reverse_ipv6(v6),
+    }
+}
+
 /// Build the full DNSBL query name for an IP under a zone.
 ///
 /// Example: `build_query_name("1.2.3.4", "bl.i1.is.")` -> `"4.3.2.1.bl.i1.is."`
 pub fn build_query_name(ip: &str, zone: &str) -> crate::Result<String> {
-    let addr: Ipv4Addr = ip
+    let addr: IpAddr = ip
         .parse()
-        .map_err(|e| crate::SrvError::Encoding(format!("invalid IPv4 address '{ip}': {e}")))?;
-    let reversed = reverse_ipv4(&addr);
+        .map_err(|e| crate::SrvError::Encoding(format!("invalid IP address '{ip}': {e}")))?;
+    let reversed = reverse_ip(&addr);
     Ok(format!("{reversed}.{zone}"))
 }
 
 /// Parse a DNSBL query name back into an IP address.
 ///
 /// Example: `parse_query_name("4.3.2.1.bl.i1.is.", "bl.i1.is.")` -> Ok("1.2.3.4")
+///
+/// A 32-label nibble name is parsed as IPv6 and returned in compressed form.
 pub fn parse_query_name(query: &str, zone: &str) -> crate::Result<String> {
     let prefix = query
         .strip_suffix(zone)
@@ -85,6 +135,9 @@ pub fn parse_query_name(query: &str, zone: &str) -> crate::Result<String> {
         })?;
 
     let octets: Vec<&str> = prefix.split('.').collect();
+    if octets.len() == 32 {
+        return parse_nibbles(&octets).map(|ip| ip.to_string());
+    }
     if octets.len() != 4 {
         return Err(crate::SrvError::Encoding(format!(
             "expected 4 octets in reversed IP, got {}",
@@ -98,6 +151,21 @@ pub fn parse_query_name(query: &str, zone: &str) -> crate::Result<String> {
     ))
 }
 
+/// Rebuild an IPv6 address from reversed nibble labels.
+fn parse_nibbles(labels: &[&str]) -> crate::Result<Ipv6Addr> {
+    let mut value: u128 = 0;
+    for label in labels.iter().rev() {
+        let nibble = u8::from_str_radix(label, 16)
+            .ok()
+            .filter(|n| label.len() == 1 && *n < 16)
+            .ok_or_else(|| {
+                crate::SrvError::Encoding(format!("invalid IPv6 nibble label '{label}'"))
+            })?;
+        value = (value << 4) | u128::from(nibble);
+    }
+    Ok(Ipv6Addr::from(value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -152,4 +220,28 @@ mod tests {
     fn test_invalid_ip() {
         assert!(build_query_name("not.an.ip", "bl.i1.is.").is_err());
     }
+
+    #[test]
+    fn test_reverse_ipv6_compressed_and_full_forms() {
+        let expected = "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2";
+        let compressed: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let full: Ipv6Addr = "2001:0db8:0000:0000:0000:0000:0000:0001".parse().unwrap();
+        assert_eq!(reverse_ipv6(&compressed), expected);
+        assert_eq!(reverse_ipv6(&full), expected);
+        assert_eq!(reverse_ipv6(&compressed).split('.').count(), 32);
+    }
+
+    #[test]
+    fn test_ipv6_roundtrip() {
+        let query = build_query_name("2001:DB8:0:0:8:800:200C:417A", "bl.i1.is.").unwrap();
+        assert!(query.starts_with("a.7.1.4.c.0.0.2.0.0.8.0.8.0.0.0"));
+        let parsed = parse_query_name(&query, "bl.i1.is.").unwrap();
+        assert_eq!(parsed, "2001:db8::8:800:200c:417a");
+    }
+
+    #[test]
+    fn test_invalid_nibble() {
+        let labels = vec!["zz"; 32].join(".");
+        assert!(parse_query_name(&format!("{labels}.bl.i1.is."), "bl.i1.is.").is_err());
+    }
 }
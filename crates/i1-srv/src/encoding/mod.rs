@@ -3,7 +3,9 @@
 //! Two encoding strategies:
 //! - **DNSBL**: Reversed IP -> A record (127.0.0.X return codes)
 //! - **TXT Intel**: Hybrid k=v pipe format + CBOR overflow for complex data
+//! - **ASN prefixes**: Chunked CBOR prefix lists behind a k=v index record
 
+pub mod asn_prefixes;
 pub mod dnsbl;
 pub mod signal;
 pub mod txt_intel;
@@ -4,7 +4,9 @@
 //! signal record to see if their cached blocklist is stale. If the serial
 //! changed, they re-query specific IPs they care about.
 //!
-//! Record: `_v.bl.i1.is  30  IN  TXT  "serial=2026021701;entries=4523;updated=2026-02-17T14:30Z"`
+//! Record: `_v.bl.i1.is  30  IN  TXT  "serial=2026021701;entries=4523;updated=2026-02-17T14:30Z;v=1"`
+//!
+//! The `v=` field is the shared wire schema version ([`i1_core::wire`]).
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -44,15 +46,22 @@ impl SignalData {
     #[must_use]
     pub fn to_txt(&self) -> String {
         format!(
-            "serial={};entries={};updated={}",
+            "serial={};entries={};updated={};{}",
             self.serial,
             self.entries,
-            self.updated.format("%Y-%m-%dT%H:%MZ")
+            self.updated.format("%Y-%m-%dT%H:%MZ"),
+            i1_core::wire::version_field()
         )
     }
 
     /// Parse from a TXT record value string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SrvError::SchemaMismatch` if the record names another wire
+    /// schema version, and `SrvError::Encoding` if a field is missing or bad.
     pub fn from_txt(txt: &str) -> crate::Result<Self> {
+        i1_core::wire::check(txt)?;
         let mut serial = None;
         let mut entries = None;
         let mut updated = None;
@@ -124,6 +133,12 @@ mod tests {
         let parsed = SignalData::from_txt(&txt).unwrap();
         assert_eq!(parsed.serial, signal.serial);
         assert_eq!(parsed.entries, signal.entries);
+
+        let newer = txt.replace("v=1", "v=2");
+        assert!(matches!(
+            SignalData::from_txt(&newer),
+            Err(crate::SrvError::SchemaMismatch(_))
+        ));
     }
 
     #[test]
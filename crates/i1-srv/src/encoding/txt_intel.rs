@@ -10,7 +10,36 @@
 //!
 //! DNS TXT records can hold up to 255 bytes per string, with multiple strings
 //! per record. We use the simple format when possible for debuggability.
+//!
+//! Richer structured intel (malware family, campaign tags, TLP marking) uses
+//! [`ComplexIntel`], which is always CBOR-encoded and served from its own zone
+//! (`intel.i1.is`) so the reputation k=v fields stay small and stable.
+//!
+//! ## Origin
+//!
+//! A record can name the node that observed the address as `origin=`,
+//! shortened to the node name's first label ([`origin_prefix`]), so
+//! consumers can weigh sources. It is omitted when unknown.
+//!
+//! ## Payload signatures
+//!
+//! Independently of DNSSEC, a publishing node can append a detached
+//! ECDSA P-256 signature (made with its i1-ca key, see [`NodeSigner`]) as a final `;sig=<base64>`
+//! field. The signature covers the record's owner name followed by the
+//! payload, so a signed record can't be replayed under another address.
+//! Consumers holding the node's public key call [`verify`] with the name
+//! they queried to trust records fetched through untrusted resolvers.
+//! Plain [`decode`] ignores it.
+//!
+//! ## Borrowed decoding
+//!
+//! Read-only consumers that only look at a field or two can use
+//! [`decode_ref`], which parses a simple-format record into a
+//! [`ReputationDataRef`] pointing into the TXT string instead of
+//! allocating a `String` per field.
 
+use chrono::{DateTime, Utc};
+use i1_ca::NodeSigner;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -18,7 +47,33 @@ use std::collections::BTreeMap;
 const SIMPLE_MAX_BYTES: usize = 250;
 
 /// CBOR prefix in TXT records.
-const CBOR_PREFIX: &str = "cbor:";
+pub(crate) const CBOR_PREFIX: &str = "cbor:";
+
+/// Separator for the trailing detached signature field.
+const SIG_SEPARATOR: &str = ";sig=";
+
+/// Append a `;sig=<base64>` field covering `owner` and the whole TXT
+/// payload.
+fn sign(signer: &NodeSigner, owner: &str, txt: &str) -> crate::Result<String> {
+    use base64::Engine;
+
+    let sig = signer
+        .sign(&signed_message(owner, txt))
+        .map_err(|e| crate::SrvError::Encoding(format!("signing failed: {e}")))?;
+    let b64 = base64::engine::general_purpose::STANDARD.encode(sig);
+    Ok(format!("{txt}{SIG_SEPARATOR}{b64}"))
+}
+
+/// The bytes a signature covers: the owner name, lowercased and fully
+/// qualified, followed by the payload.
+fn signed_message(owner: &str, payload: &str) -> Vec<u8> {
+    let owner = owner.trim_end_matches('.').to_ascii_lowercase();
+    let mut message = Vec::with_capacity(owner.len() + 1 + payload.len());
+    message.extend_from_slice(owner.as_bytes());
+    message.push(b'.');
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
 
 /// Reputation data for an IP address.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -51,6 +106,22 @@ pub struct ReputationData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hits: Option<u32>,
 
+    /// Why the address was blocked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// When the address was first seen (epoch seconds).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<i64>,
+
+    /// When the address was last active (epoch seconds).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<i64>,
+
+    /// Node that observed the address, as its name's first label.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+
     /// Extra fields for extensibility.
     #[serde(flatten)]
     pub extra: BTreeMap<String, String>,
@@ -64,6 +135,186 @@ impl ReputationData {
     }
 }
 
+/// Borrowed view of a simple-format reputation record.
+///
+/// Text fields point into the TXT string; ports and unknown fields are
+/// parsed on demand. [`into_owned`](Self::into_owned) gives the
+/// equivalent [`ReputationData`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReputationDataRef<'a> {
+    /// Country code (ISO 2-letter).
+    pub cc: Option<&'a str>,
+    /// AS number (e.g., "AS1234").
+    pub asn: Option<&'a str>,
+    /// Organization name.
+    pub org: Option<&'a str>,
+    /// Threat level.
+    pub threat: Option<&'a str>,
+    /// Attack pattern (e.g., "ssh", "web-scan", "smtp").
+    pub pattern: Option<&'a str>,
+    /// Number of hits/detections.
+    pub hits: Option<u32>,
+    /// Why the address was blocked.
+    pub reason: Option<&'a str>,
+    /// When the address was first seen (epoch seconds).
+    pub first_seen: Option<i64>,
+    /// When the address was last active (epoch seconds).
+    pub last_seen: Option<i64>,
+    /// Node that observed the address, as its name's first label.
+    pub origin: Option<&'a str>,
+    /// Raw comma-separated port list.
+    ports: &'a str,
+    /// The whole record, scanned again for unknown fields.
+    payload: &'a str,
+}
+
+impl<'a> ReputationDataRef<'a> {
+    /// Parse simple k=v fields; parts without `=` are skipped and a
+    /// repeated key keeps its last value.
+    fn parse(payload: &'a str) -> Self {
+        let mut data = Self {
+            payload,
+            ..Self::default()
+        };
+        for (key, value) in fields(payload) {
+            match key {
+                "cc" => data.cc = Some(value),
+                "asn" => data.asn = Some(value),
+                "org" => data.org = Some(value),
+                "ports" => data.ports = value,
+                "threat" => data.threat = Some(value),
+                "pattern" => data.pattern = Some(value),
+                "hits" => data.hits = value.parse().ok(),
+                "reason" => data.reason = Some(value),
+                "first_seen" => data.first_seen = value.parse().ok(),
+                "last_seen" => data.last_seen = value.parse().ok(),
+                "origin" => data.origin = Some(value),
+                _ => {}
+            }
+        }
+        data
+    }
+
+    /// Open ports; entries that aren't port numbers are skipped.
+    pub fn ports(&self) -> impl Iterator<Item = u16> + 'a {
+        self.ports.split(',').filter_map(|p| p.parse().ok())
+    }
+
+    /// Fields other than the known ones, in record order.
+    pub fn extra(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        fields(self.payload).filter(|(key, _)| !KNOWN_FIELDS.contains(key))
+    }
+
+    /// Copy into an owned [`ReputationData`].
+    #[must_use]
+    pub fn into_owned(self) -> ReputationData {
+        let owned = |field: Option<&str>| field.map(str::to_string);
+        ReputationData {
+            cc: owned(self.cc),
+            asn: owned(self.asn),
+            org: owned(self.org),
+            ports: self.ports().collect(),
+            threat: owned(self.threat),
+            pattern: owned(self.pattern),
+            hits: self.hits,
+            reason: owned(self.reason),
+            first_seen: self.first_seen,
+            last_seen: self.last_seen,
+            origin: owned(self.origin),
+            extra: self
+                .extra()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// Keys with their own [`ReputationData`] field.
+const KNOWN_FIELDS: [&str; 11] = [
+    "cc",
+    "asn",
+    "org",
+    "ports",
+    "threat",
+    "pattern",
+    "hits",
+    "reason",
+    "first_seen",
+    "last_seen",
+    "origin",
+];
+
+/// Shorten a node name to the prefix published as `origin=`: its first
+/// label (`node-a` for `node-a.srv.i1.is`).
+#[must_use]
+pub fn origin_prefix(node: &str) -> &str {
+    node.split('.').next().unwrap_or(node)
+}
+
+/// The `key=value` parts of a simple-format record.
+fn fields(payload: &str) -> impl Iterator<Item = (&str, &str)> {
+    payload.split(';').filter_map(|part| part.split_once('='))
+}
+
+/// Traffic Light Protocol marking for shared intel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tlp {
+    /// TLP:CLEAR - no restrictions on disclosure.
+    Clear,
+    /// TLP:GREEN - community-wide sharing.
+    Green,
+    /// TLP:AMBER - limited to recipients' organizations and clients.
+    Amber,
+    /// TLP:AMBER+STRICT - limited to recipients' organizations only.
+    AmberStrict,
+    /// TLP:RED - named recipients only.
+    Red,
+}
+
+/// Structured, multi-field threat intel for an indicator.
+///
+/// Unlike [`ReputationData`], this is never flattened into k=v pairs:
+/// it always travels as `cbor:<base64>` so nested lists survive intact.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplexIntel {
+    /// Malware family attributed to this indicator (e.g., "mirai").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub malware_family: Option<String>,
+
+    /// Campaign identifiers this indicator is associated with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub campaigns: Vec<String>,
+
+    /// Free-form tags (e.g., "c2", "botnet", "tor-exit").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Sharing restriction for this record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tlp: Option<Tlp>,
+
+    /// Publisher confidence (0-100).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<u8>,
+
+    /// When this indicator was first observed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime<Utc>>,
+
+    /// When this indicator was last observed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+
+    /// External references (report URLs, CVE IDs).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+
+    /// Extra fields for extensibility.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
 /// Encode reputation data into a TXT record string.
 ///
 /// Uses simple k=v format when small enough, CBOR+Base64 for overflow.
@@ -77,14 +328,127 @@ pub fn encode(data: &ReputationData) -> crate::Result<String> {
     }
 }
 
+/// Encode reputation data, appending a signature when a signer is given.
+///
+/// `owner` is the name the record is served under.
+pub fn encode_with(
+    data: &ReputationData,
+    owner: &str,
+    signer: Option<&NodeSigner>,
+) -> crate::Result<String> {
+    let txt = encode(data)?;
+    match signer {
+        Some(s) => sign(s, owner, &txt),
+        None => Ok(txt),
+    }
+}
+
 /// Decode a TXT record string back into reputation data.
+///
+/// A trailing signature field is stripped without being checked;
+/// use [`verify`] when authenticity matters.
 pub fn decode(txt: &str) -> crate::Result<ReputationData> {
-    txt.strip_prefix(CBOR_PREFIX).map_or_else(
-        || Ok(decode_simple(txt)),
+    let (payload, _) = split_signature(txt);
+    payload.strip_prefix(CBOR_PREFIX).map_or_else(
+        || Ok(decode_simple(payload)),
         decode_cbor,
     )
 }
 
+/// Decode a simple-format TXT record without copying its fields.
+///
+/// A trailing signature field is stripped without being checked, as in
+/// [`decode`].
+///
+/// Fails with [`crate::SrvError::Encoding`] for `cbor:` records, which
+/// have no borrowed form; use [`decode`] for those.
+pub fn decode_ref(txt: &str) -> crate::Result<ReputationDataRef<'_>> {
+    let (payload, _) = split_signature(txt);
+    if payload.starts_with(CBOR_PREFIX) {
+        return Err(crate::SrvError::Encoding(
+            "CBOR records can't be decoded borrowed".into(),
+        ));
+    }
+    Ok(ReputationDataRef::parse(payload))
+}
+
+/// Verify a signed TXT record against a node's public key, then decode it.
+///
+/// `owner` is the name the record was queried under; a record signed for
+/// a different name fails.
+///
+/// Fails with [`crate::SrvError::Trust`] if the signature is missing or invalid.
+pub fn verify(owner: &str, txt: &str, public_key: &[u8]) -> crate::Result<ReputationData> {
+    decode(verify_signature(owner, txt, public_key)?)
+}
+
+/// Verify a signed complex intel record queried under `owner`, then decode it.
+pub fn verify_complex(
+    owner: &str,
+    txt: &str,
+    public_key: &[u8],
+) -> crate::Result<ComplexIntel> {
+    decode_complex(verify_signature(owner, txt, public_key)?)
+}
+
+/// Check the trailing signature against `owner` and return the signed payload.
+fn verify_signature<'a>(
+    owner: &str,
+    txt: &'a str,
+    public_key: &[u8],
+) -> crate::Result<&'a str> {
+    use base64::Engine;
+
+    let (payload, sig_b64) = split_signature(txt);
+    let sig_b64 =
+        sig_b64.ok_or_else(|| crate::SrvError::Trust("record is not signed".into()))?;
+    let sig = base64::engine::general_purpose::STANDARD
+        .decode(sig_b64)
+        .map_err(|e| crate::SrvError::Trust(format!("malformed signature: {e}")))?;
+
+    i1_ca::verify_signature(public_key, &signed_message(owner, payload), &sig)
+        .map_err(|_| crate::SrvError::Trust("intel record signature mismatch".into()))?;
+
+    Ok(payload)
+}
+
+/// Split a TXT value into payload and optional base64 signature.
+fn split_signature(txt: &str) -> (&str, Option<&str>) {
+    txt.rsplit_once(SIG_SEPARATOR)
+        .map_or((txt, None), |(payload, sig)| (payload, Some(sig)))
+}
+
+/// Encode structured intel into a TXT record string (always `cbor:` prefixed).
+pub fn encode_complex(intel: &ComplexIntel) -> crate::Result<String> {
+    encode_cbor(intel)
+}
+
+/// Encode structured intel, appending a signature when a signer is given.
+///
+/// `owner` is the name the record is served under.
+pub fn encode_complex_with(
+    intel: &ComplexIntel,
+    owner: &str,
+    signer: Option<&NodeSigner>,
+) -> crate::Result<String> {
+    let txt = encode_complex(intel)?;
+    match signer {
+        Some(s) => sign(s, owner, &txt),
+        None => Ok(txt),
+    }
+}
+
+/// Decode a `cbor:` TXT record string into structured intel.
+///
+/// Unlike [`decode`], there is no k=v fallback: complex intel is CBOR-only.
+pub fn decode_complex(txt: &str) -> crate::Result<ComplexIntel> {
+    let (payload, _) = split_signature(txt);
+    let b64 = payload.strip_prefix(CBOR_PREFIX).ok_or_else(|| {
+        crate::SrvError::Encoding("complex intel record missing 'cbor:' prefix".into())
+    })?;
+    decode_cbor(b64)
+}
+
 /// Encode as simple semicolon-separated k=v pairs.
 fn encode_simple(data: &ReputationData) -> String {
     let mut parts = Vec::new();
@@ -111,6 +475,18 @@ fn encode_simple(data: &ReputationData) -> String {
     if let Some(hits) = data.hits {
         parts.push(format!("hits={hits}"));
     }
+    if let Some(ref reason) = data.reason {
+        parts.push(format!("reason={reason}"));
+    }
+    if let Some(first_seen) = data.first_seen {
+        parts.push(format!("first_seen={first_seen}"));
+    }
+    if let Some(last_seen) = data.last_seen {
+        parts.push(format!("last_seen={last_seen}"));
+    }
+    if let Some(ref origin) = data.origin {
+        parts.push(format!("origin={origin}"));
+    }
     for (k, v) in &data.extra {
         parts.push(format!("{k}={v}"));
     }
@@ -120,41 +496,11 @@ fn encode_simple(data: &ReputationData) -> String {
 
 /// Decode simple k=v format.
 fn decode_simple(txt: &str) -> ReputationData {
-    let mut data = ReputationData {
-        cc: None,
-        asn: None,
-        org: None,
-        ports: Vec::new(),
-        threat: None,
-        pattern: None,
-        hits: None,
-        extra: BTreeMap::new(),
-    };
-
-    for part in txt.split(';') {
-        if let Some((key, value)) = part.split_once('=') {
-            match key {
-                "cc" => data.cc = Some(value.to_string()),
-                "asn" => data.asn = Some(value.to_string()),
-                "org" => data.org = Some(value.to_string()),
-                "ports" => {
-                    data.ports = value.split(',').filter_map(|p| p.parse().ok()).collect();
-                }
-                "threat" => data.threat = Some(value.to_string()),
-                "pattern" => data.pattern = Some(value.to_string()),
-                "hits" => data.hits = value.parse().ok(),
-                _ => {
-                    data.extra.insert(key.to_string(), value.to_string());
-                }
-            }
-        }
-    }
-
-    data
+    ReputationDataRef::parse(txt).into_owned()
 }
 
 /// Encode as CBOR + Base64.
-fn encode_cbor(data: &ReputationData) -> crate::Result<String> {
+pub(crate) fn encode_cbor<T: Serialize>(data: &T) -> crate::Result<String> {
     use base64::Engine;
 
     let mut cbor_bytes = Vec::new();
@@ -166,7 +512,7 @@ fn encode_cbor(data: &ReputationData) -> crate::Result<String> {
 }
 
 /// Decode CBOR + Base64.
-fn decode_cbor(b64: &str) -> crate::Result<ReputationData> {
+pub(crate) fn decode_cbor<T: serde::de::DeserializeOwned>(b64: &str) -> crate::Result<T> {
     use base64::Engine;
 
     let bytes = base64::engine::general_purpose::STANDARD
@@ -189,7 +535,7 @@ mod tests {
             threat: Some("high".into()),
             pattern: Some("ssh".into()),
             hits: Some(42),
-            extra: BTreeMap::new(),
+            ..ReputationData::empty()
         }
     }
 
@@ -209,13 +555,70 @@ mod tests {
         assert_eq!(decoded.hits, data.hits);
     }
 
+    #[test]
+    fn test_block_metadata_roundtrip() {
+        let data = ReputationData {
+            reason: Some("ssh brute force".into()),
+            first_seen: Some(1_767_225_600),
+            last_seen: Some(1_769_904_000),
+            ..sample_data()
+        };
+        let encoded = encode_simple(&data);
+        assert!(encoded.contains("reason=ssh brute force;first_seen=1767225600"));
+
+        let decoded = decode_simple(&encoded);
+        assert_eq!(decoded.reason, data.reason);
+        assert_eq!(decoded.first_seen, data.first_seen);
+        assert_eq!(decoded.last_seen, data.last_seen);
+        assert!(decoded.extra.is_empty());
+    }
+
+    #[test]
+    fn test_origin_prefix_roundtrip() {
+        let data = ReputationData {
+            origin: Some(origin_prefix("node-a.srv.i1.is").into()),
+            ..sample_data()
+        };
+        let encoded = encode_simple(&data);
+        assert!(encoded.ends_with(";origin=node-a"), "{encoded}");
+        assert_eq!(decode_ref(&encoded).unwrap().origin, Some("node-a"));
+
+        let decoded = decode_simple(&encoded);
+        assert_eq!(decoded.origin.as_deref(), Some("node-a"));
+        assert!(decoded.extra.is_empty());
+        assert_eq!(origin_prefix("node-b"), "node-b");
+    }
+
+    #[test]
+    fn test_honeypot_carding_export_loads() {
+        use i1_honeypot::{CardNetwork, CardRegistry, DeploymentContext, HoneypotCard};
+
+        let card = HoneypotCard::generate(CardNetwork::Visa);
+        let mut registry = CardRegistry::new(b"secret");
+        registry
+            .register(&card, DeploymentContext::new("shop.example"))
+            .unwrap();
+        let ip = "203.0.113.9".parse().unwrap();
+        registry.record_hit(&card.number, chrono::Utc::now(), "gateway", Some(ip));
+
+        let export = registry.reputation_export();
+        let data: ReputationData = serde_json::to_value(&export[&ip])
+            .and_then(serde_json::from_value)
+            .unwrap();
+        assert_eq!(data.pattern.as_deref(), Some("carding"));
+        assert_eq!(data.hits, Some(1));
+        assert!(data.extra.is_empty());
+        assert!(encode_simple(&data).contains("pattern=carding"));
+    }
+
     #[test]
     fn test_cbor_encode_decode() {
         let data = sample_data();
         let encoded = encode_cbor(&data).unwrap();
         assert!(encoded.starts_with("cbor:"));
 
-        let decoded = decode_cbor(encoded.strip_prefix("cbor:").unwrap()).unwrap();
+        let decoded: ReputationData =
+            decode_cbor(encoded.strip_prefix("cbor:").unwrap()).unwrap();
         assert_eq!(decoded.cc, data.cc);
         assert_eq!(decoded.asn, data.asn);
         assert_eq!(decoded.ports, data.ports);
@@ -229,6 +632,34 @@ mod tests {
         assert!(!encoded.starts_with("cbor:"));
     }
 
+    #[test]
+    fn test_decode_ref_borrows_fields() {
+        let txt = "cc=cn;org=Evil Corp;ports=22,x,443;hits=7;feed=spamhaus;junk;cc=ru;sig=c2ln";
+        let data = decode_ref(txt).unwrap();
+        assert_eq!(data.cc, Some("ru"));
+        assert_eq!(data.org, Some("Evil Corp"));
+        assert!(std::ptr::eq(data.org.unwrap().as_ptr(), txt[10..].as_ptr()));
+        assert_eq!(data.ports().collect::<Vec<_>>(), [22, 443]);
+        assert_eq!(data.hits, Some(7));
+        assert_eq!(data.extra().collect::<Vec<_>>(), [("feed", "spamhaus")]);
+
+        let owned = data.into_owned();
+        let decoded = decode(txt).unwrap();
+        assert_eq!(owned.cc, decoded.cc);
+        assert_eq!(owned.ports, decoded.ports);
+        assert_eq!(owned.extra, decoded.extra);
+        assert_eq!(
+            serde_json::to_value(&owned).unwrap(),
+            serde_json::to_value(&decoded).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decode_ref_rejects_cbor() {
+        let encoded = encode_cbor(&sample_data()).unwrap();
+        assert!(decode_ref(&encoded).is_err());
+    }
+
     #[test]
     fn test_decode_dispatches_correctly() {
         let simple = "cc=us;threat=low";
@@ -245,4 +676,106 @@ mod tests {
         assert_eq!(decoded.org, data.org);
         assert_eq!(decoded.pattern, data.pattern);
     }
+
+    #[test]
+    fn test_complex_intel_roundtrip() {
+        let intel = ComplexIntel {
+            malware_family: Some("mirai".into()),
+            campaigns: vec!["op-telnet-2026".into()],
+            tags: vec!["botnet".into(), "c2".into()],
+            tlp: Some(Tlp::AmberStrict),
+            confidence: Some(85),
+            first_seen: Some(Utc::now()),
+            references: vec!["CVE-2024-1234".into()],
+            ..ComplexIntel::default()
+        };
+
+        let encoded = encode_complex(&intel).unwrap();
+        assert!(encoded.starts_with("cbor:"));
+
+        let decoded = decode_complex(&encoded).unwrap();
+        assert_eq!(decoded, intel);
+    }
+
+    #[test]
+    fn test_complex_intel_rejects_simple_format() {
+        assert!(decode_complex("cc=us;threat=low").is_err());
+    }
+
+    const OWNER: &str = "4.3.2.1.rep.i1.is";
+
+    fn test_signer() -> NodeSigner {
+        use ring::rand::SystemRandom;
+        use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+        let rng = SystemRandom::new();
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let pem_str = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec()));
+        NodeSigner::from_pkcs8_pem(&pem_str).unwrap()
+    }
+
+    #[test]
+    fn test_signed_roundtrip() {
+        let node_key = test_signer();
+        let data = sample_data();
+        let signed = encode_with(&data, OWNER, Some(&node_key)).unwrap();
+        assert!(signed.contains(";sig="));
+
+        let verified = verify(OWNER, &signed, node_key.public_key()).unwrap();
+        assert_eq!(verified.cc, data.cc);
+        // Signature field never leaks into extras.
+        assert!(verified.extra.is_empty());
+
+        // Plain decode still works and ignores the signature.
+        let decoded = decode(&signed).unwrap();
+        assert_eq!(decoded.asn, data.asn);
+        assert!(decoded.extra.is_empty());
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_and_unsigned() {
+        let node_key = test_signer();
+        let signed = encode_with(&sample_data(), OWNER, Some(&node_key)).unwrap();
+
+        let tampered = signed.replace("cc=cn", "cc=us");
+        assert!(verify(OWNER, &tampered, node_key.public_key()).is_err());
+
+        let unsigned = encode(&sample_data()).unwrap();
+        assert!(verify(OWNER, &unsigned, node_key.public_key()).is_err());
+
+        let other = test_signer();
+        assert!(verify(OWNER, &signed, other.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_record_moved_to_another_name() {
+        let node_key = test_signer();
+        let signed = encode_with(&sample_data(), OWNER, Some(&node_key)).unwrap();
+
+        // Case and the trailing dot don't change the name.
+        assert!(verify("4.3.2.1.REP.i1.is.", &signed, node_key.public_key()).is_ok());
+
+        let moved = "8.8.8.8.rep.i1.is";
+        let err = verify(moved, &signed, node_key.public_key()).unwrap_err();
+        assert!(matches!(err, crate::SrvError::Trust(_)));
+    }
+
+    #[test]
+    fn test_signed_complex_intel() {
+        let node_key = test_signer();
+        let intel = ComplexIntel {
+            malware_family: Some("qakbot".into()),
+            tlp: Some(Tlp::Red),
+            ..ComplexIntel::default()
+        };
+        let owner = "4.3.2.1.intel.i1.is";
+        let signed = encode_complex_with(&intel, owner, Some(&node_key)).unwrap();
+        assert_eq!(
+            verify_complex(owner, &signed, node_key.public_key()).unwrap(),
+            intel
+        );
+        assert!(verify_complex(OWNER, &signed, node_key.public_key()).is_err());
+        assert_eq!(decode_complex(&signed).unwrap(), intel);
+    }
 }
@@ -33,10 +33,18 @@ pub enum SrvError {
     #[error("identity error: {0}")]
     Identity(String),
 
+    /// TSIG key loading, signing or verification failed.
+    #[error("tsig error: {0}")]
+    Tsig(String),
+
     /// DANE/TLSA trust verification failed.
     #[error("trust verification failed: {0}")]
     Trust(String),
 
+    /// On-disk zone store failed.
+    #[error("zone store error: {0}")]
+    Store(String),
+
     /// Gossip/sync protocol error.
     #[error("sync error: {0}")]
     Sync(String),
@@ -53,6 +61,10 @@ pub enum SrvError {
         resolver: String,
     },
 
+    /// A record was written with another wire schema version.
+    #[error(transparent)]
+    SchemaMismatch(#[from] i1_core::wire::SchemaMismatch),
+
     /// IO error.
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
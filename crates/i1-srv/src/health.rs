@@ -0,0 +1,221 @@
+//! Liveness and readiness.
+//!
+//! Both are served next to the metrics (see [`crate::metrics`]) and answer
+//! with the node's [`HealthStatus`] as JSON:
+//!
+//! - `GET /healthz` answers 200 for as long as the process serves at all.
+//! - `GET /readyz` answers 200 once the zones are loaded and, when gossip
+//!   has seeds to join through, a peer has been heard from; 503 before
+//!   that, and from the moment shutdown starts so traffic moves elsewhere
+//!   while in-flight queries drain.
+//!
+//! [`Health`] also counts the queries being answered, which is what a
+//! graceful shutdown waits on.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::watch;
+
+use crate::server::ServedZones;
+use crate::sync::gossip::GossipNode;
+
+/// Path the liveness check is served on.
+pub const LIVE_PATH: &str = "/healthz";
+
+/// Path the readiness check is served on.
+pub const READY_PATH: &str = "/readyz";
+
+/// What both health endpoints report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthStatus {
+    /// Whether the node should be sent queries.
+    pub ready: bool,
+    /// Why not, when it shouldn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Whether the zones have been loaded.
+    pub zones_loaded: bool,
+    /// Serial of the zones being served.
+    pub serial: Option<u32>,
+    /// When the zones being served were built, restored or transferred.
+    pub last_rebuild: Option<DateTime<Utc>>,
+    /// Gossip peers known, when gossip is enabled.
+    pub peers: Option<usize>,
+    /// Queries being answered.
+    pub in_flight: usize,
+    /// Whether shutdown has started.
+    pub draining: bool,
+}
+
+/// A node's health as it starts, serves and shuts down.
+///
+/// Clones share the same state.
+#[derive(Clone)]
+pub struct Health {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    zones: OnceLock<ServedZones>,
+    gossip: OnceLock<GossipNode>,
+    draining: AtomicBool,
+    in_flight: watch::Sender<usize>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                zones: OnceLock::new(),
+                gossip: OnceLock::new(),
+                draining: AtomicBool::new(false),
+                in_flight: watch::channel(0).0,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Health")
+            .field("draining", &self.is_draining())
+            .field("in_flight", &self.in_flight())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Health {
+    /// Note that `zones` are loaded and being served.
+    pub fn zones_loaded(&self, zones: &ServedZones) {
+        let _ = self.inner.zones.set(zones.clone());
+    }
+
+    /// Wait for `node` to hear from a peer before reporting ready, if it
+    /// has seeds to join through.
+    pub fn gossip(&self, node: &GossipNode) {
+        let _ = self.inner.gossip.set(node.clone());
+    }
+
+    /// Report not ready from now on, because shutdown has started.
+    pub fn start_draining(&self) {
+        self.inner.draining.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether shutdown has started.
+    #[must_use]
+    pub fn is_draining(&self) -> bool {
+        self.inner.draining.load(Ordering::Relaxed)
+    }
+
+    /// Count a query as in flight until the guard is dropped.
+    pub fn track(&self) -> InFlight {
+        self.inner.in_flight.send_modify(|n| *n += 1);
+        InFlight {
+            health: self.clone(),
+        }
+    }
+
+    /// Queries being answered.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        *self.inner.in_flight.borrow()
+    }
+
+    /// Wait until no queries are being answered.
+    pub async fn drained(&self) {
+        let mut in_flight = self.inner.in_flight.subscribe();
+        // The sender lives as long as `self`, so this can't fail.
+        let _ = in_flight.wait_for(|n| *n == 0).await;
+    }
+
+    /// The node's health right now.
+    #[must_use]
+    pub fn status(&self) -> HealthStatus {
+        let zones = self.inner.zones.get();
+        let peers = self.inner.gossip.get().map(|node| {
+            let joined = node.members().len().saturating_sub(1);
+            (joined, node.seeds().is_empty())
+        });
+        let draining = self.is_draining();
+        let reason = if draining {
+            Some("shutting down")
+        } else if zones.is_none() {
+            Some("zones not loaded")
+        } else if matches!(peers, Some((0, false))) {
+            Some("no gossip peers yet")
+        } else {
+            None
+        };
+        HealthStatus {
+            ready: reason.is_none(),
+            reason: reason.map(String::from),
+            zones_loaded: zones.is_some(),
+            serial: zones.and_then(|zones| zones.signal.serial()),
+            last_rebuild: zones.map(|zones| zones.signal.served_since()),
+            peers: peers.map(|(joined, _)| joined),
+            in_flight: self.in_flight(),
+            draining,
+        }
+    }
+}
+
+/// A query being answered; see [`Health::track`].
+#[must_use = "the query stops counting as in flight when this is dropped"]
+pub struct InFlight {
+    health: Health,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.health
+            .inner
+            .in_flight
+            .send_modify(|n| *n = n.saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::zone_builder::{self, DefenseSnapshot};
+    use crate::config::ZoneConfig;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_readiness_follows_lifecycle() {
+        let health = Health::default();
+        let status = health.status();
+        assert!(!status.ready);
+        assert_eq!(status.reason.as_deref(), Some("zones not loaded"));
+        assert_eq!(status.serial, None);
+
+        let zones =
+            zone_builder::build_zones(&DefenseSnapshot::default(), &ZoneConfig::default(), 7)
+                .unwrap();
+        health.zones_loaded(&ServedZones::new(zones, 4));
+        let status = health.status();
+        assert!(status.ready, "{status:?}");
+        assert_eq!(status.serial, Some(7));
+        assert!(status.last_rebuild.is_some());
+
+        let query = health.track();
+        health.start_draining();
+        let status = health.status();
+        assert!(!status.ready);
+        assert_eq!(status.reason.as_deref(), Some("shutting down"));
+        assert_eq!(status.in_flight, 1);
+
+        let drained = health.clone();
+        let wait = tokio::spawn(async move { drained.drained().await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!wait.is_finished());
+        drop(query);
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(health.in_flight(), 0);
+    }
+}
@@ -13,6 +13,7 @@
 //! - `sig.i1.is` - Signal records (near-zero TTL version checks)
 //! - `bin.i1.is` - Binary hash consensus (hash-prefix -> TXT audit data)
 //! - `ca.i1.is` - Certificate fingerprint consensus (fp-prefix -> TXT cert data)
+//! - `intel.i1.is` - Structured threat intel (reversed-IP -> CBOR TXT)
 //!
 //! # Encoding
 //!
@@ -20,13 +21,19 @@
 //! - **Simple**: Semicolon-separated `k=v` pairs in TXT records (human-readable via `dig`)
 //! - **Complex**: CBOR+Base64 in TXT records, prefixed with `cbor:` for overflow data
 
+pub mod admin;
 pub mod authority;
 pub mod config;
+pub mod config_reload;
 pub mod encoding;
 pub mod error;
+pub mod health;
+pub mod metrics;
 pub mod node;
+pub mod query_log;
 pub mod server;
 pub mod sync;
+pub mod tls;
 pub mod trust;
 
 // Re-exports for convenience.
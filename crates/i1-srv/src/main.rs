@@ -0,0 +1,143 @@
+//! i1-srv node.
+//!
+//! ```text
+//! i1-srv [--config <path>] [--check-config]
+//! ```
+//!
+//! Loads the config (default: `i1-srv.toml`) and the defense state, then
+//! serves the zones until shut down. With `--check-config` the config is
+//! only validated: every problem is printed and the exit status is nonzero
+//! if there were any, so CI can catch them before a deploy.
+//!
+//! SIGHUP rebuilds the zones and reloads the config; settings that can't
+//! change while running are logged as needing a restart. SIGTERM and
+//! SIGINT drain in-flight queries and flush to disk before exiting.
+//!
+//! Logs go to stderr, as text or JSON lines (`[log] format`), filtered by
+//! `[log] level` or the `I1_LOG` environment variable. A level set by
+//! `I1_LOG` isn't touched by a reload.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use i1_srv::authority::zone_builder::DefenseSnapshot;
+use i1_srv::config::{LogConfig, LogFormat};
+use i1_srv::config_reload::LogFilter;
+use i1_srv::server::RunOptions;
+use i1_srv::sync::collector;
+use i1_srv::ServerConfig;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter};
+
+const USAGE: &str = "usage: i1-srv [--config <path>] [--check-config]";
+
+fn main() -> ExitCode {
+    let mut config_path = PathBuf::from("i1-srv.toml");
+    let mut check = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" | "-c" => match args.next() {
+                Some(path) => config_path = PathBuf::from(path),
+                None => return usage_error("--config needs a path"),
+            },
+            "--check-config" => check = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            other => return usage_error(&format!("unknown argument '{other}'")),
+        }
+    }
+
+    if check {
+        check_config(&config_path)
+    } else {
+        match serve(&config_path) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("i1-srv: {e}");
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+/// Validate the config at `path`, printing every problem.
+fn check_config(path: &std::path::Path) -> ExitCode {
+    // A missing file would silently check the defaults.
+    if !path.is_file() {
+        eprintln!("{}: no such config file", path.display());
+        return ExitCode::FAILURE;
+    }
+    let config = match ServerConfig::load(path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let problems = config.problems();
+    if problems.is_empty() {
+        println!("{}: ok", path.display());
+        return ExitCode::SUCCESS;
+    }
+    for problem in &problems {
+        eprintln!("{}: {problem}", path.display());
+    }
+    eprintln!("{} problem(s) found", problems.len());
+    ExitCode::FAILURE
+}
+
+/// Load the config and defense state, then run the server.
+fn serve(path: &std::path::Path) -> i1_srv::Result<()> {
+    let config = ServerConfig::load(path)?;
+    config.validate()?;
+    let log_filter = init_logging(&config.log);
+    let snapshot = config
+        .state_path
+        .clone()
+        .or_else(collector::default_state_path)
+        .map_or_else(
+            || Ok(DefenseSnapshot::default()),
+            |path| DefenseSnapshot::load(&path),
+        )?;
+    let options = RunOptions {
+        // Without a file there is nothing to reload on SIGHUP.
+        config_path: path.is_file().then(|| path.to_path_buf()),
+        log_filter,
+        ..RunOptions::default()
+    };
+    tokio::runtime::Runtime::new()?.block_on(i1_srv::server::run(&config, snapshot, options))
+}
+
+/// Install the stderr subscriber for `config`; returns the filter to
+/// apply level changes to, unless `I1_LOG` sets the level.
+fn init_logging(config: &LogConfig) -> Option<LogFilter> {
+    let from_env = EnvFilter::try_from_env("I1_LOG").ok();
+    let reloadable = from_env.is_none();
+    // `validate` has already checked `level`
+    let filter = from_env.unwrap_or_else(|| EnvFilter::try_new(&config.level).unwrap_or_default());
+    let (filter, handle) = reload::Layer::new(filter);
+    let registry = tracing_subscriber::registry().with(filter);
+    let layer = fmt::layer().with_writer(std::io::stderr);
+    match config.format {
+        LogFormat::Text => registry.with(layer).init(),
+        LogFormat::Json => registry
+            .with(
+                layer
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(false)
+                    .with_span_list(false),
+            )
+            .init(),
+    }
+    reloadable.then_some(handle)
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("i1-srv: {message}\n{USAGE}");
+    ExitCode::from(2)
+}
@@ -0,0 +1,277 @@
+//! Prometheus metrics endpoint.
+//!
+//! Subsystems implement [`Collector`] and register with the server's
+//! [`Registry`]. Every scrape of `/metrics` asks each collector for its
+//! current values and renders them in the Prometheus text format, so there
+//! is no separate bookkeeping to keep in step with the state it reports.
+//!
+//! The same listener answers the health checks in [`crate::health`].
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::service_fn;
+use hyper::{Method, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::convert::Infallible;
+use std::fmt::Write as _;
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::net::TcpListener;
+use tracing::{debug, warn};
+
+use crate::health::{self, Health};
+
+/// Path the metrics are served on.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Prometheus text exposition format.
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4";
+
+/// Metric type, as declared in the `# TYPE` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Only ever goes up.
+    Counter,
+    /// Goes up and down.
+    Gauge,
+}
+
+/// Metrics being rendered for one scrape.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    /// Start a metric family.
+    pub fn family(&mut self, name: &str, kind: Kind, help: &str) -> &mut Self {
+        let kind = match kind {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        };
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+        self
+    }
+
+    /// Add a sample to the current family.
+    pub fn sample(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: impl std::fmt::Display,
+    ) -> &mut Self {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            self.text.push('{');
+            for (i, (label, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.text.push(',');
+                }
+                let _ = write!(self.text, "{label}=\"{}\"", escape(value));
+            }
+            self.text.push('}');
+        }
+        let _ = writeln!(self.text, " {value}");
+        self
+    }
+
+    /// The rendered text.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+/// Something that reports metrics.
+pub trait Collector: Send + Sync {
+    /// Append current values to `out`.
+    fn collect(&self, out: &mut Exposition);
+}
+
+/// The collectors behind the metrics endpoint.
+///
+/// Clones share the same collectors.
+#[derive(Clone, Default)]
+pub struct Registry {
+    collectors: Arc<RwLock<Vec<Arc<dyn Collector>>>>,
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry").finish_non_exhaustive()
+    }
+}
+
+impl Registry {
+    /// Report `collector`'s metrics on every scrape.
+    pub fn register(&self, collector: Arc<dyn Collector>) {
+        self.collectors
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(collector);
+    }
+
+    /// Render every collector's metrics.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut out = Exposition::default();
+        for collector in self
+            .collectors
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            collector.collect(&mut out);
+        }
+        out.into_string()
+    }
+}
+
+/// Serve `GET /metrics` and the health checks over plain HTTP.
+pub async fn serve(listener: TcpListener, registry: Registry, health: Health) {
+    loop {
+        let (stream, src) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "metrics accept failed");
+                continue;
+            }
+        };
+        let registry = registry.clone();
+        let health = health.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let registry = registry.clone();
+                let health = health.clone();
+                async move { Ok::<_, Infallible>(answer(&request, &registry, &health)) }
+            });
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!(%src, error = %e, "metrics connection closed with error");
+            }
+        });
+    }
+}
+
+fn answer(
+    request: &hyper::Request<Incoming>,
+    registry: &Registry,
+    health: &Health,
+) -> hyper::Response<Full<Bytes>> {
+    let path = request.uri().path();
+    let status = if ![METRICS_PATH, health::LIVE_PATH, health::READY_PATH].contains(&path) {
+        StatusCode::NOT_FOUND
+    } else if request.method() != Method::GET {
+        StatusCode::METHOD_NOT_ALLOWED
+    } else if path == METRICS_PATH {
+        return hyper::Response::builder()
+            .header(CONTENT_TYPE, TEXT_FORMAT)
+            .body(Full::new(Bytes::from(registry.render())))
+            .unwrap_or_default();
+    } else {
+        let report = health.status();
+        let status = if path == health::READY_PATH && !report.ready {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        };
+        return hyper::Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(
+                serde_json::to_vec(&report).unwrap_or_default(),
+            )))
+            .unwrap_or_default();
+    };
+    let mut response = hyper::Response::new(Full::default());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed;
+
+    impl Collector for Fixed {
+        fn collect(&self, out: &mut Exposition) {
+            out.family("i1_test_rounds_total", Kind::Counter, "Rounds run")
+                .sample("i1_test_rounds_total", &[("result", "ok")], 3)
+                .sample("i1_test_rounds_total", &[("result", "a \"b\"\n")], 0);
+            out.family("i1_test_members", Kind::Gauge, "Members")
+                .sample("i1_test_members", &[], 2.5);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let registry = Registry::default();
+        registry.register(Arc::new(Fixed));
+        assert_eq!(
+            registry.render(),
+            "# HELP i1_test_rounds_total Rounds run\n\
+             # TYPE i1_test_rounds_total counter\n\
+             i1_test_rounds_total{result=\"ok\"} 3\n\
+             i1_test_rounds_total{result=\"a \\\"b\\\"\\n\"} 0\n\
+             # HELP i1_test_members Members\n\
+             # TYPE i1_test_members gauge\n\
+             i1_test_members 2.5\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scrape() {
+        let registry = Registry::default();
+        registry.register(Arc::new(Fixed));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, registry, Health::default()));
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{addr}{METRICS_PATH}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert_eq!(response.headers()[CONTENT_TYPE.as_str()], TEXT_FORMAT);
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("i1_test_members 2.5\n"));
+
+        let missing = client.get(format!("http://{addr}/")).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND.as_u16());
+
+        // Nothing loaded: alive, but not ready.
+        let live = client
+            .get(format!("http://{addr}{}", health::LIVE_PATH))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(live.status(), StatusCode::OK.as_u16());
+        let ready = client
+            .get(format!("http://{addr}{}", health::READY_PATH))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+        let status: health::HealthStatus = ready.json().await.unwrap();
+        assert!(!status.zones_loaded);
+    }
+}
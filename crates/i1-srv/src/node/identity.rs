@@ -3,8 +3,496 @@
 //! Each i1-srv node has a certificate signed by an i1-ca intermediate.
 //! The certificate's SHA-256 hash is published as a TLSA record for
 //! DANE-based trust verification.
+//!
+//! The identity lives in `<data_dir>/i1/node/` as `node.crt` (the leaf
+//! followed by the i1-ca intermediate chain) and `node.key` (PKCS#8).
+//!
+//! ## Rotation
+//!
+//! Peers check a node's certificate against its TLSA record, and gossip
+//! members sign their announcements with the node key, so a new key has to
+//! be announced before the node presents it:
+//!
+//! 1. [`rotate`] has i1-ca issue a fresh key and certificate, stages them
+//!    as `node.next.crt`/`node.next.key`, and publishes `3 1 1` TLSA
+//!    records for both the current and the new key.
+//! 2. Once the overlap window has passed, [`advance`] moves the current
+//!    identity to `node.prev.*` and the new one into `node.crt`/`node.key`.
+//!    Restart (or reload) so TLS and gossip pick it up.
+//! 3. After another overlap window, [`advance`] publishes the new record
+//!    alone and deletes `node.prev.*`. Until then, moving the previous
+//!    files back rolls the rotation back.
+//!
+//! The overlap must be at least the TLSA records' TTL
+//! ([`TLSA_TTL`](crate::authority::ttl_policy::TLSA_TTL), a day by
+//! default): a resolver may hold the old record set that long, and a peer
+//! checking the new certificate against it would refuse the node. Progress
+//! is kept in `rotation.json`, so an interrupted step is finished by
+//! running [`advance`] again.
+
+// TODO: Phase 2 - finish NodeIdentity
+// - Generate node certificate via i1-ca when none exists
+
+use chrono::{DateTime, Utc};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::info;
+
+use crate::trust::mesh::{self, TlsaPin, TlsaPublisher, TlsaSelector};
+
+/// Certificate chain file inside the identity directory.
+pub const CERT_FILE: &str = "node.crt";
+
+/// Private key file inside the identity directory.
+pub const KEY_FILE: &str = "node.key";
+
+/// Certificate chain staged by [`rotate`].
+pub const NEXT_CERT_FILE: &str = "node.next.crt";
+
+/// Private key staged by [`rotate`].
+pub const NEXT_KEY_FILE: &str = "node.next.key";
+
+/// Certificate chain replaced by [`advance`], kept until it's retired.
+pub const PREV_CERT_FILE: &str = "node.prev.crt";
+
+/// Private key replaced by [`advance`], kept until it's retired.
+pub const PREV_KEY_FILE: &str = "node.prev.key";
+
+/// Progress of a rotation.
+pub const ROTATION_FILE: &str = "rotation.json";
+
+/// This node's certificate chain and private key.
+pub struct NodeIdentity {
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl std::fmt::Debug for NodeIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeIdentity")
+            .field("chain_len", &self.chain.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl NodeIdentity {
+    /// Default identity directory (`<data_dir>/i1/node`).
+    #[must_use]
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|d| d.join("i1").join("node"))
+    }
+
+    /// Load `node.crt` and `node.key` from an identity directory.
+    pub fn load_dir(dir: &Path) -> crate::Result<Self> {
+        Self::load(&dir.join(CERT_FILE), &dir.join(KEY_FILE))
+    }
+
+    /// Load a PEM certificate chain (leaf first) and its private key.
+    pub fn load(cert_path: &Path, key_path: &Path) -> crate::Result<Self> {
+        let chain = CertificateDer::pem_file_iter(cert_path)
+            .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+            .map_err(|e| identity_err(cert_path, e))?;
+        if chain.is_empty() {
+            return Err(crate::SrvError::Identity(format!(
+                "no certificates in {}",
+                cert_path.display()
+            )));
+        }
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| identity_err(key_path, e))?;
+        Ok(Self { chain, key })
+    }
+
+    /// Certificate chain, leaf first.
+    #[must_use]
+    pub fn chain(&self) -> &[CertificateDer<'static>] {
+        &self.chain
+    }
+
+    /// Private key for the leaf certificate.
+    #[must_use]
+    pub fn key(&self) -> PrivateKeyDer<'static> {
+        self.key.clone_key()
+    }
+
+    /// The leaf's public key as a `3 1 1` TLSA record.
+    pub fn tlsa_pin(&self) -> crate::Result<TlsaPin> {
+        let hash = mesh::spki_hash(&self.chain[0]).ok_or_else(|| {
+            crate::SrvError::Identity("node certificate has no usable public key".into())
+        })?;
+        Ok(TlsaPin {
+            selector: TlsaSelector::Spki,
+            hash,
+        })
+    }
+}
+
+/// Where a rotation stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationPhase {
+    /// Both keys are published; the new one is staged.
+    Published,
+    /// The new key is in use; the old one is still published.
+    Active,
+}
+
+/// A rotation in progress, as kept in [`ROTATION_FILE`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// Node name the records are published for.
+    pub node: String,
+    /// Current phase.
+    pub phase: RotationPhase,
+    /// Overlap window, in seconds.
+    pub overlap: u64,
+    /// When the next step may run.
+    pub next_step: DateTime<Utc>,
+}
+
+impl Rotation {
+    /// The rotation in progress in `dir`, if any.
+    pub fn load(dir: &Path) -> crate::Result<Option<Self>> {
+        let path = dir.join(ROTATION_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| identity_err(&path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(identity_err(&path, e)),
+        }
+    }
+
+    fn save(&self, dir: &Path) -> crate::Result<()> {
+        let body = serde_json::to_string_pretty(self)
+            .map_err(|e| crate::SrvError::Identity(format!("failed to encode rotation: {e}")))?;
+        write_file(&dir.join(ROTATION_FILE), &body, false)
+    }
+}
+
+/// The identity staged by [`rotate`].
+#[derive(Debug)]
+pub struct NewIdentity {
+    /// The new certificate chain and key.
+    pub identity: NodeIdentity,
+    /// TLSA records now published: the current key's, then the new one's.
+    pub published: Vec<TlsaPin>,
+    /// When [`advance`] will switch to the new identity.
+    pub activate_after: DateTime<Utc>,
+}
+
+/// What [`advance`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationStep {
+    /// No rotation is in progress.
+    Idle,
+    /// Nothing is due until then.
+    Waiting(DateTime<Utc>),
+    /// The new identity is in place; reload it. The old one is retired
+    /// after `retire_after`.
+    Activated {
+        /// When the old key's record may be withdrawn.
+        retire_after: DateTime<Utc>,
+    },
+    /// The old key's record is withdrawn and its files are gone.
+    Retired,
+}
+
+/// Start rotating the identity in `dir`.
+///
+/// `issue` is handed the node name and returns a fresh certificate chain
+/// (leaf first) and PKCS#8 key as PEM, e.g. from i1-ca's `sign_node` with
+/// the intermediate's chain appended. Both the current and the new key's
+/// TLSA records are published right away; the new identity is only staged
+/// until [`advance`] runs after `overlap`.
+///
+/// Fails if a rotation is already in progress or the new certificate
+/// reuses the current key.
+pub async fn rotate<F>(
+    dir: &Path,
+    node: &str,
+    issue: F,
+    publisher: &dyn TlsaPublisher,
+    overlap: Duration,
+    now: DateTime<Utc>,
+) -> crate::Result<NewIdentity>
+where
+    F: FnOnce(&str) -> crate::Result<(String, String)>,
+{
+    if let Some(rotation) = Rotation::load(dir)? {
+        return Err(crate::SrvError::Identity(format!(
+            "a rotation of {} is already in progress",
+            rotation.node
+        )));
+    }
+    let current = NodeIdentity::load_dir(dir)?;
+    let (chain_pem, key_pem) = issue(node)?;
+    let (next_cert, next_key) = (dir.join(NEXT_CERT_FILE), dir.join(NEXT_KEY_FILE));
+    write_file(&next_cert, &chain_pem, false)?;
+    write_file(&next_key, &key_pem, true)?;
+    let identity = NodeIdentity::load(&next_cert, &next_key)?;
+
+    let pins = vec![current.tlsa_pin()?, identity.tlsa_pin()?];
+    if pins[0] == pins[1] {
+        return Err(crate::SrvError::Identity(
+            "the new certificate reuses the current key".into(),
+        ));
+    }
+    publisher.publish(node, &pins).await?;
+
+    let rotation = Rotation {
+        node: node.to_string(),
+        phase: RotationPhase::Published,
+        overlap: overlap.as_secs(),
+        next_step: after(now, overlap.as_secs()),
+    };
+    rotation.save(dir)?;
+    info!(node, activate_after = %rotation.next_step, "staged new node identity");
+    Ok(NewIdentity {
+        identity,
+        published: pins,
+        activate_after: rotation.next_step,
+    })
+}
+
+/// Take the rotation in `dir` one step further, if one is due at `now`.
+pub async fn advance(
+    dir: &Path,
+    publisher: &dyn TlsaPublisher,
+    now: DateTime<Utc>,
+) -> crate::Result<RotationStep> {
+    let Some(mut rotation) = Rotation::load(dir)? else {
+        return Ok(RotationStep::Idle);
+    };
+    if now < rotation.next_step {
+        return Ok(RotationStep::Waiting(rotation.next_step));
+    }
+
+    match rotation.phase {
+        RotationPhase::Published => {
+            // Each move is skipped once done, so a rerun finishes the swap.
+            for (from, to) in [(CERT_FILE, PREV_CERT_FILE), (KEY_FILE, PREV_KEY_FILE)] {
+                if !dir.join(to).exists() {
+                    rename(&dir.join(from), &dir.join(to))?;
+                }
+            }
+            for (from, to) in [(NEXT_CERT_FILE, CERT_FILE), (NEXT_KEY_FILE, KEY_FILE)] {
+                if dir.join(from).exists() {
+                    rename(&dir.join(from), &dir.join(to))?;
+                }
+            }
+            NodeIdentity::load_dir(dir)?;
+            rotation.phase = RotationPhase::Active;
+            rotation.next_step = after(now, rotation.overlap);
+            rotation.save(dir)?;
+            info!(node = %rotation.node, retire_after = %rotation.next_step, "switched to new node identity");
+            Ok(RotationStep::Activated {
+                retire_after: rotation.next_step,
+            })
+        }
+        RotationPhase::Active => {
+            let current = NodeIdentity::load_dir(dir)?;
+            publisher
+                .publish(&rotation.node, &[current.tlsa_pin()?])
+                .await?;
+            for file in [PREV_CERT_FILE, PREV_KEY_FILE, ROTATION_FILE] {
+                let path = dir.join(file);
+                match std::fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(identity_err(&path, e)),
+                }
+            }
+            info!(node = %rotation.node, "retired previous node identity");
+            Ok(RotationStep::Retired)
+        }
+    }
+}
+
+/// `secs` after `now`.
+fn after(now: DateTime<Utc>, secs: u64) -> DateTime<Utc> {
+    i64::try_from(secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|overlap| now.checked_add_signed(overlap))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+fn rename(from: &Path, to: &Path) -> crate::Result<()> {
+    std::fs::rename(from, to).map_err(|e| identity_err(from, e))
+}
+
+/// Write `content` by rename, owner-only if it's a key.
+fn write_file(path: &Path, content: &str, secret: bool) -> crate::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content).map_err(|e| identity_err(&tmp, e))?;
+    #[cfg(unix)]
+    if secret {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| identity_err(&tmp, e))?;
+    }
+    #[cfg(not(unix))]
+    let _ = secret;
+    std::fs::rename(&tmp, path).map_err(|e| identity_err(path, e))
+}
+
+fn identity_err(path: &Path, e: impl std::fmt::Display) -> crate::SrvError {
+    crate::SrvError::Identity(format!("failed to load {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Records every set of records published.
+    #[derive(Default)]
+    struct Published(Mutex<Vec<Vec<TlsaPin>>>);
+
+    #[async_trait]
+    impl TlsaPublisher for Published {
+        async fn publish(&self, node: &str, pins: &[TlsaPin]) -> crate::Result<()> {
+            assert_eq!(node, "node1.srv.i1.is");
+            self.0.lock().unwrap().push(pins.to_vec());
+            Ok(())
+        }
+    }
+
+    struct Ca {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl Ca {
+        fn new() -> Self {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            Self {
+                cert: params.self_signed(&key).unwrap(),
+                key,
+            }
+        }
+
+        fn issue(&self, node: &str) -> (String, String) {
+            let key = KeyPair::generate().unwrap();
+            let params = CertificateParams::new(vec![node.to_string()]).unwrap();
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            (
+                format!("{}{}", cert.pem(), self.cert.pem()),
+                key.serialize_pem(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotation_overlaps_then_retires() {
+        let ca = Ca::new();
+        let dir = TempDir::new().unwrap();
+        let (chain, key) = ca.issue("node1.srv.i1.is");
+        std::fs::write(dir.path().join(CERT_FILE), chain).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), key).unwrap();
+        let old = NodeIdentity::load_dir(dir.path()).unwrap();
+        let published = Published::default();
+        let overlap = Duration::from_secs(86_400);
+        let start = Utc::now();
+
+        let new = rotate(
+            dir.path(),
+            "node1.srv.i1.is",
+            |node| Ok(ca.issue(node)),
+            &published,
+            overlap,
+            start,
+        )
+        .await
+        .unwrap();
+        assert_eq!(new.published.len(), 2);
+        assert!(new.published[0].matches(&old.chain()[0]));
+        assert!(new.published[1].matches(&new.identity.chain()[0]));
+        assert_eq!(new.activate_after, start + chrono::Duration::days(1));
+        // Staged only: the node still presents the old certificate.
+        assert_eq!(
+            NodeIdentity::load_dir(dir.path()).unwrap().chain(),
+            old.chain()
+        );
+        let again = rotate(
+            dir.path(),
+            "node1.srv.i1.is",
+            |node| Ok(ca.issue(node)),
+            &published,
+            overlap,
+            start,
+        )
+        .await;
+        assert!(again.is_err());
+
+        let hour = chrono::Duration::hours(1);
+        assert_eq!(
+            advance(dir.path(), &published, start + hour).await.unwrap(),
+            RotationStep::Waiting(new.activate_after)
+        );
+        let switched = new.activate_after + hour;
+        assert_eq!(
+            advance(dir.path(), &published, switched).await.unwrap(),
+            RotationStep::Activated {
+                retire_after: switched + chrono::Duration::days(1)
+            }
+        );
+        let current = NodeIdentity::load_dir(dir.path()).unwrap();
+        assert_eq!(current.chain(), new.identity.chain());
+        assert!(dir.path().join(PREV_KEY_FILE).exists());
+        assert_eq!(published.0.lock().unwrap().len(), 1);
+
+        let retired = switched + chrono::Duration::days(1);
+        assert_eq!(
+            advance(dir.path(), &published, retired).await.unwrap(),
+            RotationStep::Retired
+        );
+        let history = published.0.lock().unwrap().clone();
+        assert_eq!(history, vec![new.published.clone(), vec![new.published[1]]]);
+        assert!(!dir.path().join(PREV_CERT_FILE).exists());
+        assert_eq!(
+            advance(dir.path(), &published, retired).await.unwrap(),
+            RotationStep::Idle
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_switch_resumes() {
+        let ca = Ca::new();
+        let dir = TempDir::new().unwrap();
+        let (chain, key) = ca.issue("node1.srv.i1.is");
+        std::fs::write(dir.path().join(CERT_FILE), chain).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), key).unwrap();
+        let published = Published::default();
+        let start = Utc::now();
+        let new = rotate(
+            dir.path(),
+            "node1.srv.i1.is",
+            |node| Ok(ca.issue(node)),
+            &published,
+            Duration::ZERO,
+            start,
+        )
+        .await
+        .unwrap();
 
-// TODO: Phase 2 - implement NodeIdentity
-// - Load or generate node certificate via i1-ca
-// - Compute SHA-256 hash for TLSA record
-// - Store identity in config directory
+        // Stopped after moving the certificate but not the key.
+        std::fs::rename(dir.path().join(CERT_FILE), dir.path().join(PREV_CERT_FILE)).unwrap();
+        std::fs::rename(dir.path().join(NEXT_CERT_FILE), dir.path().join(CERT_FILE)).unwrap();
+        assert!(matches!(
+            advance(dir.path(), &published, start).await.unwrap(),
+            RotationStep::Activated { .. }
+        ));
+        let current = NodeIdentity::load_dir(dir.path()).unwrap();
+        assert_eq!(current.chain(), new.identity.chain());
+        assert_eq!(current.tlsa_pin().unwrap(), new.published[1]);
+    }
+}
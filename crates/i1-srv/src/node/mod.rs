@@ -1,6 +1,7 @@
 //! Node identity and registration.
 //!
-//! - **Identity**: Node certificate from i1-ca, TLSA hash generation.
+//! - **Identity**: Node certificate from i1-ca, TLSA hash generation and
+//!   key rotation.
 //! - **Registration**: Register with i1-dns via TSIG-authenticated DNS UPDATE.
 
 pub mod identity;
@@ -2,9 +2,483 @@
 //!
 //! Registers this node's A/AAAA and TLSA records with the authoritative
 //! i1-dns servers via TSIG-authenticated DNS UPDATE.
+//!
+//! ## TSIG keys
+//!
+//! [`TsigKeyring`] holds the keys from `[registration]`: the first signs
+//! updates, and every key is accepted on verification until its
+//! `accept_until`. To rotate a secret without downtime:
+//!
+//! 1. On the i1-dns servers, [`TsigKeyring::rotate`] in the new key. The
+//!    old one gets an `accept_until` at the end of the overlap, so updates
+//!    signed with either verify. TSIG finds keys by name, so the new key
+//!    needs a new name (e.g. `node1-2026-10.srv.i1.is`).
+//! 2. Within the overlap, put the new key first on the node. Its updates
+//!    are signed with the new key from then on.
+//! 3. Once the overlap has passed, [`TsigKeyring::prune`] drops the old
+//!    key on both ends.
+//!
+//! Verification failures say which check failed (unknown or retired key,
+//! algorithm, MAC, clock), since a TSIG mismatch is nearly always a
+//! configuration slip on one side.
 
 // TODO: Phase 2 - implement node registration
-// - Send DNS UPDATE to ns1/ns2.i1.is with TSIG authentication
+// - Send DNS UPDATE to ns1/ns2.i1.is, signed with TsigKeyring::sign
 // - Register A/AAAA record under srv.i1.is
 // - Register TLSA record under _tlsa._tcp.node.srv.i1.is
 // - Support DDNS nodes with short TTLs
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hickory_proto::dnssec::rdata::tsig::{signed_bitmessage_to_buf, TsigAlgorithm};
+use hickory_proto::dnssec::rdata::DNSSECRData;
+use hickory_proto::dnssec::tsig::TSigner;
+use hickory_proto::op::Message;
+use hickory_proto::rr::{Name, RData};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::time::Duration;
+
+use crate::config::{RegistrationConfig, TsigKeyConfig};
+use crate::SrvError;
+
+/// A TSIG key shared with the i1-dns servers.
+#[derive(Clone)]
+pub struct TsigKey {
+    signer: TSigner,
+    accept_until: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TsigKey")
+            .field("name", self.name())
+            .field("algorithm", self.algorithm())
+            .field("accept_until", &self.accept_until)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TsigKey {
+    /// A key named `name` with a raw `secret`.
+    pub fn new(
+        name: &str,
+        algorithm: TsigAlgorithm,
+        secret: Vec<u8>,
+        fudge: u16,
+    ) -> crate::Result<Self> {
+        if secret.is_empty() {
+            return Err(SrvError::Tsig(format!(
+                "TSIG key '{name}' has an empty secret"
+            )));
+        }
+        let name = Name::from_ascii(name)
+            .map_err(|e| SrvError::Tsig(format!("bad TSIG key name '{name}': {e}")))?;
+        let signer = TSigner::new(secret, algorithm, name, fudge)
+            .map_err(|e| SrvError::Tsig(e.to_string()))?;
+        Ok(Self {
+            signer,
+            accept_until: None,
+        })
+    }
+
+    /// A key with a fresh random secret as long as the algorithm's MAC.
+    pub fn generate(name: &str, algorithm: TsigAlgorithm, fudge: u16) -> crate::Result<Self> {
+        let len = algorithm
+            .output_len()
+            .map_err(|e| SrvError::Tsig(e.to_string()))?;
+        let mut secret = vec![0; len];
+        SystemRandom::new()
+            .fill(&mut secret)
+            .map_err(|_| SrvError::Tsig("system RNG failed".into()))?;
+        Self::new(name, algorithm, secret, fudge)
+    }
+
+    /// Load a key from its `[[registration.tsig_keys]]` entry.
+    pub fn from_config(config: &TsigKeyConfig, fudge: u16) -> crate::Result<Self> {
+        let name = &config.name;
+        let algorithm = parse_algorithm(&config.algorithm).ok_or_else(|| {
+            SrvError::Tsig(format!(
+                "TSIG key '{name}' uses '{}'; expected hmac-sha256, hmac-sha384 or hmac-sha512",
+                config.algorithm
+            ))
+        })?;
+        let encoded = match (&config.secret, &config.secret_file) {
+            (Some(secret), None) => secret.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path).map_err(|e| {
+                SrvError::Tsig(format!(
+                    "TSIG key '{name}': can't read {}: {e}",
+                    path.display()
+                ))
+            })?,
+            (Some(_), Some(_)) => {
+                return Err(SrvError::Tsig(format!(
+                    "TSIG key '{name}' sets both secret and secret_file"
+                )))
+            }
+            (None, None) => {
+                return Err(SrvError::Tsig(format!(
+                    "TSIG key '{name}' needs a secret or secret_file"
+                )))
+            }
+        };
+        let secret = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| SrvError::Tsig(format!("TSIG key '{name}' secret is not base64: {e}")))?;
+        let mut key = Self::new(name, algorithm, secret, fudge)?;
+        key.accept_until = config.accept_until;
+        Ok(key)
+    }
+
+    /// The key's entry for the config file, with its secret inline.
+    #[must_use]
+    pub fn to_config(&self) -> TsigKeyConfig {
+        TsigKeyConfig {
+            name: self.name().to_string().trim_end_matches('.').to_string(),
+            algorithm: self.algorithm().to_name().to_string(),
+            secret: Some(self.secret_base64()),
+            secret_file: None,
+            accept_until: self.accept_until,
+        }
+    }
+
+    /// Key name, fully qualified.
+    #[must_use]
+    pub fn name(&self) -> &Name {
+        self.signer.signer_name()
+    }
+
+    /// MAC algorithm.
+    #[must_use]
+    pub fn algorithm(&self) -> &TsigAlgorithm {
+        self.signer.algorithm()
+    }
+
+    /// The secret in base64, as BIND and knot configs write it.
+    #[must_use]
+    pub fn secret_base64(&self) -> String {
+        base64::engine::general_purpose::STANDARD.encode(self.signer.key())
+    }
+
+    /// When the key stops being accepted, if ever.
+    #[must_use]
+    pub const fn accept_until(&self) -> Option<DateTime<Utc>> {
+        self.accept_until
+    }
+
+    fn accepted_at(&self, now: DateTime<Utc>) -> bool {
+        self.accept_until.map_or(true, |until| now <= until)
+    }
+}
+
+/// The node's TSIG keys, newest first.
+#[derive(Debug, Clone)]
+pub struct TsigKeyring {
+    keys: Vec<TsigKey>,
+}
+
+impl TsigKeyring {
+    /// A keyring signing with `current`.
+    #[must_use]
+    pub fn new(current: TsigKey) -> Self {
+        Self {
+            keys: vec![current],
+        }
+    }
+
+    /// Load every key in `[registration]`.
+    pub fn from_config(config: &RegistrationConfig) -> crate::Result<Self> {
+        let keys = config
+            .tsig_keys
+            .iter()
+            .map(|key| TsigKey::from_config(key, config.tsig_fudge_secs))
+            .collect::<crate::Result<Vec<_>>>()?;
+        if keys.is_empty() {
+            return Err(SrvError::Tsig(
+                "no TSIG keys configured in [registration]".into(),
+            ));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|other| other.name() == key.name()) {
+                return Err(SrvError::Tsig(format!(
+                    "TSIG key '{}' is configured twice",
+                    key.name()
+                )));
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    /// The key updates are signed with.
+    #[must_use]
+    pub fn current(&self) -> &TsigKey {
+        &self.keys[0]
+    }
+
+    /// Every key, newest first.
+    #[must_use]
+    pub fn keys(&self) -> &[TsigKey] {
+        &self.keys
+    }
+
+    /// Sign `message` (a DNS UPDATE) with the current key at `now`.
+    pub fn sign(&self, message: &mut Message, now: DateTime<Utc>) -> crate::Result<()> {
+        let key = self.current();
+        if !key.accepted_at(now) {
+            return Err(SrvError::Tsig(format!(
+                "current TSIG key '{}' was retired at {}; rotate in a new key",
+                key.name(),
+                key.accept_until.unwrap_or(now)
+            )));
+        }
+        let time = u32::try_from(now.timestamp())
+            .map_err(|_| SrvError::Tsig(format!("can't sign at {now}")))?;
+        message
+            .finalize(&key.signer, time)
+            .map_err(|e| SrvError::Tsig(format!("signing with '{}': {e}", key.name())))?;
+        Ok(())
+    }
+
+    /// Check the TSIG on the wire-format `message` at `now`, returning the
+    /// key that signed it.
+    pub fn verify(&self, message: &[u8], now: DateTime<Utc>) -> crate::Result<&TsigKey> {
+        let (signed, record) = signed_bitmessage_to_buf(None, message, true)
+            .map_err(|e| SrvError::Tsig(format!("message is not TSIG-signed: {e}")))?;
+        let RData::DNSSEC(DNSSECRData::TSIG(tsig)) = record.data() else {
+            return Err(SrvError::Tsig("message is not TSIG-signed".into()));
+        };
+
+        let name = record.name();
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.name() == name)
+            .ok_or_else(|| {
+                let known: Vec<_> = self.keys.iter().map(|key| key.name().to_string()).collect();
+                SrvError::Tsig(format!(
+                    "unknown TSIG key '{name}' (configured: {})",
+                    known.join(", ")
+                ))
+            })?;
+        if !key.accepted_at(now) {
+            return Err(SrvError::Tsig(format!(
+                "TSIG key '{name}' was retired at {}; the signer should use '{}'",
+                key.accept_until.unwrap_or(now),
+                self.current().name()
+            )));
+        }
+        if tsig.algorithm() != key.algorithm() {
+            return Err(SrvError::Tsig(format!(
+                "TSIG key '{name}' is {} here but the message was signed with {}",
+                key.algorithm().to_name(),
+                tsig.algorithm().to_name()
+            )));
+        }
+        key.signer.verify(&signed, tsig.mac()).map_err(|_| {
+            SrvError::Tsig(format!(
+                "bad MAC from TSIG key '{name}': the secret differs between signer and verifier"
+            ))
+        })?;
+
+        let skew = now
+            .timestamp()
+            .abs_diff(i64::try_from(tsig.time()).unwrap_or(i64::MAX));
+        if skew > u64::from(tsig.fudge()) {
+            return Err(SrvError::Tsig(format!(
+                "TSIG from '{name}' was signed {skew}s away from this server's clock \
+                 (fudge {}s); check NTP on both ends",
+                tsig.fudge()
+            )));
+        }
+        Ok(key)
+    }
+
+    /// Make `key` the signing key. The keys it replaces stay accepted for
+    /// `overlap` from `now` (or until their own `accept_until`, if sooner).
+    pub fn rotate(
+        &mut self,
+        key: TsigKey,
+        overlap: Duration,
+        now: DateTime<Utc>,
+    ) -> crate::Result<()> {
+        if self.keys.iter().any(|old| old.name() == key.name()) {
+            return Err(SrvError::Tsig(format!(
+                "TSIG key '{}' is already in the keyring; a rotated key needs a new name",
+                key.name()
+            )));
+        }
+        let until = now + chrono::Duration::from_std(overlap).unwrap_or(chrono::Duration::MAX);
+        for old in &mut self.keys {
+            old.accept_until = Some(old.accept_until.map_or(until, |own| own.min(until)));
+        }
+        self.keys.insert(0, key);
+        Ok(())
+    }
+
+    /// Drop keys retired before `now`, keeping the current one. Returns how
+    /// many were dropped.
+    pub fn prune(&mut self, now: DateTime<Utc>) -> usize {
+        let before = self.keys.len();
+        let mut index = 0;
+        self.keys.retain(|key| {
+            index += 1;
+            index == 1 || key.accepted_at(now)
+        });
+        before - self.keys.len()
+    }
+}
+
+/// Algorithms ring can compute a full-length MAC for.
+fn parse_algorithm(name: &str) -> Option<TsigAlgorithm> {
+    match name.trim_end_matches('.').to_ascii_lowercase().as_str() {
+        "hmac-sha256" => Some(TsigAlgorithm::HmacSha256),
+        "hmac-sha384" => Some(TsigAlgorithm::HmacSha384),
+        "hmac-sha512" => Some(TsigAlgorithm::HmacSha512),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::op::{MessageType, OpCode, Query};
+    use hickory_proto::rr::RecordType;
+
+    fn update() -> Message {
+        let mut message = Message::new();
+        message
+            .set_id(4321)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Update)
+            .add_query(Query::query(
+                Name::from_ascii("srv.i1.is.").unwrap(),
+                RecordType::SOA,
+            ));
+        message
+    }
+
+    fn signed(keyring: &TsigKeyring, now: DateTime<Utc>) -> Vec<u8> {
+        let mut message = update();
+        keyring.sign(&mut message, now).unwrap();
+        message.to_vec().unwrap()
+    }
+
+    fn key(name: &str, secret: &[u8]) -> TsigKey {
+        TsigKey::new(name, TsigAlgorithm::HmacSha256, secret.to_vec(), 300).unwrap()
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify_errors() {
+        let now = at(1_790_000_000);
+        let node = TsigKeyring::new(key("node1.srv.i1.is", b"first secret"));
+        let wire = signed(&node, now);
+
+        let server = TsigKeyring::new(key("node1.srv.i1.is", b"first secret"));
+        let by = server
+            .verify(&wire, now + chrono::Duration::seconds(60))
+            .unwrap();
+        assert_eq!(by.name().to_string(), "node1.srv.i1.is.");
+
+        let err = |keyring: &TsigKeyring, wire: &[u8], now| {
+            keyring.verify(wire, now).unwrap_err().to_string()
+        };
+        let wrong = TsigKeyring::new(key("node1.srv.i1.is", b"other secret"));
+        assert!(err(&wrong, &wire, now).contains("bad MAC"));
+        let unknown = TsigKeyring::new(key("node2.srv.i1.is", b"first secret"));
+        assert!(err(&unknown, &wire, now).contains("unknown TSIG key 'node1.srv.i1.is.'"));
+        let sha512 = TsigKeyring::new(
+            TsigKey::new(
+                "node1.srv.i1.is",
+                TsigAlgorithm::HmacSha512,
+                b"first secret".to_vec(),
+                300,
+            )
+            .unwrap(),
+        );
+        assert!(err(&sha512, &wire, now).contains("hmac-sha512 here"));
+        assert!(err(&server, &wire, now + chrono::Duration::seconds(301)).contains("check NTP"));
+        assert!(err(&server, &update().to_vec().unwrap(), now).contains("not TSIG-signed"));
+
+        // A tampered message (query type SOA -> A) fails the MAC.
+        let mut tampered = wire.clone();
+        tampered[24] = 1;
+        assert!(err(&server, &tampered, now).contains("bad MAC"));
+    }
+
+    #[test]
+    fn test_rotation_overlap() {
+        let now = at(1_790_000_000);
+        let old = key("node1.srv.i1.is", b"old secret");
+        let new = TsigKey::generate("node1-2.srv.i1.is", TsigAlgorithm::HmacSha256, 300).unwrap();
+        assert_eq!(new.secret_base64().len(), 44);
+
+        let mut server = TsigKeyring::new(old.clone());
+        let overlap = Duration::from_secs(3600);
+        server.rotate(new.clone(), overlap, now).unwrap();
+        assert!(server.rotate(new.clone(), overlap, now).is_err());
+        assert_eq!(server.current().name(), new.name());
+
+        // Both keys verify during the overlap.
+        let old_node = TsigKeyring::new(old);
+        let new_node = TsigKeyring::new(new);
+        let later = now + chrono::Duration::seconds(1800);
+        assert!(server.verify(&signed(&old_node, later), later).is_ok());
+        assert!(server.verify(&signed(&new_node, later), later).is_ok());
+
+        // Afterwards only the new one does.
+        let after = now + chrono::Duration::seconds(3601);
+        let err = server.verify(&signed(&old_node, after), after).unwrap_err();
+        assert!(err.to_string().contains("retired"), "{err}");
+        assert!(server.verify(&signed(&new_node, after), after).is_ok());
+
+        assert_eq!(server.prune(later), 0);
+        assert_eq!(server.prune(after), 1);
+        assert_eq!(server.keys().len(), 1);
+    }
+
+    #[test]
+    fn test_keyring_from_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret_file = dir.path().join("node1.key");
+        std::fs::write(&secret_file, "c2VjcmV0IG9uIGRpc2s=\n").unwrap();
+        let config: RegistrationConfig = toml::from_str(&format!(
+            r#"
+            tsig_fudge_secs = 120
+
+            [[tsig_keys]]
+            name = "node1-2.srv.i1.is"
+            algorithm = "hmac-sha512"
+            secret_file = "{}"
+
+            [[tsig_keys]]
+            name = "node1.srv.i1.is"
+            secret = "b2xkIHNlY3JldA=="
+            accept_until = "2026-11-01T00:00:00Z"
+            "#,
+            secret_file.display()
+        ))
+        .unwrap();
+        let keyring = TsigKeyring::from_config(&config).unwrap();
+        assert_eq!(keyring.current().secret_base64(), "c2VjcmV0IG9uIGRpc2s=");
+        assert_eq!(*keyring.current().algorithm(), TsigAlgorithm::HmacSha512);
+        assert!(keyring.keys()[1].accept_until().is_some());
+        let round_trip = keyring.keys()[1].to_config();
+        assert_eq!(round_trip.name, "node1.srv.i1.is");
+        assert_eq!(round_trip.algorithm, "hmac-sha256");
+
+        let broken = |toml: &str| {
+            let config: RegistrationConfig = toml::from_str(toml).unwrap();
+            TsigKeyring::from_config(&config).unwrap_err().to_string()
+        };
+        assert!(broken("").contains("no TSIG keys"));
+        assert!(broken("[[tsig_keys]]\nname = \"a\"").contains("needs a secret"));
+        assert!(broken("[[tsig_keys]]\nname = \"a\"\nsecret = \"!!\"").contains("not base64"));
+        assert!(
+            broken("[[tsig_keys]]\nname = \"a\"\nsecret = \"YQ==\"\nalgorithm = \"hmac-md5\"")
+                .contains("expected hmac-sha256")
+        );
+    }
+}
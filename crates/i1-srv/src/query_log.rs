@@ -0,0 +1,662 @@
+//! Sampled query log.
+//!
+//! Answered queries are written as NDJSON, one [`QueryEntry`] per line,
+//! for abuse investigation and to see which names are popular. Logging
+//! every query at DNS rates would fill the disk, so each zone logs only a
+//! fraction of its queries (see [`QueryLogConfig`]); answers other than
+//! NOERROR and NXDOMAIN are always logged. Client addresses are truncated
+//! to their network before anything is written.
+//!
+//! Lines reach the file through a bounded queue and a writer thread, so a
+//! slow disk costs dropped lines rather than slower answers. Once the file
+//! reaches `max_bytes` it is rotated to `<path>.1`, shifting older files
+//! up and keeping `keep` of them. [`QueryLog::flush`] waits for the
+//! writer to catch up, e.g. before shutting down.
+//!
+//! [`top_names`] reads a log back and ranks the names queried most, for
+//! feeding popularity back into reputation.
+
+use chrono::{DateTime, Utc};
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::{LowerName, RecordType};
+use hickory_server::server::{Request, ResponseInfo};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config::QueryLogConfig;
+use crate::metrics::{Collector, Exposition, Kind};
+
+/// Lines waiting for the writer before new ones are dropped.
+const QUEUE_LEN: usize = 8192;
+
+/// Work for the writer thread.
+enum Queued {
+    /// A line to append.
+    Line(String),
+    /// Signal once everything queued before this is on disk.
+    Flush(mpsc::Sender<()>),
+}
+
+/// One logged query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryEntry {
+    /// When the answer was sent.
+    pub ts: DateTime<Utc>,
+    /// Client network, e.g. `203.0.113.0/24`.
+    pub client: String,
+    /// Queried name, fully qualified.
+    pub qname: String,
+    /// Query type (`A`, `TXT`, ...).
+    pub qtype: String,
+    /// Response code (`NOERROR`, `NXDOMAIN`, ...).
+    pub rcode: String,
+    /// Time taken to answer, in microseconds.
+    pub latency_us: u64,
+    /// Served zone the name falls in, if any.
+    pub zone: Option<String>,
+    /// Fraction of such queries being logged; 1.0 for always-logged answers.
+    pub sample: f64,
+}
+
+/// How often a name was queried, according to a query log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NameCount {
+    /// Queried name.
+    pub qname: String,
+    /// Lines logged for it.
+    pub logged: u64,
+    /// Queries those lines stand for, scaling each by its sample rate.
+    pub estimated: f64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    sampled: AtomicU64,
+    skipped: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// A served zone and its sample rate.
+#[derive(Debug)]
+struct LoggedZone {
+    origin: LowerName,
+    rate: f64,
+}
+
+/// Sample rates per served zone, and for queries outside them.
+#[derive(Debug)]
+struct SampleRates {
+    zones: Vec<LoggedZone>,
+    default_rate: f64,
+}
+
+impl SampleRates {
+    fn new(
+        config: &QueryLogConfig,
+        origins: impl IntoIterator<Item = LowerName>,
+    ) -> crate::Result<Self> {
+        let mut rates = std::iter::once(config.sample_rate).chain(config.zones.values().copied());
+        if rates.any(|rate| !(0.0..=1.0).contains(&rate)) {
+            return Err(crate::SrvError::Config(
+                "query_log sample rates must be between 0 and 1".into(),
+            ));
+        }
+        Ok(Self {
+            zones: origins
+                .into_iter()
+                .map(|origin| LoggedZone {
+                    rate: config.zone_rate(&origin.to_string()),
+                    origin,
+                })
+                .collect(),
+            default_rate: config.sample_rate,
+        })
+    }
+}
+
+/// Handle to a running query log. Clones share the same file, counters
+/// and sample rates.
+#[derive(Clone)]
+pub struct QueryLog {
+    rates: Arc<RwLock<SampleRates>>,
+    ipv4_prefix: u8,
+    ipv6_prefix: u8,
+    queue: SyncSender<Queued>,
+    rng: Arc<SystemRandom>,
+    counters: Arc<Counters>,
+}
+
+impl std::fmt::Debug for QueryLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryLog")
+            .field("rates", &self.rates)
+            .finish_non_exhaustive()
+    }
+}
+
+impl QueryLog {
+    /// Open the log file and start the writer thread, sampling queries to
+    /// each zone in `origins` at its configured rate.
+    pub fn open(config: &QueryLogConfig, origins: &[LowerName]) -> crate::Result<Self> {
+        let rates = SampleRates::new(config, origins.iter().cloned())?;
+        let path = config.path().ok_or_else(|| {
+            crate::SrvError::Config("no query_log.path and no data directory".into())
+        })?;
+        let file = RotatingFile::open(&path, config.max_bytes, config.keep)
+            .map_err(|e| crate::SrvError::Config(format!("query log {}: {e}", path.display())))?;
+
+        let (queue, lines) = mpsc::sync_channel(QUEUE_LEN);
+        let counters = Arc::new(Counters::default());
+        let writer_counters = counters.clone();
+        std::thread::Builder::new()
+            .name("i1-query-log".into())
+            .spawn(move || write_lines(file, &lines, &writer_counters))?;
+
+        Ok(Self {
+            rates: Arc::new(RwLock::new(rates)),
+            ipv4_prefix: config.ipv4_prefix,
+            ipv6_prefix: config.ipv6_prefix,
+            queue,
+            rng: Arc::new(SystemRandom::new()),
+            counters,
+        })
+    }
+
+    /// Switch to the sample rates in `config`, for the same zones; the
+    /// file, rotation and client truncation stay as opened.
+    pub fn set_rates(&self, config: &QueryLogConfig) -> crate::Result<()> {
+        let origins: Vec<_> = self
+            .rates
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .zones
+            .iter()
+            .map(|zone| zone.origin.clone())
+            .collect();
+        let rates = SampleRates::new(config, origins)?;
+        *self.rates.write().unwrap_or_else(PoisonError::into_inner) = rates;
+        Ok(())
+    }
+
+    /// Log an answered request, if it is sampled.
+    pub fn record(&self, request: &Request, response: &ResponseInfo, latency: Duration) {
+        let Some(query) = request.queries().first() else {
+            return;
+        };
+        let Some(entry) = self.entry(
+            request.src().ip(),
+            query.name(),
+            query.query_type(),
+            response.response_code(),
+            latency,
+        ) else {
+            return;
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        // A full queue means the disk can't keep up; the line is dropped.
+        if self.queue.try_send(Queued::Line(line)).is_err() {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Write out every line queued so far, waiting up to `timeout` for
+    /// the writer; returns whether it caught up in time.
+    ///
+    /// Blocks, so async callers should run it with `spawn_blocking`.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let (done, flushed) = mpsc::channel();
+        // Unlike a line, the request waits for room in the queue.
+        self.queue.send(Queued::Flush(done)).is_ok() && flushed.recv_timeout(timeout).is_ok()
+    }
+
+    /// The line to log for a query, or `None` when it isn't sampled.
+    fn entry(
+        &self,
+        client: IpAddr,
+        name: &LowerName,
+        qtype: RecordType,
+        rcode: ResponseCode,
+        latency: Duration,
+    ) -> Option<QueryEntry> {
+        let rates = self.rates.read().unwrap_or_else(PoisonError::into_inner);
+        let zone = rates
+            .zones
+            .iter()
+            .filter(|zone| zone.origin.zone_of(name))
+            .max_by_key(|zone| zone.origin.num_labels());
+        let always = !matches!(rcode, ResponseCode::NoError | ResponseCode::NXDomain);
+        let rate = if always {
+            1.0
+        } else {
+            zone.map_or(rates.default_rate, |zone| zone.rate)
+        };
+        if !self.sampled(rate) {
+            self.counters.skipped.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        self.counters.sampled.fetch_add(1, Ordering::Relaxed);
+        Some(QueryEntry {
+            ts: Utc::now(),
+            client: client_network(client, self.ipv4_prefix, self.ipv6_prefix),
+            qname: name.to_string(),
+            qtype: qtype.to_string(),
+            rcode: rcode_name(rcode),
+            latency_us: u64::try_from(latency.as_micros()).unwrap_or(u64::MAX),
+            zone: zone.map(|zone| zone.origin.to_string()),
+            sample: rate,
+        })
+    }
+
+    fn sampled(&self, rate: f64) -> bool {
+        if rate >= 1.0 {
+            return true;
+        }
+        let mut bytes = [0u8; 4];
+        rate > 0.0
+            && self.rng.fill(&mut bytes).is_ok()
+            && f64::from(u32::from_le_bytes(bytes)) < rate * f64::from(u32::MAX)
+    }
+}
+
+impl Collector for QueryLog {
+    fn collect(&self, out: &mut Exposition) {
+        let name = "i1_query_log_queries_total";
+        out.family(name, Kind::Counter, "Answered queries by query log outcome");
+        for (result, counter) in [
+            ("sampled", &self.counters.sampled),
+            ("skipped", &self.counters.skipped),
+            ("dropped", &self.counters.dropped),
+        ] {
+            out.sample(name, &[("result", result)], counter.load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// Writer thread: append queued lines, flushing whenever the queue drains.
+fn write_lines(mut file: RotatingFile, queue: &Receiver<Queued>, counters: &Counters) {
+    while let Ok(first) = queue.recv() {
+        let mut failed = 0u64;
+        let mut waiting = Vec::new();
+        for queued in std::iter::once(first).chain(queue.try_iter()) {
+            let line = match queued {
+                Queued::Line(line) => line,
+                Queued::Flush(done) => {
+                    waiting.push(done);
+                    continue;
+                }
+            };
+            if let Err(e) = file.write_line(&line) {
+                if failed == 0 {
+                    warn!(path = %file.path.display(), error = %e, "query log write failed");
+                }
+                failed += 1;
+            }
+        }
+        if let Err(e) = file.flush() {
+            warn!(path = %file.path.display(), error = %e, "query log flush failed");
+        }
+        counters.dropped.fetch_add(failed, Ordering::Relaxed);
+        for done in waiting {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// An append-only file that rotates once it reaches a size cap.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: BufWriter<File>,
+    len: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_bytes: u64, keep: usize) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            keep,
+            file: BufWriter::new(file),
+            len,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let needed = line.len() as u64 + 1;
+        if self.len > 0 && self.len + needed > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.len += needed;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and start afresh.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        for n in (1..self.keep).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                std::fs::rename(from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        if self.keep > 0 {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.len = 0;
+        Ok(())
+    }
+}
+
+/// The `n`th rotated copy of `path`.
+#[must_use]
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// The network `ip` falls in, truncated to `ipv4_prefix` or `ipv6_prefix` bits.
+#[must_use]
+pub fn client_network(ip: IpAddr, ipv4_prefix: u8, ipv6_prefix: u8) -> String {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let bits = ipv4_prefix.min(32);
+            let mask = u32::MAX.checked_shl(32 - u32::from(bits)).unwrap_or(0);
+            format!("{}/{bits}", Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let bits = ipv6_prefix.min(128);
+            let mask = u128::MAX.checked_shl(128 - u32::from(bits)).unwrap_or(0);
+            format!("{}/{bits}", Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    }
+}
+
+/// Mnemonic for a response code, as written in zone files and by `dig`.
+fn rcode_name(rcode: ResponseCode) -> String {
+    match rcode {
+        ResponseCode::NoError => "NOERROR".into(),
+        ResponseCode::FormErr => "FORMERR".into(),
+        ResponseCode::ServFail => "SERVFAIL".into(),
+        ResponseCode::NXDomain => "NXDOMAIN".into(),
+        ResponseCode::NotImp => "NOTIMP".into(),
+        ResponseCode::Refused => "REFUSED".into(),
+        ResponseCode::NotAuth => "NOTAUTH".into(),
+        ResponseCode::NotZone => "NOTZONE".into(),
+        ResponseCode::BADVERS => "BADVERS".into(),
+        other => format!("RCODE{}", u16::from(other)),
+    }
+}
+
+/// Rank the names in a query log by estimated query count, most queried
+/// first, keeping at most `limit`. Only names in `zone` are counted when it
+/// is given; lines that don't parse are skipped.
+pub fn top_names(
+    reader: impl BufRead,
+    zone: Option<&str>,
+    limit: usize,
+) -> std::io::Result<Vec<NameCount>> {
+    let zone = zone.map(|zone| zone.trim_end_matches('.'));
+    let mut counts: HashMap<String, NameCount> = HashMap::new();
+    for line in reader.lines() {
+        let Ok(entry) = serde_json::from_str::<QueryEntry>(&line?) else {
+            continue;
+        };
+        let in_zone = zone.map_or(true, |zone| {
+            entry
+                .zone
+                .as_deref()
+                .is_some_and(|z| z.trim_end_matches('.').eq_ignore_ascii_case(zone))
+        });
+        if !in_zone {
+            continue;
+        }
+        let weight = if entry.sample > 0.0 {
+            entry.sample.recip()
+        } else {
+            1.0
+        };
+        let count = counts
+            .entry(entry.qname.clone())
+            .or_insert_with(|| NameCount {
+                qname: entry.qname,
+                logged: 0,
+                estimated: 0.0,
+            });
+        count.logged += 1;
+        count.estimated += weight;
+    }
+    let mut ranked: Vec<NameCount> = counts.into_values().collect();
+    ranked.sort_by(|a, b| {
+        b.estimated
+            .total_cmp(&a.estimated)
+            .then_with(|| a.qname.cmp(&b.qname))
+    });
+    ranked.truncate(limit);
+    Ok(ranked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::rr::Name;
+    use std::str::FromStr;
+
+    fn name(s: &str) -> LowerName {
+        LowerName::from(Name::from_str(s).unwrap())
+    }
+
+    fn open(dir: &Path, zones: &[(&str, f64)]) -> QueryLog {
+        let config = QueryLogConfig {
+            enabled: true,
+            path: Some(dir.join("queries.ndjson")),
+            sample_rate: 0.0,
+            zones: zones
+                .iter()
+                .map(|(zone, rate)| ((*zone).to_string(), *rate))
+                .collect(),
+            ..QueryLogConfig::default()
+        };
+        QueryLog::open(&config, &[name("bl.i1.is."), name("sig.i1.is.")]).unwrap()
+    }
+
+    #[test]
+    fn test_client_network() {
+        let v4 = "203.0.113.77".parse().unwrap();
+        assert_eq!(client_network(v4, 24, 48), "203.0.113.0/24");
+        assert_eq!(client_network(v4, 0, 48), "0.0.0.0/0");
+        let mapped = "::ffff:203.0.113.77".parse().unwrap();
+        assert_eq!(client_network(mapped, 24, 48), "203.0.113.0/24");
+        let v6 = "2001:db8:1234:5678::1".parse().unwrap();
+        assert_eq!(client_network(v6, 24, 48), "2001:db8:1234::/48");
+    }
+
+    #[test]
+    fn test_sampling_by_zone_and_rcode() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), &[("sig", 1.0)]);
+        let client = "198.51.100.9".parse().unwrap();
+        let latency = Duration::from_micros(250);
+
+        let entry = log
+            .entry(
+                client,
+                &name("v.sig.i1.is."),
+                RecordType::TXT,
+                ResponseCode::NoError,
+                latency,
+            )
+            .unwrap();
+        assert_eq!(entry.zone.as_deref(), Some("sig.i1.is."));
+        assert_eq!(entry.client, "198.51.100.0/24");
+        assert_eq!(entry.qtype, "TXT");
+        assert_eq!(entry.rcode, "NOERROR");
+        assert_eq!(entry.latency_us, 250);
+
+        // The blocklist falls back to the default rate of 0...
+        let bl = name("4.3.2.1.bl.i1.is.");
+        assert!(log
+            .entry(client, &bl, RecordType::A, ResponseCode::NXDomain, latency)
+            .is_none());
+        // ...but failures are always logged.
+        let failed = log
+            .entry(client, &bl, RecordType::A, ResponseCode::ServFail, latency)
+            .unwrap();
+        assert_eq!(failed.rcode, "SERVFAIL");
+        assert!((failed.sample - 1.0).abs() < f64::EPSILON);
+
+        assert_eq!(log.counters.sampled.load(Ordering::Relaxed), 2);
+        assert_eq!(log.counters.skipped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_rejects_bad_rates() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = QueryLogConfig {
+            path: Some(dir.path().join("queries.ndjson")),
+            zones: std::collections::BTreeMap::from([("bl".to_string(), 1.5)]),
+            ..QueryLogConfig::default()
+        };
+        assert!(QueryLog::open(&config, &[]).is_err());
+    }
+
+    #[test]
+    fn test_set_rates() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), &[("sig", 1.0)]);
+        let client = "198.51.100.9".parse().unwrap();
+        let sampled = |log: &QueryLog, qname: &str| {
+            log.entry(
+                client,
+                &name(qname),
+                RecordType::A,
+                ResponseCode::NoError,
+                Duration::ZERO,
+            )
+            .is_some()
+        };
+        assert!(!sampled(&log, "4.3.2.1.bl.i1.is."));
+
+        let config = QueryLogConfig {
+            zones: std::collections::BTreeMap::from([("bl".to_string(), 1.0)]),
+            ..QueryLogConfig::default()
+        };
+        log.set_rates(&config).unwrap();
+        assert!(sampled(&log, "4.3.2.1.bl.i1.is."));
+        assert!(!sampled(&log, "v.sig.i1.is."));
+
+        let bad = QueryLogConfig {
+            sample_rate: 2.0,
+            ..QueryLogConfig::default()
+        };
+        assert!(log.set_rates(&bad).is_err());
+        assert!(sampled(&log, "4.3.2.1.bl.i1.is."));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("queries.ndjson");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["aaaa", "bbbb", "cccc", "dddd", "eeee"] {
+            file.write_line(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "eeee\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "cccc\ndddd\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "aaaa\nbbbb\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        // Reopening continues the live file rather than truncating it.
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_line("ffff").unwrap();
+        file.flush().unwrap();
+        assert_eq!(read(&path), "eeee\nffff\n");
+    }
+
+    #[test]
+    fn test_writer_appends_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open(dir.path(), &[("bl", 1.0)]);
+        let client = "198.51.100.9".parse().unwrap();
+        for _ in 0..3 {
+            let entry = log
+                .entry(
+                    client,
+                    &name("4.3.2.1.bl.i1.is."),
+                    RecordType::A,
+                    ResponseCode::NoError,
+                    Duration::ZERO,
+                )
+                .unwrap();
+            log.queue
+                .send(Queued::Line(serde_json::to_string(&entry).unwrap()))
+                .unwrap();
+        }
+
+        assert!(log.flush(Duration::from_secs(5)));
+        let path = dir.path().join("queries.ndjson");
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn test_top_names() {
+        let entry = |qname: &str, zone: &str, sample: f64| {
+            serde_json::to_string(&QueryEntry {
+                ts: Utc::now(),
+                client: "198.51.100.0/24".into(),
+                qname: qname.into(),
+                qtype: "A".into(),
+                rcode: "NOERROR".into(),
+                latency_us: 10,
+                zone: Some(zone.into()),
+                sample,
+            })
+            .unwrap()
+        };
+        let log = [
+            entry("4.3.2.1.bl.i1.is.", "bl.i1.is.", 0.01),
+            entry("8.7.6.5.bl.i1.is.", "bl.i1.is.", 1.0),
+            entry("8.7.6.5.bl.i1.is.", "bl.i1.is.", 1.0),
+            "not json".to_string(),
+            entry("v.sig.i1.is.", "sig.i1.is.", 1.0),
+        ]
+        .join("\n");
+
+        let top = top_names(log.as_bytes(), Some("bl.i1.is"), 10).unwrap();
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].qname, "4.3.2.1.bl.i1.is.");
+        assert_eq!(top[0].logged, 1);
+        assert!((top[0].estimated - 100.0).abs() < 1e-9);
+        assert_eq!(top[1].logged, 2);
+
+        assert_eq!(top_names(log.as_bytes(), None, 1).unwrap().len(), 1);
+    }
+}
@@ -1,69 +1,207 @@
-//! DNS server runner: binds UDP+TCP and serves threat intelligence zones.
+//! DNS server runner: binds UDP+TCP (plus optional DoT/DoH) and serves
+//! threat intelligence zones.
+//!
+//! SIGTERM or SIGINT shuts the server down gracefully: readiness fails
+//! (see [`crate::health`]), new queries are refused, the ones being
+//! answered get up to `drain_timeout_secs` to finish, and then the
+//! listeners close and the query log and zone store are flushed.
 
-use hickory_server::authority::{Authority, AuthorityObject, Catalog};
+use hickory_server::authority::{AuthorityObject, Catalog};
 use hickory_server::server::ServerFuture;
+use hickory_server::store::in_memory::InMemoryAuthority;
+use std::future::Future;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::{TcpListener, UdpSocket};
-use tracing::info;
+use tracing::{debug, info, warn};
 
-use crate::authority::zone_builder::{self, BuiltZones, DefenseSnapshot};
-use crate::config::ServerConfig;
-use crate::sync::collector;
+use crate::admin::{self, AdminApi, AdminAuth, AdminToken, ClientAuth};
+use crate::authority::blocklist_authority::BlocklistAuthority;
+use crate::authority::chaos::ChaosResponder;
+use crate::authority::dnssec::ZoneSigningKeys;
+use crate::authority::persist::{SavedZones, ZoneDb};
+use crate::authority::transfer::{
+    ServedZone, TransferAcl, TransferHandler, TransferZone, ZoneStore,
+};
+use crate::authority::zone_builder::{
+    self, AuditData, BuildOptions, BuiltZones, DefenseSnapshot,
+};
+use crate::config::{ServerConfig, StoreConfig, TlsConfig};
+use crate::config_reload::{ConfigReloader, LogFilter};
+use crate::health::Health;
+use crate::metrics::{self, Registry};
+use crate::node::identity::NodeIdentity;
+use crate::query_log::QueryLog;
+use crate::sync::reload::{RebuildHandle, RebuildOptions, SnapshotSource, ZoneRebuilder};
+use crate::sync::gossip::transport::GossipTls;
+use crate::sync::gossip::GossipNode;
+use crate::sync::{collector, xfr};
+use crate::sync::prefixes::{AsnPrefixCache, PrefixRefresher, PrefixSource, RibDump, RipeStat};
+use crate::trust::mesh::{DnsTlsa, PeerVerifier};
+use crate::trust::node_signals::NodeSignals;
+use crate::trust::ttl_monitor::{TtlMonitor, TtlReports};
+use crate::tls;
 
 /// TCP connection timeout for DNS queries.
 const TCP_TIMEOUT: Duration = Duration::from_secs(30);
 
-/// Build a catalog from built zones.
-fn build_catalog(zones: BuiltZones) -> Catalog {
-    let mut catalog = Catalog::new();
-
-    // Register each zone in the catalog.
-    let bl_origin = Authority::origin(&zones.blocklist).clone();
-    let rep_origin = Authority::origin(&zones.reputation).clone();
-    let geo_origin = Authority::origin(&zones.geo).clone();
-    let asn_origin = Authority::origin(&zones.asn).clone();
-    let sig_origin = Authority::origin(&zones.signal).clone();
-    let bin_origin = Authority::origin(&zones.binary).clone();
-    let ca_origin = Authority::origin(&zones.cert).clone();
-
-    catalog.upsert(
-        bl_origin,
-        vec![Arc::new(zones.blocklist) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        rep_origin,
-        vec![Arc::new(zones.reputation) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        geo_origin,
-        vec![Arc::new(zones.geo) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        asn_origin,
-        vec![Arc::new(zones.asn) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        sig_origin,
-        vec![Arc::new(zones.signal) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        bin_origin,
-        vec![Arc::new(zones.binary) as Arc<dyn AuthorityObject>],
-    );
-    catalog.upsert(
-        ca_origin,
-        vec![Arc::new(zones.cert) as Arc<dyn AuthorityObject>],
-    );
+/// How long shutdown waits for the query log writer.
+const QUERY_LOG_FLUSH: Duration = Duration::from_secs(5);
+
+/// What a [`run`] is wired to besides its config.
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// Config file to reload on SIGHUP (see [`crate::config_reload`]).
+    pub config_path: Option<PathBuf>,
+    /// Log filter that `log.level` changes are applied to.
+    pub log_filter: Option<LogFilter>,
+    /// Health served next to the metrics; clones follow the run.
+    pub health: Health,
+}
 
-    catalog
+/// The zones this node serves, each in a swappable transfer slot.
+///
+/// Clones share the slots, so a rebuild through one is seen by all.
+#[derive(Clone)]
+pub struct ServedZones {
+    /// DNSBL zone (bl.i1.is).
+    pub blocklist: Arc<ServedZone<BlocklistAuthority>>,
+    /// Reputation zone (rep.i1.is).
+    pub reputation: Arc<ServedZone<InMemoryAuthority>>,
+    /// Geo zone (geo.i1.is).
+    pub geo: Arc<ServedZone<InMemoryAuthority>>,
+    /// ASN zone (asn.i1.is).
+    pub asn: Arc<ServedZone<InMemoryAuthority>>,
+    /// Signal zone (sig.i1.is).
+    pub signal: Arc<ServedZone<InMemoryAuthority>>,
+    /// Binary consensus zone (bin.i1.is).
+    pub binary: Arc<ServedZone<InMemoryAuthority>>,
+    /// Certificate consensus zone (ca.i1.is).
+    pub cert: Arc<ServedZone<InMemoryAuthority>>,
+    /// Structured intel zone (intel.i1.is).
+    pub intel: Arc<ServedZone<InMemoryAuthority>>,
+    /// Response policy zone (rpz.i1.is), when served.
+    pub rpz: Option<Arc<ServedZone<InMemoryAuthority>>>,
+}
+
+impl ServedZones {
+    /// Serve freshly built zones, keeping `journal_len` versions for IXFR.
+    #[must_use]
+    pub fn new(zones: BuiltZones, journal_len: usize) -> Self {
+        Self::with_store(zones, journal_len, None)
+    }
+
+    /// Like [`new`](Self::new), also saving the zones and every later
+    /// change to `store`.
+    #[must_use]
+    pub fn with_store(
+        zones: BuiltZones,
+        journal_len: usize,
+        store: Option<&Arc<ZoneDb>>,
+    ) -> Self {
+        Self {
+            blocklist: slot(zones.blocklist, journal_len, store),
+            reputation: slot(zones.reputation, journal_len, store),
+            geo: slot(zones.geo, journal_len, store),
+            asn: slot(zones.asn, journal_len, store),
+            signal: slot(zones.signal, journal_len, store),
+            binary: slot(zones.binary, journal_len, store),
+            cert: slot(zones.cert, journal_len, store),
+            intel: slot(zones.intel, journal_len, store),
+            rpz: zones.rpz.map(|rpz| slot(rpz, journal_len, store)),
+        }
+    }
+
+    /// Swap in rebuilt zones; the changes become available over IXFR.
+    pub fn replace(&self, zones: BuiltZones) {
+        self.blocklist.replace(zones.blocklist);
+        self.reputation.replace(zones.reputation);
+        self.geo.replace(zones.geo);
+        self.asn.replace(zones.asn);
+        self.signal.replace(zones.signal);
+        self.binary.replace(zones.binary);
+        self.cert.replace(zones.cert);
+        self.intel.replace(zones.intel);
+        if let (Some(slot), Some(rpz)) = (&self.rpz, zones.rpz) {
+            slot.replace(rpz);
+        }
+    }
+
+    /// Build a catalog over the zone slots.
+    #[must_use]
+    pub fn catalog(&self) -> Catalog {
+        let mut catalog = Catalog::new();
+        let mut authorities: Vec<Arc<dyn AuthorityObject>> = vec![
+            self.blocklist.clone(),
+            self.reputation.clone(),
+            self.geo.clone(),
+            self.asn.clone(),
+            self.signal.clone(),
+            self.binary.clone(),
+            self.cert.clone(),
+            self.intel.clone(),
+        ];
+        if let Some(rpz) = &self.rpz {
+            authorities.push(rpz.clone());
+        }
+        for authority in authorities {
+            catalog.upsert(authority.origin().clone(), vec![authority]);
+        }
+        catalog
+    }
+
+    /// Transfer views of every zone, blocklist first.
+    #[must_use]
+    pub fn transfer_zones(&self) -> Vec<Arc<dyn TransferZone>> {
+        let mut zones: Vec<Arc<dyn TransferZone>> = vec![
+            self.blocklist.clone(),
+            self.reputation.clone(),
+            self.geo.clone(),
+            self.asn.clone(),
+            self.signal.clone(),
+            self.binary.clone(),
+            self.cert.clone(),
+            self.intel.clone(),
+        ];
+        if let Some(rpz) = &self.rpz {
+            zones.push(rpz.clone());
+        }
+        zones
+    }
+}
+
+fn slot<A: ZoneStore>(
+    authority: A,
+    journal_len: usize,
+    store: Option<&Arc<ZoneDb>>,
+) -> Arc<ServedZone<A>> {
+    let zone = ServedZone::new(authority, journal_len);
+    Arc::new(match store {
+        Some(store) => zone.persisted(Arc::clone(store)),
+        None => zone,
+    })
 }
 
 /// Start the DNS server with the given configuration and defense state.
 ///
 /// This function binds UDP and TCP sockets, builds the DNS zones from
-/// the defense snapshot, and runs until shutdown.
-pub async fn run(config: &ServerConfig, mut snapshot: DefenseSnapshot) -> crate::Result<()> {
+/// the defense snapshot, and runs until shutdown. A primary rebuilds its
+/// zones from the state file whenever it changes (see [`crate::sync::reload`]).
+/// Zones saved by the previous run are restored before anything else
+/// (see [`crate::authority::persist`]). With a config path in `options`,
+/// SIGHUP also reloads the config from it (see [`crate::config_reload`]).
+///
+/// Returns once SIGTERM or SIGINT has shut the server down (see the
+/// module docs), or with an error if a listener fails.
+pub async fn run(
+    config: &ServerConfig,
+    mut snapshot: DefenseSnapshot,
+    options: RunOptions,
+) -> crate::Result<()> {
+    // Listen first, so a signal during startup isn't the default kill.
+    let shutdown = shutdown_signal()?;
+
     // Load audit snapshot if available.
     let audit_path = config
         .audit_path
@@ -71,78 +209,1263 @@ pub async fn run(config: &ServerConfig, mut snapshot: DefenseSnapshot) -> crate:
         .or_else(collector::default_audit_path);
 
     if let Some(ref path) = audit_path {
-        match collector::load_audit_snapshot(path) {
-            Ok(Some(audit)) => {
-                info!(
-                    bins = audit.binaries.len(),
-                    certs = audit.root_certs.len(),
-                    node = %audit.node_id,
-                    "loaded audit snapshot"
-                );
-                snapshot.audit = Some(audit);
-            }
-            Ok(None) => {
-                info!("no audit snapshot found, bin/ca zones will be empty");
+        snapshot.audit = load_audit(path);
+    }
+
+    let ttl_reports = TtlReports::default();
+    let zone_options = rebuild_options(config, &ttl_reports)?;
+    let source = SnapshotSource {
+        state_path: config
+            .state_path
+            .clone()
+            .or_else(collector::default_state_path),
+        audit_path,
+        blocks_path: config.admin.blocks_path(),
+        intel: snapshot.intel.clone(),
+        asn_prefixes: config.asn_prefixes.enabled.then(AsnPrefixCache::default),
+        node_signals: config
+            .node_signals
+            .enabled
+            .then(|| NodeSignals::from_config(&config.node_signals)),
+    };
+    let mut rebuilder = ZoneRebuilder::new(config.zones.clone(), source.clone(), zone_options);
+    let rebuild = config
+        .transfer
+        .primary
+        .is_none()
+        .then(|| rebuilder.handle());
+
+    let store = open_store(&config.store);
+    let compaction = store.as_ref().map(|(store, _)| Arc::clone(store));
+    let zones = initial_zones(config, &snapshot, &mut rebuilder, store)?;
+    if let Some(store) = &compaction {
+        tokio::spawn(
+            Arc::clone(store).run_compaction(Duration::from_secs(config.store.compact_secs)),
+        );
+    }
+
+    let acl = TransferAcl::parse(&config.transfer.allow_from)?;
+    if !config.transfer.allow_from.is_empty() {
+        info!(peers = ?config.transfer.allow_from, "zone transfers enabled");
+    }
+    let verifier = peer_verifier(config)?;
+    start_zone_updates(config, rebuilder, &zones, verifier.clone());
+
+    // Create server.
+    let query_log = open_query_log(config, &zones)?;
+    let health = &options.health;
+    let chaos = ChaosResponder::new(config);
+    let mut server = ServerFuture::new(handler(&zones, acl, query_log.as_ref(), health, &chaos));
+    register_plain(&mut server, config.listen).await?;
+    if config.tls.dot_enabled || config.tls.doh_enabled {
+        let doh = handler(
+            &zones,
+            TransferAcl::default(),
+            query_log.as_ref(),
+            health,
+            &chaos,
+        );
+        register_encrypted(&mut server, &config.tls, doh).await?;
+    }
+    start_services(
+        config,
+        &options,
+        &zones,
+        source,
+        rebuild,
+        ttl_reports,
+        verifier,
+        query_log.clone(),
+    )
+    .await?;
+
+    health.zones_loaded(&zones);
+    info!(
+        addr = %config.listen,
+        node = %config.node_name,
+        "i1-srv DNS threat intel server running"
+    );
+
+    // Run until a listener fails or shutdown is asked for.
+    let signal = tokio::select! {
+        result = server.block_until_done() => {
+            result.map_err(|e| crate::SrvError::Server(format!("server error: {e}")))?;
+            return Ok(());
+        }
+        signal = shutdown => signal,
+    };
+    info!(signal, "shutting down");
+    drain(
+        &mut server,
+        health,
+        Duration::from_secs(config.drain_timeout_secs),
+    )
+    .await;
+    flush(query_log, compaction).await;
+    info!("i1-srv stopped");
+    Ok(())
+}
+
+/// Keep `zones` current: transferred from the primary on a secondary,
+/// rebuilt from the snapshot sources otherwise.
+fn start_zone_updates(
+    config: &ServerConfig,
+    rebuilder: ZoneRebuilder,
+    zones: &ServedZones,
+    verifier: Option<Arc<PeerVerifier>>,
+) {
+    if let Some(primary) = config.transfer.primary {
+        let primary = if let Some(((name, addr), verifier)) = config.primary_pin().zip(verifier) {
+            xfr::Primary::Pinned(xfr::PrimaryPin {
+                verifier,
+                name,
+                addr,
+            })
+        } else {
+            warn!(
+                %primary,
+                "zone transfers are UNAUTHENTICATED: anyone on the path can rewrite \
+                 the zones this node serves; set transfer.primary_name to transfer \
+                 over DANE-verified DNS-over-TLS"
+            );
+            xfr::Primary::Tcp(primary)
+        };
+        tokio::spawn(xfr::run_secondary(
+            primary,
+            zones.transfer_zones(),
+            Duration::from_secs(config.transfer.refresh_secs),
+        ));
+    } else {
+        tokio::spawn(rebuilder.run(
+            zones.clone(),
+            Duration::from_millis(config.reload_debounce_ms),
+            Duration::from_secs(config.reload_interval_secs),
+        ));
+    }
+}
+
+/// Resolves with the signal's name on SIGTERM or SIGINT, which are
+/// listened for from the call on.
+#[cfg(unix)]
+fn shutdown_signal() -> crate::Result<impl Future<Output = &'static str>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    Ok(async move {
+        tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        }
+    })
+}
+
+/// Resolves on Ctrl-C.
+#[cfg(not(unix))]
+fn shutdown_signal() -> crate::Result<impl Future<Output = &'static str>> {
+    Ok(async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        "Ctrl-C"
+    })
+}
+
+/// Refuse new queries and give the ones being answered up to `timeout`,
+/// then close the listeners. Closing them cancels whatever is still
+/// being answered, which is why the wait comes first.
+async fn drain(server: &mut ServerFuture<TransferHandler>, health: &Health, timeout: Duration) {
+    health.start_draining();
+    let in_flight = health.in_flight();
+    if tokio::time::timeout(timeout, health.drained())
+        .await
+        .is_ok()
+    {
+        debug!(in_flight, "in-flight queries drained");
+    } else {
+        warn!(
+            in_flight = health.in_flight(),
+            "drain timed out, dropping queries still being answered"
+        );
+    }
+    if let Err(e) = server.shutdown_gracefully().await {
+        warn!(error = %e, "listener failed while shutting down");
+    }
+}
+
+/// Get what is buffered onto disk: lines queued for the query log, and
+/// the zone store's journal folded into its saved zones.
+async fn flush(query_log: Option<QueryLog>, store: Option<Arc<ZoneDb>>) {
+    let flushed = tokio::task::spawn_blocking(move || {
+        if let Some(log) = query_log {
+            if !log.flush(QUERY_LOG_FLUSH) {
+                warn!("query log writer did not catch up, lines may be lost");
             }
-            Err(e) => {
-                info!(error = %e, "failed to load audit snapshot, continuing without");
+        }
+        if let Some(store) = store {
+            match store.compact() {
+                Ok(folded) => debug!(folded, "compacted zone store"),
+                Err(e) => warn!(error = %e, "zone store compaction failed"),
             }
         }
+    })
+    .await;
+    if let Err(e) = flushed {
+        warn!(error = %e, "flushing on shutdown failed");
     }
+}
 
-    // Build zones from defense state.
-    let serial = chrono::Utc::now().format("%Y%m%d01").to_string();
-    let serial: u32 = serial
-        .parse()
-        .map_err(|e| crate::SrvError::Zone(format!("serial parse error: {e}")))?;
+/// Reload the config from `path` on SIGHUP, applying what can change live.
+fn start_config_reload(
+    config: &ServerConfig,
+    path: &std::path::Path,
+    rebuild: Option<RebuildHandle>,
+    query_log: Option<QueryLog>,
+    log_filter: Option<LogFilter>,
+    gossip: Option<GossipNode>,
+) {
+    let mut reloader = ConfigReloader::new(path, config.clone());
+    if let Some(rebuild) = rebuild {
+        reloader = reloader.with_rebuild(rebuild);
+    }
+    if let Some(query_log) = query_log {
+        reloader = reloader.with_query_log(query_log);
+    }
+    if let Some(filter) = log_filter {
+        reloader = reloader.with_log_filter(filter);
+    }
+    if let Some(gossip) = gossip {
+        reloader = reloader.with_gossip(gossip);
+    }
+    tokio::spawn(reloader.run());
+}
 
-    let zones = zone_builder::build_zones(&snapshot, &config.zones, serial)?;
+/// Open the on-disk zone store when it is enabled. A store that can't be
+/// opened is logged, and the node runs without one.
+fn open_store(config: &StoreConfig) -> Option<(Arc<ZoneDb>, SavedZones)> {
+    if !config.enabled {
+        return None;
+    }
+    let Some(path) = config.path() else {
+        warn!("no data directory for the zone store, zones will not survive restarts");
+        return None;
+    };
+    match ZoneDb::open(&path) {
+        Ok((store, saved)) => Some((Arc::new(store), saved)),
+        Err(e) => {
+            warn!(error = %e, "zone store unavailable, zones will not survive restarts");
+            None
+        }
+    }
+}
 
+/// The zones to serve when the listeners open.
+///
+/// Zones saved by the previous run come first, and a primary then catches
+/// up with its defense state, journaling the difference for IXFR. With
+/// nothing saved, a primary builds from the defense state and a secondary
+/// starts empty at serial 0 until it transfers the primary's zones.
+fn initial_zones(
+    config: &ServerConfig,
+    snapshot: &DefenseSnapshot,
+    rebuilder: &mut ZoneRebuilder,
+    store: Option<(Arc<ZoneDb>, SavedZones)>,
+) -> crate::Result<ServedZones> {
+    let rpz = config.rpz.serve.then_some(config.rpz.zone.as_str());
+    let journal_len = config.transfer.journal_len;
+    let (store, restored) = match store {
+        Some((store, mut saved)) => {
+            let restored = zone_builder::restore_zones(&mut saved, &config.zones, rpz)?;
+            (Some(store), restored)
+        }
+        None => (None, None),
+    };
+
+    if let Some(zones) = restored {
+        info!(
+            serial = zones.serial,
+            entries = zones.entry_count,
+            "serving zones saved before restart"
+        );
+        rebuilder.continue_after(zones.serial);
+        let served = ServedZones::with_store(zones, journal_len, store.as_ref());
+        if config.transfer.primary.is_none() {
+            let zones = rebuilder.build(snapshot)?;
+            info!(
+                serial = zones.serial,
+                entries = zones.entry_count,
+                "built DNS zones from defense state"
+            );
+            served.replace(zones);
+        }
+        return Ok(served);
+    }
+
+    let zones = if let Some(primary) = config.transfer.primary {
+        info!(%primary, "serving zones transferred from primary");
+        let options = BuildOptions {
+            rpz,
+            ..BuildOptions::default()
+        };
+        zone_builder::build_zones_with(&DefenseSnapshot::default(), &config.zones, 0, options)?
+    } else {
+        let zones = rebuilder.build(snapshot)?;
+        info!(
+            serial = zones.serial,
+            entries = zones.entry_count,
+            "built DNS zones from defense state"
+        );
+        zones
+    };
+    Ok(ServedZones::with_store(zones, journal_len, store.as_ref()))
+}
+
+/// Start the optional services: gossip, TTL monitor, admin API, metrics
+/// and health, and config reload when there is a file to reload.
+#[allow(clippy::too_many_arguments)]
+async fn start_services(
+    config: &ServerConfig,
+    options: &RunOptions,
+    zones: &ServedZones,
+    source: SnapshotSource,
+    rebuild: Option<RebuildHandle>,
+    ttl_reports: TtlReports,
+    verifier: Option<Arc<PeerVerifier>>,
+    query_log: Option<QueryLog>,
+) -> crate::Result<()> {
+    let registry = Registry::default();
+    // Later versions of the zone share the cache, so it counts across swaps.
+    registry.register(zones.blocklist.authority().negative_cache().clone());
+    if let Some(verifier) = &verifier {
+        registry.register(verifier.clone());
+    }
+    if let Some(query_log) = &query_log {
+        registry.register(Arc::new(query_log.clone()));
+    }
+    let gossip = match verifier.filter(|_| config.gossip.enabled) {
+        Some(verifier) => Some(start_gossip(config, &registry, verifier).await?),
+        None => None,
+    };
+    if let Some(gossip) = &gossip {
+        options.health.gossip(gossip);
+    }
+    if let Some(path) = &options.config_path {
+        start_config_reload(
+            config,
+            path,
+            rebuild.clone(),
+            query_log,
+            options.log_filter.clone(),
+            gossip.clone(),
+        );
+    }
+    let ttl_reports = config.ttl_monitor.enabled.then(|| {
+        start_ttl_monitor(config, zones, ttl_reports, rebuild.clone(), &registry)
+    });
+    if let (Some(cache), Some(rebuild)) = (&source.asn_prefixes, &rebuild) {
+        start_prefix_refresher(config, &source, cache.clone(), rebuild.clone())?;
+    }
+    if let (Some(signals), Some(rebuild)) = (&source.node_signals, &rebuild) {
+        info!("accepting signal records for other nodes");
+        tokio::spawn(signals.clone().run(rebuild.clone()));
+    }
+    if config.admin.enabled {
+        start_admin(config, zones, source, rebuild, gossip, ttl_reports).await?;
+    }
+    if config.metrics.enabled {
+        let listener = bind_tcp(config.metrics.listen, "metrics").await?;
+        info!(addr = %config.metrics.listen, "metrics and health endpoints listening");
+        tokio::spawn(metrics::serve(listener, registry, options.health.clone()));
+    }
+    Ok(())
+}
+
+/// Probe resolvers for TTL manipulation; findings are republished
+/// through `rebuild` when the monitor is set to publish them.
+fn start_ttl_monitor(
+    config: &ServerConfig,
+    zones: &ServedZones,
+    reports: TtlReports,
+    rebuild: Option<RebuildHandle>,
+    registry: &Registry,
+) -> TtlReports {
+    registry.register(Arc::new(reports.clone()));
+    let mut monitor = TtlMonitor::new(
+        zones.clone(),
+        &config.zones.signal,
+        &config.ttl_monitor,
+        reports.clone(),
+    );
+    if let Some(rebuild) = rebuild.filter(|_| config.ttl_monitor.publish) {
+        monitor = monitor.with_rebuild(rebuild);
+    }
+    tokio::spawn(monitor.run());
+    reports
+}
+
+/// Keep blocked ASNs' prefix lists current, rebuilding when they change.
+fn start_prefix_refresher(
+    config: &ServerConfig,
+    source: &SnapshotSource,
+    cache: AsnPrefixCache,
+    rebuild: RebuildHandle,
+) -> crate::Result<()> {
+    let prefixes = &config.asn_prefixes;
+    let lookup: Arc<dyn PrefixSource> = match &prefixes.rib_path {
+        Some(path) => Arc::new(RibDump::new(path.clone())),
+        None => Arc::new(RipeStat::new(&prefixes.ripestat_url)?),
+    };
     info!(
-        serial = serial,
-        entries = zones.entry_count,
-        "built DNS zones from defense state"
+        source = lookup.name(),
+        "publishing prefix lists for blocked ASNs"
     );
+    let refresher = PrefixRefresher::new(
+        lookup,
+        cache,
+        source.state_path.clone(),
+        Duration::from_secs(prefixes.refresh_secs),
+    )
+    .with_rebuild(rebuild);
+    tokio::spawn(refresher.run());
+    Ok(())
+}
 
-    let catalog = build_catalog(zones);
+/// Signing keys and zone settings used by every rebuild.
+fn rebuild_options(
+    config: &ServerConfig,
+    ttl_reports: &TtlReports,
+) -> crate::Result<RebuildOptions> {
+    // Load the intel signing key, if configured.
+    let signer = config
+        .intel_signing_key
+        .as_deref()
+        .map(|path| {
+            i1_ca::NodeSigner::load(path)
+                .map_err(|e| crate::SrvError::Identity(format!("intel signing key: {e}")))
+        })
+        .transpose()?;
+    if signer.is_some() {
+        info!("intel TXT records will be signed with the node key");
+    }
 
-    // Create server.
-    let mut server = ServerFuture::new(catalog);
+    Ok(RebuildOptions {
+        signer,
+        public_ip: config.attestation_ip(),
+        dnssec: load_dnssec_keys(config)?,
+        rpz: config.rpz.clone(),
+        ttl: config.ttl.clone(),
+        ttl_reports: (config.ttl_monitor.enabled && config.ttl_monitor.publish)
+            .then(|| ttl_reports.clone()),
+    })
+}
+
+/// Load the audit snapshot served in the bin/ca zones, logging what was found.
+fn load_audit(path: &std::path::Path) -> Option<AuditData> {
+    match collector::load_audit_snapshot(path) {
+        Ok(Some(audit)) => {
+            info!(
+                bins = audit.binaries.len(),
+                certs = audit.root_certs.len(),
+                node = %audit.node_id,
+                "loaded audit snapshot"
+            );
+            Some(audit)
+        }
+        Ok(None) => {
+            info!("no audit snapshot found, bin/ca zones will be empty");
+            None
+        }
+        Err(e) => {
+            info!(error = %e, "failed to load audit snapshot, continuing without");
+            None
+        }
+    }
+}
 
-    // Bind UDP.
-    let udp_socket = UdpSocket::bind(config.listen)
+/// Bind plain DNS on UDP and TCP at `listen`.
+async fn register_plain(
+    server: &mut ServerFuture<TransferHandler>,
+    listen: std::net::SocketAddr,
+) -> crate::Result<()> {
+    let udp_socket = UdpSocket::bind(listen)
         .await
-        .map_err(|e| crate::SrvError::Server(format!("UDP bind {}: {e}", config.listen)))?;
-    info!(addr = %config.listen, "UDP socket bound");
+        .map_err(|e| crate::SrvError::Server(format!("UDP bind {listen}: {e}")))?;
+    info!(addr = %listen, "UDP socket bound");
     server.register_socket(udp_socket);
 
-    // Bind TCP.
-    let tcp_listener = TcpListener::bind(config.listen)
+    let tcp_listener = TcpListener::bind(listen)
         .await
-        .map_err(|e| crate::SrvError::Server(format!("TCP bind {}: {e}", config.listen)))?;
-    info!(addr = %config.listen, "TCP listener bound");
+        .map_err(|e| crate::SrvError::Server(format!("TCP bind {listen}: {e}")))?;
+    info!(addr = %listen, "TCP listener bound");
     server.register_listener(tcp_listener, TCP_TIMEOUT);
+    Ok(())
+}
 
-    info!(
-        addr = %config.listen,
-        node = %config.node_name,
-        "i1-srv DNS threat intel server running"
-    );
+/// Bind the encrypted listeners enabled in `tls_config`.
+///
+/// DNS-over-TLS shares the plain server's handler, so zone transfers
+/// are available over TLS to the same peers. DNS-over-HTTPS answers
+/// with `doh_handler`, which allows no transfers.
+async fn register_encrypted(
+    server: &mut ServerFuture<TransferHandler>,
+    tls_config: &TlsConfig,
+    doh_handler: TransferHandler,
+) -> crate::Result<()> {
+    let identity = load_tls_identity(tls_config)?;
 
-    // Run until shutdown.
-    server
-        .block_until_done()
-        .await
-        .map_err(|e| crate::SrvError::Server(format!("server error: {e}")))?;
+    if tls_config.dot_enabled {
+        let listener = bind_tcp(tls_config.dot_listen, "DoT").await?;
+        server
+            .register_tls_listener_with_tls_config(
+                listener,
+                TCP_TIMEOUT,
+                tls::server_config(&identity, &[tls::DOT_ALPN])?,
+            )
+            .map_err(|e| crate::SrvError::Server(format!("DoT listener: {e}")))?;
+        info!(addr = %tls_config.dot_listen, "DNS-over-TLS listener bound");
+    }
 
+    if tls_config.doh_enabled {
+        let listener = bind_tcp(tls_config.doh_listen, "DoH").await?;
+        // Multi-message transfers don't fit one HTTP response; DoH refuses them.
+        tokio::spawn(tls::serve_doh(
+            listener,
+            tls::server_config(&identity, &tls::DOH_ALPN)?,
+            doh_handler,
+        ));
+        info!(
+            addr = %tls_config.doh_listen,
+            path = tls::DOH_PATH,
+            "DNS-over-HTTPS listener bound"
+        );
+    }
     Ok(())
 }
 
+/// The request handler for `zones`, logging queries to `query_log`,
+/// counting them in `health` and answering CHAOS queries with `chaos`.
+fn handler(
+    zones: &ServedZones,
+    acl: TransferAcl,
+    query_log: Option<&QueryLog>,
+    health: &Health,
+    chaos: &ChaosResponder,
+) -> TransferHandler {
+    let handler = TransferHandler::new(zones.catalog(), zones.transfer_zones(), acl)
+        .with_health(health.clone())
+        .with_chaos(chaos.clone());
+    match query_log {
+        Some(log) => handler.with_query_log(log.clone()),
+        None => handler,
+    }
+}
+
+/// Open the query log when it is enabled.
+fn open_query_log(config: &ServerConfig, zones: &ServedZones) -> crate::Result<Option<QueryLog>> {
+    if !config.query_log.enabled {
+        return Ok(None);
+    }
+    let origins: Vec<_> = zones
+        .transfer_zones()
+        .iter()
+        .map(|zone| zone.name().clone())
+        .collect();
+    let log = QueryLog::open(&config.query_log, &origins)?;
+    info!(path = ?config.query_log.path(), "query log enabled");
+    Ok(Some(log))
+}
+
+/// Load the certificate the encrypted listeners present: the configured
+/// files, or else the node identity.
+fn load_tls_identity(config: &TlsConfig) -> crate::Result<NodeIdentity> {
+    match (&config.cert_path, &config.key_path) {
+        (Some(cert), Some(key)) => NodeIdentity::load(cert, key),
+        (None, None) => {
+            let dir = NodeIdentity::default_dir().ok_or_else(|| {
+                crate::SrvError::Config(
+                    "no tls.cert_path and no data directory for the node identity".into(),
+                )
+            })?;
+            NodeIdentity::load_dir(&dir)
+        }
+        _ => Err(crate::SrvError::Config(
+            "tls.cert_path and tls.key_path must be set together".into(),
+        )),
+    }
+}
+
+/// Load the node identity from the data directory.
+fn node_identity() -> crate::Result<NodeIdentity> {
+    let dir = NodeIdentity::default_dir()
+        .ok_or_else(|| crate::SrvError::Config("no data directory for the node identity".into()))?;
+    NodeIdentity::load_dir(&dir)
+}
+
+/// DANE verifier for gossip peers and the transfer primary, when either
+/// needs one.
+fn peer_verifier(config: &ServerConfig) -> crate::Result<Option<Arc<PeerVerifier>>> {
+    if !config.gossip.enabled && config.primary_pin().is_none() {
+        return Ok(None);
+    }
+    let tlsa = DnsTlsa::with_servers(&config.dane.resolvers)?;
+    let verifier = PeerVerifier::new(Arc::new(tlsa), config.dane.fail_open)?;
+    Ok(Some(Arc::new(verifier)))
+}
+
+/// Join the gossip mesh with the node identity, seeded from `peers`.
+async fn start_gossip(
+    config: &ServerConfig,
+    registry: &Registry,
+    verifier: Arc<PeerVerifier>,
+) -> crate::Result<GossipNode> {
+    let gossip = &config.gossip;
+    let root = gossip.root_ca.as_deref().ok_or_else(|| {
+        crate::SrvError::Config("gossip.root_ca is required to enable gossip".into())
+    })?;
+    let identity = node_identity()?;
+    let tls = GossipTls::load(&identity, root, &gossip.crls)?;
+    let node = GossipNode::new(
+        &identity,
+        &config.node_fqdn(),
+        gossip.advertise.unwrap_or(gossip.listen),
+        tls,
+        verifier,
+    )?;
+
+    registry.register(Arc::new(node.clone()));
+
+    let listener = bind_tcp(gossip.listen, "gossip").await?;
+    tokio::spawn(node.clone().run(
+        listener,
+        config.gossip_seeds()?,
+        Duration::from_secs(gossip.interval_secs),
+        Duration::from_secs(gossip.anti_entropy_secs),
+    ));
+    Ok(node)
+}
+
+/// Serve the admin API: on loopback, or anywhere over mTLS with the node
+/// identity and the gossip root. Only a primary (`rebuild`) takes mutations.
+async fn start_admin(
+    config: &ServerConfig,
+    zones: &ServedZones,
+    source: SnapshotSource,
+    rebuild: Option<RebuildHandle>,
+    gossip: Option<GossipNode>,
+    ttl_reports: Option<TtlReports>,
+) -> crate::Result<()> {
+    let settings = &config.admin;
+    let auth = if settings.mtls {
+        let root = config.gossip.root_ca.as_deref().ok_or_else(|| {
+            crate::SrvError::Config("gossip.root_ca is required for admin.mtls".into())
+        })?;
+        let identity = node_identity()?;
+        let tls = GossipTls::load(&identity, root, &config.gossip.crls)?;
+        AdminAuth::Mtls(ClientAuth::new(&tls, &identity, &settings.clients)?)
+    } else if settings.listen.ip().is_loopback() {
+        let path = settings.token_path().ok_or_else(|| {
+            crate::SrvError::Config("no data directory for admin.token_path; set it".into())
+        })?;
+        AdminAuth::Token(AdminToken::load_or_create(&path)?)
+    } else {
+        return Err(crate::SrvError::Config(
+            "admin.listen beyond loopback requires admin.mtls".into(),
+        ));
+    };
+
+    let mut api = AdminApi::new(&config.node_fqdn(), zones.clone(), source);
+    if let Some(rebuild) = rebuild {
+        api = api.with_rebuild(rebuild);
+    }
+    if let Some(gossip) = gossip {
+        api = api.with_gossip(gossip);
+    }
+    if let Some(reports) = ttl_reports {
+        api = api.with_ttl_reports(reports);
+    }
+    let listener = bind_tcp(settings.listen, "admin").await?;
+    info!(addr = %settings.listen, mtls = settings.mtls, "admin API listening");
+    tokio::spawn(admin::serve(listener, api.router(), auth));
+    Ok(())
+}
+
+async fn bind_tcp(addr: std::net::SocketAddr, what: &str) -> crate::Result<TcpListener> {
+    TcpListener::bind(addr)
+        .await
+        .map_err(|e| crate::SrvError::Server(format!("{what} bind {addr}: {e}")))
+}
+
+/// Load (or create) the zone signing keys when DNSSEC is enabled, and log
+/// the DS records the parent zones need.
+fn load_dnssec_keys(config: &ServerConfig) -> crate::Result<Option<ZoneSigningKeys>> {
+    if !config.dnssec.enabled {
+        return Ok(None);
+    }
+    let key_dir = config.dnssec.key_dir().ok_or_else(|| {
+        crate::SrvError::Config("dnssec.key_dir is unset and no data directory exists".into())
+    })?;
+    let keys = ZoneSigningKeys::load_or_generate(
+        &key_dir,
+        &zone_builder::zone_origins(&config.zones)?,
+        Duration::from_secs(config.dnssec.signature_validity_secs),
+    )?;
+    for ds in keys.ds_records()? {
+        info!(record = %ds, "DNSSEC enabled; publish this DS at the parent");
+    }
+    Ok(Some(keys))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::ZoneConfig;
 
+    #[tokio::test]
+    async fn test_signed_zones_validate() {
+        use crate::authority::transfer::TransferAcl;
+        use hickory_proto::dnssec::{Proof, Proven, TrustAnchors};
+        use hickory_proto::rr::{LowerName, RecordType};
+        use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+        use hickory_resolver::name_server::TokioConnectionProvider;
+        use hickory_resolver::Resolver;
+
+        let config = ZoneConfig::default();
+        let origins = zone_builder::zone_origins(&config).unwrap();
+        let keys = ZoneSigningKeys::generate(&origins, Duration::from_secs(3600)).unwrap();
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into(), "10.0.0.0/8".into()],
+            ..Default::default()
+        };
+        let options = BuildOptions {
+            dnssec: Some(&keys),
+            ..Default::default()
+        };
+        let served = ServedZones::new(
+            zone_builder::build_zones_with(&snapshot, &config, 1, options).unwrap(),
+            4,
+        );
+
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(addr).await.unwrap();
+        let handler = TransferHandler::new(
+            served.catalog(),
+            served.transfer_zones(),
+            TransferAcl::default(),
+        );
+        let mut dns = ServerFuture::new(handler);
+        dns.register_socket(udp);
+        dns.register_listener(tcp, TCP_TIMEOUT);
+        tokio::spawn(async move { dns.block_until_done().await });
+
+        // Pin the zone key directly; there is no parent to hold a DS.
+        let mut anchors = TrustAnchors::empty();
+        for dnskey in keys.dnskeys(&LowerName::from(&origins[0])) {
+            anchors.insert(dnskey.public_key());
+        }
+        let resolver_config = ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true),
+        );
+        let mut builder =
+            Resolver::builder_with_config(resolver_config, TokioConnectionProvider::default())
+                .with_trust_anchor(Arc::new(anchors));
+        builder.options_mut().validate = true;
+        let resolver = builder.build();
+
+        // A stored record, then one synthesized from the CIDR and signed online.
+        for name in ["4.3.2.1.bl.i1.is.", "4.3.2.10.bl.i1.is."] {
+            let lookup = resolver.lookup(name, RecordType::A).await.unwrap();
+            let proofs: Vec<Proof> = lookup
+                .dnssec_iter()
+                .map(Proven::into_parts)
+                .filter(|(_, rdata)| rdata.record_type() == RecordType::A)
+                .map(|(proof, _)| proof)
+                .collect();
+            assert_eq!(proofs, [Proof::Secure], "{name}");
+        }
+    }
+
+    /// Serve `zones` on an ephemeral port.
+    async fn serve(zones: &ServedZones) -> (std::net::SocketAddr, tokio::task::JoinHandle<()>) {
+        let handler = TransferHandler::new(
+            zones.catalog(),
+            zones.transfer_zones(),
+            TransferAcl::default(),
+        );
+        serve_handler(handler).await
+    }
+
+    /// Answer with `handler` on an ephemeral port.
+    async fn serve_handler(
+        handler: TransferHandler,
+    ) -> (std::net::SocketAddr, tokio::task::JoinHandle<()>) {
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(addr).await.unwrap();
+        let mut dns = ServerFuture::new(handler);
+        dns.register_socket(udp);
+        dns.register_listener(tcp, TCP_TIMEOUT);
+        let task = tokio::spawn(async move {
+            let _ = dns.block_until_done().await;
+        });
+        (addr, task)
+    }
+
+    /// A node's trust digest, published through the admin API, verifies
+    /// over DNS the way `i1 audit verify --check` does.
+    #[tokio::test]
+    async fn test_published_node_signal_verifies() {
+        use crate::authority::ttl_policy;
+        use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+        use hickory_resolver::name_server::TokioConnectionProvider;
+        use hickory_resolver::Resolver;
+        use i1_audit::verify::{self, Verdict};
+
+        let source = SnapshotSource {
+            node_signals: Some(NodeSignals::new(
+                Duration::from_secs(30),
+                Duration::from_secs(3600),
+            )),
+            ..Default::default()
+        };
+        let mut rebuilder = ZoneRebuilder::new(
+            ZoneConfig::default(),
+            source.clone(),
+            RebuildOptions::default(),
+        );
+        let zones = ServedZones::new(rebuilder.build(&source.load().unwrap()).unwrap(), 4);
+        let api = AdminApi::new("node1.srv.i1.is", zones.clone(), source)
+            .with_rebuild(rebuilder.handle());
+        let forever = Duration::from_secs(3600);
+        tokio::spawn(rebuilder.run(zones.clone(), forever, forever));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let admin_url = format!("http://{}/v1/signals", listener.local_addr().unwrap());
+        let admin_token = AdminToken::generate().unwrap();
+        tokio::spawn(admin::serve(
+            listener,
+            api.router(),
+            AdminAuth::Token(admin_token.clone()),
+        ));
+        let (dns, _task) = serve(&zones).await;
+
+        let config = ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[dns.ip()], dns.port(), true),
+        );
+        let mut builder = Resolver::builder_with_config(config, TokioConnectionProvider::default());
+        builder.options_mut().cache_size = 0;
+        let resolver = builder.build();
+
+        let snapshot: i1_audit::AuditSnapshot = serde_json::from_value(serde_json::json!({
+            "node_id": "edge-7",
+            "collected_at": chrono::Utc::now(),
+            "system_uptime_secs": 60,
+            "cpu_count": 2,
+            "binaries": [],
+            "processes": [],
+            "root_certs": [],
+            "summary": {
+                "total_binaries": 0,
+                "total_processes": 0,
+                "total_root_certs": 0,
+                "running_binaries": 0,
+                "expired_certs": 0,
+                "low_trust_binaries": 0,
+                "unknown_certs": 0,
+            },
+        }))
+        .unwrap();
+        let token = verify::generate_verify_token(&snapshot);
+        let check = verify::check_token(&resolver, &token).await.unwrap();
+        assert_eq!(check.verdict, Verdict::NotPublished);
+
+        let client = reqwest::Client::new();
+        let publish = |prefix: &str, value: &str| {
+            client
+                .put(format!("{admin_url}/{prefix}"))
+                .bearer_auth(admin_token.secret())
+                .json(&serde_json::json!({ "value": value }))
+                .send()
+        };
+        let response = publish(&token.node_prefix, &token.expected_value)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["name"], token.dns_name);
+        assert_eq!(body["ttl"], ttl_policy::SIGNAL_TTL);
+
+        let check = verify::check_token(&resolver, &token).await.unwrap();
+        assert_eq!(check.verdict, Verdict::Ok);
+        assert_eq!(check.observed_ttl, Some(ttl_policy::SIGNAL_TTL));
+        assert_eq!(
+            check.observed_value.as_deref(),
+            Some(&*token.expected_value)
+        );
+
+        // Another node's prefix, and a second update straight away.
+        let response = publish("000000000000", &token.expected_value)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let response = publish(&token.node_prefix, &token.expected_value)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    async fn lookup_a(addr: std::net::SocketAddr, name: &str) -> Option<std::net::Ipv4Addr> {
+        use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+        use hickory_resolver::name_server::TokioConnectionProvider;
+        use hickory_resolver::Resolver;
+
+        let config = ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true),
+        );
+        let mut builder = Resolver::builder_with_config(config, TokioConnectionProvider::default());
+        builder.options_mut().cache_size = 0;
+        let lookup = builder.build().ipv4_lookup(name).await.ok()?;
+        lookup.iter().next().map(|a| a.0)
+    }
+
+    #[tokio::test]
+    async fn test_zones_survive_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig::default();
+        config.store.path = Some(dir.path().join("zones.redb"));
+        let listed = Some(crate::encoding::dnsbl::DnsblCode::Listed.to_ipv4());
+
+        // A primary serves one ban, then a rebuild adds a second.
+        let mut rebuilder = ZoneRebuilder::new(
+            config.zones.clone(),
+            SnapshotSource::default(),
+            RebuildOptions::default(),
+        );
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into()],
+            ..Default::default()
+        };
+        let zones = initial_zones(
+            &config,
+            &snapshot,
+            &mut rebuilder,
+            open_store(&config.store),
+        )
+        .unwrap();
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into(), "5.6.7.8".into()],
+            ..Default::default()
+        };
+        zones.replace(rebuilder.build(&snapshot).unwrap());
+        let serial = zones.blocklist.serial();
+        let (addr, task) = serve(&zones).await;
+        assert_eq!(lookup_a(addr, "8.7.6.5.bl.i1.is.").await, listed);
+
+        // Kill it mid-flight: no shutdown, no compaction.
+        task.abort();
+        let _ = task.await;
+        drop(zones);
+
+        // It comes back as a secondary whose primary is unreachable, so
+        // only the saved zones can answer.
+        config.transfer.primary = Some("127.0.0.1:9".parse().unwrap());
+        let mut rebuilder = ZoneRebuilder::new(
+            config.zones.clone(),
+            SnapshotSource::default(),
+            RebuildOptions::default(),
+        );
+        let zones = initial_zones(
+            &config,
+            &DefenseSnapshot::default(),
+            &mut rebuilder,
+            open_store(&config.store),
+        )
+        .unwrap();
+        assert_eq!(zones.blocklist.serial(), serial);
+        let (addr, _task) = serve(&zones).await;
+        assert_eq!(lookup_a(addr, "4.3.2.1.bl.i1.is.").await, listed);
+        assert_eq!(lookup_a(addr, "8.7.6.5.bl.i1.is.").await, listed);
+        assert_eq!(lookup_a(addr, "9.9.9.9.bl.i1.is.").await, None);
+    }
+
+    /// Send `signal` (e.g. `-HUP`) to this process.
+    #[cfg(unix)]
+    fn raise(signal: &str) {
+        let status = std::process::Command::new("kill")
+            .args([signal, &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    /// Poll until `check` holds, for up to ten seconds.
+    #[cfg(unix)]
+    async fn eventually(what: &str, mut check: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("timed out waiting for {what}");
+    }
+
+    /// The whole node under an init system: ready once serving, SIGHUP
+    /// reloads the config, and SIGTERM drains in-flight queries before
+    /// flushing and exiting.
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_signals_reload_and_drain() {
+        use crate::health::{self, HealthStatus};
+        use tracing_subscriber::{reload, EnvFilter, Registry};
+
+        let dir = tempfile::tempdir().unwrap();
+        let free = || {
+            std::net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+        };
+        let mut config = ServerConfig {
+            listen: free(),
+            state_path: Some(dir.path().join("state.json")),
+            audit_path: Some(dir.path().join("audit.json")),
+            ..ServerConfig::default()
+        };
+        config.admin.blocks_path = Some(dir.path().join("blocks.json"));
+        config.store.path = Some(dir.path().join("zones.redb"));
+        config.metrics.enabled = true;
+        config.metrics.listen = free();
+        config.query_log.enabled = true;
+        config.query_log.path = Some(dir.path().join("queries.ndjson"));
+        config.query_log.sample_rate = 1.0;
+        let path = dir.path().join("i1-srv.toml");
+        std::fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+
+        let (_layer, filter) = reload::Layer::<_, Registry>::new(EnvFilter::new("info"));
+        let health = Health::default();
+        let options = RunOptions {
+            config_path: Some(path.clone()),
+            log_filter: Some(filter.clone()),
+            health: health.clone(),
+        };
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into()],
+            ..Default::default()
+        };
+        let running = config.clone();
+        let server = tokio::spawn(async move { run(&running, snapshot, options).await });
+
+        let client = reqwest::Client::new();
+        let ready_url = format!("http://{}{}", config.metrics.listen, health::READY_PATH);
+        let readiness = || async {
+            let response = client.get(&ready_url).send().await.ok()?;
+            let code = response.status();
+            Some((code, response.json::<HealthStatus>().await.ok()?))
+        };
+        let mut ready = None;
+        for _ in 0..200 {
+            ready = readiness().await;
+            if ready.as_ref().is_some_and(|(code, _)| code.is_success()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let (_, status) = ready.expect("server never answered readiness");
+        assert!(status.ready && status.zones_loaded, "{status:?}");
+        assert!(status.serial.is_some());
+        let listed = Some(crate::encoding::dnsbl::DnsblCode::Listed.to_ipv4());
+        assert_eq!(lookup_a(config.listen, "4.3.2.1.bl.i1.is.").await, listed);
+
+        // SIGHUP applies a new log level.
+        let mut file = config.clone();
+        file.log.level = "debug".into();
+        std::fs::write(&path, toml::to_string(&file).unwrap()).unwrap();
+        raise("-HUP");
+        eventually("the new log level", || {
+            filter.with_current(ToString::to_string).ok().as_deref() == Some("debug")
+        })
+        .await;
+
+        // SIGTERM with a query in flight: not ready, new queries refused,
+        // and the server waits for the query before exiting.
+        let in_flight = health.track();
+        raise("-TERM");
+        eventually("draining", || health.is_draining()).await;
+        let (code, status) = readiness().await.unwrap();
+        assert_eq!(code, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status.reason.as_deref(), Some("shutting down"));
+        assert_eq!(lookup_a(config.listen, "4.3.2.1.bl.i1.is.").await, None);
+        assert!(!server.is_finished());
+
+        drop(in_flight);
+        tokio::time::timeout(Duration::from_secs(10), server)
+            .await
+            .expect("server did not stop after draining")
+            .unwrap()
+            .unwrap();
+        assert!(tokio::net::TcpStream::connect(config.listen).await.is_err());
+
+        // Everything answered made it to the query log.
+        let log = std::fs::read_to_string(dir.path().join("queries.ndjson")).unwrap();
+        let rcodes: Vec<String> = log
+            .lines()
+            .map(|line| serde_json::from_str::<crate::query_log::QueryEntry>(line).unwrap())
+            .map(|entry| entry.rcode)
+            .collect();
+        assert_eq!(rcodes.first().map(String::as_str), Some("NOERROR"));
+        assert!(rcodes.iter().any(|rcode| rcode == "REFUSED"), "{rcodes:?}");
+    }
+
+    /// Send one query over UDP, asking for an NSID when `nsid` is set.
+    async fn raw_query(
+        addr: std::net::SocketAddr,
+        name: &str,
+        class: hickory_proto::rr::DNSClass,
+        record_type: hickory_proto::rr::RecordType,
+        nsid: bool,
+    ) -> hickory_proto::op::Message {
+        use hickory_proto::op::{Edns, Message, Query};
+        use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+        use hickory_proto::rr::Name;
+
+        let mut query = Query::query(Name::from_ascii(name).unwrap(), record_type);
+        query.set_query_class(class);
+        let mut message = Message::new();
+        message.set_id(4892).add_query(query);
+        if nsid {
+            let mut edns = Edns::new();
+            edns.options_mut()
+                .insert(EdnsOption::Unknown(EdnsCode::NSID.into(), Vec::new()));
+            message.set_edns(edns);
+        }
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket
+            .send_to(&message.to_vec().unwrap(), addr)
+            .await
+            .unwrap();
+        let mut buf = [0; 4096];
+        let len = tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut buf))
+            .await
+            .expect("no answer")
+            .unwrap();
+        Message::from_vec(&buf[..len]).unwrap()
+    }
+
+    /// The TXT strings and NSID in a response.
+    fn identity(response: &hickory_proto::op::Message) -> (Vec<String>, Option<String>) {
+        use hickory_proto::rr::rdata::opt::{EdnsCode, EdnsOption};
+        use hickory_proto::rr::{DNSClass, RData};
+
+        let texts = response
+            .answers()
+            .iter()
+            .filter(|record| record.dns_class() == DNSClass::CH)
+            .filter_map(|record| match record.data() {
+                RData::TXT(txt) => Some(txt.to_string()),
+                _ => None,
+            })
+            .collect();
+        let nsid = response
+            .extensions()
+            .as_ref()
+            .and_then(|edns| edns.option(EdnsCode::NSID))
+            .and_then(|option| match option {
+                EdnsOption::Unknown(_, data) => Some(String::from_utf8_lossy(data).into_owned()),
+                _ => None,
+            });
+        (texts, nsid)
+    }
+
+    /// CHAOS identity queries are answered from the config, or refused
+    /// when hidden, and NSID is sent to whoever asks for it.
+    #[tokio::test]
+    async fn test_chaos_identity() {
+        use hickory_proto::op::ResponseCode;
+        use hickory_proto::rr::{DNSClass, RecordType};
+
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into()],
+            ..Default::default()
+        };
+        let zones = ServedZones::new(
+            zone_builder::build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap(),
+            4,
+        );
+        let mut config = ServerConfig {
+            node_name: "node7.srv.i1.is".into(),
+            ..ServerConfig::default()
+        };
+        let serve_config = |config: &ServerConfig| {
+            let chaos = ChaosResponder::new(config);
+            serve_handler(handler(
+                &zones,
+                TransferAcl::default(),
+                None,
+                &Health::default(),
+                &chaos,
+            ))
+        };
+
+        // Defaults: the crate version and the node prefix.
+        let (addr, task) = serve_config(&config).await;
+        let version = raw_query(addr, "version.bind.", DNSClass::CH, RecordType::TXT, false).await;
+        let expected = format!("i1-srv {}", env!("CARGO_PKG_VERSION"));
+        assert_eq!(identity(&version), (vec![expected], None));
+        let hostname = raw_query(addr, "hostname.bind.", DNSClass::CH, RecordType::TXT, true).await;
+        assert_eq!(
+            identity(&hostname),
+            (vec!["node7".into()], Some("node7".into()))
+        );
+        task.abort();
+
+        config.chaos.version = Some("hidden-ish".into());
+        config.chaos.hostname = Some("ams-1".into());
+        let (addr, task) = serve_config(&config).await;
+        let version = raw_query(addr, "VERSION.BIND.", DNSClass::CH, RecordType::TXT, false).await;
+        assert!(version.authoritative());
+        assert_eq!(identity(&version).0, ["hidden-ish"]);
+        let hostname =
+            raw_query(addr, "hostname.bind.", DNSClass::CH, RecordType::TXT, false).await;
+        assert_eq!(identity(&hostname).0, ["ams-1"]);
+        let id = raw_query(addr, "id.server.", DNSClass::CH, RecordType::TXT, true).await;
+        assert_eq!(identity(&id), (vec!["ams-1".into()], Some("ams-1".into())));
+        let unknown = raw_query(addr, "authors.bind.", DNSClass::CH, RecordType::TXT, false).await;
+        assert_eq!(unknown.response_code(), ResponseCode::Refused);
+
+        // NSID rides along on ordinary answers, and only when asked for.
+        let listed = raw_query(addr, "4.3.2.1.bl.i1.is.", DNSClass::IN, RecordType::A, true).await;
+        assert_eq!(listed.answers().len(), 1);
+        assert_eq!(identity(&listed).1.as_deref(), Some("ams-1"));
+        let listed = raw_query(
+            addr,
+            "4.3.2.1.bl.i1.is.",
+            DNSClass::IN,
+            RecordType::A,
+            false,
+        )
+        .await;
+        assert_eq!(identity(&listed).1, None);
+        task.abort();
+
+        config.chaos.hide = true;
+        let (addr, task) = serve_config(&config).await;
+        for name in ["version.bind.", "hostname.bind.", "id.server."] {
+            let hidden = raw_query(addr, name, DNSClass::CH, RecordType::TXT, true).await;
+            assert_eq!(hidden.response_code(), ResponseCode::Refused, "{name}");
+            assert_eq!(identity(&hidden), (Vec::new(), None), "{name}");
+        }
+        let listed = raw_query(addr, "4.3.2.1.bl.i1.is.", DNSClass::IN, RecordType::A, true).await;
+        assert_eq!(listed.answers().len(), 1);
+        assert_eq!(identity(&listed).1, None);
+        task.abort();
+    }
+
     #[test]
     fn test_build_catalog() {
         let snapshot = DefenseSnapshot {
@@ -152,8 +1475,9 @@ mod tests {
             ..Default::default()
         };
         let zones = zone_builder::build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap();
-        let catalog = build_catalog(zones);
-        // Catalog was built without panicking.
-        drop(catalog);
+        let served = ServedZones::new(zones, 4);
+        let catalog = served.catalog();
+        assert!(catalog.contains(served.blocklist.name()));
+        assert_eq!(served.transfer_zones().len(), 8);
     }
 }
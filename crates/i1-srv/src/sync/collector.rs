@@ -2,11 +2,24 @@
 //!
 //! Watches the `defend::State` file and rebuilds zone records when it changes.
 //! This is the bridge between i1-cli's local state and i1-srv's DNS zones.
+//!
+//! The file carries a `schema_version`:
+//!
+//! - **1** (or missing): `blocked_ips` holds bare addresses or ranges.
+//! - **2**: `blocked_ips` items may also be objects with the address's
+//!   threat, hits, `first_seen`/`last_seen`, `reason`, `pattern` and `ttl`.
+//!   Blocks whose `ttl` has run out are dropped on load.
+//!
+//! Newer versions are read as the latest known one, with a warning.
 
-use crate::authority::zone_builder::{AuditData, DefenseSnapshot};
+use crate::authority::zone_builder::{AuditData, BlockEntry, DefenseSnapshot};
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use tracing::warn;
+use tracing::{debug, warn};
+
+/// Newest `schema_version` of the defense state file this build understands.
+pub const SCHEMA_VERSION: u32 = 2;
 
 /// Defense state file structure (matches i1-cli's `defend::State` serialization).
 ///
@@ -14,18 +27,38 @@ use tracing::warn;
 /// depending on i1-cli (to avoid circular dependencies).
 #[derive(Debug, Clone, Default, Deserialize)]
 struct StateFile {
+    #[serde(default = "legacy_schema_version")]
+    schema_version: u32,
     #[serde(default)]
     blocked_countries: Vec<String>,
     #[serde(default)]
     blocked_countries_outbound: Vec<String>,
     #[serde(default)]
-    blocked_ips: Vec<String>,
+    blocked_ips: Vec<BlockedIp>,
     #[serde(default)]
     blocked_asns: Vec<String>,
     #[serde(default)]
     whitelisted_ips: Vec<String>,
 }
 
+/// Files written before versioning are version 1.
+const fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// A `blocked_ips` item: a bare address or range, or one with its threat
+/// metadata.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum BlockedIp {
+    Plain(String),
+    Detailed {
+        ip: String,
+        #[serde(flatten)]
+        entry: BlockEntry,
+    },
+}
+
 /// Find the default defense state file path.
 ///
 /// Uses the same path as i1-cli: `~/.local/share/i1/showdi1/defend_state.json`
@@ -37,27 +70,70 @@ pub fn default_state_path() -> Option<PathBuf> {
 
 /// Load a defense snapshot from the state file.
 ///
-/// Returns a default (empty) snapshot if the file doesn't exist,
-/// which is fine for a fresh node with no blocks yet.
+/// Same as [`DefenseSnapshot::load`].
 pub fn load_snapshot(path: &Path) -> crate::Result<DefenseSnapshot> {
-    if !path.exists() {
-        return Ok(DefenseSnapshot::default());
-    }
+    DefenseSnapshot::load(path)
+}
+
+impl DefenseSnapshot {
+    /// Read the defense state file at `path`.
+    ///
+    /// Returns a default (empty) snapshot if the file doesn't exist,
+    /// which is fine for a fresh node with no blocks yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SrvError::State`](crate::SrvError::State) if the file
+    /// can't be read or parsed.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            crate::SrvError::State(format!("failed to read {}: {e}", path.display()))
+        })?;
 
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| crate::SrvError::State(format!("failed to read {}: {e}", path.display())))?;
+        let state: StateFile = serde_json::from_str(&content)
+            .map_err(|e| crate::SrvError::State(format!("failed to parse state: {e}")))?;
+        if state.schema_version > SCHEMA_VERSION {
+            warn!(
+                path = %path.display(),
+                version = state.schema_version,
+                supported = SCHEMA_VERSION,
+                "defense state file is newer than this build; reading known fields only"
+            );
+        }
 
-    let state: StateFile = serde_json::from_str(&content)
-        .map_err(|e| crate::SrvError::State(format!("failed to parse state: {e}")))?;
+        let now = chrono::Utc::now();
+        let mut blocked_ips = Vec::with_capacity(state.blocked_ips.len());
+        let mut block_entries = BTreeMap::new();
+        for blocked in state.blocked_ips {
+            match blocked {
+                BlockedIp::Plain(ip) => blocked_ips.push(ip),
+                BlockedIp::Detailed { ip, entry } if entry.expired(now) => {
+                    debug!(%ip, "skipping expired block");
+                }
+                BlockedIp::Detailed { ip, entry } => {
+                    block_entries.insert(ip.clone(), entry);
+                    blocked_ips.push(ip);
+                }
+            }
+        }
 
-    Ok(DefenseSnapshot {
-        blocked_ips: state.blocked_ips,
-        blocked_countries: state.blocked_countries,
-        blocked_countries_outbound: state.blocked_countries_outbound,
-        blocked_asns: state.blocked_asns,
-        whitelisted_ips: state.whitelisted_ips,
-        audit: None,
-    })
+        Ok(Self {
+            blocked_ips,
+            blocked_countries: state.blocked_countries,
+            blocked_countries_outbound: state.blocked_countries_outbound,
+            blocked_asns: state.blocked_asns,
+            whitelisted_ips: state.whitelisted_ips,
+            audit: None,
+            intel: BTreeMap::new(),
+            block_entries,
+            asn_prefixes: BTreeMap::new(),
+            node_signals: Vec::new(),
+        })
+    }
 }
 
 /// Find the default audit snapshot file path.
@@ -78,7 +154,10 @@ pub fn load_audit_snapshot(path: &Path) -> crate::Result<Option<AuditData>> {
     }
 
     let content = std::fs::read_to_string(path).map_err(|e| {
-        crate::SrvError::State(format!("failed to read audit snapshot {}: {e}", path.display()))
+        crate::SrvError::State(format!(
+            "failed to read audit snapshot {}: {e}",
+            path.display()
+        ))
     })?;
 
     let snapshot: i1_audit::AuditSnapshot = serde_json::from_str(&content).map_err(|e| {
@@ -90,12 +169,14 @@ pub fn load_audit_snapshot(path: &Path) -> crate::Result<Option<AuditData>> {
         binaries: snapshot.binaries,
         root_certs: snapshot.root_certs,
         node_id: snapshot.node_id,
+        collected_at: snapshot.collected_at,
     }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::authority::ttl_policy::ThreatLevel;
     use std::io::Write;
 
     #[test]
@@ -132,6 +213,88 @@ mod tests {
         assert_eq!(snapshot.whitelisted_ips, vec!["173.71.155.73"]);
     }
 
+    #[test]
+    fn test_load_detailed_blocks() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            tmpfile,
+            r#"{{"blocked_ips": [
+                "1.1.1.1",
+                {{"ip": "2.2.2.2", "threat": "critical", "hits": 12,
+                  "first_seen": "2026-01-01T00:00:00Z", "last_seen": "2026-02-01T00:00:00Z"}}
+            ]}}"#
+        )
+        .unwrap();
+
+        let snapshot = load_snapshot(tmpfile.path()).unwrap();
+        assert_eq!(snapshot.blocked_ips, vec!["1.1.1.1", "2.2.2.2"]);
+        assert_eq!(snapshot.block_entries.len(), 1);
+        let entry = &snapshot.block_entries["2.2.2.2"];
+        assert_eq!(entry.threat, Some(ThreatLevel::Critical));
+        assert_eq!(entry.hits, 12);
+        assert!(entry.first_seen < entry.last_seen);
+    }
+
+    #[test]
+    fn test_load_ssh_honeypot_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("admin_blocks.json");
+        let sink =
+            i1_honeypot::listener::DefendSink::new(&path, std::time::Duration::from_secs(86_400));
+        let now = chrono::Utc::now();
+        sink.record("203.0.113.9".parse().unwrap(), now).unwrap();
+        sink.record("203.0.113.9".parse().unwrap(), now).unwrap();
+
+        let snapshot = load_snapshot(&path).unwrap();
+        assert_eq!(snapshot.blocked_ips, vec!["203.0.113.9"]);
+        let entry = &snapshot.block_entries["203.0.113.9"];
+        assert_eq!(entry.threat, Some(ThreatLevel::High));
+        assert_eq!(entry.hits, 2);
+        assert_eq!(entry.reason.as_deref(), Some("ssh-honeypot"));
+        assert_eq!(entry.ttl, Some(86_400));
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    #[test]
+    fn test_load_v1_fixture() {
+        let snapshot = DefenseSnapshot::load(&fixture("defend_state_v1.json")).unwrap();
+        assert_eq!(snapshot.blocked_ips, vec!["1.2.3.4", "10.0.0.0/24"]);
+        assert_eq!(snapshot.blocked_countries, vec!["cn", "ru"]);
+        assert_eq!(snapshot.blocked_asns, vec!["AS12345"]);
+        assert!(snapshot.block_entries.is_empty());
+    }
+
+    #[test]
+    fn test_load_v2_fixture() {
+        let snapshot = DefenseSnapshot::load(&fixture("defend_state_v2.json")).unwrap();
+        // 9.9.9.9's block ran out in 2025.
+        assert_eq!(snapshot.blocked_ips, vec!["1.2.3.4", "5.6.7.8"]);
+        let entry = &snapshot.block_entries["5.6.7.8"];
+        assert_eq!(entry.threat, Some(ThreatLevel::High));
+        assert_eq!(entry.reason.as_deref(), Some("ssh brute force"));
+        assert_eq!(entry.ttl, None);
+        assert!(entry.first_seen < entry.last_seen);
+        assert!(!snapshot.block_entries.contains_key("9.9.9.9"));
+    }
+
+    #[test]
+    fn test_load_future_version() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            tmpfile,
+            r#"{{"schema_version": 99, "blocked_ips": ["1.1.1.1"], "new_field": true}}"#
+        )
+        .unwrap();
+
+        let snapshot = DefenseSnapshot::load(tmpfile.path()).unwrap();
+        assert_eq!(snapshot.blocked_ips, vec!["1.1.1.1"]);
+    }
+
     #[test]
     fn test_load_minimal_state() {
         let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
@@ -0,0 +1,391 @@
+//! Anti-entropy: full-state repair between gossip peers.
+//!
+//! Dissemination only carries changes, so a node that joins late or comes
+//! back from a long partition never sees what it missed. Anti-entropy
+//! closes that gap. Periodically, and straight after joining, a node picks
+//! a random peer and compares per-zone digests with it:
+//!
+//! - Each zone's records are spread over [`BUCKETS`] buckets by key hash,
+//!   and each bucket is summarized by a SHA-256 digest.
+//! - Only buckets whose digests differ are transferred, in both directions,
+//!   in batches of at most [`MAX_BATCH`] records, bucket by bucket.
+//! - A round moves at most [`MAX_BATCHES_PER_ROUND`] batches each way.
+//!   Buckets it finished match from then on; whatever is left still shows
+//!   up as mismatched next round, so a large transfer resumes rather than
+//!   restarts.
+//!
+//! Conflicting versions of a record resolve the same way on every node:
+//! the higher zone serial wins, then the later timestamp, then the greater
+//! origin node name (and sequence and value, should a node write twice in
+//! the same millisecond). Copies of the same update that arrived by
+//! different paths differ only in hop count; the one with fewer hops wins.
+//! Hop counts differ from node to node, so they stay out of the digests.
+
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::authority::serial::serial_gt;
+
+/// Buckets per zone digest.
+pub const BUCKETS: usize = 64;
+
+/// Most records in one transfer message.
+pub const MAX_BATCH: usize = 128;
+
+/// Most batches pulled (and pushed) in one anti-entropy round.
+pub const MAX_BATCHES_PER_ROUND: usize = 4;
+
+/// A replicated record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Key within its zone (e.g. an owner name).
+    pub key: String,
+    /// Record data; `None` marks a deletion, so deletions propagate too.
+    pub value: Option<String>,
+    /// Zone serial the record was written at.
+    pub serial: u32,
+    /// Unix milliseconds when the record was written.
+    pub timestamp: i64,
+    /// Node that wrote the record.
+    pub origin: String,
+    /// The origin's sequence number for this write; with `origin`, it
+    /// identifies the update.
+    pub sequence: u64,
+    /// Times the record was passed on since the origin wrote it.
+    pub hops: u8,
+    /// The origin's ECDSA P-256 signature over everything but `hops`.
+    pub signature: Vec<u8>,
+}
+
+impl Record {
+    /// Whether `self` wins over `other` for the same key.
+    #[must_use]
+    pub fn supersedes(&self, other: &Self) -> bool {
+        self.precedence(other) == Ordering::Greater
+    }
+
+    fn precedence(&self, other: &Self) -> Ordering {
+        if self.serial != other.serial {
+            return if serial_gt(self.serial, other.serial) {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+        (self.timestamp, &self.origin, self.sequence, &self.value)
+            .cmp(&(other.timestamp, &other.origin, other.sequence, &other.value))
+            .then_with(|| other.hops.cmp(&self.hops))
+    }
+}
+
+/// Digest summary of one zone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneDigest {
+    /// Highest serial among the zone's records.
+    pub serial: u32,
+    /// Digest over all bucket digests.
+    pub root: [u8; 32],
+    /// Per-bucket digests, [`BUCKETS`] of them.
+    pub buckets: Vec<[u8; 32]>,
+}
+
+impl ZoneDigest {
+    /// The digest of a zone with no records.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self::from_buckets(0, vec![Context::new(&SHA256); BUCKETS])
+    }
+
+    fn from_buckets(serial: u32, buckets: Vec<Context>) -> Self {
+        let buckets: Vec<[u8; 32]> = buckets.into_iter().map(finish).collect();
+        let mut root = Context::new(&SHA256);
+        for bucket in &buckets {
+            root.update(bucket);
+        }
+        Self {
+            serial,
+            root: finish(root),
+            buckets,
+        }
+    }
+
+    /// Buckets whose contents differ from `other`'s.
+    #[must_use]
+    pub fn mismatched(&self, other: &Self) -> Vec<u16> {
+        if self.root == other.root {
+            return Vec::new();
+        }
+        (0..BUCKETS)
+            .filter(|&i| self.buckets.get(i) != other.buckets.get(i))
+            .filter_map(|i| u16::try_from(i).ok())
+            .collect()
+    }
+}
+
+/// Mismatched buckets for every zone either side holds.
+#[must_use]
+pub fn mismatches(
+    ours: &BTreeMap<String, ZoneDigest>,
+    theirs: &BTreeMap<String, ZoneDigest>,
+) -> BTreeMap<String, Vec<u16>> {
+    let empty = ZoneDigest::empty();
+    ours.keys()
+        .chain(theirs.keys())
+        .filter_map(|zone| {
+            let ours = ours.get(zone).unwrap_or(&empty);
+            let theirs = theirs.get(zone).unwrap_or(&empty);
+            let buckets = ours.mismatched(theirs);
+            (!buckets.is_empty()).then(|| (zone.clone(), buckets))
+        })
+        .collect()
+}
+
+/// Position in a transfer: the bucket and key of the last record sent.
+pub type Cursor = (u16, String);
+
+/// Records from one zone, ordered by bucket and then key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Batch {
+    /// Zone the records belong to.
+    pub zone: String,
+    /// At most [`MAX_BATCH`] records.
+    pub records: Vec<Record>,
+    /// Where to continue, if more records match.
+    pub next: Option<Cursor>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ZoneRecords {
+    serial: u32,
+    records: BTreeMap<String, Record>,
+}
+
+/// Replicated records, by zone.
+#[derive(Debug, Clone, Default)]
+pub struct RecordStore {
+    zones: BTreeMap<String, ZoneRecords>,
+}
+
+impl RecordStore {
+    /// Merge `record` into `zone`; returns whether it was new or newer.
+    pub fn merge(&mut self, zone: &str, record: Record) -> bool {
+        let zone = self.zones.entry(zone.to_string()).or_default();
+        if let Some(known) = zone.records.get(&record.key) {
+            if !record.supersedes(known) {
+                return false;
+            }
+        }
+        if zone.records.is_empty() || serial_gt(record.serial, zone.serial) {
+            zone.serial = record.serial;
+        }
+        zone.records.insert(record.key.clone(), record);
+        true
+    }
+
+    /// The current version of `key` in `zone`, deletions included.
+    #[must_use]
+    pub fn get(&self, zone: &str, key: &str) -> Option<&Record> {
+        self.zones.get(zone)?.records.get(key)
+    }
+
+    /// Every record in `zone`, in key order.
+    #[must_use]
+    pub fn records(&self, zone: &str) -> Vec<Record> {
+        self.zones
+            .get(zone)
+            .map(|z| z.records.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Zone names, serials and record counts.
+    #[must_use]
+    pub fn zones(&self) -> Vec<(String, u32, usize)> {
+        self.zones
+            .iter()
+            .map(|(name, zone)| (name.clone(), zone.serial, zone.records.len()))
+            .collect()
+    }
+
+    /// Digest summaries of every zone.
+    #[must_use]
+    pub fn digests(&self) -> BTreeMap<String, ZoneDigest> {
+        self.zones
+            .iter()
+            .map(|(name, zone)| {
+                let mut buckets = vec![Context::new(&SHA256); BUCKETS];
+                for record in zone.records.values() {
+                    hash_record(&mut buckets[bucket(&record.key)], record);
+                }
+                (name.clone(), ZoneDigest::from_buckets(zone.serial, buckets))
+            })
+            .collect()
+    }
+
+    /// Up to [`MAX_BATCH`] records of `zone` in `buckets`, continuing
+    /// after `after`.
+    #[must_use]
+    pub fn batch(&self, zone: &str, buckets: &[u16], after: Option<&Cursor>) -> Batch {
+        let mut matching: Vec<(u16, &Record)> = self
+            .zones
+            .get(zone)
+            .into_iter()
+            .flat_map(|z| z.records.values())
+            .filter_map(|r| {
+                let b = u16::try_from(bucket(&r.key)).ok()?;
+                buckets.contains(&b).then_some((b, r))
+            })
+            .filter(|(b, r)| after.map_or(true, |(ab, ak)| (*b, &r.key) > (*ab, ak)))
+            .collect();
+        matching.sort_unstable_by(|x, y| (x.0, &x.1.key).cmp(&(y.0, &y.1.key)));
+
+        let more = matching.len() > MAX_BATCH;
+        matching.truncate(MAX_BATCH);
+        let next = matching
+            .last()
+            .filter(|_| more)
+            .map(|(b, r)| (*b, r.key.clone()));
+        Batch {
+            zone: zone.to_string(),
+            records: matching.into_iter().map(|(_, r)| r.clone()).collect(),
+            next,
+        }
+    }
+}
+
+/// Bucket a key falls in.
+fn bucket(key: &str) -> usize {
+    let hash = ring::digest::digest(&SHA256, key.as_bytes());
+    usize::from(u16::from_be_bytes([hash.as_ref()[0], hash.as_ref()[1]])) % BUCKETS
+}
+
+/// Feed a record into a bucket digest, length-prefixing every field.
+///
+/// Hops and the signature are left out: a record's hop count differs
+/// between nodes holding the same update.
+fn hash_record(bucket: &mut Context, record: &Record) {
+    let value = record.value.as_deref();
+    let fields: [&[u8]; 7] = [
+        record.key.as_bytes(),
+        if value.is_some() { b"+" } else { b"-" },
+        value.unwrap_or_default().as_bytes(),
+        &record.serial.to_be_bytes(),
+        &record.timestamp.to_be_bytes(),
+        record.origin.as_bytes(),
+        &record.sequence.to_be_bytes(),
+    ];
+    for field in fields {
+        bucket.update(&u32::try_from(field.len()).unwrap_or(u32::MAX).to_be_bytes());
+        bucket.update(field);
+    }
+}
+
+fn finish(context: Context) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(context.finish().as_ref());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(key: &str, value: &str, serial: u32, timestamp: i64, origin: &str) -> Record {
+        Record {
+            key: key.into(),
+            value: Some(value.into()),
+            serial,
+            timestamp,
+            origin: origin.into(),
+            sequence: 0,
+            hops: 0,
+            signature: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_conflicts_resolve_deterministically() {
+        let base = record("1.2.3.4", "127.0.0.2", 100, 5_000, "node-a");
+        let higher_serial = record("1.2.3.4", "127.0.0.3", 101, 1_000, "node-a");
+        let later = record("1.2.3.4", "127.0.0.4", 100, 6_000, "node-a");
+        let tiebreak = record("1.2.3.4", "127.0.0.5", 100, 5_000, "node-b");
+
+        assert!(higher_serial.supersedes(&later));
+        assert!(later.supersedes(&base));
+        assert!(tiebreak.supersedes(&base));
+        assert!(!base.supersedes(&tiebreak));
+        assert!(!base.supersedes(&base));
+        // Serials compare under RFC 1982 arithmetic.
+        let wrapped = record("1.2.3.4", "127.0.0.6", 3, 0, "node-a");
+        assert!(wrapped.supersedes(&record("1.2.3.4", "x", u32::MAX - 3, 9_000, "z")));
+
+        // Merge order doesn't matter.
+        let mut forward = RecordStore::default();
+        let mut backward = RecordStore::default();
+        let versions = [&base, &later, &tiebreak, &higher_serial];
+        for r in versions {
+            forward.merge("bl", r.clone());
+        }
+        for r in versions.iter().rev() {
+            backward.merge("bl", (*r).clone());
+        }
+        assert_eq!(forward.get("bl", "1.2.3.4"), Some(&higher_serial));
+        assert_eq!(forward.digests(), backward.digests());
+
+        // The same update by a longer path loses, but digests agree.
+        let relayed = Record {
+            hops: 2,
+            ..higher_serial.clone()
+        };
+        assert!(higher_serial.supersedes(&relayed));
+        assert!(!relayed.supersedes(&higher_serial));
+        let mut far = RecordStore::default();
+        far.merge("bl", relayed);
+        assert_eq!(far.digests(), forward.digests());
+    }
+
+    #[test]
+    fn test_digests_locate_differences() {
+        let mut ours = RecordStore::default();
+        let mut theirs = RecordStore::default();
+        for i in 0..200 {
+            let r = record(&format!("10.0.0.{i}"), "127.0.0.2", 1, i, "node-a");
+            ours.merge("bl", r.clone());
+            theirs.merge("bl", r);
+        }
+        assert!(mismatches(&ours.digests(), &theirs.digests()).is_empty());
+
+        let changed = record("10.0.0.7", "127.0.0.3", 2, 0, "node-b");
+        theirs.merge("bl", changed.clone());
+        theirs.merge("sig", record("status", "ok", 1, 0, "node-b"));
+        let diff = mismatches(&ours.digests(), &theirs.digests());
+        assert_eq!(diff["bl"], [u16::try_from(bucket(&changed.key)).unwrap()]);
+        assert_eq!(diff["sig"].len(), 1);
+    }
+
+    #[test]
+    fn test_batches_resume_after_cursor() {
+        let mut store = RecordStore::default();
+        for i in 0..300 {
+            store.merge("bl", record(&format!("k{i:03}"), "v", 1, 0, "node-a"));
+        }
+        let all: Vec<u16> = (0..u16::try_from(BUCKETS).unwrap()).collect();
+
+        let mut seen = Vec::new();
+        let mut after = None;
+        loop {
+            let batch = store.batch("bl", &all, after.as_ref());
+            assert!(batch.records.len() <= MAX_BATCH);
+            seen.extend(batch.records.into_iter().map(|r| r.key));
+            match batch.next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), 300);
+        // Whole buckets go first, so a cut-off transfer leaves finished ones.
+        let order: Vec<_> = seen.iter().map(|k| (bucket(k), k)).collect();
+        assert!(order.windows(2).all(|w| w[0] < w[1]));
+    }
+}
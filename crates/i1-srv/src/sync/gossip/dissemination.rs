@@ -0,0 +1,208 @@
+//! Dissemination: spreading new records ahead of anti-entropy.
+//!
+//! A record written or first received here becomes a rumor, which rides
+//! along with the next few membership exchanges ([`TRANSMISSIONS`] of
+//! them). Every record carries its provenance: the origin node, its
+//! timestamp and per-origin sequence number, the origin's signature, and
+//! how many hops it has travelled.
+//!
+//! Loops and re-broadcast storms are kept in check three ways:
+//!
+//! - Each node remembers the last [`SEEN_CAPACITY`] updates it has seen,
+//!   by origin and sequence. A rumor seen before is never relayed again.
+//! - A record that has travelled [`MAX_HOPS`] hops is merged but not
+//!   relayed further; anti-entropy still carries it the rest of the way.
+//! - The rumor queue holds at most [`MAX_RUMORS`] records; the oldest are
+//!   dropped first and left to anti-entropy too.
+//!
+//! When the same update arrives by several paths, the copy with the fewest
+//! hops is kept (see [`Record::supersedes`]), so provenance reflects the
+//! most direct route.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use super::anti_entropy::{Record, MAX_BATCH};
+
+/// Hops after which a record is no longer relayed.
+pub const MAX_HOPS: u8 = 4;
+
+/// Updates remembered for duplicate suppression.
+pub const SEEN_CAPACITY: usize = 4096;
+
+/// Exchanges each rumor is sent in.
+pub const TRANSMISSIONS: u8 = 3;
+
+/// Most rumors queued, and most accepted in one exchange.
+pub const MAX_RUMORS: usize = MAX_BATCH;
+
+/// A record being disseminated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rumor {
+    /// Zone the record belongs to.
+    pub zone: String,
+    /// The record, provenance included.
+    pub record: Record,
+}
+
+/// Recently seen updates, by origin and sequence; the oldest are
+/// forgotten first.
+#[derive(Debug, Clone)]
+pub struct SeenCache {
+    capacity: usize,
+    order: VecDeque<(String, u64)>,
+    seen: HashSet<(String, u64)>,
+}
+
+impl Default for SeenCache {
+    fn default() -> Self {
+        Self::new(SEEN_CAPACITY)
+    }
+}
+
+impl SeenCache {
+    /// A cache remembering at most `capacity` updates.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Remember `record`'s update; returns whether it was new.
+    pub fn insert(&mut self, record: &Record) -> bool {
+        let id = (record.origin.clone(), record.sequence);
+        if self.seen.contains(&id) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(id.clone());
+        self.seen.insert(id);
+        true
+    }
+
+    /// Updates remembered.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether nothing has been seen yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// Rumors waiting to be sent, with their remaining transmissions.
+#[derive(Debug, Clone, Default)]
+pub struct RumorQueue {
+    pending: VecDeque<(Rumor, u8)>,
+}
+
+impl RumorQueue {
+    /// Queue `rumor`, dropping the oldest when full.
+    pub fn push(&mut self, rumor: Rumor) {
+        if self.pending.len() == MAX_RUMORS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((rumor, TRANSMISSIONS));
+    }
+
+    /// Rumors for the next exchange; each is dropped once it has been
+    /// sent [`TRANSMISSIONS`] times.
+    pub fn take(&mut self) -> Vec<Rumor> {
+        let rumors = self.pending.iter().map(|(r, _)| r.clone()).collect();
+        for (_, left) in &mut self.pending {
+            *left -= 1;
+        }
+        self.pending.retain(|(_, left)| *left > 0);
+        rumors
+    }
+
+    /// Rumors still queued.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no rumors are queued.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// What became of received rumors, as counted for metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RumorCounts {
+    /// New updates, merged and (within [`MAX_HOPS`]) relayed.
+    pub delivered: usize,
+    /// Updates seen before; never relayed again.
+    pub duplicate: usize,
+    /// Unseen updates older than what is already held, e.g. replays.
+    pub stale: usize,
+    /// Records whose origin isn't a known member, so their signature
+    /// can't be checked.
+    pub unverified: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(origin: &str, sequence: u64) -> Record {
+        Record {
+            key: format!("10.0.0.{sequence}"),
+            value: Some("127.0.0.2".into()),
+            serial: 1,
+            timestamp: 0,
+            origin: origin.into(),
+            sequence,
+            hops: 0,
+            signature: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_seen_cache_is_bounded() {
+        let mut seen = SeenCache::new(3);
+        assert!(seen.insert(&record("node-a", 1)));
+        assert!(!seen.insert(&record("node-a", 1)));
+        assert!(seen.insert(&record("node-b", 1)));
+        assert!(seen.insert(&record("node-a", 2)));
+        assert!(seen.insert(&record("node-a", 3)));
+        assert_eq!(seen.len(), 3);
+        // The oldest update was forgotten; the rest are still known.
+        assert!(!seen.insert(&record("node-a", 3)));
+        assert!(seen.insert(&record("node-a", 1)));
+    }
+
+    #[test]
+    fn test_rumors_stop_after_transmissions() {
+        let mut queue = RumorQueue::default();
+        queue.push(Rumor {
+            zone: "bl".into(),
+            record: record("node-a", 1),
+        });
+        for _ in 0..TRANSMISSIONS {
+            assert_eq!(queue.take().len(), 1);
+        }
+        assert!(queue.take().is_empty());
+
+        for sequence in 0..=u64::try_from(MAX_RUMORS).unwrap() {
+            queue.push(Rumor {
+                zone: "bl".into(),
+                record: record("node-a", sequence),
+            });
+        }
+        assert_eq!(queue.len(), MAX_RUMORS);
+        assert_eq!(queue.take()[0].record.sequence, 1);
+    }
+}
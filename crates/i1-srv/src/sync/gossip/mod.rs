@@ -0,0 +1,1419 @@
+//! SWIM gossip protocol for inter-node state synchronization.
+//!
+//! Nodes sync attack state via lightweight gossip (not heavy AXFR):
+//! - Each node maintains a local threat database
+//! - SWIM protocol disseminates changes: "IP X was blocked by node Y at time Z"
+//! - Nodes independently update their zone records from gossip state
+//! - No single master - all nodes are peers
+//!
+//! Gossip is authenticated end to end:
+//! - Peers talk over mutual TLS with their i1-ca node certificates
+//!   ([`transport`]); chains must verify against the shared root and
+//!   must not be revoked.
+//! - Before accepting anything from a peer, its certificate is checked
+//!   against the TLSA record published for it ([`crate::trust::mesh`]).
+//!   A peer that fails the check is quarantined.
+//! - Each node's membership announcement is signed with its node key and
+//!   carries its certificate chain, so gossip relayed through other nodes
+//!   can't be altered on the way. A peer relaying a forged announcement
+//!   is quarantined.
+//!
+//! Exchanges are push-pull: the connecting node sends every announcement
+//! it holds and gets the peer's in return. Anti-entropy rounds
+//! ([`anti_entropy`]) then reconcile the replicated records over the same
+//! connection.
+//!
+//! Replicated records carry their provenance: the origin node, its
+//! timestamp and sequence number, and the origin's signature, which every
+//! receiver checks against the origin's announced certificate, plus a hop
+//! count. New records also ride along with membership exchanges as rumors
+//! ([`dissemination`]), which are deduplicated and hop-limited so they
+//! don't circulate forever. A peer relaying a forged record is quarantined
+//! like one relaying a forged announcement.
+
+// TODO: Phase 3 - SWIM failure detection and threat state dissemination
+// on top of the authenticated membership exchange.
+
+pub mod anti_entropy;
+pub mod dissemination;
+pub mod transport;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::metrics::{Collector, Exposition, Kind};
+use crate::node::identity::NodeIdentity;
+use crate::trust::mesh::{self, PeerVerifier};
+use anti_entropy::{
+    Batch, Cursor, Record, RecordStore, ZoneDigest, MAX_BATCH, MAX_BATCHES_PER_ROUND,
+};
+use dissemination::{Rumor, RumorCounts, RumorQueue, SeenCache, MAX_HOPS, MAX_RUMORS};
+use transport::{read_frame, write_frame, GossipTls};
+
+/// Upper bound for one complete exchange, handshake included.
+const EXCHANGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most requests an initiator may make after the membership exchange:
+/// two digest requests plus a full round of pulls and pushes.
+const MAX_REQUESTS: usize = 2 * MAX_BATCHES_PER_ROUND + 2;
+
+/// Domain separation for membership signatures.
+const MEMBER_CONTEXT: &str = "i1-gossip-member/1";
+
+/// Domain separation for record signatures.
+const RECORD_CONTEXT: &str = "i1-gossip-record/1";
+
+/// A node in the mesh.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    /// Node name, as in its certificate (e.g. `node1.srv.i1.is`).
+    pub name: String,
+    /// Gossip address.
+    pub addr: SocketAddr,
+    /// Bumped on every restart; the highest incarnation wins.
+    pub incarnation: u64,
+}
+
+/// A member's self-announcement, signed with its node key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    /// The announced member.
+    pub member: Member,
+    /// Signer's certificate chain (DER, leaf first).
+    pub chain: Vec<Vec<u8>>,
+    /// ECDSA P-256 signature over the member.
+    pub signature: Vec<u8>,
+}
+
+impl Announcement {
+    /// Sign `member` with the node's key (ECDSA P-256, as i1-ca issues).
+    pub fn sign(member: Member, identity: &NodeIdentity) -> crate::Result<Self> {
+        let signature = sign(&signing_key(identity)?, &signed_bytes(&member))?;
+        Ok(Self {
+            member,
+            chain: identity.chain().iter().map(|c| c.to_vec()).collect(),
+            signature,
+        })
+    }
+
+    /// Check that the chain is trusted, the leaf is issued to the
+    /// announced name, and the signature covers this exact member.
+    pub fn verify(&self, tls: &GossipTls) -> crate::Result<()> {
+        let chain: Vec<CertificateDer<'_>> = self
+            .chain
+            .iter()
+            .map(|der| CertificateDer::from(der.as_slice()))
+            .collect();
+        tls.verify_chain(&chain)?;
+        let leaf = &chain[0];
+        check_name(leaf, &self.member.name)?;
+        webpki::EndEntityCert::try_from(leaf)
+            .and_then(|cert| {
+                cert.verify_signature(
+                    webpki::ring::ECDSA_P256_SHA256,
+                    &signed_bytes(&self.member),
+                    &self.signature,
+                )
+            })
+            .map_err(|e| {
+                crate::SrvError::Trust(format!(
+                    "bad signature on announcement for {}: {e}",
+                    self.member.name
+                ))
+            })
+    }
+
+    fn leaf(&self) -> CertificateDer<'_> {
+        CertificateDer::from(self.chain.first().map_or(&[][..], Vec::as_slice))
+    }
+}
+
+/// One side of a push-pull exchange.
+#[derive(Debug, Serialize, Deserialize)]
+struct Push {
+    /// Sender's node name; must match its TLS certificate.
+    from: String,
+    /// Every announcement the sender holds, its own included.
+    announcements: Vec<Announcement>,
+    /// Records being disseminated, at most [`MAX_RUMORS`].
+    rumors: Vec<Rumor>,
+}
+
+/// What the initiator asks for once membership is exchanged.
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    /// Send your zone digests.
+    Digests,
+    /// Send records of `zone` in `buckets`, continuing after `after`.
+    Pull {
+        zone: String,
+        buckets: Vec<u16>,
+        after: Option<Cursor>,
+    },
+    /// Merge these records.
+    Push(Batch),
+    /// End of exchange.
+    Done,
+}
+
+/// Answer to a [`Request`].
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    Digests(BTreeMap<String, ZoneDigest>),
+    Batch(Batch),
+    Ack,
+}
+
+/// Outcome of one anti-entropy round.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Records that were new or newer here.
+    pub received: usize,
+    /// Records sent to the peer.
+    pub sent: usize,
+    /// Every zone digest matched the peer's at the end of the round.
+    pub in_sync: bool,
+}
+
+/// Membership, quarantine and replicated records, shared by the listener
+/// and outgoing exchanges.
+#[derive(Default)]
+struct State {
+    members: BTreeMap<String, Announcement>,
+    /// Configured peers, gossiped with until membership says otherwise.
+    seeds: Vec<(String, SocketAddr)>,
+    /// Certificate fingerprint (hex SHA-256) -> reason.
+    quarantine: BTreeMap<String, String>,
+    records: RecordStore,
+    /// Updates seen, so rumors aren't relayed twice.
+    seen: SeenCache,
+    rumors: RumorQueue,
+    rumor_counts: RumorCounts,
+    /// Sequence number of this node's last write.
+    sequence: u64,
+    /// Peer -> zone -> whether digests matched after the last round.
+    agreement: BTreeMap<String, BTreeMap<String, bool>>,
+    rounds_ok: usize,
+    rounds_failed: usize,
+    records_received: usize,
+    records_sent: usize,
+}
+
+/// A gossip participant.
+///
+/// Clones share state, so one can serve while another gossips.
+#[derive(Clone)]
+pub struct GossipNode {
+    own: Arc<Announcement>,
+    key: Arc<EcdsaKeyPair>,
+    tls: Arc<GossipTls>,
+    verifier: Arc<PeerVerifier>,
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for GossipNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GossipNode")
+            .field("name", &self.own.member.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GossipNode {
+    /// Join the mesh as `name`, reachable at `addr`.
+    pub fn new(
+        identity: &NodeIdentity,
+        name: &str,
+        addr: SocketAddr,
+        tls: GossipTls,
+        verifier: Arc<PeerVerifier>,
+    ) -> crate::Result<Self> {
+        let own = Announcement::sign(
+            Member {
+                name: name.to_string(),
+                addr,
+                incarnation: u64::try_from(chrono::Utc::now().timestamp_millis()).unwrap_or(0),
+            },
+            identity,
+        )?;
+        // Catch a node certificate issued to some other name up front.
+        own.verify(&tls)?;
+        let key = signing_key(identity)?;
+
+        let mut state = State::default();
+        state.members.insert(name.to_string(), own.clone());
+        Ok(Self {
+            own: Arc::new(own),
+            key: Arc::new(key),
+            tls: Arc::new(tls),
+            verifier,
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// This node's name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.own.member.name
+    }
+
+    /// Known members, this node included, by name.
+    #[must_use]
+    pub fn members(&self) -> Vec<Member> {
+        self.lock()
+            .members
+            .values()
+            .map(|a| a.member.clone())
+            .collect()
+    }
+
+    /// The configured seed peers.
+    #[must_use]
+    pub fn seeds(&self) -> Vec<(String, SocketAddr)> {
+        self.lock().seeds.clone()
+    }
+
+    /// Gossip with `seeds` from the next round on, e.g. after a config
+    /// reload; members already learned stay known.
+    pub fn set_seeds(&self, seeds: Vec<(String, SocketAddr)>) {
+        self.lock().seeds = seeds;
+    }
+
+    /// Quarantined certificates (hex SHA-256) and why.
+    #[must_use]
+    pub fn quarantined(&self) -> Vec<(String, String)> {
+        self.lock()
+            .quarantine
+            .iter()
+            .map(|(fp, reason)| (fp.clone(), reason.clone()))
+            .collect()
+    }
+
+    /// Answer gossip from peers until the task is dropped.
+    pub async fn serve(self, listener: TcpListener) {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!(error = %e, "gossip accept failed");
+                    continue;
+                }
+            };
+            let node = self.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(EXCHANGE_TIMEOUT, node.answer(stream)).await {
+                    Ok(Ok(from)) => debug!(%peer, %from, "gossip exchange answered"),
+                    Ok(Err(e)) => warn!(%peer, error = %e, "rejected gossip peer"),
+                    Err(_) => warn!(%peer, "gossip exchange timed out"),
+                }
+            });
+        }
+    }
+
+    /// Push-pull with the peer `name` at `addr`; returns how many
+    /// announcements were new or newer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the peer can't be reached, its certificate isn't trusted or
+    /// doesn't match its TLSA record, or it sends forged announcements.
+    pub async fn exchange(&self, name: &str, addr: SocketAddr) -> crate::Result<usize> {
+        let exchange = async {
+            let (mut tls, _, updated) = self.connect(name, addr).await?;
+            write_frame(&mut tls, &Request::Done).await?;
+            Ok(updated)
+        };
+        tokio::time::timeout(EXCHANGE_TIMEOUT, exchange)
+            .await
+            .map_err(|_| crate::SrvError::Sync(format!("gossip with {name} timed out")))?
+    }
+
+    /// Run an anti-entropy round with the peer `name` at `addr`: exchange
+    /// membership, then reconcile the zones whose digests differ.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`Self::exchange`] does, or if the peer breaks the protocol.
+    pub async fn sync_with(&self, name: &str, addr: SocketAddr) -> crate::Result<SyncReport> {
+        let round = async {
+            let (mut tls, leaf, _) = self.connect(name, addr).await?;
+            let report = self.reconcile(&mut tls, name, &leaf).await?;
+            write_frame(&mut tls, &Request::Done).await?;
+            Ok(report)
+        };
+        let result = tokio::time::timeout(EXCHANGE_TIMEOUT, round)
+            .await
+            .unwrap_or_else(|_| {
+                Err(crate::SrvError::Sync(format!(
+                    "anti-entropy with {name} timed out"
+                )))
+            });
+        let mut state = self.lock();
+        if result.is_ok() {
+            state.rounds_ok += 1;
+        } else {
+            state.rounds_failed += 1;
+        }
+        drop(state);
+        result
+    }
+
+    /// Write a record to `zone` as this node; a `None` value deletes it.
+    /// Returns whether it replaced what was there, in which case it is
+    /// also disseminated.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the record can't be signed.
+    pub fn write(
+        &self,
+        zone: &str,
+        key: &str,
+        value: Option<String>,
+        serial: u32,
+    ) -> crate::Result<bool> {
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let mut state = self.lock();
+        // Seeded from the clock, so sequences keep rising across restarts.
+        state.sequence = (state.sequence + 1).max(u64::try_from(timestamp).unwrap_or(0));
+        let mut record = Record {
+            key: key.to_string(),
+            value,
+            serial,
+            timestamp,
+            origin: self.name().to_string(),
+            sequence: state.sequence,
+            hops: 0,
+            signature: Vec::new(),
+        };
+        record.signature = sign(&self.key, &record_bytes(zone, &record))?;
+        state.seen.insert(&record);
+        let written = state.records.merge(zone, record.clone());
+        if written {
+            state.rumors.push(Rumor {
+                zone: zone.to_string(),
+                record,
+            });
+        }
+        drop(state);
+        Ok(written)
+    }
+
+    /// Every record in `zone`, deletions included, in key order.
+    #[must_use]
+    pub fn records(&self, zone: &str) -> Vec<Record> {
+        self.lock().records.records(zone)
+    }
+
+    /// Digest summaries of the replicated zones.
+    #[must_use]
+    pub fn digests(&self) -> BTreeMap<String, ZoneDigest> {
+        self.lock().records.digests()
+    }
+
+    /// Serve on `listener`, gossip with one peer every `interval`, and run
+    /// anti-entropy with a random peer every `anti_entropy`.
+    ///
+    /// Peers come from `seeds` (see [`set_seeds`](Self::set_seeds)) and
+    /// then from members learned from them.
+    /// The first anti-entropy round runs straight away, so a node that
+    /// joins late catches up without waiting for dissemination.
+    pub async fn run(
+        self,
+        listener: TcpListener,
+        seeds: Vec<(String, SocketAddr)>,
+        interval: Duration,
+        anti_entropy: Duration,
+    ) {
+        info!(node = %self.name(), "gossip listener running");
+        self.set_seeds(seeds);
+        tokio::spawn(self.clone().serve(listener));
+
+        let mut gossip = tokio::time::interval(interval);
+        let mut repair = tokio::time::interval(anti_entropy);
+        let mut round = 0usize;
+        loop {
+            tokio::select! {
+                _ = gossip.tick() => {
+                    let targets = self.targets();
+                    if let Some((name, addr)) = targets.get(round % targets.len().max(1)) {
+                        round = round.wrapping_add(1);
+                        match self.exchange(name, *addr).await {
+                            Ok(updated) => debug!(peer = %name, updated, "gossip round"),
+                            Err(e) => warn!(peer = %name, error = %e, "gossip round failed"),
+                        }
+                    }
+                }
+                _ = repair.tick() => {
+                    let targets = self.targets();
+                    if let Some((name, addr)) = random_index(targets.len()).and_then(|i| targets.get(i)) {
+                        match self.sync_with(name, *addr).await {
+                            Ok(report) => debug!(
+                                peer = %name,
+                                received = report.received,
+                                sent = report.sent,
+                                in_sync = report.in_sync,
+                                "anti-entropy round"
+                            ),
+                            Err(e) => warn!(peer = %name, error = %e, "anti-entropy round failed"),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Seeds and known members other than this node, by name.
+    fn targets(&self) -> Vec<(String, SocketAddr)> {
+        let state = self.lock();
+        let mut targets: BTreeMap<String, SocketAddr> = state.seeds.iter().cloned().collect();
+        targets.extend(
+            state
+                .members
+                .values()
+                .map(|a| (a.member.name.clone(), a.member.addr)),
+        );
+        drop(state);
+        targets.remove(self.name());
+        targets.into_iter().collect()
+    }
+
+    /// Connect to a peer, check it, and exchange membership and rumors;
+    /// returns the connection, the peer's certificate and how many
+    /// announcements were new or newer.
+    async fn connect(
+        &self,
+        name: &str,
+        addr: SocketAddr,
+    ) -> crate::Result<(
+        tokio_rustls::client::TlsStream<TcpStream>,
+        CertificateDer<'static>,
+        usize,
+    )> {
+        let server_name = ServerName::try_from(name.to_string())
+            .map_err(|e| crate::SrvError::Sync(format!("invalid peer name {name}: {e}")))?;
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| crate::SrvError::Sync(format!("connect {addr}: {e}")))?;
+        let mut tls = self
+            .tls
+            .connector()
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| crate::SrvError::Trust(format!("TLS with {name}: {e}")))?;
+
+        let leaf = peer_leaf(tls.get_ref().1.peer_certificates())?;
+        self.admit(name, addr.port(), &leaf).await?;
+
+        write_frame(&mut tls, &self.push()).await?;
+        let reply: Push = read_frame(&mut tls).await?;
+        if reply.from != name {
+            return Err(self.quarantine(&leaf, format!("{name} answered as {}", reply.from)));
+        }
+        let updated = self.merge(&leaf, reply.announcements)?;
+        self.receive(&leaf, reply.rumors)?;
+        Ok((tls, leaf, updated))
+    }
+
+    /// Pull and push records in mismatched buckets, then compare digests
+    /// once more to see whether the round left both sides in agreement.
+    async fn reconcile<S>(
+        &self,
+        tls: &mut S,
+        peer: &str,
+        leaf: &CertificateDer<'static>,
+    ) -> crate::Result<SyncReport>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let theirs = request_digests(tls, peer).await?;
+        let mismatched = anti_entropy::mismatches(&self.digests(), &theirs);
+        let mut report = SyncReport::default();
+
+        let mut budget = MAX_BATCHES_PER_ROUND;
+        for (zone, buckets) in &mismatched {
+            let mut after = None;
+            while budget > 0 {
+                budget -= 1;
+                let pull = Request::Pull {
+                    zone: zone.clone(),
+                    buckets: buckets.clone(),
+                    after: after.take(),
+                };
+                write_frame(tls, &pull).await?;
+                let Response::Batch(batch) = read_frame(tls).await? else {
+                    return Err(unexpected(peer));
+                };
+                if batch.zone != *zone || batch.records.len() > MAX_BATCH {
+                    return Err(crate::SrvError::Sync(format!(
+                        "{peer} sent an invalid batch"
+                    )));
+                }
+                after.clone_from(&batch.next);
+                report.received += self.absorb(leaf, batch)?;
+                if after.is_none() {
+                    break;
+                }
+            }
+        }
+
+        let mut budget = MAX_BATCHES_PER_ROUND;
+        for (zone, buckets) in &mismatched {
+            let mut after: Option<Cursor> = None;
+            while budget > 0 {
+                let batch = self.lock().records.batch(zone, buckets, after.as_ref());
+                if batch.records.is_empty() {
+                    break;
+                }
+                budget -= 1;
+                report.sent += batch.records.len();
+                self.lock().records_sent += batch.records.len();
+                after.clone_from(&batch.next);
+                write_frame(tls, &Request::Push(batch)).await?;
+                let Response::Ack = read_frame(tls).await? else {
+                    return Err(unexpected(peer));
+                };
+                if after.is_none() {
+                    break;
+                }
+            }
+        }
+
+        let ours = self.digests();
+        let theirs = request_digests(tls, peer).await?;
+        let empty = ZoneDigest::empty();
+        let agreement: BTreeMap<String, bool> = ours
+            .keys()
+            .chain(theirs.keys())
+            .map(|zone| {
+                let ours = ours.get(zone).unwrap_or(&empty);
+                let theirs = theirs.get(zone).unwrap_or(&empty);
+                (zone.clone(), ours.root == theirs.root)
+            })
+            .collect();
+        report.in_sync = agreement.values().all(|&agrees| agrees);
+        self.lock().agreement.insert(peer.to_string(), agreement);
+        Ok(report)
+    }
+
+    /// Handle one incoming exchange; returns the peer's name.
+    async fn answer(&self, stream: TcpStream) -> crate::Result<String> {
+        let mut tls = self
+            .tls
+            .acceptor()
+            .accept(stream)
+            .await
+            .map_err(|e| crate::SrvError::Trust(format!("TLS handshake: {e}")))?;
+        let leaf = peer_leaf(tls.get_ref().1.peer_certificates())?;
+
+        let push: Push = read_frame(&mut tls).await?;
+        check_name(&leaf, &push.from)?;
+        let port = push
+            .announcements
+            .iter()
+            .find(|a| a.member.name == push.from)
+            .map(|a| a.member.addr.port())
+            .ok_or_else(|| {
+                crate::SrvError::Sync(format!("{} sent no announcement of its own", push.from))
+            })?;
+        self.admit(&push.from, port, &leaf).await?;
+
+        self.merge(&leaf, push.announcements)?;
+        // Take our rumors first so the peer's aren't echoed straight back.
+        let reply = self.push();
+        self.receive(&leaf, push.rumors)?;
+        write_frame(&mut tls, &reply).await?;
+        self.respond(&mut tls, &push.from, &leaf).await?;
+        Ok(push.from)
+    }
+
+    /// Serve an initiator's requests until it is done.
+    async fn respond<S>(
+        &self,
+        tls: &mut S,
+        peer: &str,
+        leaf: &CertificateDer<'static>,
+    ) -> crate::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        for _ in 0..=MAX_REQUESTS {
+            let response = match read_frame(tls).await? {
+                Request::Done => return Ok(()),
+                Request::Digests => Response::Digests(self.digests()),
+                Request::Pull {
+                    zone,
+                    buckets,
+                    after,
+                } => {
+                    let mut state = self.lock();
+                    let batch = state.records.batch(&zone, &buckets, after.as_ref());
+                    state.records_sent += batch.records.len();
+                    drop(state);
+                    Response::Batch(batch)
+                }
+                Request::Push(batch) => {
+                    if batch.records.len() > MAX_BATCH {
+                        return Err(crate::SrvError::Sync(format!(
+                            "{peer} pushed an oversized batch"
+                        )));
+                    }
+                    self.absorb(leaf, batch)?;
+                    Response::Ack
+                }
+            };
+            write_frame(tls, &response).await?;
+        }
+        Err(crate::SrvError::Sync(format!(
+            "{peer} made more than {MAX_REQUESTS} requests"
+        )))
+    }
+
+    /// Verify and merge a batch of records from `relayer`; returns how
+    /// many were new or newer. Repaired records aren't disseminated.
+    fn absorb(&self, relayer: &CertificateDer<'static>, batch: Batch) -> crate::Result<usize> {
+        let zone = batch.zone;
+        let rumors = batch
+            .records
+            .into_iter()
+            .map(|record| Rumor {
+                zone: zone.clone(),
+                record,
+            })
+            .collect();
+        let rumors = self.verified(relayer, rumors)?;
+
+        let mut state = self.lock();
+        let mut merged = 0;
+        for Rumor { zone, mut record } in rumors {
+            record.hops = record.hops.saturating_add(1);
+            state.seen.insert(&record);
+            if state.records.merge(&zone, record) {
+                merged += 1;
+            }
+        }
+        state.records_received += merged;
+        drop(state);
+        Ok(merged)
+    }
+
+    /// Verify and merge rumors from `relayer`; returns how many updates
+    /// were new here. Those are passed on unless they've gone
+    /// [`MAX_HOPS`] hops.
+    fn receive(
+        &self,
+        relayer: &CertificateDer<'static>,
+        rumors: Vec<Rumor>,
+    ) -> crate::Result<usize> {
+        if rumors.len() > MAX_RUMORS {
+            return Err(crate::SrvError::Sync(format!(
+                "peer sent more than {MAX_RUMORS} rumors"
+            )));
+        }
+        let rumors = self.verified(relayer, rumors)?;
+
+        let mut state = self.lock();
+        let mut delivered = 0;
+        for mut rumor in rumors {
+            rumor.record.hops = rumor.record.hops.saturating_add(1);
+            let new = state.seen.insert(&rumor.record);
+            // A duplicate may still bring a shorter path.
+            let merged = state.records.merge(&rumor.zone, rumor.record.clone());
+            if !new {
+                state.rumor_counts.duplicate += 1;
+            } else if !merged {
+                state.rumor_counts.stale += 1;
+            } else {
+                delivered += 1;
+                if rumor.record.hops < MAX_HOPS {
+                    state.rumors.push(rumor);
+                }
+            }
+        }
+        state.rumor_counts.delivered += delivered;
+        drop(state);
+        Ok(delivered)
+    }
+
+    /// Check each record's signature against its origin's certificate.
+    ///
+    /// Records from origins that aren't members (or were quarantined) are
+    /// dropped, as they can't be checked. One forged record rejects the
+    /// lot and quarantines the peer that relayed it.
+    fn verified(
+        &self,
+        relayer: &CertificateDer<'static>,
+        rumors: Vec<Rumor>,
+    ) -> crate::Result<Vec<Rumor>> {
+        let mut state = self.lock();
+        let mut verified = Vec::with_capacity(rumors.len());
+        let mut forged = None;
+        for rumor in rumors {
+            let Some(origin) = state.members.get(&rumor.record.origin) else {
+                state.rumor_counts.unverified += 1;
+                continue;
+            };
+            if let Err(e) = verify_record(&origin.leaf(), &rumor) {
+                forged = Some(e);
+                break;
+            }
+            verified.push(rumor);
+        }
+        drop(state);
+        forged.map_or(Ok(verified), |e| {
+            Err(self.quarantine(relayer, format!("relayed a forged record: {e}")))
+        })
+    }
+
+    /// Refuse quarantined peers and check a peer against its TLSA record.
+    async fn admit(
+        &self,
+        name: &str,
+        port: u16,
+        leaf: &CertificateDer<'static>,
+    ) -> crate::Result<()> {
+        let fingerprint = fingerprint(leaf);
+        if let Some(reason) = self.lock().quarantine.get(&fingerprint) {
+            return Err(crate::SrvError::Trust(format!(
+                "{name} is quarantined: {reason}"
+            )));
+        }
+        if let Err(e) = self.verifier.verify_cert(name, port, leaf).await {
+            return Err(match e {
+                crate::SrvError::Trust(reason) => self.quarantine(leaf, reason),
+                other => other,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verify and merge a peer's announcements.
+    ///
+    /// One forged announcement rejects the batch and quarantines the peer
+    /// that relayed it.
+    fn merge(
+        &self,
+        relayer: &CertificateDer<'static>,
+        announcements: Vec<Announcement>,
+    ) -> crate::Result<usize> {
+        if let Some(e) = announcements.iter().find_map(|a| a.verify(&self.tls).err()) {
+            return Err(self.quarantine(relayer, format!("relayed a forged announcement: {e}")));
+        }
+
+        let mut state = self.lock();
+        let mut updated = 0;
+        for announcement in announcements {
+            if state
+                .quarantine
+                .contains_key(&fingerprint(&announcement.leaf()))
+            {
+                continue;
+            }
+            let newer = state
+                .members
+                .get(&announcement.member.name)
+                .map_or(true, |known| {
+                    announcement.member.incarnation > known.member.incarnation
+                });
+            if newer {
+                state
+                    .members
+                    .insert(announcement.member.name.clone(), announcement);
+                updated += 1;
+            }
+        }
+        drop(state);
+        Ok(updated)
+    }
+
+    /// Quarantine a certificate, drop members announced under it, and
+    /// return the error to report.
+    fn quarantine(&self, cert: &CertificateDer<'_>, reason: String) -> crate::SrvError {
+        let banned = fingerprint(cert);
+        warn!(fingerprint = %banned, %reason, "quarantining gossip peer");
+        let mut state = self.lock();
+        state
+            .members
+            .retain(|_, a| fingerprint(&a.leaf()) != banned);
+        state.quarantine.insert(banned, reason.clone());
+        drop(state);
+        crate::SrvError::Trust(reason)
+    }
+
+    fn push(&self) -> Push {
+        let mut state = self.lock();
+        let push = Push {
+            from: self.name().to_string(),
+            announcements: state.members.values().cloned().collect(),
+            rumors: state.rumors.take(),
+        };
+        drop(state);
+        push
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The bytes a member's signature covers.
+fn signed_bytes(member: &Member) -> Vec<u8> {
+    format!(
+        "{MEMBER_CONTEXT}\n{}\n{}\n{}",
+        member.name, member.addr, member.incarnation
+    )
+    .into_bytes()
+}
+
+/// The bytes a record's signature covers: everything but its hop count.
+fn record_bytes(zone: &str, record: &Record) -> Vec<u8> {
+    serde_json::to_vec(&(
+        RECORD_CONTEXT,
+        zone,
+        &record.key,
+        &record.value,
+        record.serial,
+        record.timestamp,
+        &record.origin,
+        record.sequence,
+    ))
+    .unwrap_or_default()
+}
+
+/// Check that `origin` (its origin's leaf certificate) signed `rumor`.
+fn verify_record(origin: &CertificateDer<'_>, rumor: &Rumor) -> crate::Result<()> {
+    let record = &rumor.record;
+    webpki::EndEntityCert::try_from(origin)
+        .and_then(|cert| {
+            cert.verify_signature(
+                webpki::ring::ECDSA_P256_SHA256,
+                &record_bytes(&rumor.zone, record),
+                &record.signature,
+            )
+        })
+        .map_err(|e| {
+            crate::SrvError::Trust(format!(
+                "bad signature on {} record {} from {}: {e}",
+                rumor.zone, record.key, record.origin
+            ))
+        })
+}
+
+/// The node's ECDSA P-256 key, as i1-ca issues.
+fn signing_key(identity: &NodeIdentity) -> crate::Result<EcdsaKeyPair> {
+    let PrivateKeyDer::Pkcs8(key) = identity.key() else {
+        return Err(crate::SrvError::Identity(
+            "gossip signing needs a PKCS#8 node key".into(),
+        ));
+    };
+    EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_ASN1_SIGNING,
+        key.secret_pkcs8_der(),
+        &SystemRandom::new(),
+    )
+    .map_err(|e| crate::SrvError::Identity(format!("unsupported node key: {e}")))
+}
+
+fn sign(key: &EcdsaKeyPair, bytes: &[u8]) -> crate::Result<Vec<u8>> {
+    key.sign(&SystemRandom::new(), bytes)
+        .map(|signature| signature.as_ref().to_vec())
+        .map_err(|e| crate::SrvError::Identity(format!("signing failed: {e}")))
+}
+
+/// Check that `leaf` is issued to `name`.
+fn check_name(leaf: &CertificateDer<'_>, name: &str) -> crate::Result<()> {
+    let server_name = ServerName::try_from(name)
+        .map_err(|e| crate::SrvError::Trust(format!("invalid node name {name}: {e}")))?;
+    webpki::EndEntityCert::try_from(leaf)
+        .and_then(|cert| cert.verify_is_valid_for_subject_name(&server_name))
+        .map_err(|e| crate::SrvError::Trust(format!("certificate is not for {name}: {e}")))
+}
+
+fn peer_leaf(certs: Option<&[CertificateDer<'static>]>) -> crate::Result<CertificateDer<'static>> {
+    certs
+        .and_then(<[_]>::first)
+        .cloned()
+        .ok_or_else(|| crate::SrvError::Trust("peer presented no certificate".into()))
+}
+
+/// Ask the peer for its zone digests.
+async fn request_digests<S>(tls: &mut S, peer: &str) -> crate::Result<BTreeMap<String, ZoneDigest>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    write_frame(tls, &Request::Digests).await?;
+    match read_frame(tls).await? {
+        Response::Digests(digests) => Ok(digests),
+        _ => Err(unexpected(peer)),
+    }
+}
+
+fn unexpected(peer: &str) -> crate::SrvError {
+    crate::SrvError::Sync(format!("unexpected anti-entropy response from {peer}"))
+}
+
+/// A random index below `len`, if there is one.
+fn random_index(len: usize) -> Option<usize> {
+    let mut bytes = [0u8; std::mem::size_of::<usize>()];
+    SystemRandom::new().fill(&mut bytes).ok()?;
+    (len > 0).then(|| usize::from_ne_bytes(bytes) % len)
+}
+
+/// Hex SHA-256 of a certificate.
+fn fingerprint(cert: &CertificateDer<'_>) -> String {
+    mesh::tlsa_hash(cert)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+impl Collector for GossipNode {
+    fn collect(&self, out: &mut Exposition) {
+        let state = self.lock();
+        out.family(
+            "i1_gossip_members",
+            Kind::Gauge,
+            "Known gossip members, this node included.",
+        )
+        .sample("i1_gossip_members", &[], state.members.len());
+        out.family(
+            "i1_gossip_quarantined",
+            Kind::Gauge,
+            "Quarantined peer certificates.",
+        )
+        .sample("i1_gossip_quarantined", &[], state.quarantine.len());
+
+        let zones = state.records.zones();
+        out.family(
+            "i1_gossip_zone_records",
+            Kind::Gauge,
+            "Replicated records per zone, deletions included.",
+        );
+        for (zone, _, count) in &zones {
+            out.sample("i1_gossip_zone_records", &[("zone", zone)], count);
+        }
+        out.family(
+            "i1_gossip_zone_serial",
+            Kind::Gauge,
+            "Highest record serial per zone.",
+        );
+        for (zone, serial, _) in &zones {
+            out.sample("i1_gossip_zone_serial", &[("zone", zone)], serial);
+        }
+        out.family(
+            "i1_gossip_zone_in_sync",
+            Kind::Gauge,
+            "Whether a zone's digest matched the peer's after the last anti-entropy round.",
+        );
+        for (peer, zones) in &state.agreement {
+            for (zone, agrees) in zones {
+                out.sample(
+                    "i1_gossip_zone_in_sync",
+                    &[("peer", peer), ("zone", zone)],
+                    u8::from(*agrees),
+                );
+            }
+        }
+
+        out.family(
+            "i1_gossip_anti_entropy_rounds_total",
+            Kind::Counter,
+            "Anti-entropy rounds started by this node.",
+        )
+        .sample(
+            "i1_gossip_anti_entropy_rounds_total",
+            &[("result", "ok")],
+            state.rounds_ok,
+        )
+        .sample(
+            "i1_gossip_anti_entropy_rounds_total",
+            &[("result", "error")],
+            state.rounds_failed,
+        );
+        out.family(
+            "i1_gossip_anti_entropy_records_total",
+            Kind::Counter,
+            "Records transferred by anti-entropy.",
+        )
+        .sample(
+            "i1_gossip_anti_entropy_records_total",
+            &[("direction", "received")],
+            state.records_received,
+        )
+        .sample(
+            "i1_gossip_anti_entropy_records_total",
+            &[("direction", "sent")],
+            state.records_sent,
+        );
+
+        let counts = state.rumor_counts;
+        out.family(
+            "i1_gossip_rumors_total",
+            Kind::Counter,
+            "Disseminated records received, by outcome (unverified includes repaired ones).",
+        );
+        for (result, count) in [
+            ("delivered", counts.delivered),
+            ("duplicate", counts.duplicate),
+            ("stale", counts.stale),
+            ("unverified", counts.unverified),
+        ] {
+            out.sample("i1_gossip_rumors_total", &[("result", result)], count);
+        }
+        out.family(
+            "i1_gossip_rumors_queued",
+            Kind::Gauge,
+            "Records waiting to be disseminated.",
+        )
+        .sample("i1_gossip_rumors_queued", &[], state.rumors.len());
+        drop(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::identity::{CERT_FILE, KEY_FILE};
+    use crate::trust::mesh::{PeerVerifier, PinnedTlsa};
+    use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+    use std::net::Ipv4Addr;
+    use tempfile::TempDir;
+
+    /// A stand-in for an i1-ca root.
+    struct Ca {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    impl Ca {
+        fn new() -> Self {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            Self {
+                cert: params.self_signed(&key).unwrap(),
+                key,
+            }
+        }
+
+        fn root(&self) -> CertificateDer<'static> {
+            self.cert.der().clone()
+        }
+
+        /// Issue a node certificate, as i1-ca's `sign_node` does.
+        fn issue(&self, name: &str) -> NodeIdentity {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+            params.extended_key_usages = vec![
+                ExtendedKeyUsagePurpose::ServerAuth,
+                ExtendedKeyUsagePurpose::ClientAuth,
+            ];
+            let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+            let dir = TempDir::new().unwrap();
+            std::fs::write(dir.path().join(CERT_FILE), cert.pem()).unwrap();
+            std::fs::write(dir.path().join(KEY_FILE), key.serialize_pem()).unwrap();
+            NodeIdentity::load_dir(dir.path()).unwrap()
+        }
+    }
+
+    /// Start a node serving on loopback.
+    async fn start(
+        identity: &NodeIdentity,
+        name: &str,
+        roots: &[&Ca],
+        tlsa: &Arc<PinnedTlsa>,
+    ) -> (GossipNode, SocketAddr) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tls = GossipTls::new(
+            identity,
+            roots.iter().map(|ca| ca.root()).collect(),
+            Vec::new(),
+        )
+        .unwrap();
+        let verifier = Arc::new(PeerVerifier::new(tlsa.clone(), false).unwrap());
+        let node = GossipNode::new(identity, name, addr, tls, verifier).unwrap();
+        tokio::spawn(node.clone().serve(listener));
+        (node, addr)
+    }
+
+    fn names(node: &GossipNode) -> Vec<String> {
+        node.members().into_iter().map(|m| m.name).collect()
+    }
+
+    const A: &str = "node-a.srv.i1.is";
+    const B: &str = "node-b.srv.i1.is";
+    const C: &str = "node-c.srv.i1.is";
+
+    #[tokio::test]
+    async fn test_node_from_foreign_root_is_excluded() {
+        let mesh_ca = Ca::new();
+        let rogue_ca = Ca::new();
+        let (id_a, id_b, id_c) = (mesh_ca.issue(A), mesh_ca.issue(B), rogue_ca.issue(C));
+
+        // Even with a matching TLSA record, C's chain keeps it out.
+        let mut pins = PinnedTlsa::default();
+        pins.pin(A, &id_a.chain()[0]);
+        pins.pin(B, &id_b.chain()[0]);
+        pins.pin(C, &id_c.chain()[0]);
+        let pins = Arc::new(pins);
+
+        let (a, a_addr) = start(&id_a, A, &[&mesh_ca], &pins).await;
+        let (b, b_addr) = start(&id_b, B, &[&mesh_ca], &pins).await;
+        let (c, c_addr) = start(&id_c, C, &[&mesh_ca, &rogue_ca], &pins).await;
+
+        assert_eq!(b.exchange(A, a_addr).await.unwrap(), 1);
+        assert_eq!(names(&a), [A, B]);
+        assert_eq!(names(&b), [A, B]);
+
+        // C can't join through A or B, and neither will talk to C.
+        assert!(c.exchange(A, a_addr).await.is_err());
+        assert!(c.exchange(B, b_addr).await.is_err());
+        let err = a.exchange(C, c_addr).await.unwrap_err();
+        assert!(matches!(err, crate::SrvError::Trust(_)), "{err}");
+
+        // Nor can a trusted peer vouch for C by relaying its announcement.
+        let relayer = mesh_ca.issue("relay.srv.i1.is");
+        assert!(b
+            .merge(&relayer.chain()[0], c.push().announcements)
+            .is_err());
+
+        assert_eq!(names(&a), [A, B]);
+        assert_eq!(names(&b), [A, B]);
+        assert_eq!(names(&c), [C]);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_relay_quarantines_relayer() {
+        let ca = Ca::new();
+        let (id_a, id_b) = (ca.issue(A), ca.issue(B));
+        let pins = Arc::new(PinnedTlsa::default());
+        let (a, _) = start(&id_a, A, &[&ca], &pins).await;
+
+        let mut forged = Announcement::sign(
+            Member {
+                name: B.into(),
+                addr: "192.0.2.1:7946".parse().unwrap(),
+                incarnation: 1,
+            },
+            &id_b,
+        )
+        .unwrap();
+        assert!(a.merge(&id_b.chain()[0], vec![forged.clone()]).is_ok());
+
+        // A relayer redirects B's traffic to itself.
+        let relayer = ca.issue("relay.srv.i1.is");
+        forged.member.addr = "203.0.113.66:7946".parse().unwrap();
+        forged.member.incarnation = 2;
+        let err = a.merge(&relayer.chain()[0], vec![forged]).unwrap_err();
+        assert!(err.to_string().contains("forged"), "{err}");
+
+        assert_eq!(a.quarantined().len(), 1);
+        let b_member = a.members().into_iter().find(|m| m.name == B).unwrap();
+        assert_eq!(b_member.addr, "192.0.2.1:7946".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tlsa_mismatch_quarantines_peer() {
+        let ca = Ca::new();
+        let (id_a, id_b) = (ca.issue(A), ca.issue(B));
+
+        // B's TLSA record names a different certificate.
+        let mut pins = PinnedTlsa::default();
+        pins.pin(A, &id_a.chain()[0]);
+        pins.pin(B, &ca.issue(B).chain()[0]);
+        let pins = Arc::new(pins);
+
+        let (a, _) = start(&id_a, A, &[&ca], &pins).await;
+        let (_b, b_addr) = start(&id_b, B, &[&ca], &pins).await;
+
+        let err = a.exchange(B, b_addr).await.unwrap_err();
+        assert!(err.to_string().contains("TLSA"), "{err}");
+        assert_eq!(a.quarantined().len(), 1);
+        assert_eq!(names(&a), [A]);
+
+        let err = a.exchange(B, b_addr).await.unwrap_err();
+        assert!(err.to_string().contains("quarantined"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_late_joiner_converges() {
+        let ca = Ca::new();
+        let (id_a, id_c) = (ca.issue(A), ca.issue(C));
+        let mut pins = PinnedTlsa::default();
+        pins.pin(A, &id_a.chain()[0]);
+        pins.pin(C, &id_c.chain()[0]);
+        let pins = Arc::new(pins);
+
+        // A takes 1000 updates while C is away.
+        let (a, a_addr) = start(&id_a, A, &[&ca], &pins).await;
+        for i in 0..1000u32 {
+            let zone = if i % 4 == 0 { "rep" } else { "bl" };
+            let key = format!("{}.{}.0.10", i % 256, i / 256);
+            a.write(zone, &key, Some("127.0.0.2".into()), 1).unwrap();
+        }
+
+        // C comes back with a record of its own and a newer version of one
+        // of A's.
+        let (c, _) = start(&id_c, C, &[&ca], &pins).await;
+        c.write("bl", "1.1.1.1", Some("127.0.0.3".into()), 1)
+            .unwrap();
+        c.write("bl", "1.0.0.10", None, 2).unwrap();
+
+        let bound = 1000usize.div_ceil(MAX_BATCH * MAX_BATCHES_PER_ROUND) + 1;
+        let mut rounds = 0;
+        loop {
+            rounds += 1;
+            let report = c.sync_with(A, a_addr).await.unwrap();
+            assert!(report.received <= 2 * MAX_BATCH * MAX_BATCHES_PER_ROUND);
+            if report.in_sync {
+                break;
+            }
+            assert!(rounds < bound, "no convergence after {rounds} rounds");
+        }
+        assert!(rounds > 1, "transfer should span several bounded rounds");
+
+        assert_eq!(c.digests(), a.digests());
+        assert_eq!(c.records("bl").len(), 751);
+        assert_eq!(c.records("rep").len(), 250);
+        let deleted = a.records("bl").into_iter().find(|r| r.key == "1.0.0.10");
+        assert_eq!(deleted.map(|r| (r.origin, r.value)), Some((C.into(), None)));
+
+        let metrics = crate::metrics::Registry::default();
+        metrics.register(Arc::new(c.clone()));
+        let text = metrics.render();
+        assert!(text.contains(&format!(
+            "i1_gossip_zone_in_sync{{peer=\"{A}\",zone=\"bl\"}} 1\n"
+        )));
+        assert!(text.contains(&format!(
+            "i1_gossip_anti_entropy_rounds_total{{result=\"ok\"}} {rounds}\n"
+        )));
+    }
+
+    /// Nodes A, B and C under one root, each having heard of the others.
+    /// Returns them with their addresses, and B's certificate.
+    async fn three_nodes() -> ([GossipNode; 3], [SocketAddr; 3], CertificateDer<'static>) {
+        let ca = Ca::new();
+        let ids = [ca.issue(A), ca.issue(B), ca.issue(C)];
+        let mut pins = PinnedTlsa::default();
+        for (name, id) in [A, B, C].into_iter().zip(&ids) {
+            pins.pin(name, &id.chain()[0]);
+        }
+        let pins = Arc::new(pins);
+
+        let (a, a_addr) = start(&ids[0], A, &[&ca], &pins).await;
+        let (b, b_addr) = start(&ids[1], B, &[&ca], &pins).await;
+        let (c, c_addr) = start(&ids[2], C, &[&ca], &pins).await;
+        b.exchange(A, a_addr).await.unwrap();
+        c.exchange(B, b_addr).await.unwrap();
+        a.exchange(C, c_addr).await.unwrap();
+        for node in [&a, &b, &c] {
+            assert_eq!(names(node), [A, B, C]);
+        }
+        let b_leaf = ids[1].chain()[0].clone();
+        ([a, b, c], [a_addr, b_addr, c_addr], b_leaf)
+    }
+
+    fn rumor_count(node: &GossipNode, result: &str) -> usize {
+        let metrics = crate::metrics::Registry::default();
+        metrics.register(Arc::new(node.clone()));
+        let text = metrics.render();
+        let prefix = format!("i1_gossip_rumors_total{{result=\"{result}\"}} ");
+        text.lines()
+            .find_map(|line| line.strip_prefix(&prefix))
+            .and_then(|count| count.parse().ok())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rumor_reaches_each_node_once() {
+        let ([a, b, c], [a_addr, b_addr, c_addr], _) = three_nodes().await;
+
+        assert!(a
+            .write("bl", "203.0.113.7", Some("127.0.0.2".into()), 1)
+            .unwrap());
+        let written = a.records("bl").remove(0);
+
+        // A -> B -> C.
+        a.exchange(B, b_addr).await.unwrap();
+        b.exchange(C, c_addr).await.unwrap();
+        let relayed = c.records("bl");
+        assert_eq!(
+            relayed,
+            [Record {
+                hops: 2,
+                ..written.clone()
+            }]
+        );
+
+        // C then hears from A directly and passes the rumor on; nobody
+        // takes the update in twice, and C keeps the direct copy.
+        a.exchange(C, c_addr).await.unwrap();
+        c.exchange(B, b_addr).await.unwrap();
+        c.exchange(A, a_addr).await.unwrap();
+        assert_eq!(rumor_count(&a, "delivered"), 0);
+        assert_eq!(rumor_count(&b, "delivered"), 1);
+        assert_eq!(rumor_count(&c, "delivered"), 1);
+        assert!(rumor_count(&a, "duplicate") >= 1);
+        assert!(rumor_count(&c, "duplicate") >= 1);
+        assert_eq!(c.records("bl"), [Record { hops: 1, ..written }]);
+
+        assert_eq!(c.digests(), a.digests());
+        assert_eq!(b.digests(), a.digests());
+    }
+
+    #[tokio::test]
+    async fn test_stale_replay_is_ignored() {
+        let ([a, b, c], [_, b_addr, c_addr], b_leaf) = three_nodes().await;
+        let key = "203.0.113.7";
+        let value = |node: &GossipNode| {
+            node.records("bl")
+                .into_iter()
+                .find(|r| r.key == key)
+                .and_then(|r| r.value)
+        };
+
+        a.write("bl", key, Some("127.0.0.2".into()), 1).unwrap();
+        a.exchange(B, b_addr).await.unwrap();
+        b.exchange(C, c_addr).await.unwrap();
+        let old = Rumor {
+            zone: "bl".into(),
+            record: c.records("bl").remove(0),
+        };
+
+        a.write("bl", key, Some("127.0.0.3".into()), 2).unwrap();
+        a.exchange(B, b_addr).await.unwrap();
+        b.exchange(C, c_addr).await.unwrap();
+        assert_eq!(value(&c).as_deref(), Some("127.0.0.3"));
+
+        // B replays the old update: C has seen it.
+        let duplicates = rumor_count(&c, "duplicate");
+        assert_eq!(c.receive(&b_leaf, vec![old.clone()]).unwrap(), 0);
+        assert_eq!(rumor_count(&c, "duplicate"), duplicates + 1);
+        // Once C has forgotten it, the newer version still wins.
+        c.lock().seen = SeenCache::default();
+        assert_eq!(c.receive(&b_leaf, vec![old.clone()]).unwrap(), 0);
+        assert_eq!(rumor_count(&c, "stale"), 1);
+        assert_eq!(value(&c).as_deref(), Some("127.0.0.3"));
+
+        // Dressing the replay up as newer breaks A's signature.
+        let mut forged = old;
+        forged.record.serial = 3;
+        let err = c.receive(&b_leaf, vec![forged]).unwrap_err();
+        assert!(err.to_string().contains("forged"), "{err}");
+        assert_eq!(c.quarantined().len(), 1);
+        assert_eq!(value(&c).as_deref(), Some("127.0.0.3"));
+    }
+}
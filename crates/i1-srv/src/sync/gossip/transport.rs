@@ -0,0 +1,205 @@
+//! Mutually authenticated TLS between gossip peers.
+//!
+//! Every node presents its i1-ca node certificate and requires one from
+//! the other side. Both directions verify the chain against the shared
+//! root, and against i1-ca's CRLs when any are configured. A CRL only
+//! covers certificates from its own issuer; certificates no loaded CRL
+//! speaks for are accepted, so adding one intermediate's CRL doesn't lock
+//! out the rest of the mesh.
+//!
+//! On the wire each message is a CBOR value behind a 4-byte big-endian
+//! length.
+
+use rustls::client::WebPkiServerVerifier;
+use rustls::server::danger::ClientCertVerifier;
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, CertificateRevocationListDer, UnixTime};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::node::identity::NodeIdentity;
+
+/// ALPN protocol spoken on gossip connections.
+pub const GOSSIP_ALPN: &[u8] = b"i1-gossip/1";
+
+/// Largest message accepted from a peer.
+const MAX_FRAME: usize = 1 << 20;
+
+/// TLS settings shared by a node's gossip listener and outgoing exchanges.
+pub struct GossipTls {
+    acceptor: TlsAcceptor,
+    connector: TlsConnector,
+    verifier: Arc<dyn ClientCertVerifier>,
+}
+
+impl std::fmt::Debug for GossipTls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GossipTls").finish_non_exhaustive()
+    }
+}
+
+impl GossipTls {
+    /// Present `identity` and trust nodes chaining to `roots`.
+    pub fn new(
+        identity: &NodeIdentity,
+        roots: Vec<CertificateDer<'static>>,
+        crls: Vec<CertificateRevocationListDer<'static>>,
+    ) -> crate::Result<Self> {
+        let mut store = RootCertStore::empty();
+        for root in roots {
+            store
+                .add(root)
+                .map_err(|e| crate::SrvError::Trust(format!("invalid gossip root: {e}")))?;
+        }
+        if store.is_empty() {
+            return Err(crate::SrvError::Trust("no gossip root certificates".into()));
+        }
+        let store = Arc::new(store);
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+        let verifier = WebPkiClientVerifier::builder_with_provider(store.clone(), provider.clone())
+            .with_crls(crls.clone())
+            .allow_unknown_revocation_status()
+            .build()
+            .map_err(|e| crate::SrvError::Trust(format!("gossip client verifier: {e}")))?;
+        let server_verifier = WebPkiServerVerifier::builder_with_provider(store, provider.clone())
+            .with_crls(crls)
+            .allow_unknown_revocation_status()
+            .build()
+            .map_err(|e| crate::SrvError::Trust(format!("gossip server verifier: {e}")))?;
+
+        let mut server = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| {
+                builder
+                    .with_client_cert_verifier(verifier.clone())
+                    .with_single_cert(identity.chain().to_vec(), identity.key())
+            })
+            .map_err(|e| crate::SrvError::Identity(format!("invalid gossip identity: {e}")))?;
+        server.alpn_protocols = vec![GOSSIP_ALPN.to_vec()];
+
+        let mut client = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| {
+                builder
+                    .with_webpki_verifier(server_verifier)
+                    .with_client_auth_cert(identity.chain().to_vec(), identity.key())
+            })
+            .map_err(|e| crate::SrvError::Identity(format!("invalid gossip identity: {e}")))?;
+        client.alpn_protocols = vec![GOSSIP_ALPN.to_vec()];
+
+        Ok(Self {
+            acceptor: TlsAcceptor::from(Arc::new(server)),
+            connector: TlsConnector::from(Arc::new(client)),
+            verifier,
+        })
+    }
+
+    /// Load the shared root (PEM) and CRLs (PEM or DER) from files.
+    pub fn load(identity: &NodeIdentity, root: &Path, crls: &[PathBuf]) -> crate::Result<Self> {
+        let roots = CertificateDer::pem_file_iter(root)
+            .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+            .map_err(|e| {
+                crate::SrvError::Trust(format!("failed to load {}: {e}", root.display()))
+            })?;
+        let crls = crls
+            .iter()
+            .map(|path| load_crl(path))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Self::new(identity, roots, crls)
+    }
+
+    /// Accepts incoming gossip connections.
+    #[must_use]
+    pub const fn acceptor(&self) -> &TlsAcceptor {
+        &self.acceptor
+    }
+
+    /// Opens outgoing gossip connections.
+    #[must_use]
+    pub const fn connector(&self) -> &TlsConnector {
+        &self.connector
+    }
+
+    /// A server config presenting `identity` to clients that must present
+    /// certificates trusted the way gossip peers' are.
+    pub fn client_auth_config(
+        &self,
+        identity: &NodeIdentity,
+        alpn: &[&[u8]],
+    ) -> crate::Result<Arc<rustls::ServerConfig>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| {
+                builder
+                    .with_client_cert_verifier(self.verifier.clone())
+                    .with_single_cert(identity.chain().to_vec(), identity.key())
+            })
+            .map_err(|e| crate::SrvError::Identity(format!("invalid TLS identity: {e}")))?;
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        Ok(Arc::new(config))
+    }
+
+    /// Check a certificate chain (leaf first) the way a connecting peer's
+    /// is checked: issued under the shared root and not revoked.
+    pub fn verify_chain(&self, chain: &[CertificateDer<'_>]) -> crate::Result<()> {
+        let (leaf, intermediates) = chain
+            .split_first()
+            .ok_or_else(|| crate::SrvError::Trust("empty certificate chain".into()))?;
+        self.verifier
+            .verify_client_cert(leaf, intermediates, UnixTime::now())
+            .map(|_| ())
+            .map_err(|e| crate::SrvError::Trust(format!("untrusted certificate: {e}")))
+    }
+}
+
+fn load_crl(path: &Path) -> crate::Result<CertificateRevocationListDer<'static>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| crate::SrvError::Trust(format!("failed to read {}: {e}", path.display())))?;
+    Ok(CertificateRevocationListDer::from_pem_slice(&bytes)
+        .unwrap_or_else(|_| CertificateRevocationListDer::from(bytes)))
+}
+
+/// Write one length-prefixed CBOR message.
+pub(crate) async fn write_frame<W, T>(writer: &mut W, message: &T) -> crate::Result<()>
+where
+    W: AsyncWrite + Unpin + Send,
+    T: Serialize + Sync,
+{
+    let mut payload = Vec::new();
+    ciborium::into_writer(message, &mut payload)
+        .map_err(|e| crate::SrvError::Cbor(format!("gossip encode: {e}")))?;
+    let len = u32::try_from(payload.len())
+        .ok()
+        .filter(|len| *len as usize <= MAX_FRAME)
+        .ok_or_else(|| crate::SrvError::Sync("gossip message too large".into()))?;
+    writer.write_all(&len.to_be_bytes()).await?;
+    writer.write_all(&payload).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one length-prefixed CBOR message.
+pub(crate) async fn read_frame<R, T>(reader: &mut R) -> crate::Result<T>
+where
+    R: AsyncRead + Unpin + Send,
+    T: DeserializeOwned,
+{
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME {
+        return Err(crate::SrvError::Sync(format!(
+            "gossip message of {len} bytes exceeds the {MAX_FRAME}-byte limit"
+        )));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    ciborium::from_reader(payload.as_slice())
+        .map_err(|e| crate::SrvError::Cbor(format!("gossip decode: {e}")))
+}
@@ -2,6 +2,12 @@
 //!
 //! - **Collector**: Reads defense state and patrol data, converts to DNS records.
 //! - **Gossip**: SWIM protocol for lightweight inter-node state dissemination.
+//! - **Prefixes**: Keeps the announced prefixes of blocked ASNs current.
+//! - **Reload**: Rebuilds and swaps the served zones when the defense state changes.
+//! - **Xfr**: AXFR/IXFR client that keeps a secondary's zones in step with a primary.
 
 pub mod collector;
 pub mod gossip;
+pub mod prefixes;
+pub mod reload;
+pub mod xfr;
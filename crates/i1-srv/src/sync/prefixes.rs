@@ -0,0 +1,531 @@
+//! Prefix lists for blocked ASNs.
+//!
+//! Blocked ASNs are published with the prefixes they announce (see
+//! [`crate::encoding::asn_prefixes`]). Looking those up is too slow to do
+//! inside a zone build, so a [`PrefixRefresher`] keeps an [`AsnPrefixCache`]
+//! current in the background and requests a rebuild whenever it changes;
+//! rebuilds read the cache through [`SnapshotSource`](super::reload::SnapshotSource).
+//!
+//! Lists come from a [`PrefixSource`]: [`RipeStat`] asks the `RIPEstat`
+//! API, and [`RibDump`] reads a local RIB dump for nodes that can't reach
+//! it. A failed refresh keeps the previous list, marked stale.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+use crate::authority::blocklist_authority::Cidr;
+use crate::encoding::asn_prefixes::parse_asn;
+use crate::sync::collector;
+use crate::sync::reload::RebuildHandle;
+
+/// How often the blocked ASNs are checked for lists that are missing or due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Timeout for one `RIPEstat` request.
+const RIPESTAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The prefixes an ASN announces, as last fetched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsnPrefixes {
+    /// Prefixes in CIDR notation, sorted.
+    pub prefixes: Vec<String>,
+    /// When the list was fetched.
+    pub fetched: DateTime<Utc>,
+    /// Which source it came from.
+    pub source: String,
+    /// The last refresh failed; this is the list from before it.
+    pub stale: bool,
+}
+
+/// Where prefix lists come from.
+#[async_trait]
+pub trait PrefixSource: Send + Sync {
+    /// Name recorded with each list (`ripestat`, `rib`).
+    fn name(&self) -> &'static str;
+
+    /// Prefixes `asn` currently announces.
+    async fn announced(&self, asn: u32) -> crate::Result<Vec<String>>;
+}
+
+/// Looks prefixes up with `RIPEstat`'s announced-prefixes API.
+pub struct RipeStat {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RipeStatResponse {
+    data: RipeStatData,
+}
+
+#[derive(Deserialize)]
+struct RipeStatData {
+    prefixes: Vec<RipeStatPrefix>,
+}
+
+#[derive(Deserialize)]
+struct RipeStatPrefix {
+    prefix: String,
+}
+
+impl RipeStat {
+    /// Query the announced-prefixes endpoint at `url`.
+    pub fn new(url: &str) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(RIPESTAT_TIMEOUT)
+            .build()
+            .map_err(|e| crate::SrvError::Config(format!("RIPEstat client: {e}")))?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl PrefixSource for RipeStat {
+    fn name(&self) -> &'static str {
+        "ripestat"
+    }
+
+    async fn announced(&self, asn: u32) -> crate::Result<Vec<String>> {
+        let failed =
+            |e: reqwest::Error| crate::SrvError::DnsQuery(format!("RIPEstat AS{asn}: {e}"));
+        let response: RipeStatResponse = self
+            .client
+            .get(&self.url)
+            .query(&[("resource", format!("AS{asn}"))])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(failed)?
+            .json()
+            .await
+            .map_err(failed)?;
+        Ok(normalize(
+            response.data.prefixes.into_iter().map(|p| p.prefix),
+        ))
+    }
+}
+
+/// Prefixes announced by each origin ASN in a RIB dump.
+type RouteTable = HashMap<u32, Vec<String>>;
+
+/// Reads prefixes from a local RIB dump.
+///
+/// Each line is either `<prefix> <origin-asn>` (as written by pyasn and
+/// most IP-to-ASN tools) or `bgpdump -m` output, whose origin is the last
+/// ASN on the path. Blank lines and `#`/`;` comments are skipped, as are
+/// routes originated by an AS set. The file is parsed again whenever it
+/// changes, so replacing it is enough to refresh.
+pub struct RibDump {
+    path: PathBuf,
+    parsed: Mutex<Option<(SystemTime, Arc<RouteTable>)>>,
+}
+
+impl RibDump {
+    /// Read the dump at `path`.
+    #[must_use]
+    pub const fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            parsed: Mutex::new(None),
+        }
+    }
+
+    /// The parsed dump, reread if the file changed since the last call.
+    fn table(&self) -> crate::Result<Arc<RouteTable>> {
+        let read_failed = |e: std::io::Error| {
+            crate::SrvError::State(format!("failed to read {}: {e}", self.path.display()))
+        };
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .map_err(read_failed)?;
+        let mut parsed = self.parsed.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((seen, table)) = parsed.as_ref() {
+            if *seen == modified {
+                return Ok(table.clone());
+            }
+        }
+        let content = std::fs::read_to_string(&self.path).map_err(read_failed)?;
+        let table = Arc::new(parse_rib(&content));
+        info!(path = %self.path.display(), asns = table.len(), "loaded RIB dump");
+        *parsed = Some((modified, table.clone()));
+        drop(parsed);
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl PrefixSource for RibDump {
+    fn name(&self) -> &'static str {
+        "rib"
+    }
+
+    async fn announced(&self, asn: u32) -> crate::Result<Vec<String>> {
+        Ok(self.table()?.get(&asn).cloned().unwrap_or_default())
+    }
+}
+
+/// Group a RIB dump's prefixes by origin ASN.
+fn parse_rib(content: &str) -> RouteTable {
+    let mut routes = RouteTable::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let route = if line.contains('|') {
+            // bgpdump -m: TABLE_DUMP2|time|B|peer|peer-as|prefix|as-path|...
+            let fields: Vec<&str> = line.split('|').collect();
+            fields
+                .get(5)
+                .zip(
+                    fields
+                        .get(6)
+                        .and_then(|path| path.split_whitespace().last()),
+                )
+                .map(|(prefix, origin)| (*prefix, origin))
+        } else {
+            let mut fields = line.split_whitespace();
+            fields.next().zip(fields.next())
+        };
+        if let Some((prefix, asn)) = route.and_then(|(p, o)| Some((p, parse_asn(o)?))) {
+            routes.entry(asn).or_default().push(prefix.to_string());
+        }
+    }
+    routes
+        .into_iter()
+        .map(|(asn, prefixes)| (asn, normalize(prefixes.into_iter())))
+        .collect()
+}
+
+/// Canonical, sorted, de-duplicated CIDRs; anything unparsable is dropped.
+fn normalize(prefixes: impl Iterator<Item = String>) -> Vec<String> {
+    prefixes
+        .filter_map(|p| Cidr::parse(&p).ok().map(|cidr| cidr.to_string()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// The latest prefix list for each blocked ASN.
+///
+/// Clones share the same lists.
+#[derive(Debug, Clone, Default)]
+pub struct AsnPrefixCache {
+    lists: Arc<RwLock<BTreeMap<u32, AsnPrefixes>>>,
+}
+
+impl AsnPrefixCache {
+    /// Every ASN's latest list.
+    #[must_use]
+    pub fn snapshot(&self) -> BTreeMap<u32, AsnPrefixes> {
+        self.lists
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Whether `asn` has no list, or one fetched more than `max_age` ago.
+    fn due(&self, asn: u32, max_age: Duration, now: DateTime<Utc>) -> bool {
+        let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX);
+        self.lists
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&asn)
+            .map_or(true, |list| now - list.fetched >= max_age)
+    }
+
+    /// Record a fetch; returns whether what is published changed.
+    ///
+    /// A failed fetch marks the existing list stale without touching its
+    /// fetch time, so it is retried on the next check.
+    fn record(&self, asn: u32, source: &str, outcome: crate::Result<Vec<String>>) -> bool {
+        let mut lists = self.lists.write().unwrap_or_else(PoisonError::into_inner);
+        let changed = match outcome {
+            Ok(prefixes) => {
+                let list = AsnPrefixes {
+                    prefixes,
+                    fetched: Utc::now(),
+                    source: source.to_string(),
+                    stale: false,
+                };
+                let changed = lists.get(&asn).map_or(true, |old| {
+                    old.prefixes != list.prefixes || old.stale || old.source != list.source
+                });
+                lists.insert(asn, list);
+                changed
+            }
+            Err(e) => {
+                warn!(asn, error = %e, "ASN prefix refresh failed");
+                lists
+                    .get_mut(&asn)
+                    .is_some_and(|list| !std::mem::replace(&mut list.stale, true))
+            }
+        };
+        drop(lists);
+        changed
+    }
+
+    /// Forget ASNs no longer blocked; returns whether any were dropped.
+    fn retain(&self, blocked: &BTreeSet<u32>) -> bool {
+        let mut lists = self.lists.write().unwrap_or_else(PoisonError::into_inner);
+        let before = lists.len();
+        lists.retain(|asn, _| blocked.contains(asn));
+        before != lists.len()
+    }
+}
+
+/// Keeps an [`AsnPrefixCache`] current for the ASNs in the defense state.
+pub struct PrefixRefresher {
+    source: Arc<dyn PrefixSource>,
+    cache: AsnPrefixCache,
+    state_path: Option<PathBuf>,
+    max_age: Duration,
+    rebuild: Option<RebuildHandle>,
+}
+
+impl PrefixRefresher {
+    /// Refresh lists in `cache` from `source` once they are `max_age` old,
+    /// for the ASNs blocked in the state file at `state_path`.
+    #[must_use]
+    pub fn new(
+        source: Arc<dyn PrefixSource>,
+        cache: AsnPrefixCache,
+        state_path: Option<PathBuf>,
+        max_age: Duration,
+    ) -> Self {
+        Self {
+            source,
+            cache,
+            state_path,
+            max_age,
+            rebuild: None,
+        }
+    }
+
+    /// Request a rebuild through `rebuild` whenever a list changes.
+    #[must_use]
+    pub fn with_rebuild(mut self, rebuild: RebuildHandle) -> Self {
+        self.rebuild = Some(rebuild);
+        self
+    }
+
+    /// Fetch every list that is missing or due; returns whether anything
+    /// published changed.
+    pub async fn refresh(&self) -> crate::Result<bool> {
+        let snapshot = match &self.state_path {
+            Some(path) => collector::load_snapshot(path)?,
+            None => return Ok(false),
+        };
+        let blocked: BTreeSet<u32> = snapshot
+            .blocked_asns
+            .iter()
+            .filter_map(|asn| parse_asn(asn))
+            .collect();
+
+        let mut changed = self.cache.retain(&blocked);
+        let now = Utc::now();
+        for &asn in &blocked {
+            if !self.cache.due(asn, self.max_age, now) {
+                continue;
+            }
+            let outcome = self.source.announced(asn).await;
+            if let Ok(prefixes) = &outcome {
+                debug!(asn, prefixes = prefixes.len(), "fetched ASN prefixes");
+            }
+            changed |= self.cache.record(asn, self.source.name(), outcome);
+        }
+        Ok(changed)
+    }
+
+    /// Refresh on a fixed schedule, forever.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match self.refresh().await {
+                Ok(true) => {
+                    if let Some(rebuild) = &self.rebuild {
+                        rebuild.request();
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => warn!(error = %e, "failed to read blocked ASNs"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::transfer::ZoneStore;
+    use crate::authority::zone_builder::{build_zones, DefenseSnapshot};
+    use crate::config::ZoneConfig;
+    use crate::encoding::asn_prefixes::{decode_chunk, PrefixIndex};
+    use crate::sync::reload::SnapshotSource;
+    use hickory_proto::rr::{Name, RData};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// A small ASN, a 5000-prefix one, and a switch to make lookups fail.
+    #[derive(Default)]
+    struct Mocked {
+        down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl PrefixSource for Mocked {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        async fn announced(&self, asn: u32) -> crate::Result<Vec<String>> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(crate::SrvError::DnsQuery("source unreachable".into()));
+            }
+            Ok(match asn {
+                64500 => vec!["192.0.2.0/24".into(), "2001:db8::/32".into()],
+                64501 => (0..5000)
+                    .map(|i| format!("10.{}.{}.0/24", i / 256, i % 256))
+                    .collect(),
+                _ => Vec::new(),
+            })
+        }
+    }
+
+    fn write_state(dir: &std::path::Path, asns: &[&str]) -> PathBuf {
+        let path = dir.join("state.json");
+        let state = serde_json::json!({ "blocked_asns": asns });
+        std::fs::write(&path, state.to_string()).unwrap();
+        path
+    }
+
+    /// TXT values published at `name` in the ASN zone.
+    fn txts(zones: &mut crate::authority::zone_builder::BuiltZones, name: &str) -> Vec<String> {
+        let name = Name::from_ascii(name).unwrap();
+        zones
+            .asn
+            .zone_records()
+            .into_iter()
+            .filter(|record| *record.name() == name)
+            .filter_map(|record| match record.data() {
+                RData::TXT(txt) => Some(String::from_utf8_lossy(&txt.txt_data().concat()).into()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_prefixes_published() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = write_state(dir.path(), &["AS64500", "AS64501"]);
+        let cache = AsnPrefixCache::default();
+        let refresher = PrefixRefresher::new(
+            Arc::new(Mocked::default()),
+            cache.clone(),
+            Some(state_path.clone()),
+            Duration::from_secs(3600),
+        );
+        assert!(refresher.refresh().await.unwrap());
+        // Nothing is due again until the lists age.
+        assert!(!refresher.refresh().await.unwrap());
+
+        let source = SnapshotSource {
+            state_path: Some(state_path),
+            asn_prefixes: Some(cache),
+            ..SnapshotSource::default()
+        };
+        let mut zones = build_zones(&source.load().unwrap(), &ZoneConfig::default(), 1).unwrap();
+
+        let small = txts(&mut zones, "64500.prefixes.asn.i1.is.");
+        let index = PrefixIndex::from_txt(&small[0]).unwrap();
+        assert_eq!((index.prefixes, index.chunks), (2, 1));
+        assert_eq!(index.source, "mock");
+        assert!(!index.stale);
+        let chunk = decode_chunk(&txts(&mut zones, "0.64500.prefixes.asn.i1.is.")[0]).unwrap();
+        assert_eq!(chunk.prefixes, ["192.0.2.0/24", "2001:db8::/32"]);
+
+        let large = txts(&mut zones, "64501.prefixes.asn.i1.is.");
+        let index = PrefixIndex::from_txt(&large[0]).unwrap();
+        assert_eq!(index.prefixes, 5000);
+        assert!(index.chunks > 1);
+        let mut prefixes = Vec::new();
+        for i in 0..index.chunks {
+            let txt = &txts(&mut zones, &format!("{i}.64501.prefixes.asn.i1.is."))[0];
+            let chunk = decode_chunk(txt).unwrap();
+            assert_eq!((chunk.asn, chunk.chunk as usize), (64501, i));
+            prefixes.extend(chunk.prefixes);
+        }
+        assert_eq!(prefixes.len(), 5000);
+        assert!(txts(
+            &mut zones,
+            &format!("{}.64501.prefixes.asn.i1.is.", index.chunks)
+        )
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_marks_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = write_state(dir.path(), &["AS64500"]);
+        let source = Arc::new(Mocked::default());
+        let cache = AsnPrefixCache::default();
+        let refresher = PrefixRefresher::new(
+            source.clone(),
+            cache.clone(),
+            Some(state_path.clone()),
+            Duration::ZERO,
+        );
+        assert!(refresher.refresh().await.unwrap());
+
+        source.down.store(true, Ordering::Relaxed);
+        assert!(refresher.refresh().await.unwrap());
+        let list = &cache.snapshot()[&64500];
+        assert!(list.stale);
+        assert_eq!(list.prefixes.len(), 2);
+        // Still failing: already stale, nothing new to publish.
+        assert!(!refresher.refresh().await.unwrap());
+
+        // Unblocking the ASN drops its list.
+        write_state(dir.path(), &[]);
+        assert!(refresher.refresh().await.unwrap());
+        assert!(cache.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_parse_rib() {
+        let table = parse_rib(
+            "# pyasn\n\
+             192.0.2.0/24\t64500\n\
+             198.51.100.0/24 AS64500\n\
+             192.0.2.0/24\t64500\n\
+             203.0.113.0/24\t{64502,64503}\n\
+             TABLE_DUMP2|1760000000|B|192.0.2.1|64496|2001:db8::/32|64496 64501|IGP\n\
+             not-a-prefix 64500\n",
+        );
+        assert_eq!(table[&64500], ["192.0.2.0/24", "198.51.100.0/24"]);
+        assert_eq!(table[&64501], ["2001:db8::/32"]);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn test_without_prefix_data() {
+        // Building without any prefix data leaves the ASN zone as before.
+        let snapshot = DefenseSnapshot {
+            blocked_asns: vec!["AS64500".into()],
+            ..Default::default()
+        };
+        let mut zones = build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap();
+        assert!(txts(&mut zones, "64500.prefixes.asn.i1.is.").is_empty());
+        assert_eq!(txts(&mut zones, "64500.asn.i1.is.").len(), 1);
+    }
+}
@@ -0,0 +1,503 @@
+//! Hot zone rebuilds.
+//!
+//! Zones are rebuilt whenever the defense state changes, so new bans are
+//! served without a restart. Changes are noticed three ways: a filesystem
+//! watch on the state and audit files, a periodic modification-time poll
+//! for anything the watch misses (network filesystems, a directory that
+//! didn't exist yet), and explicit requests through a [`RebuildHandle`].
+//! A burst of changes is debounced into one rebuild; SIGHUP,
+//! [`RebuildHandle::force`] and [`RebuildHandle::rebuild`] rebuild
+//! immediately, as does a TTL change from a config reload
+//! ([`RebuildHandle::set_ttl`]).
+//!
+//! Every rebuild takes the next zone serial and swaps each zone slot in
+//! place. Queries already in flight finish against the authority they
+//! started with, and the signal zone's `updated` field records when the
+//! rebuild happened.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::authority::dnssec::ZoneSigningKeys;
+use crate::authority::rpz;
+use crate::authority::serial::ZoneSerial;
+use crate::authority::zone_builder::{self, BuildOptions, BuiltZones, DefenseSnapshot};
+use crate::config::{RpzConfig, TtlConfig, ZoneConfig};
+use crate::encoding::txt_intel::ComplexIntel;
+use crate::server::ServedZones;
+use crate::sync::collector;
+use crate::sync::prefixes::AsnPrefixCache;
+use crate::trust::node_signals::NodeSignals;
+use crate::trust::ttl_monitor::TtlReports;
+
+/// Where rebuilds read the defense state from.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotSource {
+    /// Defense state file written by `i1 defend`.
+    pub state_path: Option<PathBuf>,
+    /// Audit snapshot written by `i1 audit --publish`.
+    pub audit_path: Option<PathBuf>,
+    /// Blocks added through the admin API, in the state file's format;
+    /// they are served on top of the state file's.
+    pub blocks_path: Option<PathBuf>,
+    /// Structured intel carried into every rebuild; it isn't part of the
+    /// state file.
+    pub intel: BTreeMap<String, ComplexIntel>,
+    /// Prefix lists for blocked ASNs, kept current by a
+    /// [`PrefixRefresher`](crate::sync::prefixes::PrefixRefresher).
+    pub asn_prefixes: Option<AsnPrefixCache>,
+    /// Signal records accepted by the admin API for other nodes.
+    pub node_signals: Option<NodeSignals>,
+}
+
+impl SnapshotSource {
+    /// Read the current defense state.
+    ///
+    /// A missing state file is an empty snapshot; an unreadable audit
+    /// snapshot is logged and left out, as at startup.
+    pub fn load(&self) -> crate::Result<DefenseSnapshot> {
+        let mut snapshot = match &self.state_path {
+            Some(path) => collector::load_snapshot(path)?,
+            None => DefenseSnapshot::default(),
+        };
+        if let Some(path) = &self.blocks_path {
+            let admin = collector::load_snapshot(path)?;
+            for ip in admin.blocked_ips {
+                if !snapshot.blocked_ips.contains(&ip) {
+                    snapshot.blocked_ips.push(ip);
+                }
+            }
+            for (ip, entry) in admin.block_entries {
+                snapshot.block_entries.entry(ip).or_insert(entry);
+            }
+        }
+        if let Some(path) = &self.audit_path {
+            match collector::load_audit_snapshot(path) {
+                Ok(audit) => snapshot.audit = audit,
+                Err(e) => warn!(error = %e, "failed to load audit snapshot, rebuilding without"),
+            }
+        }
+        snapshot.intel.clone_from(&self.intel);
+        if let Some(cache) = &self.asn_prefixes {
+            snapshot.asn_prefixes = cache.snapshot();
+        }
+        if let Some(signals) = &self.node_signals {
+            snapshot.node_signals = signals.snapshot(chrono::Utc::now());
+        }
+        Ok(snapshot)
+    }
+
+    fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.state_path
+            .iter()
+            .chain(&self.audit_path)
+            .chain(&self.blocks_path)
+            .map(PathBuf::as_path)
+    }
+
+    /// Modification times of the watched files, `None` where missing.
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.paths()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
+
+/// Signing material and settings applied to every build.
+#[derive(Default)]
+pub struct RebuildOptions {
+    /// Sign reputation and intel TXT payloads with this node key.
+    pub signer: Option<i1_ca::NodeSigner>,
+    /// This node's public address, for binary attestations.
+    pub public_ip: Option<IpAddr>,
+    /// Sign every zone with DNSSEC.
+    pub dnssec: Option<ZoneSigningKeys>,
+    /// Response policy zone export.
+    pub rpz: RpzConfig,
+    /// TTL bands and decay.
+    pub ttl: TtlConfig,
+    /// TTL monitor findings to publish in the signal zone.
+    pub ttl_reports: Option<TtlReports>,
+}
+
+impl RebuildOptions {
+    fn build_options(&self) -> BuildOptions<'_> {
+        BuildOptions {
+            signer: self.signer.as_ref(),
+            public_ip: self.public_ip,
+            dnssec: self.dnssec.as_ref(),
+            rpz: self.rpz.serve.then_some(self.rpz.zone.as_str()),
+            ttl: Some(&self.ttl),
+            ttl_reports: &[],
+        }
+    }
+}
+
+/// Why a rebuild was requested.
+#[derive(Debug)]
+enum Trigger {
+    /// The state changed; wait for the burst to settle.
+    Changed,
+    /// Rebuild now, and report the outcome if someone is waiting.
+    Force(Option<oneshot::Sender<crate::Result<u32>>>),
+    /// Rebuild now with new TTL bands.
+    Ttl(TtlConfig),
+}
+
+/// Requests rebuilds from a running [`ZoneRebuilder`].
+#[derive(Debug, Clone)]
+pub struct RebuildHandle {
+    tx: mpsc::UnboundedSender<Trigger>,
+}
+
+impl RebuildHandle {
+    /// Note that the defense state changed; the rebuild is debounced.
+    pub fn request(&self) {
+        let _ = self.tx.send(Trigger::Changed);
+    }
+
+    /// Rebuild immediately.
+    pub fn force(&self) {
+        let _ = self.tx.send(Trigger::Force(None));
+    }
+
+    /// Build with `ttl` from now on, rebuilding immediately.
+    pub fn set_ttl(&self, ttl: TtlConfig) {
+        let _ = self.tx.send(Trigger::Ttl(ttl));
+    }
+
+    /// Rebuild immediately and wait for it; returns the new serial.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the rebuild does, or if the rebuilder is no longer running.
+    pub async fn rebuild(&self) -> crate::Result<u32> {
+        let (done, result) = oneshot::channel();
+        let stopped = || crate::SrvError::Server("zone rebuilder is not running".into());
+        self.tx
+            .send(Trigger::Force(Some(done)))
+            .map_err(|_| stopped())?;
+        result.await.map_err(|_| stopped())?
+    }
+}
+
+/// Builds zones with increasing serials and keeps the served zones current.
+pub struct ZoneRebuilder {
+    zone_config: ZoneConfig,
+    source: SnapshotSource,
+    options: RebuildOptions,
+    serial: ZoneSerial,
+    entries: u32,
+    /// Source modification times as of the last load.
+    seen: Vec<Option<SystemTime>>,
+    tx: mpsc::UnboundedSender<Trigger>,
+    rx: mpsc::UnboundedReceiver<Trigger>,
+}
+
+impl ZoneRebuilder {
+    /// Create a rebuilder; serials start from the current time, and
+    /// source changes from this point on are picked up by [`run`](Self::run).
+    #[must_use]
+    pub fn new(zone_config: ZoneConfig, source: SnapshotSource, options: RebuildOptions) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            zone_config,
+            seen: source.modified(),
+            source,
+            options,
+            serial: ZoneSerial::default(),
+            entries: 0,
+            tx,
+            rx,
+        }
+    }
+
+    /// Issue serials after `serial`, e.g. one restored from disk, so
+    /// secondaries see the next build as newer.
+    pub fn continue_after(&mut self, serial: u32) {
+        self.serial = ZoneSerial::starting_after(serial);
+    }
+
+    /// A handle for requesting rebuilds once [`run`](Self::run) is going.
+    #[must_use]
+    pub fn handle(&self) -> RebuildHandle {
+        RebuildHandle {
+            tx: self.tx.clone(),
+        }
+    }
+
+    /// Build zones from `snapshot` at the next serial, and rewrite the
+    /// RPZ file if one is configured.
+    pub fn build(&mut self, snapshot: &DefenseSnapshot) -> crate::Result<BuiltZones> {
+        let serial = self.serial.next(chrono::Utc::now());
+        let reports = self
+            .options
+            .ttl_reports
+            .as_ref()
+            .map(TtlReports::snapshot)
+            .unwrap_or_default();
+        let options = BuildOptions {
+            ttl_reports: &reports,
+            ..self.options.build_options()
+        };
+        let zones = zone_builder::build_zones_with(snapshot, &self.zone_config, serial, options)?;
+        self.entries = zones.entry_count;
+
+        if let Some(path) = &self.options.rpz.zone_file {
+            let text = rpz::render(snapshot, &self.options.rpz.zone, serial);
+            match rpz::write_zone_file(path, &text) {
+                Ok(()) => debug!(path = %path.display(), serial, "wrote RPZ zone file"),
+                Err(e) => warn!(error = %e, "failed to write RPZ zone file"),
+            }
+        }
+        Ok(zones)
+    }
+
+    /// Reload the defense state and swap the rebuilt zones into `zones`;
+    /// returns the new serial.
+    ///
+    /// On error the zones being served are left untouched.
+    pub fn rebuild(&mut self, zones: &ServedZones) -> crate::Result<u32> {
+        // Record what this load reads so the poll doesn't repeat it.
+        self.seen = self.source.modified();
+        let snapshot = self.source.load()?;
+        let old_serial = zones.signal.serial();
+        let old_entries = self.entries;
+        let built = self.build(&snapshot)?;
+        let serial = built.serial;
+        zones.replace(built);
+        info!(
+            old_serial = ?old_serial,
+            serial,
+            old_entries,
+            entries = self.entries,
+            "rebuilt DNS zones from defense state"
+        );
+        Ok(serial)
+    }
+
+    /// Rebuild `zones` whenever the defense state changes. Runs forever.
+    ///
+    /// `debounce` is the quiet period a burst of changes must end with;
+    /// `poll` is how often file modification times are checked in case
+    /// the watch missed an event.
+    pub async fn run(mut self, zones: ServedZones, debounce: Duration, poll: Duration) {
+        // Dropping the watcher stops it, so it lives as long as the loop.
+        let _watcher = self.watch_files();
+        forward_hangup(self.handle());
+
+        let mut poll = tokio::time::interval(poll);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut pending: Option<Instant> = None;
+
+        loop {
+            let deadline = pending;
+            let (rebuild_now, waiter) = tokio::select! {
+                Some(trigger) = self.rx.recv() => match trigger {
+                    Trigger::Changed => {
+                        pending = Some(Instant::now() + debounce);
+                        (false, None)
+                    }
+                    Trigger::Force(waiter) => (true, waiter),
+                    Trigger::Ttl(ttl) => {
+                        self.options.ttl = ttl;
+                        (true, None)
+                    }
+                },
+                _ = poll.tick() => {
+                    if self.source.modified() != self.seen {
+                        debug!("defense state changed on disk");
+                        pending.get_or_insert_with(|| Instant::now() + debounce);
+                    }
+                    (false, None)
+                }
+                () = sleep_until(deadline), if deadline.is_some() => (true, None),
+            };
+
+            if rebuild_now {
+                pending = None;
+                let result = self.rebuild(&zones);
+                if let Err(e) = &result {
+                    warn!(error = %e, "zone rebuild failed, still serving previous zones");
+                }
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(result);
+                }
+            }
+        }
+    }
+
+    /// Watch the directories holding the source files; files are often
+    /// replaced by rename, which a watch on the file itself would lose.
+    fn watch_files(&self) -> Option<RecommendedWatcher> {
+        let names: BTreeSet<OsString> = self
+            .source
+            .paths()
+            .filter_map(|path| path.file_name().map(ToOwned::to_owned))
+            .collect();
+        let dirs: BTreeSet<PathBuf> = self
+            .source
+            .paths()
+            .map(|path| match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            })
+            .collect();
+        if dirs.is_empty() {
+            return None;
+        }
+
+        let handle = self.handle();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if event.kind.is_access() {
+                return;
+            }
+            let relevant = event
+                .paths
+                .iter()
+                .filter_map(|path| path.file_name())
+                .any(|name| names.contains(name));
+            if relevant {
+                handle.request();
+            }
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                warn!(error = %e, "file watcher unavailable, polling defense state");
+                return None;
+            }
+        };
+        for dir in &dirs {
+            match watcher.watch(dir, RecursiveMode::NonRecursive) {
+                Ok(()) => debug!(dir = %dir.display(), "watching for defense state changes"),
+                Err(e) => warn!(
+                    dir = %dir.display(),
+                    error = %e,
+                    "cannot watch directory, polling instead"
+                ),
+            }
+        }
+        Some(watcher)
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    if let Some(deadline) = deadline {
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+/// Turn SIGHUP into forced rebuilds.
+#[cfg(unix)]
+fn forward_hangup(handle: RebuildHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::hangup()) {
+        Ok(mut hangup) => {
+            tokio::spawn(async move {
+                while hangup.recv().await.is_some() {
+                    info!("SIGHUP received, rebuilding zones");
+                    handle.force();
+                }
+            });
+        }
+        Err(e) => warn!(error = %e, "cannot listen for SIGHUP"),
+    }
+}
+
+#[cfg(not(unix))]
+const fn forward_hangup(_handle: RebuildHandle) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::rr::{LowerName, Name, RecordType};
+    use hickory_server::authority::{Authority, LookupOptions};
+
+    async fn is_listed(zones: &ServedZones, name: &LowerName) -> bool {
+        zones
+            .blocklist
+            .lookup(name, RecordType::A, LookupOptions::default())
+            .await
+            .map_result()
+            .is_some_and(|result| result.is_ok())
+    }
+
+    fn write_state(path: &Path, ips: &[&str]) {
+        let state = serde_json::json!({ "blocked_ips": ips });
+        std::fs::write(path, state.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_change_rebuilds_zones() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("defend_state.json");
+        write_state(&state_path, &["1.2.3.4"]);
+
+        let source = SnapshotSource {
+            state_path: Some(state_path.clone()),
+            ..Default::default()
+        };
+        let mut rebuilder = ZoneRebuilder::new(
+            ZoneConfig::default(),
+            source.clone(),
+            RebuildOptions::default(),
+        );
+        let zones = ServedZones::new(rebuilder.build(&source.load().unwrap()).unwrap(), 4);
+        let first_serial = zones.blocklist.serial().unwrap();
+
+        let added = LowerName::from(Name::from_ascii("8.7.6.5.bl.i1.is.").unwrap());
+        assert!(!is_listed(&zones, &added).await);
+
+        let debounce = Duration::from_millis(50);
+        tokio::spawn(rebuilder.run(zones.clone(), debounce, Duration::from_millis(200)));
+
+        write_state(&state_path, &["1.2.3.4", "5.6.7.8"]);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !is_listed(&zones, &added).await {
+            assert!(Instant::now() < deadline, "rebuild never happened");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(zones.blocklist.serial().unwrap() > first_serial);
+        assert_eq!(zones.signal.serial(), zones.blocklist.serial());
+    }
+
+    #[tokio::test]
+    async fn test_force_rebuild_skips_debounce() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("defend_state.json");
+        let source = SnapshotSource {
+            state_path: Some(state_path.clone()),
+            ..Default::default()
+        };
+        let mut rebuilder = ZoneRebuilder::new(
+            ZoneConfig::default(),
+            source.clone(),
+            RebuildOptions::default(),
+        );
+        let zones = ServedZones::new(rebuilder.build(&source.load().unwrap()).unwrap(), 4);
+        let handle = rebuilder.handle();
+
+        // A debounce and poll far beyond the test's patience.
+        let forever = Duration::from_secs(3600);
+        tokio::spawn(rebuilder.run(zones.clone(), forever, forever));
+
+        write_state(&state_path, &["5.6.7.8"]);
+        handle.force();
+        let added = LowerName::from(Name::from_ascii("8.7.6.5.bl.i1.is.").unwrap());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !is_listed(&zones, &added).await {
+            assert!(Instant::now() < deadline, "forced rebuild never happened");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+}
@@ -0,0 +1,599 @@
+//! Zone transfer client for secondary nodes.
+//!
+//! A secondary keeps its served zones in step with a primary by sending
+//! IXFR with its current SOA. The primary answers with the journaled
+//! changes, a lone SOA when nothing changed, or the whole zone when its
+//! journal doesn't reach back far enough. Zones with no SOA yet (fresh
+//! node) are pulled with AXFR.
+//!
+//! A [`Primary::Pinned`] primary is reached over DNS-over-TLS: each
+//! transfer runs on a connection whose certificate matched the primary's
+//! TLSA record, and a round is skipped while that fails. A
+//! [`Primary::Tcp`] primary is trusted by address alone, so anyone on the
+//! path can feed the secondary its zones.
+
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, Record, RecordType};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+use crate::authority::transfer::{soa_serial, TransferZone, ZoneDelta};
+use crate::trust::mesh::PeerVerifier;
+
+/// Upper bound for one complete transfer exchange.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A complete transfer response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferResponse {
+    /// The primary has the serial we sent.
+    UpToDate,
+    /// The whole zone, SOA first.
+    Full(Vec<Record>),
+    /// Changes since the serial we sent, oldest first.
+    Incremental(Vec<ZoneDelta>),
+}
+
+/// What a refresh did to the local zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshOutcome {
+    /// Nothing to do.
+    UpToDate,
+    /// Zone replaced by a full transfer at this serial.
+    Full(u32),
+    /// Zone brought forward to this serial by applying deltas.
+    Incremental(u32),
+}
+
+/// Bring `zone` up to date with `primary`.
+///
+/// # Errors
+///
+/// Returns `SrvError::Sync` if the primary can't be reached, refuses the
+/// transfer, or sends something that isn't a valid transfer.
+///
+/// A pinned primary that fails verification is reported as
+/// `SrvError::Trust`.
+pub async fn refresh_zone(
+    primary: &Primary,
+    zone: &dyn TransferZone,
+) -> crate::Result<RefreshOutcome> {
+    let origin = Name::from(zone.name().clone());
+    let current = zone.soa_record();
+    let response = transfer(primary, &origin, current.as_ref()).await?;
+
+    match response {
+        TransferResponse::UpToDate => Ok(RefreshOutcome::UpToDate),
+        TransferResponse::Full(records) => {
+            let serial = records.first().and_then(soa_serial).unwrap_or_default();
+            zone.load(records);
+            Ok(RefreshOutcome::Full(serial))
+        }
+        TransferResponse::Incremental(deltas) => {
+            if let Some(records) = apply_deltas(zone.records(), &deltas) {
+                let serial = records.first().and_then(soa_serial).unwrap_or_default();
+                zone.load(records);
+                return Ok(RefreshOutcome::Incremental(serial));
+            }
+
+            // Deltas don't start from our version: start over.
+            warn!(zone = %origin, "incremental transfer does not apply, retrying with AXFR");
+            let TransferResponse::Full(records) = transfer(primary, &origin, None).await? else {
+                return Err(crate::SrvError::Sync(format!(
+                    "{origin}: AXFR answered with a non-AXFR response"
+                )));
+            };
+            let serial = records.first().and_then(soa_serial).unwrap_or_default();
+            zone.load(records);
+            Ok(RefreshOutcome::Full(serial))
+        }
+    }
+}
+
+/// Where a secondary transfers its zones from.
+#[derive(Debug, Clone)]
+pub enum Primary {
+    /// Plain TCP, unauthenticated.
+    Tcp(SocketAddr),
+    /// DNS-over-TLS, with the certificate checked against its TLSA record.
+    Pinned(PrimaryPin),
+}
+
+impl std::fmt::Display for Primary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Pinned(pin) => write!(f, "{} ({})", pin.name, pin.addr),
+        }
+    }
+}
+
+/// The primary's identity, checked before transfers are accepted from it.
+#[derive(Debug, Clone)]
+pub struct PrimaryPin {
+    /// Checks the certificate against the TLSA record.
+    pub verifier: Arc<PeerVerifier>,
+    /// The primary's fully qualified node name.
+    pub name: String,
+    /// Where the primary serves DNS-over-TLS.
+    pub addr: SocketAddr,
+}
+
+/// Refresh `zones` from `primary` every `interval`, forever.
+pub async fn run_secondary(
+    primary: Primary,
+    zones: Vec<Arc<dyn TransferZone>>,
+    interval: Duration,
+) {
+    info!(%primary, zones = zones.len(), "running as secondary");
+    loop {
+        for zone in &zones {
+            match refresh_zone(&primary, zone.as_ref()).await {
+                Ok(RefreshOutcome::UpToDate) => {
+                    debug!(zone = %zone.name(), "zone up to date");
+                }
+                Ok(outcome) => info!(zone = %zone.name(), ?outcome, "zone refreshed"),
+                Err(e @ crate::SrvError::Trust(_)) => {
+                    warn!(%primary, error = %e, "primary failed DANE verification, skipping refresh");
+                    break;
+                }
+                Err(e) => warn!(zone = %zone.name(), error = %e, "zone refresh failed"),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Run one AXFR (no `current` SOA) or IXFR exchange with `primary`.
+async fn transfer(
+    primary: &Primary,
+    origin: &Name,
+    current: Option<&Record>,
+) -> crate::Result<TransferResponse> {
+    let exchange = async {
+        match primary {
+            Primary::Tcp(addr) => {
+                let mut stream = TcpStream::connect(addr)
+                    .await
+                    .map_err(|e| crate::SrvError::Sync(format!("{origin} via {primary}: {e}")))?;
+                exchange(&mut stream, primary, origin, current).await
+            }
+            Primary::Pinned(pin) => {
+                let (mut stream, _) = pin.verifier.connect(pin.addr, &pin.name).await?;
+                exchange(&mut stream, primary, origin, current).await
+            }
+        }
+    };
+    tokio::time::timeout(TRANSFER_TIMEOUT, exchange)
+        .await
+        .map_err(|_| {
+            crate::SrvError::Sync(format!("{origin}: transfer from {primary} timed out"))
+        })?
+}
+
+async fn exchange<S>(
+    stream: &mut S,
+    primary: &Primary,
+    origin: &Name,
+    current: Option<&Record>,
+) -> crate::Result<TransferResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let sync_err =
+        |e: std::io::Error| crate::SrvError::Sync(format!("{origin} via {primary}: {e}"));
+
+    let rtype = if current.is_some() {
+        RecordType::IXFR
+    } else {
+        RecordType::AXFR
+    };
+    let mut id = [0u8; 2];
+    SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| crate::SrvError::Sync("failed to generate message id".into()))?;
+    let id = u16::from_be_bytes(id);
+
+    let mut request = Message::new();
+    request
+        .set_id(id)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .add_query(Query::query(origin.clone(), rtype));
+    if let Some(soa) = current {
+        request.add_name_server(soa.clone());
+    }
+    let bytes = request
+        .to_vec()
+        .map_err(|e| crate::SrvError::Sync(format!("{origin}: encode request: {e}")))?;
+    let len = u16::try_from(bytes.len())
+        .map_err(|_| crate::SrvError::Sync(format!("{origin}: request too large")))?;
+
+    stream.write_u16(len).await.map_err(sync_err)?;
+    stream.write_all(&bytes).await.map_err(sync_err)?;
+    stream.flush().await.map_err(sync_err)?;
+
+    let current_serial = current.and_then(soa_serial);
+    let mut records = Vec::new();
+    loop {
+        let len = stream.read_u16().await.map_err(sync_err)?;
+        let mut buf = vec![0u8; usize::from(len)];
+        stream.read_exact(&mut buf).await.map_err(sync_err)?;
+        let response = Message::from_vec(&buf)
+            .map_err(|e| crate::SrvError::Sync(format!("{origin}: decode response: {e}")))?;
+
+        if response.id() != id {
+            return Err(crate::SrvError::Sync(format!(
+                "{origin}: response id mismatch"
+            )));
+        }
+        if response.response_code() != ResponseCode::NoError {
+            return Err(crate::SrvError::Sync(format!(
+                "{origin}: {primary} answered {rtype} with {}",
+                response.response_code()
+            )));
+        }
+
+        records.extend(response.answers().iter().cloned());
+        if let Some(done) = parse_transfer(&records, current_serial)? {
+            return Ok(done);
+        }
+    }
+}
+
+/// Interpret the answers received so far; `None` means more are coming.
+///
+/// `current` is the serial sent with IXFR. An IXFR answer whose second
+/// record is an SOA other than the final one is incremental (RFC 1995);
+/// anything else is AXFR-style and ends at the second copy of the SOA.
+///
+/// # Errors
+///
+/// Returns `SrvError::Sync` if the answers don't form a valid transfer.
+pub fn parse_transfer(
+    records: &[Record],
+    current: Option<u32>,
+) -> crate::Result<Option<TransferResponse>> {
+    let Some(first) = records.first() else {
+        return Ok(None);
+    };
+    let serial = soa_serial(first)
+        .ok_or_else(|| crate::SrvError::Sync("transfer does not start with an SOA".into()))?;
+
+    if records.len() == 1 {
+        return Ok((current == Some(serial)).then_some(TransferResponse::UpToDate));
+    }
+
+    let incremental =
+        current.is_some() && soa_serial(&records[1]).is_some_and(|second| second != serial);
+    if !incremental {
+        let last = &records[records.len() - 1];
+        return Ok((soa_serial(last) == Some(serial))
+            .then(|| TransferResponse::Full(records[..records.len() - 1].to_vec())));
+    }
+
+    // SOA(new), then per delta: SOA(from), removed..., SOA(to), added...,
+    // and finally SOA(new) again.
+    let mut deltas = Vec::new();
+    let mut i = 1;
+    loop {
+        let Some(from) = records.get(i) else {
+            return Ok(None);
+        };
+        match soa_serial(from) {
+            Some(s) if s == serial && i == records.len() - 1 => {
+                return Ok(Some(TransferResponse::Incremental(deltas)));
+            }
+            Some(s) if s != serial => {}
+            _ => {
+                return Err(crate::SrvError::Sync(
+                    "malformed incremental transfer".into(),
+                ))
+            }
+        }
+
+        let removed_end = match records[i + 1..]
+            .iter()
+            .position(|r| soa_serial(r).is_some())
+        {
+            Some(offset) => i + 1 + offset,
+            None => return Ok(None),
+        };
+        let added_end = records[removed_end + 1..]
+            .iter()
+            .position(|r| soa_serial(r).is_some())
+            .map_or(records.len(), |offset| removed_end + 1 + offset);
+
+        deltas.push(ZoneDelta {
+            from: from.clone(),
+            to: records[removed_end].clone(),
+            removed: records[i + 1..removed_end].to_vec(),
+            added: records[removed_end + 1..added_end].to_vec(),
+        });
+        i = added_end;
+    }
+}
+
+/// Apply `deltas` to a zone's records (SOA first), or `None` if they
+/// don't start from that zone's serial.
+fn apply_deltas(mut records: Vec<Record>, deltas: &[ZoneDelta]) -> Option<Vec<Record>> {
+    let mut serial = records.first().and_then(soa_serial)?;
+    for delta in deltas {
+        if soa_serial(&delta.from) != Some(serial) {
+            return None;
+        }
+        records.retain(|r| !delta.removed.contains(r));
+        records.extend(delta.added.iter().cloned());
+        records[0] = delta.to.clone();
+        serial = soa_serial(&delta.to)?;
+    }
+    Some(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::threat_authority;
+    use crate::authority::transfer::{ServedZone, TransferAcl, TransferHandler};
+    use crate::authority::zone_builder::{self, DefenseSnapshot};
+    use crate::config::ZoneConfig;
+    use crate::encoding::dnsbl::DnsblCode;
+    use crate::node::identity::{NodeIdentity, CERT_FILE, KEY_FILE};
+    use crate::server::ServedZones;
+    use crate::trust::mesh::PinnedTlsa;
+    use hickory_proto::rr::RData;
+    use hickory_server::authority::{Authority, LookupOptions};
+    use hickory_server::server::ServerFuture;
+    use hickory_server::store::in_memory::InMemoryAuthority;
+    use rcgen::{CertificateParams, KeyPair};
+    use std::net::Ipv4Addr;
+    use tokio::net::TcpListener;
+
+    fn zone(serial: u32, ips: &[&str]) -> InMemoryAuthority {
+        let origin = Name::parse("bl.i1.is.", None).unwrap();
+        let mut authority = threat_authority::create_zone(&origin, serial).unwrap();
+        for ip in ips {
+            threat_authority::insert_dnsbl_record(
+                &mut authority,
+                &ip.parse().unwrap(),
+                DnsblCode::Listed,
+                "bl.i1.is.",
+                serial,
+            )
+            .unwrap();
+        }
+        authority
+    }
+
+    fn snapshot(ips: &[&str]) -> DefenseSnapshot {
+        DefenseSnapshot {
+            blocked_ips: ips.iter().map(|ip| (*ip).to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Serve `zones` over TCP on an ephemeral loopback port.
+    async fn serve(zones: &ServedZones, acl: TransferAcl) -> SocketAddr {
+        let handler = TransferHandler::new(zones.catalog(), zones.transfer_zones(), acl);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = ServerFuture::new(handler);
+        server.register_listener(listener, Duration::from_secs(5));
+        tokio::spawn(async move { server.block_until_done().await });
+        addr
+    }
+
+    /// Serve `zones` over DNS-over-TLS as `identity` on loopback.
+    async fn serve_tls(zones: &ServedZones, identity: &NodeIdentity) -> SocketAddr {
+        let handler = TransferHandler::new(zones.catalog(), zones.transfer_zones(), loopback());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = ServerFuture::new(handler);
+        server
+            .register_tls_listener_with_tls_config(
+                listener,
+                Duration::from_secs(5),
+                crate::tls::server_config(identity, &[crate::tls::DOT_ALPN]).unwrap(),
+            )
+            .unwrap();
+        tokio::spawn(async move { server.block_until_done().await });
+        addr
+    }
+
+    /// A self-signed node identity for `name`.
+    fn identity(name: &str) -> NodeIdentity {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(CERT_FILE), cert.pem()).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), key.serialize_pem()).unwrap();
+        NodeIdentity::load_dir(dir.path()).unwrap()
+    }
+
+    fn loopback() -> TransferAcl {
+        TransferAcl::parse(&["127.0.0.1".into()]).unwrap()
+    }
+
+    async fn bl_answer(zones: &ServedZones, ip: &str) -> Option<RData> {
+        let name = Name::parse(
+            &format!(
+                "{}.bl.i1.is.",
+                crate::encoding::dnsbl::reverse_ip(&ip.parse().unwrap())
+            ),
+            None,
+        )
+        .unwrap();
+        let lookup = zones
+            .blocklist
+            .lookup(&name.into(), RecordType::A, LookupOptions::default())
+            .await;
+        let lookup = lookup.map_result().and_then(Result::ok)?;
+        lookup.iter().next().map(|r| r.data().clone())
+    }
+
+    #[test]
+    fn test_parse_axfr() {
+        let served = ServedZone::new(zone(3, &["1.2.3.4"]), 4);
+        let axfr = served.axfr();
+        assert_eq!(parse_transfer(&axfr[..2], None).unwrap(), None);
+        match parse_transfer(&axfr, None).unwrap() {
+            Some(TransferResponse::Full(records)) => assert_eq!(records.len(), 2),
+            other => panic!("expected full transfer, got {other:?}"),
+        }
+        assert!(parse_transfer(&axfr[1..], None).is_err());
+    }
+
+    #[test]
+    fn test_parse_ixfr() {
+        let served = ServedZone::new(zone(3, &["1.2.3.4"]), 4);
+        served.replace(zone(4, &["5.6.7.8"]));
+        served.replace(zone(5, &["5.6.7.8", "9.9.9.9"]));
+
+        let lone = served.ixfr(5).unwrap();
+        assert_eq!(
+            parse_transfer(&lone, Some(5)).unwrap(),
+            Some(TransferResponse::UpToDate)
+        );
+
+        let ixfr = served.ixfr(3).unwrap();
+        assert_eq!(
+            parse_transfer(&ixfr[..ixfr.len() - 1], Some(3)).unwrap(),
+            None
+        );
+        let Some(TransferResponse::Incremental(deltas)) = parse_transfer(&ixfr, Some(3)).unwrap()
+        else {
+            panic!("expected incremental transfer");
+        };
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].removed.len(), 1);
+        assert_eq!(deltas[1].added.len(), 1);
+
+        let base = ServedZone::new(zone(3, &["1.2.3.4"]), 4);
+        let mut applied = apply_deltas(base.records(), &deltas).unwrap();
+        let mut expected = served.records();
+        expected.sort();
+        applied.sort();
+        assert_eq!(applied, expected);
+
+        // Deltas that start elsewhere don't apply.
+        assert!(apply_deltas(ServedZone::new(zone(4, &[]), 4).records(), &deltas).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_secondary_converges() {
+        let config = ZoneConfig::default();
+        let primary = ServedZones::new(
+            zone_builder::build_zones(&snapshot(&["1.2.3.4", "5.6.7.8"]), &config, 100).unwrap(),
+            4,
+        );
+        let primary_addr = Primary::Tcp(serve(&primary, loopback()).await);
+
+        let secondary = ServedZones::new(
+            zone_builder::build_zones(&DefenseSnapshot::default(), &config, 0).unwrap(),
+            4,
+        );
+        let bl = secondary.transfer_zones().remove(0);
+        assert_eq!(bl.name(), primary.blocklist.name());
+
+        // Bootstrap: our serial 0 isn't in the primary's journal.
+        let outcome = refresh_zone(&primary_addr, bl.as_ref()).await.unwrap();
+        assert_eq!(outcome, RefreshOutcome::Full(100));
+        assert!(bl_answer(&secondary, "5.6.7.8").await.is_some());
+        assert_eq!(
+            refresh_zone(&primary_addr, bl.as_ref()).await.unwrap(),
+            RefreshOutcome::UpToDate
+        );
+
+        // Primary adds 9.9.9.9 and drops 5.6.7.8.
+        primary.replace(
+            zone_builder::build_zones(&snapshot(&["1.2.3.4", "9.9.9.9"]), &config, 101).unwrap(),
+        );
+        let outcome = refresh_zone(&primary_addr, bl.as_ref()).await.unwrap();
+        assert_eq!(outcome, RefreshOutcome::Incremental(101));
+        assert_eq!(
+            bl_answer(&secondary, "9.9.9.9").await,
+            Some(RData::A(Ipv4Addr::LOCALHOST.into()))
+        );
+        assert!(bl_answer(&secondary, "5.6.7.8").await.is_none());
+        assert_eq!(bl.records().len(), primary.blocklist.records().len());
+
+        // The converged secondary serves the zone itself.
+        let secondary_addr = serve(&secondary, TransferAcl::default()).await;
+        let mut request = Message::new();
+        request.set_id(7).add_query(Query::query(
+            Name::parse("9.9.9.9.bl.i1.is.", None).unwrap(),
+            RecordType::A,
+        ));
+        let bytes = request.to_vec().unwrap();
+        let mut stream = TcpStream::connect(secondary_addr).await.unwrap();
+        stream
+            .write_u16(u16::try_from(bytes.len()).unwrap())
+            .await
+            .unwrap();
+        stream.write_all(&bytes).await.unwrap();
+        let len = stream.read_u16().await.unwrap();
+        let mut buf = vec![0u8; usize::from(len)];
+        stream.read_exact(&mut buf).await.unwrap();
+        let response = Message::from_vec(&buf).unwrap();
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert_eq!(response.answers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_refused_outside_acl() {
+        let primary = ServedZones::new(
+            zone_builder::build_zones(&snapshot(&["1.2.3.4"]), &ZoneConfig::default(), 1).unwrap(),
+            4,
+        );
+        let addr = serve(&primary, TransferAcl::default()).await;
+        let secondary = ServedZone::new(zone(0, &[]), 4);
+
+        let err = refresh_zone(&Primary::Tcp(addr), &secondary)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Refused"), "{err}");
+        assert_eq!(secondary.serial(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_primary_transfers_over_tls() {
+        const NODE: &str = "node1.srv.i1.is";
+        let primary = ServedZones::new(
+            zone_builder::build_zones(&snapshot(&["1.2.3.4"]), &ZoneConfig::default(), 7).unwrap(),
+            4,
+        );
+        let node = identity(NODE);
+        let addr = serve_tls(&primary, &node).await;
+        let pinned = |cert| {
+            let mut pins = PinnedTlsa::default();
+            pins.pin_spki(NODE, cert);
+            Primary::Pinned(PrimaryPin {
+                verifier: Arc::new(PeerVerifier::new(Arc::new(pins), false).unwrap()),
+                name: NODE.into(),
+                addr,
+            })
+        };
+
+        // A certificate the TLSA record doesn't vouch for: nothing is taken.
+        let secondary = ServedZone::new(zone(0, &[]), 4);
+        let impostor = identity(NODE);
+        let err = refresh_zone(&pinned(&impostor.chain()[0]), &secondary)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::SrvError::Trust(_)), "{err}");
+        assert_eq!(secondary.serial(), Some(0));
+
+        let outcome = refresh_zone(&pinned(&node.chain()[0]), &secondary)
+            .await
+            .unwrap();
+        assert_eq!(outcome, RefreshOutcome::Full(7));
+    }
+}
@@ -0,0 +1,369 @@
+//! Encrypted DNS transports: DNS-over-TLS (RFC 7858) and DNS-over-HTTPS
+//! (RFC 8484).
+//!
+//! Plain UDP/TCP DNS is easy for middleboxes to observe and block. Both
+//! encrypted listeners hand queries to the same request handler as the
+//! plain sockets, so they answer from the same authorities. DNS-over-TLS
+//! uses hickory's TLS listener. DNS-over-HTTPS is served here with hyper
+//! because hickory's endpoint only accepts POST over HTTP/2, while
+//! RFC 8484 clients also send GET and use HTTP/1.1.
+
+use async_trait::async_trait;
+use base64::Engine;
+use bytes::Bytes;
+use hickory_proto::rr::Record;
+use hickory_proto::serialize::binary::{BinDecodable, BinEncoder};
+use hickory_proto::xfer::Protocol;
+use hickory_server::authority::{MessageRequest, MessageResponse};
+use hickory_server::server::{Request, RequestHandler, ResponseHandler, ResponseInfo};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::Incoming;
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::service::service_fn;
+use hyper::{Method, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, warn};
+
+use crate::node::identity::NodeIdentity;
+
+/// Path DNS-over-HTTPS queries are served on.
+pub const DOH_PATH: &str = "/dns-query";
+
+/// ALPN protocol for DNS-over-TLS (RFC 7858).
+pub const DOT_ALPN: &[u8] = b"dot";
+
+/// ALPN protocols for DNS-over-HTTPS.
+pub const DOH_ALPN: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// Media type of DNS-over-HTTPS request and response bodies.
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// Largest DNS message an HTTPS request may carry.
+const MAX_MESSAGE: usize = 65_535;
+
+/// How long an HTTPS client gets to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build a rustls server config presenting `identity` with the given ALPN protocols.
+pub fn server_config(
+    identity: &NodeIdentity,
+    alpn: &[&[u8]],
+) -> crate::Result<Arc<rustls::ServerConfig>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| {
+            builder
+                .with_no_client_auth()
+                .with_single_cert(identity.chain().to_vec(), identity.key())
+        })
+        .map_err(|e| crate::SrvError::Identity(format!("invalid TLS identity: {e}")))?;
+    config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    Ok(Arc::new(config))
+}
+
+/// Serve DNS-over-HTTPS on `listener` until the task is dropped.
+pub async fn serve_doh<T: RequestHandler>(
+    listener: TcpListener,
+    tls: Arc<rustls::ServerConfig>,
+    handler: T,
+) {
+    let acceptor = TlsAcceptor::from(tls);
+    let handler = Arc::new(handler);
+    loop {
+        let (stream, src) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!(error = %e, "DoH accept failed");
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let handler = Arc::clone(&handler);
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        debug!(%src, error = %e, "DoH TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        debug!(%src, "DoH TLS handshake timed out");
+                        return;
+                    }
+                };
+            let service = service_fn(move |request| {
+                let handler = Arc::clone(&handler);
+                async move { Ok::<_, Infallible>(answer_doh(request, src, handler.as_ref()).await) }
+            });
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!(%src, error = %e, "DoH connection closed with error");
+            }
+        });
+    }
+}
+
+/// Answer one DNS-over-HTTPS request: `GET ?dns=<base64url>` or `POST` wire format.
+async fn answer_doh<T: RequestHandler>(
+    request: hyper::Request<Incoming>,
+    src: SocketAddr,
+    handler: &T,
+) -> hyper::Response<Full<Bytes>> {
+    if request.uri().path() != DOH_PATH {
+        return status(StatusCode::NOT_FOUND);
+    }
+    let wire = match *request.method() {
+        Method::GET => {
+            let query = request.uri().query().and_then(|q| {
+                q.split('&')
+                    .find_map(|pair| pair.strip_prefix("dns="))
+                    .and_then(|b64| {
+                        base64::engine::general_purpose::URL_SAFE_NO_PAD
+                            .decode(b64)
+                            .ok()
+                    })
+            });
+            match query {
+                Some(wire) => Bytes::from(wire),
+                None => return status(StatusCode::BAD_REQUEST),
+            }
+        }
+        Method::POST => {
+            let content_type = request.headers().get(CONTENT_TYPE);
+            if !content_type.is_some_and(|v| v == DNS_MESSAGE) {
+                return status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            }
+            match Limited::new(request.into_body(), MAX_MESSAGE)
+                .collect()
+                .await
+            {
+                Ok(body) => body.to_bytes(),
+                Err(_) => return status(StatusCode::PAYLOAD_TOO_LARGE),
+            }
+        }
+        _ => return status(StatusCode::METHOD_NOT_ALLOWED),
+    };
+
+    let Ok(message) = MessageRequest::from_bytes(&wire) else {
+        return status(StatusCode::BAD_REQUEST);
+    };
+    let response = CapturedResponse::default();
+    handler
+        .handle_request(
+            &Request::new(message, src, Protocol::Https),
+            response.clone(),
+        )
+        .await;
+    response.take().map_or_else(
+        || status(StatusCode::INTERNAL_SERVER_ERROR),
+        |body| {
+            hyper::Response::builder()
+                .header(CONTENT_TYPE, DNS_MESSAGE)
+                .body(Full::new(Bytes::from(body)))
+                .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR))
+        },
+    )
+}
+
+fn status(code: StatusCode) -> hyper::Response<Full<Bytes>> {
+    let mut response = hyper::Response::new(Full::default());
+    *response.status_mut() = code;
+    if code == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+        if let Ok(value) = DNS_MESSAGE.parse() {
+            response.headers_mut().insert(ACCEPT, value);
+        }
+    }
+    response
+}
+
+/// Response handler that keeps the encoded message for the HTTP body.
+#[derive(Clone, Default)]
+struct CapturedResponse(Arc<Mutex<Option<Vec<u8>>>>);
+
+impl CapturedResponse {
+    fn take(&self) -> Option<Vec<u8>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+}
+
+#[async_trait]
+impl ResponseHandler for CapturedResponse {
+    async fn send_response<'a>(
+        &mut self,
+        response: MessageResponse<
+            '_,
+            'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+            impl Iterator<Item = &'a Record> + Send + 'a,
+        >,
+    ) -> std::io::Result<ResponseInfo> {
+        let mut bytes = Vec::with_capacity(512);
+        let info = response
+            .destructive_emit(&mut BinEncoder::new(&mut bytes))
+            .map_err(std::io::Error::other)?;
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(bytes);
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::transfer::{TransferAcl, TransferHandler};
+    use crate::authority::zone_builder::{self, DefenseSnapshot};
+    use crate::config::ZoneConfig;
+    use crate::node::identity::{CERT_FILE, KEY_FILE};
+    use crate::server::ServedZones;
+    use hickory_proto::op::{Message, Query};
+    use hickory_proto::rr::{Name, RData, RecordType};
+    use hickory_server::server::ServerFuture;
+    use rustls_pki_types::pem::PemObject;
+    use rustls_pki_types::{CertificateDer, ServerName};
+    use std::net::Ipv4Addr;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+
+    /// Self-signed `localhost` identity, loaded from an identity directory.
+    fn identity() -> (NodeIdentity, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(CERT_FILE), cert.cert.pem()).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), cert.key_pair.serialize_pem()).unwrap();
+        (NodeIdentity::load_dir(dir.path()).unwrap(), cert.cert.pem())
+    }
+
+    fn handler() -> TransferHandler {
+        let snapshot = DefenseSnapshot {
+            blocked_ips: vec!["1.2.3.4".into()],
+            ..Default::default()
+        };
+        let zones = ServedZones::new(
+            zone_builder::build_zones(&snapshot, &ZoneConfig::default(), 1).unwrap(),
+            4,
+        );
+        TransferHandler::new(
+            zones.catalog(),
+            zones.transfer_zones(),
+            TransferAcl::default(),
+        )
+    }
+
+    fn query() -> Vec<u8> {
+        let mut message = Message::new();
+        message.add_query(Query::query(
+            Name::parse("4.3.2.1.bl.i1.is.", None).unwrap(),
+            RecordType::A,
+        ));
+        message.to_vec().unwrap()
+    }
+
+    fn assert_listed(wire: &[u8]) {
+        let response = Message::from_vec(wire).unwrap();
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(
+            response.answers()[0].data(),
+            &RData::A(Ipv4Addr::LOCALHOST.into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_doh_get_and_post() {
+        let (identity, pem) = identity();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let tls = server_config(&identity, &DOH_ALPN).unwrap();
+        tokio::spawn(serve_doh(listener, tls, handler()));
+
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(pem.as_bytes()).unwrap())
+            .resolve("localhost", addr)
+            .build()
+            .unwrap();
+        let url = format!("https://localhost:{}{DOH_PATH}", addr.port());
+
+        let post = client
+            .post(&url)
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .body(query())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::OK.as_u16());
+        assert_eq!(post.headers()[CONTENT_TYPE.as_str()], DNS_MESSAGE);
+        assert_listed(&post.bytes().await.unwrap());
+
+        let dns = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(query());
+        let get = client.get(format!("{url}?dns={dns}")).send().await.unwrap();
+        assert_eq!(get.status(), StatusCode::OK.as_u16());
+        assert_listed(&get.bytes().await.unwrap());
+
+        let untyped = client.post(&url).body(query()).send().await.unwrap();
+        assert_eq!(
+            untyped.status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE.as_u16()
+        );
+        let elsewhere = format!("https://localhost:{}/", addr.port());
+        let missing = client.get(elsewhere).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+
+    #[tokio::test]
+    async fn test_dot_query() {
+        let (identity, pem) = identity();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut dns = ServerFuture::new(handler());
+        dns.register_tls_listener_with_tls_config(
+            listener,
+            Duration::from_secs(5),
+            server_config(&identity, &[DOT_ALPN]).unwrap(),
+        )
+        .unwrap();
+        tokio::spawn(async move { dns.block_until_done().await });
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_slice(pem.as_bytes()).unwrap())
+            .unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![DOT_ALPN.to_vec()];
+
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), tcp)
+            .await
+            .unwrap();
+        let wire = query();
+        stream
+            .write_u16(u16::try_from(wire.len()).unwrap())
+            .await
+            .unwrap();
+        stream.write_all(&wire).await.unwrap();
+        stream.flush().await.unwrap();
+
+        let len = stream.read_u16().await.unwrap();
+        let mut buf = vec![0u8; usize::from(len)];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_listed(&buf);
+    }
+}
@@ -6,5 +6,753 @@
 //! 3. Client connects to node1, gets its TLS cert
 //! 4. Computes SHA-256 of cert, compares to TLSA record
 //! 5. Match = trusted. Mismatch = MITM alert.
+//!
+//! Sync peers run the same check on each other before accepting state:
+//! gossip members on the certificate from their own handshake, and a
+//! secondary on its transfer primary's DNS-over-TLS certificate, on the
+//! connection it then transfers the zones over. Only end-entity records
+//! (usage 1 or 3) hashing with SHA-256 are compared, over either the full
+//! certificate (`3 0 1`) or its public key (`3 1 1`); other selectors and
+//! matching types are ignored.
+//!
+//! A match is cached for the TLSA record's TTL. A peer publishing no
+//! record at all is refused, unless the verifier is set to fail open.
+
+use async_trait::async_trait;
+use hickory_proto::rr::rdata::tlsa::{CertUsage, Matching, Selector};
+use hickory_proto::rr::{RData, RecordType};
+use hickory_resolver::config::{NameServerConfig, NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::TokioResolver;
+use ring::digest::{digest, SHA256};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, warn};
+
+use crate::metrics::{Collector, Exposition, Kind};
+use crate::tls::DOT_ALPN;
+
+/// Upper bound for connecting to a peer and completing the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// SHA-256 of a DER certificate, as published in a `3 0 1` TLSA record.
+#[must_use]
+pub fn tlsa_hash(cert: &CertificateDer<'_>) -> [u8; 32] {
+    sha256(cert.as_ref())
+}
+
+/// SHA-256 of a certificate's `SubjectPublicKeyInfo`, as published in a
+/// `3 1 1` TLSA record, or `None` if the certificate can't be parsed.
+#[must_use]
+pub fn spki_hash(cert: &CertificateDer<'_>) -> Option<[u8; 32]> {
+    let cert = webpki::EndEntityCert::try_from(cert).ok()?;
+    Some(sha256(cert.subject_public_key_info().as_ref()))
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest(&SHA256, data).as_ref());
+    hash
+}
+
+/// Owner name of a node's TLSA record for a service port.
+#[must_use]
+pub fn tlsa_name(node: &str, port: u16) -> String {
+    format!("_{port}._tcp.{}.", node.trim_end_matches('.'))
+}
+
+/// The part of a certificate a TLSA record hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsaSelector {
+    /// The whole DER certificate (selector 0).
+    Cert,
+    /// The `SubjectPublicKeyInfo` (selector 1), which survives reissuance
+    /// under the same key.
+    Spki,
+}
+
+/// One usable TLSA record: a SHA-256 hash over the selected part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlsaPin {
+    /// What the hash covers.
+    pub selector: TlsaSelector,
+    /// SHA-256 hash.
+    pub hash: [u8; 32],
+}
+
+impl TlsaPin {
+    /// Whether `cert` is the certificate (or key) this record pins.
+    #[must_use]
+    pub fn matches(&self, cert: &CertificateDer<'_>) -> bool {
+        match self.selector {
+            TlsaSelector::Cert => tlsa_hash(cert) == self.hash,
+            TlsaSelector::Spki => spki_hash(cert) == Some(self.hash),
+        }
+    }
+}
+
+/// The usable TLSA records published for a node and port.
+#[derive(Debug, Clone, Default)]
+pub struct TlsaRecords {
+    /// Records we can check; empty when none are published.
+    pub pins: Vec<TlsaPin>,
+    /// Seconds a match may be cached.
+    pub ttl: u32,
+}
+
+/// Where published TLSA hashes come from.
+#[async_trait]
+pub trait TlsaSource: Send + Sync {
+    /// Usable TLSA records published for `node` on `port`. A name with no
+    /// records is not an error: it yields no pins.
+    async fn tlsa_records(&self, node: &str, port: u16) -> crate::Result<TlsaRecords>;
+}
+
+/// Where a node's own TLSA records are published.
+///
+/// Used by [`identity::rotate`](crate::node::identity::rotate) to announce
+/// a new key before the node presents it.
+#[async_trait]
+pub trait TlsaPublisher: Send + Sync {
+    /// Replace the TLSA records published for `node`, on every port it
+    /// serves, with `pins`.
+    async fn publish(&self, node: &str, pins: &[TlsaPin]) -> crate::Result<()>;
+}
+
+/// Looks TLSA records up in DNS.
+///
+/// Point the resolver at a different server than the one being checked,
+/// so a single compromised node can't vouch for itself.
+pub struct DnsTlsa {
+    resolver: TokioResolver,
+}
+
+impl DnsTlsa {
+    /// Use a configured resolver.
+    #[must_use]
+    pub const fn new(resolver: TokioResolver) -> Self {
+        Self { resolver }
+    }
+
+    /// Use the system resolver configuration.
+    pub fn system() -> crate::Result<Self> {
+        let resolver = TokioResolver::builder_tokio()
+            .map_err(|e| crate::SrvError::DnsQuery(format!("system resolver: {e}")))?
+            .build();
+        Ok(Self::new(resolver))
+    }
+
+    /// Ask `servers` (over UDP, then TCP), or the system resolver when
+    /// there are none.
+    pub fn with_servers(servers: &[SocketAddr]) -> crate::Result<Self> {
+        if servers.is_empty() {
+            return Self::system();
+        }
+        let mut group = NameServerConfigGroup::new();
+        for server in servers {
+            group.push(NameServerConfig::new(
+                *server,
+                hickory_proto::xfer::Protocol::Udp,
+            ));
+            group.push(NameServerConfig::new(
+                *server,
+                hickory_proto::xfer::Protocol::Tcp,
+            ));
+        }
+        let config = ResolverConfig::from_parts(None, vec![], group);
+        let resolver =
+            TokioResolver::builder_with_config(config, TokioConnectionProvider::default()).build();
+        Ok(Self::new(resolver))
+    }
+}
+
+#[async_trait]
+impl TlsaSource for DnsTlsa {
+    async fn tlsa_records(&self, node: &str, port: u16) -> crate::Result<TlsaRecords> {
+        let name = tlsa_name(node, port);
+        let lookup = match self.resolver.lookup(name.as_str(), RecordType::TLSA).await {
+            Ok(lookup) => lookup,
+            Err(e) if e.is_no_records_found() || e.is_nx_domain() => {
+                return Ok(TlsaRecords::default())
+            }
+            Err(e) => return Err(crate::SrvError::DnsQuery(format!("TLSA {name}: {e}"))),
+        };
+        let ttl = lookup
+            .records()
+            .iter()
+            .map(hickory_proto::rr::Record::ttl)
+            .min()
+            .unwrap_or(0);
+        let pins = lookup
+            .iter()
+            .filter_map(|rdata| match rdata {
+                RData::TLSA(tlsa)
+                    if matches!(tlsa.cert_usage(), CertUsage::DaneEe | CertUsage::PkixEe)
+                        && tlsa.matching() == Matching::Sha256 =>
+                {
+                    let selector = match tlsa.selector() {
+                        Selector::Full => TlsaSelector::Cert,
+                        Selector::Spki => TlsaSelector::Spki,
+                        _ => return None,
+                    };
+                    let hash = tlsa.cert_data().try_into().ok()?;
+                    Some(TlsaPin { selector, hash })
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(TlsaRecords { pins, ttl })
+    }
+}
+
+/// Fixed TLSA hashes, for pinned meshes and tests.
+#[derive(Debug, Clone, Default)]
+pub struct PinnedTlsa {
+    pins: BTreeMap<String, Vec<TlsaPin>>,
+    ttl: u32,
+}
+
+impl PinnedTlsa {
+    /// Pin `cert` as a valid certificate for `node` (any port), like `3 0 1`.
+    pub fn pin(&mut self, node: impl Into<String>, cert: &CertificateDer<'_>) {
+        self.push(
+            node,
+            TlsaPin {
+                selector: TlsaSelector::Cert,
+                hash: tlsa_hash(cert),
+            },
+        );
+    }
+
+    /// Pin `cert`'s public key for `node` (any port), like `3 1 1`.
+    pub fn pin_spki(&mut self, node: impl Into<String>, cert: &CertificateDer<'_>) {
+        if let Some(hash) = spki_hash(cert) {
+            self.push(
+                node,
+                TlsaPin {
+                    selector: TlsaSelector::Spki,
+                    hash,
+                },
+            );
+        }
+    }
+
+    /// Report `ttl` seconds as the records' TTL (default 0: never cached).
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn push(&mut self, node: impl Into<String>, pin: TlsaPin) {
+        self.pins.entry(node.into()).or_default().push(pin);
+    }
+}
+
+#[async_trait]
+impl TlsaSource for PinnedTlsa {
+    async fn tlsa_records(&self, node: &str, _port: u16) -> crate::Result<TlsaRecords> {
+        Ok(TlsaRecords {
+            pins: self.pins.get(node).cloned().unwrap_or_default(),
+            ttl: self.ttl,
+        })
+    }
+}
+
+/// Why a peer failed verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DaneFailure {
+    /// The TLS connection or handshake failed.
+    Handshake,
+    /// The TLSA lookup failed.
+    Lookup,
+    /// No usable TLSA record is published.
+    NoRecord,
+    /// The certificate matches none of the published records.
+    Mismatch,
+}
+
+impl DaneFailure {
+    /// Metric label.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Handshake => "handshake",
+            Self::Lookup => "lookup",
+            Self::NoRecord => "no_record",
+            Self::Mismatch => "mismatch",
+        }
+    }
+}
+
+/// A peer that passed verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PeerVerification {
+    /// Node name the certificate was checked for.
+    pub node: String,
+    /// Port whose TLSA record was consulted.
+    pub port: u16,
+    /// Selector of the matching record; `None` when the peer publishes no
+    /// record and was accepted because the verifier fails open.
+    pub matched: Option<TlsaSelector>,
+    /// Answered from a cached match rather than a fresh lookup.
+    pub cached: bool,
+}
+
+/// A cached match: which leaf matched, until when.
+struct CachedMatch {
+    leaf: [u8; 32],
+    selector: TlsaSelector,
+    expires: Instant,
+}
+
+#[derive(Default)]
+struct Counters {
+    verified: usize,
+    cached: usize,
+    unpinned: usize,
+    failures: BTreeMap<DaneFailure, usize>,
+}
+
+/// Checks sync peers against their TLSA records, caching matches.
+pub struct PeerVerifier {
+    source: Arc<dyn TlsaSource>,
+    fail_open: bool,
+    connector: TlsConnector,
+    cache: Mutex<HashMap<(String, u16), CachedMatch>>,
+    counters: Mutex<Counters>,
+}
+
+impl std::fmt::Debug for PeerVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerVerifier")
+            .field("fail_open", &self.fail_open)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PeerVerifier {
+    /// Verify against `source`. With `fail_open`, peers publishing no TLSA
+    /// record are accepted (and counted) instead of refused.
+    pub fn new(source: Arc<dyn TlsaSource>, fail_open: bool) -> crate::Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| crate::SrvError::Trust(format!("DANE client config: {e}")))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(DaneServerCert { provider }))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![DOT_ALPN.to_vec()];
+        Ok(Self {
+            source,
+            fail_open,
+            connector: TlsConnector::from(Arc::new(config)),
+            cache: Mutex::new(HashMap::new()),
+            counters: Mutex::new(Counters::default()),
+        })
+    }
+
+    /// Connect to `peer_addr` over TLS and check the certificate it
+    /// presents against the TLSA record for `expected_name` on that port.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SrvError::Trust` if the handshake fails or the certificate
+    /// isn't vouched for, or the lookup error if the records can't be
+    /// fetched.
+    pub async fn verify_peer(
+        &self,
+        peer_addr: SocketAddr,
+        expected_name: &str,
+    ) -> crate::Result<PeerVerification> {
+        self.connect(peer_addr, expected_name)
+            .await
+            .map(|(_, verification)| verification)
+    }
+
+    /// Connect and verify as [`verify_peer`](Self::verify_peer) does, and
+    /// keep the connection, to talk to the peer over.
+    ///
+    /// # Errors
+    ///
+    /// As [`verify_peer`](Self::verify_peer).
+    pub async fn connect(
+        &self,
+        peer_addr: SocketAddr,
+        expected_name: &str,
+    ) -> crate::Result<(TlsStream<TcpStream>, PeerVerification)> {
+        let (tls, leaf) = match self.handshake(peer_addr, expected_name).await {
+            Ok(handshake) => handshake,
+            Err(reason) => {
+                return Err(self.refuse(
+                    expected_name,
+                    peer_addr.port(),
+                    DaneFailure::Handshake,
+                    reason,
+                ))
+            }
+        };
+        let verification = self
+            .verify_cert(expected_name, peer_addr.port(), &leaf)
+            .await?;
+        Ok((tls, verification))
+    }
+
+    /// Check `cert`, presented by `node` on `port`, against its TLSA record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SrvError::Trust` if no record matches (including when none
+    /// is published and the verifier fails closed), or the lookup error if
+    /// the records can't be fetched.
+    pub async fn verify_cert(
+        &self,
+        node: &str,
+        port: u16,
+        cert: &CertificateDer<'_>,
+    ) -> crate::Result<PeerVerification> {
+        let key = (node.to_string(), port);
+        let leaf = tlsa_hash(cert);
+        let hit = self
+            .lock_cache()
+            .get(&key)
+            .filter(|cached| cached.leaf == leaf && cached.expires > Instant::now())
+            .map(|cached| cached.selector);
+        if let Some(selector) = hit {
+            self.lock_counters().cached += 1;
+            return Ok(PeerVerification {
+                node: node.to_string(),
+                port,
+                matched: Some(selector),
+                cached: true,
+            });
+        }
+
+        let records = match self.source.tlsa_records(node, port).await {
+            Ok(records) => records,
+            Err(e) => {
+                warn!(node, port, error = %e, "TLSA lookup failed");
+                *self
+                    .lock_counters()
+                    .failures
+                    .entry(DaneFailure::Lookup)
+                    .or_default() += 1;
+                return Err(e);
+            }
+        };
+
+        if records.pins.is_empty() {
+            if self.fail_open {
+                warn!(node, port, "no TLSA record, accepting peer (fail-open)");
+                self.lock_counters().unpinned += 1;
+                return Ok(PeerVerification {
+                    node: node.to_string(),
+                    port,
+                    matched: None,
+                    cached: false,
+                });
+            }
+            let reason = format!("no TLSA record for {}", tlsa_name(node, port));
+            return Err(self.refuse(node, port, DaneFailure::NoRecord, reason));
+        }
+
+        let Some(pin) = records.pins.iter().find(|pin| pin.matches(cert)) else {
+            self.lock_cache().remove(&key);
+            let reason = format!("certificate presented by {node} does not match its TLSA record");
+            return Err(self.refuse(node, port, DaneFailure::Mismatch, reason));
+        };
+
+        debug!(node, port, selector = ?pin.selector, ttl = records.ttl, "peer matches TLSA");
+        if records.ttl > 0 {
+            self.lock_cache().insert(
+                key,
+                CachedMatch {
+                    leaf,
+                    selector: pin.selector,
+                    expires: Instant::now() + Duration::from_secs(u64::from(records.ttl)),
+                },
+            );
+        }
+        self.lock_counters().verified += 1;
+        Ok(PeerVerification {
+            node: node.to_string(),
+            port,
+            matched: Some(pin.selector),
+            cached: false,
+        })
+    }
+
+    /// Connect, returning the connection and the leaf certificate the
+    /// peer presents.
+    async fn handshake(
+        &self,
+        peer_addr: SocketAddr,
+        expected_name: &str,
+    ) -> Result<(TlsStream<TcpStream>, CertificateDer<'static>), String> {
+        let server_name = ServerName::try_from(expected_name.to_string())
+            .map_err(|e| format!("invalid node name {expected_name}: {e}"))?;
+        let connect = async {
+            let tcp = TcpStream::connect(peer_addr).await?;
+            self.connector.connect(server_name, tcp).await
+        };
+        let tls = tokio::time::timeout(HANDSHAKE_TIMEOUT, connect)
+            .await
+            .map_err(|_| format!("TLS handshake with {peer_addr} timed out"))?
+            .map_err(|e| format!("TLS handshake with {peer_addr}: {e}"))?;
+        let leaf = tls
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(<[_]>::first)
+            .map(|leaf| leaf.clone().into_owned())
+            .ok_or_else(|| format!("{peer_addr} presented no certificate"))?;
+        Ok((tls, leaf))
+    }
+
+    /// Log and count a refusal, and return the error to report.
+    fn refuse(
+        &self,
+        node: &str,
+        port: u16,
+        failure: DaneFailure,
+        reason: String,
+    ) -> crate::SrvError {
+        warn!(node, port, failure = failure.as_str(), %reason, "refusing peer");
+        *self.lock_counters().failures.entry(failure).or_default() += 1;
+        crate::SrvError::Trust(reason)
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, HashMap<(String, u16), CachedMatch>> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Collector for PeerVerifier {
+    fn collect(&self, out: &mut Exposition) {
+        let counters = self.lock_counters();
+        out.family(
+            "i1_dane_verifications_total",
+            Kind::Counter,
+            "Sync peers accepted after DANE verification.",
+        )
+        .sample(
+            "i1_dane_verifications_total",
+            &[("result", "verified")],
+            counters.verified,
+        )
+        .sample(
+            "i1_dane_verifications_total",
+            &[("result", "cached")],
+            counters.cached,
+        )
+        .sample(
+            "i1_dane_verifications_total",
+            &[("result", "unpinned")],
+            counters.unpinned,
+        );
+        out.family(
+            "i1_dane_failures_total",
+            Kind::Counter,
+            "Sync peers refused by DANE verification, by reason.",
+        );
+        for failure in [
+            DaneFailure::Handshake,
+            DaneFailure::Lookup,
+            DaneFailure::NoRecord,
+            DaneFailure::Mismatch,
+        ] {
+            out.sample(
+                "i1_dane_failures_total",
+                &[("reason", failure.as_str())],
+                counters.failures.get(&failure).copied().unwrap_or(0),
+            );
+        }
+        drop(counters);
+    }
+}
+
+/// Accepts whatever certificate the peer presents (while still checking
+/// handshake signatures): the TLSA record, not a CA, decides trust.
+#[derive(Debug)]
+struct DaneServerCert {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for DaneServerCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::identity::{NodeIdentity, CERT_FILE, KEY_FILE};
+    use rcgen::{CertificateParams, KeyPair};
+    use std::net::Ipv4Addr;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    const NODE: &str = "node1.srv.i1.is";
+
+    fn identity(name: &str) -> NodeIdentity {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(CERT_FILE), cert.pem()).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE), key.serialize_pem()).unwrap();
+        NodeIdentity::load_dir(dir.path()).unwrap()
+    }
+
+    /// Serve DNS-over-TLS handshakes as `identity` on loopback.
+    async fn serve(identity: &NodeIdentity) -> SocketAddr {
+        let acceptor = TlsAcceptor::from(crate::tls::server_config(identity, &[DOT_ALPN]).unwrap());
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = acceptor.accept(stream).await;
+            }
+        });
+        addr
+    }
+
+    fn pinned(pins: PinnedTlsa, fail_open: bool) -> PeerVerifier {
+        PeerVerifier::new(Arc::new(pins), fail_open).unwrap()
+    }
+
+    fn failures(verifier: &PeerVerifier, failure: DaneFailure) -> usize {
+        verifier
+            .lock_counters()
+            .failures
+            .get(&failure)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn test_matching_pin_is_cached() {
+        let node = identity(NODE);
+        let addr = serve(&node).await;
+        let mut pins = PinnedTlsa::default().with_ttl(300);
+        pins.pin_spki(NODE, &node.chain()[0]);
+        let verifier = pinned(pins, false);
+
+        let first = verifier.verify_peer(addr, NODE).await.unwrap();
+        assert_eq!(first.matched, Some(TlsaSelector::Spki));
+        assert!(!first.cached);
+        assert_eq!(first.port, addr.port());
+
+        let second = verifier.verify_peer(addr, NODE).await.unwrap();
+        assert!(second.cached);
+
+        // A full-certificate pin works too, with nothing cached at TTL 0.
+        let mut pins = PinnedTlsa::default();
+        pins.pin(NODE, &node.chain()[0]);
+        let verifier = pinned(pins, false);
+        let result = verifier.verify_cert(NODE, 853, &node.chain()[0]).await;
+        assert_eq!(result.unwrap().matched, Some(TlsaSelector::Cert));
+        assert!(verifier.lock_cache().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_pin_is_refused() {
+        let node = identity(NODE);
+        let addr = serve(&node).await;
+        let mut pins = PinnedTlsa::default().with_ttl(300);
+        pins.pin_spki(NODE, &identity(NODE).chain()[0]);
+        let verifier = pinned(pins, true);
+
+        let err = verifier.verify_peer(addr, NODE).await.unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+        assert_eq!(failures(&verifier, DaneFailure::Mismatch), 1);
+        assert!(verifier.lock_cache().is_empty());
+
+        // Nobody listening: the handshake failure is counted separately.
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        assert!(verifier.verify_peer(closed, NODE).await.is_err());
+        assert_eq!(failures(&verifier, DaneFailure::Handshake), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_tlsa_follows_policy() {
+        let node = identity(NODE);
+        let cert = &node.chain()[0];
+
+        let closed = pinned(PinnedTlsa::default(), false);
+        let err = closed.verify_cert(NODE, 853, cert).await.unwrap_err();
+        assert!(
+            err.to_string().contains("_853._tcp.node1.srv.i1.is."),
+            "{err}"
+        );
+        assert_eq!(failures(&closed, DaneFailure::NoRecord), 1);
 
-// TODO: Phase 2 - implement DANE cross-verification
+        let open = pinned(PinnedTlsa::default(), true);
+        let accepted = open.verify_cert(NODE, 853, cert).await.unwrap();
+        assert_eq!(accepted.matched, None);
+        assert_eq!(open.lock_counters().unpinned, 1);
+        assert_eq!(failures(&open, DaneFailure::NoRecord), 0);
+    }
+}
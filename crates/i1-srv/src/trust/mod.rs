@@ -2,6 +2,8 @@
 //!
 //! - **Mesh**: Cross-node DANE/TLSA verification ("ask another server").
 //! - **TTL Monitor**: Detects TTL manipulation by DNS resolvers.
+//! - **Node Signals**: Trust digests published for nodes without a DNS server.
 
 pub mod mesh;
+pub mod node_signals;
 pub mod ttl_monitor;
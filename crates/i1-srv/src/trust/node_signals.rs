@@ -0,0 +1,308 @@
+//! Signal records published on other nodes' behalf.
+//!
+//! A node that runs `i1 audit verify` but no DNS server of its own needs
+//! somewhere to publish its trust digest. It sends the TXT value
+//! [`build_signal_txt`](i1_audit::verify::build_signal_txt) wrote to a
+//! primary's admin API (`PUT /v1/signals/{prefix}`), which serves it as
+//! `<prefix>.sig.i1.is. TXT` with the signal zone's near-zero TTL:
+//!
+//! ```text
+//! a3f2b8c91d4e.sig.i1.is. 30 TXT "digest=a3f2b8c91d4e...;ts=1760000000;bins=412;certs=146"
+//! ```
+//!
+//! A record is refused unless it parses as a
+//! [`SignalRecord`](i1_audit::verify::SignalRecord), its digest starts
+//! with the prefix it is published under, and its timestamp is no older
+//! than the configured maximum age. Each prefix may be updated once per
+//! minimum interval. Records are dropped once their timestamp passes the
+//! maximum age, so a node that stops publishing stops verifying.
+
+use chrono::{DateTime, Utc};
+use i1_audit::verify::{SignalRecord, NODE_PREFIX_LEN};
+use i1_audit::AuditError;
+use i1_core::wire::SchemaMismatch;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::config::NodeSignalConfig;
+use crate::sync::reload::RebuildHandle;
+
+/// Furthest a record's timestamp may be ahead of this node's clock (seconds).
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+/// How often expired records are looked for.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A published signal record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeSignal {
+    /// Node prefix: the first 12 digest characters, and the record's label.
+    pub prefix: String,
+    /// TXT value, as sent.
+    pub value: String,
+    /// The record's `ts` field.
+    pub ts: DateTime<Utc>,
+    /// When this node accepted it.
+    pub received_at: DateTime<Utc>,
+}
+
+/// Why a signal record was refused.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SignalRejected {
+    /// The prefix isn't 12 hex characters.
+    #[error("'{0}' is not a {NODE_PREFIX_LEN}-character node prefix")]
+    BadPrefix(String),
+    /// The value doesn't follow the signal record schema.
+    #[error("{0}")]
+    Malformed(String),
+    /// The record was written with another wire schema version.
+    #[error(transparent)]
+    SchemaMismatch(#[from] SchemaMismatch),
+    /// The digest belongs to another node.
+    #[error("digest {digest} does not start with node prefix {prefix}")]
+    PrefixMismatch {
+        /// Prefix the record was published under.
+        prefix: String,
+        /// Digest the record carries.
+        digest: String,
+    },
+    /// The timestamp is too old, or too far ahead, to publish.
+    #[error("record timestamp {0} is outside the accepted window")]
+    Expired(i64),
+    /// The prefix was updated too recently.
+    #[error("{prefix} was updated too recently; retry in {retry_after_secs}s")]
+    RateLimited {
+        /// The prefix.
+        prefix: String,
+        /// Seconds until an update is accepted.
+        retry_after_secs: u64,
+    },
+}
+
+/// The signal records served for other nodes, keyed by prefix.
+///
+/// Clones share the same records.
+#[derive(Debug, Clone)]
+pub struct NodeSignals {
+    records: Arc<RwLock<BTreeMap<String, NodeSignal>>>,
+    min_interval: chrono::Duration,
+    max_age: chrono::Duration,
+}
+
+impl NodeSignals {
+    /// Accept updates at most once per `min_interval` per node, and
+    /// records no older than `max_age`.
+    #[must_use]
+    pub fn new(min_interval: Duration, max_age: Duration) -> Self {
+        let duration = |d| chrono::Duration::from_std(d).unwrap_or(chrono::Duration::MAX);
+        Self {
+            records: Arc::default(),
+            min_interval: duration(min_interval),
+            max_age: duration(max_age),
+        }
+    }
+
+    /// Limits from `config`.
+    #[must_use]
+    pub fn from_config(config: &NodeSignalConfig) -> Self {
+        Self::new(
+            Duration::from_secs(config.min_interval_secs),
+            Duration::from_secs(config.max_age_secs),
+        )
+    }
+
+    /// Publish `value` as `prefix`'s record.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the record was refused; the current one stays served.
+    pub fn upsert(
+        &self,
+        prefix: &str,
+        value: &str,
+        now: DateTime<Utc>,
+    ) -> Result<NodeSignal, SignalRejected> {
+        let prefix = prefix.to_ascii_lowercase();
+        if prefix.len() != NODE_PREFIX_LEN || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(SignalRejected::BadPrefix(prefix));
+        }
+        let record = SignalRecord::parse(value).map_err(|e| match e {
+            AuditError::SchemaMismatch(mismatch) => SignalRejected::SchemaMismatch(mismatch),
+            e => SignalRejected::Malformed(e.to_string()),
+        })?;
+        if record.node_prefix() != prefix {
+            return Err(SignalRejected::PrefixMismatch {
+                prefix,
+                digest: record.digest,
+            });
+        }
+        let ts = DateTime::from_timestamp(record.ts, 0)
+            .filter(|ts| !self.expired(*ts, now))
+            .filter(|ts| (*ts - now).num_seconds() <= MAX_CLOCK_SKEW_SECS)
+            .ok_or(SignalRejected::Expired(record.ts))?;
+
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(current) = records.get(&prefix) {
+            let next = current.received_at + self.min_interval;
+            if next > now {
+                let wait = (next - now).num_seconds().max(1);
+                return Err(SignalRejected::RateLimited {
+                    prefix,
+                    retry_after_secs: wait.unsigned_abs(),
+                });
+            }
+        }
+        let signal = NodeSignal {
+            prefix: prefix.clone(),
+            value: value.to_string(),
+            ts,
+            received_at: now,
+        };
+        records.insert(prefix, signal.clone());
+        drop(records);
+        Ok(signal)
+    }
+
+    /// Records still within their maximum age at `now`.
+    #[must_use]
+    pub fn snapshot(&self, now: DateTime<Utc>) -> Vec<NodeSignal> {
+        self.records
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter(|signal| !self.expired(signal.ts, now))
+            .cloned()
+            .collect()
+    }
+
+    /// Drop records past their maximum age; returns how many went.
+    pub fn prune(&self, now: DateTime<Utc>) -> usize {
+        let mut records = self.records.write().unwrap_or_else(PoisonError::into_inner);
+        let before = records.len();
+        records.retain(|_, signal| !self.expired(signal.ts, now));
+        let after = records.len();
+        drop(records);
+        before - after
+    }
+
+    /// Prune on a fixed schedule forever, rebuilding when records expire.
+    pub async fn run(self, rebuild: RebuildHandle) {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let expired = self.prune(Utc::now());
+            if expired > 0 {
+                debug!(expired, "dropped expired node signal records");
+                rebuild.request();
+            }
+        }
+    }
+
+    fn expired(&self, ts: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - ts > self.max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "a3f2b8c91d4e00112233445566778899aabbccddeeff00112233445566778899";
+
+    fn value(digest: &str, ts: i64) -> String {
+        format!("digest={digest};ts={ts};bins=3;certs=2")
+    }
+
+    fn signals() -> NodeSignals {
+        NodeSignals::new(Duration::from_secs(30), Duration::from_secs(3600))
+    }
+
+    #[test]
+    fn accepts_matching_records() {
+        let signals = signals();
+        let now = Utc::now();
+        let signal = signals
+            .upsert("A3F2B8C91D4E", &value(DIGEST, now.timestamp()), now)
+            .unwrap();
+        assert_eq!(signal.prefix, "a3f2b8c91d4e");
+        assert_eq!(signals.snapshot(now), [signal]);
+    }
+
+    #[test]
+    fn rejects_bad_records() {
+        let signals = signals();
+        let now = Utc::now();
+        let ts = now.timestamp();
+        let reject = |prefix: &str, value: &str| signals.upsert(prefix, value, now).unwrap_err();
+
+        assert!(matches!(
+            reject("a3f2", &value(DIGEST, ts)),
+            SignalRejected::BadPrefix(_)
+        ));
+        assert!(matches!(
+            reject("a3f2b8c91d4e", "digest=a3f2b8c91d4e;ts=1"),
+            SignalRejected::Malformed(_)
+        ));
+        assert!(matches!(
+            reject("a3f2b8c91d4e", &format!("{};v=2", value(DIGEST, ts))),
+            SignalRejected::SchemaMismatch(SchemaMismatch { found: 2, .. })
+        ));
+        assert!(matches!(
+            reject("000000000000", &value(DIGEST, ts)),
+            SignalRejected::PrefixMismatch { .. }
+        ));
+        assert_eq!(
+            reject("a3f2b8c91d4e", &value(DIGEST, ts - 3601)),
+            SignalRejected::Expired(ts - 3601)
+        );
+        assert_eq!(
+            reject("a3f2b8c91d4e", &value(DIGEST, ts + 3600)),
+            SignalRejected::Expired(ts + 3600)
+        );
+        assert!(signals.snapshot(now).is_empty());
+    }
+
+    #[test]
+    fn rate_limits_each_prefix() {
+        let signals = signals();
+        let now = Utc::now();
+        signals
+            .upsert("a3f2b8c91d4e", &value(DIGEST, now.timestamp()), now)
+            .unwrap();
+
+        let soon = now + chrono::Duration::seconds(10);
+        assert_eq!(
+            signals.upsert("a3f2b8c91d4e", &value(DIGEST, soon.timestamp()), soon),
+            Err(SignalRejected::RateLimited {
+                prefix: "a3f2b8c91d4e".into(),
+                retry_after_secs: 20,
+            })
+        );
+
+        let later = now + chrono::Duration::seconds(30);
+        let updated = signals
+            .upsert("a3f2b8c91d4e", &value(DIGEST, later.timestamp()), later)
+            .unwrap();
+        assert_eq!(updated.ts.timestamp(), later.timestamp());
+    }
+
+    #[test]
+    fn expired_records_are_pruned() {
+        let signals = signals();
+        let now = Utc::now();
+        signals
+            .upsert("a3f2b8c91d4e", &value(DIGEST, now.timestamp() - 3000), now)
+            .unwrap();
+        assert_eq!(signals.prune(now), 0);
+
+        let later = now + chrono::Duration::seconds(601);
+        assert!(signals.snapshot(later).is_empty());
+        assert_eq!(signals.prune(later), 1);
+        assert_eq!(signals.prune(later), 0);
+    }
+}
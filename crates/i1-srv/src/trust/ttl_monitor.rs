@@ -1,15 +1,874 @@
 //! TTL manipulation detection.
 //!
-//! If observed TTL from a resolver is consistently < 3600 when i1-srv
-//! set it to 86400, warn that the user's DNS resolver may be tampering.
+//! Resolvers that cap our TTLs defeat the long caching blocklist answers
+//! rely on; resolvers that stretch them keep serving lifted blocks and
+//! replay stale signal records. The monitor measures both by probing
+//! canary records in the signal zone through public resolvers:
+//!
+//! - the signal record (`_v.sig.i1.is.`), whose value we know, and
+//! - a `ttlcheck` canary: every name under `ttlcheck.sig.i1.is.` answers
+//!   `ttl=86400` with TTL 86400. Each probe asks for a fresh random name,
+//!   so the resolver can't answer from its cache and must pass on the TTL
+//!   it got from us.
+//!
+//! Each canary is queried twice in quick succession. A fresh canary
+//! answered with a lower TTL than published is clamped, as is one whose
+//! second answer counted down faster than the time between the queries.
+//! A TTL more than [`MAX_TTL_DRIFT`] above the published one is extended,
+//! the rule `i1 audit verify` applies to trust digests. Values that don't
+//! match are reported too.
 //!
 //! ```text
-//! WARNING: Your DNS resolver (8.8.8.8) is reducing TTLs.
-//!   Expected: 86400s, Observed: 600s (consistently over 5 queries)
-//!   Your provider may be manipulating DNS responses.
+//! WARN resolver=8.8.8.8:53 finding=clamped=ttlcheck:21600/86400
+//!   resolver rewrites TTLs
 //! ```
+//!
+//! Findings are kept per resolver in [`TtlReports`], which feeds the
+//! metrics endpoint and the admin status, and can be published in the
+//! signal zone (`8-8-8-8.ttlmon.sig.i1.is. TXT`) so other nodes can
+//! corroborate them.
+
+use chrono::{DateTime, Utc};
+use hickory_proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_proto::rr::{Name, RData, RecordType};
+use i1_audit::verify::MAX_TTL_DRIFT;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::authority::transfer::TransferZone;
+use crate::authority::ttl_policy::{SIGNAL_TTL, TTLCHECK_TTL};
+use crate::authority::zone_builder;
+use crate::config::TtlMonitorConfig;
+use crate::encoding::signal::SignalData;
+use crate::metrics::{Collector, Exposition, Kind};
+use crate::server::ServedZones;
+use crate::sync::reload::RebuildHandle;
+
+/// Label under the signal zone holding the `ttlcheck` canary.
+pub const TTLCHECK_LABEL: &str = "ttlcheck";
+
+/// Label under the signal zone holding published findings.
+pub const REPORT_LABEL: &str = "ttlmon";
+
+/// How long a resolver gets to answer one query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest response read from a resolver.
+const MAX_RESPONSE: usize = 4096;
+
+/// Wildcard owner of the `ttlcheck` canary in a signal zone.
+#[must_use]
+pub fn ttlcheck_wildcard(signal_zone: &str) -> String {
+    format!("*.{TTLCHECK_LABEL}.{signal_zone}")
+}
+
+/// Value of the `ttlcheck` canary.
+#[must_use]
+pub fn ttlcheck_value() -> String {
+    format!("ttl={TTLCHECK_TTL}")
+}
+
+/// Owner of a resolver's published findings.
+#[must_use]
+pub fn report_name(resolver: SocketAddr, signal_zone: &str) -> String {
+    let label: String = resolver
+        .ip()
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{label}.{REPORT_LABEL}.{signal_zone}")
+}
+
+/// A canary record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Canary {
+    /// The signal zone's version record.
+    Signal,
+    /// A fresh name under the `ttlcheck` wildcard.
+    #[serde(rename = "ttlcheck")]
+    TtlCheck,
+}
+
+impl Canary {
+    /// Name used in findings and metrics.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Signal => "signal",
+            Self::TtlCheck => TTLCHECK_LABEL,
+        }
+    }
+}
+
+/// One answer from a resolver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    /// TXT value, `None` when the name doesn't exist or has no TXT.
+    pub value: Option<String>,
+    /// TTL of the answer.
+    pub ttl: u32,
+}
+
+/// Something wrong with a resolver's answers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    /// Answered or cached with a TTL below the published one.
+    Clamped {
+        /// Canary affected.
+        record: Canary,
+        /// Published TTL.
+        expected: u32,
+        /// Lowest TTL implied by the answers.
+        observed: u32,
+    },
+    /// Answered with a TTL above the published one.
+    Extended {
+        /// Canary affected.
+        record: Canary,
+        /// Published TTL.
+        expected: u32,
+        /// Highest TTL answered.
+        observed: u32,
+    },
+    /// Answered with a value we never published.
+    Mismatch {
+        /// Canary affected.
+        record: Canary,
+        /// Published value.
+        expected: String,
+        /// Value answered, `None` for no record.
+        observed: Option<String>,
+    },
+}
+
+impl Finding {
+    /// Kind, as used in metrics.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Clamped { .. } => "clamped",
+            Self::Extended { .. } => "extended",
+            Self::Mismatch { .. } => "mismatch",
+        }
+    }
+
+    /// Canary affected.
+    #[must_use]
+    pub const fn record(&self) -> Canary {
+        match self {
+            Self::Clamped { record, .. }
+            | Self::Extended { record, .. }
+            | Self::Mismatch { record, .. } => *record,
+        }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let record = self.record().as_str();
+        match self {
+            Self::Clamped {
+                expected, observed, ..
+            }
+            | Self::Extended {
+                expected, observed, ..
+            } => write!(f, "{}={record}:{observed}/{expected}", self.kind()),
+            Self::Mismatch { .. } => write!(f, "mismatch={record}"),
+        }
+    }
+}
+
+/// A canary as we serve it.
+#[derive(Debug, Clone, Copy)]
+pub struct Published<'a> {
+    /// Canary served.
+    pub record: Canary,
+    /// Its TTL.
+    pub ttl: u32,
+    /// Its current value.
+    pub value: &'a str,
+}
+
+/// Compare two answers for a canary, `elapsed_secs` apart, with what we
+/// publish; `value_ok` accepts the values we could have served.
+pub fn assess(
+    published: Published<'_>,
+    first: &Observation,
+    second: &Observation,
+    elapsed_secs: u32,
+    value_ok: impl Fn(Option<&str>) -> bool,
+) -> Vec<Finding> {
+    let Published {
+        record,
+        ttl: expected_ttl,
+        ..
+    } = published;
+    let mut findings = Vec::new();
+    if let Some(bad) = [first, second]
+        .into_iter()
+        .find(|o| !value_ok(o.value.as_deref()))
+    {
+        findings.push(Finding::Mismatch {
+            record,
+            expected: published.value.to_string(),
+            observed: bad.value.clone(),
+        });
+    }
+    // Without a record there is no TTL to judge.
+    if first.value.is_none() || second.value.is_none() {
+        return findings;
+    }
+
+    let highest = first.ttl.max(second.ttl);
+    if highest > expected_ttl.saturating_add(MAX_TTL_DRIFT) {
+        findings.push(Finding::Extended {
+            record,
+            expected: expected_ttl,
+            observed: highest,
+        });
+    } else if record == Canary::TtlCheck {
+        // A fresh name must come back with the full TTL, and count down
+        // no faster than the clock.
+        let answered_short = first.ttl.saturating_add(MAX_TTL_DRIFT) < expected_ttl;
+        let cached_short = second
+            .ttl
+            .saturating_add(elapsed_secs)
+            .saturating_add(MAX_TTL_DRIFT)
+            < first.ttl;
+        if answered_short || cached_short {
+            findings.push(Finding::Clamped {
+                record,
+                expected: expected_ttl,
+                observed: first.ttl.min(second.ttl.saturating_add(elapsed_secs)),
+            });
+        }
+    }
+    findings
+}
+
+/// What one resolver answered in the latest round.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolverReport {
+    /// Resolver probed.
+    pub resolver: SocketAddr,
+    /// When the latest round finished.
+    pub checked_at: Option<DateTime<Utc>>,
+    /// Problems with its answers.
+    pub findings: Vec<Finding>,
+    /// Why the latest round failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Rounds run.
+    pub probes: u64,
+    /// Rounds that failed.
+    pub failures: u64,
+}
+
+impl ResolverReport {
+    /// Findings as published in the signal zone:
+    /// `checked=<epoch>;clamped=ttlcheck:300/86400;...`.
+    #[must_use]
+    pub fn to_txt(&self) -> String {
+        let mut txt = format!("checked={}", self.checked_at.map_or(0, |at| at.timestamp()));
+        if self.error.is_some() {
+            txt.push_str(";error=1");
+        }
+        for finding in &self.findings {
+            let _ = write!(txt, ";{finding}");
+        }
+        txt
+    }
+}
+
+/// The latest findings for each resolver.
+///
+/// Clones share the same reports.
+#[derive(Debug, Clone, Default)]
+pub struct TtlReports {
+    reports: Arc<RwLock<BTreeMap<SocketAddr, ResolverReport>>>,
+}
+
+impl TtlReports {
+    /// Every resolver's latest report.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<ResolverReport> {
+        self.reports
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Record a round; returns whether the resolver's findings changed.
+    fn record(&self, resolver: SocketAddr, outcome: crate::Result<Vec<Finding>>) -> bool {
+        let mut reports = self.reports.write().unwrap_or_else(PoisonError::into_inner);
+        let report = reports.entry(resolver).or_insert_with(|| ResolverReport {
+            resolver,
+            checked_at: None,
+            findings: Vec::new(),
+            error: None,
+            probes: 0,
+            failures: 0,
+        });
+        report.probes += 1;
+        report.checked_at = Some(Utc::now());
+        let before = (report.findings.clone(), report.error.is_some());
+        match outcome {
+            Ok(findings) => {
+                report.findings = findings;
+                report.error = None;
+            }
+            Err(e) => {
+                report.failures += 1;
+                report.error = Some(e.to_string());
+            }
+        }
+        let changed = before != (report.findings.clone(), report.error.is_some());
+        drop(reports);
+        changed
+    }
+}
+
+impl Collector for TtlReports {
+    fn collect(&self, out: &mut Exposition) {
+        let reports = self.snapshot();
+        let label = |report: &ResolverReport| report.resolver.to_string();
+
+        out.family(
+            "i1_ttl_probes_total",
+            Kind::Counter,
+            "TTL monitor probe rounds per resolver.",
+        );
+        for report in &reports {
+            out.sample(
+                "i1_ttl_probes_total",
+                &[("resolver", &label(report))],
+                report.probes,
+            );
+        }
+        out.family(
+            "i1_ttl_probe_failures_total",
+            Kind::Counter,
+            "TTL monitor probe rounds that got no usable answer.",
+        );
+        for report in &reports {
+            out.sample(
+                "i1_ttl_probe_failures_total",
+                &[("resolver", &label(report))],
+                report.failures,
+            );
+        }
+        out.family(
+            "i1_ttl_findings",
+            Kind::Gauge,
+            "Canaries a resolver clamped, extended or altered in the latest round.",
+        );
+        for report in &reports {
+            let resolver = label(report);
+            for kind in ["clamped", "extended", "mismatch"] {
+                let count = report.findings.iter().filter(|f| f.kind() == kind).count();
+                out.sample(
+                    "i1_ttl_findings",
+                    &[("resolver", &resolver), ("kind", kind)],
+                    count,
+                );
+            }
+        }
+    }
+}
+
+/// Probes resolvers for TTL manipulation.
+pub struct TtlMonitor {
+    zones: ServedZones,
+    signal_zone: String,
+    resolvers: Vec<SocketAddr>,
+    interval: Duration,
+    recheck: Duration,
+    reports: TtlReports,
+    rebuild: Option<RebuildHandle>,
+}
+
+impl std::fmt::Debug for TtlMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TtlMonitor")
+            .field("resolvers", &self.resolvers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TtlMonitor {
+    /// Probe the canaries `zones` serves under `signal_zone`, recording
+    /// findings in `reports`.
+    #[must_use]
+    pub fn new(
+        zones: ServedZones,
+        signal_zone: &str,
+        config: &TtlMonitorConfig,
+        reports: TtlReports,
+    ) -> Self {
+        Self {
+            zones,
+            signal_zone: signal_zone.to_string(),
+            resolvers: config.resolvers(),
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            recheck: Duration::from_secs(config.recheck_secs),
+            reports,
+            rebuild: None,
+        }
+    }
+
+    /// Rebuild the zones through `rebuild` whenever findings change, so
+    /// the published ones stay current.
+    #[must_use]
+    pub fn with_rebuild(mut self, rebuild: RebuildHandle) -> Self {
+        self.rebuild = Some(rebuild);
+        self
+    }
+
+    /// Probe every resolver every interval. Runs forever.
+    pub async fn run(self) {
+        info!(resolvers = ?self.resolvers, "TTL monitor started");
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if self.probe_all().await {
+                if let Some(rebuild) = &self.rebuild {
+                    if let Err(e) = rebuild.rebuild().await {
+                        warn!(error = %e, "failed to publish TTL findings");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run one round against every resolver; returns whether any
+    /// resolver's findings changed.
+    pub async fn probe_all(&self) -> bool {
+        let canaries = match self.canaries() {
+            Ok(canaries) => Arc::new(canaries),
+            Err(e) => {
+                warn!(error = %e, "TTL monitor has nothing to probe");
+                return false;
+            }
+        };
+
+        let mut probes = JoinSet::new();
+        for &resolver in &self.resolvers {
+            let canaries = Arc::clone(&canaries);
+            let recheck = self.recheck;
+            probes.spawn(async move { (resolver, probe(resolver, &canaries, recheck).await) });
+        }
+
+        let mut changed = false;
+        while let Some(joined) = probes.join_next().await {
+            let Ok((resolver, outcome)) = joined else {
+                continue;
+            };
+            match &outcome {
+                Ok(findings) if findings.is_empty() => {
+                    debug!(%resolver, "resolver keeps our TTLs");
+                }
+                Ok(findings) => {
+                    for finding in findings {
+                        warn!(%resolver, %finding, "resolver rewrites TTLs");
+                    }
+                }
+                Err(e) => debug!(%resolver, error = %e, "TTL probe failed"),
+            }
+            changed |= self.reports.record(resolver, outcome);
+        }
+        changed
+    }
+
+    /// The canaries as currently served.
+    fn canaries(&self) -> crate::Result<Canaries> {
+        let signal = zone_builder::read_signal(&self.zones.signal.records())
+            .ok_or_else(|| crate::SrvError::Zone("no signal record served".into()))?;
+        let parse = |name: &str| {
+            Name::from_ascii(name)
+                .map_err(|e| crate::SrvError::Zone(format!("invalid canary name {name}: {e}")))
+        };
+        Ok(Canaries {
+            signal_name: parse(&SignalData::query_name(&self.signal_zone))?,
+            ttlcheck_zone: parse(&format!("{TTLCHECK_LABEL}.{}", self.signal_zone))?,
+            signal,
+        })
+    }
+}
+
+/// What the probes ask for, and what we serve there.
+#[derive(Debug)]
+struct Canaries {
+    signal_name: Name,
+    ttlcheck_zone: Name,
+    signal: SignalData,
+}
+
+impl Canaries {
+    /// Whether an answered signal value is one we could have served: the
+    /// current record, or an older one still cached.
+    fn signal_ok(&self, value: Option<&str>) -> bool {
+        value
+            .and_then(|value| SignalData::from_txt(value).ok())
+            .is_some_and(|seen| {
+                seen.serial < self.signal.serial
+                    || (seen.serial == self.signal.serial && seen.entries == self.signal.entries)
+            })
+    }
+}
+
+/// Query both canaries through `resolver` twice, `recheck` apart.
+async fn probe(
+    resolver: SocketAddr,
+    canaries: &Canaries,
+    recheck: Duration,
+) -> crate::Result<Vec<Finding>> {
+    let ttlcheck = fresh_name(&canaries.ttlcheck_zone)?;
+
+    let started = Instant::now();
+    let signal_first = query_txt(resolver, &canaries.signal_name).await?;
+    let check_first = query_txt(resolver, &ttlcheck).await?;
+    tokio::time::sleep(recheck).await;
+    let signal_second = query_txt(resolver, &canaries.signal_name).await?;
+    let check_second = query_txt(resolver, &ttlcheck).await?;
+    // Partial seconds count, so a countdown is never mistaken for a clamp.
+    let elapsed = u32::try_from(started.elapsed().as_secs() + 1).unwrap_or(u32::MAX);
+
+    let signal = canaries.signal.to_txt();
+    let mut findings = assess(
+        Published {
+            record: Canary::Signal,
+            ttl: SIGNAL_TTL,
+            value: &signal,
+        },
+        &signal_first,
+        &signal_second,
+        elapsed,
+        |value| canaries.signal_ok(value),
+    );
+    let check = ttlcheck_value();
+    findings.extend(assess(
+        Published {
+            record: Canary::TtlCheck,
+            ttl: TTLCHECK_TTL,
+            value: &check,
+        },
+        &check_first,
+        &check_second,
+        elapsed,
+        |value| value == Some(check.as_str()),
+    ));
+    Ok(findings)
+}
+
+/// A random name under `zone`, so no resolver has it cached.
+fn fresh_name(zone: &Name) -> crate::Result<Name> {
+    let mut nonce = [0u8; 6];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| crate::SrvError::DnsQuery("failed to generate canary name".into()))?;
+    let label = nonce
+        .iter()
+        .fold(String::with_capacity(12), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    Name::from_ascii(&label)
+        .and_then(|label| label.append_domain(zone))
+        .map_err(|e| crate::SrvError::DnsQuery(format!("invalid canary name: {e}")))
+}
+
+/// Ask `resolver` for the TXT records at `name` over UDP.
+///
+/// # Errors
+///
+/// Returns `SrvError::DnsQuery` if the resolver doesn't answer in time or
+/// answers with an error other than NXDOMAIN.
+pub async fn query_txt(resolver: SocketAddr, name: &Name) -> crate::Result<Observation> {
+    tokio::time::timeout(QUERY_TIMEOUT, exchange(resolver, name))
+        .await
+        .map_err(|_| crate::SrvError::DnsQuery(format!("{name} via {resolver}: timed out")))?
+}
+
+async fn exchange(resolver: SocketAddr, name: &Name) -> crate::Result<Observation> {
+    let query_err = |e: &dyn std::fmt::Display| {
+        crate::SrvError::DnsQuery(format!("{name} via {resolver}: {e}"))
+    };
+
+    let mut id = [0u8; 2];
+    SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| crate::SrvError::DnsQuery("failed to generate message id".into()))?;
+    let id = u16::from_be_bytes(id);
+
+    let mut request = Message::new();
+    request
+        .set_id(id)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .add_query(Query::query(name.clone(), RecordType::TXT));
+    let bytes = request.to_vec().map_err(|e| query_err(&e))?;
+
+    let local: SocketAddr = if resolver.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(local).await.map_err(|e| query_err(&e))?;
+    socket.connect(resolver).await.map_err(|e| query_err(&e))?;
+    socket.send(&bytes).await.map_err(|e| query_err(&e))?;
+
+    let mut buf = vec![0u8; MAX_RESPONSE];
+    let response = loop {
+        let len = socket.recv(&mut buf).await.map_err(|e| query_err(&e))?;
+        match Message::from_vec(&buf[..len]) {
+            // Skip stray datagrams and answers to other queries.
+            Ok(response) if response.id() == id => break response,
+            _ => {}
+        }
+    };
+
+    match response.response_code() {
+        ResponseCode::NoError => {}
+        ResponseCode::NXDomain => {
+            return Ok(Observation {
+                value: None,
+                ttl: 0,
+            })
+        }
+        code => return Err(query_err(&code)),
+    }
+    Ok(response
+        .answers()
+        .iter()
+        .find_map(|record| match record.data() {
+            RData::TXT(txt) => Some(Observation {
+                value: Some(
+                    txt.txt_data()
+                        .iter()
+                        .map(|part| String::from_utf8_lossy(part))
+                        .collect(),
+                ),
+                ttl: record.ttl(),
+            }),
+            _ => None,
+        })
+        .unwrap_or(Observation {
+            value: None,
+            ttl: 0,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authority::transfer::{TransferAcl, TransferHandler};
+    use crate::authority::zone_builder::{build_zones_with, BuildOptions, DefenseSnapshot};
+    use crate::config::ZoneConfig;
+    use hickory_server::ServerFuture;
+
+    fn seen(value: &str, ttl: u32) -> Observation {
+        Observation {
+            value: Some(value.into()),
+            ttl,
+        }
+    }
+
+    #[test]
+    fn test_assess_rules() {
+        let published = Published {
+            record: Canary::TtlCheck,
+            ttl: 86_400,
+            value: "ttl=86400",
+        };
+        let ok = |value: Option<&str>| value == Some("ttl=86400");
+        let check = |first, second, elapsed| {
+            assess(published, &first, &second, elapsed, ok)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        // Full TTL, counting down with the clock.
+        assert!(check(seen("ttl=86400", 86_400), seen("ttl=86400", 86_398), 3).is_empty());
+        // Capped on the way through, or in the resolver's cache.
+        assert_eq!(
+            check(seen("ttl=86400", 300), seen("ttl=86400", 298), 3),
+            ["clamped=ttlcheck:300/86400"]
+        );
+        assert_eq!(
+            check(seen("ttl=86400", 86_400), seen("ttl=86400", 3600), 3),
+            ["clamped=ttlcheck:3603/86400"]
+        );
+        // Stretched, and altered.
+        assert_eq!(
+            check(seen("ttl=86400", 604_800), seen("ttl=86400", 604_800), 3),
+            ["extended=ttlcheck:604800/86400"]
+        );
+        assert_eq!(
+            check(seen("ttl=1", 86_400), seen("ttl=86400", 86_400), 3),
+            ["mismatch=ttlcheck"]
+        );
+        // A missing canary is a mismatch, with no TTL to judge.
+        let missing = Observation {
+            value: None,
+            ttl: 0,
+        };
+        assert_eq!(check(missing.clone(), missing, 3), ["mismatch=ttlcheck"]);
+
+        // Cached signal records may count down freely, but not grow.
+        let signal = Published {
+            record: Canary::Signal,
+            ttl: 30,
+            value: "s",
+        };
+        let any = |_: Option<&str>| true;
+        assert!(assess(signal, &seen("s", 4), &seen("s", 1), 3, any).is_empty());
+        assert_eq!(
+            assess(signal, &seen("s", 3600), &seen("s", 3598), 3, any)[0].kind(),
+            "extended"
+        );
+    }
+
+    #[test]
+    fn test_report_txt() {
+        let resolver: SocketAddr = "9.9.9.9:53".parse().unwrap();
+        assert_eq!(
+            report_name(resolver, "sig.i1.is."),
+            "9-9-9-9.ttlmon.sig.i1.is."
+        );
+        let report = ResolverReport {
+            resolver,
+            checked_at: Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap()),
+            findings: vec![Finding::Extended {
+                record: Canary::Signal,
+                expected: 30,
+                observed: 300,
+            }],
+            error: None,
+            probes: 1,
+            failures: 0,
+        };
+        assert_eq!(report.to_txt(), "checked=1700000000;extended=signal:300/30");
+    }
+
+    /// Serve freshly built zones over UDP, as an honest resolver would
+    /// relay them.
+    async fn authoritative() -> (ServedZones, SocketAddr) {
+        let built = build_zones_with(
+            &DefenseSnapshot::default(),
+            &ZoneConfig::default(),
+            1,
+            BuildOptions::default(),
+        )
+        .unwrap();
+        let zones = ServedZones::new(built, 4);
+        let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = udp.local_addr().unwrap();
+        let handler = TransferHandler::new(
+            zones.catalog(),
+            zones.transfer_zones(),
+            TransferAcl::default(),
+        );
+        let mut dns = ServerFuture::new(handler);
+        dns.register_socket(udp);
+        tokio::spawn(async move { dns.block_until_done().await });
+        (zones, addr)
+    }
+
+    /// A resolver that relays to `upstream` but caps every TTL at `cap`.
+    async fn clamping_resolver(upstream: SocketAddr, cap: u32) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; MAX_RESPONSE];
+            loop {
+                let (len, client) = socket.recv_from(&mut buf).await.unwrap();
+                let request = Message::from_vec(&buf[..len]).unwrap();
+                let name = request.queries()[0].name().clone();
+                let answer = query_txt(upstream, &name).await.unwrap();
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .add_query(request.queries()[0].clone());
+                if let Some(value) = answer.value {
+                    response.add_answer(hickory_proto::rr::Record::from_rdata(
+                        name,
+                        answer.ttl.min(cap),
+                        RData::TXT(hickory_proto::rr::rdata::TXT::new(vec![value])),
+                    ));
+                }
+                socket
+                    .send_to(&response.to_vec().unwrap(), client)
+                    .await
+                    .unwrap();
+            }
+        });
+        addr
+    }
+
+    fn monitor(zones: ServedZones, resolvers: Vec<SocketAddr>) -> TtlMonitor {
+        let config = TtlMonitorConfig {
+            public_resolvers: false,
+            resolvers,
+            recheck_secs: 0,
+            ..TtlMonitorConfig::default()
+        };
+        TtlMonitor::new(zones, "sig.i1.is.", &config, TtlReports::default())
+    }
+
+    #[tokio::test]
+    async fn test_probes_find_clamping_resolver() {
+        let (zones, upstream) = authoritative().await;
+        let clamping = clamping_resolver(upstream, 300).await;
+        let monitor = monitor(zones, vec![upstream, clamping]);
+
+        assert!(monitor.probe_all().await);
+        let reports = monitor.reports.snapshot();
+        let report = |addr| {
+            reports
+                .iter()
+                .find(|report| report.resolver == addr)
+                .unwrap()
+        };
+
+        // The canaries are served with the published TTLs...
+        let honest = report(upstream);
+        assert!(honest.error.is_none(), "{:?}", honest.error);
+        assert!(honest.findings.is_empty(), "{:?}", honest.findings);
+        // ...and the signal record's TTL is under the cap, so only the
+        // ttlcheck canary gives the clamping resolver away.
+        let clamped = report(clamping);
+        assert_eq!(
+            clamped.to_txt().split_once(';').unwrap().1,
+            "clamped=ttlcheck:300/86400"
+        );
+
+        let metrics = {
+            let mut out = Exposition::default();
+            monitor.reports.collect(&mut out);
+            out.into_string()
+        };
+        assert!(metrics.contains(&format!(
+            r#"i1_ttl_findings{{resolver="{clamping}",kind="clamped"}} 1"#
+        )));
 
-// TODO: Phase 2 - implement TTL monitor
-// - Track observed TTLs over multiple queries
-// - Warn when consistently below expected values
-// - Expose via `i1 srv ttl-check` CLI command
+        // Nothing changed, so a second round reports no change.
+        assert!(!monitor.probe_all().await);
+    }
+}
@@ -48,7 +48,20 @@
 //! - `recon` - Enable local reconnaissance tools
 //! - `scanner` - Enable port scanning
 //! - `whois` - Enable WHOIS lookups
+//! - `geoip` - Enable offline geo/ASN enrichment from `.mmdb` databases,
+//!   also applied to [`I1Client`] host lookups
 //! - `full-recon` - Enable all local recon tools
+//! - `audit` - Re-export `i1-audit` as [`audit`](crate::audit): system
+//!   audits, consensus lookups and signal records
+//! - `ca` - Re-export `i1-ca` as [`ca`](crate::ca)
+//! - `srv-encoding` - Re-export the `i1-srv` record encoders as
+//!   [`srv`](crate::srv)
+//!
+//! # Wire schema
+//!
+//! The TXT records `i1-audit` and `i1-srv` exchange carry
+//! [`WIRE_SCHEMA_VERSION`]; a record from a build with another schema
+//! fails to decode with [`SchemaMismatch`].
 
 #![doc(html_root_url = "https://docs.rs/i1/0.1.0")]
 
@@ -57,8 +70,9 @@ pub use i1_core::*;
 
 // Re-export provider traits
 pub use i1_providers::{
-    DnsProvider, DomainInfo, HealthStatus, HostLookup, Provider, ProviderHealth, RateLimitConfig,
-    SearchProvider, SearchResults, VulnInfo, VulnProvider, WhoisInfo, WhoisProvider,
+    check_health, DnsProvider, DomainInfo, HealthStatus, HostLookup, Provider, ProviderHealth,
+    RateLimitConfig, SearchProvider, SearchResults, VulnInfo, VulnProvider, WhoisInfo,
+    WhoisProvider,
 };
 
 // Re-export unified client
@@ -66,7 +80,7 @@ pub use i1_client::{I1Client, I1ClientBuilder};
 
 // Re-export providers
 #[cfg(feature = "shodan")]
-pub use i1_shodan::ShodanProvider;
+pub use i1_shodan::{Banner, CreateAlert, ShodanAlerts, ShodanProvider, ShodanStream};
 
 #[cfg(feature = "censys")]
 pub use i1_censys::CensysProvider;
@@ -81,6 +95,20 @@ pub use i1_native::NativeProvider;
 #[cfg(feature = "recon")]
 pub use i1_recon as recon;
 
+// Re-export defense crates if enabled
+#[cfg(feature = "audit")]
+pub use i1_audit as audit;
+
+#[cfg(feature = "ca")]
+pub use i1_ca as ca;
+
+/// Record encoders from `i1-srv`.
+#[cfg(feature = "srv-encoding")]
+pub mod srv {
+    pub use i1_srv::encoding::*;
+    pub use i1_srv::{Result, SrvError};
+}
+
 // Re-export runtime for convenience
 pub use async_trait::async_trait;
 pub use serde;
@@ -89,7 +117,7 @@ pub use tokio;
 
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::{I1Client, I1ClientBuilder, Result};
+    pub use crate::{I1Client, I1ClientBuilder, Result, SchemaMismatch, WIRE_SCHEMA_VERSION};
     pub use i1_providers::{
         DnsProvider, HostLookup, Provider, ProviderHealth, SearchProvider, WhoisProvider,
     };
@@ -105,4 +133,117 @@ pub mod prelude {
 
     #[cfg(feature = "native")]
     pub use i1_native::NativeProvider;
+
+    #[cfg(feature = "audit")]
+    pub use i1_audit::{
+        verify::SignalRecord, Attestation, AuditError, AuditSnapshot, BinaryInfo, HashAlgorithm,
+    };
+
+    #[cfg(feature = "ca")]
+    pub use i1_ca::{CaError, IntermediateCa, RootCa};
+
+    #[cfg(feature = "srv-encoding")]
+    pub use i1_srv::encoding::signal::SignalData;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use i1_audit::consensus::parse_consensus_records;
+    use i1_audit::encoding::{encode_attestation_txt, encode_binary_txt};
+    use i1_audit::types::{Attestation, BinaryInfo, FileIdentity};
+    use i1_audit::verify::SignalRecord;
+    use i1_audit::{AuditError, HashAlgorithm};
+    use i1_srv::encoding::signal::SignalData;
+    use i1_srv::trust::node_signals::{NodeSignals, SignalRejected};
+    use i1_srv::SrvError;
+    use std::time::Duration;
+
+    const DIGEST: &str = "a3f2b8c91d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8";
+
+    /// Point a record at another schema version.
+    fn bump(txt: &str) -> String {
+        let current = format!("v={WIRE_SCHEMA_VERSION}");
+        assert!(txt.contains(&current), "{txt}");
+        txt.replace(&current, &format!("v={}", WIRE_SCHEMA_VERSION + 1))
+    }
+
+    fn is_bumped(mismatch: SchemaMismatch) -> bool {
+        mismatch
+            == SchemaMismatch {
+                expected: WIRE_SCHEMA_VERSION,
+                found: WIRE_SCHEMA_VERSION + 1,
+            }
+    }
+
+    #[test]
+    fn audit_signal_records_publish_through_srv() {
+        let now = Utc::now();
+        let txt = SignalRecord {
+            digest: DIGEST.into(),
+            ts: now.timestamp(),
+            bins: 412,
+            certs: 146,
+            sig: None,
+        }
+        .to_string();
+
+        let signals = NodeSignals::new(Duration::from_secs(30), Duration::from_secs(3600));
+        let signal = signals.upsert("a3f2b8c91d4e", &txt, now).unwrap();
+        assert_eq!(signal.value, txt);
+
+        let err = signals
+            .upsert("a3f2b8c91d4e", &bump(&txt), now)
+            .unwrap_err();
+        assert!(matches!(err, SignalRejected::SchemaMismatch(m) if is_bumped(m)));
+    }
+
+    #[test]
+    fn consensus_records_decode_on_audit_side() {
+        let binary = BinaryInfo {
+            path: "/usr/sbin/sshd".into(),
+            hash: "c".repeat(64),
+            hash_algorithm: HashAlgorithm::Sha256,
+            create_date: Utc::now(),
+            modify_date: Utc::now(),
+            identity: FileIdentity {
+                inode: 1,
+                device_id: 1,
+            },
+            size: 1_047_552,
+            running: true,
+            process_names: vec!["sshd".into()],
+            trust_score: None,
+        };
+        let attestation = Attestation {
+            node: "a1b2c3d4e5f6".into(),
+            network: Some("203.0".into()),
+            first_seen: Utc::now(),
+            digest_at: Utc::now(),
+        };
+        let txts = vec![
+            encode_binary_txt(&binary, 3).unwrap(),
+            encode_attestation_txt(&attestation),
+        ];
+
+        let result = parse_consensus_records(&binary.hash, &txts, 3600).unwrap();
+        assert!(result.found);
+        assert_eq!(result.node_count, 3);
+        assert_eq!(result.attestations.len(), 1);
+
+        let newer: Vec<String> = txts.iter().map(|txt| bump(txt)).collect();
+        let err = parse_consensus_records(&binary.hash, &newer, 3600).unwrap_err();
+        assert!(matches!(err, AuditError::SchemaMismatch(m) if is_bumped(m)));
+    }
+
+    #[test]
+    fn srv_signal_data_round_trips() {
+        let signal = SignalData::new(2_026_021_701, 4523);
+        let txt = signal.to_txt();
+        assert_eq!(SignalData::from_txt(&txt).unwrap().serial, signal.serial);
+
+        let err = SignalData::from_txt(&bump(&txt)).unwrap_err();
+        assert!(matches!(err, SrvError::SchemaMismatch(m) if is_bumped(m)));
+    }
 }